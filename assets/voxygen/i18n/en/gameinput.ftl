@@ -60,6 +60,7 @@ gameinput-acceptgroupinvite = Accept Group Invite
 gameinput-declinegroupinvite = Decline Group Invite
 gameinput-cyclecamera = Cycle camera
 gameinput-crafting = Crafting
+gameinput-quests = Quests
 gameinput-fly = Fly
 gameinput-sneak = Sneak
 gameinput-swimdown = Swim downwards
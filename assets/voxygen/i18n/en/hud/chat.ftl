@@ -50,4 +50,6 @@ hud-loot-pickup-msg = {$actor} picked up { $amount ->
 }
 hud-chat-loot_fail = Your Inventory is full!
 hud-chat-goodbye = Goodbye!
-hud-chat-connection_lost = Connection lost. Kicking in { $time } seconds.
\ No newline at end of file
+hud-chat-connection_lost = Connection lost. Kicking in { $time } seconds.
+hud-chat-reconnecting = Connection lost, reconnecting (attempt { $attempt })...
+hud-chat-reconnected = Reconnected.
\ No newline at end of file
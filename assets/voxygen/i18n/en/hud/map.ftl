@@ -35,4 +35,6 @@ hud-map-zoom_minimap_explanation =
 hud-map-gnarling = Gnarling Fortification
 hud-map-chapel_site = Sea Chapel
 hud-map-adlet = Adlet Stronghold
-hud-map-placed_by = Placed by { $name }
\ No newline at end of file
+hud-map-placed_by = Placed by { $name }
+hud-map-site_faction = Held by { $faction }
+hud-map-site_at_war = At war with { $factions }
\ No newline at end of file
@@ -1,7 +1,13 @@
 hud-quest = Quest
 hud-quest-intro = Greetings, { $playername }!
-hud-quest-desc-fetch = Please help me find:
-hud-quest-desc-kill = Could you help me kill
+hud-quest-desc = { $giver } asks for your help:
 hud-quest-reward = I will reward you with:
 hud-quest-accept = Accept
-hud-quest-decline = Decline
\ No newline at end of file
+hud-quest-decline = Decline
+hud-quest-abandon = Abandon
+hud-quest-none = You have not taken on any quests. Ask people in towns whether they have work for you.
+hud-quest-done = { $objective } (Done, return to { $giver })
+quest-objective-kill = Kill { $target }
+quest-objective-fetch = Bring { $amount } { $item }
+quest-objective-escort = Escort { $npc } to { $site }
+quest-objective-deliver = Deliver a message to { $npc }
@@ -99,6 +99,8 @@ hud-settings-fullscreen_mode-borderless = Borderless
 hud-settings-gpu_profiler = Enable GPU timing (not supported everywhere)
 hud-settings-particles = Particles
 hud-settings-lossy_terrain_compression = Lossy terrain compression
+hud-settings-record_demos = Record demos
+hud-settings-auto_reconnect = Reconnect automatically
 hud-settings-weapon_trails = Weapon trails
 hud-settings-flashing_lights = Flashing lights
 hud-settings-flashing_lights_info = Enables all kinds of flashing, e.g. flickering or lightning strikes
@@ -224,6 +224,26 @@ npc-speech-prisoner =
     .a3 = These Clerics are up to no good.
 npc-speech-moving_on =
     .a0 = I've spent enough time here, onward to { $site }!
+npc-speech-caravan_departing =
+    .a0 = My pack is loaded, off to { $site }!
+    .a1 = They pay well for these goods in { $site }. Care to come along?
+npc-speech-caravan_thanks =
+    .a0 = We made it, thanks for watching my back!
+    .a1 = Arrived safe and sound, I owe you one.
+npc-speech-quest_offer =
+    .a0 = There is something you could do for me, if you're willing.
+    .a1 = Looking for work? I might have a job for you.
+npc-speech-no_work =
+    .a0 = Sorry, I have nothing for you to do.
+    .a1 = Work? Not from me, try asking around.
+npc-speech-quest_complete =
+    .a0 = You did it! Here's your reward, as promised.
+    .a1 = Much obliged, take this for your trouble.
+npc-speech-quest_not_done =
+    .a0 = You're not done yet, come back when you are.
+    .a1 = That's not what we agreed on.
+npc-speech-quest_inventory_full =
+    .a0 = Your pack is full, make some room for your reward first.
 npc-speech-migrating =
     .a0 = I'm no longer happy living here. Time to migrate to { $site }.
     .a1 = Time to move to { $site }, I've had it with this place.
@@ -269,6 +289,40 @@ npc-speech-witness_death =
     .a0 = No!
     .a1 = This is terrible!
     .a2 = Oh my goodness!
+npc-speech-witness_theft =
+    .a0 = Thief! Put that back!
+    .a1 = Hey, that's not yours!
+    .a2 = Stop, thief!
+npc-speech-heard_theft =
+    .a0 = Someone's been stealing from us, keep an eye on your things.
+    .a1 = I heard there's a thief about.
+npc-speech-monster_sighted =
+    .a0 = Somebody spotted a { $body } to the { $dir } of here!
+    .a1 = Watch out, there's a { $body } roaming around to the { $dir }.
+npc-speech-heard_deed =
+    .a0 = I heard you helped out { $npc }, that was good of you.
+    .a1 = You're the one who gave { $npc } a hand, aren't you? Thank you.
+npc-speech-shortage =
+    .a0 = Times are lean, we're running short on supplies.
+    .a1 = Stores are running low around here.
+npc-speech-storm =
+    .a0 = That storm did a number on the fields.
+    .a1 = What a storm! I hope we don't get another one like it soon.
+npc-speech-site_attacked =
+    .a0 = We're under attack!
+    .a1 = They killed one of us! To arms!
+npc-speech-raid_set_out =
+    .a0 = To arms! We march on our enemies!
+    .a1 = Form up, we have a town to take.
+npc-speech-at_war =
+    .a0 = We are at war with { $faction }, travel carefully.
+    .a1 = { $faction } will never take our land!
+npc-speech-site_conquered =
+    .a0 = We have new rulers now, best keep your head down.
+    .a1 = Our town has fallen...
+npc-speech-victory =
+    .a0 = Did you hear? { $site } is ours now!
+    .a1 = Our guards took { $site }, what a victory!
 npc-speech-welcome-aboard =
     .a0 = Welcome aboard!
     .a1 = Can I see your ticket... just kidding it's free!
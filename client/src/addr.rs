@@ -1,4 +1,5 @@
-use std::net::SocketAddr;
+pub use network::LinkSimulation;
+use std::{net::SocketAddr, path::PathBuf};
 use tokio::net::lookup_host;
 use tracing::trace;
 
@@ -15,10 +16,39 @@ pub enum ConnectionArgs {
         prefer_ipv6: bool,
     },
     Mpsc(u64),
+    /// Any of the above, but everything the client sends goes over a degraded
+    /// link, to test bad connections.
+    Simulated(Box<ConnectionArgs>, LinkSimulation),
+    /// Any of the above, but everything the server sends is recorded into a
+    /// demo file at the given path.
+    Recorded(Box<ConnectionArgs>, PathBuf),
+    /// Plays back a demo file instead of connecting to a server.
+    Demo(PathBuf),
 }
 
 impl ConnectionArgs {
     const DEFAULT_PORT: u16 = 14004;
+
+    /// Splits off the innermost [`LinkSimulation`] and demo recording path,
+    /// if any.
+    pub(crate) fn split_options(self) -> (Self, Option<LinkSimulation>, Option<PathBuf>) {
+        let mut args = self;
+        let mut link_simulation = None;
+        let mut demo_path = None;
+        loop {
+            match args {
+                ConnectionArgs::Simulated(inner, sim) => {
+                    args = *inner;
+                    link_simulation = Some(sim);
+                },
+                ConnectionArgs::Recorded(inner, path) => {
+                    args = *inner;
+                    demo_path = Some(path);
+                },
+                args => return (args, link_simulation, demo_path),
+            }
+        }
+    }
 }
 
 /// Parse ip address or resolves hostname.
@@ -0,0 +1,100 @@
+use clap::Parser;
+use std::{path::PathBuf, process::ExitCode, sync::Arc, thread};
+use tokio::runtime::Runtime;
+use tracing::{error, info};
+use veloren_client::{
+    addr::{ConnectionArgs, LinkSimulation},
+    bot::{Bot, BotError, Scenario},
+    Client,
+};
+
+#[derive(Parser)]
+#[command(about = "Runs a scenario with headless bots against a server without auth.")]
+struct Opt {
+    /// The scenario to run, see `veloren_client::bot::Scenario` for the format
+    scenario: PathBuf,
+    /// Server to connect to
+    #[arg(long, default_value = "localhost")]
+    server: String,
+    /// Number of bots, overrides the one of the scenario
+    #[arg(long)]
+    bots: Option<u32>,
+    /// Bots are called `<PREFIX>0`, `<PREFIX>1`, ...
+    #[arg(long, default_value = "bot")]
+    prefix: String,
+    /// Simulate a bad connection, e.g. `latency=150,jitter=30,drop=0.05`
+    #[arg(long, value_parser = LinkSimulation::parse)]
+    link_simulation: Option<LinkSimulation>,
+}
+
+fn main() -> ExitCode {
+    let opt = Opt::parse();
+    let _guards = common_frontend::init_stdout(None);
+
+    let scenario = match Scenario::load(&opt.scenario) {
+        Ok(scenario) => Arc::new(scenario),
+        Err(e) => {
+            error!("Failed to load the scenario: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+    let bots = opt.bots.unwrap_or(scenario.bots);
+    let runtime = Arc::new(Runtime::new().unwrap());
+
+    let handles = (0..bots)
+        .map(|i| {
+            let name = format!("{}{}", opt.prefix, i);
+            let server = opt.server.clone();
+            let link_simulation = opt.link_simulation;
+            let scenario = Arc::clone(&scenario);
+            let runtime = Arc::clone(&runtime);
+            thread::spawn(move || {
+                let result = run_bot(&name, server, link_simulation, &scenario, runtime);
+                match &result {
+                    Ok(()) => info!(?name, "Finished the scenario"),
+                    Err(e) => error!(?name, "Failed the scenario: {}", e),
+                }
+                result
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let failed = handles
+        .into_iter()
+        .filter(|handle| !matches!(handle.join(), Ok(Ok(()))))
+        .count();
+    if failed == 0 {
+        info!("All {} bots finished the scenario", bots);
+        ExitCode::SUCCESS
+    } else {
+        error!("{} of {} bots failed the scenario", failed, bots);
+        ExitCode::FAILURE
+    }
+}
+
+fn run_bot(
+    name: &str,
+    server: String,
+    link_simulation: Option<LinkSimulation>,
+    scenario: &Scenario,
+    runtime: Arc<Runtime>,
+) -> Result<(), BotError> {
+    let mut addr = ConnectionArgs::Tcp {
+        prefer_ipv6: false,
+        hostname: server,
+    };
+    if let Some(sim) = link_simulation {
+        addr = ConnectionArgs::Simulated(Box::new(addr), sim);
+    }
+    // NOTE: use a no-auth server
+    let client = runtime.block_on(Client::new(
+        addr,
+        Arc::clone(&runtime),
+        &mut None,
+        name,
+        "",
+        |_| false,
+        &|_| {},
+    ))?;
+    scenario.run(&mut Bot::new(client), name)
+}
@@ -15,7 +15,10 @@ use std::{
 };
 use tokio::runtime::Runtime;
 use vek::*;
-use veloren_client::{addr::ConnectionArgs, Client};
+use veloren_client::{
+    addr::{ConnectionArgs, LinkSimulation},
+    Client,
+};
 
 const CHUNK_SIZE: f32 = TerrainChunkSize::RECT_SIZE.x as f32;
 
@@ -33,6 +36,9 @@ struct Opt {
     /// Whether the clients should move
     #[arg(short, long)]
     movement: bool,
+    /// Simulate a bad connection, e.g. `latency=150,jitter=30,drop=0.05`
+    #[arg(long, value_parser = LinkSimulation::parse)]
+    link_simulation: Option<LinkSimulation>,
 }
 
 fn main() {
@@ -110,10 +116,13 @@ fn run_client(
 ) -> Result<(), veloren_client::Error> {
     let mut client = loop {
         // Connect to localhost
-        let addr = ConnectionArgs::Tcp {
+        let mut addr = ConnectionArgs::Tcp {
             prefer_ipv6: false,
             hostname: "localhost".into(),
         };
+        if let Some(sim) = opt.link_simulation {
+            addr = ConnectionArgs::Simulated(Box::new(addr), sim);
+        }
         let runtime_clone = Arc::clone(&runtime);
         // NOTE: use a no-auth server
         match runtime.block_on(Client::new(
@@ -123,6 +132,7 @@ fn run_client(
             &username,
             "",
             |_| false,
+            &|_| {},
         )) {
             Err(e) => tracing::warn!(?e, "Client {} disconnected", index),
             Ok(client) => break client,
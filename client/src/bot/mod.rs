@@ -0,0 +1,728 @@
+//! Headless bots, a [`Client`] driven by high-level actions instead of player
+//! input.
+//!
+//! A [`Bot`] ticks its client on its own clock and blocks until an action is
+//! done, e.g. [`Bot::goto`] walks to a position using the same pathfinding as
+//! NPCs. Actions can also be described as [`BotAction`]s, which is what
+//! [`Scenario`]s are made of. Running a bot per thread is the easiest way to
+//! simulate a lot of players, see the `scenario` binary.
+//!
+//! Bots act like a player would, so the server treats them like players too:
+//! everything they do is subject to the usual checks.
+
+mod scenario;
+
+pub use scenario::Scenario;
+
+use crate::{Client, Error, Event};
+use common::{
+    clock::Clock,
+    comp::{
+        self,
+        inventory::{item::ItemDefinitionIdOwned, InventoryUpdateEvent},
+        invite::InviteKind,
+        Alignment, Body, ControllerInputs, Health, InputKind, Item, Pos,
+    },
+    consts::MAX_PICKUP_RANGE,
+    mounting::VolumePos,
+    path::{Chaser, TraversalConfig},
+    terrain::SpriteKind,
+    trade::{PendingTrade, TradeAction, TradeId},
+    uid::Uid,
+    util::Dir,
+    vol::ReadVol,
+    ViewDistances,
+};
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+use specs::{Entity as EcsEntity, Join, WorldExt};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    time::{Duration, Instant},
+};
+use vek::*;
+
+/// How often bots tick their client.
+const TICK_RATE: f64 = 30.0;
+/// Distance (in blocks) within which an enemy is attacked instead of chased.
+const ATTACK_RANGE: f32 = 2.5;
+/// Distance (in blocks) crafting stations are searched for.
+const STATION_SEARCH_RADIUS: i32 = 24;
+/// How long to wait for the server to answer a pickup before trying another
+/// item.
+const PICKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum BotError {
+    Client(Error),
+    Kicked(String),
+    Disconnected,
+    /// The action didn't finish in time.
+    Timeout,
+    /// Selecting or creating a character failed.
+    Character(String),
+    /// There was nothing in range to act on.
+    NoTarget,
+    /// The recipe is unknown, ingredients are missing or no crafting station
+    /// is close enough.
+    Craft(String),
+    /// An [`Expectation`] didn't hold.
+    Expectation(String),
+    Io(std::io::Error),
+    Scenario(String),
+    /// A step of a scenario failed.
+    Step(usize, Box<BotError>),
+}
+
+impl From<Error> for BotError {
+    fn from(err: Error) -> Self { Self::Client(err) }
+}
+
+impl From<std::io::Error> for BotError {
+    fn from(err: std::io::Error) -> Self { Self::Io(err) }
+}
+
+impl From<ron::error::SpannedError> for BotError {
+    fn from(err: ron::error::SpannedError) -> Self { Self::Scenario(err.to_string()) }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Client(err) => write!(f, "Client error: {:?}", err),
+            Self::Kicked(reason) => write!(f, "Kicked: {}", reason),
+            Self::Disconnected => write!(f, "Disconnected from the server"),
+            Self::Timeout => write!(f, "Timed out"),
+            Self::Character(err) => write!(f, "Couldn't join with a character: {}", err),
+            Self::NoTarget => write!(f, "Nothing in range"),
+            Self::Craft(recipe) => write!(f, "Can't craft {}", recipe),
+            Self::Expectation(err) => write!(f, "Expectation failed: {}", err),
+            Self::Io(err) => write!(f, "IO error: {}", err),
+            Self::Scenario(err) => write!(f, "Invalid scenario: {}", err),
+            Self::Step(step, err) => write!(f, "Step {} failed: {}", step, err),
+        }
+    }
+}
+
+/// How a bot answers trade requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradePolicy {
+    /// Let invites time out.
+    #[default]
+    Ignore,
+    Decline,
+    /// Accept trades in which the bot gives nothing away.
+    AcceptGifts,
+    AcceptAll,
+}
+
+/// Something that is checked about the bot, to turn a scenario into a test.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Expectation {
+    Alive,
+    /// The bot stands within `tolerance` blocks of `pos`, horizontally.
+    Near {
+        pos: Vec3<f32>,
+        tolerance: f32,
+    },
+    /// The inventory holds at least `amount` of the item, e.g.
+    /// `"common.items.food.apple"`.
+    Item {
+        item: String,
+        amount: u32,
+    },
+}
+
+/// A high-level action, see the methods of [`Bot`] of the same name.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BotAction {
+    Goto {
+        pos: Vec3<f32>,
+        tolerance: f32,
+    },
+    /// Like `Goto`, relative to where the bot stands.
+    Walk {
+        offset: Vec2<f32>,
+        tolerance: f32,
+    },
+    AttackNearest {
+        range: f32,
+    },
+    PickUp {
+        range: f32,
+    },
+    Craft {
+        recipe: String,
+        amount: u32,
+    },
+    Chat(String),
+    Command(String, Vec<String>),
+    SetTradePolicy(TradePolicy),
+    Respawn,
+    /// Wait for the given number of seconds.
+    Wait(f32),
+    Expect(Expectation),
+}
+
+pub struct Bot {
+    client: Client,
+    clock: Clock,
+    chaser: Chaser,
+    /// Inputs for the next tick.
+    inputs: ControllerInputs,
+    /// Inputs that are held down.
+    pressed: BTreeSet<InputKind>,
+    trade_policy: TradePolicy,
+    /// The last trade state the bot answered, to not answer twice before the
+    /// server updated it.
+    answered_trade: Option<(TradeId, PendingTrade)>,
+}
+
+impl Bot {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            clock: Clock::new(Duration::from_secs_f64(1.0 / TICK_RATE)),
+            chaser: Chaser::default(),
+            inputs: ControllerInputs::default(),
+            pressed: BTreeSet::new(),
+            trade_policy: TradePolicy::default(),
+            answered_trade: None,
+        }
+    }
+
+    pub fn client(&self) -> &Client { &self.client }
+
+    pub fn client_mut(&mut self) -> &mut Client { &mut self.client }
+
+    pub fn trade_policy(&self) -> TradePolicy { self.trade_policy }
+
+    pub fn set_trade_policy(&mut self, trade_policy: TradePolicy) {
+        self.trade_policy = trade_policy;
+    }
+
+    /// Waits for the next tick and ticks the client with the inputs the
+    /// current action chose. Trade requests are answered here.
+    pub fn tick(&mut self) -> Result<Vec<Event>, BotError> {
+        self.clock.tick();
+        let inputs = std::mem::take(&mut self.inputs);
+        let events = self.client.tick(inputs, self.clock.dt(), |_| {})?;
+        self.client.cleanup();
+        for event in &events {
+            match event {
+                Event::Disconnect => return Err(BotError::Disconnected),
+                Event::Kicked(reason) => return Err(BotError::Kicked(reason.clone())),
+                _ => {},
+            }
+        }
+        self.answer_trades();
+        Ok(events)
+    }
+
+    /// Ticks until `f` returns a value, `f` is called with the events of each
+    /// tick and may set the inputs for the next one.
+    fn run_until<T>(
+        &mut self,
+        timeout: Duration,
+        mut f: impl FnMut(&mut Self, &[Event]) -> Result<Option<T>, BotError>,
+    ) -> Result<T, BotError> {
+        let deadline = Instant::now() + timeout;
+        let result = loop {
+            let events = match self.tick() {
+                Ok(events) => events,
+                Err(err) => break Err(err),
+            };
+            match f(self, &events) {
+                Ok(Some(value)) => break Ok(value),
+                Ok(None) if Instant::now() > deadline => break Err(BotError::Timeout),
+                Ok(None) => {},
+                Err(err) => break Err(err),
+            }
+        };
+        self.release_all();
+        result
+    }
+
+    /// Selects the first character of the account, creating one called `name`
+    /// if there is none, and waits until the world around it is loaded.
+    pub fn join(
+        &mut self,
+        name: &str,
+        view_distance: u32,
+        timeout: Duration,
+    ) -> Result<(), BotError> {
+        self.client.load_character_list();
+        self.run_until(timeout, |bot, _| {
+            Ok((!bot.client.character_list().loading).then_some(()))
+        })?;
+
+        if self.client.character_list().characters.is_empty() {
+            self.client.create_character(
+                name.to_owned(),
+                Some("common.items.weapons.sword.starter".into()),
+                None,
+                default_body(),
+                None,
+            );
+            self.run_until(timeout, |_, events| {
+                for event in events {
+                    match event {
+                        Event::CharacterCreated(_) => return Ok(Some(())),
+                        Event::CharacterError(err) => {
+                            return Err(BotError::Character(err.clone()));
+                        },
+                        _ => {},
+                    }
+                }
+                Ok(None)
+            })?;
+            self.client.load_character_list();
+            self.run_until(timeout, |bot, _| {
+                let list = bot.client.character_list();
+                Ok((!list.loading && !list.characters.is_empty()).then_some(()))
+            })?;
+        }
+
+        let character_id = self
+            .client
+            .character_list()
+            .characters
+            .first()
+            .and_then(|item| item.character.id)
+            .ok_or_else(|| BotError::Character("No character to select".into()))?;
+        self.client.request_character(character_id, ViewDistances {
+            terrain: view_distance,
+            entity: view_distance,
+        });
+        self.run_until(timeout, |_, events| {
+            for event in events {
+                match event {
+                    Event::CharacterJoined(_) => return Ok(Some(())),
+                    Event::CharacterError(err) => return Err(BotError::Character(err.clone())),
+                    _ => {},
+                }
+            }
+            Ok(None)
+        })?;
+        // Pathfinding needs the terrain around the character
+        self.run_until(timeout, |bot, _| Ok(bot.client.current_chunk().map(|_| ())))
+    }
+
+    /// Ticks without doing anything.
+    pub fn wait(&mut self, duration: Duration) -> Result<(), BotError> {
+        let end = Instant::now() + duration;
+        self.run_until(duration * 2, |_, _| {
+            Ok((Instant::now() >= end).then_some(()))
+        })
+    }
+
+    pub fn chat(&mut self, msg: String) { self.client.send_chat(msg); }
+
+    pub fn command(&mut self, name: String, args: Vec<String>) {
+        self.client.send_command(name, args);
+    }
+
+    /// Walks to `pos` until the bot stands within `tolerance` blocks of it,
+    /// horizontally.
+    pub fn goto(
+        &mut self,
+        pos: Vec3<f32>,
+        tolerance: f32,
+        timeout: Duration,
+    ) -> Result<(), BotError> {
+        self.chaser = Chaser::default();
+        self.run_until(timeout, |bot, _| {
+            let own_pos = bot.position()?;
+            if own_pos.xy().distance_squared(pos.xy()) <= tolerance.powi(2) {
+                return Ok(Some(()));
+            }
+            bot.steer_towards(pos, tolerance);
+            Ok(None)
+        })
+    }
+
+    /// Fights the nearest enemy within `range` blocks until it's dead.
+    pub fn attack_nearest(&mut self, range: f32, timeout: Duration) -> Result<(), BotError> {
+        let target = self.nearest_enemy(range).ok_or(BotError::NoTarget)?;
+        self.chaser = Chaser::default();
+        self.run_until(timeout, |bot, _| {
+            let own_pos = bot.position()?;
+            let target_pos = {
+                let ecs = bot.client.state().ecs();
+                match (
+                    ecs.read_storage::<Pos>().get(target),
+                    ecs.read_storage::<Health>().get(target),
+                ) {
+                    (Some(pos), Some(health)) if !health.is_dead => pos.0,
+                    // Killed, or it despawned
+                    _ => return Ok(Some(())),
+                }
+            };
+
+            if bot.client.is_wielding() == Some(false) {
+                bot.client.toggle_wield();
+            }
+            if let Some(dir) = Dir::from_unnormalized(target_pos - own_pos) {
+                bot.inputs.look_dir = dir;
+            }
+            let in_range = own_pos.distance_squared(target_pos) <= ATTACK_RANGE.powi(2);
+            if !in_range {
+                bot.steer_towards(target_pos, ATTACK_RANGE * 0.5);
+            }
+            bot.press(InputKind::Primary, in_range, Some(target));
+            Ok(None)
+        })
+    }
+
+    /// Picks up all items lying within `range` blocks, returns how many were
+    /// picked up. Items the server refuses to give to the bot are skipped.
+    pub fn pick_up(&mut self, range: f32, timeout: Duration) -> Result<u32, BotError> {
+        let mut picked_up = 0;
+        let mut skipped = HashSet::new();
+        let mut pending: Option<(Uid, ItemDefinitionIdOwned, Instant)> = None;
+        self.chaser = Chaser::default();
+        self.run_until(timeout, |bot, events| {
+            if let Some((uid, item, sent)) = &pending {
+                let answer = events.iter().find_map(|event| match event {
+                    Event::InventoryUpdated(updates) => {
+                        updates.iter().find_map(|update| match update {
+                            // Other items might be collected at the same time, e.g. by
+                            // commands
+                            InventoryUpdateEvent::Collected(collected)
+                                if collected.item_definition_id() == item.as_ref() =>
+                            {
+                                Some(true)
+                            },
+                            InventoryUpdateEvent::EntityCollectFailed { entity, .. }
+                                if entity == uid =>
+                            {
+                                Some(false)
+                            },
+                            _ => None,
+                        })
+                    },
+                    _ => None,
+                });
+                match answer {
+                    Some(true) => picked_up += 1,
+                    Some(false) => {
+                        skipped.insert(*uid);
+                    },
+                    None if sent.elapsed() > PICKUP_TIMEOUT => {
+                        skipped.insert(*uid);
+                    },
+                    None => return Ok(None),
+                }
+                pending = None;
+            }
+
+            let own_pos = bot.position()?;
+            let Some((entity, uid, item, item_pos)) = bot.nearest_item(range, &skipped) else {
+                return Ok(Some(picked_up));
+            };
+            if own_pos.distance_squared(item_pos) < (MAX_PICKUP_RANGE - 1.0).powi(2) {
+                bot.client.pick_up(entity);
+                pending = Some((uid, item, Instant::now()));
+            } else {
+                bot.steer_towards(item_pos, 1.0);
+            }
+            Ok(None)
+        })
+    }
+
+    /// Crafts a recipe, walking to a crafting station nearby first if it needs
+    /// one.
+    pub fn craft(&mut self, recipe: &str, amount: u32, timeout: Duration) -> Result<(), BotError> {
+        let (can_craft, station) = self.client.can_craft_recipe(recipe, amount);
+        if !can_craft {
+            return Err(BotError::Craft(recipe.to_owned()));
+        }
+        let craft_sprite = match station {
+            Some(kind) => {
+                let pos = self
+                    .nearest_sprite(kind)
+                    .ok_or_else(|| BotError::Craft(recipe.to_owned()))?;
+                self.goto(pos.as_::<f32>() + 0.5, MAX_PICKUP_RANGE - 2.0, timeout)?;
+                Some((VolumePos::terrain(pos), kind))
+            },
+            None => None,
+        };
+
+        let slots = self
+            .client
+            .recipe_book()
+            .get(recipe)
+            .zip(self.client.inventories().get(self.client.entity()))
+            .and_then(|(recipe, inv)| recipe.inventory_contains_ingredients(inv, 1).ok())
+            .ok_or_else(|| BotError::Craft(recipe.to_owned()))?;
+        if !self
+            .client
+            .craft_recipe(recipe, slots, craft_sprite, amount)
+        {
+            return Err(BotError::Craft(recipe.to_owned()));
+        }
+        self.run_until(timeout, |_, events| {
+            Ok(events
+                .iter()
+                .any(|event| {
+                    matches!(event, Event::InventoryUpdated(updates)
+                    if updates.iter().any(|update| {
+                        matches!(update, InventoryUpdateEvent::Craft)
+                    }))
+                })
+                .then_some(()))
+        })
+    }
+
+    /// Respawns if the bot is dead and waits until it's alive again.
+    pub fn respawn(&mut self, timeout: Duration) -> Result<(), BotError> {
+        self.client.respawn();
+        self.run_until(timeout, |bot, _| Ok((!bot.client.is_dead()).then_some(())))
+    }
+
+    pub fn check(&self, expectation: &Expectation) -> Result<(), BotError> {
+        let holds = match expectation {
+            Expectation::Alive => !self.client.is_dead(),
+            Expectation::Near { pos, tolerance } => self.client.position().map_or(false, |own| {
+                own.xy().distance_squared(pos.xy()) <= tolerance.powi(2)
+            }),
+            Expectation::Item { item, amount } => self.item_count(item) >= u64::from(*amount),
+        };
+        if holds {
+            Ok(())
+        } else {
+            Err(BotError::Expectation(format!("{:?}", expectation)))
+        }
+    }
+
+    /// Performs an action, giving up on it after `timeout`.
+    pub fn perform(&mut self, action: &BotAction, timeout: Duration) -> Result<(), BotError> {
+        match action {
+            BotAction::Goto { pos, tolerance } => self.goto(*pos, *tolerance, timeout),
+            BotAction::Walk { offset, tolerance } => {
+                let pos = self.position()? + Vec3::from(*offset);
+                self.goto(pos, *tolerance, timeout)
+            },
+            BotAction::AttackNearest { range } => self.attack_nearest(*range, timeout),
+            BotAction::PickUp { range } => self.pick_up(*range, timeout).map(|_| ()),
+            BotAction::Craft { recipe, amount } => self.craft(recipe, *amount, timeout),
+            BotAction::Chat(msg) => {
+                self.chat(msg.clone());
+                Ok(())
+            },
+            BotAction::Command(name, args) => {
+                self.command(name.clone(), args.clone());
+                Ok(())
+            },
+            BotAction::SetTradePolicy(trade_policy) => {
+                self.set_trade_policy(*trade_policy);
+                Ok(())
+            },
+            BotAction::Respawn => self.respawn(timeout),
+            BotAction::Wait(secs) => {
+                let duration = Duration::try_from_secs_f32(*secs)
+                    .map_err(|err| BotError::Scenario(format!("Can't wait {}s: {}", secs, err)))?;
+                self.wait(duration)
+            },
+            BotAction::Expect(expectation) => self.check(expectation),
+        }
+    }
+
+    fn position(&self) -> Result<Vec3<f32>, BotError> {
+        self.client
+            .position()
+            .ok_or_else(|| BotError::Character("Not in game".into()))
+    }
+
+    /// Sets the inputs to move towards `tgt` for the next tick.
+    fn steer_towards(&mut self, tgt: Vec3<f32>, min_tgt_dist: f32) {
+        let (Some(pos), Some(vel), Some(physics), Some(body)) = (
+            self.client.position(),
+            self.client.current::<comp::Vel>(),
+            self.client.current::<comp::PhysicsState>(),
+            self.client.current::<Body>(),
+        ) else {
+            return;
+        };
+        let traversal_config = TraversalConfig {
+            node_tolerance: 1.5,
+            slow_factor: (body.base_accel() / 250.0).min(1.0),
+            on_ground: physics.on_ground.is_some(),
+            in_liquid: physics.in_liquid().is_some(),
+            min_tgt_dist,
+            can_climb: body.can_climb(),
+            can_fly: false,
+        };
+        let terrain = self.client.state().terrain();
+        let bearing = self
+            .chaser
+            .chase(&*terrain, pos, vel.0, tgt, traversal_config);
+        drop(terrain);
+
+        if let Some((bearing, speed)) = bearing {
+            self.inputs.move_dir = bearing.xy().try_normalized().unwrap_or_else(Vec2::zero) * speed;
+            self.inputs.move_z = bearing.z;
+            if bearing.z > 0.0 {
+                self.inputs.climb = Some(comp::Climb::Up);
+            }
+            if let Some(dir) = Dir::from_unnormalized(bearing.with_z(0.0)) {
+                self.inputs.look_dir = dir;
+            }
+            self.press(InputKind::Jump, bearing.z > 1.5, None);
+        } else {
+            self.press(InputKind::Jump, false, None);
+        }
+    }
+
+    /// Presses or releases an input, only telling the server about changes.
+    fn press(&mut self, input: InputKind, pressed: bool, target: Option<EcsEntity>) {
+        let changed = if pressed {
+            self.pressed.insert(input)
+        } else {
+            self.pressed.remove(&input)
+        };
+        if changed {
+            self.client.handle_input(input, pressed, None, target);
+        }
+    }
+
+    fn release_all(&mut self) {
+        for input in std::mem::take(&mut self.pressed) {
+            self.client.handle_input(input, false, None, None);
+        }
+    }
+
+    fn nearest_enemy(&self, range: f32) -> Option<EcsEntity> {
+        let own_pos = self.client.position()?;
+        let own_alignment = self
+            .client
+            .current::<Alignment>()
+            .or_else(|| self.client.uid().map(Alignment::Owned))?;
+        let ecs = self.client.state().ecs();
+        (
+            &ecs.entities(),
+            &ecs.read_storage::<Pos>(),
+            &ecs.read_storage::<Health>(),
+            &ecs.read_storage::<Alignment>(),
+        )
+            .join()
+            .filter(|(entity, _, health, alignment)| {
+                *entity != self.client.entity()
+                    && !health.is_dead
+                    && alignment.hostile_towards(own_alignment)
+            })
+            .map(|(entity, pos, _, _)| (entity, pos.0.distance_squared(own_pos)))
+            .filter(|(_, dist_sqrd)| *dist_sqrd <= range.powi(2))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    }
+
+    fn nearest_item(
+        &self,
+        range: f32,
+        skipped: &HashSet<Uid>,
+    ) -> Option<(EcsEntity, Uid, ItemDefinitionIdOwned, Vec3<f32>)> {
+        let own_pos = self.client.position()?;
+        let ecs = self.client.state().ecs();
+        (
+            &ecs.entities(),
+            &ecs.read_storage::<Uid>(),
+            &ecs.read_storage::<Pos>(),
+            &ecs.read_storage::<Item>(),
+        )
+            .join()
+            .filter(|(_, uid, pos, _)| {
+                !skipped.contains(*uid) && pos.0.distance_squared(own_pos) <= range.powi(2)
+            })
+            .min_by(|(_, _, a, _), (_, _, b, _)| {
+                a.0.distance_squared(own_pos)
+                    .total_cmp(&b.0.distance_squared(own_pos))
+            })
+            .map(|(entity, uid, pos, item)| {
+                (entity, *uid, item.item_definition_id().to_owned(), pos.0)
+            })
+    }
+
+    fn nearest_sprite(&self, kind: SpriteKind) -> Option<Vec3<i32>> {
+        let own_pos = self.client.position()?.map(|e| e.floor() as i32);
+        let terrain = self.client.state().terrain();
+        let r = STATION_SEARCH_RADIUS;
+        (-r..=r)
+            .flat_map(|x| (-r..=r).flat_map(move |y| (-r..=r).map(move |z| Vec3::new(x, y, z))))
+            .map(|offset| own_pos + offset)
+            .filter(|pos| {
+                terrain
+                    .get(*pos)
+                    .map_or(false, |block| block.get_sprite() == Some(kind))
+            })
+            .min_by_key(|pos| pos.distance_squared(own_pos))
+    }
+
+    fn item_count(&self, item: &str) -> u64 {
+        let item = ItemDefinitionIdOwned::Simple(item.to_owned());
+        self.client
+            .inventories()
+            .get(self.client.entity())
+            .map_or(0, |inv| {
+                inv.slots()
+                    .flatten()
+                    .filter(|slot_item| slot_item.item_definition_id() == item.as_ref())
+                    .map(|slot_item| u64::from(slot_item.amount()))
+                    .sum()
+            })
+    }
+
+    fn answer_trades(&mut self) {
+        if let Some((_, _, _, InviteKind::Trade)) = self.client.invite() {
+            match self.trade_policy {
+                TradePolicy::Ignore => {},
+                TradePolicy::Decline => self.client.decline_invite(),
+                TradePolicy::AcceptGifts | TradePolicy::AcceptAll => self.client.accept_invite(),
+            }
+        }
+
+        let Some((id, trade, _)) = self.client.pending_trade().clone() else {
+            self.answered_trade = None;
+            return;
+        };
+        let Some(party) = self.client.uid().and_then(|uid| trade.which_party(uid)) else {
+            return;
+        };
+        if trade.accept_flags[party]
+            || self
+                .answered_trade
+                .as_ref()
+                .map_or(false, |answered| *answered == (id, trade.clone()))
+        {
+            return;
+        }
+        let accept = match self.trade_policy {
+            TradePolicy::Ignore => return,
+            TradePolicy::Decline => false,
+            TradePolicy::AcceptGifts => trade.offers[party].is_empty(),
+            TradePolicy::AcceptAll => true,
+        };
+        self.client.perform_trade_action(if accept {
+            TradeAction::Accept(trade.phase())
+        } else {
+            TradeAction::Decline
+        });
+        self.answered_trade = Some((id, trade));
+    }
+}
+
+fn default_body() -> Body {
+    comp::body::humanoid::Body {
+        species: comp::body::humanoid::Species::Human,
+        body_type: comp::body::humanoid::BodyType::Male,
+        hair_style: 0,
+        beard: 0,
+        eyes: 0,
+        accessory: 0,
+        hair_color: 0,
+        skin: 0,
+        eye_color: 0,
+    }
+    .into()
+}
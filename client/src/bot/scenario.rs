@@ -0,0 +1,112 @@
+use super::{Bot, BotAction, BotError};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, path::Path, time::Duration};
+use tracing::debug;
+
+/// A list of [`BotAction`]s for bots to perform, written in RON:
+///
+/// ```ron
+/// Scenario(
+///     bots: 20,
+///     repeat: 0,
+///     steps: [
+///         Chat("Hello"),
+///         Walk(offset: (x: 40.0, y: 0.0), tolerance: 2.0),
+///         AttackNearest(range: 30.0),
+///         PickUp(range: 10.0),
+///         Expect(Alive),
+///     ],
+/// )
+/// ```
+///
+/// Many bots repeating a scenario forever make a load test, a single bot
+/// running it once with `Expect` steps makes a gameplay test.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    /// How many bots run the scenario at the same time.
+    pub bots: u32,
+    pub view_distance: u32,
+    /// Seconds after which a step is considered failed.
+    pub step_timeout: f32,
+    /// How often the steps are performed, 0 repeats them until a step fails.
+    pub repeat: u32,
+    pub steps: Vec<BotAction>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            bots: 1,
+            view_distance: 5,
+            step_timeout: 60.0,
+            repeat: 1,
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, BotError> {
+        let file = File::open(path)?;
+        Ok(ron::de::from_reader(file)?)
+    }
+
+    /// Joins with a character called `name` and performs the steps.
+    pub fn run(&self, bot: &mut Bot, name: &str) -> Result<(), BotError> {
+        let timeout = Duration::try_from_secs_f32(self.step_timeout)
+            .map_err(|err| BotError::Scenario(format!("Invalid step_timeout: {}", err)))?;
+        bot.join(name, self.view_distance, timeout)?;
+
+        let mut round = 0;
+        while self.repeat == 0 || round < self.repeat {
+            for (i, step) in self.steps.iter().enumerate() {
+                debug!(?name, ?round, ?step, "Performing step");
+                bot.perform(step, timeout)
+                    .map_err(|err| BotError::Step(i, Box::new(err)))?;
+            }
+            round += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Expectation, TradePolicy};
+
+    #[test]
+    fn parse_scenario() {
+        let scenario: Scenario = ron::de::from_str(
+            r#"Scenario(
+                bots: 3,
+                steps: [
+                    Goto(pos: (x: 10.0, y: 20.0, z: 30.0), tolerance: 1.0),
+                    Craft(recipe: "common.items.food.apple_stick", amount: 2),
+                    Command("tp", ["bot0"]),
+                    SetTradePolicy(AcceptGifts),
+                    Wait(1.5),
+                    Expect(Item(item: "common.items.food.apple", amount: 1)),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.bots, 3);
+        assert_eq!(scenario.repeat, 1);
+        assert_eq!(scenario.steps.len(), 6);
+        assert!(matches!(
+            scenario.steps[0],
+            BotAction::Goto { pos, .. } if pos.z == 30.0
+        ));
+        assert!(matches!(
+            scenario.steps[3],
+            BotAction::SetTradePolicy(TradePolicy::AcceptGifts)
+        ));
+        assert!(matches!(
+            &scenario.steps[5],
+            BotAction::Expect(Expectation::Item { amount: 1, .. })
+        ));
+    }
+}
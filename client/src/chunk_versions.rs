@@ -0,0 +1,163 @@
+//! Versions of the terrain chunks the client has loaded.
+//!
+//! The server numbers the states of each chunk and sends changes to it as a
+//! diff between two versions (see `ServerGeneral::TerrainChunkDiff`), but only
+//! to players who have the chunk within their view distance. The client keeps
+//! chunks loaded a bit further than that, so chunks that were out of view might
+//! have missed diffs. They are requested again with the version the client
+//! has once they come back into view, so that the server can send the changes
+//! that were missed.
+
+use hashbrown::{HashMap, HashSet};
+use vek::*;
+
+/// Distance (in chunks) beyond the view distance at which the server stops
+/// sending diffs of a chunk. It's 2 on the server, one chunk of it is left as
+/// a margin for the server's view of the player's position lagging behind.
+const DIFF_DISTANCE_MARGIN: u32 = 1;
+
+/// What to do with a diff received from the server
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum DiffAction {
+    /// The diff builds on the loaded version, apply it
+    Apply,
+    /// The loaded version already includes the diff
+    Ignore,
+    /// Changes between the loaded version (if any) and the diff's base were
+    /// missed, request them
+    Request(Option<u64>),
+}
+
+#[derive(Default)]
+pub(crate) struct ChunkVersions {
+    versions: HashMap<Vec2<i32>, u64>,
+    /// Loaded chunks that were out of view, which might have missed diffs
+    out_of_view: HashSet<Vec2<i32>>,
+}
+
+impl ChunkVersions {
+    pub(crate) fn get(&self, key: Vec2<i32>) -> Option<u64> { self.versions.get(&key).copied() }
+
+    /// Records the version of a chunk that was received in full, returns
+    /// whether it's newer than the loaded one
+    pub(crate) fn receive_chunk(&mut self, key: Vec2<i32>, version: u64) -> bool {
+        // Diffs might have already brought the chunk further than this
+        if self.get(key).map_or(false, |current| current > version) {
+            return false;
+        }
+        self.versions.insert(key, version);
+        self.out_of_view.remove(&key);
+        true
+    }
+
+    /// Decides what to do with a diff from `base` to `version`. Once an
+    /// [`DiffAction::Apply`] diff was applied, [`ChunkVersions::applied`] has
+    /// to be called.
+    pub(crate) fn diff_action(&self, key: Vec2<i32>, base: u64, version: u64) -> DiffAction {
+        match self.get(key) {
+            Some(current) if current == base => DiffAction::Apply,
+            Some(current) if current >= version => DiffAction::Ignore,
+            current => DiffAction::Request(current),
+        }
+    }
+
+    pub(crate) fn applied(&mut self, key: Vec2<i32>, version: u64) {
+        self.versions.insert(key, version);
+    }
+
+    pub(crate) fn remove(&mut self, key: Vec2<i32>) {
+        self.versions.remove(&key);
+        self.out_of_view.remove(&key);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.versions.clear();
+        self.out_of_view.clear();
+    }
+
+    /// Updates which loaded chunks are out of the player's view distance, and
+    /// returns the chunks which came back into it after they might have missed
+    /// diffs, along with their loaded version.
+    pub(crate) fn update_view(
+        &mut self,
+        player_chunk: Vec2<i32>,
+        view_distance: u32,
+    ) -> Vec<(Vec2<i32>, Option<u64>)> {
+        let dist_sqr = |key: Vec2<i32>| {
+            (player_chunk - key)
+                .map(|e| i64::from(e.unsigned_abs()))
+                .magnitude_squared()
+        };
+        let diff_distance = i64::from(view_distance + DIFF_DISTANCE_MARGIN);
+        for &key in self.versions.keys() {
+            if dist_sqr(key) > diff_distance.pow(2) {
+                self.out_of_view.insert(key);
+            }
+        }
+
+        let in_view = |key: Vec2<i32>| dist_sqr(key) <= i64::from(view_distance).pow(2);
+        let back_in_view = self
+            .out_of_view
+            .iter()
+            .copied()
+            .filter(|key| in_view(*key))
+            .collect::<Vec<_>>();
+        back_in_view
+            .into_iter()
+            .map(|key| {
+                self.out_of_view.remove(&key);
+                (key, self.get(key))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_are_applied_in_order() {
+        let key = Vec2::new(3, 4);
+        let mut versions = ChunkVersions::default();
+        assert!(versions.receive_chunk(key, 5));
+        assert_eq!(versions.diff_action(key, 5, 6), DiffAction::Apply);
+        versions.applied(key, 6);
+        // Already included, e.g. when the diff raced a full chunk
+        assert_eq!(versions.diff_action(key, 4, 6), DiffAction::Ignore);
+        assert!(!versions.receive_chunk(key, 5));
+        assert_eq!(versions.get(key), Some(6));
+    }
+
+    #[test]
+    fn missed_diffs_are_requested() {
+        let key = Vec2::new(3, 4);
+        let mut versions = ChunkVersions::default();
+        assert_eq!(versions.diff_action(key, 0, 1), DiffAction::Request(None));
+        versions.receive_chunk(key, 2);
+        assert_eq!(
+            versions.diff_action(key, 4, 5),
+            DiffAction::Request(Some(2))
+        );
+    }
+
+    #[test]
+    fn chunks_back_in_view_are_requested() {
+        let (near, far) = (Vec2::new(1, 0), Vec2::new(8, 0));
+        let mut versions = ChunkVersions::default();
+        versions.receive_chunk(near, 1);
+        versions.receive_chunk(far, 2);
+        assert!(versions.update_view(Vec2::zero(), 5).is_empty());
+
+        // Walking away leaves the near chunk loaded, but out of view
+        assert!(versions.update_view(Vec2::new(-6, 0), 5).is_empty());
+        assert_eq!(versions.update_view(Vec2::zero(), 5), [(near, Some(1))]);
+        // Only once
+        assert!(versions.update_view(Vec2::zero(), 5).is_empty());
+
+        // Unless a full chunk was received in the meantime
+        versions.update_view(Vec2::new(-6, 0), 5);
+        versions.receive_chunk(near, 3);
+        assert!(versions.update_view(Vec2::zero(), 5).is_empty());
+    }
+}
@@ -0,0 +1,585 @@
+//! Demo files, recordings of everything the server sent to a client that can be
+//! watched again later without a server.
+//!
+//! A demo starts with the messages the client received while connecting,
+//! followed by every `ServerGeneral` message it received afterwards, together
+//! with the time since the recording started. The server doesn't tell a client
+//! where its own entity is, so that is recorded alongside. The whole file is
+//! deflate compressed.
+//!
+//! For playback a [`Client`] connects to a [`DemoServer`] over an in-process
+//! channel, which answers the handshake with the recorded messages. From then
+//! on the recorded messages are handed to the client directly each tick (see
+//! [`DemoPlayback`]), so they can be paused and seeked through. The client
+//! spectates with a free camera and the recorded player is shown like any
+//! other entity.
+//!
+//! [`Client`]: crate::Client
+
+use crate::error::Error;
+use common::{
+    comp::{Ori, Pos, Vel},
+    uid::Uid,
+};
+use common_net::{
+    msg::{
+        ClientGeneral, ClientRegister, ClientType, EcsCompPacket, PingMsg, ResumeToken,
+        ServerGeneral, ServerInfo, ServerInit, ServerRegisterAnswer,
+    },
+    sync::EntityPackage,
+};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use network::{ListenAddr, Network, Participant, Pid, Promises, Stream};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::{runtime::Runtime, task::JoinHandle};
+use vek::Vec3;
+
+/// Bumped whenever the format of demo files changes. Demos also stop working
+/// when the messages they contain change, which isn't tracked here.
+pub const DEMO_VERSION: u32 = 1;
+
+/// How often recorded frames are written out, so that demos of clients that
+/// crashed don't lose more than that.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Uid of the spectator a demo is watched as, the recorded player keeps its
+/// own.
+const SPECTATOR_UID: Uid = Uid(u64::MAX);
+
+#[derive(Debug)]
+pub enum DemoError {
+    Io(std::io::Error),
+    Encoding(bincode::Error),
+    /// The demo was recorded with an incompatible version.
+    Version(u32),
+}
+
+impl From<std::io::Error> for DemoError {
+    fn from(err: std::io::Error) -> Self { Self::Io(err) }
+}
+
+impl From<bincode::Error> for DemoError {
+    fn from(err: bincode::Error) -> Self { Self::Encoding(err) }
+}
+
+impl fmt::Display for DemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "IO error: {}", err),
+            Self::Encoding(err) => write!(f, "Invalid demo: {}", err),
+            Self::Version(version) => write!(
+                f,
+                "Demo has version {}, only version {} is supported",
+                version, DEMO_VERSION
+            ),
+        }
+    }
+}
+
+/// The stream a recorded message arrived on, which decides how it's handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum DemoStream {
+    General,
+    CharacterScreen,
+    InGame,
+    Terrain,
+    Physics,
+}
+
+/// `M` is a reference when recording, to avoid cloning messages.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum DemoFrame<M = ServerGeneral> {
+    Msg {
+        time: f64,
+        stream: DemoStream,
+        msg: M,
+    },
+    /// Physics of the recording player's own entity.
+    Player {
+        time: f64,
+        pos: Pos,
+        vel: Vel,
+        ori: Ori,
+    },
+}
+
+impl<M> DemoFrame<M> {
+    fn time(&self) -> f64 {
+        match self {
+            Self::Msg { time, .. } | Self::Player { time, .. } => *time,
+        }
+    }
+}
+
+pub(crate) struct DemoRecorder {
+    writer: DeflateEncoder<BufWriter<File>>,
+    started: Instant,
+    last_flush: Instant,
+    last_player: Option<(Pos, Vel, Ori)>,
+}
+
+impl DemoRecorder {
+    /// Creates the demo file, `server_info` and `init` are what the server sent
+    /// while connecting. `init` is the [`ServerInit`], unless testing.
+    pub(crate) fn create<I: Serialize>(
+        path: &Path,
+        server_info: &ServerInfo,
+        init: &I,
+    ) -> Result<Self, DemoError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer =
+            DeflateEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        bincode::serialize_into(&mut writer, &DEMO_VERSION)?;
+        bincode::serialize_into(&mut writer, server_info)?;
+        bincode::serialize_into(&mut writer, init)?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            last_flush: Instant::now(),
+            last_player: None,
+        })
+    }
+
+    fn time(&self) -> f64 { self.started.elapsed().as_secs_f64() }
+
+    fn write<M: Serialize>(&mut self, frame: &DemoFrame<M>) -> Result<(), DemoError> {
+        bincode::serialize_into(&mut self.writer, frame)?;
+        if self.last_flush.elapsed() > FLUSH_INTERVAL {
+            self.writer.flush()?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn record_msg(
+        &mut self,
+        stream: DemoStream,
+        msg: &ServerGeneral,
+    ) -> Result<(), DemoError> {
+        self.write(&DemoFrame::Msg {
+            time: self.time(),
+            stream,
+            msg,
+        })
+    }
+
+    /// Only records the player's physics if they changed since last time.
+    pub(crate) fn record_player(&mut self, pos: Pos, vel: Vel, ori: Ori) -> Result<(), DemoError> {
+        if self.last_player == Some((pos, vel, ori)) {
+            return Ok(());
+        }
+        self.last_player = Some((pos, vel, ori));
+        self.write(&DemoFrame::<()>::Player {
+            time: self.time(),
+            pos,
+            vel,
+            ori,
+        })
+    }
+}
+
+struct DemoReader {
+    reader: DeflateDecoder<BufReader<File>>,
+}
+
+impl DemoReader {
+    /// `I` is the [`ServerInit`], unless testing.
+    fn open<I: DeserializeOwned>(path: &Path) -> Result<(Self, ServerInfo, I), DemoError> {
+        let mut reader = DeflateDecoder::new(BufReader::new(File::open(path)?));
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != DEMO_VERSION {
+            return Err(DemoError::Version(version));
+        }
+        let server_info = bincode::deserialize_from(&mut reader)?;
+        let init = bincode::deserialize_from(&mut reader)?;
+        Ok((Self { reader }, server_info, init))
+    }
+
+    /// Returns `None` at the end of the demo. Demos of clients that crashed
+    /// end with a partial frame, which is ignored.
+    fn next_frame(&mut self) -> Result<Option<DemoFrame>, DemoError> {
+        match bincode::deserialize_from(&mut self.reader) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(e) => match &*e {
+                bincode::ErrorKind::Io(io_err) if io_err.kind() == ErrorKind::UnexpectedEof => {
+                    Ok(None)
+                },
+                _ => Err(e.into()),
+            },
+        }
+    }
+}
+
+/// The server's end of a playback connection, in the order the server opens
+/// them.
+struct ServerStreams {
+    general: Stream,
+    ping: Stream,
+    register: Stream,
+    character_screen: Stream,
+    in_game: Stream,
+    terrain: Stream,
+    physics: Stream,
+}
+
+impl ServerStreams {
+    async fn open(participant: &Participant) -> Result<Self, Error> {
+        let reliable = Promises::ORDERED | Promises::CONSISTENCY | Promises::GUARANTEED_DELIVERY;
+        let reliablec = reliable | Promises::COMPRESSED;
+
+        Ok(Self {
+            general: participant.open(3, reliablec, 500).await?,
+            ping: participant.open(2, reliable, 500).await?,
+            register: participant.open(3, reliablec, 500).await?,
+            character_screen: participant.open(3, reliablec, 500).await?,
+            in_game: participant.open(3, reliablec, 100_000).await?,
+            terrain: participant.open(4, reliable, 20_000).await?,
+            physics: participant.open(3, reliablec, 100_000).await?,
+        })
+    }
+}
+
+/// Answers the connection handshake of a client with the messages recorded in
+/// a demo.
+pub(crate) struct DemoServer {
+    mpsc_id: u64,
+    handshake: JoinHandle<Result<DemoPlayback, Error>>,
+}
+
+impl DemoServer {
+    pub(crate) async fn start(path: PathBuf, runtime: &Runtime) -> Result<Self, Error> {
+        // Reading through the whole demo takes a while
+        let (path, reader, mut server_info, mut init, scan) =
+            tokio::task::spawn_blocking(move || {
+                let (reader, server_info, init) = DemoReader::open(&path)?;
+                let scan = DemoScan::new(&path)?;
+                Ok::<_, DemoError>((path, reader, server_info, init, scan))
+            })
+            .await
+            .expect("Demo scan should not panic")?;
+
+        // There is nobody to authenticate with
+        server_info.auth_provider = None;
+        // The client spectates, while the recorded player gets an entity of its own
+        let ServerInit::GameSync { entity_package, .. } = &mut init;
+        let player = entity_package.clone();
+        entity_package.uid = SPECTATOR_UID;
+
+        let mut network = Network::new(Pid::new(), runtime);
+        let mpsc_id = rand::random();
+        network.listen(ListenAddr::Mpsc(mpsc_id)).await?;
+        let handshake = runtime.spawn(async move {
+            let participant = network.connected().await?;
+            let mut streams = ServerStreams::open(&participant).await?;
+            streams.register.recv::<ClientType>().await?;
+            streams.register.send(server_info)?;
+            streams.register.recv::<ClientRegister>().await?;
+            streams
+                .register
+                .send(ServerRegisterAnswer::Ok(ResumeToken(rand::random())))?;
+            streams.register.send(init)?;
+
+            Ok(DemoPlayback {
+                path,
+                reader,
+                next: None,
+                time: 0.0,
+                duration: scan.duration,
+                speed: 1.0,
+                paused: false,
+                seek: None,
+                spawn_player: true,
+                spawn_point: scan.spawn_point.unwrap_or_default(),
+                player,
+                streams,
+                _participant: participant,
+                _network: network,
+            })
+        });
+
+        Ok(Self { mpsc_id, handshake })
+    }
+
+    pub(crate) fn mpsc_id(&self) -> u64 { self.mpsc_id }
+
+    /// Waits for the handshake to finish, which is the case once the client
+    /// received the initial sync.
+    pub(crate) async fn playback(self) -> Result<DemoPlayback, Error> {
+        self.handshake
+            .await
+            .expect("Demo handshake should not panic")
+    }
+}
+
+/// Recorded frames that are due, see [`DemoPlayback::advance`].
+pub(crate) struct DemoTick {
+    /// The playback jumped back to the start, everything received so far
+    /// has to be forgotten.
+    pub rewound: bool,
+    /// The recorded player's entity, to be created after the client's state
+    /// was reset.
+    pub player: Option<EntityPackage<EcsCompPacket>>,
+    pub frames: Vec<DemoFrame>,
+}
+
+/// Plays back a demo in a client connected to a [`DemoServer`].
+pub struct DemoPlayback {
+    path: PathBuf,
+    reader: DemoReader,
+    /// Read ahead of `time`
+    next: Option<DemoFrame>,
+    time: f64,
+    duration: f64,
+    speed: f64,
+    paused: bool,
+    seek: Option<f64>,
+    /// The recorded player's entity has to be (re)created
+    spawn_player: bool,
+    spawn_point: Vec3<f32>,
+    player: EntityPackage<EcsCompPacket>,
+    streams: ServerStreams,
+    _participant: Participant,
+    _network: Network,
+}
+
+impl DemoPlayback {
+    /// Position of the playback in seconds since the recording started.
+    pub fn time(&self) -> f64 { self.time }
+
+    /// Length of the demo in seconds.
+    pub fn duration(&self) -> f64 { self.duration }
+
+    pub fn is_paused(&self) -> bool { self.paused }
+
+    /// Playback pauses by itself at the end of the demo.
+    pub fn set_paused(&mut self, paused: bool) { self.paused = paused; }
+
+    pub fn speed(&self) -> f64 { self.speed }
+
+    pub fn set_speed(&mut self, speed: f64) { self.speed = speed.max(0.0); }
+
+    /// Jumps to the given time in seconds. Everything up to that time is
+    /// played at once, so jumping backwards plays the demo from the start.
+    pub fn seek(&mut self, time: f64) { self.seek = Some(time.clamp(0.0, self.duration)); }
+
+    pub(crate) fn player_uid(&self) -> Uid { self.player.uid }
+
+    /// Answers the messages the client sent, all of which are ignored except
+    /// for pings and the request to spectate.
+    pub(crate) fn respond(&mut self) -> Result<(), Error> {
+        let streams = &mut self.streams;
+        while let Some(msg) = streams.ping.try_recv::<PingMsg>()? {
+            if let PingMsg::Ping = msg {
+                streams.ping.send(PingMsg::Pong)?;
+            }
+        }
+        while let Some(msg) = streams.character_screen.try_recv::<ClientGeneral>()? {
+            if let ClientGeneral::Spectate(_) = msg {
+                streams
+                    .character_screen
+                    .send(ServerGeneral::SpectatorSuccess(self.spawn_point))?;
+            }
+        }
+        for stream in [
+            &mut streams.general,
+            &mut streams.in_game,
+            &mut streams.terrain,
+            &mut streams.physics,
+        ] {
+            while stream.try_recv::<ClientGeneral>()?.is_some() {}
+        }
+        Ok(())
+    }
+
+    /// Moves the playback forward by `dt` seconds (scaled by the speed) or to
+    /// the time it was seeked to, and returns the frames that became due.
+    pub(crate) fn advance(&mut self, dt: f64) -> Result<DemoTick, Error> {
+        let target = match self.seek.take() {
+            Some(time) => time,
+            None if self.paused => self.time,
+            None => self.time + dt * self.speed,
+        };
+        let rewound = target < self.time;
+        if rewound {
+            self.reader = DemoReader::open::<ServerInit>(&self.path)?.0;
+            self.next = None;
+            self.spawn_player = true;
+        }
+
+        let mut frames = Vec::new();
+        loop {
+            let frame = match self.next.take() {
+                Some(frame) => frame,
+                None => match self.reader.next_frame()? {
+                    Some(frame) => frame,
+                    None => {
+                        self.paused = true;
+                        break;
+                    },
+                },
+            };
+            if frame.time() > target {
+                self.next = Some(frame);
+                break;
+            }
+            if Self::is_played_back(&frame) {
+                frames.push(frame);
+            }
+        }
+        self.time = target.min(self.duration);
+
+        Ok(DemoTick {
+            rewound,
+            player: std::mem::take(&mut self.spawn_player).then(|| self.player.clone()),
+            frames,
+        })
+    }
+
+    /// Skips messages that would take the client out of spectating.
+    fn is_played_back(frame: &DemoFrame) -> bool {
+        !matches!(
+            frame,
+            DemoFrame::Msg {
+                stream: DemoStream::CharacterScreen,
+                ..
+            } | DemoFrame::Msg {
+                msg: ServerGeneral::ExitInGameSuccess
+                    | ServerGeneral::SetPlayerEntity(_)
+                    | ServerGeneral::Disconnect(_),
+                ..
+            }
+        )
+    }
+}
+
+/// What is known about a demo before playing it.
+struct DemoScan {
+    duration: f64,
+    spawn_point: Option<Vec3<f32>>,
+}
+
+impl DemoScan {
+    fn new(path: &Path) -> Result<Self, DemoError> {
+        let (mut reader, _, _) = DemoReader::open::<ServerInit>(path)?;
+        let mut scan = Self {
+            duration: 0.0,
+            spawn_point: None,
+        };
+        while let Some(frame) = reader.next_frame()? {
+            scan.duration = frame.time();
+            if let DemoFrame::Player { pos, .. } = frame {
+                scan.spawn_point.get_or_insert(pos.0);
+            }
+        }
+        Ok(scan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demo_path() -> PathBuf {
+        std::env::temp_dir().join(format!("veloren-demo-{}", rand::random::<u64>()))
+    }
+
+    fn server_info() -> ServerInfo {
+        ServerInfo {
+            name: "Demo".to_string(),
+            description: String::new(),
+            git_hash: String::new(),
+            git_date: String::new(),
+            auth_provider: None,
+        }
+    }
+
+    #[test]
+    fn demo_roundtrip() {
+        let path = demo_path();
+        let mut recorder = DemoRecorder::create(&path, &server_info(), &42u32).unwrap();
+        recorder
+            .record_msg(DemoStream::InGame, &ServerGeneral::ExitInGameSuccess)
+            .unwrap();
+        let pos = Pos(Vec3::new(1.0, 2.0, 3.0));
+        recorder
+            .record_player(pos, Vel(Vec3::zero()), Ori::default())
+            .unwrap();
+        // Unchanged physics aren't recorded again
+        recorder
+            .record_player(pos, Vel(Vec3::zero()), Ori::default())
+            .unwrap();
+        drop(recorder);
+
+        let (mut reader, info, init) = DemoReader::open::<u32>(&path).unwrap();
+        assert_eq!(info.name, "Demo");
+        assert_eq!(init, 42);
+        assert!(matches!(
+            reader.next_frame().unwrap(),
+            Some(DemoFrame::Msg {
+                stream: DemoStream::InGame,
+                msg: ServerGeneral::ExitInGameSuccess,
+                ..
+            })
+        ));
+        assert!(matches!(
+            reader.next_frame().unwrap(),
+            Some(DemoFrame::Player { pos: player, .. }) if player == pos
+        ));
+        assert!(reader.next_frame().unwrap().is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_demo_ends_at_the_last_complete_frame() {
+        let path = demo_path();
+        let mut recorder = DemoRecorder::create(&path, &server_info(), &42u32).unwrap();
+        recorder
+            .record_msg(DemoStream::InGame, &ServerGeneral::ExitInGameSuccess)
+            .unwrap();
+        // A client that crashed while recording leaves a partial frame behind
+        let frame = bincode::serialize(&DemoFrame::Msg {
+            time: 1.0,
+            stream: DemoStream::InGame,
+            msg: &ServerGeneral::ExitInGameSuccess,
+        })
+        .unwrap();
+        recorder
+            .writer
+            .write_all(&frame[..frame.len() / 2])
+            .unwrap();
+        recorder.writer.flush().unwrap();
+
+        let (mut reader, _, _) = DemoReader::open::<u32>(&path).unwrap();
+        assert!(reader.next_frame().unwrap().is_some());
+        assert!(reader.next_frame().unwrap().is_none());
+        drop(recorder);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let path = demo_path();
+        let mut writer = DeflateEncoder::new(File::create(&path).unwrap(), Compression::default());
+        bincode::serialize_into(&mut writer, &(DEMO_VERSION + 1)).unwrap();
+        bincode::serialize_into(&mut writer, &server_info()).unwrap();
+        bincode::serialize_into(&mut writer, &42u32).unwrap();
+        writer.finish().unwrap();
+
+        let version = DEMO_VERSION + 1;
+        assert!(matches!(
+            DemoReader::open::<u32>(&path),
+            Err(DemoError::Version(v)) if v == version
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
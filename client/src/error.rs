@@ -1,3 +1,4 @@
+use crate::demo::DemoError;
 use authc::AuthClientError;
 pub use network::{InitProtocolError, NetworkConnectError, NetworkError};
 use network::{ParticipantError, StreamError};
@@ -24,6 +25,8 @@ pub enum Error {
     //TODO: InvalidAlias,
     Other(String),
     SpecsErr(SpecsError),
+    /// A demo couldn't be played back
+    Demo(DemoError),
 }
 
 impl From<SpecsError> for Error {
@@ -42,6 +45,10 @@ impl From<StreamError> for Error {
     fn from(err: StreamError) -> Self { Self::StreamErr(err) }
 }
 
+impl From<DemoError> for Error {
+    fn from(err: DemoError) -> Self { Self::Demo(err) }
+}
+
 impl From<AuthClientError> for Error {
     fn from(err: AuthClientError) -> Self { Self::AuthClientError(err) }
 }
@@ -1,9 +1,14 @@
 #![deny(unsafe_code)]
 #![deny(clippy::clone_on_ref_ptr)]
-#![feature(option_zip)]
+#![feature(let_chains, option_zip)]
 
 pub mod addr;
+#[cfg(feature = "bot")] pub mod bot;
+pub mod demo;
 pub mod error;
+mod chunk_versions;
+mod prediction;
+mod reconnect;
 
 // Reexports
 pub use crate::error::Error;
@@ -13,7 +18,13 @@ pub use specs::{
     Builder, DispatcherBuilder, Entity as EcsEntity, Join, LendJoin, ReadStorage, World, WorldExt,
 };
 
-use crate::addr::ConnectionArgs;
+use crate::{
+    addr::ConnectionArgs,
+    chunk_versions::{ChunkVersions, DiffAction},
+    demo::{DemoError, DemoFrame, DemoPlayback, DemoRecorder, DemoServer, DemoStream, DemoTick},
+    prediction::{self, Prediction},
+    reconnect::{Credentials, Reconnect, ReconnectStatus, Reconnected},
+};
 use byteorder::{ByteOrder, LittleEndian};
 use common::{
     character::{CharacterId, CharacterItem},
@@ -38,7 +49,8 @@ use common::{
     mounting::{Rider, VolumePos, VolumeRider},
     outcome::Outcome,
     recipe::{ComponentRecipeBook, RecipeBook, RepairRecipeBook},
-    resources::{GameMode, PlayerEntity, Time, TimeOfDay},
+    resources::{DeltaTime, GameMode, PlayerEntity, Time, TimeOfDay},
+    rtsim::{QuestId, QuestInfo, QuestResponse},
     shared_server_config::ServerConstants,
     spiral::Spiral2d,
     terrain::{
@@ -56,15 +68,15 @@ use common_base::{prof_span, span};
 use common_net::{
     msg::{
         self,
-        world_msg::{EconomyInfo, PoiInfo, SiteId, SiteInfo},
+        world_msg::{EconomyInfo, PoiInfo, SiteFactionInfo, SiteId, SiteInfo},
         ChatTypeContext, ClientGeneral, ClientMsg, ClientRegister, ClientType, DisconnectReason,
         InviteAnswer, Notification, PingMsg, PlayerInfo, PlayerListUpdate, RegisterError,
-        ServerGeneral, ServerInit, ServerRegisterAnswer,
+        ResumeToken, ServerGeneral, ServerInit, ServerRegisterAnswer,
     },
     sync::WorldSyncExt,
 };
 use common_state::State;
-use common_systems::add_local_systems;
+use common_systems::{add_local_systems, phys};
 use comp::BuffKind;
 use hashbrown::{HashMap, HashSet};
 use image::DynamicImage;
@@ -79,7 +91,7 @@ use std::{
     time::{Duration, Instant},
 };
 use tokio::runtime::Runtime;
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 use vek::*;
 
 pub const MAX_SELECTABLE_VIEW_DISTANCE: u32 = 65;
@@ -113,6 +125,15 @@ pub enum Event {
     MapMarker(comp::MapMarkerUpdate),
     StartSpectate(Vec3<f32>),
     SpectatePosition(Vec3<f32>),
+    /// An NPC offered the character a quest, see [`Client::quest_offer`].
+    QuestOffer,
+    /// The connection to the server dropped, the client tries to reconnect.
+    /// The state is kept but not updated until then.
+    Reconnecting {
+        attempt: u32,
+    },
+    /// The client reconnected and picks up where it left off.
+    Reconnected,
 }
 
 #[derive(Debug)]
@@ -174,6 +195,7 @@ impl WorldData {
 pub struct SiteInfoRich {
     pub site: SiteInfo,
     pub economy: Option<EconomyInfo>,
+    pub faction: Option<SiteFactionInfo>,
 }
 
 struct WeatherLerp {
@@ -243,6 +265,9 @@ pub struct Client {
     lod_zones: HashMap<Vec2<i32>, lod::Zone>,
     lod_last_requested: Option<Instant>,
     force_update_counter: u64,
+    prediction: Prediction,
+    demo_recorder: Option<DemoRecorder>,
+    demo_playback: Option<DemoPlayback>,
 
     max_group_size: u32,
     // Client has received an invite (inviter uid, time out instant)
@@ -254,6 +279,10 @@ pub struct Client {
     pending_invites: HashSet<Uid>,
     // The pending trade the client is involved in, and it's id
     pending_trade: Option<(TradeId, PendingTrade, Option<SitePrices>)>,
+    // The quests the character took on
+    quests: Vec<QuestInfo>,
+    // The quest an NPC offered the character, awaiting an answer
+    quest_offer: Option<QuestInfo>,
 
     network: Option<Network>,
     participant: Option<Participant>,
@@ -263,6 +292,11 @@ pub struct Client {
     character_screen_stream: Stream,
     in_game_stream: Stream,
     terrain_stream: Stream,
+    physics_stream: Stream,
+    /// Set if the client can log in again on its own.
+    credentials: Option<Credentials>,
+    auto_reconnect: bool,
+    reconnect: Option<Reconnect>,
 
     client_timeout: Duration,
     last_server_ping: f64,
@@ -278,11 +312,14 @@ pub struct Client {
     /// Terrrain view distance
     server_view_distance_limit: Option<u32>,
     view_distance: Option<u32>,
+    requested_view_distances: Option<common::ViewDistances>,
     lod_distance: f32,
     // TODO: move into voxygen
     loaded_distance: f32,
 
     pending_chunks: HashMap<Vec2<i32>, Instant>,
+    /// The server's version of each loaded chunk, which diffs build on
+    chunk_versions: ChunkVersions,
     target_time_of_day: Option<TimeOfDay>,
     dt_adjustment: f64,
 
@@ -297,52 +334,57 @@ pub struct CharacterList {
     pub loading: bool,
 }
 
-impl Client {
-    pub async fn new(
-        addr: ConnectionArgs,
-        runtime: Arc<Runtime>,
-        // TODO: refactor to avoid needing to use this out parameter
-        mismatched_server_info: &mut Option<ServerInfo>,
-        username: &str,
-        password: &str,
-        auth_trusted: impl FnMut(&str) -> bool,
-        init_stage_update: &(dyn Fn(ClientInitStage) + Send + Sync),
-    ) -> Result<Self, Error> {
-        let network = Network::new(Pid::new(), &runtime);
+/// How the player proves who they are when registering.
+enum Login<'a> {
+    /// Signs in with the auth server of the server, if it has one.
+    Password {
+        username: &'a str,
+        password: &'a str,
+    },
+    /// Resumes the session the connection dropped out of, see [`reconnect`].
+    Resume(ResumeToken),
+}
 
-        init_stage_update(ClientInitStage::ConnectionEstablish);
-        let mut participant = match addr {
-            ConnectionArgs::Tcp {
-                hostname,
-                prefer_ipv6,
-            } => addr::try_connect(&network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?,
-            ConnectionArgs::Quic {
-                hostname,
-                prefer_ipv6,
-            } => {
-                warn!(
-                    "QUIC is enabled. This is experimental and you won't be able to connect to \
-                     TCP servers unless deactivated"
-                );
-                let config = quinn::ClientConfig::with_native_roots();
-                addr::try_connect(&network, &hostname, prefer_ipv6, |a| {
-                    ConnectAddr::Quic(a, config.clone(), hostname.clone())
-                })
-                .await?
-            },
-            ConnectionArgs::Mpsc(id) => network.connect(ConnectAddr::Mpsc(id)).await?,
-        };
+/// A connection to a server, before it is handed to a [`Client`].
+struct Connection {
+    network: Network,
+    participant: Participant,
+    general_stream: Stream,
+    ping_stream: Stream,
+    register_stream: Stream,
+    character_screen_stream: Stream,
+    in_game_stream: Stream,
+    terrain_stream: Stream,
+    physics_stream: Stream,
+}
 
-        let stream = participant.opened().await?;
-        let ping_stream = participant.opened().await?;
-        let mut register_stream = participant.opened().await?;
-        let character_screen_stream = participant.opened().await?;
-        let in_game_stream = participant.opened().await?;
-        let terrain_stream = participant.opened().await?;
+impl Connection {
+    /// Waits for the streams the server opens.
+    async fn open(network: Network, mut participant: Participant) -> Result<Self, Error> {
+        Ok(Self {
+            general_stream: participant.opened().await?,
+            ping_stream: participant.opened().await?,
+            register_stream: participant.opened().await?,
+            character_screen_stream: participant.opened().await?,
+            in_game_stream: participant.opened().await?,
+            terrain_stream: participant.opened().await?,
+            physics_stream: participant.opened().await?,
+            network,
+            participant,
+        })
+    }
 
+    /// Logs in and waits for the initial sync.
+    async fn handshake(
+        &mut self,
+        login: Login<'_>,
+        auth_trusted: impl FnMut(&str) -> bool,
+        mismatched_server_info: &mut Option<ServerInfo>,
+        init_stage_update: &(dyn Fn(ClientInitStage) + Send + Sync),
+    ) -> Result<(ServerInfo, ServerInit, ResumeToken), Error> {
         init_stage_update(ClientInitStage::WatingForServerVersion);
-        register_stream.send(ClientType::Game)?;
-        let server_info: ServerInfo = register_stream.recv().await?;
+        self.register_stream.send(ClientType::Game)?;
+        let server_info: ServerInfo = self.register_stream.recv().await?;
         if server_info.git_hash != *common::util::GIT_HASH {
             warn!(
                 "Server is running {}[{}], you are running {}[{}], versions might be incompatible!",
@@ -357,22 +399,92 @@ impl Client {
         mem::swap(mismatched_server_info, &mut Some(server_info.clone()));
         debug!("Auth Server: {:?}", server_info.auth_provider);
 
-        ping_stream.send(PingMsg::Ping)?;
+        self.ping_stream.send(PingMsg::Ping)?;
 
         init_stage_update(ClientInitStage::Authentication);
         // Register client
-        Self::register(
-            username,
-            password,
-            auth_trusted,
-            &server_info,
-            &mut register_stream,
-        )
-        .await?;
+        let resume_token =
+            Client::register(login, auth_trusted, &server_info, &mut self.register_stream).await?;
 
         init_stage_update(ClientInitStage::LoadingInitData);
         // Wait for initial sync
         let mut ping_interval = tokio::time::interval(Duration::from_secs(1));
+        let init = loop {
+            tokio::select! {
+                // Spawn in a blocking thread (leaving the network thread free).  This is mostly
+                // useful for bots.
+                res = self.register_stream.recv() => break res?,
+                _ = ping_interval.tick() => self.ping_stream.send(PingMsg::Ping)?,
+            }
+        };
+        Ok((server_info, init, resume_token))
+    }
+}
+
+impl Client {
+    pub async fn new(
+        addr: ConnectionArgs,
+        runtime: Arc<Runtime>,
+        // TODO: refactor to avoid needing to use this out parameter
+        mismatched_server_info: &mut Option<ServerInfo>,
+        username: &str,
+        password: &str,
+        auth_trusted: impl FnMut(&str) -> bool,
+        init_stage_update: &(dyn Fn(ClientInitStage) + Send + Sync),
+    ) -> Result<Self, Error> {
+        let network = Network::new(Pid::new(), &runtime);
+        let (addr, link_simulation, demo_path) = addr.split_options();
+        network.set_link_simulation(link_simulation);
+        let mut demo_server = None;
+        // Connecting again only makes sense to a remote server
+        let reconnect_addr = matches!(
+            addr,
+            ConnectionArgs::Tcp { .. } | ConnectionArgs::Quic { .. }
+        )
+        .then(|| addr.clone());
+
+        init_stage_update(ClientInitStage::ConnectionEstablish);
+        let participant = match addr {
+            ConnectionArgs::Demo(path) => {
+                let server = DemoServer::start(path, &runtime).await?;
+                let participant = network.connect(ConnectAddr::Mpsc(server.mpsc_id())).await?;
+                demo_server = Some(server);
+                participant
+            },
+            addr => Self::connect(&network, addr).await?,
+        };
+        let mut connection = Connection::open(network, participant).await?;
+        let (server_info, init, resume_token) = connection
+            .handshake(
+                Login::Password { username, password },
+                auth_trusted,
+                mismatched_server_info,
+                init_stage_update,
+            )
+            .await?;
+        // The password isn't kept, reconnecting resumes the session instead
+        let credentials = reconnect_addr.map(|addr| Credentials {
+            addr,
+            link_simulation,
+            resume_token,
+        });
+        let demo_recorder =
+            demo_path.and_then(
+                |path| match DemoRecorder::create(&path, &server_info, &init) {
+                    Ok(recorder) => {
+                        info!(?path, "Recording a demo");
+                        Some(recorder)
+                    },
+                    Err(e) => {
+                        warn!(?e, ?path, "Couldn't start recording a demo");
+                        None
+                    },
+                },
+            );
+        let demo_playback = match demo_server {
+            Some(server) => Some(server.playback().await?),
+            None => None,
+        };
         let ServerInit::GameSync {
             entity_package,
             time_of_day,
@@ -385,16 +497,10 @@ impl Client {
             ability_map,
             server_constants,
             repair_recipe_book,
-        } = loop {
-            tokio::select! {
-                // Spawn in a blocking thread (leaving the network thread free).  This is mostly
-                // useful for bots.
-                res = register_stream.recv() => break res?,
-                _ = ping_interval.tick() => ping_stream.send(PingMsg::Ping)?,
-            }
-        };
+        } = init;
 
         init_stage_update(ClientInitStage::StartingClient);
+        let mut ping_interval = tokio::time::interval(Duration::from_secs(1));
         // Spawn in a blocking thread (leaving the network thread free).  This is mostly
         // useful for bots.
         let mut task = tokio::task::spawn_blocking(move || {
@@ -703,10 +809,10 @@ impl Client {
         ) = loop {
             tokio::select! {
                 res = &mut task => break res.expect("Client thread should not panic")?,
-                _ = ping_interval.tick() => ping_stream.send(PingMsg::Ping)?,
+                _ = ping_interval.tick() => connection.ping_stream.send(PingMsg::Ping)?,
             }
         };
-        ping_stream.send(PingMsg::Ping)?;
+        connection.ping_stream.send(PingMsg::Ping)?;
 
         debug!("Initial sync done");
 
@@ -730,6 +836,7 @@ impl Client {
                     (s.id, SiteInfoRich {
                         site: s.clone(),
                         economy: None,
+                        faction: None,
                     })
                 })
                 .collect(),
@@ -745,6 +852,9 @@ impl Client {
             lod_last_requested: None,
 
             force_update_counter: 0,
+            prediction: Prediction::default(),
+            demo_recorder,
+            demo_playback,
 
             max_group_size,
             invite: None,
@@ -752,15 +862,21 @@ impl Client {
             group_members: HashMap::new(),
             pending_invites: HashSet::new(),
             pending_trade: None,
-
-            network: Some(network),
-            participant: Some(participant),
-            general_stream: stream,
-            ping_stream,
-            register_stream,
-            character_screen_stream,
-            in_game_stream,
-            terrain_stream,
+            quests: Vec::new(),
+            quest_offer: None,
+
+            network: Some(connection.network),
+            participant: Some(connection.participant),
+            general_stream: connection.general_stream,
+            ping_stream: connection.ping_stream,
+            register_stream: connection.register_stream,
+            character_screen_stream: connection.character_screen_stream,
+            in_game_stream: connection.in_game_stream,
+            terrain_stream: connection.terrain_stream,
+            physics_stream: connection.physics_stream,
+            credentials,
+            auto_reconnect: false,
+            reconnect: None,
 
             client_timeout,
 
@@ -776,10 +892,12 @@ impl Client {
 
             server_view_distance_limit: None,
             view_distance: None,
+            requested_view_distances: None,
             lod_distance: 4.0,
             loaded_distance: 0.0,
 
             pending_chunks: HashMap::new(),
+            chunk_versions: ChunkVersions::default(),
             target_time_of_day: None,
             dt_adjustment: 1.0,
 
@@ -787,14 +905,56 @@ impl Client {
         })
     }
 
-    /// Request a state transition to `ClientState::Registered`.
+    /// Connects to a server, trying every address a hostname resolves to.
+    async fn connect(network: &Network, addr: ConnectionArgs) -> Result<Participant, Error> {
+        Ok(match addr {
+            ConnectionArgs::Tcp {
+                hostname,
+                prefer_ipv6,
+            } => addr::try_connect(network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?,
+            ConnectionArgs::Quic {
+                hostname,
+                prefer_ipv6,
+            } => {
+                warn!(
+                    "QUIC is enabled. This is experimental and you won't be able to connect to \
+                     TCP servers unless deactivated"
+                );
+                let config = quinn::ClientConfig::with_native_roots();
+                addr::try_connect(network, &hostname, prefer_ipv6, |a| {
+                    ConnectAddr::Quic(a, config.clone(), hostname.clone())
+                })
+                .await?
+            },
+            ConnectionArgs::Mpsc(id) => network.connect(ConnectAddr::Mpsc(id)).await?,
+            ConnectionArgs::Simulated(..)
+            | ConnectionArgs::Recorded(..)
+            | ConnectionArgs::Demo(..) => {
+                unreachable!("split off or handled by the caller")
+            },
+        })
+    }
+
+    /// Request a state transition to `ClientState::Registered`, returns the
+    /// token to resume the session with if the connection drops.
     async fn register(
-        username: &str,
-        password: &str,
+        login: Login<'_>,
         mut auth_trusted: impl FnMut(&str) -> bool,
         server_info: &ServerInfo,
         register_stream: &mut Stream,
-    ) -> Result<(), Error> {
+    ) -> Result<ResumeToken, Error> {
+        let (username, password) = match login {
+            Login::Password { username, password } => (username, password),
+            Login::Resume(resume_token) => {
+                debug!("Resuming session...");
+                register_stream.send(ClientRegister {
+                    token_or_username: String::new(),
+                    resume_token: Some(resume_token),
+                })?;
+                return Self::registered(register_stream).await;
+            },
+        };
+
         // Authentication
         let token_or_username = match &server_info.auth_provider {
             Some(addr) => {
@@ -828,8 +988,16 @@ impl Client {
 
         debug!("Registering client...");
 
-        register_stream.send(ClientRegister { token_or_username })?;
+        register_stream.send(ClientRegister {
+            token_or_username,
+            resume_token: None,
+        })?;
+
+        Self::registered(register_stream).await
+    }
 
+    /// Waits for the answer to `ClientRegister`.
+    async fn registered(register_stream: &mut Stream) -> Result<ResumeToken, Error> {
         match register_stream.recv::<ServerRegisterAnswer>().await? {
             Err(RegisterError::AuthError(err)) => Err(Error::AuthErr(err)),
             Err(RegisterError::InvalidCharacter) => Err(Error::InvalidCharacter),
@@ -837,9 +1005,9 @@ impl Client {
             Err(RegisterError::Kicked(err)) => Err(Error::Kicked(err)),
             Err(RegisterError::Banned(reason)) => Err(Error::Banned(reason)),
             Err(RegisterError::TooManyPlayers) => Err(Error::TooManyPlayers),
-            Ok(()) => {
+            Ok(resume_token) => {
                 debug!("Client registered successfully.");
-                Ok(())
+                Ok(resume_token)
             },
         }
     }
@@ -849,6 +1017,10 @@ impl Client {
         S: Into<ClientMsg>,
     {
         prof_span!("send_msg_err");
+        if self.reconnect.is_some() {
+            // The streams are closed, there's nothing to send to
+            return Ok(());
+        }
         let msg: ClientMsg = msg.into();
         #[cfg(debug_assertions)]
         {
@@ -881,7 +1053,7 @@ impl Client {
                     | ClientGeneral::Character(_, _)
                     | ClientGeneral::Spectate(_) => &mut self.character_screen_stream,
                     //Only in game
-                    ClientGeneral::ControllerInputs(_)
+                    ClientGeneral::ControllerInputs(..)
                     | ClientGeneral::ControlEvent(_)
                     | ClientGeneral::ControlAction(_)
                     | ClientGeneral::SetViewDistance(_)
@@ -894,6 +1066,7 @@ impl Client {
                     | ClientGeneral::RequestPlayerPhysics { .. }
                     | ClientGeneral::RequestLossyTerrainCompression { .. }
                     | ClientGeneral::UpdateMapMarker(_)
+                    | ClientGeneral::QuestResponse(_, _)
                     | ClientGeneral::SpectatePosition(_) => {
                         #[cfg(feature = "tracy")]
                         {
@@ -1052,6 +1225,7 @@ impl Client {
             entity: view_distances.entity.max(1),
         };
         self.view_distance = Some(view_distances.terrain);
+        self.requested_view_distances = Some(view_distances);
         view_distances
     }
 
@@ -1107,6 +1281,17 @@ impl Client {
         }
     }
 
+    pub fn respond_to_quest(&mut self, quest: QuestId, response: QuestResponse) {
+        if self
+            .quest_offer
+            .as_ref()
+            .map_or(false, |offer| offer.id == quest)
+        {
+            self.quest_offer = None;
+        }
+        self.send_msg(ClientGeneral::QuestResponse(quest, response));
+    }
+
     pub fn is_dead(&self) -> bool { self.current::<comp::Health>().map_or(false, |h| h.is_dead) }
 
     pub fn is_gliding(&self) -> bool {
@@ -1422,6 +1607,10 @@ impl Client {
 
     pub fn is_trading(&self) -> bool { self.pending_trade.is_some() }
 
+    pub fn quests(&self) -> &[QuestInfo] { &self.quests }
+
+    pub fn quest_offer(&self) -> Option<&QuestInfo> { self.quest_offer.as_ref() }
+
     pub fn send_invite(&mut self, invitee: Uid, kind: InviteKind) {
         self.send_msg(ClientGeneral::ControlEvent(ControlEvent::InitiateInvite(
             invitee, kind,
@@ -1701,6 +1890,31 @@ impl Client {
         self.state.read_storage::<C>().get(self.entity()).cloned()
     }
 
+    /// Corrects the predicted physics of the player's entity if the server
+    /// came to a different result for the same input.
+    fn reconcile_prediction(&mut self, ack: &msg::PredictionAck) {
+        let entity = self.entity();
+        let ecs = self.state.ecs();
+        let terrain = self.state.terrain();
+        let collider = ecs.read_storage::<comp::Collider>().get(entity).cloned();
+        let scale = ecs.read_storage::<comp::Scale>().get(entity).map_or(1.0, |s| s.0);
+        let overlaps_terrain = |pos| {
+            collider.as_ref().map_or(false, |collider| {
+                phys::overlaps_terrain(&*terrain, collider, scale, pos, prediction::TERRAIN_MARGIN)
+            })
+        };
+        let mut positions = ecs.write_storage::<comp::Pos>();
+        let mut velocities = ecs.write_storage::<comp::Vel>();
+        if let (Some(pos), Some(vel)) = (positions.get_mut(entity), velocities.get_mut(entity))
+            && let Some((corrected_pos, corrected_vel)) =
+                self.prediction.reconcile(ack, overlaps_terrain)
+        {
+            debug!(?ack, ?pos, ?corrected_pos, "Corrected mispredicted movement");
+            *pos = corrected_pos;
+            *vel = corrected_vel;
+        }
+    }
+
     pub fn current_biome(&self) -> BiomeKind {
         match self.current_chunk() {
             Some(chunk) => chunk.meta().biome(),
@@ -1753,6 +1967,7 @@ impl Client {
     pub fn clear_terrain(&mut self) {
         self.state.clear_terrain();
         self.pending_chunks.clear();
+        self.chunk_versions.clear();
     }
 
     pub fn place_block(&mut self, pos: Vec3<i32>, block: Block) {
@@ -1796,11 +2011,27 @@ impl Client {
 
     /// Execute a single client tick, handle input and update the game state by
     /// the given duration.
+    ///
+    /// While reconnecting after the connection dropped, see
+    /// [`Client::set_auto_reconnect`], the game state is left as it is.
     pub fn tick(
         &mut self,
         inputs: ControllerInputs,
         dt: Duration,
         add_foreign_systems: impl Fn(&mut DispatcherBuilder),
+    ) -> Result<Vec<Event>, Error> {
+        if self.reconnect.is_some() {
+            return self.tick_reconnect();
+        }
+        let result = self.tick_connected(inputs, dt, add_foreign_systems);
+        self.reconnect_on_connection_loss(result)
+    }
+
+    fn tick_connected(
+        &mut self,
+        inputs: ControllerInputs,
+        dt: Duration,
+        add_foreign_systems: impl Fn(&mut DispatcherBuilder),
     ) -> Result<Vec<Event>, Error> {
         span!(_guard, "tick", "Client::tick");
         // This tick function is the centre of the Veloren universe. Most client-side
@@ -1847,7 +2078,8 @@ impl Client {
                     "Couldn't access controller component on client entity"
                 );
             }
-            self.send_msg_err(ClientGeneral::ControllerInputs(Box::new(inputs)))?;
+            let input_seq = self.prediction.next_input_seq();
+            self.send_msg_err(ClientGeneral::ControllerInputs(Box::new(inputs), input_seq))?;
         }
 
         // 2) Build up a list of events for this frame, to be passed to the frontend.
@@ -1879,6 +2111,7 @@ impl Client {
         }
 
         // Handle new messages from the server.
+        self.tick_demo_playback(dt, &mut frontend_events)?;
         frontend_events.append(&mut self.handle_new_messages()?);
 
         // 3) Update client local data
@@ -1925,6 +2158,16 @@ impl Client {
         // care about this?) and the server doesn't need to send them)
         let _ = self.state.ecs().fetch::<EventBus<Outcome>>().recv_all();
 
+        // Remember where the inputs of this tick took the player, in case the
+        // server disagrees.
+        if self.presence.is_some()
+            && let (Some(pos), Some(vel)) =
+                (self.current::<comp::Pos>(), self.current::<comp::Vel>())
+        {
+            let dt = self.state.ecs().read_resource::<DeltaTime>().0;
+            self.prediction.record(dt, pos, vel);
+        }
+
         // 5) Terrain
         self.tick_terrain()?;
 
@@ -1947,6 +2190,7 @@ impl Client {
                     ori,
                     force_counter: self.force_update_counter,
                 })?;
+                self.record_demo(|recorder| recorder.record_player(pos, vel, ori));
             }
         }
 
@@ -2007,6 +2251,15 @@ impl Client {
             });
             for key in chunks_to_remove {
                 self.state.remove_chunk(key);
+                self.chunk_versions.remove(key);
+            }
+
+            // Chunks that were kept while out of view might have missed diffs
+            for (key, version) in self.chunk_versions.update_view(chunk_pos, view_distance) {
+                if !self.pending_chunks.contains_key(&key) {
+                    self.send_msg_err(ClientGeneral::TerrainChunkRequest { key, version })?;
+                    self.pending_chunks.insert(key, Instant::now());
+                }
             }
 
             let mut current_tick_send_chunk_requests = 0;
@@ -2063,6 +2316,7 @@ impl Client {
                                 {
                                     self.send_msg_err(ClientGeneral::TerrainChunkRequest {
                                         key: *key,
+                                        version: None,
                                     })?;
                                     current_tick_send_chunk_requests += 1;
                                     self.pending_chunks.insert(*key, Instant::now());
@@ -2291,11 +2545,23 @@ impl Client {
                     .apply_entity_sync_package(entity_sync_package, uid);
             },
             ServerGeneral::CompSync(comp_sync_package, force_counter) => {
+                if force_counter != self.force_update_counter {
+                    // Our position was forced, predictions made before are meaningless
+                    self.prediction.clear();
+                }
                 self.force_update_counter = force_counter;
                 self.state
                     .ecs_mut()
                     .apply_comp_sync_package(comp_sync_package);
             },
+            ServerGeneral::PhysicsSync(comp_sync_package, prediction_ack) => {
+                self.state
+                    .ecs_mut()
+                    .apply_comp_sync_package(comp_sync_package);
+                if let Some(ack) = prediction_ack {
+                    self.reconcile_prediction(&ack);
+                }
+            },
             ServerGeneral::CreateEntity(entity_package) => {
                 self.state.ecs_mut().apply_entity_package(entity_package);
             },
@@ -2500,6 +2766,28 @@ impl Client {
                     frontend_events.push(Event::TradeComplete { result, trade })
                 }
             },
+            ServerGeneral::QuestOffer(quest) => {
+                self.quest_offer = Some(quest);
+                frontend_events.push(Event::QuestOffer);
+            },
+            ServerGeneral::QuestUpdate(quests) => {
+                if let Some(offer) = &self.quest_offer
+                    && quests.iter().any(|quest| quest.id == offer.id)
+                {
+                    self.quest_offer = None;
+                }
+                self.quests = quests;
+            },
+            ServerGeneral::SiteFactions(factions) => {
+                for rich in self.sites.values_mut() {
+                    rich.faction = None;
+                }
+                for faction in factions {
+                    if let Some(rich) = self.sites.get_mut(&faction.id) {
+                        rich.faction = Some(faction);
+                    }
+                }
+            },
             ServerGeneral::SiteEconomy(economy) => {
                 if let Some(rich) = self.sites_mut().get_mut(&economy.id) {
                     rich.economy = Some(economy);
@@ -2522,8 +2810,14 @@ impl Client {
     fn handle_server_terrain_msg(&mut self, msg: ServerGeneral) -> Result<(), Error> {
         prof_span!("handle_server_terrain_mgs");
         match msg {
-            ServerGeneral::TerrainChunkUpdate { key, chunk } => {
-                if let Some(chunk) = chunk.ok().and_then(|c| c.to_chunk()) {
+            ServerGeneral::TerrainChunkUpdate {
+                key,
+                version,
+                chunk,
+            } => {
+                if let Some(chunk) = chunk.ok().and_then(|c| c.to_chunk())
+                    && self.chunk_versions.receive_chunk(key, version)
+                {
                     self.state.insert_chunk(key, Arc::new(chunk));
                 }
                 self.pending_chunks.remove(&key);
@@ -2532,12 +2826,34 @@ impl Client {
                 self.lod_zones.insert(key, zone);
                 self.lod_last_requested = None;
             },
-            ServerGeneral::TerrainBlockUpdates(blocks) => {
-                if let Some(mut blocks) = blocks.decompress() {
-                    blocks.drain().for_each(|(pos, block)| {
-                        self.state.set_block(pos, block);
-                    });
-                }
+            ServerGeneral::TerrainChunkDiff {
+                key,
+                base,
+                version,
+                blocks,
+            } => match self.chunk_versions.diff_action(key, base, version) {
+                DiffAction::Apply => {
+                    if let Some(mut blocks) = blocks.decompress() {
+                        blocks.drain().for_each(|(pos, block)| {
+                            self.state.set_block(pos, block);
+                        });
+                        self.chunk_versions.applied(key, version);
+                    }
+                    self.pending_chunks.remove(&key);
+                },
+                DiffAction::Ignore => {},
+                // We missed changes to the chunk, ask for them
+                DiffAction::Request(current) => {
+                    if self.state.terrain().get_key(key).is_some()
+                        && !self.pending_chunks.contains_key(&key)
+                    {
+                        self.send_msg_err(ClientGeneral::TerrainChunkRequest {
+                            key,
+                            version: current,
+                        })?;
+                        self.pending_chunks.insert(key, Instant::now());
+                    }
+                },
             },
             _ => unreachable!("Not a terrain message"),
         }
@@ -2616,6 +2932,7 @@ impl Client {
 
             while let Some(msg) = self.general_stream.try_recv()? {
                 cnt += 1;
+                self.record_demo(|recorder| recorder.record_msg(DemoStream::General, &msg));
                 self.handle_server_msg(frontend_events, msg)?;
             }
             while let Some(msg) = self.ping_stream.try_recv()? {
@@ -2624,10 +2941,12 @@ impl Client {
             }
             while let Some(msg) = self.character_screen_stream.try_recv()? {
                 cnt += 1;
+                self.record_demo(|recorder| recorder.record_msg(DemoStream::CharacterScreen, &msg));
                 self.handle_server_character_screen_msg(frontend_events, msg)?;
             }
             while let Some(msg) = self.in_game_stream.try_recv()? {
                 cnt += 1;
+                self.record_demo(|recorder| recorder.record_msg(DemoStream::InGame, &msg));
                 #[cfg(feature = "tracy")]
                 {
                     ingame_cnt += 1;
@@ -2636,6 +2955,7 @@ impl Client {
             }
             while let Some(msg) = self.terrain_stream.try_recv()? {
                 cnt += 1;
+                self.record_demo(|recorder| recorder.record_msg(DemoStream::Terrain, &msg));
                 #[cfg(feature = "tracy")]
                 {
                     if let ServerGeneral::TerrainChunkUpdate { chunk, .. } = &msg {
@@ -2644,6 +2964,11 @@ impl Client {
                 }
                 self.handle_server_terrain_msg(msg)?;
             }
+            while let Some(msg) = self.physics_stream.try_recv()? {
+                cnt += 1;
+                self.record_demo(|recorder| recorder.record_msg(DemoStream::Physics, &msg));
+                self.handle_server_msg(frontend_events, msg)?;
+            }
 
             if cnt_start == cnt {
                 #[cfg(feature = "tracy")]
@@ -2656,6 +2981,71 @@ impl Client {
         }
     }
 
+    /// Passes a frame to the demo recorder, if any. Recording stops on errors,
+    /// so they aren't reported every tick.
+    fn record_demo(&mut self, f: impl FnOnce(&mut DemoRecorder) -> Result<(), DemoError>) {
+        if let Some(recorder) = &mut self.demo_recorder
+            && let Err(e) = f(recorder)
+        {
+            warn!(?e, "Failed to record demo, stopping the recording");
+            self.demo_recorder = None;
+        }
+    }
+
+    /// Handle the recorded messages that are due, if a demo is played back.
+    fn tick_demo_playback(
+        &mut self,
+        dt: Duration,
+        frontend_events: &mut Vec<Event>,
+    ) -> Result<(), Error> {
+        let Some(playback) = &mut self.demo_playback else {
+            return Ok(());
+        };
+        let player_uid = playback.player_uid();
+        playback.respond()?;
+        let DemoTick {
+            rewound,
+            player,
+            frames,
+        } = playback.advance(dt.as_secs_f64())?;
+
+        if rewound {
+            // Keep the camera where it is
+            let pos = self.current::<comp::Pos>();
+            self.clean_state();
+            if let Some(pos) = pos {
+                let entity = self.entity();
+                self.state.write_component_ignore_entity_dead(entity, pos);
+            }
+        }
+        if let Some(player) = player {
+            self.state.ecs_mut().apply_entity_package(player);
+        }
+
+        for frame in frames {
+            match frame {
+                DemoFrame::Msg { stream, msg, .. } => match stream {
+                    DemoStream::General | DemoStream::Physics => {
+                        self.handle_server_msg(frontend_events, msg)?
+                    },
+                    DemoStream::CharacterScreen => {
+                        self.handle_server_character_screen_msg(frontend_events, msg)?
+                    },
+                    DemoStream::InGame => self.handle_server_in_game_msg(frontend_events, msg)?,
+                    DemoStream::Terrain => self.handle_server_terrain_msg(msg)?,
+                },
+                DemoFrame::Player { pos, vel, ori, .. } => {
+                    if let Some(entity) = self.state.ecs().entity_from_uid(player_uid) {
+                        self.state.write_component_ignore_entity_dead(entity, pos);
+                        self.state.write_component_ignore_entity_dead(entity, vel);
+                        self.state.write_component_ignore_entity_dead(entity, ori);
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
     /// Handle new server messages.
     fn handle_new_messages(&mut self) -> Result<Vec<Event>, Error> {
         prof_span!("handle_new_messages");
@@ -2716,6 +3106,15 @@ impl Client {
 
     pub fn presence(&self) -> Option<PresenceKind> { self.presence }
 
+    /// Whether everything the server sends is recorded into a demo.
+    pub fn is_recording_demo(&self) -> bool { self.demo_recorder.is_some() }
+
+    /// The demo that is played back instead of being connected to a server,
+    /// if any.
+    pub fn demo_playback(&self) -> Option<&DemoPlayback> { self.demo_playback.as_ref() }
+
+    pub fn demo_playback_mut(&mut self) -> Option<&mut DemoPlayback> { self.demo_playback.as_mut() }
+
     pub fn registered(&self) -> bool { self.registered }
 
     pub fn get_tick(&self) -> u64 { self.tick }
@@ -2770,10 +3169,142 @@ impl Client {
             .map_or(false, |info| info.is_moderator)
     }
 
+    /// Whether to reconnect on its own when the connection to the server drops.
+    /// The server holds the character in the world for a while, so the
+    /// client can pick up where it left off. Not possible for singleplayer and
+    /// demos.
+    pub fn set_auto_reconnect(&mut self, auto_reconnect: bool) {
+        self.auto_reconnect = auto_reconnect;
+    }
+
+    pub fn is_reconnecting(&self) -> bool { self.reconnect.is_some() }
+
+    /// Starts reconnecting if the tick failed because the connection dropped.
+    fn reconnect_on_connection_loss<T: Default>(
+        &mut self,
+        result: Result<T, Error>,
+    ) -> Result<T, Error> {
+        match result {
+            Err(e)
+                if self.auto_reconnect
+                    && self.credentials.is_some()
+                    && reconnect::is_connection_loss(&e) =>
+            {
+                warn!(?e, "Lost the connection to the server, reconnecting");
+                self.reconnect = Some(Reconnect::new(self.presence));
+                Ok(T::default())
+            },
+            result => result,
+        }
+    }
+
+    fn tick_reconnect(&mut self) -> Result<Vec<Event>, Error> {
+        let (Some(reconnect), Some(credentials)) = (&mut self.reconnect, &self.credentials) else {
+            return Ok(Vec::new());
+        };
+        match reconnect.poll(&self.runtime, credentials) {
+            ReconnectStatus::Waiting => Ok(Vec::new()),
+            ReconnectStatus::Attempting(attempt) => Ok(vec![Event::Reconnecting { attempt }]),
+            ReconnectStatus::Failed(e) => {
+                self.reconnect = None;
+                Err(e)
+            },
+            ReconnectStatus::Connected(reconnected) => {
+                let presence = self.reconnect.take().and_then(|r| r.presence);
+                self.resume(*reconnected, presence);
+                Ok(vec![Event::Reconnected])
+            },
+        }
+    }
+
+    /// Continues on a new connection, with the character that was played
+    /// before, if any.
+    fn resume(&mut self, reconnected: Reconnected, presence: Option<PresenceKind>) {
+        let Reconnected {
+            connection,
+            server_info,
+            init,
+            resume_token,
+        } = reconnected;
+        let ServerInit::GameSync {
+            entity_package,
+            time_of_day,
+            max_group_size,
+            client_timeout,
+            material_stats,
+            ability_map,
+            server_constants,
+            ..
+        } = init;
+        info!("Reconnected to the server");
+
+        self.network = Some(connection.network);
+        self.participant = Some(connection.participant);
+        self.general_stream = connection.general_stream;
+        self.ping_stream = connection.ping_stream;
+        self.register_stream = connection.register_stream;
+        self.character_screen_stream = connection.character_screen_stream;
+        self.in_game_stream = connection.in_game_stream;
+        self.terrain_stream = connection.terrain_stream;
+        self.physics_stream = connection.physics_stream;
+        self.server_info = server_info;
+        if let Some(credentials) = &mut self.credentials {
+            // The old token was used up
+            credentials.resume_token = resume_token;
+        }
+        self.max_group_size = max_group_size;
+        self.client_timeout = client_timeout;
+        self.connected_server_constants = server_constants;
+        if self.demo_recorder.take().is_some() {
+            info!("Stopped recording the demo, it can't be continued on a new connection");
+        }
+
+        // Start over with what the server sends, the terrain stays
+        self.pending_trade = None;
+        self.quest_offer = None;
+        self.invite = None;
+        self.pending_invites.clear();
+        self.pending_chunks.clear();
+        // The server might have been restarted, chunks are requested again as they
+        // change
+        self.chunk_versions.clear();
+        self.force_update_counter = 0;
+        self.prediction.clear();
+        self.state.ecs_mut().delete_all();
+        self.state.ecs_mut().maintain();
+        self.state.ecs_mut().insert(IdMaps::default());
+        let entity = self.state.ecs_mut().apply_entity_package(entity_package);
+        *self.state.ecs_mut().write_resource() = time_of_day;
+        *self.state.ecs_mut().write_resource() = PlayerEntity(Some(entity));
+        self.state.ecs_mut().insert(material_stats);
+        self.state.ecs_mut().insert(ability_map);
+
+        let now = self.state.get_program_time();
+        self.last_server_ping = now;
+        self.last_server_pong = now;
+        self.ping_deltas.clear();
+        self.registered = true;
+        self.presence = None;
+
+        let view_distances = self.requested_view_distances;
+        match (presence, view_distances) {
+            (
+                Some(PresenceKind::Character(id) | PresenceKind::LoadingCharacter(id)),
+                Some(view_distances),
+            ) => self.request_character(id, view_distances),
+            (Some(PresenceKind::Spectator), Some(view_distances)) => {
+                self.request_spectate(view_distances)
+            },
+            _ => {},
+        }
+    }
+
     /// Clean client ECS state
     fn clean_state(&mut self) {
         // Clear pending trade
         self.pending_trade = None;
+        self.quests.clear();
+        self.quest_offer = None;
 
         let client_uid = self.uid().expect("Client doesn't have a Uid!!!");
 
@@ -2881,8 +3412,17 @@ impl Client {
     /// running the client. This method is for use in testing a server with
     /// many clients connected.
     #[cfg(feature = "tick_network")]
-    #[allow(clippy::needless_collect)] // False positive
     pub fn tick_network(&mut self, dt: Duration) -> Result<(), Error> {
+        if self.reconnect.is_some() {
+            return self.tick_reconnect().map(drop);
+        }
+        let result = self.tick_network_connected(dt);
+        self.reconnect_on_connection_loss(result).map(drop)
+    }
+
+    #[cfg(feature = "tick_network")]
+    #[allow(clippy::needless_collect)] // False positive
+    fn tick_network_connected(&mut self, dt: Duration) -> Result<(), Error> {
         span!(_guard, "tick_network", "Client::tick_network");
         // Advance state time manually since we aren't calling `State::tick`
         self.state
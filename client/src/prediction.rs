@@ -0,0 +1,241 @@
+//! Prediction of the player's own movement under server-authoritative physics.
+//!
+//! The client simulates its own entity with the same systems as the server, so
+//! inputs take effect immediately instead of after a round trip. Every
+//! `ControllerInputs` message is numbered and the server answers with the
+//! physics state that resulted from the newest input it simulated (see
+//! [`PredictionAck`]). When that state diverges from what the client predicted
+//! for the same input, the client rewinds to the server's state and replays
+//! the inputs the server hasn't seen yet from there.
+//!
+//! The ECS systems run for all entities at once, so replaying an input doesn't
+//! run them again. Instead, the velocity they derived from the input is
+//! integrated over the tick it was predicted for, starting from the server's
+//! state and stopping at terrain the corrected path runs into.
+
+use common::comp::{Pos, Vel};
+use common_net::msg::PredictionAck;
+use std::collections::VecDeque;
+use vek::Vec3;
+
+/// Maximum number of unacknowledged ticks to remember, older ones are
+/// forgotten (about 4 seconds at 60 ticks per second).
+const MAX_HISTORY: usize = 256;
+/// Divergence in position (in blocks) that is tolerated without correction,
+/// the client and server tick at different rates so they never agree exactly.
+const POS_TOLERANCE: f32 = 0.25;
+/// Divergence in velocity (in blocks per second) that is tolerated without
+/// correction.
+const VEL_TOLERANCE: f32 = 1.0;
+/// Divergence in position (in blocks) beyond which replaying the inputs isn't
+/// meaningful (e.g. the player got teleported) and the server's state is taken
+/// as is.
+const SNAP_DISTANCE: f32 = 16.0;
+/// Overlap with terrain (in blocks) that a replayed position may have, so
+/// standing on the ground doesn't count as running into it.
+pub(crate) const TERRAIN_MARGIN: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug)]
+struct PredictedTick {
+    input_seq: u64,
+    dt: f32,
+    pos: Pos,
+    vel: Vel,
+}
+
+#[derive(Default)]
+pub(crate) struct Prediction {
+    input_seq: u64,
+    history: VecDeque<PredictedTick>,
+}
+
+impl Prediction {
+    /// Number for the next `ControllerInputs` sent to the server.
+    pub(crate) fn next_input_seq(&mut self) -> u64 {
+        self.input_seq += 1;
+        self.input_seq
+    }
+
+    /// Remember the physics state that resulted from the last input, simulated
+    /// over `dt` seconds.
+    pub(crate) fn record(&mut self, dt: f32, pos: Pos, vel: Vel) {
+        if self.history.len() >= MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(PredictedTick {
+            input_seq: self.input_seq,
+            dt,
+            pos,
+            vel,
+        });
+    }
+
+    /// Forget all predictions, e.g. when the server forced a new position.
+    pub(crate) fn clear(&mut self) { self.history.clear(); }
+
+    /// Compares the server's state with what was predicted for the same input.
+    ///
+    /// Returns the corrected current state if the prediction was wrong,
+    /// `None` if it was right or there is nothing to compare with.
+    /// `overlaps_terrain` tells whether the player's entity at a position
+    /// would be inside terrain.
+    pub(crate) fn reconcile(
+        &mut self,
+        ack: &PredictionAck,
+        overlaps_terrain: impl Fn(Vec3<f32>) -> bool,
+    ) -> Option<(Pos, Vel)> {
+        while self
+            .history
+            .front()
+            .map_or(false, |tick| tick.input_seq < ack.input_seq)
+        {
+            self.history.pop_front();
+        }
+        let predicted = self
+            .history
+            .front()
+            .filter(|tick| tick.input_seq == ack.input_seq)
+            .copied()?;
+        self.history.pop_front();
+
+        let pos_error = ack.pos.0 - predicted.pos.0;
+        let vel_error = ack.vel.0 - predicted.vel.0;
+        if pos_error.magnitude_squared() > SNAP_DISTANCE.powi(2) {
+            self.history.clear();
+            return Some((ack.pos, ack.vel));
+        }
+        if pos_error.magnitude_squared() <= POS_TOLERANCE.powi(2)
+            && vel_error.magnitude_squared() <= VEL_TOLERANCE.powi(2)
+        {
+            return None;
+        }
+
+        // Rewind to the server's state and replay the unacknowledged inputs. The
+        // replayed states replace the predictions, so they are compared against
+        // the corrected path once their acknowledgements arrive.
+        let (mut pos, mut vel) = (ack.pos, ack.vel);
+        for tick in &mut self.history {
+            (pos, vel) = replay(tick, pos, &overlaps_terrain);
+            tick.pos = pos;
+            tick.vel = vel;
+        }
+        Some((pos, vel))
+    }
+}
+
+/// Moves from `pos` with the velocity predicted for `tick`. Movement along an
+/// axis that would end up inside terrain is dropped, z first so that landing
+/// on the ground still lets the player slide along it.
+fn replay(
+    tick: &PredictedTick,
+    pos: Pos,
+    overlaps_terrain: impl Fn(Vec3<f32>) -> bool,
+) -> (Pos, Vel) {
+    let mut vel = tick.vel.0;
+    let target = pos.0 + vel * tick.dt;
+    if !overlaps_terrain(target) {
+        return (Pos(target), Vel(vel));
+    }
+
+    let mut pos = pos.0;
+    for axis in [2, 0, 1] {
+        let mut next = pos;
+        next[axis] += vel[axis] * tick.dt;
+        if overlaps_terrain(next) {
+            vel[axis] = 0.0;
+        } else {
+            pos = next;
+        }
+    }
+    (Pos(pos), Vel(vel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ack(input_seq: u64, x: f32) -> PredictionAck {
+        PredictionAck {
+            input_seq,
+            pos: Pos(Vec3::new(x, 0.0, 0.0)),
+            vel: Vel(Vec3::new(1.0, 0.0, 0.0)),
+        }
+    }
+
+    fn open(_: Vec3<f32>) -> bool { false }
+
+    /// Predicts moving 1 block along x per input, starting at 0.
+    fn predict(prediction: &mut Prediction, inputs: u64) {
+        let vel = Vel(Vec3::new(1.0, 0.0, 0.0));
+        let mut pos = Pos(Vec3::zero());
+        for _ in 0..inputs {
+            prediction.next_input_seq();
+            pos.0.x += 1.0;
+            prediction.record(1.0, pos, vel);
+        }
+    }
+
+    #[test]
+    fn correct_prediction_is_kept() {
+        let mut prediction = Prediction::default();
+        predict(&mut prediction, 5);
+        assert!(prediction.reconcile(&ack(2, 2.1), open).is_none());
+        assert!(prediction.reconcile(&ack(4, 4.0), open).is_none());
+    }
+
+    #[test]
+    fn divergence_replays_unacknowledged_inputs() {
+        let mut prediction = Prediction::default();
+        predict(&mut prediction, 5);
+        // The server got stopped by something after the 2nd input
+        let (pos, vel) = prediction
+            .reconcile(&ack(3, 2.0), open)
+            .expect("prediction diverged");
+        assert_eq!(pos.0.x, 4.0);
+        assert_eq!(vel.0.x, 1.0);
+        // Later inputs are compared against the replayed path
+        assert!(prediction.reconcile(&ack(5, 4.0), open).is_none());
+    }
+
+    #[test]
+    fn replay_stops_at_terrain() {
+        let mut prediction = Prediction::default();
+        predict(&mut prediction, 5);
+        let wall = |pos: Vec3<f32>| pos.x >= 4.0;
+        let (pos, vel) = prediction
+            .reconcile(&ack(2, 1.5), wall)
+            .expect("prediction diverged");
+        assert_eq!(pos.0.x, 3.5);
+        assert_eq!(vel.0.x, 0.0);
+        assert!(prediction.reconcile(&ack(5, 3.5), open).is_none());
+    }
+
+    #[test]
+    fn acknowledged_last_input_takes_server_state() {
+        let mut prediction = Prediction::default();
+        predict(&mut prediction, 5);
+        let (pos, _) = prediction
+            .reconcile(&ack(5, 3.0), open)
+            .expect("prediction diverged");
+        assert_eq!(pos.0.x, 3.0);
+    }
+
+    #[test]
+    fn large_divergence_snaps_to_server() {
+        let mut prediction = Prediction::default();
+        predict(&mut prediction, 5);
+        let (pos, _) = prediction
+            .reconcile(&ack(3, 100.0), open)
+            .expect("prediction diverged");
+        assert_eq!(pos.0.x, 100.0);
+        assert!(prediction.reconcile(&ack(5, 4.0), open).is_none());
+    }
+
+    #[test]
+    fn unknown_input_is_ignored() {
+        let mut prediction = Prediction::default();
+        predict(&mut prediction, 5);
+        assert!(prediction.reconcile(&ack(0, 50.0), open).is_none());
+        assert!(prediction.reconcile(&ack(9, 50.0), open).is_none());
+    }
+}
@@ -0,0 +1,165 @@
+//! Reconnecting to the server after the connection dropped.
+//!
+//! The server keeps the character of a player that lost its connection in
+//! the world for a while, logging in again in that time hands it back. The
+//! client keeps its state in the meantime and picks up where it left off. It
+//! logs in again with the [`ResumeToken`] the server issued, so the password
+//! doesn't have to be kept around.
+
+use crate::{
+    addr::{ConnectionArgs, LinkSimulation},
+    Client, Connection, Error, Login,
+};
+use common::comp::PresenceKind;
+use common_net::msg::{ResumeToken, ServerInfo, ServerInit};
+use network::{Network, Pid};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{runtime::Runtime, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+const MAX_ATTEMPTS: u32 = 8;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// What is needed to log in again.
+#[derive(Clone)]
+pub(crate) struct Credentials {
+    pub addr: ConnectionArgs,
+    pub link_simulation: Option<LinkSimulation>,
+    /// Issued by the server when registering, each one can only be used once.
+    pub resume_token: ResumeToken,
+}
+
+pub(crate) struct Reconnected {
+    pub connection: Connection,
+    pub server_info: ServerInfo,
+    pub init: ServerInit,
+    pub resume_token: ResumeToken,
+}
+
+pub(crate) enum ReconnectStatus {
+    Waiting,
+    Attempting(u32),
+    Connected(Box<Reconnected>),
+    Failed(Error),
+}
+
+pub(crate) struct Reconnect {
+    /// What to join as again once connected
+    pub presence: Option<PresenceKind>,
+    attempt: u32,
+    next_attempt: Instant,
+    pending: Option<JoinHandle<Result<Reconnected, Error>>>,
+}
+
+impl Reconnect {
+    pub fn new(presence: Option<PresenceKind>) -> Self {
+        Self {
+            presence,
+            attempt: 0,
+            next_attempt: Instant::now(),
+            pending: None,
+        }
+    }
+
+    /// Starts the next attempt when it is time to, and checks on the running
+    /// one.
+    pub fn poll(&mut self, runtime: &Arc<Runtime>, credentials: &Credentials) -> ReconnectStatus {
+        if let Some(pending) = &mut self.pending {
+            if !pending.is_finished() {
+                return ReconnectStatus::Waiting;
+            }
+            let result = runtime
+                .block_on(pending)
+                .expect("Reconnect task should not panic");
+            self.pending = None;
+            match result {
+                Ok(reconnected) => return ReconnectStatus::Connected(Box::new(reconnected)),
+                Err(e) if !is_connection_loss(&e) || self.attempt >= MAX_ATTEMPTS => {
+                    warn!(?e, "Giving up reconnecting");
+                    return ReconnectStatus::Failed(e);
+                },
+                Err(e) => {
+                    let backoff = backoff(self.attempt);
+                    debug!(?e, ?backoff, "Reconnecting failed, trying again later");
+                    self.next_attempt = Instant::now() + backoff;
+                },
+            }
+        }
+
+        if Instant::now() < self.next_attempt {
+            return ReconnectStatus::Waiting;
+        }
+        self.attempt += 1;
+        info!(attempt = self.attempt, "Reconnecting");
+        self.pending = Some(runtime.spawn(attempt(Arc::clone(runtime), credentials.clone())));
+        ReconnectStatus::Attempting(self.attempt)
+    }
+}
+
+impl Drop for Reconnect {
+    fn drop(&mut self) {
+        if let Some(pending) = &self.pending {
+            pending.abort();
+        }
+    }
+}
+
+/// Whether the error means that the connection to the server dropped, as
+/// opposed to the server refusing us.
+pub(crate) fn is_connection_loss(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::NetworkErr(_)
+            | Error::ParticipantErr(_)
+            | Error::StreamErr(_)
+            | Error::ServerTimeout
+            | Error::HostnameLookupFailed(_)
+    )
+}
+
+/// How long to wait after the given failed attempt, doubling each time.
+fn backoff(attempt: u32) -> Duration {
+    MIN_BACKOFF
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+async fn attempt(runtime: Arc<Runtime>, credentials: Credentials) -> Result<Reconnected, Error> {
+    let network = Network::new(Pid::new(), &runtime);
+    network.set_link_simulation(credentials.link_simulation);
+    let participant = Client::connect(&network, credentials.addr).await?;
+    let mut connection = Connection::open(network, participant).await?;
+    let (server_info, init, resume_token) = connection
+        .handshake(
+            Login::Resume(credentials.resume_token),
+            // Resuming doesn't involve the auth server
+            |_| false,
+            &mut None,
+            &|_| {},
+        )
+        .await?;
+    Ok(Reconnected {
+        connection,
+        server_info,
+        init,
+        resume_token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(6), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+}
@@ -48,6 +48,10 @@ pub fn start_hot_reloading() { ASSETS.enhance_hot_reloading(); }
 #[cfg(feature = "plugins")]
 pub fn register_tar(path: PathBuf) -> std::io::Result<()> { ASSETS.register_tar(path) }
 
+// unregister a plugin, e.g. before reloading it
+#[cfg(feature = "plugins")]
+pub fn unregister_tar(path: &std::path::Path) { ASSETS.unregister_tar(path) }
+
 pub type AssetHandle<T> = assets_manager::Handle<'static, T>;
 pub type AssetGuard<T> = assets_manager::AssetGuard<'static, T>;
 pub type AssetDirHandle<T> = assets_manager::DirHandle<'static, T>;
@@ -1,4 +1,7 @@
-use std::{path::PathBuf, sync::RwLock};
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
 
 use crate::Concatenate;
 
@@ -190,6 +193,19 @@ impl CombinedCache {
             .push(PluginEntry { path, cache });
         Ok(())
     }
+
+    /// Remove a tar archive previously added with
+    /// [`register_tar`](Self::register_tar).
+    ///
+    /// Assets which were already loaded from it stay in the cache.
+    pub fn unregister_tar(&self, path: &Path) {
+        self.0
+            .raw_source()
+            .plugin_list
+            .write()
+            .unwrap()
+            .retain(|plugin| plugin.path != path);
+    }
 }
 
 // Delegate all cache operations directly to the contained cache object
@@ -1,5 +1,12 @@
-use super::{world_msg::SiteId, PingMsg};
-use common::{character::CharacterId, comp, comp::Skill, terrain::block::Block, ViewDistances};
+use super::{world_msg::SiteId, PingMsg, ResumeToken};
+use common::{
+    character::CharacterId,
+    comp,
+    comp::Skill,
+    rtsim::{QuestId, QuestResponse},
+    terrain::block::Block,
+    ViewDistances,
+};
 use serde::{Deserialize, Serialize};
 use vek::*;
 
@@ -36,6 +43,9 @@ pub enum ClientType {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClientRegister {
     pub token_or_username: String,
+    /// Resumes the session the connection of the client dropped out of
+    /// instead of logging in with `token_or_username`.
+    pub resume_token: Option<ResumeToken>,
 }
 
 /// Messages sent from the client to the server
@@ -59,7 +69,11 @@ pub enum ClientGeneral {
     Character(CharacterId, ViewDistances),
     Spectate(ViewDistances),
     //Only in game
-    ControllerInputs(Box<comp::ControllerInputs>),
+    /// The inputs of the current client tick, numbered so the server can
+    /// acknowledge them in [`PredictionAck`].
+    ///
+    /// [`PredictionAck`]: super::PredictionAck
+    ControllerInputs(Box<comp::ControllerInputs>, u64),
     ControlEvent(comp::ControlEvent),
     ControlAction(comp::ControlAction),
     SetViewDistance(ViewDistances),
@@ -75,11 +89,15 @@ pub enum ClientGeneral {
     UnlockSkill(Skill),
     RequestSiteInfo(SiteId),
     UpdateMapMarker(comp::MapMarkerChange),
+    QuestResponse(QuestId, QuestResponse),
 
     SpectatePosition(Vec3<f32>),
     //Only in Game, via terrain stream
     TerrainChunkRequest {
         key: Vec2<i32>,
+        /// The version of the chunk the client already has, if any, so that
+        /// only the changes since then need to be sent.
+        version: Option<u64>,
     },
     LodZoneRequest {
         key: Vec2<i32>,
@@ -119,7 +137,7 @@ impl ClientMsg {
                             c_type == ClientType::Game && presence.is_none()
                         },
                         //Only in game
-                        ClientGeneral::ControllerInputs(_)
+                        ClientGeneral::ControllerInputs(..)
                         | ClientGeneral::ControlEvent(_)
                         | ClientGeneral::ControlAction(_)
                         | ClientGeneral::SetViewDistance(_)
@@ -134,6 +152,7 @@ impl ClientMsg {
                         | ClientGeneral::RequestPlayerPhysics { .. }
                         | ClientGeneral::RequestLossyTerrainCompression { .. }
                         | ClientGeneral::UpdateMapMarker(_)
+                        | ClientGeneral::QuestResponse(_, _)
                         | ClientGeneral::SpectatePosition(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
@@ -1,6 +1,7 @@
 use super::{
-    world_msg::EconomyInfo, ClientType, CompressedData, EcsCompPacket, PingMsg, QuadPngEncoding,
-    TriPngEncoding, WidePacking, WireChonk,
+    world_msg::{EconomyInfo, SiteFactionInfo},
+    ClientType, CompressedData, EcsCompPacket, PingMsg, QuadPngEncoding, TriPngEncoding,
+    WidePacking, WireChonk,
 };
 use crate::sync;
 use common::{
@@ -12,6 +13,7 @@ use common::{
     outcome::Outcome,
     recipe::{ComponentRecipeBook, RecipeBook, RepairRecipeBook},
     resources::{Time, TimeOfDay, TimeScale},
+    rtsim::QuestInfo,
     shared_server_config::ServerConstants,
     terrain::{Block, TerrainChunk, TerrainChunkMeta, TerrainChunkSize},
     trade::{PendingTrade, SitePrices, TradeId, TradeResult},
@@ -21,7 +23,7 @@ use common::{
 };
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{fmt, time::Duration};
 use tracing::warn;
 use vek::*;
 
@@ -72,7 +74,20 @@ pub enum ServerInit {
     },
 }
 
-pub type ServerRegisterAnswer = Result<(), RegisterError>;
+/// Answers `ClientRegister` with the token the client can resume its session
+/// with, if its connection drops.
+pub type ServerRegisterAnswer = Result<ResumeToken, RegisterError>;
+
+/// Lets a client whose connection dropped log in again as the same player
+/// without authenticating again. It is issued when registering and can only be
+/// used once, resuming issues a new one.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResumeToken(pub u128);
+
+impl fmt::Debug for ResumeToken {
+    // Tokens must not end up in logs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("ResumeToken(..)") }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SerializedTerrainChunk {
@@ -181,13 +196,23 @@ pub enum ServerGeneral {
     // Ingame related AND terrain stream
     TerrainChunkUpdate {
         key: Vec2<i32>,
+        /// The version of the chunk on the server, later diffs of the chunk
+        /// build on it.
+        version: u64,
         chunk: Result<SerializedTerrainChunk, ()>,
     },
     LodZoneUpdate {
         key: Vec2<i32>,
         zone: lod::Zone,
     },
-    TerrainBlockUpdates(CompressedData<HashMap<Vec3<i32>, Block>>),
+    /// The blocks that changed in a chunk between version `base` and
+    /// `version`, only applicable to a chunk that is at version `base`.
+    TerrainChunkDiff {
+        key: Vec2<i32>,
+        base: u64,
+        version: u64,
+        blocks: CompressedData<HashMap<Vec3<i32>, Block>>,
+    },
     // Always possible
     PlayerListUpdate(PlayerListUpdate),
     /// A message to go into the client chat box. The client is responsible for
@@ -198,6 +223,13 @@ pub enum ServerGeneral {
     TimeOfDay(TimeOfDay, Calendar, Time, TimeScale),
     EntitySync(sync::EntitySyncPackage),
     CompSync(sync::CompSyncPackage<EcsCompPacket>, u64),
+    /// Physics components (`Pos`, `Vel` and `Ori`) of entities around the
+    /// client. These are sent over a lossy stream where only the newest
+    /// package is of interest, so any of them may never arrive.
+    ///
+    /// Clients with server-authoritative physics also get the physics state
+    /// of their own entity, to correct their prediction.
+    PhysicsSync(sync::CompSyncPackage<EcsCompPacket>, Option<PredictionAck>),
     CreateEntity(sync::EntityPackage<EcsCompPacket>),
     DeleteEntity(Uid),
     Disconnect(DisconnectReason),
@@ -207,6 +239,12 @@ pub enum ServerGeneral {
     FinishedTrade(TradeResult),
     /// Economic information about sites
     SiteEconomy(EconomyInfo),
+    /// An NPC offers the character a quest
+    QuestOffer(QuestInfo),
+    /// All the quests the character has taken on
+    QuestUpdate(Vec<QuestInfo>),
+    /// Which faction holds each site
+    SiteFactions(Vec<SiteFactionInfo>),
     MapMarker(comp::MapMarkerUpdate),
     WeatherUpdate(WeatherGrid),
     /// Suggest the client to spectate a position. Called after client has
@@ -247,6 +285,15 @@ pub struct PlayerInfo {
     pub uuid: Uuid,
 }
 
+/// The physics state of the client's own entity after the server simulated
+/// all inputs up to `input_seq`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PredictionAck {
+    pub input_seq: u64,
+    pub pos: comp::Pos,
+    pub vel: comp::Vel,
+}
+
 /// used for localisation, filled by client and used by i18n code
 pub struct ChatTypeContext {
     pub you: Uid,
@@ -325,13 +372,16 @@ impl ServerMsg {
                         | ServerGeneral::GroupInventoryUpdate(_, _, _)
                         | ServerGeneral::TerrainChunkUpdate { .. }
                         | ServerGeneral::LodZoneUpdate { .. }
-                        | ServerGeneral::TerrainBlockUpdates(_)
+                        | ServerGeneral::TerrainChunkDiff { .. }
                         | ServerGeneral::SetViewDistance(_)
                         | ServerGeneral::Outcomes(_)
                         | ServerGeneral::Knockback(_)
                         | ServerGeneral::UpdatePendingTrade(_, _, _)
                         | ServerGeneral::FinishedTrade(_)
                         | ServerGeneral::SiteEconomy(_)
+                        | ServerGeneral::QuestOffer(_)
+                        | ServerGeneral::QuestUpdate(_)
+                        | ServerGeneral::SiteFactions(_)
                         | ServerGeneral::MapMarker(_)
                         | ServerGeneral::WeatherUpdate(_)
                         | ServerGeneral::SpectatePosition(_) => {
@@ -345,6 +395,7 @@ impl ServerMsg {
                         | ServerGeneral::TimeOfDay(_, _, _, _)
                         | ServerGeneral::EntitySync(_)
                         | ServerGeneral::CompSync(_, _)
+                        | ServerGeneral::PhysicsSync(..)
                         | ServerGeneral::CreateEntity(_)
                         | ServerGeneral::DeleteEntity(_)
                         | ServerGeneral::Disconnect(_)
@@ -166,6 +166,14 @@ pub struct EconomyInfo {
     pub resources: HashMap<Good, f32>,
 }
 
+/// Which faction holds a site, and who that faction is at war with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteFactionInfo {
+    pub id: SiteId,
+    pub faction: String,
+    pub at_war_with: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoiInfo {
     pub kind: PoiKind,
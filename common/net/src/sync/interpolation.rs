@@ -60,6 +60,9 @@ const PHYSICS_VS_EXTRAPOLATION_FACTOR: f32 = 0.1;
 const POSITION_INTERP_SANITY: Option<f32> = None;
 const VELOCITY_INTERP_SANITY: Option<f32> = None;
 const ENABLE_POSITION_HERMITE: bool = false;
+/// Physics updates can get lost, so don't extrapolate further than this (in
+/// seconds) past the last update we received.
+const MAX_EXTRAPOLATION: f64 = 0.25;
 
 impl InterpolatableComponent for Pos {
     type InterpData = InterpBuffer<Pos>;
@@ -79,6 +82,7 @@ impl InterpolatableComponent for Pos {
         if (t1 - t0).abs() < f64::EPSILON {
             return self;
         }
+        let t2 = t2.min(t1 + MAX_EXTRAPOLATION);
         if POSITION_INTERP_SANITY
             .map_or(false, |limit| p0.0.distance_squared(p1.0) > limit.powf(2.0))
         {
@@ -133,6 +137,7 @@ impl InterpolatableComponent for Vel {
         if (t1 - t0).abs() < f64::EPSILON {
             return self;
         }
+        let t2 = t2.min(t1 + MAX_EXTRAPOLATION);
         if VELOCITY_INTERP_SANITY
             .map_or(false, |limit| p0.0.distance_squared(p1.0) > limit.powf(2.0))
         {
@@ -170,6 +175,7 @@ impl InterpolatableComponent for Ori {
         if (t1 - t0).abs() < f64::EPSILON {
             return self;
         }
+        let t2 = t2.min(t1 + MAX_EXTRAPOLATION);
         let lerp_factor = 1.0 + ((t2 - t1) / (t1 - t0)) as f32;
         let mut out = Slerp::slerp_unclamped(p0.to_quat(), p1.to_quat(), lerp_factor);
         if out.into_vec4().map(|x| x.is_nan()).reduce_or() {
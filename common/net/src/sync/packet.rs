@@ -69,21 +69,27 @@ pub fn handle_interp_insert<C: InterpolatableComponent + Clone>(
     handle_insert(interp_data, entity, world);
 }
 
-pub fn handle_interp_modify<C: InterpolatableComponent + Debug>(
+/// Useful for implementing CompPacket trait
+///
+/// Interpolated components are synced over a lossy stream, so the insertion
+/// might never have arrived. In that case this acts like
+/// [`handle_interp_insert`].
+pub fn handle_interp_modify<C: InterpolatableComponent + Clone + Debug>(
     comp: C,
     entity: Entity,
     world: &World,
     force_update: bool,
 ) {
-    if let Some(mut interp_data) = world.write_storage::<C::InterpData>().get_mut(entity) {
-        let time = world.read_resource::<Time>().0;
-        comp.update_component(interp_data.access_mut(), time, force_update);
+    let time = world.read_resource::<Time>().0;
+    let updated = world
+        .write_storage::<C::InterpData>()
+        .get_mut(entity)
+        .map(|mut interp_data| comp.update_component(interp_data.access_mut(), time, force_update))
+        .is_some();
+    if updated {
         handle_modify(comp, entity, world);
     } else {
-        error!(
-            ?comp,
-            "Error modifying interpolation data for synced component, it doesn't seem to exist"
-        );
+        handle_interp_insert(comp, entity, world, force_update);
     }
 }
 
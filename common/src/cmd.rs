@@ -273,13 +273,21 @@ pub enum ServerChatCommand {
     AreaAdd,
     AreaList,
     AreaRemove,
+    AuditLog,
+    Backup,
     Ban,
+    BanInfo,
+    BanIp,
+    BanNote,
+    BanTemplate,
     BattleMode,
     BattleModeForce,
     Body,
     Buff,
     Build,
     Campfire,
+    CharacterExport,
+    CharacterImport,
     CreateLocation,
     DebugColumn,
     DebugWays,
@@ -313,9 +321,11 @@ pub enum ServerChatCommand {
     MakeSprite,
     MakeVolume,
     Motd,
+    MovementReport,
     Object,
     PermitBuild,
     Players,
+    Plugin,
     Portal,
     Region,
     ReloadChunks,
@@ -345,6 +355,7 @@ pub enum ServerChatCommand {
     TimeScale,
     Tp,
     Unban,
+    UnbanIp,
     Version,
     Waypoint,
     WeatherZone,
@@ -386,6 +397,20 @@ impl ServerChatCommand {
                 "Change your alias",
                 Some(Moderator),
             ),
+            ServerChatCommand::AuditLog => cmd(
+                vec![
+                    Any("player", Optional),
+                    Command(Optional),
+                    Integer("num", 20, Optional),
+                ],
+                "Search the privileged commands run by or on a player",
+                Some(Moderator),
+            ),
+            ServerChatCommand::Backup => cmd(
+                vec![],
+                "Back up the database, persisted terrain and rtsim data",
+                Some(Admin),
+            ),
             ServerChatCommand::Buff => cmd(
                 vec![
                     Enum("buff", BUFFS.clone(), Required),
@@ -406,6 +431,36 @@ impl ServerChatCommand {
                  true for overwrite to alter an existing ban..",
                 Some(Moderator),
             ),
+            ServerChatCommand::BanInfo => cmd(
+                vec![PlayerName(Required)],
+                "Show the ban history, IP bans and moderator notes for a player",
+                Some(Moderator),
+            ),
+            ServerChatCommand::BanIp => cmd(
+                vec![
+                    PlayerName(Required),
+                    Boolean("overwrite", "true".to_string(), Optional),
+                    Any("ban duration", Optional),
+                    Message(Optional),
+                ],
+                "Like /ban, but also bans the address the player is currently connected from. The \
+                 player must be online.",
+                Some(Moderator),
+            ),
+            ServerChatCommand::BanNote => cmd(
+                vec![PlayerName(Required), Message(Required)],
+                "Attach a moderator note (e.g. about an appeal) to a player's ban entry",
+                Some(Moderator),
+            ),
+            ServerChatCommand::BanTemplate => cmd(
+                vec![
+                    PlayerName(Required),
+                    Any("template", Required),
+                    Boolean("overwrite", "true".to_string(), Optional),
+                ],
+                "Ban a player using a reason and duration preset from the server settings",
+                Some(Moderator),
+            ),
             #[rustfmt::skip]
             ServerChatCommand::BattleMode => cmd(
                 vec![Enum(
@@ -459,6 +514,18 @@ impl ServerChatCommand {
                 Some(Admin),
             ),
             ServerChatCommand::Campfire => cmd(vec![], "Spawns a campfire", Some(Admin)),
+            ServerChatCommand::CharacterExport => cmd(
+                vec![PlayerName(Required), Integer("character id", 0, Optional)],
+                "Export a player's character to an archive that can be imported on another \
+                 server. Lists the player's characters if no id is given",
+                Some(Admin),
+            ),
+            ServerChatCommand::CharacterImport => cmd(
+                vec![PlayerName(Required), Any("archive", Required)],
+                "Import a character archive from the server's character_archives directory as a \
+                 new character of the given player",
+                Some(Admin),
+            ),
             ServerChatCommand::DebugColumn => cmd(
                 vec![Integer("x", 15000, Required), Integer("y", 15000, Required)],
                 "Prints some debug information about a column",
@@ -614,6 +681,11 @@ impl ServerChatCommand {
             ServerChatCommand::Motd => {
                 cmd(vec![Message(Optional)], "View the server description", None)
             },
+            ServerChatCommand::MovementReport => cmd(
+                vec![Any("player", Optional), Integer("num", 20, Optional)],
+                "Show recent movement of players that isn't possible without cheating",
+                Some(Moderator),
+            ),
             ServerChatCommand::Object => cmd(
                 vec![Enum("object", OBJECTS.clone(), Required)],
                 "Spawn an object",
@@ -625,6 +697,22 @@ impl ServerChatCommand {
                 Some(Admin),
             ),
             ServerChatCommand::Players => cmd(vec![], "Lists players currently online", None),
+            ServerChatCommand::Plugin => cmd(
+                vec![
+                    Enum(
+                        "action",
+                        ["list", "load", "unload", "reload"]
+                            .iter()
+                            .map(|s| s.to_string())
+                            .collect(),
+                        Required,
+                    ),
+                    Any("plugin", Optional),
+                ],
+                "Manage the server plugins. Plugins are loaded by the name of their archive, and \
+                 unloaded or reloaded by their plugin name",
+                Some(Admin),
+            ),
             ServerChatCommand::Portal => cmd(
                 vec![
                     Float("x", 0., Required),
@@ -805,6 +893,12 @@ impl ServerChatCommand {
                 "Remove the ban for the given username",
                 Some(Moderator),
             ),
+            ServerChatCommand::UnbanIp => cmd(
+                vec![PlayerName(Required)],
+                "Remove the ban for the given username, along with any bans of addresses they \
+                 were connected from",
+                Some(Moderator),
+            ),
             ServerChatCommand::Version => cmd(vec![], "Prints server version", None),
             ServerChatCommand::Waypoint => cmd(
                 vec![],
@@ -873,6 +967,10 @@ impl ServerChatCommand {
             ServerChatCommand::Airship => "airship",
             ServerChatCommand::Alias => "alias",
             ServerChatCommand::Ban => "ban",
+            ServerChatCommand::BanInfo => "baninfo",
+            ServerChatCommand::BanIp => "ban_ip",
+            ServerChatCommand::BanNote => "ban_note",
+            ServerChatCommand::BanTemplate => "ban_template",
             ServerChatCommand::BattleMode => "battlemode",
             ServerChatCommand::BattleModeForce => "battlemode_force",
             ServerChatCommand::Body => "body",
@@ -881,7 +979,11 @@ impl ServerChatCommand {
             ServerChatCommand::AreaAdd => "area_add",
             ServerChatCommand::AreaList => "area_list",
             ServerChatCommand::AreaRemove => "area_remove",
+            ServerChatCommand::AuditLog => "auditlog",
+            ServerChatCommand::Backup => "backup",
             ServerChatCommand::Campfire => "campfire",
+            ServerChatCommand::CharacterExport => "character_export",
+            ServerChatCommand::CharacterImport => "character_import",
             ServerChatCommand::DebugColumn => "debug_column",
             ServerChatCommand::DebugWays => "debug_ways",
             ServerChatCommand::DisconnectAllPlayers => "disconnect_all_players",
@@ -911,9 +1013,11 @@ impl ServerChatCommand {
             ServerChatCommand::MakeNpc => "make_npc",
             ServerChatCommand::MakeSprite => "make_sprite",
             ServerChatCommand::Motd => "motd",
+            ServerChatCommand::MovementReport => "movement_report",
             ServerChatCommand::Object => "object",
             ServerChatCommand::PermitBuild => "permit_build",
             ServerChatCommand::Players => "players",
+            ServerChatCommand::Plugin => "plugin",
             ServerChatCommand::Portal => "portal",
             ServerChatCommand::Region => "region",
             ServerChatCommand::ReloadChunks => "reload_chunks",
@@ -940,6 +1044,7 @@ impl ServerChatCommand {
             ServerChatCommand::RtsimPurge => "rtsim_purge",
             ServerChatCommand::RtsimChunk => "rtsim_chunk",
             ServerChatCommand::Unban => "unban",
+            ServerChatCommand::UnbanIp => "unban_ip",
             ServerChatCommand::Version => "version",
             ServerChatCommand::Waypoint => "waypoint",
             ServerChatCommand::Wiring => "wiring",
@@ -205,7 +205,7 @@ impl Attack {
         time: Time,
         mut emit: impl FnMut(ServerEvent),
         mut emit_outcome: impl FnMut(Outcome),
-        rng: &mut rand::rngs::ThreadRng,
+        rng: &mut impl Rng,
         damage_instance_offset: u64,
     ) -> bool {
         // TODO: Maybe move this higher and pass it as argument into this function?
@@ -407,7 +407,7 @@ impl Attack {
                                     cause: None,
                                     time,
                                     precise: false,
-                                    instance: rand::random(),
+                                    instance: rng.gen(),
                                 };
                                 if change.amount.abs() > Health::HEALTH_EPSILON {
                                     emit(ServerEvent::HealthChange {
@@ -449,7 +449,7 @@ impl Attack {
                                 cause: None,
                                 time,
                                 precise: false,
-                                instance: rand::random(),
+                                instance: rng.gen(),
                             };
                             if change.amount.abs() > Health::HEALTH_EPSILON {
                                 emit(ServerEvent::HealthChange {
@@ -627,7 +627,7 @@ impl Attack {
                                 cause: None,
                                 time,
                                 precise: false,
-                                instance: rand::random(),
+                                instance: rng.gen(),
                             };
                             if change.amount.abs() > Health::HEALTH_EPSILON {
                                 emit(ServerEvent::HealthChange {
@@ -669,7 +669,7 @@ impl Attack {
                             cause: None,
                             time,
                             precise: false,
-                            instance: rand::random(),
+                            instance: rng.gen(),
                         };
                         if change.amount.abs() > Health::HEALTH_EPSILON {
                             emit(ServerEvent::HealthChange {
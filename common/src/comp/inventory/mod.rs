@@ -575,6 +575,32 @@ impl Inventory {
             .sum()
     }
 
+    /// Removes `amount` of a particular item from the inventory, taking them
+    /// from as many slots as needed. Returns whether there were enough of the
+    /// item, nothing is removed if there weren't.
+    pub fn remove_item_amount(&mut self, item_def: &ItemDef, amount: u32) -> bool {
+        if self.item_count(item_def) < u64::from(amount) {
+            return false;
+        }
+        let mut left = amount;
+        for slot in self.slots_mut() {
+            if left == 0 {
+                break;
+            }
+            if let Some(item) = slot.as_mut().filter(|item| item.is_same_item_def(item_def)) {
+                if item.amount() > left {
+                    item.decrease_amount(left)
+                        .expect("The item has more than the amount to remove");
+                    left = 0;
+                } else {
+                    left -= item.amount();
+                    *slot = None;
+                }
+            }
+        }
+        true
+    }
+
     /// Adds a new item to the first empty slot of the inventory. Returns the
     /// item again in an Err if no free slot was found, otherwise returns a
     /// reference to the item.
@@ -1,10 +1,14 @@
 use super::*;
-use crate::comp::{
-    inventory::{slot::ArmorSlot, test_helpers::get_test_bag},
-    item::ItemDefinitionId,
-    Item,
+use crate::{
+    assets::AssetExt,
+    comp::{
+        inventory::{slot::ArmorSlot, test_helpers::get_test_bag},
+        item::ItemDefinitionId,
+        Item,
+    },
 };
 use lazy_static::lazy_static;
+use std::sync::Arc;
 lazy_static! {
     static ref TEST_ITEMS: Vec<Item> = vec![Item::new_from_asset_expect(
         "common.items.debug.admin_stick"
@@ -517,6 +521,33 @@ fn backpack_crash() {
     );
 }
 
+/// Removing an amount of an item takes it from as many stacks as needed, and
+/// removes nothing at all if there isn't enough of it.
+#[test]
+fn remove_item_amount_across_stacks() {
+    let msm = &MaterialStatManifest::load().read();
+    let ability_map = &AbilityMap::load().read();
+    let coins_def = Arc::<ItemDef>::load_expect_cloned("common.items.utility.coins");
+    let mut coins = Item::new_from_asset_expect("common.items.utility.coins");
+    coins.set_amount(5).unwrap();
+    let mut inv = Inventory {
+        next_sort_order: InventorySortOrder::Name,
+        slots: vec![
+            Some(coins.duplicate(ability_map, msm)),
+            None,
+            Some(coins.duplicate(ability_map, msm)),
+        ],
+        loadout: LoadoutBuilder::empty().build(),
+    };
+
+    assert!(!inv.remove_item_amount(&coins_def, 11));
+    assert_eq!(inv.item_count(&coins_def), 10);
+
+    assert!(inv.remove_item_amount(&coins_def, 7));
+    assert_eq!(inv.item_count(&coins_def), 3);
+    assert_eq!(inv.populated_slots(), 1);
+}
+
 fn fill_inv_slots(inv: &mut Inventory, items: u16) {
     let msm = &MaterialStatManifest::load().read();
     let ability_map = &AbilityMap::load().read();
@@ -12,8 +12,8 @@ use crate::{
     lottery::LootSpec,
     mounting::VolumePos,
     outcome::Outcome,
-    rtsim::RtSimEntity,
-    terrain::SpriteKind,
+    rtsim::{QuestId, QuestResponse, RtSimEntity},
+    terrain::{Block, SpriteKind},
     trade::{TradeAction, TradeId},
     uid::Uid,
     util::Dir,
@@ -193,6 +193,23 @@ pub enum ServerEvent {
     InviteResponse(EcsEntity, InviteResponse),
     InitiateInvite(EcsEntity, Uid, InviteKind),
     ProcessTradeAction(EcsEntity, TradeId, TradeAction),
+    /// An NPC offers a quest to the character of `entity`.
+    OfferQuest {
+        entity: EcsEntity,
+        quest: QuestId,
+    },
+    /// The character of `entity` hands a quest in to `npc`.
+    CompleteQuest {
+        entity: EcsEntity,
+        npc: EcsEntity,
+        quest: QuestId,
+    },
+    /// The character of `entity` responds to a quest.
+    QuestResponse {
+        entity: EcsEntity,
+        quest: QuestId,
+        response: QuestResponse,
+    },
     Mount(EcsEntity, EcsEntity),
     MountVolume(EcsEntity, VolumePos),
     Unmount(EcsEntity),
@@ -240,6 +257,12 @@ pub enum ServerEvent {
     CreateTeleporter(Vec3<f32>, PortalData),
     ClientDisconnect(EcsEntity, DisconnectReason),
     ClientDisconnectWithoutPersistence(EcsEntity),
+    /// Hands the character a player left behind when their connection dropped
+    /// back to the client that just logged in as them.
+    ResumeSession {
+        entity: EcsEntity,
+        held: EcsEntity,
+    },
     Command(EcsEntity, String, Vec<String>),
     /// Send a chat message to the player from an npc or other player
     Chat(comp::UnresolvedChatMsg),
@@ -274,6 +297,19 @@ pub enum ServerEvent {
         pos: Vec3<i32>,
         tool: Option<comp::tool::ToolKind>,
     },
+    // Attempt to remove a block in build mode, the build area has already been
+    // checked
+    BreakBlock {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+    },
+    // Attempt to place a block in build mode, the build area has already been
+    // checked
+    PlaceBlock {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+        new_block: Block,
+    },
     TeleportTo {
         entity: EcsEntity,
         target: Uid,
@@ -235,12 +235,7 @@ pub enum LootSpec<T: AsRef<str>> {
 }
 
 impl<T: AsRef<str>> LootSpec<T> {
-    fn to_items_inner(
-        &self,
-        rng: &mut rand::rngs::ThreadRng,
-        amount: u32,
-        items: &mut Vec<(u32, Item)>,
-    ) {
+    fn to_items_inner(&self, rng: &mut impl Rng, amount: u32, items: &mut Vec<(u32, Item)>) {
         let convert_item = |item: &T| {
             Item::new_from_asset(item.as_ref()).map_or_else(
                 |e| {
@@ -288,7 +283,9 @@ impl<T: AsRef<str>> LootSpec<T> {
             Self::LootTable(table) => {
                 let loot_spec = Lottery::<LootSpec<String>>::load_expect(table.as_ref()).read();
                 for _ in 0..amount {
-                    loot_spec.choose().to_items_inner(rng, 1, items)
+                    loot_spec
+                        .choose_seeded(rng.gen())
+                        .to_items_inner(rng, 1, items)
                 }
             },
             Self::Nothing => {},
@@ -348,9 +345,11 @@ impl<T: AsRef<str>> LootSpec<T> {
         }
     }
 
-    pub fn to_items(&self) -> Option<Vec<(u32, Item)>> {
+    pub fn to_items(&self) -> Option<Vec<(u32, Item)>> { self.to_items_with_rng(&mut thread_rng()) }
+
+    pub fn to_items_with_rng(&self, rng: &mut impl Rng) -> Option<Vec<(u32, Item)>> {
         let mut items = Vec::new();
-        self.to_items_inner(&mut thread_rng(), 1, &mut items);
+        self.to_items_inner(rng, 1, &mut items);
 
         if !items.is_empty() {
             items.sort_unstable_by_key(|(amount, _)| *amount);
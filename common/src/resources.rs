@@ -1,7 +1,12 @@
 use crate::comp::Pos;
+use fxhash::FxHasher64;
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use specs::Entity;
-use std::ops::{Mul, MulAssign};
+use std::{
+    hash::{Hash, Hasher},
+    ops::{Mul, MulAssign},
+};
 
 /// A resource that stores the time of day.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Default)]
@@ -11,6 +16,28 @@ pub struct TimeOfDay(pub f64);
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct Time(pub f64);
 
+/// A resource that stores the seed of the randomness in the game logic (e.g.
+/// combat, loot and agents), so that recorded sessions are replayed the same.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RngSeed(pub u64);
+
+impl RngSeed {
+    /// Creates the random number generator for `purpose` at `time`, e.g. the
+    /// name of a system, or what an entity needs it for along with its uid.
+    ///
+    /// The generators don't depend on the order they are created in, so
+    /// systems running in parallel get the same numbers every time, as long as
+    /// every purpose is only used once per tick. The hasher is fixed, unlike
+    /// `DefaultHasher` which may change between Rust versions and would break
+    /// replays of recordings made with another build.
+    pub fn rng(&self, time: Time, purpose: impl Hash) -> StdRng {
+        let mut hasher = FxHasher64::default();
+        (self.0, time.0.to_bits()).hash(&mut hasher);
+        purpose.hash(&mut hasher);
+        StdRng::seed_from_u64(hasher.finish())
+    }
+}
+
 /// A resource that stores the real tick, local to the server/client.
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct ProgramTime(pub f64);
@@ -23,6 +23,8 @@ slotmap::new_key_type! { pub struct FactionId; }
 
 slotmap::new_key_type! { pub struct ReportId; }
 
+slotmap::new_key_type! { pub struct QuestId; }
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct RtSimEntity(pub NpcId);
 
@@ -265,6 +267,36 @@ pub enum NpcAction {
     Say(Option<Actor>, Content),
     /// Attack the given target
     Attack(Actor),
+    /// Offer a quest to the given character
+    OfferQuest(Actor, QuestId),
+    /// Let the given character hand in a quest, rewarding them if they
+    /// achieved its objective
+    CompleteQuest(Actor, QuestId),
+}
+
+/// A quest, as shown to the character that was offered it or took it on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuestInfo {
+    pub id: QuestId,
+    /// The name of the NPC that gave the quest.
+    pub giver: String,
+    /// What has to be done to complete the quest.
+    pub objective: Content,
+    /// The asset ids and amounts of the items the quest is rewarded with.
+    pub rewards: Vec<(String, u32)>,
+    /// Whether the objective was achieved and the quest only needs handing in.
+    pub done: bool,
+}
+
+/// What a character makes of a quest.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QuestResponse {
+    /// Take on a quest that was offered.
+    Accept,
+    /// Turn down a quest that was offered.
+    Decline,
+    /// Give up on a quest that was taken on earlier.
+    Abandon,
 }
 
 // Represents a message passed back to rtsim from an agent's brain
@@ -10,6 +10,13 @@ pub enum PluginError {
     Encoding(Box<ErrorKind>),
     PluginModuleError(String, String, PluginModuleError),
     ProcessExit,
+    /// A plugin with this name is already loaded
+    AlreadyLoaded(String),
+    /// No plugin with this name is loaded
+    NotLoaded(String),
+    /// A timer was started with a delay that isn't a positive number of
+    /// seconds
+    InvalidTimerDelay(f64),
 }
 
 #[derive(Debug)]
@@ -20,6 +27,10 @@ pub enum PluginModuleError {
     MemoryUninit(ExportError),
     FindFunction(ExportError),
     RunFunction(RuntimeError),
+    /// The function used up all of its fuel before returning
+    OutOfFuel,
+    /// The function ran for longer than it is allowed to
+    TimedOut,
     InvalidArgumentType(),
     Encoding(Box<ErrorKind>),
     CompileError(CompileError),
@@ -10,12 +10,13 @@ use specs::{
 use wasmer::{Memory, StoreMut, StoreRef, TypedFunction, WasmPtr};
 
 use common::{
-    comp::{Health, Player},
+    comp::{Buffs, Health, Inventory, Player, Pos, Stats},
     uid::{IdMaps, Uid},
 };
 
 use super::{
     errors::{MemoryAllocationError, PluginModuleError},
+    storage::PluginStorage,
     MemoryModel,
 };
 
@@ -24,7 +25,12 @@ pub struct EcsWorld<'a, 'b> {
     pub health: EcsComponentAccess<'a, 'b, Health>,
     pub uid: EcsComponentAccess<'a, 'b, Uid>,
     pub player: EcsComponentAccess<'a, 'b, Player>,
+    pub pos: EcsComponentAccess<'a, 'b, Pos>,
+    pub stats: EcsComponentAccess<'a, 'b, Stats>,
+    pub buffs: EcsComponentAccess<'a, 'b, Buffs>,
+    pub inventory: EcsComponentAccess<'a, 'b, Inventory>,
     pub id_maps: &'b Read<'a, IdMaps>,
+    pub storage: &'b Read<'a, PluginStorage>,
 }
 
 pub enum EcsComponentAccess<'a, 'b, T: Component> {
@@ -2,10 +2,11 @@ pub mod errors;
 pub mod exports;
 pub mod memory_manager;
 pub mod module;
+pub mod storage;
 pub mod wasm_env;
 
 use bincode::ErrorKind;
-use common::assets::ASSETS_PATH;
+use common::{assets::ASSETS_PATH, resources::GameMode};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
@@ -16,7 +17,10 @@ use std::{
 use tracing::{error, info};
 use wasmer::Memory64;
 
-use plugin_api::Event;
+use plugin_api::{
+    event::{PluginLoadEvent, PluginTickEvent, TimerEvent},
+    Action, Event,
+};
 
 use self::{
     errors::PluginError,
@@ -34,13 +38,27 @@ pub struct PluginData {
     name: String,
     modules: HashSet<PathBuf>,
     dependencies: HashSet<String>,
+    /// Minimum time in seconds between two `on_tick` calls, every tick if
+    /// unset
+    #[serde(default)]
+    tick_interval: Option<f64>,
+}
+
+/// A timer started by a plugin, see [`Action::StartTimer`]
+struct Timer {
+    due: f64,
+    repeat: Option<f64>,
 }
 
 pub struct Plugin {
     data: PluginData,
+    /// The archive this plugin was loaded from, if any
+    path: Option<PathBuf>,
     modules: Vec<PluginModule>,
     #[allow(dead_code)]
     files: HashMap<PathBuf, Vec<u8>>,
+    timers: HashMap<String, Timer>,
+    last_tick: Option<f64>,
 }
 
 impl Plugin {
@@ -85,11 +103,113 @@ impl Plugin {
 
         Ok(Plugin {
             data,
+            path: None,
             modules,
             files,
+            timers: HashMap::new(),
+            last_tick: None,
         })
     }
 
+    /// Loads the plugin archive at `path` and registers its assets
+    pub fn from_path(path: PathBuf) -> Result<Self, PluginError> {
+        let plugin = Self::read_path(path)?;
+        plugin.register_assets();
+        Ok(plugin)
+    }
+
+    /// Loads the plugin archive at `path` without registering its assets yet
+    fn read_path(path: PathBuf) -> Result<Self, PluginError> {
+        let mut plugin = Self::from_reader(fs::File::open(&path).map_err(PluginError::Io)?)?;
+        plugin.path = Some(path);
+        Ok(plugin)
+    }
+
+    fn register_assets(&self) {
+        if let Some(path) = &self.path {
+            if let Err(e) = common::assets::register_tar(path.clone()) {
+                error!("Plugin {:?} tar error {e:?}", path);
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.data.name }
+
+    pub fn path(&self) -> Option<&Path> { self.path.as_deref() }
+
+    pub fn module_count(&self) -> usize { self.modules.len() }
+
+    /// Starts or replaces a timer firing `delay` seconds after `time`
+    pub fn start_timer(
+        &mut self,
+        name: String,
+        delay: f64,
+        repeat: bool,
+        time: f64,
+    ) -> Result<(), PluginError> {
+        // A repeating timer without a positive delay would fire forever within a
+        // single tick
+        if !(delay > 0.0 && delay.is_finite()) {
+            return Err(PluginError::InvalidTimerDelay(delay));
+        }
+        self.timers.insert(name, Timer {
+            due: time + delay,
+            repeat: repeat.then_some(delay),
+        });
+        Ok(())
+    }
+
+    pub fn cancel_timer(&mut self, name: &str) { self.timers.remove(name); }
+
+    /// Runs the `on_tick` event if the tick interval elapsed, then the
+    /// `on_timer` event for every timer that is due. Failures are logged, and
+    /// don't keep the other events from running.
+    pub fn tick(&mut self, ecs: &EcsWorld, time: f64) {
+        let interval = self.data.tick_interval.unwrap_or(0.0);
+        if self.last_tick.map_or(true, |last| time - last >= interval) {
+            let dt = self.last_tick.map_or(0.0, |last| time - last);
+            self.last_tick = Some(time);
+            if let Err(e) = PreparedEventQuery::new(&PluginTickEvent { time, dt })
+                .and_then(|event| self.execute_prepared(ecs, &event))
+            {
+                error!(?e, "Plugin {} failed to tick", self.name());
+            }
+        }
+
+        let mut due = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.due <= time)
+            .map(|(name, timer)| (timer.due, name.clone()))
+            .collect::<Vec<_>>();
+        due.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        for (_, name) in due {
+            if let Some(timer) = self.timers.get_mut(&name) {
+                match timer.repeat {
+                    // Don't try to catch up on missed firings after a long tick
+                    Some(repeat) => timer.due = time + repeat,
+                    None => {
+                        self.timers.remove(&name);
+                    },
+                }
+            }
+            if let Err(e) = PreparedEventQuery::new(&TimerEvent { name: name.clone() })
+                .and_then(|event| self.execute_prepared(ecs, &event))
+            {
+                error!(?e, "Plugin {} failed to run timer {}", self.name(), name);
+            }
+        }
+    }
+
+    /// Takes the actions emitted by all modules of this plugin since the last
+    /// call
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        self.modules
+            .iter_mut()
+            .flat_map(PluginModule::take_actions)
+            .collect()
+    }
+
     pub fn execute_prepared<T>(
         &mut self,
         ecs: &EcsWorld,
@@ -146,29 +266,179 @@ pub struct PluginMgr {
 }
 
 impl PluginMgr {
+    /// The directory plugins are loaded from
+    pub fn plugin_dir() -> PathBuf { ASSETS_PATH.join("plugins") }
+
     pub fn from_assets() -> Result<Self, PluginError> {
-        let mut assets_path = (*ASSETS_PATH).clone();
-        assets_path.push("plugins");
+        let assets_path = Self::plugin_dir();
         info!("Searching {:?} for plugins...", assets_path);
         Self::from_dir(assets_path)
     }
 
+    pub fn plugins(&self) -> impl Iterator<Item = &Plugin> { self.plugins.iter() }
+
+    /// Loads the plugin archive at `path` and runs its `on_load` event.
+    /// Returns the name of the loaded plugin.
+    pub fn load(
+        &mut self,
+        path: PathBuf,
+        ecs: &EcsWorld,
+        game_mode: GameMode,
+    ) -> Result<String, PluginError> {
+        info!("Loading plugin at {:?}", path);
+        let mut plugin = Plugin::read_path(path)?;
+        let name = plugin.name().to_owned();
+        // Assets are registered by path, so the same archive can't be loaded twice
+        // either without dropping the assets of the live plugin on failure
+        if self
+            .plugins
+            .iter()
+            .any(|p| p.name() == name || p.path() == plugin.path())
+        {
+            return Err(PluginError::AlreadyLoaded(name));
+        }
+        plugin.register_assets();
+        if let Err(e) = plugin.execute_prepared(
+            ecs,
+            &PreparedEventQuery::new(&PluginLoadEvent { game_mode })?,
+        ) {
+            if let Some(path) = plugin.path() {
+                common::assets::unregister_tar(path);
+            }
+            return Err(e);
+        }
+        info!(
+            "Loaded plugin '{}' with {} module(s)",
+            name,
+            plugin.module_count()
+        );
+        self.plugins.push(plugin);
+        Ok(name)
+    }
+
+    /// Unloads the plugin called `name`, dropping its modules along with any
+    /// pending actions and timers
+    pub fn unload(&mut self, name: &str) -> Result<(), PluginError> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|p| p.name() == name)
+            .ok_or_else(|| PluginError::NotLoaded(name.to_owned()))?;
+        let plugin = self.plugins.remove(index);
+        if let Some(path) = plugin.path() {
+            common::assets::unregister_tar(path);
+        }
+        info!("Unloaded plugin '{}'", name);
+        Ok(())
+    }
+
+    /// Replaces the plugin called `name` with the current version of the
+    /// archive it was loaded from.
+    ///
+    /// The old version is only unloaded once the new one loaded successfully.
+    pub fn reload(
+        &mut self,
+        name: &str,
+        ecs: &EcsWorld,
+        game_mode: GameMode,
+    ) -> Result<String, PluginError> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|p| p.name() == name)
+            .ok_or_else(|| PluginError::NotLoaded(name.to_owned()))?;
+        let path = self.plugins[index]
+            .path()
+            .ok_or_else(|| PluginError::NotLoaded(name.to_owned()))?
+            .to_owned();
+        // Take the old version out so that it does not clash with the new one
+        let old = self.plugins.remove(index);
+        common::assets::unregister_tar(&path);
+        match self.load(path.clone(), ecs, game_mode) {
+            Ok(name) => Ok(name),
+            Err(e) => {
+                if let Err(e) = common::assets::register_tar(path) {
+                    error!("Plugin {:?} tar error {e:?}", old.path());
+                }
+                self.plugins.insert(index, old);
+                Err(e)
+            },
+        }
+    }
+
     pub fn execute_prepared<T>(
         &mut self,
         ecs: &EcsWorld,
         event: &PreparedEventQuery<T>,
-    ) -> Result<Vec<T::Response>, PluginError>
+    ) -> Vec<T::Response>
     where
         T: Event,
     {
-        Ok(self
-            .plugins
+        // A plugin failing (e.g. by running out of fuel) doesn't discard the
+        // responses of the others
+        self.plugins
             .par_iter_mut()
-            .map(|plugin| plugin.execute_prepared(ecs, event))
-            .collect::<Result<Vec<_>, _>>()?
+            .filter_map(|plugin| match plugin.execute_prepared(ecs, event) {
+                Ok(responses) => Some(responses),
+                Err(e) => {
+                    error!(
+                        ?e,
+                        "Plugin {} failed to run {}",
+                        plugin.name(),
+                        event.get_function_name()
+                    );
+                    None
+                },
+            })
+            .collect::<Vec<_>>()
             .into_iter()
             .flatten()
-            .collect())
+            .collect()
+    }
+
+    /// Takes the actions emitted by all plugins since the last call, along
+    /// with the name of the plugin which emitted them. The host is expected to
+    /// apply them once per tick.
+    pub fn take_actions(&mut self) -> Vec<(String, Action)> {
+        self.plugins
+            .iter_mut()
+            .flat_map(|plugin| {
+                let name = plugin.name().to_owned();
+                plugin
+                    .take_actions()
+                    .into_iter()
+                    .map(move |action| (name.clone(), action))
+            })
+            .collect()
+    }
+
+    pub fn start_timer(
+        &mut self,
+        plugin: &str,
+        name: String,
+        delay: f64,
+        repeat: bool,
+        time: f64,
+    ) -> Result<(), PluginError> {
+        self.plugins
+            .iter_mut()
+            .find(|p| p.name() == plugin)
+            .ok_or_else(|| PluginError::NotLoaded(plugin.to_owned()))?
+            .start_timer(name, delay, repeat, time)
+    }
+
+    pub fn cancel_timer(&mut self, plugin: &str, name: &str) {
+        if let Some(plugin) = self.plugins.iter_mut().find(|p| p.name() == plugin) {
+            plugin.cancel_timer(name);
+        }
+    }
+
+    /// Runs the `on_tick` and `on_timer` events of every plugin, see
+    /// [`Plugin::tick`]
+    pub fn tick(&mut self, ecs: &EcsWorld, time: f64) {
+        self.plugins
+            .par_iter_mut()
+            .for_each(|plugin| plugin.tick(ecs, time));
     }
 
     pub fn execute_event<T>(
@@ -179,7 +449,7 @@ impl PluginMgr {
     where
         T: Event,
     {
-        self.execute_prepared(ecs, &PreparedEventQuery::new(event)?)
+        Ok(self.execute_prepared(ecs, &PreparedEventQuery::new(event)?))
     }
 
     pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self, PluginError> {
@@ -196,14 +466,7 @@ impl PluginMgr {
                         .unwrap_or(false)
                 {
                     info!("Loading plugin at {:?}", entry.path());
-                    Plugin::from_reader(fs::File::open(entry.path()).map_err(PluginError::Io)?).map(
-                        |plugin| {
-                            if let Err(e) = common::assets::register_tar(entry.path()) {
-                                error!("Plugin {:?} tar error {e:?}", entry.path());
-                            }
-                            Some(plugin)
-                        },
-                    )
+                    Plugin::from_path(entry.path()).map(Some)
                 } else {
                     Ok(None)
                 }
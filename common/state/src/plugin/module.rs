@@ -1,20 +1,47 @@
 use hashbrown::HashSet;
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use wasmer::{
-    imports, AsStoreMut, AsStoreRef, Function, FunctionEnv, FunctionEnvMut, Instance, Memory,
-    Module, Store, TypedFunction, WasmPtr,
+    imports, wasmparser::Operator, AsStoreMut, AsStoreRef, CompilerConfig, Cranelift,
+    EngineBuilder, Function, FunctionEnv, FunctionEnvMut, Instance, Memory, Module, Store,
+    TypedFunction, WasmPtr,
+};
+use wasmer_middlewares::{
+    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    Metering,
 };
 
 use super::{
     errors::{PluginError, PluginModuleError},
     exports,
     memory_manager::{self, EcsAccessManager, EcsWorld},
-    wasm_env::HostFunctionEnvironment,
+    storage::StorageKey,
+    wasm_env::{DeadlineExceeded, HostFunctionEnvironment},
     MemoryModel,
 };
 
-use plugin_api::{Action, EcsAccessError, Event, Retrieve, RetrieveError, RetrieveResult};
+use common::uid::Uid;
+use plugin_api::{
+    Action, EcsAccessError, Event, InventoryItem, Retrieve, RetrieveError, RetrieveResult,
+};
+
+/// Amount of fuel (roughly the number of WASM operators) a plugin gets for each
+/// call into it, so that a plugin stuck in a loop can't stall the server
+const FUEL_PER_CALL: u64 = 100_000_000;
+/// Calls into a plugin that take longer than this are reported
+const SLOW_CALL_THRESHOLD: Duration = Duration::from_millis(10);
+/// Calls into a plugin that take longer than this fail.
+///
+/// The deadline is only checked when the plugin calls back into the host,
+/// which traps once it has passed. A plugin that doesn't call into the host
+/// keeps running until it returns or runs out of fuel (wasmer has no epoch
+/// interruption), so [`FUEL_PER_CALL`] is what bounds its running time; it
+/// fails with [`PluginModuleError::TimedOut`] once it returned late.
+const MAX_CALL_DURATION: Duration = Duration::from_millis(50);
 
 // #[derive(Clone)]
 /// This structure represent the WASM State of the plugin.
@@ -25,17 +52,24 @@ pub struct PluginModule {
     allocator: TypedFunction<<MemoryModel as wasmer::MemorySize>::Offset, WasmPtr<u8, MemoryModel>>,
     memory: Memory,
     store: Store,
+    env: FunctionEnv<HostFunctionEnvironment>,
     #[allow(dead_code)]
     name: String,
+    pending_actions: Arc<Mutex<Vec<Action>>>,
     pub(crate) exit_code: Option<i32>,
 }
 
 impl PluginModule {
     /// This function takes bytes from a WASM File and compile them
     pub fn new(name: String, wasm_data: &[u8]) -> Result<Self, PluginModuleError> {
+        // Every operator costs one unit of fuel, the remaining fuel is reset before
+        // each call
+        let metering = Arc::new(Metering::new(FUEL_PER_CALL, |_: &Operator| 1));
+        let mut compiler = Cranelift::default();
+        compiler.push_middleware(metering);
         // The store contains all data for a specific instance, including the linear
         // memory
-        let mut store = Store::default();
+        let mut store = Store::new(EngineBuilder::new(compiler));
         // We are compiling the WASM file in the previously generated environement
         let module = Module::from_binary(store.engine(), wasm_data)
             .map_err(PluginModuleError::CompileError)?;
@@ -46,16 +80,19 @@ impl PluginModule {
             // store: &wasmer::StoreRef<'_>,
             ptr: WasmPtr<u8, MemoryModel>,
             len: <MemoryModel as wasmer::MemorySize>::Offset,
-        ) {
+        ) -> Result<(), DeadlineExceeded> {
+            env.data().check_deadline()?;
             handle_actions(
+                env.data(),
                 match env.data().read_serialized(&env.as_store_ref(), ptr, len) {
                     Ok(e) => e,
                     Err(e) => {
                         tracing::error!(?e, "Can't decode action");
-                        return;
+                        return Ok(());
                     },
                 },
             );
+            Ok(())
         }
 
         fn raw_retrieve_action(
@@ -63,18 +100,20 @@ impl PluginModule {
             // store: &wasmer::StoreRef<'_>,
             ptr: WasmPtr<u8, MemoryModel>,
             len: <MemoryModel as wasmer::MemorySize>::Offset,
-        ) -> <MemoryModel as wasmer::MemorySize>::Offset {
+        ) -> Result<<MemoryModel as wasmer::MemorySize>::Offset, DeadlineExceeded> {
+            env.data().check_deadline()?;
             let out = match env.data().read_serialized(&env.as_store_ref(), ptr, len) {
-                Ok(data) => retrieve_action(env.data().ecs(), data),
+                Ok(data) => retrieve_action(env.data().ecs(), env.data().name(), data),
                 Err(e) => Err(RetrieveError::BincodeError(e.to_string())),
             };
 
             let data = env.data().clone();
-            data.write_serialized_with_length(&mut env.as_store_mut(), &out)
+            Ok(data
+                .write_serialized_with_length(&mut env.as_store_mut(), &out)
                 .unwrap_or_else(|_e|
                     // return a null pointer so the WASM side can tell an error occured
                     WasmPtr::null())
-                .offset()
+                .offset())
         }
 
         fn dbg(a: i32) {
@@ -82,11 +121,16 @@ impl PluginModule {
         }
 
         let ecs = Arc::new(EcsAccessManager::default());
+        let pending_actions = Arc::new(Mutex::new(Vec::new()));
 
         // Environment to pass ecs and memory_manager to callbacks
         let env = FunctionEnv::new(
             &mut store,
-            HostFunctionEnvironment::new(name.clone(), Arc::clone(&ecs)),
+            HostFunctionEnvironment::new(
+                name.clone(),
+                Arc::clone(&ecs),
+                Arc::clone(&pending_actions),
+            ),
         );
         // Create an import object.
         let import_object = imports! {
@@ -127,7 +171,9 @@ impl PluginModule {
                 .collect(),
             wasm_state: Arc::new(instance),
             store,
+            env,
             name,
+            pending_actions,
             exit_code: None,
         })
     }
@@ -157,6 +203,17 @@ impl PluginModule {
     }
 
     pub fn name(&self) -> &str { &self.name }
+
+    /// Takes all the actions emitted by this module since the last call, in
+    /// the order they were emitted
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::take(
+            &mut *self
+                .pending_actions
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
 }
 
 /// This structure represent a Pre-encoded event object (Useful to avoid
@@ -193,6 +250,13 @@ fn execute_raw(
     event_name: &str,
     bytes: &[u8],
 ) -> Result<Vec<u8>, PluginModuleError> {
+    set_remaining_points(&mut module.store, &module.wasm_state, FUEL_PER_CALL);
+    let start = Instant::now();
+    module
+        .env
+        .as_mut(&mut module.store)
+        .set_deadline(start + MAX_CALL_DURATION);
+
     // This write into memory `bytes` using allocation if necessary returning a
     // pointer and a length
 
@@ -219,9 +283,30 @@ fn execute_raw(
 
     // We call the function with the pointer and the length
 
-    let result_ptr = func
-        .call(&mut module.store.as_store_mut(), ptr, len)
-        .map_err(PluginModuleError::RunFunction)?;
+    let result = func.call(&mut module.store.as_store_mut(), ptr, len);
+    let result_ptr = result.map_err(|e| {
+        if let MeteringPoints::Exhausted =
+            get_remaining_points(&mut module.store, &module.wasm_state)
+        {
+            PluginModuleError::OutOfFuel
+        } else if e.downcast_ref::<DeadlineExceeded>().is_some() {
+            PluginModuleError::TimedOut
+        } else {
+            PluginModuleError::RunFunction(e)
+        }
+    })?;
+
+    let elapsed = start.elapsed();
+    if elapsed > MAX_CALL_DURATION {
+        return Err(PluginModuleError::TimedOut);
+    } else if elapsed > SLOW_CALL_THRESHOLD {
+        tracing::warn!(
+            ?elapsed,
+            "Module {} took a long time to run {}",
+            module.name,
+            event_name
+        );
+    }
 
     // The first bytes correspond to the length of the result
     let result_len: [u8; std::mem::size_of::<<MemoryModel as wasmer::MemorySize>::Offset>()] =
@@ -245,82 +330,103 @@ fn execute_raw(
 
 fn retrieve_action(
     ecs: &EcsAccessManager,
+    plugin: &str,
     action: Retrieve,
 ) -> Result<RetrieveResult, RetrieveError> {
-    match action {
-        Retrieve::GetPlayerName(e) => {
-            // Safety: No reference is leaked out the function so it is safe.
-            let world = unsafe {
-                ecs.get().ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsPointerNotAvailable,
-                ))?
-            };
-            let player = world
-                .id_maps
-                .uid_entity(e)
-                .ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsEntityNotFound(e),
-                ))?;
-
-            Ok(RetrieveResult::GetPlayerName(
-                world
-                    .player
-                    .get(player)
-                    .ok_or_else(|| {
-                        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
-                            e,
-                            "Player".to_owned(),
-                        ))
-                    })?
-                    .alias
-                    .to_owned(),
-            ))
-        },
-        Retrieve::GetEntityHealth(e) => {
-            // Safety: No reference is leaked out the function so it is safe.
-            let world = unsafe {
-                ecs.get().ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsPointerNotAvailable,
-                ))?
-            };
-            let player = world
-                .id_maps
-                .uid_entity(e)
-                .ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsEntityNotFound(e),
-                ))?;
-            Ok(RetrieveResult::GetEntityHealth(
-                world
-                    .health
-                    .get(player)
-                    .ok_or_else(|| {
-                        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
-                            e,
-                            "Health".to_owned(),
-                        ))
-                    })?
-                    .clone(),
+    // Safety: No reference is leaked out the function so it is safe.
+    let world = unsafe {
+        ecs.get().ok_or(RetrieveError::EcsAccessError(
+            EcsAccessError::EcsPointerNotAvailable,
+        ))?
+    };
+    let entity = |uid: Uid| {
+        world
+            .id_maps
+            .uid_entity(uid)
+            .ok_or(RetrieveError::EcsAccessError(
+                EcsAccessError::EcsEntityNotFound(uid),
             ))
-        },
+    };
+    let not_found = |uid: Uid, component: &str| {
+        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
+            uid,
+            component.to_owned(),
+        ))
+    };
+    match action {
+        Retrieve::GetPlayerName(e) => Ok(RetrieveResult::GetPlayerName(
+            world
+                .player
+                .get(entity(e)?)
+                .ok_or_else(|| not_found(e, "Player"))?
+                .alias
+                .to_owned(),
+        )),
+        Retrieve::GetEntityHealth(e) => Ok(RetrieveResult::GetEntityHealth(
+            world
+                .health
+                .get(entity(e)?)
+                .ok_or_else(|| not_found(e, "Health"))?
+                .clone(),
+        )),
+        Retrieve::GetEntityPosition(e) => Ok(RetrieveResult::GetEntityPosition(
+            *world
+                .pos
+                .get(entity(e)?)
+                .ok_or_else(|| not_found(e, "Pos"))?,
+        )),
+        Retrieve::GetEntityStats(e) => Ok(RetrieveResult::GetEntityStats(
+            world
+                .stats
+                .get(entity(e)?)
+                .ok_or_else(|| not_found(e, "Stats"))?
+                .clone(),
+        )),
+        Retrieve::GetEntityBuffs(e) => Ok(RetrieveResult::GetEntityBuffs(
+            world
+                .buffs
+                .get(entity(e)?)
+                .ok_or_else(|| not_found(e, "Buffs"))?
+                .kinds
+                .iter()
+                .filter_map(|(kind, keys)| keys.is_some().then_some(kind))
+                .collect(),
+        )),
+        Retrieve::GetEntityInventory(e) => Ok(RetrieveResult::GetEntityInventory(
+            world
+                .inventory
+                .get(entity(e)?)
+                .ok_or_else(|| not_found(e, "Inventory"))?
+                .slots()
+                .flatten()
+                .map(|item| InventoryItem {
+                    item_definition_id: item.item_definition_id().to_owned(),
+                    amount: item.amount(),
+                })
+                .collect(),
+        )),
+        Retrieve::GetStorageValue(character_id, key) => Ok(RetrieveResult::GetStorageValue(
+            world
+                .storage
+                .get(&StorageKey {
+                    plugin: plugin.to_owned(),
+                    character_id,
+                    key,
+                })
+                .map(<[u8]>::to_vec),
+        )),
     }
 }
 
-fn handle_actions(actions: Vec<Action>) {
+fn handle_actions(env: &HostFunctionEnvironment, actions: Vec<Action>) {
     for action in actions {
         match action {
-            Action::ServerClose => {
-                tracing::info!("Server closed by plugin");
-                std::process::exit(-1);
-            },
             Action::Print(e) => {
-                tracing::info!("{}", e);
-            },
-            Action::PlayerSendMessage(a, b) => {
-                tracing::info!("SendMessage {} -> {}", a, b);
-            },
-            Action::KillEntity(e) => {
-                tracing::info!("Kill Entity {}", e);
+                tracing::info!("[{}]: {}", env.name(), e);
             },
+            // Everything else mutates the ECS, which is only borrowed immutably
+            // while an event runs, so it is applied by the host afterwards
+            action => env.push_action(action),
         }
     }
 }
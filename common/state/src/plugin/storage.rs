@@ -0,0 +1,44 @@
+use common::character::CharacterId;
+use hashbrown::HashMap;
+
+/// Identifies a value in the [`PluginStorage`].
+///
+/// Every plugin has its own namespace, and values can optionally be attached
+/// to a character so that they are removed along with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StorageKey {
+    pub plugin: String,
+    pub character_id: Option<CharacterId>,
+    pub key: String,
+}
+
+/// Key-value data that plugins want to keep across server restarts.
+///
+/// The host is responsible for filling this resource from its database on
+/// startup and for persisting the changes plugins make to it.
+#[derive(Default)]
+pub struct PluginStorage {
+    values: HashMap<StorageKey, Vec<u8>>,
+}
+
+impl PluginStorage {
+    pub fn get(&self, key: &StorageKey) -> Option<&[u8]> { self.values.get(key).map(Vec::as_slice) }
+
+    pub fn set(&mut self, key: StorageKey, value: Vec<u8>) { self.values.insert(key, value); }
+
+    pub fn remove(&mut self, key: &StorageKey) -> Option<Vec<u8>> { self.values.remove(key) }
+
+    /// Removes the values attached to a character, once it was deleted
+    pub fn remove_character(&mut self, character_id: CharacterId) {
+        self.values
+            .retain(|key, _| key.character_id != Some(character_id));
+    }
+}
+
+impl FromIterator<(StorageKey, Vec<u8>)> for PluginStorage {
+    fn from_iter<T: IntoIterator<Item = (StorageKey, Vec<u8>)>>(iter: T) -> Self {
+        Self {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
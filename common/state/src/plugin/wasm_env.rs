@@ -1,5 +1,9 @@
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
+use plugin_api::Action;
 use serde::{de::DeserializeOwned, Serialize};
 use wasmer::{ExportError, Instance, Memory, Store, StoreMut, StoreRef, TypedFunction, WasmPtr};
 
@@ -19,6 +23,8 @@ pub struct HostFunctionEnvironment {
         TypedFunction<<MemoryModel as wasmer::MemorySize>::Offset, WasmPtr<u8, MemoryModel>>,
     >, /* Linked to: wasm_prepare_buffer */
     name: String,           // This represent the plugin name
+    pending_actions: Arc<Mutex<Vec<Action>>>, // Actions waiting to be applied by the host
+    deadline: Option<Instant>, // When the call currently running has to return by
 }
 
 pub struct HostFunctionEnvironmentInit {
@@ -39,14 +45,32 @@ impl core::fmt::Display for HostFunctionException {
 
 impl std::error::Error for HostFunctionException {}
 
+#[derive(Debug, Clone, Copy)]
+// Thrown from a native wasm callback when the call ran past its deadline
+pub struct DeadlineExceeded;
+
+impl core::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "plugin call exceeded its deadline")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
 impl HostFunctionEnvironment {
     /// Create a new environment for functions providing functionality to WASM
-    pub fn new(name: String, ecs: Arc<EcsAccessManager>) -> Self {
+    pub fn new(
+        name: String,
+        ecs: Arc<EcsAccessManager>,
+        pending_actions: Arc<Mutex<Vec<Action>>>,
+    ) -> Self {
         Self {
             ecs,
             allocator: Default::default(),
             memory: Default::default(),
             name,
+            pending_actions,
+            deadline: None,
         }
     }
 
@@ -66,6 +90,26 @@ impl HostFunctionEnvironment {
     #[inline]
     pub(crate) fn name(&self) -> &str { &self.name }
 
+    /// Sets when the call about to be made has to return by
+    pub(crate) fn set_deadline(&mut self, deadline: Instant) { self.deadline = Some(deadline); }
+
+    /// Traps the running call once it has gone past its deadline
+    pub(crate) fn check_deadline(&self) -> Result<(), DeadlineExceeded> {
+        match self.deadline {
+            Some(deadline) if Instant::now() > deadline => Err(DeadlineExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// Queue an action to be applied by the host once the current event has
+    /// finished executing
+    pub(crate) fn push_action(&self, action: Action) {
+        self.pending_actions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(action);
+    }
+
     /// This function is a safe interface to WASM memory that serializes and
     /// writes an object to linear memory returning a pointer
     pub(crate) fn write_serialized_with_length<T: Serialize>(
@@ -1,7 +1,7 @@
 #[cfg(feature = "plugins")]
 use crate::plugin::memory_manager::EcsWorld;
 #[cfg(feature = "plugins")]
-use crate::plugin::PluginMgr;
+use crate::plugin::{storage::PluginStorage, PluginMgr};
 use crate::{BuildArea, NoDurabilityArea};
 #[cfg(feature = "plugins")]
 use common::uid::IdMaps;
@@ -303,6 +303,10 @@ impl State {
         ecs.insert(PlayerPhysicsSettings::default());
         ecs.insert(VolumeRiders::default());
 
+        // Filled by the server from its database, if it has one
+        #[cfg(feature = "plugins")]
+        ecs.insert(PluginStorage::default());
+
         // Load plugins from asset directory
         #[cfg(feature = "plugins")]
         ecs.insert(match PluginMgr::from_assets() {
@@ -313,6 +317,11 @@ impl State {
                     uid: ecs.read_component().into(),
                     id_maps: &ecs.read_resource::<IdMaps>().into(),
                     player: ecs.read_component().into(),
+                    pos: ecs.read_component().into(),
+                    stats: ecs.read_component().into(),
+                    buffs: ecs.read_component().into(),
+                    inventory: ecs.read_component().into(),
+                    storage: &ecs.read_resource::<PluginStorage>().into(),
                 };
                 if let Err(e) = plugin_mgr
                     .execute_event(&ecs_world, &plugin_api::event::PluginLoadEvent {
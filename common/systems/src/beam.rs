@@ -7,7 +7,7 @@ use common::{
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
-    resources::{DeltaTime, Time},
+    resources::{DeltaTime, RngSeed, Time},
     terrain::TerrainGrid,
     uid::{IdMaps, Uid},
     vol::ReadVol,
@@ -28,6 +28,7 @@ pub struct ReadData<'a> {
     players: ReadStorage<'a, Player>,
     server_bus: Read<'a, EventBus<ServerEvent>>,
     time: Read<'a, Time>,
+    rng_seed: Read<'a, RngSeed>,
     dt: Read<'a, DeltaTime>,
     terrain: ReadExpect<'a, TerrainGrid>,
     id_maps: Read<'a, IdMaps>,
@@ -113,9 +114,9 @@ impl<'a> System<'a> for Sys {
                 || (Vec::new(), Vec::new(), Vec::new()),
                 |(mut server_events, mut add_hit_entities, mut outcomes),
                  (entity, pos, ori, uid, beam)| {
-                    // Note: rayon makes it difficult to hold onto a thread-local RNG, if grabbing
-                    // this becomes a bottleneck we can look into alternatives.
-                    let mut rng = rand::thread_rng();
+                    // Every beam gets its own RNG, so the numbers don't depend on how rayon
+                    // splits the work.
+                    let mut rng = read_data.rng_seed.rng(*read_data.time, (Self::NAME, *uid));
                     if rng.gen_bool(0.005) {
                         server_events.push(ServerEvent::Sound {
                             sound: Sound::new(SoundKind::Beam, pos.0, 13.0, read_data.time.0),
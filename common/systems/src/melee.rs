@@ -8,7 +8,7 @@ use common::{
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
-    resources::Time,
+    resources::{RngSeed, Time},
     terrain::TerrainGrid,
     uid::{IdMaps, Uid},
     util::{find_dist::Cylinder, Dir},
@@ -26,6 +26,7 @@ use vek::*;
 #[derive(SystemData)]
 pub struct ReadData<'a> {
     time: Read<'a, Time>,
+    rng_seed: Read<'a, RngSeed>,
     terrain: ReadExpect<'a, TerrainGrid>,
     id_maps: Read<'a, IdMaps>,
     entities: Entities<'a>,
@@ -66,7 +67,7 @@ impl<'a> System<'a> for Sys {
     fn run(_job: &mut Job<Self>, (read_data, mut melee_attacks, outcomes): Self::SystemData) {
         let mut server_emitter = read_data.server_bus.emitter();
         let mut outcomes_emitter = outcomes.emitter();
-        let mut rng = rand::thread_rng();
+        let mut rng = read_data.rng_seed.rng(*read_data.time, Self::NAME);
 
         // Attacks
         for (attacker, uid, pos, ori, melee_attack, body, scale) in (
@@ -124,6 +124,54 @@ fn calc_z_limit(char_state_maybe: Option<&CharacterState>, collider: &Collider)
     collider.get_z_limits(modifier)
 }
 
+/// The cylinder (radius, z_min, z_max) used to collide an entity with a
+/// `Collider::CapsulePrism` with terrain.
+fn capsule_terrain_cylinder(collider: &Collider, z_max: f32, scale: f32) -> (f32, f32, f32) {
+    let radius = collider.bounding_radius().min(0.45) * scale;
+    let z_max = z_max.clamped(1.2, 1.95) * scale;
+    (radius, 0.0, z_max)
+}
+
+/// Whether an entity with a `Collider::CapsulePrism` at `pos` overlaps solid
+/// terrain by more than `margin` blocks, which the terrain collision of this
+/// system never lets happen. Always false for other colliders.
+pub fn overlaps_terrain<T: BaseVol<Vox = Block> + ReadVol>(
+    terrain: &T,
+    collider: &Collider,
+    scale: f32,
+    pos: Vec3<f32>,
+    margin: f32,
+) -> bool {
+    let Collider::CapsulePrism { z_max, .. } = collider else {
+        return false;
+    };
+    // Scaled twice, like the cylinder passed to `box_voxel_collision`
+    let cylinder = capsule_terrain_cylinder(collider, *z_max, scale);
+    let (radius, z_min, z_max) = (Vec3::from(cylinder) * scale.min(10.0)).into_tuple();
+    let radius = radius - margin;
+    let entity_aabb = Aabb {
+        min: pos + Vec3::new(-radius, -radius, z_min + margin),
+        max: pos + Vec3::new(radius, radius, z_max - margin),
+    };
+    let near_aabb = Aabb {
+        min: entity_aabb.min.map(|e| e.floor() as i32)
+            - Vec3::unit_z() * (Block::MAX_HEIGHT.ceil() as i32 - 1),
+        max: entity_aabb.max.map(|e| e.floor() as i32),
+    };
+
+    let mut overlaps = false;
+    terrain.for_each_in(near_aabb, |block_pos, block| {
+        if block.is_solid() {
+            let block_aabb = Aabb {
+                min: block_pos.map(|e| e as f32),
+                max: block_pos.map(|e| e as f32) + Vec3::new(1.0, 1.0, block.solid_height()),
+            };
+            overlaps |= entity_aabb.collides_with_aabb(block_aabb);
+        }
+    });
+    overlaps
+}
+
 /// This system applies forces and calculates new positions and velocities.
 #[derive(Default)]
 pub struct Sys;
@@ -917,12 +965,7 @@ impl<'a> PhysicsData<'a> {
                             p1: _,
                             radius: _,
                         } => {
-                            // Scale collider
-                            let radius = collider.bounding_radius().min(0.45) * scale;
-                            let z_min = 0.0;
-                            let z_max = z_max.clamped(1.2, 1.95) * scale;
-
-                            let cylinder = (radius, z_min, z_max);
+                            let cylinder = capsule_terrain_cylinder(collider, *z_max, scale);
                             let mut cpos = *pos;
                             box_voxel_collision(
                                 cylinder,
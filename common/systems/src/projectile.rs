@@ -7,7 +7,7 @@ use common::{
     },
     event::{Emitter, EventBus, ServerEvent},
     outcome::Outcome,
-    resources::{DeltaTime, Time},
+    resources::{DeltaTime, RngSeed, Time},
     uid::{IdMaps, Uid},
     util::Dir,
     GroupTarget,
@@ -28,6 +28,7 @@ use common::terrain::TerrainGrid;
 #[derive(SystemData)]
 pub struct ReadData<'a> {
     time: Read<'a, Time>,
+    rng_seed: Read<'a, RngSeed>,
     entities: Entities<'a>,
     players: ReadStorage<'a, Player>,
     dt: Read<'a, DeltaTime>,
@@ -71,7 +72,7 @@ impl<'a> System<'a> for Sys {
     ) {
         let mut server_emitter = read_data.server_bus.emitter();
         let mut outcomes_emitter = outcomes.emitter();
-        let mut rng = rand::thread_rng();
+        let mut rng = read_data.rng_seed.rng(*read_data.time, Self::NAME);
 
         // Attacks
         'projectile_loop: for (entity, pos, physics, vel, projectile) in (
@@ -265,7 +266,7 @@ fn dispatch_hit(
     projectile_vanished: &mut bool,
     outcomes_emitter: &mut Emitter<Outcome>,
     server_emitter: &mut Emitter<ServerEvent>,
-    rng: &mut rand::rngs::ThreadRng,
+    rng: &mut impl Rng,
 ) {
     match projectile_info.effect {
         projectile::Effect::Attack(attack) => {
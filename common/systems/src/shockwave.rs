@@ -8,7 +8,7 @@ use common::{
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
-    resources::{DeltaTime, Time},
+    resources::{DeltaTime, RngSeed, Time},
     uid::{IdMaps, Uid},
     util::Dir,
     GroupTarget,
@@ -25,6 +25,7 @@ pub struct ReadData<'a> {
     entities: Entities<'a>,
     server_bus: Read<'a, EventBus<ServerEvent>>,
     time: Read<'a, Time>,
+    rng_seed: Read<'a, RngSeed>,
     players: ReadStorage<'a, Player>,
     dt: Read<'a, DeltaTime>,
     id_maps: Read<'a, IdMaps>,
@@ -67,7 +68,7 @@ impl<'a> System<'a> for Sys {
     ) {
         let mut server_emitter = read_data.server_bus.emitter();
         let mut outcomes_emitter = outcomes.emitter();
-        let mut rng = rand::thread_rng();
+        let mut rng = read_data.rng_seed.rng(*read_data.time, Self::NAME);
 
         let time = read_data.time.0;
         let dt = read_data.dt.0;
@@ -28,9 +28,8 @@ impl<E: std::fmt::Debug + Send> From<ProtocolError<E>> for InitProtocolError<E>
     fn from(err: ProtocolError<E>) -> Self {
         match err {
             ProtocolError::Custom(e) => InitProtocolError::Custom(e),
-            ProtocolError::Violated => {
-                unreachable!("not possible as the Init has raw access to the I/O")
-            },
+            // Only UDP, which gives up on a handshake the remote side never got
+            ProtocolError::Violated => InitProtocolError::NotHandshake,
         }
     }
 }
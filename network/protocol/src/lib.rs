@@ -14,8 +14,7 @@
 //!  - TCP
 //!  - MPSC
 //!  - QUIC
-//!
-//! eventually a pure UDP implementation will follow
+//!  - UDP
 //!
 //! warning: don't mix protocol, using the TCP variant for actual UDP socket
 //! will result in dropped data  using UDP with a TCP socket will be a waste of
@@ -60,6 +59,7 @@ mod prio;
 mod quic;
 mod tcp;
 mod types;
+mod udp;
 mod util;
 
 pub use error::{InitProtocolError, ProtocolError};
@@ -71,6 +71,9 @@ pub use mpsc::{MpscMsg, MpscRecvProtocol, MpscSendProtocol};
 pub use quic::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
 pub use tcp::{TcpRecvProtocol, TcpSendProtocol};
 pub use types::{Bandwidth, Cid, Pid, Prio, Promises, Sid, HIGHEST_PRIO, VELOREN_NETWORK_VERSION};
+pub use udp::{
+    hello_datagram, is_hello_datagram, UdpRecvProtocol, UdpSendProtocol, MAX_DATAGRAM_SIZE,
+};
 
 ///use at own risk, might change any time, for internal benchmarks
 pub mod _internal {
@@ -78,6 +78,9 @@ impl OTMessage {
     }
 
     pub(crate) fn get_sid_len(&self) -> (Sid, u64) { (self.sid, self.original_length) }
+
+    /// returns if the first frame of this message was grabbed already
+    pub(crate) fn is_started(&self) -> bool { self.send_header }
 }
 
 impl ITMessage {
@@ -67,6 +67,7 @@ where
             | Promises::GUARANTEED_DELIVERY
             | Promises::COMPRESSED
             | Promises::ENCRYPTED /*assume a direct mpsc connection is secure*/
+            | Promises::LATEST_ONLY
     }
 }
 
@@ -14,7 +14,6 @@ use std::{
 struct StreamInfo {
     pub(crate) guaranteed_bandwidth: Bandwidth,
     pub(crate) prio: Prio,
-    #[allow(dead_code)]
     pub(crate) promises: Promises,
     pub(crate) messages: VecDeque<OTMessage>,
 }
@@ -67,11 +66,20 @@ impl PrioManager {
     pub fn is_empty(&self) -> bool { self.streams.is_empty() }
 
     pub fn add(&mut self, buffer: Bytes, mid: Mid, sid: Sid) {
-        self.streams
-            .get_mut(&sid)
-            .unwrap()
-            .messages
-            .push_back(OTMessage::new(buffer, mid, sid));
+        let stream = self.streams.get_mut(&sid).unwrap();
+        if stream.promises.contains(Promises::LATEST_ONLY) {
+            // messages which weren't started yet are superseded by this one
+            let metrics = &mut self.metrics;
+            stream.messages.retain(|msg| {
+                if msg.is_started() {
+                    return true;
+                }
+                let (sid, bytes) = msg.get_sid_len();
+                metrics.smsg_ob(sid, RemoveReason::Dropped, bytes);
+                false
+            });
+        }
+        stream.messages.push_back(OTMessage::new(buffer, mid, sid));
     }
 
     /// bandwidth might be extended, as for technical reasons
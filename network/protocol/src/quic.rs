@@ -127,6 +127,7 @@ where
             | Promises::GUARANTEED_DELIVERY
             | Promises::COMPRESSED
             | Promises::ENCRYPTED
+            | Promises::LATEST_ONLY
     }
 }
 
@@ -77,6 +77,7 @@ where
             | Promises::CONSISTENCY
             | Promises::GUARANTEED_DELIVERY
             | Promises::COMPRESSED
+            | Promises::LATEST_ONLY
     }
 }
 
@@ -476,6 +477,35 @@ mod tests {
         assert_eq!(event, e)
     }
 
+    #[tokio::test]
+    async fn latest_only_drops_superseded() {
+        let sid = Sid::new(1);
+        let [p1, p2] = tcp_bound(10000, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = ProtocolEvent::OpenStream {
+            sid,
+            prio: 3u8,
+            promises: Promises::ORDERED | Promises::LATEST_ONLY,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        s.send(event).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        for i in 0..3u8 {
+            s.send(ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![i; 100]),
+            })
+            .await
+            .unwrap();
+        }
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let e = r.recv().await.unwrap();
+        assert_eq!(e, ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(vec![2u8; 100]),
+        });
+    }
+
     #[tokio::test]
     async fn send_long_msg() {
         let mut metrics =
@@ -41,6 +41,12 @@ bitflags! {
         /// this will enable the internal encryption on this
         /// [`Stream`](crate::api::Stream)
         const ENCRYPTED = 0b00010000;
+        /// only the newest message on this stream is of interest, e.g. because every
+        /// message contains the complete state of something. Messages which weren't
+        /// sent yet when a newer one is sent are dropped, and protocols which may lose
+        /// messages drop those arriving after a newer one. Don't combine this with
+        /// `GUARANTEED_DELIVERY`.
+        const LATEST_ONLY = 0b00100000;
     }
 }
 
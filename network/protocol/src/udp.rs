@@ -1,37 +1,2231 @@
-// TODO: quick and dirty which activly waits for an ack!
-/*
-UDP protocol
-
-All Good Case:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> R
-S <--FINISHED-- R
-
-
-Delayed HEADER:
-S --HEADER-->
-S --DATA--> R // STORE IT
-  --HEADER--> R // apply left data and continue
-S --DATA--> R
-S <--FINISHED-- R
-
-
-NO HEADER:
-S --HEADER--> !
-S --DATA--> R // STORE IT
-S --DATA--> R // STORE IT
-S <--MISSING_HEADER-- R // SEND AFTER 10 ms after DATA1
-S --HEADER--> R
-S <--FINISHED-- R
-
-
-NO DATA:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> !
-S --STATUS--> R
-S <--MISSING_DATA -- R
-S --DATA--> R
-S <--FINISHED-- R
-*/
+//! UDP implementation of the protocol.
+//!
+//! Every datagram is a self contained packet which starts with a checksum, so
+//! that corrupted packets are dropped instead of being misread:
+//! ```ignore
+//! [checksum: u32][kind: u8][packet number: u64][frames...]
+//! ```
+//!
+//! Packets carrying frames are acknowledged by the remote side, in `ACK`
+//! frames which are added to its own packets. A packet is considered lost if
+//! it wasn't acknowledged within the retransmission timeout, or if
+//! `REORDER_THRESHOLD` packets sent after it were acknowledged. The frames of
+//! lost packets are only sent again if they are control frames (opening and
+//! closing streams, shutdown) or belong to a stream with
+//! `GUARANTEED_DELIVERY`. They are sent in new packets, so that every
+//! acknowledgement gives an unambiguous round trip time sample.
+//!
+//! Messages are split into fragments which fit into a single datagram. Every
+//! fragment carries its stream, the index of its message in that stream, the
+//! length of the message and its own offset, so fragments can be reassembled
+//! in any order. The index is used to deliver messages of `ORDERED` streams in
+//! order (holding back messages of reliable streams, and dropping stale
+//! messages of unreliable streams) and to drop duplicates. Unreliable
+//! `LATEST_ONLY` streams are handled like unreliable `ORDERED` streams, and
+//! additionally drop the unsent fragments of a message once a newer one is
+//! sent.
+//!
+//! The amount of unacknowledged data is limited by a congestion window, which
+//! grows with every acknowledgement (exponentially until the first loss, then
+//! linearly) and is halved when packets are lost, at most once per round trip.
+//!
+//! The send and receive side share the acknowledgement state, which is why
+//! they are created together by [`UdpSendProtocol::new`].
+//!
+//! The listening side only learns about a new remote side from its datagrams,
+//! but starts the handshake itself. So the connecting side sends `HELLO`
+//! packets, carrying the magic number, until it gets an answer; listeners
+//! should only set up a channel for those (see [`is_hello_datagram`]).
+//!
+//! NOTE: there is no acknowledgement state yet during the handshake, so its
+//! frames are sent multiple times instead. The first one is only sent once, so
+//! that hellos with a spoofed address can't be used to flood someone else. If
+//! it gets lost, the connecting side keeps sending hellos, so that the
+//! listener gives up and answers a later hello on a new channel. If all
+//! copies of a later handshake frame get lost, the connection attempt times
+//! out and has to be retried.
+//!
+//! [`UdpSendProtocol::new`]: crate::UdpSendProtocol::new
+
+use crate::{
+    error::ProtocolError,
+    event::ProtocolEvent,
+    frame::{ITFrame, InitFrame, OTFrame},
+    handshake::{ReliableDrain, ReliableSink},
+    message::ALLOC_BLOCK,
+    metrics::{ProtocolMetricCache, RemoveReason},
+    prio::PrioManager,
+    types::{Bandwidth, Mid, Promises, Sid, HIGHEST_PRIO, VELOREN_MAGIC_NUMBER},
+    RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
+};
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hashbrown::{HashMap, HashSet};
+use std::{
+    collections::{btree_map, BTreeMap, BTreeSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "trace_pedantic")]
+use tracing::trace;
+
+const PACKET_INIT: u8 = 1;
+const PACKET_DATA: u8 = 2;
+const PACKET_HELLO: u8 = 3;
+
+const FRAME_ACK: u8 = 1;
+const FRAME_PING: u8 = 2;
+const FRAME_CONTROL: u8 = 3;
+const FRAME_FRAGMENT: u8 = 4;
+
+/// Datagrams are kept below the minimal IPv6 MTU (1280 bytes) minus the IP and
+/// UDP headers, so they are never fragmented on the way.
+pub const MAX_DATAGRAM_SIZE: usize = 1200;
+// checksum, kind, packet number
+const PACKET_HEADER_SIZE: usize = 4 + 1 + 8;
+// kind, sid, message index, length, offset, data length
+const FRAGMENT_HEADER_SIZE: usize = 1 + 8 + 8 + 8 + 8 + 2;
+const MAX_FRAGMENT_DATA: usize = MAX_DATAGRAM_SIZE - PACKET_HEADER_SIZE - FRAGMENT_HEADER_SIZE;
+// kind, control sequence, data length
+const CONTROL_HEADER_SIZE: usize = 1 + 8 + 2;
+
+/// Received packet numbers remembered for acknowledgements
+const ACK_HISTORY: usize = 256;
+const MAX_ACK_RANGES: usize = 16;
+/// Copies sent of frames which can't be retransmitted
+const REDUNDANCY: usize = 3;
+/// Packets acknowledged after an unacknowledged one before it counts as lost
+const REORDER_THRESHOLD: u64 = 3;
+const INITIAL_RTO: Duration = Duration::from_millis(300);
+const MIN_RTO: Duration = Duration::from_millis(25);
+const MAX_RTO: Duration = Duration::from_secs(2);
+/// Without anything else to send, a ping is sent this often so that the
+/// remote side knows we are still there
+const KEEP_ALIVE: Duration = Duration::from_secs(1);
+const INITIAL_WINDOW: usize = 10 * MAX_DATAGRAM_SIZE;
+const MIN_WINDOW: usize = 2 * MAX_DATAGRAM_SIZE;
+const MAX_WINDOW: usize = 16 * 1024 * 1024;
+/// Incomplete messages kept per unreliable stream, older ones are dropped
+const MAX_INCOMPLETE_MESSAGES: usize = 64;
+/// Fragments kept for streams which weren't opened yet
+const MAX_EARLY_FRAGMENTS: usize = 1024;
+/// Data packets kept which arrive before our side of the handshake is done
+const MAX_STASHED_PACKETS: usize = 64;
+/// Hellos received before the handshake is given up on. The remote side sends
+/// them until it gets our first handshake frame.
+const MAX_UNANSWERED_HELLOS: usize = 8;
+/// Longest message accepted from the remote side, messages are buffered until
+/// they are complete
+const MAX_MESSAGE_LENGTH: u64 = ALLOC_BLOCK as u64;
+/// Counted for every buffered message on top of its data, so that many small
+/// messages can't exhaust memory while staying below the buffer limits
+const MESSAGE_COST: usize = 256;
+/// Counted for every buffered fragment on top of its data
+const FRAGMENT_COST: usize = 64;
+/// Bytes buffered per stream, see [`MESSAGE_COST`] and [`FRAGMENT_COST`]. For
+/// reliable streams the sender never exceeds it, so the channel is closed if
+/// it does. Unreliable streams drop their oldest incomplete messages instead.
+const MAX_STREAM_BUFFER: usize = 2 * MAX_MESSAGE_LENGTH as usize;
+/// Bytes buffered by all streams of a channel together
+const MAX_CHANNEL_BUFFER: usize = 4 * MAX_MESSAGE_LENGTH as usize;
+/// The sender keeps the messages of its reliable streams below this, except
+/// for the oldest one, which always fits into the rest of
+/// [`MAX_CHANNEL_BUFFER`] together with its bookkeeping costs
+const MAX_CHANNEL_SPAN: usize = MAX_CHANNEL_BUFFER - 2 * MAX_MESSAGE_LENGTH as usize;
+/// How far ahead of the oldest undelivered message a reliable stream may send.
+/// A sender within [`MAX_STREAM_BUFFER`] can't get further ahead than this.
+const MAX_MESSAGE_LEAD: u64 = (MAX_STREAM_BUFFER / MESSAGE_COST) as u64;
+/// Delivered message indices remembered per unordered unreliable stream to
+/// drop duplicates
+const DEDUP_WINDOW: usize = 1024;
+
+/// Acknowledgement state shared by the send and receive side
+#[derive(Debug, Default)]
+struct Shared {
+    /// Packets recently received from the remote side
+    received: BTreeSet<u64>,
+    /// Whether a packet which needs to be acknowledged was received since the
+    /// last acknowledgement was sent
+    ack_pending: bool,
+    /// Ranges of our packets acknowledged by the remote side
+    acked: Vec<(u64, u64)>,
+    /// Streams opened by us, which the remote side might send messages on
+    /// without opening them itself
+    opened: Vec<(Sid, Promises)>,
+    /// Streams closed by us
+    closed: Vec<Sid>,
+    /// Whether a handshake frame was received from the remote side, before
+    /// that it might be spoofing its address
+    remote_answered: bool,
+}
+
+impl Shared {
+    /// Ranges of received packets, from the newest to the oldest
+    fn ack_ranges(&self) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = vec![];
+        for &pn in self.received.iter().rev() {
+            match ranges.last_mut() {
+                Some((first, _)) if *first == pn + 1 => *first = pn,
+                _ => {
+                    if ranges.len() == MAX_ACK_RANGES {
+                        break;
+                    }
+                    ranges.push((pn, pn));
+                },
+            }
+        }
+        ranges
+    }
+}
+
+#[derive(Debug, Clone)]
+enum UdpFrame {
+    /// A [`OTFrame`] opening or closing a stream, or shutting down
+    Control { seq: u64, data: Bytes },
+    Fragment {
+        sid: Sid,
+        msg: u64,
+        length: u64,
+        offset: u64,
+        data: Bytes,
+    },
+}
+
+impl UdpFrame {
+    fn size(&self) -> usize {
+        match self {
+            Self::Control { data, .. } => CONTROL_HEADER_SIZE + data.len(),
+            Self::Fragment { data, .. } => FRAGMENT_HEADER_SIZE + data.len(),
+        }
+    }
+
+    fn write_bytes(&self, bytes: &mut BytesMut) {
+        match self {
+            Self::Control { seq, data } => {
+                bytes.put_u8(FRAME_CONTROL);
+                bytes.put_u64_le(*seq);
+                bytes.put_u16_le(data.len() as u16);
+                bytes.put_slice(data);
+            },
+            Self::Fragment {
+                sid,
+                msg,
+                length,
+                offset,
+                data,
+            } => {
+                bytes.put_u8(FRAME_FRAGMENT);
+                sid.to_bytes(bytes);
+                bytes.put_u64_le(*msg);
+                bytes.put_u64_le(*length);
+                bytes.put_u64_le(*offset);
+                bytes.put_u16_le(data.len() as u16);
+                bytes.put_slice(data);
+            },
+        }
+    }
+
+    fn is_fragment_of(&self, sid: Sid) -> bool {
+        matches!(self, Self::Fragment { sid: s, .. } if *s == sid)
+    }
+}
+
+/// Adler-32 checksum
+fn checksum(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    // largest n such that the sums can't overflow before the modulo
+    const NMAX: usize = 5552;
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(NMAX) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// Returns the kind and number of a packet, or `None` if it's corrupted
+fn read_packet_header(datagram: &mut BytesMut) -> Option<(u8, u64)> {
+    if datagram.len() < PACKET_HEADER_SIZE {
+        return None;
+    }
+    let expected = u32::from_le_bytes([datagram[0], datagram[1], datagram[2], datagram[3]]);
+    if checksum(&datagram[4..]) != expected {
+        return None;
+    }
+    datagram.advance(4);
+    Some((datagram.get_u8(), datagram.get_u64_le()))
+}
+
+/// A packet announcing the sender to a listening remote side, which answers
+/// by starting the handshake
+pub fn hello_datagram() -> BytesMut {
+    let mut datagram = BytesMut::with_capacity(PACKET_HEADER_SIZE + VELOREN_MAGIC_NUMBER.len());
+    datagram.put_u32_le(0);
+    datagram.put_u8(PACKET_HELLO);
+    datagram.put_u64_le(0);
+    datagram.put_slice(&VELOREN_MAGIC_NUMBER);
+    let checksum = checksum(&datagram[4..]);
+    datagram[..4].copy_from_slice(&checksum.to_le_bytes());
+    datagram
+}
+
+/// Whether `datagram` is an intact packet created by [`hello_datagram`]
+pub fn is_hello_datagram(datagram: &[u8]) -> bool {
+    let mut packet = BytesMut::from(datagram);
+    matches!(read_packet_header(&mut packet), Some((PACKET_HELLO, 0)))
+        && packet[..] == VELOREN_MAGIC_NUMBER
+}
+
+#[derive(Debug)]
+struct SendStream {
+    promises: Promises,
+    next_msg: u64,
+    /// Messages of reliable streams, starting with the oldest one which
+    /// wasn't completely acknowledged yet. The remote side might still buffer
+    /// all of them.
+    unacked: VecDeque<UnackedMessage>,
+    /// Costs of `unacked`, counted like the remote side counts its buffers
+    span: usize,
+}
+
+#[derive(Debug)]
+struct UnackedMessage {
+    mid: Mid,
+    length: u64,
+    /// End of the data sent so far, `None` if nothing was sent yet
+    sent: Option<u64>,
+    /// Fragments sent which weren't acknowledged yet
+    unacked_fragments: usize,
+    cost: usize,
+}
+
+impl UnackedMessage {
+    fn is_acked(&self) -> bool { self.sent == Some(self.length) && self.unacked_fragments == 0 }
+}
+
+/// A message which was taken from the [`PrioManager`] but not completely
+/// fragmented yet
+#[derive(Debug)]
+struct OutgoingMessage {
+    sid: Sid,
+    msg: u64,
+    length: u64,
+    offset: u64,
+}
+
+#[derive(Debug)]
+struct SentPacket {
+    time: Instant,
+    size: usize,
+    /// Frames to send again if this packet gets lost
+    frames: Vec<UdpFrame>,
+}
+
+#[derive(Debug)]
+struct Congestion {
+    /// Maximal bytes in flight
+    window: usize,
+    slow_start_threshold: usize,
+    smoothed_rtt: Option<Duration>,
+    rtt_variance: Duration,
+    /// Losses of packets sent before this one don't shrink the window again
+    recovery_start: u64,
+}
+
+impl Congestion {
+    fn new() -> Self {
+        Self {
+            window: INITIAL_WINDOW,
+            slow_start_threshold: usize::MAX,
+            smoothed_rtt: None,
+            rtt_variance: Duration::ZERO,
+            recovery_start: 0,
+        }
+    }
+
+    fn rto(&self) -> Duration {
+        match self.smoothed_rtt {
+            Some(srtt) => (srtt + (self.rtt_variance * 4).max(Duration::from_millis(10)))
+                .clamp(MIN_RTO, MAX_RTO),
+            None => INITIAL_RTO,
+        }
+    }
+
+    fn on_rtt_sample(&mut self, rtt: Duration) {
+        match self.smoothed_rtt {
+            Some(srtt) => {
+                let diff = if srtt > rtt { srtt - rtt } else { rtt - srtt };
+                self.rtt_variance = (self.rtt_variance * 3 + diff) / 4;
+                self.smoothed_rtt = Some((srtt * 7 + rtt) / 8);
+            },
+            None => {
+                self.smoothed_rtt = Some(rtt);
+                self.rtt_variance = rtt / 2;
+            },
+        }
+    }
+
+    fn on_ack(&mut self, size: usize) {
+        if self.window < self.slow_start_threshold {
+            self.window += size;
+        } else {
+            self.window += MAX_DATAGRAM_SIZE * size / self.window;
+        }
+        self.window = self.window.min(MAX_WINDOW);
+    }
+
+    fn on_loss(&mut self, pn: u64, next_packet: u64) {
+        if pn >= self.recovery_start {
+            self.window = (self.window / 2).max(MIN_WINDOW);
+            self.slow_start_threshold = self.window;
+            self.recovery_start = next_packet;
+        }
+    }
+}
+
+/// UDP implementation of [`SendProtocol`]
+///
+/// [`SendProtocol`]: crate::SendProtocol
+#[derive(Debug)]
+pub struct UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    buffer: BytesMut,
+    store: PrioManager,
+    next_mid: Mid,
+    streams: HashMap<Sid, SendStream>,
+    outgoing: HashMap<Mid, OutgoingMessage>,
+    /// Frames waiting for space in the congestion window, lost frames are
+    /// added to the front
+    pending: VecDeque<UdpFrame>,
+    in_flight: BTreeMap<u64, SentPacket>,
+    bytes_in_flight: usize,
+    /// Sum of the spans of all streams
+    span: usize,
+    next_packet: u64,
+    largest_acked: Option<u64>,
+    next_control: u64,
+    next_init: u64,
+    congestion: Congestion,
+    closing_streams: Vec<Sid>,
+    notify_closing_streams: Vec<Sid>,
+    pending_shutdown: bool,
+    last_sent: Instant,
+    shared: Arc<Mutex<Shared>>,
+    drain: D,
+    metrics: ProtocolMetricCache,
+}
+
+#[derive(Debug)]
+struct IncomingMessage {
+    /// Received fragments by their offset, they are only joined once the
+    /// message is complete, as the length can't be trusted
+    fragments: BTreeMap<u64, Bytes>,
+    length: u64,
+    received: u64,
+}
+
+impl IncomingMessage {
+    fn cost(&self) -> usize {
+        MESSAGE_COST + self.received as usize + self.fragments.len() * FRAGMENT_COST
+    }
+
+    /// Joins the fragments, `None` if they overlap or leave gaps
+    fn assemble(self) -> Option<Bytes> {
+        let mut data = BytesMut::with_capacity(self.length as usize);
+        for (offset, fragment) in self.fragments {
+            if offset != data.len() as u64 {
+                return None;
+            }
+            data.extend_from_slice(&fragment);
+        }
+        (data.len() as u64 == self.length).then(|| data.freeze())
+    }
+}
+
+fn completed_cost(data: &Option<Bytes>) -> usize {
+    MESSAGE_COST + data.as_ref().map_or(0, Bytes::len)
+}
+
+#[derive(Debug)]
+struct RecvStream {
+    promises: Promises,
+    /// All messages before this one were delivered (or are stale, for ordered
+    /// unreliable streams)
+    next_msg: u64,
+    /// Messages after `next_msg` which were completed. Ordered reliable
+    /// streams hold them back here, other streams only remember that they
+    /// were delivered.
+    completed: BTreeMap<u64, Option<Bytes>>,
+    incoming: BTreeMap<u64, IncomingMessage>,
+    /// Costs of `completed` and `incoming`
+    buffered: usize,
+}
+
+impl RecvStream {
+    fn new(promises: Promises) -> Self {
+        Self {
+            promises,
+            next_msg: 0,
+            completed: BTreeMap::new(),
+            incoming: BTreeMap::new(),
+            buffered: 0,
+        }
+    }
+
+    fn is_done(&self, msg: u64) -> bool { msg < self.next_msg || self.completed.contains_key(&msg) }
+
+    /// Returns the messages which can be delivered now that `msg` is complete,
+    /// and the incomplete messages which became stale
+    fn complete(&mut self, msg: u64, data: Bytes) -> (Vec<Bytes>, Vec<IncomingMessage>) {
+        let ordered = self.promises.contains(Promises::ORDERED);
+        let reliable = self.promises.contains(Promises::GUARANTEED_DELIVERY);
+        let mut deliver = vec![];
+        let mut stale = vec![];
+        if ordered && reliable {
+            self.insert_completed(msg, Some(data));
+            while let Some(Some(data)) = self.remove_completed(self.next_msg) {
+                deliver.push(data);
+                self.next_msg += 1;
+            }
+        } else if ordered || self.promises.contains(Promises::LATEST_ONLY) {
+            deliver.push(data);
+            self.next_msg = msg + 1;
+            stale = self.remove_stale();
+        } else {
+            deliver.push(data);
+            self.insert_completed(msg, None);
+            if !reliable && self.completed.len() > DEDUP_WINDOW {
+                // Give up on the oldest missing messages
+                if let Some(&oldest) = self.completed.keys().next() {
+                    self.remove_completed(oldest);
+                    self.next_msg = oldest + 1;
+                }
+                stale = self.remove_stale();
+            }
+            while self.remove_completed(self.next_msg).is_some() {
+                self.next_msg += 1;
+            }
+        }
+        (deliver, stale)
+    }
+
+    fn insert_completed(&mut self, msg: u64, data: Option<Bytes>) {
+        self.buffered += completed_cost(&data);
+        self.completed.insert(msg, data);
+    }
+
+    fn remove_completed(&mut self, msg: u64) -> Option<Option<Bytes>> {
+        let data = self.completed.remove(&msg)?;
+        self.buffered -= completed_cost(&data);
+        Some(data)
+    }
+
+    fn remove_incoming(&mut self, msg: u64) -> Option<IncomingMessage> {
+        let message = self.incoming.remove(&msg)?;
+        self.buffered -= message.cost();
+        Some(message)
+    }
+
+    fn remove_oldest_incoming(&mut self) -> Option<IncomingMessage> {
+        let (_, message) = self.incoming.pop_first()?;
+        self.buffered -= message.cost();
+        Some(message)
+    }
+
+    /// Removes the incomplete messages before `next_msg`
+    fn remove_stale(&mut self) -> Vec<IncomingMessage> {
+        let mut stale = vec![];
+        while let Some(entry) = self.incoming.first_entry() {
+            if *entry.key() >= self.next_msg {
+                break;
+            }
+            let message = entry.remove();
+            self.buffered -= message.cost();
+            stale.push(message);
+        }
+        stale
+    }
+}
+
+#[derive(Debug)]
+struct Fragment {
+    sid: Sid,
+    msg: u64,
+    length: u64,
+    offset: u64,
+    data: BytesMut,
+}
+
+/// UDP implementation of [`RecvProtocol`]
+///
+/// [`RecvProtocol`]: crate::RecvProtocol
+#[derive(Debug)]
+pub struct UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    streams: HashMap<Sid, RecvStream>,
+    /// Sum of the buffers of all streams
+    buffered: usize,
+    next_control: u64,
+    /// Control frames received out of order
+    controls: BTreeMap<u64, BytesMut>,
+    early_fragments: Vec<Fragment>,
+    events: VecDeque<ProtocolEvent>,
+    next_init: u64,
+    /// Packets received during the handshake
+    stashed: VecDeque<BytesMut>,
+    /// Hellos received during the handshake
+    hellos: usize,
+    shared: Arc<Mutex<Shared>>,
+    sink: S,
+    metrics: ProtocolMetricCache,
+}
+
+impl<D> UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    /// Creates the send and receive side of a UDP channel. `drain` and `sink`
+    /// must send and receive single datagrams.
+    pub fn new<S>(drain: D, sink: S, metrics: ProtocolMetricCache) -> (Self, UdpRecvProtocol<S>)
+    where
+        S: UnreliableSink<DataFormat = BytesMut>,
+    {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let send = Self {
+            buffer: BytesMut::with_capacity(MAX_DATAGRAM_SIZE),
+            store: PrioManager::new(metrics.clone()),
+            next_mid: 0u64,
+            streams: HashMap::new(),
+            outgoing: HashMap::new(),
+            pending: VecDeque::new(),
+            in_flight: BTreeMap::new(),
+            bytes_in_flight: 0,
+            span: 0,
+            next_packet: 0,
+            largest_acked: None,
+            next_control: 0,
+            next_init: 0,
+            congestion: Congestion::new(),
+            closing_streams: vec![],
+            notify_closing_streams: vec![],
+            pending_shutdown: false,
+            last_sent: Instant::now(),
+            shared: Arc::clone(&shared),
+            drain,
+            metrics: metrics.clone(),
+        };
+        let recv = UdpRecvProtocol {
+            streams: HashMap::new(),
+            buffered: 0,
+            next_control: 0,
+            controls: BTreeMap::new(),
+            early_fragments: vec![],
+            events: VecDeque::new(),
+            next_init: 0,
+            stashed: VecDeque::new(),
+            hellos: 0,
+            shared,
+            sink,
+            metrics,
+        };
+        (send, recv)
+    }
+
+    /// returns all promises that this Protocol can take care of
+    /// If you open a Stream anyway, unsupported promises are ignored.
+    pub fn supported_promises() -> Promises {
+        Promises::ORDERED
+            | Promises::CONSISTENCY
+            | Promises::GUARANTEED_DELIVERY
+            | Promises::COMPRESSED
+            | Promises::LATEST_ONLY
+    }
+
+    fn open_stream(&mut self, sid: Sid, prio: u8, promises: Promises, guaranteed_bandwidth: u64) {
+        self.store
+            .open_stream(sid, prio, promises, guaranteed_bandwidth);
+        self.streams.insert(sid, SendStream {
+            promises,
+            next_msg: 0,
+            unacked: VecDeque::new(),
+            span: 0,
+        });
+    }
+
+    fn is_reliable(&self, frame: &UdpFrame) -> bool {
+        match frame {
+            UdpFrame::Control { .. } => true,
+            UdpFrame::Fragment { sid, .. } => self.streams.get(sid).map_or(false, |s| {
+                s.promises.contains(Promises::GUARANTEED_DELIVERY)
+            }),
+        }
+    }
+
+    /// Whether parts of messages of the stream still wait to be sent or
+    /// acknowledged
+    fn in_transit(&self, sid: Sid) -> bool {
+        self.outgoing.values().any(|m| m.sid == sid)
+            || self.pending.iter().any(|f| f.is_fragment_of(sid))
+            || self
+                .in_flight
+                .values()
+                .any(|p| p.frames.iter().any(|f| f.is_fragment_of(sid)))
+    }
+
+    fn can_shutdown(&self) -> bool {
+        self.store.is_empty()
+            && self.pending.is_empty()
+            && self.in_flight.values().all(|p| p.frames.is_empty())
+    }
+
+    fn take_ack(&mut self) -> Option<Vec<(u64, u64)>> {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.ack_pending {
+            shared.ack_pending = false;
+            Some(shared.ack_ranges())
+        } else {
+            None
+        }
+    }
+
+    fn start_packet(&mut self, kind: u8, number: u64) {
+        self.buffer.put_u32_le(0); // checksum, filled in by `send_buffer`
+        self.buffer.put_u8(kind);
+        self.buffer.put_u64_le(number);
+    }
+
+    fn write_ack(&mut self, ranges: &[(u64, u64)]) {
+        self.buffer.put_u8(FRAME_ACK);
+        self.buffer.put_u8(ranges.len() as u8);
+        for (first, last) in ranges {
+            self.buffer.put_u64_le(*first);
+            self.buffer.put_u64_le(*last);
+        }
+    }
+
+    async fn send_buffer(&mut self) -> Result<usize, ProtocolError<D::CustomErr>> {
+        let checksum = checksum(&self.buffer[4..]);
+        self.buffer[..4].copy_from_slice(&checksum.to_le_bytes());
+        let size = self.buffer.len();
+        self.drain.send(self.buffer.split()).await?;
+        self.last_sent = Instant::now();
+        Ok(size)
+    }
+
+    /// Sends a packet with the given frames, and ACK frame, right away
+    async fn send_packet(
+        &mut self,
+        ack: Option<Vec<(u64, u64)>>,
+        frames: &[UdpFrame],
+        ping: bool,
+    ) -> Result<(), ProtocolError<D::CustomErr>> {
+        let pn = self.next_packet;
+        self.next_packet += 1;
+        self.start_packet(PACKET_DATA, pn);
+        if let Some(ranges) = &ack {
+            self.write_ack(ranges);
+        }
+        if ping {
+            self.buffer.put_u8(FRAME_PING);
+        }
+        for frame in frames {
+            frame.write_bytes(&mut self.buffer);
+        }
+        let size = self.send_buffer().await?;
+        if ping || !frames.is_empty() {
+            let frames = frames
+                .iter()
+                .filter(|f| self.is_reliable(f))
+                .cloned()
+                .collect();
+            self.track(pn, size, frames);
+        }
+        Ok(())
+    }
+
+    fn track(&mut self, pn: u64, size: usize, frames: Vec<UdpFrame>) {
+        self.in_flight.insert(pn, SentPacket {
+            time: Instant::now(),
+            size,
+            frames,
+        });
+        self.bytes_in_flight += size;
+    }
+
+    /// Sends a control frame right away, as `flush` might not be called
+    /// anymore (e.g. after shutting down)
+    async fn send_control(
+        &mut self,
+        frame: OTFrame,
+        copies: usize,
+    ) -> Result<(), ProtocolError<D::CustomErr>> {
+        let mut data = BytesMut::new();
+        frame.write_bytes(&mut data);
+        let control = UdpFrame::Control {
+            seq: self.next_control,
+            data: data.freeze(),
+        };
+        self.next_control += 1;
+        let ack = self.take_ack();
+        self.send_packet(ack, &[control.clone()], false).await?;
+        for _ in 1..copies {
+            // Only the first copy is retransmitted when lost
+            let pn = self.next_packet;
+            self.next_packet += 1;
+            self.start_packet(PACKET_DATA, pn);
+            control.write_bytes(&mut self.buffer);
+            let size = self.send_buffer().await?;
+            self.track(pn, size, vec![]);
+        }
+        Ok(())
+    }
+
+    fn process_acks(&mut self) {
+        let acked = std::mem::take(&mut self.shared.lock().unwrap().acked);
+        let mut newest = None;
+        for (first, last) in acked {
+            let pns = self
+                .in_flight
+                .range(first..=last)
+                .map(|(pn, _)| *pn)
+                .collect::<Vec<_>>();
+            for pn in pns {
+                if let Some(packet) = self.in_flight.remove(&pn) {
+                    self.bytes_in_flight -= packet.size;
+                    self.congestion.on_ack(packet.size);
+                    if newest.map_or(true, |(newest, _)| pn > newest) {
+                        newest = Some((pn, packet.time));
+                    }
+                    for frame in &packet.frames {
+                        self.fragment_acked(frame);
+                    }
+                }
+            }
+        }
+        if let Some((pn, time)) = newest {
+            self.congestion.on_rtt_sample(time.elapsed());
+            self.largest_acked = self.largest_acked.max(Some(pn));
+        }
+    }
+
+    /// Forgets the oldest messages of the stream of an acknowledged fragment
+    /// once the remote side can't buffer them anymore
+    fn fragment_acked(&mut self, frame: &UdpFrame) {
+        let UdpFrame::Fragment { sid, msg, .. } = frame else {
+            return;
+        };
+        let Some(stream) = self.streams.get_mut(sid) else {
+            return;
+        };
+        let first = stream.next_msg - stream.unacked.len() as u64;
+        if let Some(message) = msg
+            .checked_sub(first)
+            .and_then(|i| stream.unacked.get_mut(i as usize))
+        {
+            message.unacked_fragments -= 1;
+        }
+        while stream
+            .unacked
+            .front()
+            .map_or(false, UnackedMessage::is_acked)
+        {
+            let message = stream.unacked.pop_front().unwrap();
+            stream.span -= message.cost;
+            self.span -= message.cost;
+        }
+    }
+
+    /// Whether the remote side can buffer `frame` if it's sent now, counts it
+    /// as sent if so. Fragments sent before and fragments of the oldest
+    /// message of all reliable streams are always admitted, so that the
+    /// streams can't get stuck.
+    fn admit(&mut self, frame: &UdpFrame, oldest: Option<Mid>) -> bool {
+        let UdpFrame::Fragment {
+            sid,
+            msg,
+            offset,
+            data,
+            ..
+        } = frame
+        else {
+            return true;
+        };
+        let Some(stream) = self.streams.get_mut(sid) else {
+            return true;
+        };
+        let first = stream.next_msg - stream.unacked.len() as u64;
+        let Some(message) = msg
+            .checked_sub(first)
+            .and_then(|i| stream.unacked.get_mut(i as usize))
+        else {
+            return true;
+        };
+        let end = offset + data.len() as u64;
+        if message.sent.map_or(false, |sent| end <= sent) {
+            return true;
+        }
+        let cost = (end - message.sent.unwrap_or(0)) as usize
+            + FRAGMENT_COST
+            + if message.sent.is_none() {
+                MESSAGE_COST
+            } else {
+                0
+            };
+        if Some(message.mid) != oldest
+            && (stream.span + cost > MAX_STREAM_BUFFER || self.span + cost > MAX_CHANNEL_SPAN)
+        {
+            return false;
+        }
+        message.sent = Some(end);
+        message.unacked_fragments += 1;
+        message.cost += cost;
+        stream.span += cost;
+        self.span += cost;
+        true
+    }
+
+    fn detect_losses(&mut self, now: Instant) {
+        let rto = self.congestion.rto();
+        let largest_acked = self.largest_acked;
+        let lost = self
+            .in_flight
+            .iter()
+            .filter(|(pn, packet)| {
+                largest_acked.map_or(false, |l| l >= **pn + REORDER_THRESHOLD)
+                    || now.duration_since(packet.time) > rto
+            })
+            .map(|(pn, _)| *pn)
+            .collect::<Vec<_>>();
+        let mut lost_frames = vec![];
+        for pn in lost {
+            if let Some(packet) = self.in_flight.remove(&pn) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?pn, "packet lost");
+                self.bytes_in_flight -= packet.size;
+                self.congestion.on_loss(pn, self.next_packet);
+                lost_frames.extend(packet.frames);
+            }
+        }
+        for frame in lost_frames.into_iter().rev() {
+            self.pending.push_front(frame);
+        }
+    }
+
+    /// Splits the frames grabbed from the [`PrioManager`] into fragments
+    fn fragment(&mut self, frame: OTFrame) {
+        match frame {
+            OTFrame::DataHeader { mid, sid, length } => {
+                let (msg, latest_only) = match self.streams.get_mut(&sid) {
+                    Some(stream) => {
+                        stream.next_msg += 1;
+                        if stream.promises.contains(Promises::GUARANTEED_DELIVERY) {
+                            stream.unacked.push_back(UnackedMessage {
+                                mid,
+                                length,
+                                sent: None,
+                                unacked_fragments: 0,
+                                cost: 0,
+                            });
+                        }
+                        (
+                            stream.next_msg - 1,
+                            stream.promises.contains(Promises::LATEST_ONLY)
+                                && !stream.promises.contains(Promises::GUARANTEED_DELIVERY),
+                        )
+                    },
+                    None => return,
+                };
+                if latest_only {
+                    // the receiver only completes the newest message anyway
+                    self.pending.retain(|f| !f.is_fragment_of(sid));
+                    self.outgoing.retain(|_, m| m.sid != sid);
+                }
+                if length == 0 {
+                    self.pending.push_back(UdpFrame::Fragment {
+                        sid,
+                        msg,
+                        length,
+                        offset: 0,
+                        data: Bytes::new(),
+                    });
+                } else {
+                    self.outgoing.insert(mid, OutgoingMessage {
+                        sid,
+                        msg,
+                        length,
+                        offset: 0,
+                    });
+                }
+            },
+            OTFrame::Data { mid, mut data } => {
+                if let Some(message) = self.outgoing.get_mut(&mid) {
+                    while !data.is_empty() {
+                        let chunk = data.split_to(data.len().min(MAX_FRAGMENT_DATA));
+                        let len = chunk.len() as u64;
+                        self.pending.push_back(UdpFrame::Fragment {
+                            sid: message.sid,
+                            msg: message.msg,
+                            length: message.length,
+                            offset: message.offset,
+                            data: chunk,
+                        });
+                        message.offset += len;
+                    }
+                    if message.offset >= message.length {
+                        self.outgoing.remove(&mid);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Index of the next pending frame which may be sent, starting at
+    /// `start`. Streams are added to `blocked` once a fragment of them isn't
+    /// admitted, so that their later fragments are held back as well.
+    fn next_admitted(
+        &mut self,
+        start: usize,
+        oldest: Option<Mid>,
+        blocked: &mut HashSet<Sid>,
+    ) -> Option<usize> {
+        (start..self.pending.len()).find(|&i| {
+            let frame = self.pending[i].clone();
+            match &frame {
+                UdpFrame::Fragment { sid, .. } if blocked.contains(sid) => false,
+                UdpFrame::Fragment { sid, .. } if !self.admit(&frame, oldest) => {
+                    blocked.insert(*sid);
+                    false
+                },
+                _ => true,
+            }
+        })
+    }
+
+    /// Sends pending frames as far as the congestion window and the buffers of
+    /// the remote side allow, returns the number and bytes of data frames sent
+    async fn send_pending(&mut self) -> Result<(u64, usize), ProtocolError<D::CustomErr>> {
+        let mut data_frames = 0;
+        let mut data_bandwidth = 0;
+        let oldest = self
+            .streams
+            .values()
+            .filter_map(|s| s.unacked.front())
+            .map(|m| m.mid)
+            .min();
+        let mut blocked = HashSet::new();
+        // All pending frames before it are held back
+        let mut next = self.next_admitted(0, oldest, &mut blocked);
+        loop {
+            let ack = self.take_ack();
+            let window_full =
+                self.bytes_in_flight > 0 && self.bytes_in_flight >= self.congestion.window;
+            if next.is_none() || window_full {
+                if ack.is_some() {
+                    self.send_packet(ack, &[], false).await?;
+                }
+                break;
+            }
+            let pn = self.next_packet;
+            self.next_packet += 1;
+            self.start_packet(PACKET_DATA, pn);
+            if let Some(ranges) = &ack {
+                self.write_ack(ranges);
+            }
+            let mut ack_eliciting = false;
+            let mut reliable = vec![];
+            while let Some(i) = next {
+                if self.buffer.len() + self.pending[i].size() > MAX_DATAGRAM_SIZE {
+                    break;
+                }
+                let frame = self.pending.remove(i).unwrap();
+                next = self.next_admitted(i, oldest, &mut blocked);
+                frame.write_bytes(&mut self.buffer);
+                ack_eliciting = true;
+                if let UdpFrame::Fragment { data, .. } = &frame {
+                    data_frames += 1;
+                    data_bandwidth += data.len();
+                }
+                if self.is_reliable(&frame) {
+                    reliable.push(frame);
+                }
+            }
+            let size = self.send_buffer().await?;
+            if ack_eliciting {
+                self.track(pn, size, reliable);
+            }
+        }
+        Ok((data_frames, data_bandwidth))
+    }
+}
+
+impl<S> UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    /// Applies the streams opened and closed by our send side
+    fn sync_streams(&mut self) {
+        let (opened, closed) = {
+            let mut shared = self.shared.lock().unwrap();
+            (
+                std::mem::take(&mut shared.opened),
+                std::mem::take(&mut shared.closed),
+            )
+        };
+        for (sid, promises) in opened {
+            self.streams
+                .entry(sid)
+                .or_insert_with(|| RecvStream::new(promises));
+        }
+        for sid in closed {
+            if let Some(stream) = self.streams.remove(&sid) {
+                self.buffered -= stream.buffered;
+                for (_, message) in stream.incoming {
+                    self.metrics
+                        .rmsg_ob(sid, RemoveReason::Dropped, message.length);
+                }
+            }
+        }
+    }
+
+    fn handle_datagram(
+        &mut self,
+        mut datagram: BytesMut,
+    ) -> Result<(), ProtocolError<S::CustomErr>> {
+        self.sync_streams();
+        match read_packet_header(&mut datagram) {
+            // Corrupted
+            None => Ok(()),
+            // Late copy of a handshake frame or hello
+            Some((PACKET_INIT | PACKET_HELLO, _)) => Ok(()),
+            Some((PACKET_DATA, pn)) => self.handle_packet(pn, datagram),
+            Some(_) => Err(ProtocolError::Violated),
+        }
+    }
+
+    fn handle_packet(
+        &mut self,
+        pn: u64,
+        mut bytes: BytesMut,
+    ) -> Result<(), ProtocolError<S::CustomErr>> {
+        let mut ack_eliciting = false;
+        while bytes.has_remaining() {
+            match bytes.get_u8() {
+                FRAME_ACK => {
+                    if bytes.remaining() < 1 {
+                        return Err(ProtocolError::Violated);
+                    }
+                    let count = bytes.get_u8() as usize;
+                    if bytes.remaining() < count * 16 {
+                        return Err(ProtocolError::Violated);
+                    }
+                    let mut ranges = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let (first, last) = (bytes.get_u64_le(), bytes.get_u64_le());
+                        if first > last {
+                            return Err(ProtocolError::Violated);
+                        }
+                        ranges.push((first, last));
+                    }
+                    self.shared.lock().unwrap().acked.extend(ranges);
+                },
+                FRAME_PING => ack_eliciting = true,
+                FRAME_CONTROL => {
+                    if bytes.remaining() < CONTROL_HEADER_SIZE - 1 {
+                        return Err(ProtocolError::Violated);
+                    }
+                    let seq = bytes.get_u64_le();
+                    let len = bytes.get_u16_le() as usize;
+                    if bytes.remaining() < len {
+                        return Err(ProtocolError::Violated);
+                    }
+                    let data = bytes.split_to(len);
+                    ack_eliciting = true;
+                    if seq >= self.next_control {
+                        self.controls.insert(seq, data);
+                    }
+                },
+                FRAME_FRAGMENT => {
+                    if bytes.remaining() < FRAGMENT_HEADER_SIZE - 1 {
+                        return Err(ProtocolError::Violated);
+                    }
+                    let sid = Sid::from_bytes(&mut bytes);
+                    let msg = bytes.get_u64_le();
+                    let length = bytes.get_u64_le();
+                    let offset = bytes.get_u64_le();
+                    let len = bytes.get_u16_le() as usize;
+                    if bytes.remaining() < len {
+                        return Err(ProtocolError::Violated);
+                    }
+                    let data = bytes.split_to(len);
+                    ack_eliciting = true;
+                    self.handle_fragment(Fragment {
+                        sid,
+                        msg,
+                        length,
+                        offset,
+                        data,
+                    })?;
+                },
+                _ => return Err(ProtocolError::Violated),
+            }
+        }
+
+        {
+            let mut shared = self.shared.lock().unwrap();
+            shared.received.insert(pn);
+            while shared.received.len() > ACK_HISTORY {
+                shared.received.pop_first();
+            }
+            shared.ack_pending |= ack_eliciting;
+        }
+
+        while let Some(data) = self.controls.remove(&self.next_control) {
+            self.next_control += 1;
+            self.handle_control(data)?;
+        }
+        Ok(())
+    }
+
+    fn handle_control(&mut self, mut data: BytesMut) -> Result<(), ProtocolError<S::CustomErr>> {
+        let frame = match ITFrame::read_frame(&mut data) {
+            Ok(Some(frame)) => frame,
+            _ => return Err(ProtocolError::Violated),
+        };
+        #[cfg(feature = "trace_pedantic")]
+        trace!(?frame, "recv");
+        match frame {
+            ITFrame::Shutdown => self.events.push_back(ProtocolEvent::Shutdown),
+            ITFrame::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => {
+                if let Some(stream) = self.streams.insert(sid, RecvStream::new(promises)) {
+                    self.buffered -= stream.buffered;
+                }
+                self.events.push_back(ProtocolEvent::OpenStream {
+                    sid,
+                    prio: prio.min(HIGHEST_PRIO),
+                    promises,
+                    guaranteed_bandwidth,
+                });
+                let (early, other) = std::mem::take(&mut self.early_fragments)
+                    .into_iter()
+                    .partition(|f| f.sid == sid);
+                self.early_fragments = other;
+                for fragment in early {
+                    self.handle_fragment(fragment)?;
+                }
+            },
+            ITFrame::CloseStream { sid } => {
+                if let Some(stream) = self.streams.remove(&sid) {
+                    self.buffered -= stream.buffered;
+                    for (_, message) in stream.incoming {
+                        self.metrics
+                            .rmsg_ob(sid, RemoveReason::Dropped, message.length);
+                    }
+                }
+                self.events.push_back(ProtocolEvent::CloseStream { sid });
+            },
+            _ => return Err(ProtocolError::Violated),
+        }
+        Ok(())
+    }
+
+    fn handle_fragment(&mut self, fragment: Fragment) -> Result<(), ProtocolError<S::CustomErr>> {
+        let Fragment {
+            sid,
+            msg,
+            length,
+            offset,
+            data,
+        } = fragment;
+        if length > MAX_MESSAGE_LENGTH {
+            return Err(ProtocolError::Violated);
+        }
+        let stream = match self.streams.get_mut(&sid) {
+            Some(stream) => stream,
+            None => {
+                if self.early_fragments.len() < MAX_EARLY_FRAGMENTS {
+                    self.early_fragments.push(Fragment {
+                        sid,
+                        msg,
+                        length,
+                        offset,
+                        data,
+                    });
+                }
+                return Ok(());
+            },
+        };
+        offset
+            .checked_add(data.len() as u64)
+            .filter(|end| *end <= length)
+            .ok_or(ProtocolError::Violated)?;
+        if stream.is_done(msg) {
+            return Ok(());
+        }
+        let reliable = stream.promises.contains(Promises::GUARANTEED_DELIVERY);
+        if reliable && msg >= stream.next_msg + MAX_MESSAGE_LEAD {
+            return Err(ProtocolError::Violated);
+        }
+        self.metrics.rdata_frames_b(data.len() as u64);
+
+        let stream_buffered = stream.buffered;
+        let metrics = &mut self.metrics;
+        let buffered = &mut stream.buffered;
+        let message = stream.incoming.entry(msg).or_insert_with(|| {
+            metrics.rmsg_ib(sid, length);
+            *buffered += MESSAGE_COST;
+            IncomingMessage {
+                fragments: BTreeMap::new(),
+                length,
+                received: 0,
+            }
+        });
+        if message.length != length {
+            return Err(ProtocolError::Violated);
+        }
+        if let btree_map::Entry::Vacant(entry) = message.fragments.entry(offset) {
+            // Copied, so that the rest of the datagram isn't kept alive
+            entry.insert(Bytes::copy_from_slice(&data));
+            message.received += data.len() as u64;
+            *buffered += data.len() + FRAGMENT_COST;
+        }
+
+        if message.received >= message.length {
+            let message = stream.remove_incoming(msg).ok_or(ProtocolError::Violated)?;
+            let data = message.assemble().ok_or(ProtocolError::Violated)?;
+            self.metrics.rmsg_ob(sid, RemoveReason::Finished, length);
+            let (deliver, stale) = stream.complete(msg, data);
+            for data in deliver {
+                self.events.push_back(ProtocolEvent::Message { data, sid });
+            }
+            for message in stale {
+                self.metrics
+                    .rmsg_ob(sid, RemoveReason::Dropped, message.length);
+            }
+        }
+        if !reliable {
+            while stream.incoming.len() > MAX_INCOMPLETE_MESSAGES
+                || stream.buffered > MAX_STREAM_BUFFER
+            {
+                match stream.remove_oldest_incoming() {
+                    Some(message) => {
+                        self.metrics
+                            .rmsg_ob(sid, RemoveReason::Dropped, message.length)
+                    },
+                    None => break,
+                }
+            }
+        } else if stream.buffered > MAX_STREAM_BUFFER {
+            return Err(ProtocolError::Violated);
+        }
+        self.buffered = self.buffered - stream_buffered + stream.buffered;
+        if self.buffered > MAX_CHANNEL_BUFFER {
+            self.shrink_unreliable();
+            if self.buffered > MAX_CHANNEL_BUFFER {
+                return Err(ProtocolError::Violated);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops incomplete messages of unreliable streams until the channel
+    /// doesn't buffer more than [`MAX_CHANNEL_BUFFER`] anymore
+    fn shrink_unreliable(&mut self) {
+        for (sid, stream) in self.streams.iter_mut() {
+            if stream.promises.contains(Promises::GUARANTEED_DELIVERY) {
+                continue;
+            }
+            while self.buffered > MAX_CHANNEL_BUFFER {
+                let stream_buffered = stream.buffered;
+                match stream.remove_oldest_incoming() {
+                    Some(message) => {
+                        self.buffered -= stream_buffered - stream.buffered;
+                        self.metrics
+                            .rmsg_ob(*sid, RemoveReason::Dropped, message.length);
+                    },
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<D> SendProtocol for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    type CustomErr = D::CustomErr;
+
+    fn notify_from_recv(&mut self, event: ProtocolEvent) {
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => self.open_stream(sid, prio, promises, guaranteed_bandwidth),
+            ProtocolEvent::CloseStream { sid } => {
+                if !self.in_transit(sid) && self.store.try_close_stream(sid) {
+                    self.streams.remove(&sid);
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back notify close stream");
+                    self.notify_closing_streams.push(sid);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    async fn send(&mut self, event: ProtocolEvent) -> Result<(), ProtocolError<Self::CustomErr>> {
+        #[cfg(feature = "trace_pedantic")]
+        trace!(?event, "send");
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => {
+                self.open_stream(sid, prio, promises, guaranteed_bandwidth);
+                self.shared.lock().unwrap().opened.push((sid, promises));
+                self.send_control(event.to_frame(), 1).await?;
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                if !self.in_transit(sid) && self.store.try_close_stream(sid) {
+                    self.streams.remove(&sid);
+                    self.shared.lock().unwrap().closed.push(sid);
+                    self.send_control(event.to_frame(), 1).await?;
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back close stream");
+                    self.closing_streams.push(sid);
+                }
+            },
+            ProtocolEvent::Shutdown => {
+                if self.can_shutdown() {
+                    self.send_control(event.to_frame(), REDUNDANCY).await?;
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!("hold back shutdown");
+                    self.pending_shutdown = true;
+                }
+            },
+            ProtocolEvent::Message { data, sid } => {
+                self.metrics.smsg_ib(sid, data.len() as u64);
+                self.store.add(data, self.next_mid, sid);
+                self.next_mid += 1;
+            },
+        }
+        Ok(())
+    }
+
+    async fn flush(
+        &mut self,
+        bandwidth: Bandwidth,
+        dt: Duration,
+    ) -> Result</* actual */ Bandwidth, ProtocolError<Self::CustomErr>> {
+        let now = Instant::now();
+        self.process_acks();
+        self.detect_losses(now);
+
+        // Only take as many new messages from the store as fit into the congestion
+        // window, so that the store, not this protocol, keeps deciding what to send
+        // first
+        let pending_bytes = self.pending.iter().map(UdpFrame::size).sum::<usize>();
+        let budget = self
+            .congestion
+            .window
+            .saturating_sub(self.bytes_in_flight + pending_bytes);
+        if budget > 0 {
+            let bandwidth = bandwidth.min((budget as f64 / dt.as_secs_f64()) as u64);
+            let (frames, _) = self.store.grab(bandwidth, dt);
+            for (_, frame) in frames {
+                self.fragment(frame);
+            }
+        }
+
+        let (data_frames, data_bandwidth) = self.send_pending().await?;
+        self.metrics
+            .sdata_frames_b(data_frames, data_bandwidth as u64);
+
+        for sid in std::mem::take(&mut self.closing_streams) {
+            if !self.in_transit(sid) && self.store.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty");
+                self.streams.remove(&sid);
+                self.shared.lock().unwrap().closed.push(sid);
+                self.send_control(OTFrame::CloseStream { sid }, 1).await?;
+            } else {
+                self.closing_streams.push(sid);
+            }
+        }
+
+        for sid in std::mem::take(&mut self.notify_closing_streams) {
+            if !self.in_transit(sid) && self.store.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty");
+                self.streams.remove(&sid);
+            } else {
+                self.notify_closing_streams.push(sid);
+            }
+        }
+
+        if self.pending_shutdown && self.can_shutdown() {
+            #[cfg(feature = "trace_pedantic")]
+            trace!("shutdown, as it's now empty");
+            self.send_control(OTFrame::Shutdown, REDUNDANCY).await?;
+            self.pending_shutdown = false;
+        }
+
+        if self.last_sent.elapsed() > KEEP_ALIVE {
+            self.send_packet(None, &[], true).await?;
+        }
+        Ok(data_bandwidth as u64)
+    }
+}
+
+#[async_trait]
+impl<S> RecvProtocol for UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    type CustomErr = S::CustomErr;
+
+    async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError<Self::CustomErr>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?event, "recv");
+                return Ok(event);
+            }
+            let datagram = match self.stashed.pop_front() {
+                Some(datagram) => datagram,
+                None => self.sink.recv().await?,
+            };
+            self.handle_datagram(datagram)?;
+        }
+    }
+}
+
+#[async_trait]
+impl<D> ReliableDrain for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    type CustomErr = D::CustomErr;
+
+    async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError<Self::CustomErr>> {
+        let seq = self.next_init;
+        self.next_init += 1;
+        let mut data = BytesMut::with_capacity(500);
+        frame.write_bytes(&mut data);
+        // Spoofed hellos must not make us send more than we received
+        let copies = if self.shared.lock().unwrap().remote_answered {
+            REDUNDANCY
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            self.start_packet(PACKET_INIT, seq);
+            self.buffer.extend_from_slice(&data);
+            self.send_buffer().await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S> ReliableSink for UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    type CustomErr = S::CustomErr;
+
+    async fn recv(&mut self) -> Result<InitFrame, ProtocolError<Self::CustomErr>> {
+        loop {
+            let datagram = self.sink.recv().await?;
+            let mut packet = datagram.clone();
+            match read_packet_header(&mut packet) {
+                Some((PACKET_INIT, seq)) if seq >= self.next_init => {
+                    self.next_init = seq + 1;
+                    self.shared.lock().unwrap().remote_answered = true;
+                    return Ok(InitFrame::read_frame(&mut packet)
+                        .unwrap_or_else(|| InitFrame::Raw(packet.to_vec())));
+                },
+                // The remote side keeps asking for a handshake, so ours got lost. It
+                // reconnects once we give up.
+                Some((PACKET_HELLO, _)) if self.next_init == 0 => {
+                    self.hellos += 1;
+                    if self.hellos > MAX_UNANSWERED_HELLOS {
+                        return Err(ProtocolError::Violated);
+                    }
+                },
+                // Duplicates, late hellos and corrupted packets
+                Some((PACKET_INIT | PACKET_HELLO, _)) | None => {},
+                // The remote side finished the handshake already, it retransmits
+                // what doesn't fit
+                Some(_) if self.stashed.len() < MAX_STASHED_PACKETS => {
+                    self.stashed.push_back(datagram)
+                },
+                Some(_) => {},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_utils {
+    //UDP protocol based on Channel
+    use super::*;
+    use crate::metrics::{ProtocolMetricCache, ProtocolMetrics};
+    use async_channel::*;
+
+    pub struct UdpDrain {
+        pub sender: Sender<BytesMut>,
+        pub drop_ratio: f32,
+    }
+
+    pub struct UdpSink {
+        pub receiver: Receiver<BytesMut>,
+    }
+
+    /// emulate Udp protocol on Channels, dropping datagrams with `drop_ratio`
+    pub fn udp_bound(
+        drop_ratio: f32,
+        metrics: Option<ProtocolMetricCache>,
+    ) -> [(UdpSendProtocol<UdpDrain>, UdpRecvProtocol<UdpSink>); 2] {
+        let (s1, r1) = unbounded();
+        let (s2, r2) = unbounded();
+        let m = metrics.unwrap_or_else(|| {
+            ProtocolMetricCache::new("udp", Arc::new(ProtocolMetrics::new().unwrap()))
+        });
+        [
+            UdpSendProtocol::new(
+                UdpDrain {
+                    sender: s1,
+                    drop_ratio,
+                },
+                UdpSink {
+                    receiver: r2.clone(),
+                },
+                m.clone(),
+            ),
+            UdpSendProtocol::new(
+                UdpDrain {
+                    sender: s2,
+                    drop_ratio,
+                },
+                UdpSink { receiver: r1 },
+                m,
+            ),
+        ]
+    }
+
+    /// Keeps flushing the send side, so that acknowledgements and
+    /// retransmissions are sent
+    pub fn keep_flushing(mut s: UdpSendProtocol<UdpDrain>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while s
+                .flush(1_000_000_000, Duration::from_millis(1))
+                .await
+                .is_ok()
+            {
+                tokio::task::yield_now().await;
+            }
+        })
+    }
+
+    /// Keeps receiving on the receive side, so that acknowledgements are
+    /// processed
+    pub fn keep_receiving(mut r: UdpRecvProtocol<UdpSink>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move { while RecvProtocol::recv(&mut r).await.is_ok() {} })
+    }
+
+    #[async_trait]
+    impl UnreliableDrain for UdpDrain {
+        type CustomErr = ();
+        type DataFormat = BytesMut;
+
+        async fn send(
+            &mut self,
+            data: Self::DataFormat,
+        ) -> Result<(), ProtocolError<Self::CustomErr>> {
+            use rand::Rng;
+            if rand::thread_rng().gen::<f32>() < self.drop_ratio {
+                return Ok(());
+            }
+            self.sender
+                .send(data)
+                .await
+                .map_err(|_| ProtocolError::Custom(()))
+        }
+    }
+
+    #[async_trait]
+    impl UnreliableSink for UdpSink {
+        type CustomErr = ();
+        type DataFormat = BytesMut;
+
+        async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError<Self::CustomErr>> {
+            self.receiver
+                .recv()
+                .await
+                .map_err(|_| ProtocolError::Custom(()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        checksum, hello_datagram, is_hello_datagram, UdpFrame, FRAGMENT_COST, MAX_CHANNEL_BUFFER,
+        MAX_INCOMPLETE_MESSAGES, MAX_MESSAGE_LEAD, MAX_MESSAGE_LENGTH, MAX_STASHED_PACKETS,
+        MAX_STREAM_BUFFER, MAX_UNANSWERED_HELLOS, MESSAGE_COST, PACKET_DATA, PACKET_HEADER_SIZE,
+        REDUNDANCY,
+    };
+    use crate::{
+        error::ProtocolError,
+        frame::InitFrame,
+        metrics::{ProtocolMetricCache, ProtocolMetrics, RemoveReason},
+        types::{Pid, Promises, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
+        udp::{test_utils::*, UdpRecvProtocol, UdpSendProtocol},
+        InitProtocol, ProtocolEvent, RecvProtocol, SendProtocol,
+    };
+    use bytes::{BufMut, Bytes, BytesMut};
+    use std::{sync::Arc, time::Duration};
+
+    fn open(sid: Sid, promises: Promises) -> ProtocolEvent {
+        ProtocolEvent::OpenStream {
+            sid,
+            prio: 3u8,
+            promises,
+            guaranteed_bandwidth: 1_000_000,
+        }
+    }
+
+    fn numbered_msg(sid: Sid, i: u32, len: usize) -> ProtocolEvent {
+        let mut data = i.to_le_bytes().to_vec();
+        data.resize(len.max(4), i as u8);
+        ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(data),
+        }
+    }
+
+    fn msg_number(event: &ProtocolEvent) -> u32 {
+        match event {
+            ProtocolEvent::Message { data, .. } => {
+                u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+            },
+            _ => panic!("expected a message, got {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn handshake_all_good() {
+        let [mut p1, mut p2] = udp_bound(0.0, None);
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+    }
+
+    #[tokio::test]
+    async fn open_stream() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = open(Sid::new(10), Promises::ORDERED);
+        s.send(event.clone()).await.unwrap();
+        let e = r.recv().await.unwrap();
+        assert_eq!(event, e);
+    }
+
+    #[tokio::test]
+    async fn send_short_msg() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        s.send(open(Sid::new(10), Promises::ORDERED)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[188u8; 600][..]),
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let e = r.recv().await.unwrap();
+        assert_eq!(event, e);
+        // 2nd short message
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[7u8; 30][..]),
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let e = r.recv().await.unwrap();
+        assert_eq!(event, e)
+    }
+
+    #[tokio::test]
+    async fn send_empty_msg() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        s.send(open(Sid::new(10), Promises::ORDERED)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::new(),
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let e = r.recv().await.unwrap();
+        assert_eq!(event, e);
+    }
+
+    #[tokio::test]
+    async fn send_long_msg() {
+        let mut metrics =
+            ProtocolMetricCache::new("long_udp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let sid = Sid::new(1);
+        let [p1, p2] = udp_bound(0.0, Some(metrics.clone()));
+        let (mut s, r) = p1;
+        let (s2, mut r2) = p2;
+        s.send(open(sid, Promises::COMPRESSED | Promises::ORDERED))
+            .await
+            .unwrap();
+        let _ = r2.recv().await.unwrap();
+        let event = ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[99u8; 500_000][..]),
+        };
+        s.send(event.clone()).await.unwrap();
+        // the congestion window needs acknowledgements to grow
+        let tasks = [keep_flushing(s), keep_receiving(r), keep_flushing(s2)];
+        let e = r2.recv().await.unwrap();
+        assert_eq!(event, e);
+        metrics.assert_msg(sid, 1, RemoveReason::Finished);
+        metrics.assert_msg_bytes(sid, 500_000, RemoveReason::Finished);
+        metrics.assert_data_frames_bytes(500_000);
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn reliable_ordered_under_loss() {
+        const COUNT: u32 = 200;
+        let sid = Sid::new(1);
+        let [p1, p2] = udp_bound(0.3, None);
+        let (mut s, r) = p1;
+        let (s2, mut r2) = p2;
+        s.send(open(sid, Promises::ORDERED | Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        for i in 0..COUNT {
+            // mix short messages with ones that need multiple fragments
+            s.send(numbered_msg(sid, i, (i as usize * 97) % 5000))
+                .await
+                .unwrap();
+        }
+        let tasks = [keep_flushing(s), keep_receiving(r), keep_flushing(s2)];
+        assert!(matches!(
+            r2.recv().await.unwrap(),
+            ProtocolEvent::OpenStream { .. }
+        ));
+        for i in 0..COUNT {
+            let e = r2.recv().await.unwrap();
+            assert_eq!(msg_number(&e), i);
+            assert_eq!(e, numbered_msg(sid, i, (i as usize * 97) % 5000));
+        }
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn reliable_unordered_under_loss() {
+        const COUNT: u32 = 200;
+        let sid = Sid::new(1);
+        let [p1, p2] = udp_bound(0.3, None);
+        let (mut s, r) = p1;
+        let (s2, mut r2) = p2;
+        s.send(open(sid, Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        for i in 0..COUNT {
+            s.send(numbered_msg(sid, i, 2000)).await.unwrap();
+        }
+        let tasks = [keep_flushing(s), keep_receiving(r), keep_flushing(s2)];
+        assert!(matches!(
+            r2.recv().await.unwrap(),
+            ProtocolEvent::OpenStream { .. }
+        ));
+        let mut received = vec![false; COUNT as usize];
+        for _ in 0..COUNT {
+            let i = msg_number(&r2.recv().await.unwrap()) as usize;
+            assert!(!received[i], "message {} received twice", i);
+            received[i] = true;
+        }
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn unreliable_ordered_skips_lost() {
+        const MIN_CHECK: usize = 10;
+        const COUNT: u32 = 1000;
+        let sid = Sid::new(1337);
+        let [p1, p2] = udp_bound(0.5, None);
+        let (mut s, r) = p1;
+        let (s2, mut r2) = p2;
+        s.send(open(sid, Promises::ORDERED)).await.unwrap();
+        // the open stream frame is retransmitted, the messages are not
+        for i in 0..COUNT {
+            s.send(numbered_msg(sid, i, 100)).await.unwrap();
+        }
+        let tasks = [keep_flushing(s), keep_receiving(r), keep_flushing(s2)];
+        assert!(matches!(
+            r2.recv().await.unwrap(),
+            ProtocolEvent::OpenStream { .. }
+        ));
+        let mut last = None;
+        for _ in 0..MIN_CHECK {
+            let i = msg_number(&r2.recv().await.unwrap());
+            assert!(last.map_or(true, |last| i > last));
+            last = Some(i);
+        }
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn latest_only_drops_superseded() {
+        let sid = Sid::new(1);
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        s.send(open(sid, Promises::LATEST_ONLY)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        for i in 0..5 {
+            s.send(numbered_msg(sid, i, 3000)).await.unwrap();
+        }
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        s.send(numbered_msg(sid, 5, 3000)).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(msg_number(&r.recv().await.unwrap()), 4);
+        assert_eq!(msg_number(&r.recv().await.unwrap()), 5);
+    }
+
+    #[tokio::test]
+    async fn msg_finishes_after_close() {
+        let sid = Sid::new(1);
+        let [p1, p2] = udp_bound(0.2, None);
+        let (mut s, r) = p1;
+        let (s2, mut r2) = p2;
+        s.send(open(sid, Promises::ORDERED | Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        s.send(ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[99u8; 500_000][..]),
+        })
+        .await
+        .unwrap();
+        s.send(ProtocolEvent::CloseStream { sid }).await.unwrap();
+        let tasks = [keep_flushing(s), keep_receiving(r), keep_flushing(s2)];
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::OpenStream { .. }));
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::Message { .. }));
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::CloseStream { .. }));
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn msg_finishes_after_shutdown() {
+        let sid = Sid::new(1);
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, r) = p1;
+        let (s2, mut r2) = p2;
+        s.send(open(sid, Promises::ORDERED | Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        s.send(ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&[99u8; 500_000][..]),
+        })
+        .await
+        .unwrap();
+        s.send(ProtocolEvent::Shutdown {}).await.unwrap();
+        s.send(ProtocolEvent::CloseStream { sid }).await.unwrap();
+        let tasks = [keep_flushing(s), keep_receiving(r), keep_flushing(s2)];
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::OpenStream { .. }));
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::Message { .. }));
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::CloseStream { .. }));
+        let e = r2.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::Shutdown { .. }));
+        tasks.iter().for_each(|t| t.abort());
+    }
+
+    #[tokio::test]
+    async fn send_on_stream_from_remote() {
+        //remote opens stream
+        //we send on it
+        let [mut p1, mut p2] = udp_bound(0.0, None);
+        p1.0.send(open(Sid::new(10), Promises::ORDERED))
+            .await
+            .unwrap();
+        let e = p2.1.recv().await.unwrap();
+        p2.0.notify_from_recv(e);
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[188u8; 600][..]),
+        };
+        p2.0.send(event.clone()).await.unwrap();
+        p2.0.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let e = p1.1.recv().await.unwrap();
+        assert_eq!(event, e);
+    }
+
+    #[tokio::test]
+    async fn corrupted_datagrams_are_dropped() {
+        let (s1, r1) = async_channel::unbounded();
+        let (s2, r2) = async_channel::unbounded();
+        let m = ProtocolMetricCache::new("udp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let (mut s, _) = UdpSendProtocol::new(
+            UdpDrain {
+                sender: s1,
+                drop_ratio: 0.0,
+            },
+            UdpSink {
+                receiver: async_channel::unbounded().1,
+            },
+            m.clone(),
+        );
+        let (_, mut r) = UdpSendProtocol::new(
+            UdpDrain {
+                sender: async_channel::unbounded().0,
+                drop_ratio: 0.0,
+            },
+            UdpSink {
+                receiver: r2.clone(),
+            },
+            m,
+        );
+        let event = open(Sid::new(10), Promises::CONSISTENCY);
+        s.send(event.clone()).await.unwrap();
+        let datagram = r1.recv().await.unwrap();
+        let mut corrupted = datagram.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0b0001_0000;
+        s2.send(corrupted).await.unwrap();
+        s2.send(datagram).await.unwrap();
+        let e = r.recv().await.unwrap();
+        assert_eq!(event, e);
+        assert!(r2.is_empty());
+    }
+
+    #[test]
+    fn hello_datagrams() {
+        let hello = hello_datagram();
+        assert!(is_hello_datagram(&hello));
+        assert!(!is_hello_datagram(&[]));
+        let mut corrupted = hello.clone();
+        corrupted[PACKET_HEADER_SIZE] ^= 1;
+        assert!(!is_hello_datagram(&corrupted));
+    }
+
+    fn forged_datagram(frames: &[UdpFrame]) -> BytesMut {
+        let mut datagram = BytesMut::new();
+        datagram.put_u32_le(0);
+        datagram.put_u8(PACKET_DATA);
+        datagram.put_u64_le(1_000);
+        for frame in frames {
+            frame.write_bytes(&mut datagram);
+        }
+        let sum = checksum(&datagram[4..]);
+        datagram[..4].copy_from_slice(&sum.to_le_bytes());
+        datagram
+    }
+
+    fn forged_fragment(sid: Sid, msg: u64, length: u64, offset: u64) -> UdpFrame {
+        UdpFrame::Fragment {
+            sid,
+            msg,
+            length,
+            offset,
+            data: Bytes::from_static(&[0]),
+        }
+    }
+
+    #[tokio::test]
+    async fn forged_fragment_offset_is_buffered_sparsely() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let sid = Sid::new(10);
+        s.send(open(sid, Promises::ORDERED)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+
+        let length = MAX_MESSAGE_LENGTH;
+        let datagram = forged_datagram(&[forged_fragment(sid, 0, length, length - 1)]);
+        s.drain.sender.send(datagram).await.unwrap();
+        let datagram = forged_datagram(&[forged_fragment(sid, 1, length + 1, 0)]);
+        s.drain.sender.send(datagram).await.unwrap();
+        assert_eq!(r.recv().await, Err(ProtocolError::Violated));
+        assert_eq!(r.buffered, MESSAGE_COST + FRAGMENT_COST + 1);
+    }
+
+    #[tokio::test]
+    async fn forged_message_index_is_rejected() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let sid = Sid::new(10);
+        s.send(open(sid, Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        let _ = r.recv().await.unwrap();
+
+        let datagram = forged_datagram(&[forged_fragment(sid, MAX_MESSAGE_LEAD, 2, 0)]);
+        s.drain.sender.send(datagram).await.unwrap();
+        assert_eq!(r.recv().await, Err(ProtocolError::Violated));
+        assert_eq!(r.buffered, 0);
+    }
+
+    /// Receives a fragment of each of `messages` incomplete messages
+    fn flood(
+        r: &mut UdpRecvProtocol<UdpSink>,
+        sid: Sid,
+        messages: u64,
+    ) -> Result<(), ProtocolError<()>> {
+        for first in (1..=messages).step_by(30) {
+            let frames = (first..(first + 30).min(messages + 1))
+                .map(|msg| forged_fragment(sid, msg, 2, 0))
+                .collect::<Vec<_>>();
+            let datagram = forged_datagram(&frames);
+            r.handle_datagram(datagram)?;
+            assert!(r.buffered <= MAX_CHANNEL_BUFFER);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reliable_stream_buffer_is_capped() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let sid = Sid::new(10);
+        s.send(open(sid, Promises::ORDERED | Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        let _ = r.recv().await.unwrap();
+
+        let messages = (MAX_STREAM_BUFFER / (MESSAGE_COST + FRAGMENT_COST + 1)) as u64 + 1;
+        assert!(messages < MAX_MESSAGE_LEAD);
+        assert_eq!(flood(&mut r, sid, messages), Err(ProtocolError::Violated));
+    }
+
+    #[tokio::test]
+    async fn unreliable_stream_drops_incomplete_messages() {
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let sid = Sid::new(10);
+        s.send(open(sid, Promises::ORDERED)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+
+        flood(&mut r, sid, 1_000).unwrap();
+        assert_eq!(r.streams[&sid].incoming.len(), MAX_INCOMPLETE_MESSAGES);
+    }
+
+    #[tokio::test]
+    async fn sender_stays_within_remote_buffers() {
+        let [p1, _p2] = udp_bound(0.0, None);
+        let mut s = p1.0;
+        let sid = Sid::new(10);
+        s.send(open(sid, Promises::ORDERED | Promises::GUARANTEED_DELIVERY))
+            .await
+            .unwrap();
+        // Nothing gets acknowledged, so only the buffer limits hold messages back
+        s.congestion.window = usize::MAX;
+        for _ in 0..3 {
+            s.send(ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![0; MAX_MESSAGE_LENGTH as usize]),
+            })
+            .await
+            .unwrap();
+        }
+        s.flush(1 << 40, Duration::from_secs(1)).await.unwrap();
+        assert!(s.streams[&sid].span <= MAX_STREAM_BUFFER);
+        assert!(s.span > MAX_MESSAGE_LENGTH as usize);
+        assert!(!s.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stashed_packets_are_bounded() {
+        use crate::handshake::{ReliableDrain, ReliableSink};
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let sid = Sid::new(10);
+        for msg in 0..MAX_STASHED_PACKETS as u64 + 10 {
+            let datagram = forged_datagram(&[forged_fragment(sid, msg, 1, 0)]);
+            s.drain.sender.send(datagram).await.unwrap();
+        }
+        ReliableDrain::send(&mut s, InitFrame::Raw(vec![1]))
+            .await
+            .unwrap();
+        assert_eq!(
+            ReliableSink::recv(&mut r).await,
+            Ok(InitFrame::Raw(vec![1]))
+        );
+        assert_eq!(r.stashed.len(), MAX_STASHED_PACKETS);
+    }
+
+    #[tokio::test]
+    async fn first_handshake_frame_is_not_amplified() {
+        use crate::handshake::{ReliableDrain, ReliableSink};
+        let [p1, p2] = udp_bound(0.0, None);
+        let (mut s1, mut r1) = p1;
+        let (mut s2, r2) = p2;
+        ReliableDrain::send(&mut s1, InitFrame::Raw(vec![1]))
+            .await
+            .unwrap();
+        assert_eq!(r2.sink.receiver.len(), 1);
+
+        ReliableDrain::send(&mut s2, InitFrame::Raw(vec![2]))
+            .await
+            .unwrap();
+        assert_eq!(
+            ReliableSink::recv(&mut r1).await,
+            Ok(InitFrame::Raw(vec![2]))
+        );
+        ReliableDrain::send(&mut s1, InitFrame::Raw(vec![3]))
+            .await
+            .unwrap();
+        assert_eq!(r2.sink.receiver.len(), 1 + REDUNDANCY);
+    }
+
+    #[tokio::test]
+    async fn handshake_is_given_up_on_repeated_hellos() {
+        use crate::handshake::ReliableSink;
+        let [p1, p2] = udp_bound(0.0, None);
+        let (s, mut r) = (p1.0, p2.1);
+        for _ in 0..=MAX_UNANSWERED_HELLOS {
+            s.drain.sender.send(hello_datagram()).await.unwrap();
+        }
+        assert_eq!(
+            ReliableSink::recv(&mut r).await,
+            Err(ProtocolError::Violated)
+        );
+    }
+}
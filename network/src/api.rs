@@ -3,6 +3,7 @@ use crate::{
     message::{partial_eq_bincode, Message},
     participant::{A2bStreamOpen, S2bShutdownBparticipant},
     scheduler::{A2sConnect, Scheduler},
+    simulation::LinkSimulation,
 };
 use bytes::Bytes;
 use hashbrown::HashMap;
@@ -72,6 +73,7 @@ pub struct Participant {
     b2a_event_r: mpsc::UnboundedReceiver<ParticipantEvent>,
     b2a_bandwidth_stats_r: watch::Receiver<f32>,
     a2s_disconnect_s: A2sDisconnect,
+    remote_address: ConnectAddr,
 }
 
 /// `Streams` represents a channel to send `n` messages with a certain priority
@@ -198,6 +200,7 @@ pub struct Network {
     listen_sender: mpsc::UnboundedSender<(ListenAddr, oneshot::Sender<io::Result<()>>)>,
     connect_sender: mpsc::UnboundedSender<A2sConnect>,
     connected_receiver: mpsc::UnboundedReceiver<Participant>,
+    link_simulation_s: watch::Sender<Option<LinkSimulation>>,
     shutdown_network_s: Option<oneshot::Sender<oneshot::Sender<()>>>,
 }
 
@@ -274,9 +277,11 @@ impl Network {
         let p = participant_id;
         let span = info_span!("network", ?p);
         span.in_scope(|| trace!("Starting Network"));
+        let (link_simulation_s, link_simulation_r) = watch::channel(None);
         let (scheduler, listen_sender, connect_sender, connected_receiver, shutdown_sender) =
             Scheduler::new(
                 participant_id,
+                link_simulation_r,
                 #[cfg(feature = "metrics")]
                 registry,
             );
@@ -303,10 +308,34 @@ impl Network {
             listen_sender,
             connect_sender,
             connected_receiver,
+            link_simulation_s,
             shutdown_network_s: Some(shutdown_network_s),
         }
     }
 
+    /// Degrades the data sent over all channels that are created from now on,
+    /// to test how an application copes with a bad connection. Channels that
+    /// already exist aren't affected, `None` turns the simulation off again.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tokio::runtime::Runtime;
+    /// use veloren_network::{LinkSimulation, Network, Pid};
+    ///
+    /// let runtime = Runtime::new().unwrap();
+    /// let network = Network::new(Pid::new(), &runtime);
+    /// network.set_link_simulation(Some(LinkSimulation {
+    ///     latency_ms: 150,
+    ///     drop: 0.05,
+    ///     ..Default::default()
+    /// }));
+    /// ```
+    ///
+    /// [`LinkSimulation`]: crate::LinkSimulation
+    pub fn set_link_simulation(&self, link_simulation: Option<LinkSimulation>) {
+        self.link_simulation_s.send_replace(link_simulation);
+    }
+
     /// starts listening on an [`ListenAddr`].
     /// When the method returns the `Network` is ready to listen for incoming
     /// connections OR has returned a [`NetworkError`] (e.g. port already used).
@@ -516,6 +545,7 @@ impl Network {
 }
 
 impl Participant {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         local_pid: Pid,
         remote_pid: Pid,
@@ -524,6 +554,7 @@ impl Participant {
         b2a_event_r: mpsc::UnboundedReceiver<ParticipantEvent>,
         b2a_bandwidth_stats_r: watch::Receiver<f32>,
         a2s_disconnect_s: mpsc::UnboundedSender<(Pid, S2bShutdownBparticipant)>,
+        remote_address: ConnectAddr,
     ) -> Self {
         Self {
             local_pid,
@@ -533,6 +564,7 @@ impl Participant {
             b2a_event_r,
             b2a_bandwidth_stats_r,
             a2s_disconnect_s: Arc::new(Mutex::new(Some(a2s_disconnect_s))),
+            remote_address,
         }
     }
 
@@ -817,6 +849,10 @@ impl Participant {
 
     /// Returns the remote [`Pid`](network_protocol::Pid)
     pub fn remote_pid(&self) -> Pid { self.remote_pid }
+
+    /// Returns the address of the remote side of the first channel this
+    /// `Participant` connected with
+    pub fn remote_address(&self) -> &ConnectAddr { &self.remote_address }
 }
 
 impl Stream {
@@ -1,4 +1,7 @@
-use crate::api::{ConnectAddr, NetworkConnectError};
+use crate::{
+    api::{ConnectAddr, NetworkConnectError},
+    simulation::{LinkSimulation, SimSendProtocol},
+};
 use async_trait::async_trait;
 use bytes::BytesMut;
 use futures_util::FutureExt;
@@ -6,18 +9,18 @@ use hashbrown::HashMap;
 use network_protocol::{
     Bandwidth, Cid, InitProtocolError, MpscMsg, MpscRecvProtocol, MpscSendProtocol, Pid,
     ProtocolError, ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, Sid, TcpRecvProtocol,
-    TcpSendProtocol, UnreliableDrain, UnreliableSink,
+    TcpSendProtocol, UdpRecvProtocol, UdpSendProtocol, UnreliableDrain, UnreliableSink,
 };
 #[cfg(feature = "quic")]
 use network_protocol::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
 use std::{
     io,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -35,6 +38,7 @@ pub(crate) enum Protocols {
     Mpsc((MpscSendProtocol<MpscDrain>, MpscRecvProtocol<MpscSink>)),
     #[cfg(feature = "quic")]
     Quic((QuicSendProtocol<QuicDrain>, QuicRecvProtocol<QuicSink>)),
+    Udp((UdpSendProtocol<UdpDrain>, UdpRecvProtocol<UdpSink>)),
 }
 
 #[derive(Debug)]
@@ -43,6 +47,8 @@ pub(crate) enum SendProtocols {
     Mpsc(MpscSendProtocol<MpscDrain>),
     #[cfg(feature = "quic")]
     Quic(QuicSendProtocol<QuicDrain>),
+    Udp(UdpSendProtocol<UdpDrain>),
+    Sim(Box<SimSendProtocol<SendProtocols>>),
 }
 
 #[derive(Debug)]
@@ -51,6 +57,7 @@ pub(crate) enum RecvProtocols {
     Mpsc(MpscRecvProtocol<MpscSink>),
     #[cfg(feature = "quic")]
     Quic(QuicRecvProtocol<QuicSink>),
+    Udp(UdpRecvProtocol<UdpSink>),
 }
 
 lazy_static::lazy_static! {
@@ -66,7 +73,6 @@ pub(crate) type C2cMpscConnect = (
 pub(crate) type C2sProtocol = (Protocols, ConnectAddr, Cid);
 
 fn anonymize_addr(addr: &SocketAddr) -> String {
-    use std::net::IpAddr;
     match addr.ip() {
         IpAddr::V4(ip) => {
             let [o0, _, o2, _] = ip.octets();
@@ -84,6 +90,15 @@ fn anonymize_addr(addr: &SocketAddr) -> String {
 
 impl Protocols {
     const MPSC_CHANNEL_BOUND: usize = 1000;
+    /// Datagrams buffered per UDP channel, further ones are dropped
+    const UDP_CHANNEL_BOUND: usize = 1000;
+    /// Addresses tracked for [`Self::UDP_NEW_PEERS_PER_INTERVAL`] before
+    /// expired ones are forgotten
+    const UDP_MAX_TRACKED_PEERS: usize = 1024;
+    /// UDP channels opened per address within [`Self::UDP_NEW_PEER_INTERVAL`],
+    /// handshakes beyond that are dropped
+    const UDP_NEW_PEERS_PER_INTERVAL: u32 = 8;
+    const UDP_NEW_PEER_INTERVAL: Duration = Duration::from_secs(10);
 
     pub(crate) async fn with_tcp_connect(
         addr: SocketAddr,
@@ -274,7 +289,7 @@ impl Protocols {
     ) -> Result<Self, NetworkConnectError> {
         let config = config.clone();
 
-        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+        use std::net::{Ipv4Addr, Ipv6Addr};
 
         let bindsock = match addr {
             SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
@@ -394,12 +409,196 @@ impl Protocols {
         Ok(Protocols::Quic((sp, rp)))
     }
 
+    pub(crate) async fn with_udp_connect(
+        addr: SocketAddr,
+        metrics: ProtocolMetricCache,
+    ) -> Result<Self, NetworkConnectError> {
+        let bind_addr: SocketAddr = if addr.is_ipv4() {
+            (std::net::Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = net::UdpSocket::bind(bind_addr)
+            .await
+            .map_err(NetworkConnectError::Io)?;
+        socket
+            .connect(addr)
+            .await
+            .map_err(NetworkConnectError::Io)?;
+        info!("Connecting Udp to: {}", &addr);
+        let socket = Arc::new(socket);
+        let (datagram_s, datagram_r) = mpsc::channel(Self::UDP_CHANNEL_BOUND);
+
+        let read_socket = Arc::clone(&socket);
+        tokio::spawn(async move {
+            let mut buffer = [0u8; UDP_RECV_BUFFER_SIZE];
+            // The listener only learns about us from our datagrams, but the handshake
+            // is started by the listening side. So hellos, which the protocol
+            // ignores, are sent until the remote side answers.
+            let hello_datagram = network_protocol::hello_datagram();
+            let mut hello = tokio::time::interval(UDP_HELLO_INTERVAL);
+            let mut answered = false;
+            loop {
+                let n = select! {
+                    n = read_socket.recv(&mut buffer).fuse() => n,
+                    _ = hello.tick(), if !answered => {
+                        if let Err(e) = read_socket.send(&hello_datagram).await {
+                            trace!(?e, "error sending udp hello");
+                        }
+                        continue;
+                    },
+                    _ = datagram_s.closed().fuse() => break,
+                };
+                match n {
+                    // Dropped when the protocol can't keep up, it's UDP after all
+                    Ok(n) => {
+                        answered = true;
+                        let _ = datagram_s.try_send(BytesMut::from(&buffer[..n]));
+                    },
+                    // e.g. ICMP port unreachable, the sink will time out
+                    Err(e) => trace!(?e, "error receiving udp datagram"),
+                }
+            }
+            trace!(?addr, "Udp reader stopped");
+        });
+
+        Ok(Self::new_udp(socket, None, datagram_r, metrics))
+    }
+
+    pub(crate) async fn with_udp_listen(
+        addr: SocketAddr,
+        cids: Arc<AtomicU64>,
+        metrics: Arc<ProtocolMetrics>,
+        s2s_stop_listening_r: oneshot::Receiver<()>,
+        c2s_protocol_s: mpsc::UnboundedSender<C2sProtocol>,
+    ) -> io::Result<()> {
+        let socket = Arc::new(net::UdpSocket::bind(addr).await?);
+        trace!(?addr, "Udp Listener bound");
+        let mut end_receiver = s2s_stop_listening_r.fuse();
+        tokio::spawn(async move {
+            // All channels share the socket, so datagrams are sorted by their sender
+            let mut channels: HashMap<SocketAddr, mpsc::Sender<BytesMut>> = HashMap::new();
+            // Channels opened per address recently, and since when they are counted
+            let mut new_peers: HashMap<IpAddr, (Instant, u32)> = HashMap::new();
+            let mut listening = true;
+            let mut buffer = [0u8; UDP_RECV_BUFFER_SIZE];
+            loop {
+                let (n, remote_addr) = select! {
+                    next = socket.recv_from(&mut buffer).fuse() => match next {
+                        Ok(next) => next,
+                        Err(e) => {
+                            trace!(?e, "error receiving udp datagram");
+                            continue;
+                        },
+                    },
+                    _ = &mut end_receiver, if listening => {
+                        listening = false;
+                        channels.retain(|_, datagram_s| !datagram_s.is_closed());
+                        if channels.is_empty() {
+                            break;
+                        }
+                        continue;
+                    },
+                };
+                let mut datagram = BytesMut::from(&buffer[..n]);
+                if let Some(datagram_s) = channels.get(&remote_addr) {
+                    match datagram_s.try_send(datagram) {
+                        Ok(()) | Err(mpsc::error::TrySendError::Full(_)) => continue,
+                        // The channel was closed, the remote side reconnects
+                        Err(mpsc::error::TrySendError::Closed(d)) => {
+                            datagram = d;
+                            channels.remove(&remote_addr);
+                            if !listening && channels.is_empty() {
+                                break;
+                            }
+                        },
+                    }
+                }
+                // Only open channels for intact hellos, so that stray or corrupted
+                // datagrams don't allocate anything
+                if !listening || !network_protocol::is_hello_datagram(&datagram) {
+                    continue;
+                }
+                let now = Instant::now();
+                if new_peers.len() > Self::UDP_MAX_TRACKED_PEERS {
+                    new_peers.retain(|_, (since, _)| {
+                        now.duration_since(*since) < Self::UDP_NEW_PEER_INTERVAL
+                    });
+                }
+                let (since, count) = new_peers.entry(remote_addr.ip()).or_insert((now, 0));
+                if now.duration_since(*since) >= Self::UDP_NEW_PEER_INTERVAL {
+                    *since = now;
+                    *count = 0;
+                }
+                if *count >= Self::UDP_NEW_PEERS_PER_INTERVAL {
+                    trace!(
+                        remote_addr = anonymize_addr(&remote_addr),
+                        "Too many new Udp channels from address"
+                    );
+                    continue;
+                }
+                *count += 1;
+
+                let cid = cids.fetch_add(1, Ordering::Relaxed);
+                info!(
+                    remote_addr = anonymize_addr(&remote_addr),
+                    ?cid,
+                    "Accepting Udp from"
+                );
+                let (datagram_s, datagram_r) = mpsc::channel(Self::UDP_CHANNEL_BOUND);
+                let _ = datagram_s.try_send(datagram);
+                channels.insert(remote_addr, datagram_s);
+                let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
+                let _ = c2s_protocol_s.send((
+                    Self::new_udp(Arc::clone(&socket), Some(remote_addr), datagram_r, metrics),
+                    ConnectAddr::Udp(remote_addr),
+                    cid,
+                ));
+            }
+            trace!(?addr, "Udp Listener stopped");
+        });
+        Ok(())
+    }
+
+    pub(crate) fn new_udp(
+        socket: Arc<net::UdpSocket>,
+        remote_addr: Option<SocketAddr>,
+        receiver: mpsc::Receiver<BytesMut>,
+        metrics: ProtocolMetricCache,
+    ) -> Self {
+        let (sp, rp) = UdpSendProtocol::new(
+            UdpDrain {
+                socket,
+                remote_addr,
+            },
+            UdpSink { receiver },
+            metrics,
+        );
+        Protocols::Udp((sp, rp))
+    }
+
     pub(crate) fn split(self) -> (SendProtocols, RecvProtocols) {
         match self {
             Protocols::Tcp((s, r)) => (SendProtocols::Tcp(s), RecvProtocols::Tcp(r)),
             Protocols::Mpsc((s, r)) => (SendProtocols::Mpsc(s), RecvProtocols::Mpsc(r)),
             #[cfg(feature = "quic")]
             Protocols::Quic((s, r)) => (SendProtocols::Quic(s), RecvProtocols::Quic(r)),
+            Protocols::Udp((s, r)) => (SendProtocols::Udp(s), RecvProtocols::Udp(r)),
+        }
+    }
+}
+
+impl SendProtocols {
+    /// Degrades everything sent via this protocol, see [`LinkSimulation`].
+    pub(crate) fn simulated(self, sim: LinkSimulation) -> Self {
+        SendProtocols::Sim(Box::new(SimSendProtocol::new(self, sim)))
+    }
+
+    /// The protocol which actually sends the data, behind any simulation.
+    pub(crate) fn underlying(&self) -> &SendProtocols {
+        match self {
+            SendProtocols::Sim(s) => s.inner().underlying(),
+            p => p,
         }
     }
 }
@@ -419,6 +618,7 @@ impl network_protocol::InitProtocol for Protocols {
             Protocols::Mpsc(p) => p.initialize(initializer, local_pid, secret).await,
             #[cfg(feature = "quic")]
             Protocols::Quic(p) => p.initialize(initializer, local_pid, secret).await,
+            Protocols::Udp(p) => p.initialize(initializer, local_pid, secret).await,
         }
     }
 }
@@ -433,6 +633,8 @@ impl network_protocol::SendProtocol for SendProtocols {
             SendProtocols::Mpsc(s) => s.notify_from_recv(event),
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.notify_from_recv(event),
+            SendProtocols::Udp(s) => s.notify_from_recv(event),
+            SendProtocols::Sim(s) => s.notify_from_recv(event),
         }
     }
 
@@ -442,6 +644,8 @@ impl network_protocol::SendProtocol for SendProtocols {
             SendProtocols::Mpsc(s) => s.send(event).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.send(event).await,
+            SendProtocols::Udp(s) => s.send(event).await,
+            SendProtocols::Sim(s) => s.send(event).await,
         }
     }
 
@@ -455,6 +659,8 @@ impl network_protocol::SendProtocol for SendProtocols {
             SendProtocols::Mpsc(s) => s.flush(bandwidth, dt).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.flush(bandwidth, dt).await,
+            SendProtocols::Udp(s) => s.flush(bandwidth, dt).await,
+            SendProtocols::Sim(s) => s.flush(bandwidth, dt).await,
         }
     }
 }
@@ -469,6 +675,7 @@ impl network_protocol::RecvProtocol for RecvProtocols {
             RecvProtocols::Mpsc(r) => r.recv().await,
             #[cfg(feature = "quic")]
             RecvProtocols::Quic(r) => r.recv().await,
+            RecvProtocols::Udp(r) => r.recv().await,
         }
     }
 }
@@ -583,6 +790,67 @@ impl UnreliableSink for MpscSink {
     }
 }
 
+///////////////////////////////////////
+// UDP
+/// Large enough for any datagram sent by the protocol, bigger ones are cut off
+/// and then dropped by the protocol's checksum
+const UDP_RECV_BUFFER_SIZE: usize = 2 * network_protocol::MAX_DATAGRAM_SIZE;
+/// The remote side sends keep alive pings every second, so without any
+/// datagram for this long it is considered gone
+const UDP_TIMEOUT: Duration = Duration::from_secs(10);
+const UDP_HELLO_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug)]
+pub struct UdpDrain {
+    socket: Arc<net::UdpSocket>,
+    /// `None` if the socket is connected to the remote side
+    remote_addr: Option<SocketAddr>,
+}
+
+#[derive(Debug)]
+pub struct UdpSink {
+    receiver: mpsc::Receiver<BytesMut>,
+}
+
+#[async_trait]
+impl UnreliableDrain for UdpDrain {
+    type CustomErr = ProtocolsError;
+    type DataFormat = BytesMut;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError<Self::CustomErr>> {
+        let result = match self.remote_addr {
+            Some(remote_addr) => self.socket.send_to(&data, remote_addr).await,
+            None => self.socket.send(&data).await,
+        };
+        match result {
+            Ok(_) => Ok(()),
+            // Full socket buffers are handled like lost datagrams
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(ProtocolError::Custom(ProtocolsError::Udp(e))),
+        }
+    }
+}
+
+#[async_trait]
+impl UnreliableSink for UdpSink {
+    type CustomErr = ProtocolsError;
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError<Self::CustomErr>> {
+        match tokio::time::timeout(UDP_TIMEOUT, self.receiver.recv()).await {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => Err(ProtocolError::Custom(ProtocolsError::Udp(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "udp socket closed",
+            )))),
+            Err(_) => Err(ProtocolError::Custom(ProtocolsError::Udp(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no udp datagram received",
+            )))),
+        }
+    }
+}
+
 ///////////////////////////////////////
 // QUIC
 #[cfg(feature = "quic")]
@@ -807,6 +1075,55 @@ mod tests {
         r.recv().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn udp_sinks() {
+        let server_socket = Arc::new(net::UdpSocket::bind("127.0.0.1:5002").await.unwrap());
+        let client = Arc::new(net::UdpSocket::bind("127.0.0.1:5003").await.unwrap());
+        let (server_s, server_r) = mpsc::channel(10);
+        let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
+        let client = Protocols::new_udp(
+            client,
+            Some("127.0.0.1:5002".parse().unwrap()),
+            mpsc::channel(10).1,
+            metrics.clone(),
+        );
+        let server = Protocols::new_udp(
+            Arc::clone(&server_socket),
+            Some("127.0.0.1:5003".parse().unwrap()),
+            server_r,
+            metrics,
+        );
+        let (mut s, _) = client.split();
+        let (_, mut r) = server.split();
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(1),
+            prio: 4u8,
+            promises: Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 1_000,
+        };
+        s.send(event).await.unwrap();
+        s.send(ProtocolEvent::Message {
+            sid: Sid::new(1),
+            data: Bytes::from(&[8u8; 8][..]),
+        })
+        .await
+        .unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        for _ in 0..2 {
+            let mut buffer = [0u8; UDP_RECV_BUFFER_SIZE];
+            let (n, _) = server_socket.recv_from(&mut buffer).await.unwrap();
+            server_s.send(BytesMut::from(&buffer[..n])).await.unwrap();
+        }
+        assert!(matches!(
+            r.recv().await.unwrap(),
+            ProtocolEvent::OpenStream { .. }
+        ));
+        assert!(matches!(
+            r.recv().await.unwrap(),
+            ProtocolEvent::Message { .. }
+        ));
+    }
+
     #[tokio::test]
     async fn tokio_sink_stop_after_drop() {
         let listener = TcpListener::bind("127.0.0.1:5001").await.unwrap();
@@ -105,6 +105,7 @@ mod message;
 mod metrics;
 mod participant;
 mod scheduler;
+mod simulation;
 mod util;
 
 pub use api::{
@@ -113,3 +114,4 @@ pub use api::{
 };
 pub use message::Message;
 pub use network_protocol::{InitProtocolError, Pid, Promises};
+pub use simulation::LinkSimulation;
@@ -2,6 +2,7 @@ use crate::{
     api::{ConnectAddr, ParticipantError, ParticipantEvent, Stream},
     channel::{Protocols, ProtocolsError, RecvProtocols, SendProtocols},
     metrics::NetworkMetrics,
+    simulation::LinkSimulation,
     util::DeferredTracer,
 };
 use bytes::Bytes;
@@ -27,7 +28,14 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::*;
 
 pub(crate) type A2bStreamOpen = (Prio, Promises, Bandwidth, oneshot::Sender<Stream>);
-pub(crate) type S2bCreateChannel = (Cid, Sid, Protocols, ConnectAddr, oneshot::Sender<()>);
+pub(crate) type S2bCreateChannel = (
+    Cid,
+    Sid,
+    Protocols,
+    ConnectAddr,
+    Option<LinkSimulation>,
+    oneshot::Sender<()>,
+);
 pub(crate) type S2bShutdownBparticipant = (Duration, oneshot::Sender<Result<(), ParticipantError>>);
 pub(crate) type B2sPrioStatistic = (Pid, u64, u64);
 
@@ -203,12 +211,12 @@ impl BParticipant {
 
     fn best_protocol(all: &SortedVec<Cid, SendProtocols>, promises: Promises) -> Option<Cid> {
         // check for mpsc
-        all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Mpsc(_))).map(|(c, _)| *c).or_else(
+        all.data.iter().find(|(_, p)| matches!(p.underlying(), SendProtocols::Mpsc(_))).map(|(c, _)| *c).or_else(
             || if network_protocol::TcpSendProtocol::<crate::channel::TcpDrain>::supported_promises()
                 .contains(promises)
             {
                 // check for tcp
-                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Tcp(_))).map(|(c, _)| *c)
+                all.data.iter().find(|(_, p)| matches!(p.underlying(), SendProtocols::Tcp(_))).map(|(c, _)| *c)
             } else {
                 None
             }
@@ -217,7 +225,16 @@ impl BParticipant {
             || if network_protocol::QuicSendProtocol::<crate::channel::QuicDrain>::supported_promises()
                 .contains(promises)
             {
-                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Quic(_))).map(|(c, _)| *c)
+                all.data.iter().find(|(_, p)| matches!(p.underlying(), SendProtocols::Quic(_))).map(|(c, _)| *c)
+            } else {
+                None
+            }
+        ).or_else(
+            || if network_protocol::UdpSendProtocol::<crate::channel::UdpDrain>::supported_promises()
+                .contains(promises)
+            {
+                // check for udp
+                all.data.iter().find(|(_, p)| matches!(p.underlying(), SendProtocols::Udp(_))).map(|(c, _)| *c)
             } else {
                 None
             }
@@ -585,7 +602,14 @@ impl BParticipant {
         s2b_create_channel_r
             .for_each_concurrent(
                 None,
-                |(cid, _, protocol, remote_con_addr, b2s_create_channel_done_s)| {
+                |(
+                    cid,
+                    _,
+                    protocol,
+                    remote_con_addr,
+                    link_simulation,
+                    b2s_create_channel_done_s,
+                )| {
                     // This channel is now configured, and we are running it in scope of the
                     // participant.
                     let channels = Arc::clone(&self.channels);
@@ -604,7 +628,11 @@ impl BParticipant {
                             }),
                         );
                         drop(lock);
-                        let (send, recv) = protocol.split();
+                        let (mut send, recv) = protocol.split();
+                        if let Some(sim) = link_simulation {
+                            debug!(?cid, ?sim, "simulating a degraded link");
+                            send = send.simulated(sim);
+                        }
                         b2b_add_send_protocol_s.send((cid, send)).unwrap();
                         b2b_add_recv_protocol_s.send((cid, recv)).unwrap();
                         if let Err(e) =
@@ -874,7 +902,14 @@ mod tests {
         let p1 = Protocols::new_mpsc(s1, r2, metrics);
         let (complete_s, complete_r) = oneshot::channel();
         create_channel
-            .send((cid, Sid::new(0), p1, ConnectAddr::Mpsc(42), complete_s))
+            .send((
+                cid,
+                Sid::new(0),
+                p1,
+                ConnectAddr::Mpsc(42),
+                None,
+                complete_s,
+            ))
             .unwrap();
         complete_r.await.unwrap();
         let metrics = ProtocolMetricCache::new(&cid.to_string(), met);
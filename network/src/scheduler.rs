@@ -3,6 +3,7 @@ use crate::{
     channel::Protocols,
     metrics::{NetworkMetrics, ProtocolInfo},
     participant::{B2sPrioStatistic, BParticipant, S2bCreateChannel, S2bShutdownBparticipant},
+    simulation::LinkSimulation,
 };
 use futures_util::StreamExt;
 use hashbrown::HashMap;
@@ -19,7 +20,7 @@ use std::{
 };
 use tokio::{
     io,
-    sync::{mpsc, oneshot, Mutex},
+    sync::{mpsc, oneshot, watch, Mutex},
 };
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::*;
@@ -76,11 +77,13 @@ pub struct Scheduler {
     channel_listener: Mutex<HashMap<ProtocolInfo, oneshot::Sender<()>>>,
     metrics: Arc<NetworkMetrics>,
     protocol_metrics: Arc<ProtocolMetrics>,
+    link_simulation: watch::Receiver<Option<LinkSimulation>>,
 }
 
 impl Scheduler {
     pub fn new(
         local_pid: Pid,
+        link_simulation: watch::Receiver<Option<LinkSimulation>>,
         #[cfg(feature = "metrics")] registry: Option<&Registry>,
     ) -> (
         Self,
@@ -137,6 +140,7 @@ impl Scheduler {
                 channel_listener: Mutex::new(HashMap::new()),
                 metrics,
                 protocol_metrics,
+                link_simulation,
             },
             a2s_listen_s,
             a2s_connect_s,
@@ -219,7 +223,16 @@ impl Scheduler {
                             )
                             .await
                         },
-                        _ => unimplemented!(),
+                        ListenAddr::Udp(addr) => {
+                            Protocols::with_udp_listen(
+                                addr,
+                                cids,
+                                metrics,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
                     };
                     let _ = s2a_listen_result_s.send(res);
 
@@ -246,7 +259,7 @@ impl Scheduler {
                     Protocols::with_quic_connect(addr, config.clone(), name, metrics).await
                 },
                 ConnectAddr::Mpsc(addr) => Protocols::with_mpsc_connect(addr, metrics).await,
-                _ => unimplemented!(),
+                ConnectAddr::Udp(addr) => Protocols::with_udp_connect(addr, metrics).await,
             };
             let protocol = match protocol {
                 Ok(p) => p,
@@ -395,6 +408,7 @@ impl Scheduler {
         let metrics = Arc::clone(&self.metrics);
         let local_pid = self.local_pid;
         let local_secret = self.local_secret;
+        let link_simulation = *self.link_simulation.borrow();
         // this is necessary for UDP to work at all and to remove code duplication
         tokio::spawn(
             async move {
@@ -432,6 +446,7 @@ impl Scheduler {
                                 b2a_event_r,
                                 b2a_bandwidth_stats_r,
                                 participant_channels.a2s_disconnect_s,
+                                con_addr.clone(),
                             );
 
                             #[cfg(feature = "metrics")]
@@ -454,7 +469,14 @@ impl Scheduler {
                                 oneshot::channel();
                             //From now on wire connects directly with bparticipant!
                             s2b_create_channel_s
-                                .send((cid, sid, protocol, con_addr, b2s_create_channel_done_s))
+                                .send((
+                                    cid,
+                                    sid,
+                                    protocol,
+                                    con_addr,
+                                    link_simulation,
+                                    b2s_create_channel_done_s,
+                                ))
                                 .unwrap();
                             b2s_create_channel_done_r.await.unwrap();
                             if let Some(pid_oneshot) = s2a_return_pid_s {
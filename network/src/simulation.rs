@@ -0,0 +1,229 @@
+//! Degrades the outgoing traffic of a channel, so bad connections can be
+//! reproduced locally. See [`LinkSimulation`].
+use async_trait::async_trait;
+use hashbrown::HashMap;
+use network_protocol::{Bandwidth, Promises, ProtocolError, ProtocolEvent, SendProtocol, Sid};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Impairments applied to everything a [`Network`] sends over channels that
+/// are created while it's set via [`Network::set_link_simulation`].
+///
+/// Only outgoing data is affected, set it on both ends to degrade both
+/// directions. Messages are never lost on streams that promise
+/// `GUARANTEED_DELIVERY` and never reordered on streams that promise
+/// `ORDERED`, instead they arrive late, like they would after a
+/// retransmission.
+///
+/// [`Network`]: crate::api::Network
+/// [`Network::set_link_simulation`]: crate::api::Network::set_link_simulation
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkSimulation {
+    /// Delay in milliseconds that is added to every message.
+    pub latency_ms: u64,
+    /// Maximum random delay in milliseconds added on top of `latency_ms`.
+    pub jitter_ms: u64,
+    /// Maximum bytes per second that are sent, `None` for no limit.
+    pub bandwidth: Option<u64>,
+    /// Chance (0.0 to 1.0) that a message is held back for another
+    /// `latency_ms` so that later messages overtake it.
+    pub reorder: f32,
+    /// Chance (0.0 to 1.0) that a message gets lost.
+    pub drop: f32,
+}
+
+impl LinkSimulation {
+    /// Parses the short form used on command lines, a comma separated list of
+    /// `latency`, `jitter`, `bandwidth`, `reorder` and `drop` settings, e.g.
+    /// `latency=150,jitter=30,drop=0.05`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut sim = Self::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected `key=value`, got `{}`", part))?;
+            let invalid = |e: &dyn std::fmt::Display| format!("invalid {}: {}", key, e);
+            match key.trim() {
+                "latency" => sim.latency_ms = value.parse().map_err(|e| invalid(&e))?,
+                "jitter" => sim.jitter_ms = value.parse().map_err(|e| invalid(&e))?,
+                "bandwidth" => sim.bandwidth = Some(value.parse().map_err(|e| invalid(&e))?),
+                "reorder" => sim.reorder = value.parse().map_err(|e| invalid(&e))?,
+                "drop" => sim.drop = value.parse().map_err(|e| invalid(&e))?,
+                _ => return Err(format!("unknown link simulation setting `{}`", key)),
+            }
+        }
+        Ok(sim)
+    }
+
+    fn latency(&self) -> Duration { Duration::from_millis(self.latency_ms) }
+}
+
+#[derive(Debug)]
+struct SimStream {
+    promises: Promises,
+    opened: Instant,
+    last: Instant,
+}
+
+#[derive(Debug)]
+struct Delayed {
+    due: Instant,
+    event: ProtocolEvent,
+}
+
+/// Wraps any [`SendProtocol`] and holds back events according to a
+/// [`LinkSimulation`] before passing them on.
+#[derive(Debug)]
+pub(crate) struct SimSendProtocol<P: SendProtocol> {
+    inner: P,
+    sim: LinkSimulation,
+    streams: HashMap<Sid, SimStream>,
+    /// sorted by `due`, events with the same `due` keep their order
+    queue: VecDeque<Delayed>,
+    rng: StdRng,
+}
+
+impl<P: SendProtocol> SimSendProtocol<P> {
+    pub(crate) fn new(inner: P, sim: LinkSimulation) -> Self {
+        Self {
+            inner,
+            sim,
+            streams: HashMap::new(),
+            queue: VecDeque::new(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub(crate) fn inner(&self) -> &P { &self.inner }
+
+    fn chance(&mut self, p: f32) -> bool { p > 0.0 && self.rng.gen::<f32>() < p }
+
+    /// Returns `None` if the event is lost.
+    fn due(&mut self, event: &ProtocolEvent, now: Instant) -> Option<Instant> {
+        let mut due = now + self.sim.latency();
+        if self.sim.jitter_ms > 0 {
+            due += Duration::from_millis(self.rng.gen_range(0..=self.sim.jitter_ms));
+        }
+        match event {
+            ProtocolEvent::OpenStream { sid, promises, .. } => {
+                self.streams.insert(*sid, SimStream {
+                    promises: *promises,
+                    opened: due,
+                    last: due,
+                });
+            },
+            ProtocolEvent::Message { sid, .. } => {
+                let drop = self.chance(self.sim.drop);
+                let reorder = self.chance(self.sim.reorder);
+                let Some(stream) = self.streams.get_mut(sid) else {
+                    return Some(due);
+                };
+                if drop {
+                    if !stream.promises.contains(Promises::GUARANTEED_DELIVERY) {
+                        return None;
+                    }
+                    // a retransmission takes another round trip
+                    due += self.sim.latency() * 2;
+                }
+                if stream.promises.contains(Promises::ORDERED) {
+                    due = due.max(stream.last);
+                } else {
+                    if reorder {
+                        due += self.sim.latency();
+                    }
+                    due = due.max(stream.opened);
+                }
+                stream.last = stream.last.max(due);
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                if let Some(stream) = self.streams.remove(sid) {
+                    due = due.max(stream.last);
+                }
+            },
+            ProtocolEvent::Shutdown => {},
+        }
+        Some(due)
+    }
+}
+
+#[async_trait]
+impl<P: SendProtocol + Send> SendProtocol for SimSendProtocol<P> {
+    type CustomErr = P::CustomErr;
+
+    fn notify_from_recv(&mut self, event: ProtocolEvent) {
+        match &event {
+            ProtocolEvent::OpenStream { sid, promises, .. } => {
+                let now = Instant::now();
+                self.streams.insert(*sid, SimStream {
+                    promises: *promises,
+                    opened: now,
+                    last: now,
+                });
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                self.streams.remove(sid);
+            },
+            _ => {},
+        }
+        self.inner.notify_from_recv(event);
+    }
+
+    async fn send(&mut self, event: ProtocolEvent) -> Result<(), ProtocolError<Self::CustomErr>> {
+        if matches!(event, ProtocolEvent::Shutdown) {
+            // Shutdown must not be delayed, deliver what's left first
+            while let Some(delayed) = self.queue.pop_front() {
+                self.inner.send(delayed.event).await?;
+            }
+            return self.inner.send(event).await;
+        }
+        if let Some(due) = self.due(&event, Instant::now()) {
+            let i = self.queue.partition_point(|d| d.due <= due);
+            self.queue.insert(i, Delayed { due, event });
+        }
+        Ok(())
+    }
+
+    async fn flush(
+        &mut self,
+        bandwidth: Bandwidth,
+        dt: Duration,
+    ) -> Result<Bandwidth, ProtocolError<Self::CustomErr>> {
+        let now = Instant::now();
+        while self.queue.front().map_or(false, |d| d.due <= now) {
+            let delayed = self.queue.pop_front().unwrap();
+            self.inner.send(delayed.event).await?;
+        }
+        let bandwidth = self.sim.bandwidth.map_or(bandwidth, |b| b.min(bandwidth));
+        self.inner.flush(bandwidth, dt).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_link_simulation() {
+        assert_eq!(
+            LinkSimulation::parse("latency=150, jitter=30,drop=0.05").unwrap(),
+            LinkSimulation {
+                latency_ms: 150,
+                jitter_ms: 30,
+                drop: 0.05,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            LinkSimulation::parse("").unwrap(),
+            LinkSimulation::default()
+        );
+        assert!(LinkSimulation::parse("latency").is_err());
+        assert!(LinkSimulation::parse("latency=fast").is_err());
+        assert!(LinkSimulation::parse("ping=20").is_err());
+    }
+}
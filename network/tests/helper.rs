@@ -11,7 +11,9 @@ use std::{
 use tokio::runtime::Runtime;
 use tracing::*;
 use tracing_subscriber::EnvFilter;
-use veloren_network::{ConnectAddr, ListenAddr, Network, Participant, Pid, Promises, Stream};
+use veloren_network::{
+    ConnectAddr, LinkSimulation, ListenAddr, Network, Participant, Pid, Promises, Stream,
+};
 
 // sleep time when only internal rust calculations are done
 #[allow(dead_code)]
@@ -64,17 +66,38 @@ pub fn network_participant_stream(
     Network,
     Participant,
     Stream,
+) {
+    network_participant_stream_simulated(addr, None, Promises::ORDERED)
+}
+
+/// Like [`network_participant_stream`], but both networks send over a
+/// degraded link and the stream is opened with the given `promises`.
+#[allow(dead_code)]
+pub fn network_participant_stream_simulated(
+    addr: (ListenAddr, ConnectAddr),
+    link_simulation: Option<LinkSimulation>,
+    promises: Promises,
+) -> (
+    Arc<Runtime>,
+    Network,
+    Participant,
+    Stream,
+    Network,
+    Participant,
+    Stream,
 ) {
     let runtime = Arc::new(Runtime::new().unwrap());
     let (n_a, p1_a, s1_a, n_b, p1_b, s1_b) = runtime.block_on(async {
         let mut n_a = Network::new(Pid::fake(0), &runtime);
         let n_b = Network::new(Pid::fake(1), &runtime);
+        n_a.set_link_simulation(link_simulation);
+        n_b.set_link_simulation(link_simulation);
 
         n_a.listen(addr.0).await.unwrap();
         let mut p1_b = n_b.connect(addr.1).await.unwrap();
         let p1_a = n_a.connected().await.unwrap();
 
-        let s1_a = p1_a.open(4, Promises::ORDERED, 0).await.unwrap();
+        let s1_a = p1_a.open(4, promises, 0).await.unwrap();
         let s1_b = p1_b.opened().await.unwrap();
 
         (n_a, p1_a, s1_a, n_b, p1_b, s1_b)
@@ -3,9 +3,17 @@ use std::sync::Arc;
 use tokio::runtime::Runtime;
 use veloren_network::{NetworkError, StreamError};
 mod helper;
-use helper::{mpsc, network_participant_stream, quic, tcp, udp, SLEEP_EXTERNAL, SLEEP_INTERNAL};
-use std::io::ErrorKind;
-use veloren_network::{ConnectAddr, ListenAddr, Network, ParticipantEvent, Pid, Promises};
+use helper::{
+    mpsc, network_participant_stream, network_participant_stream_simulated, quic, tcp, udp,
+    SLEEP_EXTERNAL, SLEEP_INTERNAL,
+};
+use std::{
+    io::ErrorKind,
+    time::{Duration, Instant},
+};
+use veloren_network::{
+    ConnectAddr, LinkSimulation, ListenAddr, Network, ParticipantEvent, Pid, Promises,
+};
 
 #[test]
 fn stream_simple() {
@@ -91,7 +99,6 @@ fn stream_simple_quic_3msg() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -102,7 +109,6 @@ fn stream_simple_udp() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp_3msg() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -360,3 +366,67 @@ fn check_correct_channel_events() {
 
     drop((p_a, p_b)); //clean teardown
 }
+
+#[test]
+fn simulated_latency_delays_messages() {
+    let (_, _) = helper::setup(false, 0);
+    let sim = LinkSimulation {
+        latency_ms: 300,
+        ..Default::default()
+    };
+    let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) =
+        network_participant_stream_simulated(tcp(), Some(sim), Promises::ORDERED);
+
+    let start = Instant::now();
+    s1_a.send("Hello World").unwrap();
+    assert_eq!(r.block_on(s1_b.recv()), Ok("Hello World".to_string()));
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
+
+#[test]
+fn simulated_bad_link_keeps_reliable_streams_intact() {
+    let (_, _) = helper::setup(false, 0);
+    let sim = LinkSimulation {
+        latency_ms: 20,
+        jitter_ms: 20,
+        reorder: 0.3,
+        drop: 0.3,
+        ..Default::default()
+    };
+    let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream_simulated(
+        mpsc(),
+        Some(sim),
+        Promises::ORDERED | Promises::GUARANTEED_DELIVERY,
+    );
+
+    for i in 0..100u32 {
+        s1_a.send(i).unwrap();
+    }
+    for i in 0..100u32 {
+        assert_eq!(r.block_on(s1_b.recv()), Ok(i));
+    }
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
+
+#[test]
+fn simulated_loss_drops_unreliable_messages() {
+    let (_, _) = helper::setup(false, 0);
+    let sim = LinkSimulation {
+        drop: 0.5,
+        ..Default::default()
+    };
+    let (_r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) =
+        network_participant_stream_simulated(mpsc(), Some(sim), Promises::ORDERED);
+
+    for i in 0..100u32 {
+        s1_a.send(i).unwrap();
+    }
+    std::thread::sleep(SLEEP_INTERNAL);
+    let mut received = 0;
+    while let Ok(Some(_)) = s1_b.try_recv::<u32>() {
+        received += 1;
+    }
+    assert!(received > 0 && received < 100, "received {}", received);
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
@@ -1,9 +1,10 @@
 pub extern crate common;
 
-pub use common::comp::Health;
+pub use common::comp::{item::ItemDefinitionIdOwned, BuffKind, Health, Pos, Stats};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-pub use common::{resources::GameMode, uid::Uid};
+pub use common::{character::CharacterId, resources::GameMode, terrain::Block, uid::Uid};
+pub use vek::Vec3;
 
 mod errors;
 
@@ -35,6 +36,35 @@ pub enum Action {
     Print(String),
     PlayerSendMessage(Uid, String),
     KillEntity(Uid),
+    /// Moves an entity to a new position, dismounting it from any volume it
+    /// is riding
+    TeleportEntity(Uid, Vec3<f32>),
+    /// Sets the current health of an entity
+    SetEntityHealth(Uid, f32),
+    /// Gives an amount of the item with the given asset identifier (for
+    /// instance `common.items.food.apple`) to an entity
+    GiveItem(Uid, String, u32),
+    /// Applies a buff with the given strength and duration in seconds (`None`
+    /// lasts forever) to an entity
+    ApplyBuff(Uid, BuffKind, f32, Option<f64>),
+    /// Removes all buffs of the given kind from an entity
+    RemoveBuff(Uid, BuffKind),
+    /// Spawns an NPC described by an entity config asset (for instance
+    /// `common.entity.wild.peaceful.deer`) at a position
+    SpawnNpc(String, Vec3<f32>),
+    /// Stores a value under a key in this plugin's persistent storage,
+    /// optionally attached to a character. Values are kept across server
+    /// restarts, and character values are removed with the character. Values
+    /// can only be attached to characters that are in game.
+    SetStorageValue(Option<CharacterId>, String, Vec<u8>),
+    /// Removes a value from this plugin's persistent storage
+    DeleteStorageValue(Option<CharacterId>, String),
+    /// Starts a timer with the given name which calls `on_timer` after a delay
+    /// in seconds, and then again after each delay if it is repeating.
+    /// Starting a timer with the name of a running one replaces it.
+    StartTimer(String, f64, bool),
+    /// Stops the timer with the given name
+    CancelTimer(String),
 }
 
 /// The [`Retrieve`] enum represents read of the ECS is sync and blocking.
@@ -68,6 +98,14 @@ pub enum Action {
 pub enum Retrieve {
     GetPlayerName(Uid),
     GetEntityHealth(Uid),
+    GetEntityPosition(Uid),
+    GetEntityStats(Uid),
+    GetEntityBuffs(Uid),
+    GetEntityInventory(Uid),
+    /// Reads a value from this plugin's persistent storage. Note that values
+    /// set with [`Action::SetStorageValue`] are only visible once the action
+    /// has been applied, during the next tick.
+    GetStorageValue(Option<CharacterId>, String),
 }
 
 /// The [`RetrieveResult`] struct is generated while using the `retrieve_action`
@@ -95,6 +133,19 @@ pub enum Retrieve {
 pub enum RetrieveResult {
     GetPlayerName(String),
     GetEntityHealth(Health),
+    GetEntityPosition(Pos),
+    GetEntityStats(Stats),
+    GetEntityBuffs(Vec<BuffKind>),
+    GetEntityInventory(Vec<InventoryItem>),
+    GetStorageValue(Option<Vec<u8>>),
+}
+
+/// A stack of items held in an inventory slot, as returned by
+/// [`Retrieve::GetEntityInventory`]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct InventoryItem {
+    pub item_definition_id: ItemDefinitionIdOwned,
+    pub amount: u32,
 }
 
 /// This trait is implement by all events and ensure type safety of FFI.
@@ -195,6 +246,194 @@ pub mod event {
         fn default() -> Self { Self::None }
     }
 
+    /// This event is called when a player disconnects from the server.
+    /// Your event should be named `on_logout`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct PlayerLogoutEvent {
+        pub player: Player,
+    }
+
+    impl Event for PlayerLogoutEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_logout".to_owned() }
+    }
+
+    /// This event is called when a player enters the world with one of their
+    /// characters.
+    /// Your event should be named `on_character_select`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct CharacterSelectEvent {
+        pub player: Player,
+        pub character_id: CharacterId,
+    }
+
+    impl Event for CharacterSelectEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_character_select".to_owned() }
+    }
+
+    /// This event is called when an entity dies.
+    /// Your event should be named `on_entity_death`
+    ///
+    /// `killer` is the entity that dealt the killing blow, if there is one.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct EntityDeathEvent {
+        pub entity: Uid,
+        pub killer: Option<Uid>,
+    }
+
+    impl Event for EntityDeathEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_entity_death".to_owned() }
+    }
+
+    /// This event is called before an entity takes damage.
+    /// Your event should be named `on_entity_damage`
+    ///
+    /// `amount` is the positive amount of health that will be removed.
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_entity_damage(damage: EntityDamageEvent) -> DamageResult {
+    ///     DamageResult::SetAmount(damage.amount / 2.0)
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct EntityDamageEvent {
+        pub target: Uid,
+        pub attacker: Option<Uid>,
+        pub amount: f32,
+    }
+
+    impl Event for EntityDamageEvent {
+        type Response = DamageResult;
+
+        fn get_event_name(&self) -> String { "on_entity_damage".to_owned() }
+    }
+
+    /// This is the return type of an `on_entity_damage` event. See
+    /// [`EntityDamageEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will prevent the damage from being dealt.
+    ///  - `SetAmount` will replace the amount of damage dealt.
+    ///  - `None` will let the damage through unchanged.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub enum DamageResult {
+        Cancel,
+        SetAmount(f32),
+        None,
+    }
+
+    impl Default for DamageResult {
+        fn default() -> Self { Self::None }
+    }
+
+    /// This event is called before a player breaks a block in build mode.
+    /// Your event should be named `on_block_break`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct BlockBreakEvent {
+        pub player: Player,
+        pub pos: Vec3<i32>,
+        pub block: Block,
+    }
+
+    impl Event for BlockBreakEvent {
+        type Response = CancellableResult;
+
+        fn get_event_name(&self) -> String { "on_block_break".to_owned() }
+    }
+
+    /// This event is called before a player places a block in build mode.
+    /// Your event should be named `on_block_place`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct BlockPlaceEvent {
+        pub player: Player,
+        pub pos: Vec3<i32>,
+        pub block: Block,
+    }
+
+    impl Event for BlockPlaceEvent {
+        type Response = CancellableResult;
+
+        fn get_event_name(&self) -> String { "on_block_place".to_owned() }
+    }
+
+    /// This event is called before a player picks up an item.
+    /// Your event should be named `on_item_pickup`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct ItemPickupEvent {
+        pub player: Player,
+        pub item: InventoryItem,
+    }
+
+    impl Event for ItemPickupEvent {
+        type Response = CancellableResult;
+
+        fn get_event_name(&self) -> String { "on_item_pickup".to_owned() }
+    }
+
+    /// This is the return type of events that can be vetoed, such as
+    /// [`BlockBreakEvent`], [`BlockPlaceEvent`] and [`ItemPickupEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will prevent the action from happening.
+    ///  - `None` will let the action happen.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub enum CancellableResult {
+        Cancel,
+        None,
+    }
+
+    impl Default for CancellableResult {
+        fn default() -> Self { Self::None }
+    }
+
+    /// This event is called before a chat message sent by a player is
+    /// broadcast.
+    /// Your event should be named `on_chat_message`
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_chat_message(chat: ChatMessageEvent) -> ChatMessageResult {
+    ///     ChatMessageResult::Modify(chat.message.replace("heck", "****"))
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct ChatMessageEvent {
+        pub player: Player,
+        pub message: String,
+    }
+
+    impl Event for ChatMessageEvent {
+        type Response = ChatMessageResult;
+
+        fn get_event_name(&self) -> String { "on_chat_message".to_owned() }
+    }
+
+    /// This is the return type of an `on_chat_message` event. See
+    /// [`ChatMessageEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will drop the message.
+    ///  - `Modify` will replace the content of the message.
+    ///  - `None` will send the message unchanged.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub enum ChatMessageResult {
+        Cancel,
+        Modify(String),
+        None,
+    }
+
+    impl Default for ChatMessageResult {
+        fn default() -> Self { Self::None }
+    }
+
     /// This event is called when the plugin is loaded
     /// Your event should be named `on_load`
     ///
@@ -220,6 +459,46 @@ pub mod event {
         fn get_event_name(&self) -> String { "on_load".to_owned() }
     }
 
+    /// This event is called regularly while the server is running, every
+    /// tick unless the plugin sets a `tick_interval` (in seconds) in its
+    /// `plugin.toml`.
+    /// Your event should be named `on_tick`
+    ///
+    /// `dt` is the time in seconds since this event was last called.
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_tick(tick: PluginTickEvent) {
+    ///     emit_action(Action::Print(format!("{} seconds passed", tick.dt)));
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct PluginTickEvent {
+        pub time: f64,
+        pub dt: f64,
+    }
+
+    impl Event for PluginTickEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_tick".to_owned() }
+    }
+
+    /// This event is called when a timer started with [`Action::StartTimer`]
+    /// fires.
+    /// Your event should be named `on_timer`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct TimerEvent {
+        pub name: String,
+    }
+
+    impl Event for TimerEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_timer".to_owned() }
+    }
+
     // impl Default for PlayerJoinResult {
     //     fn default() -> Self {
     //         Self::None
@@ -1,6 +1,7 @@
 pub extern crate plugin_derive;
 
 pub mod retrieve;
+pub mod storage;
 
 use api::RetrieveError;
 pub use retrieve::*;
@@ -1,4 +1,4 @@
-use plugin_api::{Health, RetrieveError};
+use plugin_api::{BuffKind, Health, InventoryItem, Pos, RetrieveError, Stats};
 
 use crate::api::{Retrieve, RetrieveResult};
 
@@ -33,3 +33,67 @@ impl GetPlayerName for crate::api::event::Player {
         }
     }
 }
+
+pub trait GetEntityPosition {
+    fn get_entity_position(&self) -> Result<Pos, RetrieveError>;
+}
+
+pub trait GetEntityStats {
+    fn get_entity_stats(&self) -> Result<Stats, RetrieveError>;
+}
+
+pub trait GetEntityBuffs {
+    fn get_entity_buffs(&self) -> Result<Vec<BuffKind>, RetrieveError>;
+}
+
+pub trait GetEntityInventory {
+    fn get_entity_inventory(&self) -> Result<Vec<InventoryItem>, RetrieveError>;
+}
+
+impl GetEntityPosition for crate::api::event::Player {
+    fn get_entity_position(&self) -> Result<Pos, RetrieveError> {
+        if let RetrieveResult::GetEntityPosition(e) =
+            crate::retrieve_action(&Retrieve::GetEntityPosition(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+impl GetEntityStats for crate::api::event::Player {
+    fn get_entity_stats(&self) -> Result<Stats, RetrieveError> {
+        if let RetrieveResult::GetEntityStats(e) =
+            crate::retrieve_action(&Retrieve::GetEntityStats(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+impl GetEntityBuffs for crate::api::event::Player {
+    fn get_entity_buffs(&self) -> Result<Vec<BuffKind>, RetrieveError> {
+        if let RetrieveResult::GetEntityBuffs(e) =
+            crate::retrieve_action(&Retrieve::GetEntityBuffs(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+impl GetEntityInventory for crate::api::event::Player {
+    fn get_entity_inventory(&self) -> Result<Vec<InventoryItem>, RetrieveError> {
+        if let RetrieveResult::GetEntityInventory(e) =
+            crate::retrieve_action(&Retrieve::GetEntityInventory(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Typed access to the plugin's persistent key-value storage.
+//!
+//! Values are serialized with bincode, and can optionally be attached to a
+//! character so that they are removed along with it.
+
+use plugin_api::{CharacterId, RetrieveError};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::api::{Action, Retrieve, RetrieveResult};
+
+/// Reads the value stored under `key`, if there is one. Values written during
+/// the current tick are only visible from the next one.
+pub fn get_value<T: DeserializeOwned>(
+    character_id: Option<CharacterId>,
+    key: &str,
+) -> Result<Option<T>, RetrieveError> {
+    if let RetrieveResult::GetStorageValue(value) =
+        crate::retrieve_action(&Retrieve::GetStorageValue(character_id, key.to_owned()))?
+    {
+        value
+            .map(|bytes| bincode::deserialize(&bytes))
+            .transpose()
+            .map_err(|e| RetrieveError::BincodeError(e.to_string()))
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Stores `value` under `key`, replacing any previous value
+pub fn set_value<T: Serialize>(character_id: Option<CharacterId>, key: &str, value: &T) {
+    let value = bincode::serialize(value).expect("Can't serialize storage value");
+    crate::emit_action(Action::SetStorageValue(character_id, key.to_owned(), value));
+}
+
+/// Removes the value stored under `key`
+pub fn delete_value(character_id: Option<CharacterId>, key: &str) {
+    crate::emit_action(Action::DeleteStorageValue(character_id, key.to_owned()));
+}
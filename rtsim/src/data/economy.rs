@@ -0,0 +1,478 @@
+use crate::data::{Factions, Sites};
+use common::{
+    rtsim::{ChunkResource, SiteId},
+    trade::{Good, SiteInformation, SitePrices},
+};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// How strongly prices react to a good being scarce or plentiful.
+const PRICE_ELASTICITY: f32 = 0.5;
+/// Prices never move further than this factor away from the prices the world
+/// simulation came up with.
+const MAX_PRICE_FACTOR: f32 = 4.0;
+/// Stock that is always assumed to be there when working out prices, so that
+/// goods the site barely deals in don't swing wildly in price.
+const STOCK_BUFFER: f32 = 1.0;
+/// How much a single merchant can carry between sites.
+const CARAVAN_CAPACITY: f32 = 40.0;
+/// Merchants only bother carrying goods that sell for at least this much more
+/// at the site they take them to.
+const MIN_TRADE_MARGIN: f32 = 1.2;
+/// The fraction of its usual stock that a site must fall below before it is
+/// considered to be short of a good.
+const SCARCITY: f32 = 0.5;
+/// The time, in seconds, it takes a site to make up for most of the difference
+/// between its stock and the stock that its production and consumption settle
+/// on.
+const RECOVERY_TIME: f32 = 60.0 * 60.0;
+
+/// The economy of a site while the game is running.
+///
+/// The world simulation runs the full economic simulation during worldgen, but
+/// that is frozen afterwards. This starts out from where the world simulation
+/// left off and keeps track of how the stock of goods changes as they are
+/// traded, produced and consumed, pricing goods by how scarce they are
+/// compared to where the site's economy would settle on its own.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SiteEconomy {
+    /// The amount of each good that the site has in stock.
+    pub stocks: HashMap<Good, f32>,
+    /// The stock of each good that the production and consumption of the site
+    /// settle on, as found by the world simulation.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub equilibrium: HashMap<Good, f32>,
+    /// The prices that the world simulation came up with for the site.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub base_prices: HashMap<Good, f32>,
+    /// The sites that the merchants of this site trade with, nearest first.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub trade_partners: Vec<SiteId>,
+}
+
+impl SiteEconomy {
+    /// Whether the site takes part in the economy at all.
+    pub fn is_active(&self) -> bool { !self.base_prices.is_empty() }
+
+    pub fn stock(&self, good: Good) -> f32 { self.stocks.get(&good).copied().unwrap_or(0.0) }
+
+    pub fn equilibrium(&self, good: Good) -> f32 {
+        self.equilibrium.get(&good).copied().unwrap_or(0.0).max(0.0)
+    }
+
+    /// Whether the stock of a good has fallen far below what the site usually
+    /// has.
+    pub fn is_short_of(&self, good: Good) -> bool {
+        let equilibrium = self.equilibrium(good);
+        equilibrium > 0.0 && self.stock(good) < equilibrium * SCARCITY
+    }
+
+    /// Adds (or, if negative, removes) some of a good to the stock.
+    pub fn change_stock(&mut self, good: Good, amount: f32) {
+        let stock = self.stocks.entry(good).or_default();
+        *stock = (*stock + amount).max(0.0);
+    }
+
+    /// Moves the stock towards what production and consumption settle on over
+    /// `dt` seconds. `availability` is the fraction of the natural resources
+    /// a good is made from that is left around the site, which lowers the
+    /// stock the site settles on.
+    pub fn produce_and_consume(&mut self, availability: impl Fn(Good) -> f32, dt: f32) {
+        let recovery = 1.0 - (-dt / RECOVERY_TIME).exp();
+        let goods = self.equilibrium.keys().copied().collect::<Vec<_>>();
+        for good in goods {
+            let target = self.equilibrium(good) * availability(good);
+            let stock = self.stock(good);
+            self.change_stock(good, (target - stock) * recovery);
+        }
+    }
+
+    /// The price of a good, relative to the other goods at this site.
+    pub fn price(&self, good: Good) -> Option<f32> {
+        let base = *self.base_prices.get(&good)?;
+        // Coin is what everything else is measured in
+        if good == Good::Coin {
+            return Some(base);
+        }
+        let scarcity = ((self.equilibrium(good) + STOCK_BUFFER)
+            / (self.stock(good) + STOCK_BUFFER))
+            .powf(PRICE_ELASTICITY)
+            .clamp(1.0 / MAX_PRICE_FACTOR, MAX_PRICE_FACTOR);
+        Some(base * scarcity)
+    }
+
+    pub fn prices(&self) -> SitePrices {
+        SitePrices {
+            values: self
+                .base_prices
+                .keys()
+                .filter_map(|good| Some((*good, self.price(*good)?)))
+                .collect(),
+        }
+    }
+
+    /// What merchants of the site have to offer.
+    pub fn trade_information(&self, id: common::trade::SiteId) -> SiteInformation {
+        SiteInformation {
+            id,
+            unconsumed_stock: self.stocks.clone(),
+        }
+    }
+}
+
+/// Goods that a merchant is carrying from one site to another. They only
+/// count towards the stock of the site they are taken to once the merchant
+/// gets there, and are lost if the merchant dies on the way.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cargo {
+    pub from: SiteId,
+    pub to: SiteId,
+    pub goods: Vec<(Good, f32)>,
+}
+
+impl Cargo {
+    /// Adds (or, if negative, removes) some of a good to the cargo.
+    pub fn change_goods(&mut self, good: Good, amount: f32) {
+        match self.goods.iter_mut().find(|(carried, _)| *carried == good) {
+            Some((_, carried)) => *carried = (*carried + amount).max(0.0),
+            None => self.goods.push((good, amount.max(0.0))),
+        }
+        self.goods.retain(|(_, amount)| *amount > 0.0);
+    }
+
+    /// What the merchant carrying the cargo has to offer.
+    pub fn trade_information(&self, id: common::trade::SiteId) -> SiteInformation {
+        SiteInformation {
+            id,
+            unconsumed_stock: self.goods.iter().copied().collect(),
+        }
+    }
+}
+
+/// Picks the trade partner of a site that its surplus goods fetch the best
+/// price at, and the goods a merchant should take there.
+pub fn plan_cargo(sites: &Sites, from: SiteId) -> Option<Cargo> {
+    let site = sites.get(from)?;
+    site.economy
+        .trade_partners
+        .iter()
+        .filter_map(|partner_id| {
+            let partner = sites.get(*partner_id)?;
+            let mut goods = site
+                .economy
+                .stocks
+                .iter()
+                .filter(|(good, _)| **good != Good::Coin)
+                .filter_map(|(good, stock)| {
+                    let surplus = stock - site.economy.equilibrium(*good);
+                    let margin = partner.economy.price(*good)? / site.economy.price(*good)?;
+                    (surplus > 0.0 && margin >= MIN_TRADE_MARGIN)
+                        .then_some((*good, surplus, margin))
+                })
+                .collect::<Vec<_>>();
+            goods.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+
+            let mut space = CARAVAN_CAPACITY;
+            let mut profit = 0.0;
+            let goods = goods
+                .into_iter()
+                .map_while(|(good, surplus, margin)| {
+                    let amount = surplus.min(space);
+                    space -= amount;
+                    profit += amount * (margin - 1.0);
+                    (amount > 0.0).then_some((good, amount))
+                })
+                .collect::<Vec<_>>();
+            (!goods.is_empty()).then_some((
+                Cargo {
+                    from,
+                    to: *partner_id,
+                    goods,
+                },
+                profit,
+            ))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(cargo, _)| cargo)
+}
+
+/// Whether the cargo can still be sold where it is being taken, which is no
+/// longer the case once the destination is gone or is held by a faction that
+/// is at war with the holder of the site the cargo came from.
+pub fn is_deliverable(sites: &Sites, factions: &Factions, cargo: &Cargo) -> bool {
+    let from = sites.get(cargo.from).and_then(|site| site.faction);
+    sites
+        .get(cargo.to)
+        .map_or(false, |to| !factions.are_hostile(from, to.faction))
+}
+
+/// Takes the goods of the cargo out of the stock of the site it leaves from,
+/// returning what could actually be loaded.
+pub fn load_cargo(sites: &mut Sites, mut cargo: Cargo) -> Cargo {
+    if let Some(site) = sites.get_mut(cargo.from) {
+        for (good, amount) in cargo.goods.iter_mut() {
+            *amount = amount.min(site.economy.stock(*good));
+            site.economy.change_stock(*good, -*amount);
+        }
+    } else {
+        cargo.goods.clear();
+    }
+    cargo.goods.retain(|(_, amount)| *amount > 0.0);
+    cargo
+}
+
+/// Adds the goods of the cargo to the stock of the site it was taken to, which
+/// pays the site it came from for them at its current prices.
+pub fn deliver_cargo(sites: &mut Sites, cargo: Cargo) {
+    let Some(site) = sites.get_mut(cargo.to) else {
+        return;
+    };
+    let coin_price = site
+        .economy
+        .price(Good::Coin)
+        .unwrap_or(1.0)
+        .max(f32::EPSILON);
+    let value = cargo
+        .goods
+        .iter()
+        .map(|(good, amount)| site.economy.price(*good).unwrap_or(0.0) * amount)
+        .sum::<f32>();
+    let coins = (value / coin_price).min(site.economy.stock(Good::Coin));
+    for (good, amount) in cargo.goods {
+        site.economy.change_stock(good, amount);
+    }
+    site.economy.change_stock(Good::Coin, -coins);
+
+    if let Some(site) = sites.get_mut(cargo.from) {
+        site.economy.change_stock(Good::Coin, coins);
+    }
+}
+
+/// Puts the goods of a cargo that could not be delivered back into the stock of
+/// the site it came from, without anybody being paid for them.
+pub fn return_cargo(sites: &mut Sites, cargo: Cargo) {
+    if let Some(site) = sites.get_mut(cargo.from) {
+        for (good, amount) in cargo.goods {
+            site.economy.change_stock(good, amount);
+        }
+    }
+}
+
+/// The good that gathering a natural resource around a site produces, if any.
+pub fn resource_good(resource: ChunkResource) -> Option<Good> {
+    match resource {
+        ChunkResource::Wood => Some(Good::Wood),
+        ChunkResource::Stone | ChunkResource::Ore | ChunkResource::Gem => Some(Good::Stone),
+        ChunkResource::Fruit | ChunkResource::Vegetable | ChunkResource::Mushroom => {
+            Some(Good::Food)
+        },
+        ChunkResource::Plant | ChunkResource::Flower | ChunkResource::Grass => {
+            Some(Good::Ingredients)
+        },
+        ChunkResource::Loot => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Site;
+
+    fn economy(goods: &[(Good, f32, f32, f32)]) -> SiteEconomy {
+        let mut economy = SiteEconomy::default();
+        for (good, base_price, equilibrium, stock) in goods {
+            economy.base_prices.insert(*good, *base_price);
+            economy.equilibrium.insert(*good, *equilibrium);
+            economy.stocks.insert(*good, *stock);
+        }
+        economy
+    }
+
+    fn site(economy: SiteEconomy) -> Site {
+        Site {
+            economy,
+            ..Site::at(Default::default())
+        }
+    }
+
+    #[test]
+    fn price_at_equilibrium_is_base_price() {
+        let economy = economy(&[(Good::Food, 2.0, 100.0, 100.0)]);
+        assert_eq!(economy.price(Good::Food), Some(2.0));
+        assert_eq!(economy.price(Good::Wood), None);
+    }
+
+    #[test]
+    fn scarcity_raises_prices() {
+        let plentiful = economy(&[(Good::Food, 2.0, 100.0, 200.0)]);
+        let settled = economy(&[(Good::Food, 2.0, 100.0, 100.0)]);
+        let scarce = economy(&[(Good::Food, 2.0, 100.0, 20.0)]);
+        let plentiful = plentiful.price(Good::Food).unwrap();
+        let settled = settled.price(Good::Food).unwrap();
+        let scarce = scarce.price(Good::Food).unwrap();
+        assert!(plentiful < settled);
+        assert!(settled < scarce);
+    }
+
+    #[test]
+    fn prices_are_clamped() {
+        let sold_out = economy(&[(Good::Food, 2.0, 1000.0, 0.0)]);
+        assert_eq!(sold_out.price(Good::Food), Some(2.0 * MAX_PRICE_FACTOR));
+        let flooded = economy(&[(Good::Food, 2.0, 0.0, 1000.0)]);
+        assert_eq!(flooded.price(Good::Food), Some(2.0 / MAX_PRICE_FACTOR));
+        // Coin is what prices are measured in, it doesn't get more valuable
+        let broke = economy(&[(Good::Coin, 1.0, 1000.0, 0.0)]);
+        assert_eq!(broke.price(Good::Coin), Some(1.0));
+    }
+
+    #[test]
+    fn negative_equilibrium_counts_as_none() {
+        let economy = economy(&[(Good::Food, 2.0, -50.0, 0.0)]);
+        assert_eq!(economy.equilibrium(Good::Food), 0.0);
+        assert!(!economy.is_short_of(Good::Food));
+        assert_eq!(economy.price(Good::Food), Some(2.0));
+    }
+
+    #[test]
+    fn stock_recovers_towards_equilibrium() {
+        let mut short = economy(&[(Good::Food, 2.0, 100.0, 20.0)]);
+        let mut surplus = economy(&[(Good::Food, 2.0, 100.0, 180.0)]);
+        short.produce_and_consume(|_| 1.0, RECOVERY_TIME);
+        surplus.produce_and_consume(|_| 1.0, RECOVERY_TIME);
+        let short = short.stock(Good::Food);
+        let surplus = surplus.stock(Good::Food);
+        assert!(short > 20.0 && short < 100.0, "{short}");
+        assert!(surplus < 180.0 && surplus > 100.0, "{surplus}");
+        // Most of the difference is made up for within the recovery time
+        assert!(short > 60.0 && surplus < 140.0);
+
+        let mut settled = economy(&[(Good::Food, 2.0, 100.0, 20.0)]);
+        for _ in 0..100 {
+            settled.produce_and_consume(|_| 1.0, RECOVERY_TIME);
+        }
+        assert!((settled.stock(Good::Food) - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn depleted_resources_lower_the_stock_settled_on() {
+        let mut economy = economy(&[
+            (Good::Wood, 1.0, 100.0, 100.0),
+            (Good::Food, 1.0, 100.0, 100.0),
+        ]);
+        let availability = |good| if good == Good::Wood { 0.25 } else { 1.0 };
+        for _ in 0..100 {
+            economy.produce_and_consume(availability, RECOVERY_TIME);
+        }
+        assert!((economy.stock(Good::Wood) - 25.0).abs() < 0.01);
+        assert!((economy.stock(Good::Food) - 100.0).abs() < 0.01);
+        assert!(economy.is_short_of(Good::Wood));
+        assert!(economy.price(Good::Wood).unwrap() > economy.price(Good::Food).unwrap());
+    }
+
+    #[test]
+    fn plan_cargo_picks_the_most_profitable_partner() {
+        let mut sites = Sites::default();
+        let from = sites.create(site(economy(&[
+            (Good::Food, 1.0, 50.0, 150.0),
+            (Good::Wood, 1.0, 50.0, 60.0),
+            (Good::Stone, 1.0, 50.0, 100.0),
+        ])));
+        // Pays well for wood, but too little for food to be worth the trip
+        let near = sites.create(site(economy(&[
+            (Good::Food, 0.6, 50.0, 50.0),
+            (Good::Wood, 1.5, 50.0, 50.0),
+        ])));
+        // Pays well for food and stone, but too little for wood
+        let far = sites.create(site(economy(&[
+            (Good::Food, 1.0, 50.0, 50.0),
+            (Good::Wood, 1.0, 50.0, 50.0),
+            (Good::Stone, 1.0, 50.0, 50.0),
+        ])));
+
+        sites.get_mut(from).unwrap().economy.trade_partners = vec![near];
+        let cargo = plan_cargo(&sites, from).unwrap();
+        assert_eq!(cargo.to, near);
+        assert_eq!(cargo.goods, vec![(Good::Wood, 10.0)]);
+
+        // Food fetches the best margin and fills the caravan on its own
+        sites.get_mut(from).unwrap().economy.trade_partners = vec![near, far];
+        let cargo = plan_cargo(&sites, from).unwrap();
+        assert_eq!(cargo.from, from);
+        assert_eq!(cargo.to, far);
+        assert_eq!(cargo.goods, vec![(Good::Food, CARAVAN_CAPACITY)]);
+    }
+
+    #[test]
+    fn plan_cargo_fills_the_caravan_best_margin_first() {
+        let mut sites = Sites::default();
+        let from = sites.create(site(economy(&[
+            (Good::Food, 1.0, 50.0, 80.0),
+            (Good::Wood, 1.0, 50.0, 80.0),
+            (Good::Coin, 1.0, 50.0, 1000.0),
+        ])));
+        let to = sites.create(site(economy(&[
+            (Good::Food, 2.0, 50.0, 50.0),
+            (Good::Wood, 1.5, 50.0, 50.0),
+            (Good::Coin, 1.0, 50.0, 0.0),
+        ])));
+        sites.get_mut(from).unwrap().economy.trade_partners = vec![to];
+        let cargo = plan_cargo(&sites, from).unwrap();
+        assert_eq!(cargo.goods, vec![
+            (Good::Food, 30.0),
+            (Good::Wood, CARAVAN_CAPACITY - 30.0)
+        ]);
+
+        // Nothing is worth carrying to a site that pays less
+        sites.get_mut(to).unwrap().economy = economy(&[(Good::Food, 0.5, 50.0, 50.0)]);
+        assert!(plan_cargo(&sites, from).is_none());
+    }
+
+    #[test]
+    fn loading_is_limited_by_stock() {
+        let mut sites = Sites::default();
+        let from = sites.create(site(economy(&[
+            (Good::Food, 1.0, 50.0, 10.0),
+            (Good::Wood, 1.0, 50.0, 0.0),
+        ])));
+        let to = sites.create(site(economy(&[])));
+        let cargo = load_cargo(&mut sites, Cargo {
+            from,
+            to,
+            goods: vec![(Good::Food, 25.0), (Good::Wood, 5.0)],
+        });
+        assert_eq!(cargo.goods, vec![(Good::Food, 10.0)]);
+        assert_eq!(sites.get(from).unwrap().economy.stock(Good::Food), 0.0);
+    }
+
+    #[test]
+    fn delivery_pays_no_more_than_the_destination_has() {
+        let mut sites = Sites::default();
+        let from = sites.create(site(economy(&[(Good::Coin, 1.0, 0.0, 0.0)])));
+        let rich = sites.create(site(economy(&[
+            (Good::Food, 2.0, 100.0, 100.0),
+            (Good::Coin, 1.0, 100.0, 100.0),
+        ])));
+        let poor = sites.create(site(economy(&[
+            (Good::Food, 2.0, 100.0, 100.0),
+            (Good::Coin, 1.0, 100.0, 5.0),
+        ])));
+
+        deliver_cargo(&mut sites, Cargo {
+            from,
+            to: rich,
+            goods: vec![(Good::Food, 10.0)],
+        });
+        assert_eq!(sites.get(from).unwrap().economy.stock(Good::Coin), 20.0);
+        assert_eq!(sites.get(rich).unwrap().economy.stock(Good::Coin), 80.0);
+        assert_eq!(sites.get(rich).unwrap().economy.stock(Good::Food), 110.0);
+
+        deliver_cargo(&mut sites, Cargo {
+            from,
+            to: poor,
+            goods: vec![(Good::Food, 10.0)],
+        });
+        assert_eq!(sites.get(from).unwrap().economy.stock(Good::Coin), 25.0);
+        assert_eq!(sites.get(poor).unwrap().economy.stock(Good::Coin), 0.0);
+        assert_eq!(sites.get(poor).unwrap().economy.stock(Good::Food), 110.0);
+    }
+}
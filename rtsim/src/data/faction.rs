@@ -1,10 +1,12 @@
-use crate::data::Sentiments;
+use crate::{data::Sentiments, gen::name};
 use common::rtsim::Actor;
 pub use common::rtsim::FactionId;
+use hashbrown::HashSet;
 use serde::{Deserialize, Serialize};
 use slotmap::HopSlotMap;
 use std::ops::{Deref, DerefMut};
 use vek::*;
+use world::util::RandomPerm;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Faction {
@@ -14,9 +16,17 @@ pub struct Faction {
 
     #[serde(default)]
     pub sentiments: Sentiments,
+
+    /// The factions that this faction has declared hostility toward, and so
+    /// raids the sites of.
+    #[serde(default)]
+    pub hostile_to: HashSet<FactionId>,
 }
 
 impl Faction {
+    // TODO: Persist names, like NPC names
+    pub fn get_name(&self) -> String { name::generate(&mut RandomPerm::new(self.seed)) }
+
     pub fn cleanup(&mut self) {
         self.sentiments
             .cleanup(crate::data::sentiment::FACTION_MAX_SENTIMENTS);
@@ -26,10 +36,23 @@ impl Faction {
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Factions {
     pub factions: HopSlotMap<FactionId, Faction>,
+    /// Whether the holders of sites, or the hostilities between factions,
+    /// changed since characters were last told about them.
+    #[serde(skip)]
+    pub changed: bool,
 }
 
 impl Factions {
     pub fn create(&mut self, faction: Faction) -> FactionId { self.factions.insert(faction) }
+
+    /// Whether either of two factions has declared hostility toward the other.
+    pub fn are_hostile(&self, a: Option<FactionId>, b: Option<FactionId>) -> bool {
+        let Some((a, b)) = a.zip(b) else {
+            return false;
+        };
+        self.get(a).map_or(false, |a| a.hostile_to.contains(&b))
+            || self.get(b).map_or(false, |b| b.hostile_to.contains(&a))
+    }
 }
 
 impl Deref for Factions {
@@ -1,14 +1,18 @@
+pub mod economy;
 pub mod faction;
 pub mod nature;
 pub mod npc;
+pub mod quest;
 pub mod report;
 pub mod sentiment;
 pub mod site;
 
 pub use self::{
+    economy::SiteEconomy,
     faction::{Faction, FactionId, Factions},
     nature::Nature,
     npc::{Npc, NpcId, Npcs},
+    quest::{Quest, QuestId, QuestObjective, Quests},
     report::{Report, ReportId, ReportKind, Reports},
     sentiment::{Sentiment, Sentiments},
     site::{Site, SiteId, Sites},
@@ -46,6 +50,8 @@ pub struct Data {
     pub factions: Factions,
     #[serde(default)]
     pub reports: Reports,
+    #[serde(default)]
+    pub quests: Quests,
 
     #[serde(default)]
     pub tick: u64,
@@ -75,6 +81,23 @@ impl fmt::Debug for ReadError {
 pub type WriteError = rmp_serde::encode::Error;
 
 impl Data {
+    /// Data that doesn't belong to any world, for testing rules.
+    #[cfg(test)]
+    pub fn empty() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            nature: Nature::empty(),
+            npcs: Default::default(),
+            sites: Default::default(),
+            factions: Default::default(),
+            reports: Default::default(),
+            quests: Default::default(),
+            tick: 0,
+            time_of_day: TimeOfDay(0.0),
+            should_purge: false,
+        }
+    }
+
     pub fn spawn_npc(&mut self, npc: Npc) -> NpcId {
         let home = npc.home;
         let id = self.npcs.create_npc(npc);
@@ -84,6 +107,32 @@ impl Data {
         id
     }
 
+    /// Whether a site already knows of a report of the given kind.
+    pub fn site_knows(&self, site: SiteId, kind: ReportKind) -> bool {
+        self.sites.get(site).map_or(false, |site| {
+            site.known_reports
+                .iter()
+                .filter_map(|report| self.reports.get(*report))
+                .any(|report| report.kind == kind)
+        })
+    }
+
+    /// Creates a report that the whole of a site knows of straight away, unless
+    /// it already does. Returns whether the report is new.
+    pub fn report_to_site(&mut self, site: SiteId, kind: ReportKind) -> bool {
+        if self.site_knows(site, kind) {
+            return false;
+        }
+        let report = self.reports.create(Report {
+            kind,
+            at: self.time_of_day,
+        });
+        if let Some(site) = self.sites.get_mut(site) {
+            site.known_reports.insert(report);
+        }
+        true
+    }
+
     pub fn from_reader<R: Read>(reader: R) -> Result<Box<Self>, ReadError> {
         rmp_serde::decode::from_read(reader)
             .map_err(ReadError::Load)
@@ -24,6 +24,16 @@ impl Nature {
         }
     }
 
+    /// Nature without any chunks, for testing rules that don't touch it.
+    #[cfg(test)]
+    pub fn empty() -> Self {
+        Self {
+            chunks: Grid::new(Vec2::zero(), Chunk {
+                res: EnumMap::default(),
+            }),
+        }
+    }
+
     // TODO: Clean up this API a bit
     pub fn get_chunk_resources(&self, key: Vec2<i32>) -> EnumMap<ChunkResource, f32> {
         self.chunks.get(key).map(|c| c.res).unwrap_or_default()
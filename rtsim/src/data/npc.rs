@@ -1,6 +1,6 @@
 use crate::{
     ai::Action,
-    data::{Reports, Sentiments},
+    data::{economy::Cargo, Reports, Sentiments},
     gen::name,
 };
 pub use common::rtsim::{NpcId, Profession};
@@ -9,8 +9,8 @@ use common::{
     comp,
     grid::Grid,
     rtsim::{
-        Actor, ChunkResource, FactionId, NpcAction, NpcActivity, NpcInput, Personality, ReportId,
-        Role, SiteId,
+        Actor, ChunkResource, FactionId, NpcAction, NpcActivity, NpcInput, Personality, QuestId,
+        ReportId, Role, SiteId,
     },
     store::Id,
     terrain::CoordinateConversions,
@@ -54,12 +54,23 @@ pub struct PathingMemory {
     pub intersite_path: Option<(PathData<(Id<Track>, bool), SiteId>, usize)>,
 }
 
+pub enum CargoAction {
+    /// Load the cargo at the site it leaves from.
+    Load(Cargo),
+    /// Hand the cargo over to the site it was taken to or, if the merchant
+    /// didn't make it there, give it back to the site it came from.
+    Unload,
+}
+
 #[derive(Default)]
 pub struct Controller {
     pub actions: Vec<NpcAction>,
     pub activity: Option<NpcActivity>,
     pub new_home: Option<SiteId>,
+    pub cargo_action: Option<CargoAction>,
     pub look_dir: Option<Dir>,
+    /// Reports to pass on to other NPCs.
+    pub shared_reports: Vec<(NpcId, ReportId)>,
 }
 
 impl Controller {
@@ -90,6 +101,26 @@ impl Controller {
     }
 
     pub fn set_new_home(&mut self, new_home: SiteId) { self.new_home = Some(new_home); }
+
+    pub fn offer_quest(&mut self, target: impl Into<Actor>, quest: QuestId) {
+        self.actions
+            .push(NpcAction::OfferQuest(target.into(), quest));
+    }
+
+    pub fn complete_quest(&mut self, target: impl Into<Actor>, quest: QuestId) {
+        self.actions
+            .push(NpcAction::CompleteQuest(target.into(), quest));
+    }
+
+    pub fn load_cargo(&mut self, cargo: Cargo) {
+        self.cargo_action = Some(CargoAction::Load(cargo));
+    }
+
+    pub fn unload_cargo(&mut self) { self.cargo_action = Some(CargoAction::Unload); }
+
+    pub fn share_report(&mut self, npc: NpcId, report: ReportId) {
+        self.shared_reports.push((npc, report));
+    }
 }
 
 pub struct Brain {
@@ -119,6 +150,13 @@ pub struct Npc {
     #[serde(default)]
     pub sentiments: Sentiments,
 
+    /// The goods that the NPC is carrying to another site, if any.
+    #[serde(default)]
+    pub cargo: Option<Cargo>,
+    /// The site that the NPC is off to raid, if any.
+    #[serde(default)]
+    pub raid: Option<SiteId>,
+
     // Unpersisted state
     #[serde(skip)]
     pub chunk_pos: Option<Vec2<i32>>,
@@ -156,6 +194,8 @@ impl Clone for Npc {
             body: self.body,
             personality: self.personality,
             sentiments: self.sentiments.clone(),
+            cargo: self.cargo.clone(),
+            raid: self.raid,
             // Not persisted
             chunk_pos: None,
             current_site: Default::default(),
@@ -186,6 +226,8 @@ impl Npc {
             faction: None,
             is_dead: false,
             known_reports: Default::default(),
+            cargo: None,
+            raid: None,
             chunk_pos: None,
             current_site: None,
             controller: Default::default(),
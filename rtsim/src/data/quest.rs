@@ -0,0 +1,452 @@
+use crate::data::{Data, NpcId, Npcs, SiteId};
+use common::{
+    character::CharacterId,
+    comp::{Content, Item, LocalizationArg},
+    resources::TimeOfDay,
+    rtsim::QuestInfo,
+};
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+use slotmap::HopSlotMap;
+use std::ops::Deref;
+use world::IndexRef;
+
+pub use common::rtsim::QuestId;
+
+/// The most quests that a single character may have taken on at once.
+pub const MAX_TAKEN_QUESTS: usize = 5;
+
+/// A task that an NPC wants done, which characters may take on in exchange for
+/// a reward.
+///
+/// Quests are not written by hand, they arise from the state of rtsim: a site
+/// running low on a good, a monster that has been killing people, a merchant
+/// about to set out on a long road, etc. They are offered to characters through
+/// dialogue with the NPC that gives them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Quest {
+    pub giver: NpcId,
+    pub objective: QuestObjective,
+    /// The asset ids and amounts of the items the quest is rewarded with.
+    pub rewards: Vec<(String, u32)>,
+    pub taken_by: Option<CharacterId>,
+    /// When the quest was last taken on.
+    #[serde(default)]
+    pub taken_at: TimeOfDay,
+    /// The characters that the giver offered the quest to, who are the only
+    /// ones that may take it on.
+    #[serde(skip)]
+    pub offered_to: HashSet<CharacterId>,
+    /// Whether the objective was achieved and the quest only needs handing in
+    /// to the giver. Objectives that are achieved by handing the quest in
+    /// (fetching and delivering) never get marked as done.
+    pub done: bool,
+    pub created: TimeOfDay,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum QuestObjective {
+    /// Kill the given NPC.
+    Kill { target: NpcId },
+    /// Bring the given amount of an item to the giver.
+    Fetch { item: String, amount: u32 },
+    /// Accompany the giver on their way to a site.
+    Escort { site: SiteId },
+    /// Take a message from the giver to another NPC, who hands the quest in.
+    Deliver { to: NpcId },
+}
+
+const DAYS: f64 = 60.0 * 60.0 * 24.0;
+
+impl Quest {
+    /// The time, in in-game seconds, for which a quest stays on offer before
+    /// the giver stops looking for someone to take it on.
+    fn offer_for(&self) -> f64 {
+        match &self.objective {
+            // Merchants don't wait around for long
+            QuestObjective::Escort { .. } => DAYS * 0.5,
+            QuestObjective::Kill { .. }
+            | QuestObjective::Fetch { .. }
+            | QuestObjective::Deliver { .. } => DAYS * 3.0,
+        }
+    }
+
+    /// The time, in in-game seconds, that the taker of a quest has to hand it
+    /// in before it fails.
+    fn take_for(&self) -> f64 {
+        match &self.objective {
+            // Escorts fail once the giver arrives anyway
+            QuestObjective::Escort { .. } => DAYS * 2.0,
+            QuestObjective::Kill { .. }
+            | QuestObjective::Fetch { .. }
+            | QuestObjective::Deliver { .. } => DAYS * 7.0,
+        }
+    }
+
+    /// The NPC that the quest has to be handed in to.
+    pub fn receiver(&self) -> NpcId {
+        match &self.objective {
+            QuestObjective::Deliver { to } => *to,
+            QuestObjective::Kill { .. }
+            | QuestObjective::Fetch { .. }
+            | QuestObjective::Escort { .. } => self.giver,
+        }
+    }
+
+    /// Whether the quest can be handed in, as far as rtsim can tell. Fetch
+    /// quests additionally need the taker to have the items on them.
+    pub fn can_hand_in(&self) -> bool {
+        match &self.objective {
+            QuestObjective::Kill { .. } | QuestObjective::Escort { .. } => self.done,
+            QuestObjective::Fetch { .. } | QuestObjective::Deliver { .. } => true,
+        }
+    }
+
+    /// Whether the NPCs the quest depends on are still around.
+    fn is_valid(&self, npcs: &Npcs) -> bool {
+        let alive = |npc| npcs.get(npc).map_or(false, |npc| !npc.is_dead);
+        alive(self.giver)
+            && match &self.objective {
+                // The target may be gone once killed
+                QuestObjective::Kill { target } => self.done || alive(*target),
+                QuestObjective::Deliver { to } => alive(*to),
+                QuestObjective::Fetch { .. } | QuestObjective::Escort { .. } => true,
+            }
+    }
+
+    pub fn objective_content(&self, data: &Data, index: IndexRef) -> Content {
+        let npc_name = |npc| {
+            data.npcs
+                .get(npc)
+                .map(|npc| match npc.profession() {
+                    Some(_) => Content::Plain(npc.get_name()),
+                    None => npc.body.localize(),
+                })
+                .unwrap_or_else(|| Content::Plain(String::new()))
+        };
+        match &self.objective {
+            QuestObjective::Kill { target } => Content::localized_with_args(
+                "quest-objective-kill",
+                [("target", npc_name(*target))],
+            ),
+            QuestObjective::Fetch { item, amount } => {
+                let name = Item::new_from_asset(item)
+                    .map(|item| item.name().into_owned())
+                    .unwrap_or_else(|_| item.clone());
+                Content::localized_with_args("quest-objective-fetch", [
+                    ("amount", LocalizationArg::from(u64::from(*amount))),
+                    ("item", LocalizationArg::from(Content::Plain(name))),
+                ])
+            },
+            QuestObjective::Escort { site } => {
+                let site_name = data
+                    .sites
+                    .get(*site)
+                    .and_then(|site| site.world_site)
+                    .map(|ws| index.sites.get(ws).name().to_string())
+                    .unwrap_or_default();
+                Content::localized_with_args("quest-objective-escort", [
+                    ("npc", npc_name(self.giver)),
+                    ("site", Content::Plain(site_name)),
+                ])
+            },
+            QuestObjective::Deliver { to } => {
+                Content::localized_with_args("quest-objective-deliver", [("npc", npc_name(*to))])
+            },
+        }
+    }
+
+    pub fn info(&self, id: QuestId, data: &Data, index: IndexRef) -> QuestInfo {
+        QuestInfo {
+            id,
+            giver: data
+                .npcs
+                .get(self.giver)
+                .map(|npc| npc.get_name())
+                .unwrap_or_default(),
+            objective: self.objective_content(data, index),
+            rewards: self.rewards.clone(),
+            done: self.done,
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Quests {
+    pub quests: HopSlotMap<QuestId, Quest>,
+    /// Characters whose quests changed since they were last told about them.
+    #[serde(skip)]
+    pub changed: HashSet<CharacterId>,
+}
+
+impl Quests {
+    pub fn create(&mut self, quest: Quest) -> QuestId { self.quests.insert(quest) }
+
+    pub fn remove(&mut self, id: QuestId) -> Option<Quest> {
+        let quest = self.quests.remove(id)?;
+        self.changed.extend(quest.taken_by);
+        Some(quest)
+    }
+
+    /// The quests that the given character has taken on.
+    pub fn taken_by(
+        &self,
+        character: CharacterId,
+    ) -> impl Iterator<Item = (QuestId, &Quest)> + Clone + '_ {
+        self.quests
+            .iter()
+            .filter(move |(_, quest)| quest.taken_by == Some(character))
+    }
+
+    /// Offers a quest that nobody took on yet to the character. Returns
+    /// whether it was free to offer.
+    pub fn offer(&mut self, id: QuestId, character: CharacterId) -> bool {
+        match self.quests.get_mut(id) {
+            Some(quest) if quest.taken_by.is_none() => {
+                quest.offered_to.insert(character);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Has the character take on a quest that was offered to them, unless
+    /// they already took on too many. Returns whether they did.
+    pub fn accept(&mut self, id: QuestId, character: CharacterId, current_time: TimeOfDay) -> bool {
+        let taken = self.taken_by(character).count();
+        let Some(quest) = self.quests.get_mut(id) else {
+            return false;
+        };
+        if quest.taken_by.is_none()
+            && taken < MAX_TAKEN_QUESTS
+            && quest.offered_to.remove(&character)
+        {
+            quest.taken_by = Some(character);
+            quest.taken_at = current_time;
+            quest.offered_to.clear();
+            self.changed.insert(character);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn decline(&mut self, id: QuestId, character: CharacterId) {
+        if let Some(quest) = self.quests.get_mut(id) {
+            quest.offered_to.remove(&character);
+        }
+    }
+
+    /// Puts a quest that the character took on back on offer.
+    pub fn abandon(&mut self, id: QuestId, character: CharacterId) {
+        if let Some(quest) = self.quests.get_mut(id)
+            && quest.taken_by == Some(character)
+        {
+            quest.taken_by = None;
+            quest.done = false;
+            self.changed.insert(character);
+        }
+    }
+
+    /// The quest, if the character took it on and may hand it in to the
+    /// given NPC.
+    pub fn handing_in(&self, id: QuestId, character: CharacterId, to: NpcId) -> Option<&Quest> {
+        self.quests
+            .get(id)
+            .filter(|quest| quest.taken_by == Some(character) && quest.receiver() == to)
+    }
+
+    /// Forgets about a character that was deleted, along with the quests they
+    /// took on.
+    pub fn remove_character(&mut self, character: CharacterId) {
+        self.quests.retain(|_, quest| {
+            quest.offered_to.remove(&character);
+            quest.taken_by != Some(character)
+        });
+        self.changed.remove(&character);
+    }
+
+    pub fn cleanup(&mut self, npcs: &Npcs, current_time: TimeOfDay) {
+        let changed = &mut self.changed;
+        self.quests.retain(|_, quest| {
+            let keep = quest.is_valid(npcs)
+                // Forget quests that nobody took on, or that the taker didn't hand in, for too
+                // long
+                && match quest.taken_by {
+                    Some(_) => (current_time.0 - quest.taken_at.0).max(0.0) < quest.take_for(),
+                    None => (current_time.0 - quest.created.0).max(0.0) < quest.offer_for(),
+                };
+            if !keep {
+                changed.extend(quest.taken_by);
+            }
+            keep
+        });
+    }
+}
+
+impl Deref for Quests {
+    type Target = HopSlotMap<QuestId, Quest>;
+
+    fn deref(&self) -> &Self::Target { &self.quests }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Npc;
+    use common::{comp, rtsim::Role};
+    use vek::*;
+
+    fn npc(data: &mut Data) -> NpcId {
+        data.spawn_npc(Npc::new(
+            0,
+            Vec3::zero(),
+            comp::Body::Humanoid(comp::humanoid::Body::random()),
+            Role::Civilised(None),
+        ))
+    }
+
+    fn quest(giver: NpcId, objective: QuestObjective) -> Quest {
+        Quest {
+            giver,
+            objective,
+            rewards: Vec::new(),
+            taken_by: None,
+            taken_at: TimeOfDay(0.0),
+            offered_to: Default::default(),
+            done: false,
+            created: TimeOfDay(0.0),
+        }
+    }
+
+    fn fetch(giver: NpcId) -> Quest {
+        quest(giver, QuestObjective::Fetch {
+            item: "common.items.food.apple".to_string(),
+            amount: 5,
+        })
+    }
+
+    #[test]
+    fn quests_are_offered_taken_and_handed_in() {
+        let mut data = Data::empty();
+        let (giver, target) = (npc(&mut data), npc(&mut data));
+        let character = CharacterId(1);
+        let id = data
+            .quests
+            .create(quest(giver, QuestObjective::Kill { target }));
+
+        assert!(data.quests.offer(id, character));
+        assert!(data.quests.accept(id, character, TimeOfDay(10.0)));
+        assert_eq!(data.quests[id].taken_by, Some(character));
+        assert!(data.quests.changed.contains(&character));
+        // Taken quests are no longer on offer to anyone
+        assert!(!data.quests.offer(id, CharacterId(2)));
+
+        let handing_in = data.quests.handing_in(id, character, giver);
+        assert!(handing_in.map_or(false, |quest| !quest.can_hand_in()));
+        data.quests.quests[id].done = true;
+        assert!(
+            data.quests
+                .handing_in(id, character, giver)
+                .map_or(false, |quest| quest.can_hand_in())
+        );
+        // Only to the giver, and only by the taker
+        assert!(data.quests.handing_in(id, character, target).is_none());
+        assert!(data.quests.handing_in(id, CharacterId(2), giver).is_none());
+
+        data.quests.changed.clear();
+        assert!(data.quests.remove(id).is_some());
+        assert!(data.quests.changed.contains(&character));
+    }
+
+    #[test]
+    fn only_offered_quests_can_be_taken() {
+        let mut data = Data::empty();
+        let giver = npc(&mut data);
+        let (offered, other) = (CharacterId(1), CharacterId(2));
+        let id = data.quests.create(fetch(giver));
+
+        assert!(data.quests.offer(id, offered));
+        assert!(!data.quests.accept(id, other, TimeOfDay(0.0)));
+        data.quests.decline(id, offered);
+        assert!(!data.quests.accept(id, offered, TimeOfDay(0.0)));
+
+        assert!(data.quests.offer(id, offered));
+        assert!(data.quests.accept(id, offered, TimeOfDay(0.0)));
+        // Abandoned quests have to be offered again
+        data.quests.abandon(id, offered);
+        assert_eq!(data.quests[id].taken_by, None);
+        assert!(!data.quests.accept(id, offered, TimeOfDay(0.0)));
+    }
+
+    #[test]
+    fn taken_quests_are_limited() {
+        let mut data = Data::empty();
+        let giver = npc(&mut data);
+        let character = CharacterId(1);
+        for _ in 0..MAX_TAKEN_QUESTS {
+            let id = data.quests.create(fetch(giver));
+            data.quests.offer(id, character);
+            assert!(data.quests.accept(id, character, TimeOfDay(0.0)));
+        }
+
+        let id = data.quests.create(fetch(giver));
+        data.quests.offer(id, character);
+        assert!(!data.quests.accept(id, character, TimeOfDay(0.0)));
+        assert_eq!(data.quests.taken_by(character).count(), MAX_TAKEN_QUESTS);
+    }
+
+    #[test]
+    fn quests_expire() {
+        let mut data = Data::empty();
+        let giver = npc(&mut data);
+        let character = CharacterId(1);
+        let offered = data.quests.create(fetch(giver));
+        let taken = data.quests.create(fetch(giver));
+        data.quests.offer(taken, character);
+        let taken_at = TimeOfDay(DAYS * 2.0);
+        data.quests.accept(taken, character, taken_at);
+
+        // Quests that nobody takes on are only offered for so long
+        let offer_for = data.quests[offered].offer_for();
+        data.quests.cleanup(&data.npcs, TimeOfDay(offer_for));
+        assert!(!data.quests.contains_key(offered));
+        assert!(data.quests.contains_key(taken));
+
+        // The taker only has so long to hand it in
+        data.quests.changed.clear();
+        let deadline = taken_at.0 + data.quests[taken].take_for();
+        data.quests.cleanup(&data.npcs, TimeOfDay(deadline - 1.0));
+        assert!(data.quests.contains_key(taken));
+        data.quests.cleanup(&data.npcs, TimeOfDay(deadline));
+        assert!(!data.quests.contains_key(taken));
+        assert!(data.quests.changed.contains(&character));
+
+        // Quests are forgotten along with their giver
+        let id = data.quests.create(fetch(giver));
+        data.npcs[giver].is_dead = true;
+        data.quests.cleanup(&data.npcs, TimeOfDay(0.0));
+        assert!(!data.quests.contains_key(id));
+    }
+
+    #[test]
+    fn deleted_characters_are_forgotten() {
+        let mut data = Data::empty();
+        let giver = npc(&mut data);
+        let (deleted, other) = (CharacterId(1), CharacterId(2));
+        let taken = data.quests.create(fetch(giver));
+        let offered = data.quests.create(fetch(giver));
+        let others = data.quests.create(fetch(giver));
+        data.quests.offer(taken, deleted);
+        data.quests.accept(taken, deleted, TimeOfDay(0.0));
+        data.quests.offer(offered, deleted);
+        data.quests.offer(others, other);
+        data.quests.accept(others, other, TimeOfDay(0.0));
+
+        data.quests.remove_character(deleted);
+        assert!(!data.quests.contains_key(taken));
+        assert!(data.quests[offered].offered_to.is_empty());
+        assert_eq!(data.quests[others].taken_by, Some(other));
+        assert!(!data.quests.changed.contains(&deleted));
+    }
+}
@@ -1,4 +1,8 @@
-use common::{resources::TimeOfDay, rtsim::Actor};
+use common::{
+    resources::TimeOfDay,
+    rtsim::{Actor, FactionId, NpcId, SiteId},
+    trade::Good,
+};
 use serde::{Deserialize, Serialize};
 use slotmap::HopSlotMap;
 use std::ops::Deref;
@@ -37,13 +41,58 @@ impl Report {
                     DAYS * 5.0
                 }
             },
+            ReportKind::Theft { .. } => DAYS * 7.0,
+            // Monsters move on, so sightings are soon out of date
+            ReportKind::MonsterSighting { .. } => DAYS * 2.0,
+            ReportKind::Deed { .. } => DAYS * 10.0,
+            ReportKind::Shortage { .. } => DAYS * 3.0,
+            ReportKind::Storm { .. } => DAYS * 2.0,
+            ReportKind::SiteAttack { .. } => DAYS * 10.0,
+            ReportKind::SiteConquered { .. } => DAYS * 30.0,
         }
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ReportKind {
-    Death { actor: Actor, killer: Option<Actor> },
+    Death {
+        actor: Actor,
+        killer: Option<Actor>,
+    },
+    /// Someone took crops or the contents of containers that belong to a site.
+    Theft {
+        thief: Actor,
+        site: SiteId,
+    },
+    /// A monster was seen roaming close to a site.
+    MonsterSighting {
+        monster: NpcId,
+        site: SiteId,
+    },
+    /// Someone helped out an NPC by completing a quest for them.
+    Deed {
+        actor: Actor,
+        beneficiary: NpcId,
+    },
+    /// A site is running low on a good.
+    Shortage {
+        site: SiteId,
+        good: Good,
+    },
+    /// A storm struck a site.
+    Storm {
+        site: SiteId,
+    },
+    /// A resident of a site was killed inside of it by an outsider.
+    SiteAttack {
+        site: SiteId,
+        attacker: Actor,
+    },
+    /// A faction took a site over after defeating its defenders.
+    SiteConquered {
+        site: SiteId,
+        faction: FactionId,
+    },
 }
 
 #[derive(Clone, Default, Serialize, Deserialize)]
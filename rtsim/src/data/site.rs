@@ -1,4 +1,4 @@
-use crate::data::{ReportId, Reports};
+use crate::data::{ReportId, Reports, SiteEconomy};
 pub use common::rtsim::SiteId;
 use common::{
     rtsim::{FactionId, NpcId},
@@ -21,6 +21,10 @@ pub struct Site {
     /// noticeboard or something).
     pub known_reports: HashSet<ReportId>,
 
+    /// The goods the site has in stock and what it charges for them.
+    #[serde(default)]
+    pub economy: SiteEconomy,
+
     /// The site generated during initial worldgen that this site corresponds
     /// to.
     ///
@@ -50,6 +54,22 @@ pub struct Site {
 }
 
 impl Site {
+    /// A site that doesn't correspond to any site in the world, for testing
+    /// rules.
+    #[cfg(test)]
+    pub fn at(wpos: Vec2<i32>) -> Self {
+        Self {
+            seed: 0,
+            wpos,
+            faction: None,
+            known_reports: Default::default(),
+            economy: Default::default(),
+            world_site: None,
+            population: Default::default(),
+            nearby_sites_by_size: Vec::new(),
+        }
+    }
+
     pub fn with_faction(mut self, faction: impl Into<Option<FactionId>>) -> Self {
         self.faction = faction.into();
         self
@@ -2,7 +2,9 @@ use crate::{RtState, Rule};
 use common::{
     mounting::VolumePos,
     resources::{Time, TimeOfDay},
-    rtsim::{Actor, NpcId},
+    rtsim::{Actor, NpcId, SiteId},
+    terrain::SpriteKind,
+    trade::Good,
 };
 use vek::*;
 use world::{IndexRef, World};
@@ -27,6 +29,9 @@ pub struct OnTick {
     pub time: Time,
     pub tick: u64,
     pub dt: f32,
+    /// Seed for the random numbers of this tick, so that recorded sessions are
+    /// replayed the same.
+    pub seed: u64,
 }
 impl Event for OnTick {}
 
@@ -44,3 +49,38 @@ pub struct OnMountVolume {
     pub pos: VolumePos<NpcId>,
 }
 impl Event for OnMountVolume {}
+
+/// A merchant of a site traded with someone.
+#[derive(Clone)]
+pub struct OnTrade {
+    pub site: SiteId,
+    /// The merchant, if it is an rtsim NPC.
+    pub npc: Option<NpcId>,
+    /// How much of each good the site gained, negative for goods it gave away.
+    pub goods: Vec<(Good, f32)>,
+}
+impl Event for OnTrade {}
+
+/// Someone collected a sprite, such as crops or the contents of a container.
+#[derive(Clone)]
+pub struct OnCollectSprite {
+    pub actor: Actor,
+    pub wpos: Vec3<i32>,
+    pub sprite: SpriteKind,
+}
+impl Event for OnCollectSprite {}
+
+/// A storm is raging over a site.
+#[derive(Clone)]
+pub struct OnStorm {
+    pub site: SiteId,
+}
+impl Event for OnStorm {}
+
+/// Someone completed a quest for an NPC.
+#[derive(Clone)]
+pub struct OnQuestComplete {
+    pub actor: Actor,
+    pub giver: NpcId,
+}
+impl Event for OnQuestComplete {}
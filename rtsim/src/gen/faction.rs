@@ -9,6 +9,7 @@ impl Faction {
             leader: None,
             good_or_evil: rng.gen(),
             sentiments: Default::default(),
+            hostile_to: Default::default(),
         }
     }
 }
@@ -35,6 +35,7 @@ impl Data {
             sites: Default::default(),
             factions: Default::default(),
             reports: Default::default(),
+            quests: Default::default(),
 
             tick: 0,
             time_of_day: TimeOfDay(settings.start_time),
@@ -67,6 +67,7 @@ impl Site {
             }),
             population: Default::default(),
             known_reports: Default::default(),
+            economy: Default::default(),
             nearby_sites_by_size: Vec::new(),
         }
     }
@@ -66,10 +66,13 @@ impl RtState {
         info!("Starting default rtsim rules...");
         self.start_rule::<rule::migrate::Migrate>();
         self.start_rule::<rule::replenish_resources::ReplenishResources>();
+        self.start_rule::<rule::simulate_economy::SimulateEconomy>();
         self.start_rule::<rule::report::ReportEvents>();
         self.start_rule::<rule::sync_npcs::SyncNpcs>();
         self.start_rule::<rule::simulate_npcs::SimulateNpcs>();
         self.start_rule::<rule::npc_ai::NpcAi>();
+        self.start_rule::<rule::manage_quests::ManageQuests>();
+        self.start_rule::<rule::wage_war::WageWar>();
         self.start_rule::<rule::cleanup::CleanUp>();
     }
 
@@ -174,6 +177,7 @@ impl RtState {
         time_of_day: TimeOfDay,
         time: Time,
         dt: f32,
+        rng_seed: u64,
     ) {
         let tick = {
             let mut data = self.data_mut();
@@ -186,6 +190,7 @@ impl RtState {
             tick,
             time,
             dt,
+            seed: rng_seed.wrapping_add(tick),
         };
         self.emit(event, world, index);
     }
@@ -1,10 +1,13 @@
 pub mod cleanup;
+pub mod manage_quests;
 pub mod migrate;
 pub mod npc_ai;
 pub mod replenish_resources;
 pub mod report;
+pub mod simulate_economy;
 pub mod simulate_npcs;
 pub mod sync_npcs;
+pub mod wage_war;
 
 use super::RtState;
 use std::fmt;
@@ -63,6 +63,9 @@ impl Rule for CleanUp {
 
             // Clean up old reports
             data.reports.cleanup(data.time_of_day);
+
+            // Clean up quests that can no longer be completed
+            data.quests.cleanup(&data.npcs, data.time_of_day);
         });
 
         Ok(Self)
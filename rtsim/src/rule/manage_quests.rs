@@ -0,0 +1,303 @@
+use crate::{
+    data::{npc::Profession, Data, Quest, QuestObjective, Quests, ReportKind, Sentiment, SiteId},
+    event::{EventCtx, OnDeath, OnTick},
+    RtState, Rule, RuleError,
+};
+use common::{
+    rtsim::{Actor, NpcId, Role},
+    trade::Good,
+};
+use rand::prelude::*;
+use rand_chacha::ChaChaRng;
+
+/// Sites only look for new quests every so many ticks
+const SITE_QUEST_TICK_SKIP: u64 = 600;
+/// The most quests that the residents of a site offer at once.
+const MAX_OFFERED_QUESTS: usize = 2;
+/// How close, in blocks, the taker of an escort quest must be to the giver when
+/// they arrive.
+const ESCORT_RADIUS: f32 = 48.0;
+const COINS: &str = "common.items.utility.coins";
+
+/// A rule that comes up with quests for characters to take on, from what is
+/// going on around sites, and keeps track of the progress made on them.
+pub struct ManageQuests;
+
+impl Rule for ManageQuests {
+    fn start(rtstate: &mut RtState) -> Result<Self, RuleError> {
+        rtstate.bind::<Self, OnTick>(on_tick);
+        rtstate.bind::<Self, OnDeath>(on_death);
+
+        Ok(Self)
+    }
+}
+
+fn on_tick(ctx: EventCtx<ManageQuests, OnTick>) {
+    let data = &mut *ctx.state.data_mut();
+    let mut rng = ChaChaRng::seed_from_u64(ctx.event.seed);
+
+    // Escorts are done once the giver arrives with the taker close by, and
+    // failed if they arrive without them
+    let arrived = data
+        .quests
+        .iter()
+        .filter_map(|(id, quest)| {
+            if let QuestObjective::Escort { site } = quest.objective
+                && !quest.done
+                && let Some(taker) = quest.taken_by
+                && let Some(giver) = data.npcs.get(quest.giver)
+                && giver.current_site == Some(site)
+            {
+                let escorted = data
+                    .npcs
+                    .nearby(Some(quest.giver), giver.wpos, ESCORT_RADIUS)
+                    .any(|actor| actor == Actor::Character(taker));
+                Some((id, escorted))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    for (id, escorted) in arrived {
+        if !escorted {
+            data.quests.remove(id);
+            continue;
+        }
+        let Quests { quests, changed } = &mut data.quests;
+        if let Some(quest) = quests.get_mut(id) {
+            quest.done = true;
+            changed.extend(quest.taken_by);
+        }
+    }
+
+    let site_ids = data
+        .sites
+        .iter()
+        .filter(|(_, site)| (site.seed as u64 + ctx.event.tick) % SITE_QUEST_TICK_SKIP == 0)
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    for site_id in site_ids {
+        let offered = data
+            .quests
+            .values()
+            .filter(|quest| {
+                quest.taken_by.is_none()
+                    && data
+                        .npcs
+                        .get(quest.giver)
+                        .map_or(false, |giver| giver.home == Some(site_id))
+            })
+            .count();
+        if offered < MAX_OFFERED_QUESTS
+            && let Some(quest) = generate_quest(data, site_id, &mut rng)
+        {
+            data.quests.create(quest);
+        }
+    }
+}
+
+fn on_death(ctx: EventCtx<ManageQuests, OnDeath>) {
+    let data = &mut *ctx.state.data_mut();
+    let Actor::Npc(dead) = ctx.event.actor else {
+        return;
+    };
+
+    // Quests that depend on other NPCs are cleaned up along with them, only the
+    // targets of kill quests need handling here
+    let Quests { quests, changed } = &mut data.quests;
+    quests.retain(|_, quest| match quest.objective {
+        QuestObjective::Kill { target } if target == dead => {
+            if let Some(taker) = quest.taken_by
+                && ctx.event.killer == Some(Actor::Character(taker))
+            {
+                quest.done = true;
+                changed.insert(taker);
+                true
+            } else {
+                // Somebody else got there first
+                changed.extend(quest.taken_by);
+                false
+            }
+        },
+        _ => true,
+    });
+}
+
+/// The item that residents ask for when their site runs low on a good.
+fn good_item(good: Good) -> Option<&'static str> {
+    match good {
+        Good::Food => Some("common.items.food.apple"),
+        Good::Wood => Some("common.items.log.wood"),
+        Good::Stone => Some("common.items.crafting_ing.stones"),
+        Good::Ingredients => Some("common.items.flowers.sunflower"),
+        _ => None,
+    }
+}
+
+/// Whether NPCs of the given profession work with the given good, and so are
+/// the ones to ask for it.
+fn works_with(profession: &Profession, good: Good) -> bool {
+    matches!(
+        (profession, good),
+        (Profession::Farmer | Profession::Chef, Good::Food)
+            | (Profession::Blacksmith, Good::Wood | Good::Stone)
+            | (
+                Profession::Herbalist | Profession::Alchemist,
+                Good::Ingredients
+            )
+    )
+}
+
+fn generate_quest(data: &Data, site_id: SiteId, rng: &mut ChaChaRng) -> Option<Quest> {
+    let site = data.sites.get(site_id)?;
+    let is_giver = |npc_id: NpcId| data.quests.values().any(|quest| quest.giver == npc_id);
+    // Residents that are free to give out a quest
+    let residents = site
+        .population
+        .iter()
+        .copied()
+        .filter(|npc_id| !is_giver(*npc_id))
+        .filter_map(|npc_id| Some((npc_id, data.npcs.get(npc_id)?)))
+        .filter(|(_, npc)| !npc.is_dead && npc.profession().is_some())
+        .collect::<Vec<_>>();
+    let any_resident = |rng: &mut ChaChaRng| residents.choose(rng).map(|(id, _)| *id);
+    let is_hunted = |npc_id: NpcId| {
+        data.quests.values().any(
+            |quest| matches!(quest.objective, QuestObjective::Kill { target } if target == npc_id),
+        )
+    };
+
+    let mut candidates = Vec::new();
+
+    for report in site
+        .known_reports
+        .iter()
+        .filter_map(|report| data.reports.get(*report))
+    {
+        match report.kind {
+            // Residents want whoever has been killing people dealt with, if the site
+            // considers them an enemy
+            ReportKind::Death {
+                killer: Some(Actor::Npc(killer)),
+                ..
+            } => {
+                let Some(killer_npc) = data.npcs.get(killer) else {
+                    continue;
+                };
+                let is_enemy = matches!(killer_npc.role, Role::Monster)
+                    || site
+                        .faction
+                        .and_then(|faction| data.factions.get(faction))
+                        .map_or(false, |faction| {
+                            faction.sentiments.toward(killer).is(Sentiment::ENEMY)
+                        });
+                if !killer_npc.is_dead && is_enemy && !is_hunted(killer) {
+                    // Guards are the first to ask for help
+                    let giver = residents
+                        .iter()
+                        .find(|(_, npc)| matches!(npc.profession(), Some(Profession::Guard)))
+                        .map(|(id, _)| *id)
+                        .or_else(|| any_resident(rng));
+                    if let Some(giver) = giver {
+                        candidates.push((giver, QuestObjective::Kill { target: killer }, vec![
+                            (COINS.to_string(), rng.gen_range(120..200)),
+                            ("common.items.consumable.potion_med".to_string(), 2),
+                        ]));
+                    }
+                }
+            },
+            // Monsters seen roaming close to the site are a threat to it
+            ReportKind::MonsterSighting { monster, .. } => {
+                if data.npcs.get(monster).map_or(false, |npc| !npc.is_dead)
+                    && !is_hunted(monster)
+                    && let Some(giver) = any_resident(rng)
+                {
+                    candidates.push((giver, QuestObjective::Kill { target: monster }, vec![(
+                        COINS.to_string(),
+                        rng.gen_range(80..150),
+                    )]));
+                }
+            },
+            // Residents ask for goods that the site is running low on
+            ReportKind::Shortage { good, .. } => {
+                let Some(item) = good_item(good) else {
+                    continue;
+                };
+                // The shortage may have been made up for since
+                if !site.economy.is_short_of(good) {
+                    continue;
+                }
+                let (stock, equilibrium) =
+                    (site.economy.stock(good), site.economy.equilibrium(good));
+                let giver = residents
+                    .iter()
+                    .filter(|(_, npc)| npc.profession().map_or(false, |p| works_with(&p, good)))
+                    .choose(rng)
+                    .map(|(id, _)| *id)
+                    .or_else(|| any_resident(rng));
+                if let Some(giver) = giver {
+                    let amount = ((1.0 - stock / equilibrium) * 20.0).clamp(5.0, 20.0) as u32;
+                    candidates.push((
+                        giver,
+                        QuestObjective::Fetch {
+                            item: item.to_string(),
+                            amount,
+                        },
+                        vec![(COINS.to_string(), amount * 6)],
+                    ));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // Merchants setting out from the site want company on the road
+    for (merchant_id, merchant) in data.npcs.iter().filter(|(id, npc)| {
+        matches!(npc.profession(), Some(Profession::Merchant)) && !npc.is_dead && !is_giver(*id)
+    }) {
+        if let Some(cargo) = &merchant.cargo
+            && cargo.from == site_id
+        {
+            candidates.push((merchant_id, QuestObjective::Escort { site: cargo.to }, vec![(
+                COINS.to_string(),
+                rng.gen_range(60..120),
+            )]));
+        }
+    }
+
+    // Residents have messages for people living in nearby sites
+    if let Some(other_site) = site
+        .nearby_sites_by_size
+        .iter()
+        .take(3)
+        .filter_map(|site| data.sites.get(*site))
+        .choose(rng)
+        && let Some(to) = other_site
+            .population
+            .iter()
+            .filter(|npc_id| {
+                data.npcs
+                    .get(**npc_id)
+                    .map_or(false, |npc| !npc.is_dead && npc.profession().is_some())
+            })
+            .choose(rng)
+        && let Some(giver) = any_resident(rng)
+    {
+        candidates.push((giver, QuestObjective::Deliver { to: *to }, vec![(
+            COINS.to_string(),
+            rng.gen_range(20..50),
+        )]));
+    }
+
+    let (giver, objective, rewards) = candidates.into_iter().choose(rng)?;
+    Some(Quest {
+        giver,
+        objective,
+        rewards,
+        taken_by: None,
+        taken_at: Default::default(),
+        offered_to: Default::default(),
+        done: false,
+        created: data.time_of_day,
+    })
+}
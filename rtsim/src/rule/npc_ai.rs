@@ -7,6 +7,7 @@ use crate::{
         seq, until, Action, NpcCtx, State,
     },
     data::{
+        economy::{is_deliverable, plan_cargo},
         npc::{Brain, PathData, SimulationMode},
         ReportKind, Sentiment, Sites,
     },
@@ -305,7 +306,7 @@ impl Rule for NpcAi {
                             } else {
                                 simulated_dt
                             },
-                            rng: ChaChaRng::from_seed(thread_rng().gen::<[u8; 32]>()),
+                            rng: ChaChaRng::seed_from_u64(ctx.event.seed ^ npc.seed as u64),
                         }, &mut ());
                     });
             }
@@ -578,7 +579,7 @@ fn travel_to_site<S: State>(tgt_site: SiteId, speed_factor: f32) -> impl Action<
         .map(|_, _| ())
 }
 
-fn talk_to<S: State>(tgt: Actor, _subject: Option<Subject>) -> impl Action<S> {
+fn talk_to<S: State>(tgt: Actor, subject: Option<Subject>) -> impl Action<S> {
     now(move |ctx, _| {
         if matches!(tgt, Actor::Npc(_)) && ctx.rng.gen_bool(0.2) {
             // Cut off the conversation sometimes to avoid infinite conversations (but only
@@ -586,8 +587,54 @@ fn talk_to<S: State>(tgt: Actor, _subject: Option<Subject>) -> impl Action<S> {
             // some sort of 'bored of conversation' system
             idle().l()
         } else {
+            // Characters may hand in quests to us, or take on the ones we have to offer
+            let (hand_in, offer) = if let Actor::Character(character) = tgt {
+                let data = ctx.state.data();
+                let quests = &data.quests;
+                let hand_in = quests
+                    .taken_by(character)
+                    .find(|(_, quest)| quest.receiver() == ctx.npc_id && quest.can_hand_in())
+                    .map(|(id, _)| id);
+                let offer = quests
+                    .iter()
+                    .find(|(_, quest)| quest.giver == ctx.npc_id && quest.taken_by.is_none())
+                    .map(|(id, _)| id)
+                    .filter(|_| matches!(subject, Some(Subject::Regular | Subject::Work)));
+                (hand_in, offer)
+            } else {
+                (None, None)
+            };
+            // Pass on something we heard about to other NPCs
+            let gossip = if let Actor::Npc(other) = tgt {
+                ctx.known_reports
+                    .iter()
+                    .copied()
+                    .choose(&mut ctx.rng)
+                    .map(|report| (other, report))
+            } else {
+                None
+            };
+
+            let comment = if hand_in.is_some() {
+                // Whether the quest is completed is up to the game, so let it do the talking
+                None
+            } else if offer.is_some() {
+                Some(Content::localized("npc-speech-quest_offer"))
+            } else if matches!(subject, Some(Subject::Work)) {
+                Some(Content::localized("npc-speech-no_work"))
+            // Talk about the factions that we're at war with
+            } else if ctx.rng.gen_bool(0.3)
+                && let Some(enemy) = ctx.npc.faction.and_then(|faction| {
+                    let data = ctx.state.data();
+                    data.factions.get(faction)?.hostile_to.iter().copied().choose(&mut ctx.rng)
+                })
+                && let Some(enemy) = ctx.state.data().factions.get(enemy)
+            {
+                Some(Content::localized_with_args("npc-speech-at_war", [
+                    ("faction", Content::Plain(enemy.get_name())),
+                ]))
             // Mention nearby sites
-            let comment = if ctx.rng.gen_bool(0.3)
+            } else if ctx.rng.gen_bool(0.3)
                 && let Some(current_site) = ctx.npc.current_site
                 && let Some(current_site) = ctx.state.data().sites.get(current_site)
                 && let Some(mention_site) = current_site.nearby_sites_by_size.choose(&mut ctx.rng)
@@ -595,11 +642,11 @@ fn talk_to<S: State>(tgt: Actor, _subject: Option<Subject>) -> impl Action<S> {
                 && let Some(mention_site_name) = mention_site.world_site
                     .map(|ws| ctx.index.sites.get(ws).name().to_string())
             {
-                Content::localized_with_args("npc-speech-tell_site", [
+                Some(Content::localized_with_args("npc-speech-tell_site", [
                     ("site", Content::Plain(mention_site_name)),
                     ("dir", Direction::from_dir(mention_site.wpos.as_() - ctx.npc.wpos.xy()).localize_npc()),
                     ("dist", Distance::from_length(mention_site.wpos.as_().distance(ctx.npc.wpos.xy()) as i32).localize_npc()),
-                ])
+                ]))
             // Mention nearby monsters
             } else if ctx.rng.gen_bool(0.3)
                 && let Some(monster) = ctx.state.data().npcs
@@ -607,13 +654,13 @@ fn talk_to<S: State>(tgt: Actor, _subject: Option<Subject>) -> impl Action<S> {
                     .filter(|other| matches!(&other.role, Role::Monster))
                     .min_by_key(|other| other.wpos.xy().distance(ctx.npc.wpos.xy()) as i32)
             {
-                Content::localized_with_args("npc-speech-tell_monster", [
+                Some(Content::localized_with_args("npc-speech-tell_monster", [
                     ("body", monster.body.localize()),
                     ("dir", Direction::from_dir(monster.wpos.xy() - ctx.npc.wpos.xy()).localize_npc()),
                     ("dist", Distance::from_length(monster.wpos.xy().distance(ctx.npc.wpos.xy()) as i32).localize_npc()),
-                ])
+                ]))
             } else {
-                ctx.npc.personality.get_generic_comment(&mut ctx.rng)
+                Some(ctx.npc.personality.get_generic_comment(&mut ctx.rng))
             };
             // TODO: Don't special-case players
             let wait = if matches!(tgt, Actor::Character(_)) {
@@ -624,7 +671,20 @@ fn talk_to<S: State>(tgt: Actor, _subject: Option<Subject>) -> impl Action<S> {
             idle()
                 .repeat()
                 .stop_if(timeout(wait))
-                .then(just(move |ctx, _| ctx.controller.say(tgt, comment.clone())))
+                .then(just(move |ctx, _| {
+                    if let Some(comment) = &comment {
+                        ctx.controller.say(tgt, comment.clone());
+                    }
+                    if let Some(quest) = hand_in {
+                        ctx.controller.complete_quest(tgt, quest);
+                    }
+                    if let Some(quest) = offer {
+                        ctx.controller.offer_quest(tgt, quest);
+                    }
+                    if let Some((other, report)) = gossip {
+                        ctx.controller.share_report(other, report);
+                    }
+                }))
                 .r()
         }
     })
@@ -710,6 +770,92 @@ fn adventure() -> impl Action<DefaultState> {
     .debug(move || "adventure")
 }
 
+/// Merchants take the goods that the site they are at has too much of to a
+/// nearby site that pays more for them. The goods are only sold once they get
+/// there, so anybody that wants them to arrive had better come along. If the
+/// trip takes too long, or the destination falls to an enemy of the site the
+/// goods came from, the merchant gives up and the goods are returned.
+fn merchant() -> impl Action<DefaultState> {
+    choose(|ctx, _| {
+        const CARAVAN_SPEED: f32 = 0.5;
+        const ESCORT_DIST: f32 = 24.0;
+        const MAX_TRIP_TIME: f64 = 60.0 * 30.0;
+
+        let data = ctx.state.data();
+        // Finish the journey first if we're already carrying goods
+        let is_loaded = ctx.npc.cargo.is_some();
+        let cargo = ctx
+            .npc
+            .cargo
+            .clone()
+            .or_else(|| {
+                ctx.npc
+                    .current_site
+                    .and_then(|current_site| plan_cargo(&data.sites, current_site))
+            })
+            .filter(|cargo| is_deliverable(&data.sites, &data.factions, cargo));
+
+        if let Some(cargo) = cargo {
+            let tgt_site = cargo.to;
+            let site_name = data
+                .sites
+                .get(tgt_site)
+                .and_then(|site| site.world_site)
+                .map(|ws| ctx.index.sites.get(ws).name().to_string())
+                .unwrap_or_default();
+            important(
+                just(move |ctx, _| {
+                    if !is_loaded {
+                        ctx.controller.load_cargo(cargo.clone());
+                    }
+                    ctx.controller.say(
+                        None,
+                        Content::localized_with_args("npc-speech-caravan_departing", [(
+                            "site",
+                            site_name.clone(),
+                        )]),
+                    )
+                })
+                .then(travel_to_site(tgt_site, CARAVAN_SPEED).stop_if(timeout(MAX_TRIP_TIME)))
+                .then(now(move |ctx, _| {
+                    ctx.controller.unload_cargo();
+                    if ctx.npc.current_site != Some(tgt_site) {
+                        return finish().boxed();
+                    }
+                    // Thank those that came along to keep us safe
+                    let escorts = ctx
+                        .state
+                        .data()
+                        .npcs
+                        .nearby(Some(ctx.npc_id), ctx.npc.wpos, ESCORT_DIST)
+                        .filter(|actor| matches!(actor, Actor::Character(_)))
+                        .collect::<Vec<_>>();
+                    for escort in escorts {
+                        ctx.sentiments
+                            .toward_mut(escort)
+                            .change_by(0.2, Sentiment::FRIEND);
+                        ctx.controller
+                            .say(escort, Content::localized("npc-speech-caravan_thanks"));
+                    }
+                    // Sell the goods before heading off again
+                    villager(tgt_site)
+                        .repeat()
+                        .stop_if(timeout(60.0 * 10.0))
+                        .map(|_, _| ())
+                        .boxed()
+                }))
+                .boxed(),
+            )
+        } else if is_loaded {
+            // The goods can't be sold where they were headed any more
+            important(just(|ctx, _| ctx.controller.unload_cargo()).boxed())
+        } else {
+            casual(adventure().boxed())
+        }
+    })
+    .debug(|| "merchant")
+}
+
 fn gather_ingredients<S: State>() -> impl Action<S> {
     just(|ctx, _| {
         ctx.controller.do_gather(
@@ -758,6 +904,27 @@ fn choose_plaza(ctx: &mut NpcCtx, site: SiteId) -> Option<Vec2<f32>> {
         })
 }
 
+/// March on a site held by a hostile faction and hold its plaza until the
+/// outcome of the raid is decided.
+fn raid(target: SiteId) -> impl Action<DefaultState> {
+    just(|ctx, _| {
+        ctx.controller
+            .say(None, Content::localized("npc-speech-raid_set_out"))
+    })
+    .then(travel_to_site(target, 0.8))
+    .then(now(move |ctx, _| {
+        if let Some(plaza) = choose_plaza(ctx, target) {
+            travel_to_point(plaza, 0.8).boxed()
+        } else {
+            finish().boxed()
+        }
+    }))
+    .then(idle().repeat())
+    .stop_if(move |ctx: &mut NpcCtx| ctx.npc.raid != Some(target))
+    .map(|_, _| ())
+    .debug(move || format!("raid {:?}", target))
+}
+
 fn villager(visiting_site: SiteId) -> impl Action<DefaultState> {
     choose(move |ctx, state: &mut DefaultState| {
         // Consider moving home if the home site gets too full
@@ -1081,59 +1248,164 @@ fn captain<S: State>() -> impl Action<S> {
     .map(|_, _| ())
 }
 
+/// Changes how we feel about those involved in a report that we just learnt of,
+/// and comes up with what we have to say about it.
+fn react_to_report(ctx: &mut NpcCtx, kind: ReportKind) -> Option<(Option<Actor>, Content)> {
+    let is_home = |site| ctx.npc.home == Some(site);
+    match kind {
+        ReportKind::Death { killer, actor, .. } => {
+            // TODO: Don't report self
+            let phrase = if let Some(killer) = killer {
+                // TODO: For now, we don't make sentiment changes if the killer was an
+                // NPC because NPCs can't hurt one-another.
+                // This should be changed in the future.
+                if !matches!(killer, Actor::Npc(_)) {
+                    // TODO: Don't hard-code sentiment change
+                    let mut change = -0.7;
+                    if ctx.sentiments.toward(actor).is(Sentiment::ENEMY) {
+                        // Like the killer if we have negative sentiment towards the
+                        // killed.
+                        change *= -1.0;
+                    }
+                    ctx.sentiments
+                        .toward_mut(killer)
+                        .change_by(change, Sentiment::VILLAIN);
+                }
+
+                // This is a murder of a player. Feel bad for the player and stop
+                // attacking them.
+                if let Actor::Character(_) = actor {
+                    ctx.sentiments
+                        .toward_mut(actor)
+                        .limit_below(Sentiment::ENEMY)
+                }
+
+                if ctx.sentiments.toward(actor).is(Sentiment::ENEMY) {
+                    "npc-speech-witness_enemy_murder"
+                } else {
+                    "npc-speech-witness_murder"
+                }
+            } else {
+                "npc-speech-witness_death"
+            };
+            Some((killer, Content::localized(phrase)))
+        },
+        ReportKind::Theft { thief, site } => {
+            // Stealing from our own home is worse
+            let (change, cap) = if is_home(site) {
+                (-0.3, Sentiment::RIVAL)
+            } else {
+                (-0.1, Sentiment::NEGATIVE)
+            };
+            ctx.sentiments.toward_mut(thief).change_by(change, cap);
+
+            // Call out the thief if they're still around
+            let caught = ctx
+                .state
+                .data()
+                .npcs
+                .nearby(Some(ctx.npc_id), ctx.npc.wpos, 24.0)
+                .any(|actor| actor == thief);
+            if caught {
+                Some((Some(thief), Content::localized("npc-speech-witness_theft")))
+            } else if is_home(site) {
+                Some((None, Content::localized("npc-speech-heard_theft")))
+            } else {
+                None
+            }
+        },
+        ReportKind::MonsterSighting { monster, site } if is_home(site) => {
+            let data = ctx.state.data();
+            let monster = data.npcs.get(monster)?;
+            Some((
+                None,
+                Content::localized_with_args("npc-speech-monster_sighted", [
+                    ("body", monster.body.localize()),
+                    (
+                        "dir",
+                        Direction::from_dir(monster.wpos.xy() - ctx.npc.wpos.xy()).localize_npc(),
+                    ),
+                ]),
+            ))
+        },
+        ReportKind::Deed { actor, beneficiary } => {
+            // Whoever was helped out has already thanked them
+            if beneficiary == ctx.npc_id {
+                return None;
+            }
+            ctx.sentiments
+                .toward_mut(actor)
+                .change_by(0.1, Sentiment::ALLY);
+            let name = ctx.state.data().npcs.get(beneficiary)?.get_name();
+            Some((
+                Some(actor),
+                Content::localized_with_args("npc-speech-heard_deed", [(
+                    "npc",
+                    Content::Plain(name),
+                )]),
+            ))
+        },
+        ReportKind::Shortage { site, .. } if is_home(site) => {
+            Some((None, Content::localized("npc-speech-shortage")))
+        },
+        ReportKind::Storm { site } if is_home(site) => {
+            Some((None, Content::localized("npc-speech-storm")))
+        },
+        ReportKind::SiteAttack { site, attacker } if is_home(site) => {
+            // NPCs can't hurt one-another, see above
+            if !matches!(attacker, Actor::Npc(_)) {
+                ctx.sentiments
+                    .toward_mut(attacker)
+                    .change_by(-0.5, Sentiment::ENEMY);
+            }
+            Some((
+                Some(attacker),
+                Content::localized("npc-speech-site_attacked"),
+            ))
+        },
+        ReportKind::SiteConquered { site, faction } => {
+            if is_home(site) {
+                Some((None, Content::localized("npc-speech-site_conquered")))
+            } else if ctx.npc.faction == Some(faction) {
+                let data = ctx.state.data();
+                let site_name = ctx
+                    .index
+                    .sites
+                    .get(data.sites.get(site)?.world_site?)
+                    .name();
+                Some((
+                    None,
+                    Content::localized_with_args("npc-speech-victory", [(
+                        "site",
+                        site_name.to_string(),
+                    )]),
+                ))
+            } else {
+                None
+            }
+        },
+        // What goes on elsewhere is only worth remembering
+        ReportKind::MonsterSighting { .. }
+        | ReportKind::Shortage { .. }
+        | ReportKind::Storm { .. }
+        | ReportKind::SiteAttack { .. } => None,
+    }
+}
+
 fn check_inbox<S: State>(ctx: &mut NpcCtx) -> Option<impl Action<S>> {
     loop {
         match ctx.inbox.pop_front() {
-            Some(NpcInput::Report(report_id)) if !ctx.known_reports.contains(&report_id) => {
-                #[allow(clippy::single_match)]
-                match ctx.state.data().reports.get(report_id).map(|r| r.kind) {
-                    Some(ReportKind::Death { killer, actor, .. })
-                        if matches!(&ctx.npc.role, Role::Civilised(_)) =>
-                    {
-                        // TODO: Don't report self
-                        let phrase = if let Some(killer) = killer {
-                            // TODO: For now, we don't make sentiment changes if the killer was an
-                            // NPC because NPCs can't hurt one-another.
-                            // This should be changed in the future.
-                            if !matches!(killer, Actor::Npc(_)) {
-                                // TODO: Don't hard-code sentiment change
-                                let mut change = -0.7;
-                                if ctx.sentiments.toward(actor).is(Sentiment::ENEMY) {
-                                    // Like the killer if we have negative sentiment towards the
-                                    // killed.
-                                    change *= -1.0;
-                                }
-                                ctx.sentiments
-                                    .toward_mut(killer)
-                                    .change_by(change, Sentiment::VILLAIN);
-                            }
-
-                            // This is a murder of a player. Feel bad for the player and stop
-                            // attacking them.
-                            if let Actor::Character(_) = actor {
-                                ctx.sentiments
-                                    .toward_mut(actor)
-                                    .limit_below(Sentiment::ENEMY)
-                            }
-
-                            if ctx.sentiments.toward(actor).is(Sentiment::ENEMY) {
-                                "npc-speech-witness_enemy_murder"
-                            } else {
-                                "npc-speech-witness_murder"
-                            }
-                        } else {
-                            "npc-speech-witness_death"
-                        };
-                        ctx.known_reports.insert(report_id);
-                        break Some(
-                            just(move |ctx, _| {
-                                ctx.controller.say(killer, Content::localized(phrase))
-                            })
-                            .l(),
-                        );
-                    },
-                    Some(ReportKind::Death { .. }) => {}, // We don't care about death
-                    None => {},                           // Stale report, ignore
+            Some(NpcInput::Report(report_id))
+                if !ctx.known_reports.contains(&report_id)
+                    && matches!(&ctx.npc.role, Role::Civilised(_)) =>
+            {
+                // Stale reports are ignored
+                let Some(kind) = ctx.state.data().reports.get(report_id).map(|r| r.kind) else {
+                    continue;
+                };
+                ctx.known_reports.insert(report_id);
+                if let Some((target, phrase)) = react_to_report(ctx, kind) {
+                    break Some(just(move |ctx, _| ctx.controller.say(target, phrase.clone())).l());
                 }
             },
             Some(NpcInput::Report(_)) => {}, // Reports we already know of are ignored
@@ -1143,6 +1415,20 @@ fn check_inbox<S: State>(ctx: &mut NpcCtx) -> Option<impl Action<S>> {
     }
 }
 
+/// Guards fight the members of factions that their own faction is hostile to.
+fn is_hostile_guard(ctx: &NpcCtx, actor: Actor) -> bool {
+    let data = ctx.state.data();
+    if matches!(ctx.npc.profession(), Some(Profession::Guard))
+        && let Some(faction) = ctx.npc.faction.and_then(|faction| data.factions.get(faction))
+        && let Some(other) = actor.npc().and_then(|npc| data.npcs.get(npc))
+        && let Some(other_faction) = other.faction
+    {
+        faction.hostile_to.contains(&other_faction)
+    } else {
+        false
+    }
+}
+
 fn check_for_enemies<S: State>(ctx: &mut NpcCtx) -> Option<impl Action<S>> {
     // TODO: Instead of checking all nearby actors every tick, it would be more
     // effective to have the actor grid generate a per-tick diff so that we only
@@ -1154,7 +1440,9 @@ fn check_for_enemies<S: State>(ctx: &mut NpcCtx) -> Option<impl Action<S>> {
         .data()
         .npcs
         .nearby(Some(ctx.npc_id), ctx.npc.wpos, 24.0)
-        .find(|actor| ctx.sentiments.toward(*actor).is(Sentiment::ENEMY))
+        .find(|actor| {
+            ctx.sentiments.toward(*actor).is(Sentiment::ENEMY) || is_hostile_guard(ctx, *actor)
+        })
         .map(|enemy| just(move |ctx, _| ctx.controller.attack(enemy)))
 }
 
@@ -1188,15 +1476,16 @@ fn humanoid() -> impl Action<DefaultState> {
                 )
             }
         } else {
-            let action = if matches!(
-                ctx.npc.profession(),
-                Some(Profession::Adventurer(_) | Profession::Merchant)
-            ) {
-                adventure().l().l()
+            let action = if matches!(ctx.npc.profession(), Some(Profession::Merchant)) {
+                merchant().l().l()
+            } else if matches!(ctx.npc.profession(), Some(Profession::Adventurer(_))) {
+                adventure().r().l()
+            } else if let Some(target) = ctx.npc.raid {
+                raid(target).l().l().r()
             } else if let Some(home) = ctx.npc.home {
-                villager(home).r().l()
+                villager(home).r().l().r()
             } else {
-                idle().r() // Homeless
+                idle().r().r() // Homeless
             };
 
             casual(action.interrupt_with(react_to_events))
@@ -1,20 +1,64 @@
 use crate::{
-    data::{report::ReportKind, Report},
-    event::{EventCtx, OnDeath},
+    data::{report::ReportKind, Data, NpcId, Report, ReportId, Sentiment, SiteId},
+    event::{EventCtx, OnCollectSprite, OnDeath, OnQuestComplete, OnStorm, OnTick},
     RtState, Rule, RuleError,
 };
-use common::rtsim::NpcInput;
+use common::{
+    rtsim::{Actor, NpcInput, Role},
+    terrain::{CoordinateConversions, SpriteKind},
+    trade::Good,
+};
+use vek::*;
+use world::World;
+
+/// Sites only look around for things worth reporting every so many ticks
+const SITE_REPORT_TICK_SKIP: u64 = 300;
+/// The distance, in blocks, from a site within which monsters are noticed by
+/// its residents.
+const SIGHTING_RADIUS: f32 = 320.0;
+/// The distance, in blocks, within which NPCs notice someone stealing.
+const THEFT_WITNESS_RADIUS: f32 = 24.0;
+/// Thefts by the same thief within this many in-game seconds are reported as
+/// one.
+const THEFT_REPORT_WINDOW: f64 = 60.0 * 60.0;
+/// The fraction of its food that a site loses to a storm.
+const STORM_DAMAGE: f32 = 0.1;
 
 pub struct ReportEvents;
 
 impl Rule for ReportEvents {
     fn start(rtstate: &mut RtState) -> Result<Self, RuleError> {
         rtstate.bind::<Self, OnDeath>(on_death);
+        rtstate.bind::<Self, OnTick>(on_tick);
+        rtstate.bind::<Self, OnCollectSprite>(on_collect_sprite);
+        rtstate.bind::<Self, OnStorm>(on_storm);
+        rtstate.bind::<Self, OnQuestComplete>(on_quest_complete);
 
         Ok(Self)
     }
 }
 
+/// The site that the given position lies within, if any.
+fn site_at(data: &Data, world: &World, wpos: Vec2<i32>) -> Option<SiteId> {
+    world.sim().get(wpos.wpos_to_cpos()).and_then(|chunk| {
+        chunk
+            .sites
+            .iter()
+            .find_map(|site| data.sites.world_site_map.get(site).copied())
+    })
+}
+
+fn inform_npcs(data: &mut Data, npcs: impl IntoIterator<Item = Actor>, report: ReportId) {
+    // TODO: Don't push report to NPC inboxes, have a dedicated data structure that
+    // tracks reports by chunks and then have NPCs decide to query this
+    // data structure in their own time.
+    for npc_id in npcs.into_iter().filter_map(|actor| actor.npc()) {
+        if let Some(npc) = data.npcs.get_mut(npc_id) {
+            npc.inbox.push_back(NpcInput::Report(report));
+        }
+    }
+}
+
 fn on_death(ctx: EventCtx<ReportEvents, OnDeath>) {
     let data = &mut *ctx.state.data_mut();
 
@@ -34,14 +78,325 @@ fn on_death(ctx: EventCtx<ReportEvents, OnDeath>) {
                 at: data.time_of_day,
             });
 
-            // TODO: Don't push report to NPC inboxes, have a dedicated data structure that
-            // tracks reports by chunks and then have NPCs decide to query this
-            // data structure in their own time.
-            for npc_id in nearby {
-                if let Some(npc) = data.npcs.get_mut(npc_id) {
-                    npc.inbox.push_back(NpcInput::Report(report));
-                }
-            }
+            inform_npcs(data, nearby.into_iter().map(Actor::Npc), report);
         }
+
+        // Residents being killed inside of their own site by an outsider is an attack
+        // on the site
+        if let Actor::Npc(dead) = ctx.event.actor
+            && let Some(attacker) = ctx.event.killer
+            && let Some(home) = data
+                .npcs
+                .get(dead)
+                .filter(|npc| matches!(npc.role, Role::Civilised(_)))
+                .and_then(|npc| npc.home)
+            && site_at(data, ctx.world, wpos.xy().as_()) == Some(home)
+            && attacker
+                .npc()
+                .and_then(|attacker| data.npcs.get(attacker))
+                .map_or(true, |attacker| attacker.home != Some(home))
+        {
+            data.report_to_site(home, ReportKind::SiteAttack {
+                site: home,
+                attacker,
+            });
+        }
+    }
+}
+
+fn on_tick(ctx: EventCtx<ReportEvents, OnTick>) {
+    let data = &mut *ctx.state.data_mut();
+
+    let site_ids = data
+        .sites
+        .iter()
+        .filter(|(_, site)| (site.seed as u64 + ctx.event.tick) % SITE_REPORT_TICK_SKIP == 0)
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    for site_id in site_ids {
+        survey_site(data, site_id);
+    }
+}
+
+/// Reports the monsters roaming close to a site, and the goods it is running
+/// low on, to the site.
+fn survey_site(data: &mut Data, site_id: SiteId) {
+    let Some(site) = data.sites.get(site_id) else {
+        return;
+    };
+
+    // Residents keep an eye out for monsters roaming close by
+    let sighted = data
+        .npcs
+        .iter()
+        .filter(|(_, npc)| {
+            matches!(npc.role, Role::Monster)
+                && !npc.is_dead
+                && npc.wpos.xy().distance(site.wpos.as_()) < SIGHTING_RADIUS
+        })
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    // The site notices when it runs low on goods
+    let shortages = if site.economy.is_active() {
+        site.economy
+            .equilibrium
+            .keys()
+            .copied()
+            .filter(|good| *good != Good::Coin && site.economy.is_short_of(*good))
+            .collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+
+    for monster in sighted {
+        data.report_to_site(site_id, ReportKind::MonsterSighting {
+            monster,
+            site: site_id,
+        });
+    }
+    for good in shortages {
+        data.report_to_site(site_id, ReportKind::Shortage {
+            site: site_id,
+            good,
+        });
+    }
+}
+
+/// Whether the sprite belongs to the people living where it is found.
+fn is_owned(sprite: SpriteKind) -> bool {
+    sprite.is_container()
+        || matches!(
+            sprite,
+            SpriteKind::WheatYellow
+                | SpriteKind::Cabbage
+                | SpriteKind::Flax
+                | SpriteKind::Carrot
+                | SpriteKind::Tomato
+                | SpriteKind::Radish
+                | SpriteKind::Turnip
+                | SpriteKind::Pumpkin
+                | SpriteKind::Beehive
+        )
+}
+
+fn on_collect_sprite(ctx: EventCtx<ReportEvents, OnCollectSprite>) {
+    let data = &mut *ctx.state.data_mut();
+    let wpos = ctx.event.wpos;
+
+    if !is_owned(ctx.event.sprite) {
+        return;
+    }
+    let Some(site) = site_at(data, ctx.world, wpos.xy()) else {
+        return;
+    };
+    // Residents don't steal from their own site
+    if let Some(npc) = ctx.event.actor.npc().and_then(|npc| data.npcs.get(npc))
+        && npc.home == Some(site)
+    {
+        return;
+    }
+
+    // It's only theft if somebody saw it
+    let witnesses = data
+        .npcs
+        .nearby(ctx.event.actor.npc(), wpos.as_(), THEFT_WITNESS_RADIUS)
+        .filter(|actor| {
+            actor
+                .npc()
+                .and_then(|npc| data.npcs.get(npc))
+                .map_or(false, |npc| matches!(npc.role, Role::Civilised(_)))
+        })
+        .collect::<Vec<_>>();
+    if witnesses.is_empty() {
+        return;
+    }
+
+    report_theft(data, ctx.event.actor, site, witnesses);
+}
+
+/// Tells the witnesses of a theft from a site about it.
+fn report_theft(data: &mut Data, thief: Actor, site: SiteId, witnesses: Vec<Actor>) {
+    let kind = ReportKind::Theft { thief, site };
+    // Taking a whole field of crops is still just the one theft
+    let recent = data
+        .reports
+        .iter()
+        .find(|(_, report)| {
+            report.kind == kind && data.time_of_day.0 - report.at.0 < THEFT_REPORT_WINDOW
+        })
+        .map(|(id, _)| id);
+    let report = recent.unwrap_or_else(|| {
+        data.reports.create(Report {
+            kind,
+            at: data.time_of_day,
+        })
+    });
+    inform_npcs(data, witnesses, report);
+}
+
+fn on_storm(ctx: EventCtx<ReportEvents, OnStorm>) {
+    let data = &mut *ctx.state.data_mut();
+    report_storm(data, ctx.event.site);
+}
+
+fn report_storm(data: &mut Data, site_id: SiteId) {
+    // The same storm only gets reported, and ruins crops, once
+    if data.report_to_site(site_id, ReportKind::Storm { site: site_id })
+        && let Some(site) = data.sites.get_mut(site_id)
+    {
+        let food = site.economy.stock(Good::Food);
+        site.economy.change_stock(Good::Food, -food * STORM_DAMAGE);
+    }
+}
+
+fn on_quest_complete(ctx: EventCtx<ReportEvents, OnQuestComplete>) {
+    let data = &mut *ctx.state.data_mut();
+    report_deed(data, ctx.event.actor, ctx.event.giver);
+}
+
+/// Lets the NPC that a quest was completed for know who helped them out.
+fn report_deed(data: &mut Data, actor: Actor, giver: NpcId) {
+    let report = data.reports.create(Report {
+        kind: ReportKind::Deed {
+            actor,
+            beneficiary: giver,
+        },
+        at: data.time_of_day,
+    });
+    if let Some(giver) = data.npcs.get_mut(giver) {
+        giver
+            .sentiments
+            .toward_mut(actor)
+            .change_by(0.25, Sentiment::FRIEND);
+        giver.inbox.push_back(NpcInput::Report(report));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Npc, Site};
+    use common::{character::CharacterId, comp, resources::TimeOfDay};
+
+    fn npc(wpos: Vec3<f32>, role: Role) -> Npc {
+        Npc::new(
+            0,
+            wpos,
+            comp::Body::Humanoid(comp::humanoid::Body::random()),
+            role,
+        )
+    }
+
+    #[test]
+    fn thefts_within_the_window_are_reported_once() {
+        let mut data = Data::empty();
+        let site = data.sites.create(Site::at(Vec2::zero()));
+        let witness = data.spawn_npc(npc(Vec3::zero(), Role::Civilised(None)));
+        let thief = Actor::Character(CharacterId(1));
+
+        report_theft(&mut data, thief, site, vec![Actor::Npc(witness)]);
+        data.time_of_day = TimeOfDay(THEFT_REPORT_WINDOW * 0.5);
+        report_theft(&mut data, thief, site, vec![Actor::Npc(witness)]);
+        assert_eq!(data.reports.len(), 1);
+
+        // Somebody else stealing is another theft
+        let other = Actor::Character(CharacterId(2));
+        report_theft(&mut data, other, site, vec![Actor::Npc(witness)]);
+        assert_eq!(data.reports.len(), 2);
+
+        data.time_of_day = TimeOfDay(THEFT_REPORT_WINDOW * 1.5);
+        report_theft(&mut data, thief, site, vec![Actor::Npc(witness)]);
+        assert_eq!(data.reports.len(), 3);
+
+        // The witness hears about every one of them
+        let inbox = &data.npcs.get(witness).unwrap().inbox;
+        assert_eq!(inbox.len(), 4);
+        assert!(
+            inbox
+                .iter()
+                .all(|input| matches!(input, NpcInput::Report(_)))
+        );
+    }
+
+    #[test]
+    fn storms_ruin_some_of_the_food_once() {
+        let mut data = Data::empty();
+        let mut site = Site::at(Vec2::zero());
+        site.economy.stocks.insert(Good::Food, 100.0);
+        site.economy.stocks.insert(Good::Wood, 100.0);
+        let site = data.sites.create(site);
+
+        report_storm(&mut data, site);
+        report_storm(&mut data, site);
+        let economy = &data.sites.get(site).unwrap().economy;
+        assert_eq!(economy.stock(Good::Food), 100.0 - 100.0 * STORM_DAMAGE);
+        assert_eq!(economy.stock(Good::Wood), 100.0);
+        assert!(data.site_knows(site, ReportKind::Storm { site }));
+        assert_eq!(data.reports.len(), 1);
+    }
+
+    #[test]
+    fn monsters_close_to_a_site_are_sighted() {
+        let mut data = Data::empty();
+        let site = data.sites.create(Site::at(Vec2::zero()));
+        let near = data.spawn_npc(npc(Vec3::new(100.0, 0.0, 0.0), Role::Monster));
+        let far = data.spawn_npc(npc(
+            Vec3::new(SIGHTING_RADIUS * 2.0, 0.0, 0.0),
+            Role::Monster,
+        ));
+        let mut dead = npc(Vec3::new(100.0, 0.0, 0.0), Role::Monster);
+        dead.is_dead = true;
+        let dead = data.spawn_npc(dead);
+        let resident = data.spawn_npc(npc(Vec3::zero(), Role::Civilised(None)));
+
+        survey_site(&mut data, site);
+        survey_site(&mut data, site);
+        let sighted = |monster| ReportKind::MonsterSighting { monster, site };
+        assert!(data.site_knows(site, sighted(near)));
+        assert!(!data.site_knows(site, sighted(far)));
+        assert!(!data.site_knows(site, sighted(dead)));
+        assert!(!data.site_knows(site, sighted(resident)));
+        assert_eq!(data.reports.len(), 1);
+    }
+
+    #[test]
+    fn sites_notice_shortages() {
+        let mut data = Data::empty();
+        let mut site = Site::at(Vec2::zero());
+        for (good, stock) in [(Good::Food, 10.0), (Good::Wood, 100.0), (Good::Coin, 0.0)] {
+            site.economy.base_prices.insert(good, 1.0);
+            site.economy.equilibrium.insert(good, 100.0);
+            site.economy.stocks.insert(good, stock);
+        }
+        let site = data.sites.create(site);
+
+        survey_site(&mut data, site);
+        let shortage = |good| ReportKind::Shortage { site, good };
+        assert!(data.site_knows(site, shortage(Good::Food)));
+        assert!(!data.site_knows(site, shortage(Good::Wood)));
+        // Running out of coin isn't something residents can do anything about
+        assert!(!data.site_knows(site, shortage(Good::Coin)));
+    }
+
+    #[test]
+    fn deeds_please_the_giver() {
+        let mut data = Data::empty();
+        let giver_id = data.spawn_npc(npc(Vec3::zero(), Role::Civilised(None)));
+        let actor = Actor::Character(CharacterId(1));
+
+        report_deed(&mut data, actor, giver_id);
+        let giver = data.npcs.get(giver_id).unwrap();
+        assert!(giver.sentiments.toward(actor).is(Sentiment::POSITIVE));
+        let Some(NpcInput::Report(report)) = giver.inbox.front() else {
+            panic!("The giver wasn't told about the deed");
+        };
+        assert!(data.reports.get(*report).map_or(false, |report| {
+            report.kind
+                == ReportKind::Deed {
+                    actor,
+                    beneficiary: giver_id,
+                }
+        }));
     }
 }
@@ -0,0 +1,167 @@
+use crate::{
+    data::{economy::resource_good, Data, SiteId},
+    event::{EventCtx, OnSetup, OnTick, OnTrade},
+    RtState, Rule, RuleError,
+};
+use common::{terrain::CoordinateConversions, trade::Good};
+use hashbrown::HashMap;
+use vek::*;
+
+/// Sites only update their economy every so many ticks
+const SITE_ECONOMY_TICK_SKIP: u64 = 150;
+/// The radius, in chunks, around a site from which it gathers natural
+/// resources.
+const GATHER_RADIUS: i32 = 6;
+/// How many other sites the merchants of a site trade with.
+const TRADE_PARTNERS: usize = 4;
+
+/// Keeps the economy of sites going while the game is running: sites produce
+/// and consume goods, limited by the natural resources left around them, and
+/// trades with players change the stock of the site they happened at, or the
+/// cargo of merchants on the road. Goods carried between sites by merchants
+/// are handled by the merchants themselves.
+pub struct SimulateEconomy;
+
+impl Rule for SimulateEconomy {
+    fn start(rtstate: &mut RtState) -> Result<Self, RuleError> {
+        rtstate.bind::<Self, OnSetup>(on_setup);
+        rtstate.bind::<Self, OnTick>(on_tick);
+        rtstate.bind::<Self, OnTrade>(on_trade);
+
+        Ok(Self)
+    }
+}
+
+fn on_setup(ctx: EventCtx<SimulateEconomy, OnSetup>) {
+    let data = &mut *ctx.state.data_mut();
+
+    // Pick up where the world simulation left off
+    for site in data.sites.values_mut() {
+        let Some(world_site) = site.world_site.map(|ws| ctx.index.sites.get(ws)) else {
+            continue;
+        };
+        if !world_site.do_economic_simulation() {
+            continue;
+        }
+        let economy = &mut site.economy;
+        economy.equilibrium = world_site.economy.get_available_stock();
+        economy.base_prices = world_site.economy.get_site_prices().values;
+        // Sites that didn't have an economy yet start out settled
+        if economy.stocks.is_empty() {
+            economy.stocks = economy
+                .equilibrium
+                .iter()
+                .map(|(good, amount)| (*good, amount.max(0.0)))
+                .collect();
+        }
+    }
+
+    let trade_partners = data
+        .sites
+        .iter()
+        .filter(|(_, site)| site.economy.is_active())
+        .map(|(site_id, site)| {
+            let mut partners = data
+                .sites
+                .iter()
+                .filter(|(other_id, other)| *other_id != site_id && other.economy.is_active())
+                .map(|(other_id, other)| {
+                    (
+                        other_id,
+                        other.wpos.as_::<i64>().distance_squared(site.wpos.as_()),
+                    )
+                })
+                .collect::<Vec<_>>();
+            partners.sort_by_key(|(_, dist_sqr)| *dist_sqr);
+            let partners = partners
+                .into_iter()
+                .take(TRADE_PARTNERS)
+                .map(|(other_id, _)| other_id)
+                .collect();
+            (site_id, partners)
+        })
+        .collect::<Vec<_>>();
+    for (site_id, partners) in trade_partners {
+        if let Some(site) = data.sites.get_mut(site_id) {
+            site.economy.trade_partners = partners;
+        }
+    }
+}
+
+fn on_tick(ctx: EventCtx<SimulateEconomy, OnTick>) {
+    let data = &mut *ctx.state.data_mut();
+    let dt = ctx.event.dt * SITE_ECONOMY_TICK_SKIP as f32;
+
+    let ticking_sites = data
+        .sites
+        .iter()
+        .filter(|(_, site)| {
+            site.economy.is_active()
+                && (site.seed as u64 + ctx.event.tick) % SITE_ECONOMY_TICK_SKIP == 0
+        })
+        .map(|(site_id, _)| site_id)
+        .collect::<Vec<_>>();
+
+    for site_id in ticking_sites {
+        produce_and_consume(data, site_id, dt);
+    }
+}
+
+fn on_trade(ctx: EventCtx<SimulateEconomy, OnTrade>) {
+    let data = &mut *ctx.state.data_mut();
+
+    // Merchants on the road sell what they are carrying, which was already taken
+    // out of the stock of the site when loading it
+    if let Some(cargo) = ctx
+        .event
+        .npc
+        .and_then(|npc| data.npcs.get_mut(npc))
+        .and_then(|npc| npc.cargo.as_mut())
+    {
+        for (good, amount) in ctx.event.goods.iter() {
+            cargo.change_goods(*good, *amount);
+        }
+    } else if let Some(site) = data.sites.get_mut(ctx.event.site) {
+        for (good, amount) in ctx.event.goods.iter() {
+            site.economy.change_stock(*good, *amount);
+        }
+    }
+}
+
+/// Moves the stock of the site towards what its production and consumption
+/// settle on, which is lower for goods whose natural resources around the site
+/// were used up.
+fn produce_and_consume(data: &mut Data, site_id: SiteId, dt: f32) {
+    let Some(site) = data.sites.get(site_id) else {
+        return;
+    };
+
+    let site_chunk = site.wpos.wpos_to_cpos();
+    let mut resources = HashMap::<Good, (f32, f32)>::new();
+    for x in -GATHER_RADIUS..=GATHER_RADIUS {
+        for y in -GATHER_RADIUS..=GATHER_RADIUS {
+            let chunk_resources = data
+                .nature
+                .get_chunk_resources(site_chunk + Vec2::new(x, y));
+            for (resource, amount) in chunk_resources {
+                if let Some(good) = resource_good(resource) {
+                    let (total, count) = resources.entry(good).or_default();
+                    *total += amount;
+                    *count += 1.0;
+                }
+            }
+        }
+    }
+
+    let Some(site) = data.sites.get_mut(site_id) else {
+        return;
+    };
+    site.economy.produce_and_consume(
+        |good| {
+            resources
+                .get(&good)
+                .map_or(1.0, |(total, count)| total / count.max(1.0))
+        },
+        dt,
+    );
+}
@@ -1,12 +1,16 @@
 use crate::{
-    data::{npc::SimulationMode, Npc},
+    data::{
+        economy::{deliver_cargo, is_deliverable, load_cargo, return_cargo},
+        npc::{CargoAction, SimulationMode},
+        Npc,
+    },
     event::{EventCtx, OnDeath, OnMountVolume, OnTick},
     RtState, Rule, RuleError,
 };
 use common::{
     comp::{self, Body},
     mounting::{Volume, VolumePos},
-    rtsim::{Actor, NpcAction, NpcActivity, Personality},
+    rtsim::{Actor, NpcAction, NpcActivity, NpcInput, Personality},
     terrain::{CoordinateConversions, TerrainChunkSize},
     vol::RectVolSize,
 };
@@ -194,13 +198,18 @@ fn on_tick(ctx: EventCtx<SimulateNpcs, OnTick>) {
         }
     }
 
+    let mut shared_reports = Vec::new();
     for (npc_id, npc) in data.npcs.npcs.iter_mut().filter(|(_, npc)| !npc.is_dead) {
+        shared_reports.append(&mut npc.controller.shared_reports);
+
         if matches!(npc.mode, SimulationMode::Simulated) {
             // Consume NPC actions
             for action in std::mem::take(&mut npc.controller.actions) {
                 match action {
                     NpcAction::Say(_, _) => {}, // Currently, just swallow interactions
                     NpcAction::Attack(_) => {}, // TODO: Implement simulated combat
+                    // Characters only interact with loaded NPCs
+                    NpcAction::OfferQuest(_, _) | NpcAction::CompleteQuest(_, _) => {},
                 }
             }
 
@@ -299,5 +308,34 @@ fn on_tick(ctx: EventCtx<SimulateNpcs, OnTick>) {
             }
             npc.home = Some(new_home);
         }
+
+        // Load or unload cargo if required
+        match npc.controller.cargo_action.take() {
+            Some(CargoAction::Load(cargo)) if npc.cargo.is_none() => {
+                let cargo = load_cargo(&mut data.sites, cargo);
+                npc.cargo = (!cargo.goods.is_empty()).then_some(cargo);
+            },
+            // Goods only count once they've actually arrived, and go back to where they came
+            // from if the merchant gave up on getting them there
+            Some(CargoAction::Unload) => {
+                if let Some(cargo) = npc.cargo.take() {
+                    if npc.current_site == Some(cargo.to)
+                        && is_deliverable(&data.sites, &data.factions, &cargo)
+                    {
+                        deliver_cargo(&mut data.sites, cargo);
+                    } else {
+                        return_cargo(&mut data.sites, cargo);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // Pass on gossip
+    for (npc_id, report) in shared_reports {
+        if let Some(npc) = data.npcs.get_mut(npc_id) {
+            npc.inbox.push_back(NpcInput::Report(report));
+        }
     }
 }
@@ -4,7 +4,7 @@ use crate::{
 };
 use common::{
     grid::Grid,
-    rtsim::{Actor, NpcInput},
+    rtsim::{Actor, NpcInput, Role},
     terrain::CoordinateConversions,
 };
 
@@ -97,16 +97,19 @@ fn on_tick(ctx: EventCtx<SyncNpcs, OnTick>) {
                     .find_map(|site| data.sites.world_site_map.get(site).copied())
             });
 
-        // Share known reports with current site, if it's our home
+        // Share known reports with current site, if it's our home. Civilised visitors
+        // read up on what's going on at the site too, and carry the news with them.
         // TODO: Only share new reports
         if let Some(current_site) = npc.current_site
-            && Some(current_site) == npc.home
+            && (Some(current_site) == npc.home || matches!(npc.role, Role::Civilised(_)))
         {
             if let Some(site) = data.sites.get_mut(current_site) {
                 // TODO: Sites should have an inbox and their own AI code
-                site.known_reports.extend(npc.known_reports
-                    .iter()
-                    .copied());
+                if Some(current_site) == npc.home {
+                    site.known_reports.extend(npc.known_reports
+                        .iter()
+                        .copied());
+                }
                 npc.inbox.extend(site.known_reports
                     .iter()
                     .copied()
@@ -0,0 +1,729 @@
+use crate::{
+    data::{npc::Profession, Data, FactionId, ReportKind, Sentiment, SiteId},
+    event::{EventCtx, OnTick},
+    RtState, Rule, RuleError,
+};
+use common::rtsim::{Actor, NpcId, Role};
+use hashbrown::HashSet;
+use rand::prelude::*;
+use rand_chacha::ChaChaRng;
+use world::{site::SiteKind, IndexRef};
+
+/// Factions only reconsider how they feel about their neighbours every so many
+/// ticks
+const FACTION_WAR_TICK_SKIP: u64 = 900;
+/// Sites only send out, or fight off, raiding parties every so many ticks
+const SITE_WAR_TICK_SKIP: u64 = 300;
+/// The distance, in blocks, within which the towns of two factions make them
+/// neighbours, and so able to raid one another.
+const RAID_RANGE: f32 = 3000.0;
+/// The number of guards that always stay behind to defend their town.
+const MIN_GARRISON: usize = 3;
+/// The most guards that a town sends out on a single raid.
+const MAX_RAID_PARTY: usize = 6;
+/// How many times stronger than another a faction or raiding party must be
+/// before it feels confident enough to attack.
+const STRENGTH_ADVANTAGE: f32 = 1.5;
+
+/// A rule that has factions fall out with their neighbours, declare hostility,
+/// and send raiding parties of guards to take their towns from them.
+pub struct WageWar;
+
+impl Rule for WageWar {
+    fn start(rtstate: &mut RtState) -> Result<Self, RuleError> {
+        rtstate.bind::<Self, OnTick>(on_tick);
+
+        Ok(Self)
+    }
+}
+
+/// Whether the site is a town, the only kind of site that factions fight over.
+fn is_town(data: &Data, index: IndexRef, site: SiteId) -> bool {
+    data.sites
+        .get(site)
+        .and_then(|site| site.world_site)
+        .map_or(false, |ws| {
+            matches!(
+                &index.sites.get(ws).kind,
+                SiteKind::Refactor(_)
+                    | SiteKind::CliffTown(_)
+                    | SiteKind::SavannahPit(_)
+                    | SiteKind::CoastalTown(_)
+                    | SiteKind::DesertCity(_)
+            )
+        })
+}
+
+/// The towns held by the given faction, out of all the `towns` of the world
+/// (see [`is_town`]).
+fn towns_of(data: &Data, towns: &HashSet<SiteId>, faction: FactionId) -> Vec<SiteId> {
+    data.sites
+        .iter()
+        .filter(|(id, site)| site.faction == Some(faction) && towns.contains(id))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// The living guards of a site that are not off raiding elsewhere.
+fn guards(data: &Data, site: SiteId) -> Vec<NpcId> {
+    data.sites.get(site).map_or(Vec::new(), |site| {
+        site.population
+            .iter()
+            .copied()
+            .filter(|npc_id| {
+                data.npcs.get(*npc_id).map_or(false, |npc| {
+                    !npc.is_dead
+                        && npc.raid.is_none()
+                        && matches!(npc.profession(), Some(Profession::Guard))
+                })
+            })
+            .collect()
+    })
+}
+
+/// The military strength of a faction, judged by the guards of its towns.
+fn strength(data: &Data, towns: &HashSet<SiteId>, faction: FactionId) -> usize {
+    towns_of(data, towns, faction)
+        .into_iter()
+        .map(|town| guards(data, town).len())
+        .sum()
+}
+
+fn distance(data: &Data, a: SiteId, b: SiteId) -> f32 {
+    match (data.sites.get(a), data.sites.get(b)) {
+        (Some(a), Some(b)) => a.wpos.as_::<f32>().distance(b.wpos.as_()),
+        _ => f32::INFINITY,
+    }
+}
+
+fn move_home(data: &mut Data, npc_id: NpcId, new_home: SiteId) {
+    let Some(npc) = data.npcs.get_mut(npc_id) else {
+        return;
+    };
+    if let Some(old_home) = npc.home.and_then(|home| data.sites.get_mut(home)) {
+        old_home.population.remove(&npc_id);
+    }
+    if let Some(new_home) = data.sites.get_mut(new_home) {
+        new_home.population.insert(npc_id);
+    }
+    npc.home = Some(new_home);
+}
+
+fn set_hostile(data: &mut Data, a: FactionId, b: FactionId, hostile: bool) {
+    for (this, other) in [(a, b), (b, a)] {
+        if let Some(faction) = data.factions.get_mut(this) {
+            if hostile {
+                faction.hostile_to.insert(other);
+            } else {
+                faction.hostile_to.remove(&other);
+            }
+        }
+    }
+    data.factions.changed = true;
+}
+
+fn on_tick(ctx: EventCtx<WageWar, OnTick>) {
+    let data = &mut *ctx.state.data_mut();
+    let mut rng = ChaChaRng::seed_from_u64(ctx.event.seed);
+
+    let faction_ids = data
+        .factions
+        .iter()
+        .filter(|(_, faction)| (faction.seed as u64 + ctx.event.tick) % FACTION_WAR_TICK_SKIP == 0)
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    let site_ids = data
+        .sites
+        .iter()
+        .filter(|(_, site)| (site.seed as u64 + ctx.event.tick) % SITE_WAR_TICK_SKIP == 0)
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    if faction_ids.is_empty() && site_ids.is_empty() {
+        return;
+    }
+
+    let towns = data
+        .sites
+        .keys()
+        .filter(|site_id| is_town(data, ctx.index, *site_id))
+        .collect::<HashSet<_>>();
+    for faction_id in faction_ids {
+        update_relations(data, &towns, faction_id);
+    }
+    for site_id in site_ids {
+        if towns.contains(&site_id) {
+            fight_raid(data, &towns, site_id, &mut rng);
+            send_raid(data, &towns, site_id);
+        }
+    }
+}
+
+/// Have a faction reconsider how it feels about its neighbours, declaring or
+/// ending hostilities as a result.
+fn update_relations(data: &mut Data, towns: &HashSet<SiteId>, faction_id: FactionId) {
+    let our_towns = towns_of(data, towns, faction_id);
+    let our_strength = strength(data, towns, faction_id);
+
+    let neighbours = data
+        .sites
+        .iter()
+        .filter(|(id, site)| {
+            site.faction.map_or(false, |f| f != faction_id)
+                && towns.contains(id)
+                && our_towns
+                    .iter()
+                    .any(|town| distance(data, *town, *id) < RAID_RANGE)
+        })
+        .filter_map(|(_, site)| site.faction)
+        .collect::<HashSet<_>>();
+
+    let known_reports = our_towns
+        .iter()
+        .filter_map(|town| data.sites.get(*town))
+        .flat_map(|town| town.known_reports.iter())
+        .filter_map(|report| data.reports.get(*report))
+        .map(|report| report.kind)
+        .collect::<Vec<_>>();
+    let short_of_goods = known_reports
+        .iter()
+        .any(|kind| matches!(kind, ReportKind::Shortage { .. }));
+
+    for other in neighbours.iter().copied() {
+        let mut change = 0.0;
+        // Stronger factions covet the land of their weaker neighbours...
+        if our_strength as f32 > strength(data, towns, other) as f32 * STRENGTH_ADVANTAGE {
+            change -= 0.01;
+        }
+        // ...all the more so when their own land can't provide for them
+        if short_of_goods {
+            change -= 0.01;
+        }
+        // Merchants travelling between the two keep relations friendly
+        let site_faction = |site| data.sites.get(site).and_then(|site| site.faction);
+        if data
+            .npcs
+            .values()
+            .filter_map(|npc| npc.cargo.as_ref())
+            .any(|cargo| {
+                let (from, to) = (site_faction(cargo.from), site_faction(cargo.to));
+                (from == Some(faction_id) && to == Some(other))
+                    || (from == Some(other) && to == Some(faction_id))
+            })
+        {
+            change += 0.02;
+        }
+        // Attacks on our towns by their people are not easily forgotten
+        let attacks = known_reports
+            .iter()
+            .filter(|kind| {
+                matches!(kind, ReportKind::SiteAttack { attacker: Actor::Npc(attacker), .. }
+                    if data.npcs.get(*attacker).map_or(false, |npc| npc.faction == Some(other)))
+            })
+            .count();
+        change -= 0.03 * attacks as f32;
+
+        if let Some(faction) = data.factions.get_mut(faction_id) {
+            let sentiment = faction.sentiments.toward_mut(other);
+            if change < 0.0 {
+                sentiment.change_by(change, Sentiment::VILLAIN);
+            } else {
+                sentiment.change_by(change, Sentiment::ALLY);
+            }
+        }
+    }
+
+    let Some(hostile_to) = data
+        .factions
+        .get(faction_id)
+        .map(|faction| faction.hostile_to.clone())
+    else {
+        return;
+    };
+    let feels = |a: FactionId, b: FactionId, sentiment: f32| {
+        data.factions
+            .get(a)
+            .map_or(false, |faction| faction.sentiments.toward(b).is(sentiment))
+    };
+    let (declare, make_peace) = neighbours
+        .union(&hostile_to)
+        .copied()
+        .partition::<Vec<_>, _>(|other| !hostile_to.contains(other));
+    let declare = declare
+        .into_iter()
+        .filter(|other| feels(faction_id, *other, Sentiment::ENEMY))
+        .collect::<Vec<_>>();
+    // Hostilities end once both sides have cooled off, or there's nothing left to
+    // fight over
+    let make_peace = make_peace
+        .into_iter()
+        .filter(|other| {
+            !data.factions.contains_key(*other)
+                || towns_of(data, towns, *other).is_empty()
+                || (!feels(faction_id, *other, Sentiment::RIVAL)
+                    && !feels(*other, faction_id, Sentiment::RIVAL))
+        })
+        .collect::<Vec<_>>();
+
+    for other in declare {
+        set_hostile(data, faction_id, other, true);
+    }
+    for other in make_peace {
+        set_hostile(data, faction_id, other, false);
+    }
+}
+
+/// Send a party of guards from the site to raid the nearest town of a hostile
+/// faction that they think they can take.
+fn send_raid(data: &mut Data, towns: &HashSet<SiteId>, site_id: SiteId) {
+    let Some(faction_id) = data.sites.get(site_id).and_then(|site| site.faction) else {
+        return;
+    };
+    let hostile_to = data
+        .factions
+        .get(faction_id)
+        .map(|faction| faction.hostile_to.clone())
+        .unwrap_or_default();
+
+    // Call off raids on sites that are no longer held by a hostile faction
+    let raiders = data
+        .npcs
+        .iter()
+        .filter(|(_, npc)| npc.home == Some(site_id))
+        .filter_map(|(id, npc)| Some((id, npc.raid?)))
+        .collect::<Vec<_>>();
+    let mut raiding = false;
+    for (npc_id, target) in raiders {
+        if data
+            .sites
+            .get(target)
+            .and_then(|site| site.faction)
+            .map_or(false, |f| hostile_to.contains(&f))
+        {
+            raiding = true;
+        } else if let Some(npc) = data.npcs.get_mut(npc_id) {
+            npc.raid = None;
+        }
+    }
+    // Only one raid at a time
+    if raiding || hostile_to.is_empty() {
+        return;
+    }
+
+    let garrison = guards(data, site_id);
+    let party_size = garrison
+        .len()
+        .saturating_sub(MIN_GARRISON)
+        .min(MAX_RAID_PARTY);
+    if party_size == 0 {
+        return;
+    }
+
+    let Some(target) = data
+        .sites
+        .iter()
+        .filter(|(id, site)| {
+            site.faction.map_or(false, |f| hostile_to.contains(&f))
+                && towns.contains(id)
+                && distance(data, site_id, *id) < RAID_RANGE
+                && party_size as f32 >= guards(data, *id).len() as f32 * STRENGTH_ADVANTAGE
+        })
+        .min_by(|(a, _), (b, _)| {
+            distance(data, site_id, *a).total_cmp(&distance(data, site_id, *b))
+        })
+        .map(|(id, _)| id)
+    else {
+        return;
+    };
+
+    for npc_id in garrison.into_iter().take(party_size) {
+        if let Some(npc) = data.npcs.get_mut(npc_id) {
+            npc.raid = Some(target);
+        }
+    }
+}
+
+/// Once most of a raiding party has arrived at the site, the raiders and the
+/// defenders fight it out.
+fn fight_raid(data: &mut Data, towns: &HashSet<SiteId>, site_id: SiteId, rng: &mut impl Rng) {
+    let Some(defender_faction) = data.sites.get(site_id).and_then(|site| site.faction) else {
+        return;
+    };
+
+    let raiders = data
+        .npcs
+        .iter()
+        .filter(|(_, npc)| npc.raid == Some(site_id) && !npc.is_dead)
+        .map(|(id, npc)| (id, npc.current_site == Some(site_id)))
+        .collect::<Vec<_>>();
+    let arrived = raiders
+        .iter()
+        .filter(|(_, arrived)| *arrived)
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    // Wait for most of the party to arrive before attacking
+    if arrived.is_empty() || arrived.len() * 2 < raiders.len() {
+        return;
+    }
+    let Some(attacker_faction) = data.npcs.get(arrived[0]).and_then(|npc| npc.faction) else {
+        return;
+    };
+    // The site might have changed hands, or the factions made peace, while the
+    // party was on its way
+    let hostile = data
+        .factions
+        .get(attacker_faction)
+        .map_or(false, |faction| {
+            faction.hostile_to.contains(&defender_faction)
+        });
+    if attacker_faction == defender_faction || !hostile {
+        end_raid(data, &raiders);
+        return;
+    }
+
+    let defenders = guards(data, site_id)
+        .into_iter()
+        .filter(|npc_id| {
+            data.npcs
+                .get(*npc_id)
+                .map_or(false, |npc| npc.current_site == Some(site_id))
+        })
+        .count();
+
+    data.report_to_site(site_id, ReportKind::SiteAttack {
+        site: site_id,
+        attacker: Actor::Npc(arrived[0]),
+    });
+
+    if rng.gen_bool(arrived.len() as f64 / (arrived.len() + defenders) as f64) {
+        conquer(data, towns, site_id, attacker_faction, &arrived);
+    } else if let Some(faction) = data.factions.get_mut(defender_faction) {
+        // Being raided, even unsuccessfully, leaves a grudge
+        faction
+            .sentiments
+            .toward_mut(attacker_faction)
+            .change_by(-0.1, Sentiment::VILLAIN);
+    }
+
+    // Win or lose, the raid is over
+    end_raid(data, &raiders);
+}
+
+/// Call off the raid of the given raiders, as collected by [`fight_raid`].
+fn end_raid(data: &mut Data, raiders: &[(NpcId, bool)]) {
+    for (npc_id, _) in raiders {
+        if let Some(npc) = data.npcs.get_mut(*npc_id) {
+            npc.raid = None;
+        }
+    }
+}
+
+/// Hand the site over to the given faction.
+fn conquer(
+    data: &mut Data,
+    towns: &HashSet<SiteId>,
+    site_id: SiteId,
+    faction_id: FactionId,
+    raiders: &[NpcId],
+) {
+    let Some(site) = data.sites.get_mut(site_id) else {
+        return;
+    };
+    let old_faction = site.faction.replace(faction_id);
+    data.factions.changed = true;
+
+    if let Some(old_faction) = old_faction {
+        // The defeated guards fall back to the nearest town still held by their
+        // faction, everybody else bows to the new rulers
+        let refuge = towns_of(data, towns, old_faction)
+            .into_iter()
+            .min_by(|a, b| distance(data, site_id, *a).total_cmp(&distance(data, site_id, *b)));
+        let residents = data.sites[site_id]
+            .population
+            .iter()
+            .copied()
+            .filter(|npc_id| {
+                data.npcs.get(*npc_id).map_or(false, |npc| {
+                    matches!(npc.role, Role::Civilised(_)) && npc.faction == Some(old_faction)
+                })
+            })
+            .collect::<Vec<_>>();
+        for npc_id in residents {
+            let is_guard = data.npcs.get(npc_id).map_or(false, |npc| {
+                matches!(npc.profession(), Some(Profession::Guard))
+            });
+            if let Some(refuge) = refuge
+                && is_guard
+            {
+                move_home(data, npc_id, refuge);
+            } else if let Some(npc) = data.npcs.get_mut(npc_id) {
+                npc.faction = Some(faction_id);
+            }
+        }
+
+        if let Some(faction) = data.factions.get_mut(faction_id) {
+            faction
+                .sentiments
+                .toward_mut(old_faction)
+                .change_by(0.1, Sentiment::ALLY);
+        }
+        if let Some(faction) = data.factions.get_mut(old_faction) {
+            faction
+                .sentiments
+                .toward_mut(faction_id)
+                .change_by(-0.2, Sentiment::VILLAIN);
+        }
+    }
+
+    // Half of the raiders stay behind to hold the town
+    for npc_id in raiders.iter().take((raiders.len() + 1) / 2) {
+        move_home(data, *npc_id, site_id);
+    }
+
+    let kind = ReportKind::SiteConquered {
+        site: site_id,
+        faction: faction_id,
+    };
+    data.report_to_site(site_id, kind);
+    for home in raiders
+        .iter()
+        .filter_map(|npc_id| data.npcs.get(*npc_id)?.home)
+        .collect::<HashSet<_>>()
+    {
+        data.report_to_site(home, kind);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Faction, Npc, Site};
+    use common::comp;
+    use vek::*;
+
+    fn faction(data: &mut Data) -> FactionId {
+        data.factions.create(Faction {
+            seed: 0,
+            leader: None,
+            good_or_evil: false,
+            sentiments: Default::default(),
+            hostile_to: Default::default(),
+        })
+    }
+
+    fn town(data: &mut Data, towns: &mut HashSet<SiteId>, x: i32, faction: FactionId) -> SiteId {
+        let site = data
+            .sites
+            .create(Site::at(Vec2::new(x, 0)).with_faction(faction));
+        towns.insert(site);
+        site
+    }
+
+    fn resident(
+        data: &mut Data,
+        home: SiteId,
+        faction: FactionId,
+        profession: Profession,
+    ) -> NpcId {
+        let wpos = data.sites[home].wpos.as_::<f32>().with_z(0.0);
+        data.spawn_npc(
+            Npc::new(
+                0,
+                wpos,
+                comp::Body::Humanoid(comp::humanoid::Body::random()),
+                Role::Civilised(Some(profession)),
+            )
+            .with_home(home)
+            .with_faction(faction),
+        )
+    }
+
+    fn guards_of(data: &mut Data, home: SiteId, faction: FactionId, n: usize) -> Vec<NpcId> {
+        (0..n)
+            .map(|_| resident(data, home, faction, Profession::Guard))
+            .collect()
+    }
+
+    fn raiding(data: &Data, target: SiteId) -> usize {
+        data.npcs
+            .values()
+            .filter(|npc| npc.raid == Some(target))
+            .count()
+    }
+
+    #[test]
+    fn conquering_hands_the_town_over() {
+        let mut data = Data::empty();
+        let mut towns = HashSet::new();
+        let (attacker, defender) = (faction(&mut data), faction(&mut data));
+        let home = town(&mut data, &mut towns, -1000, attacker);
+        let site = town(&mut data, &mut towns, 0, defender);
+        let refuge = town(&mut data, &mut towns, 1000, defender);
+        let far = town(&mut data, &mut towns, 2000, defender);
+        let defeated = guards_of(&mut data, site, defender, 2);
+        let farmer = resident(&mut data, site, defender, Profession::Farmer);
+        let raiders = guards_of(&mut data, home, attacker, 3);
+
+        conquer(&mut data, &towns, site, attacker, &raiders);
+
+        assert_eq!(data.sites[site].faction, Some(attacker));
+        assert!(data.factions.changed);
+        // The defeated guards fall back to the nearest town their faction still holds
+        for guard in defeated {
+            assert_eq!(data.npcs[guard].home, Some(refuge));
+            assert_eq!(data.npcs[guard].faction, Some(defender));
+            assert!(data.sites[refuge].population.contains(&guard));
+            assert!(!data.sites[site].population.contains(&guard));
+            assert!(!data.sites[far].population.contains(&guard));
+        }
+        // Everybody else stays and switches sides
+        assert_eq!(data.npcs[farmer].home, Some(site));
+        assert_eq!(data.npcs[farmer].faction, Some(attacker));
+        // Half of the raiders stay behind to hold the town
+        let holding = raiders
+            .iter()
+            .filter(|raider| data.npcs[**raider].home == Some(site))
+            .count();
+        assert_eq!(holding, 2);
+
+        let conquered = ReportKind::SiteConquered {
+            site,
+            faction: attacker,
+        };
+        assert!(data.site_knows(site, conquered));
+        assert!(data.site_knows(home, conquered));
+    }
+
+    #[test]
+    fn raids_leave_a_garrison_behind() {
+        let mut data = Data::empty();
+        let mut towns = HashSet::new();
+        let (ours, theirs) = (faction(&mut data), faction(&mut data));
+        let home = town(&mut data, &mut towns, 0, ours);
+        let target = town(&mut data, &mut towns, 1000, theirs);
+        guards_of(&mut data, home, ours, MIN_GARRISON);
+
+        // Nobody raids a faction that they're not hostile to
+        guards_of(&mut data, home, ours, 1);
+        send_raid(&mut data, &towns, home);
+        assert_eq!(raiding(&data, target), 0);
+
+        set_hostile(&mut data, ours, theirs, true);
+        send_raid(&mut data, &towns, home);
+        assert_eq!(raiding(&data, target), 1);
+        assert_eq!(guards(&data, home).len(), MIN_GARRISON);
+
+        // Only one raid at a time
+        guards_of(&mut data, home, ours, 1);
+        send_raid(&mut data, &towns, home);
+        assert_eq!(raiding(&data, target), 1);
+    }
+
+    #[test]
+    fn raiding_parties_are_limited_in_size() {
+        let mut data = Data::empty();
+        let mut towns = HashSet::new();
+        let (ours, theirs) = (faction(&mut data), faction(&mut data));
+        let home = town(&mut data, &mut towns, 0, ours);
+        let strong = town(&mut data, &mut towns, 500, theirs);
+        let weak = town(&mut data, &mut towns, 1000, theirs);
+        guards_of(&mut data, home, ours, MIN_GARRISON + MAX_RAID_PARTY * 2);
+        guards_of(&mut data, strong, theirs, MAX_RAID_PARTY);
+        guards_of(&mut data, weak, theirs, 4);
+        set_hostile(&mut data, ours, theirs, true);
+
+        // The nearest town is too well defended for the biggest party allowed
+        send_raid(&mut data, &towns, home);
+        assert_eq!(raiding(&data, strong), 0);
+        assert_eq!(raiding(&data, weak), MAX_RAID_PARTY);
+        assert_eq!(guards(&data, home).len(), MIN_GARRISON + MAX_RAID_PARTY);
+    }
+
+    #[test]
+    fn raids_end_without_a_fight_once_peace_is_made() {
+        let mut data = Data::empty();
+        let mut towns = HashSet::new();
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let (ours, theirs) = (faction(&mut data), faction(&mut data));
+        let home = town(&mut data, &mut towns, 0, ours);
+        let target = town(&mut data, &mut towns, 1000, theirs);
+        let raiders = guards_of(&mut data, home, ours, MAX_RAID_PARTY);
+        let arrive = |data: &mut Data| {
+            for raider in &raiders {
+                let npc = &mut data.npcs[*raider];
+                npc.raid = Some(target);
+                npc.current_site = Some(target);
+            }
+        };
+
+        // Not hostile (any more)
+        arrive(&mut data);
+        fight_raid(&mut data, &towns, target, &mut rng);
+        assert_eq!(raiding(&data, target), 0);
+        assert_eq!(data.sites[target].faction, Some(theirs));
+        assert!(!data.site_knows(target, ReportKind::SiteAttack {
+            site: target,
+            attacker: Actor::Npc(raiders[0]),
+        }));
+
+        // The town already belongs to the raiders' faction
+        set_hostile(&mut data, ours, theirs, true);
+        data.sites.get_mut(target).unwrap().faction = Some(ours);
+        arrive(&mut data);
+        fight_raid(&mut data, &towns, target, &mut rng);
+        assert_eq!(raiding(&data, target), 0);
+        assert!(
+            raiders
+                .iter()
+                .all(|raider| data.npcs[*raider].home == Some(home))
+        );
+    }
+
+    #[test]
+    fn hostility_is_declared_and_ended() {
+        let mut data = Data::empty();
+        let mut towns = HashSet::new();
+        let (ours, theirs, distant) = (faction(&mut data), faction(&mut data), faction(&mut data));
+        town(&mut data, &mut towns, 0, ours);
+        let their_town = town(&mut data, &mut towns, 1000, theirs);
+        town(&mut data, &mut towns, RAID_RANGE as i32 * 2, distant);
+        for other in [theirs, distant] {
+            data.factions[ours]
+                .sentiments
+                .toward_mut(other)
+                .change_by(-0.7, Sentiment::VILLAIN);
+        }
+
+        // Only neighbours are worth declaring hostility toward
+        update_relations(&mut data, &towns, ours);
+        assert!(data.factions[ours].hostile_to.contains(&theirs));
+        assert!(data.factions[theirs].hostile_to.contains(&ours));
+        assert!(!data.factions[ours].hostile_to.contains(&distant));
+
+        // Hostility lasts while either side still holds a grudge...
+        data.factions[theirs]
+            .sentiments
+            .toward_mut(ours)
+            .change_by(-0.4, Sentiment::VILLAIN);
+        data.factions[ours]
+            .sentiments
+            .toward_mut(theirs)
+            .change_by(0.7, Sentiment::ALLY);
+        update_relations(&mut data, &towns, ours);
+        assert!(data.factions[ours].hostile_to.contains(&theirs));
+
+        // ...and ends once both have cooled off
+        data.factions[theirs]
+            .sentiments
+            .toward_mut(ours)
+            .change_by(0.4, Sentiment::ALLY);
+        update_relations(&mut data, &towns, ours);
+        assert!(!data.factions[ours].hostile_to.contains(&theirs));
+        assert!(!data.factions[theirs].hostile_to.contains(&ours));
+
+        // Or once there's nothing left to fight over
+        set_hostile(&mut data, ours, theirs, true);
+        data.sites.get_mut(their_town).unwrap().faction = None;
+        update_relations(&mut data, &towns, ours);
+        assert!(!data.factions[ours].hostile_to.contains(&theirs));
+    }
+}
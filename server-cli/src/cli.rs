@@ -4,8 +4,12 @@
 
 use clap::Parser;
 use common::comp;
-use server::persistence::SqlLogMode;
-use std::sync::mpsc::Sender;
+use server::persistence::{
+    audit_log::{AuditLogEntry, AuditLogSearch},
+    character_archive::CharacterArchiveJob,
+    SqlLogMode,
+};
+use std::{path::PathBuf, sync::mpsc::Sender};
 use tracing::error;
 
 #[derive(Clone, Debug, Parser)]
@@ -40,6 +44,65 @@ pub enum Shutdown {
     Cancel,
 }
 
+#[derive(Clone, Debug, Parser)]
+pub enum Plugin {
+    /// Lists the loaded plugins
+    List,
+    /// Loads a plugin from the plugin directory
+    Load {
+        /// Name of the plugin archive, without the `.plugin.tar` extension
+        file_name: String,
+    },
+    /// Unloads a plugin
+    Unload {
+        /// Name of the plugin
+        name: String,
+    },
+    /// Reloads a plugin from the archive it was loaded from
+    Reload {
+        /// Name of the plugin
+        name: String,
+    },
+}
+
+#[derive(Clone, Debug, Parser)]
+pub enum Character {
+    /// Lists the characters of a player
+    List {
+        /// Name of the player
+        username: String,
+    },
+    /// Exports a character to the character_archives directory
+    Export {
+        /// Name of the player the character belongs to
+        username: String,
+        /// Id of the character, as shown by `character list`
+        character_id: i64,
+    },
+    /// Imports an archive from the character_archives directory as a new
+    /// character
+    Import {
+        /// Name of the player who will own the character
+        username: String,
+        /// File name of the archive
+        file_name: String,
+    },
+}
+
+#[derive(Clone, Debug, Parser)]
+pub enum Whitelist {
+    /// Adds a player to the whitelist
+    Add {
+        /// Name of the player
+        username: String,
+    },
+    /// Removes a player from the whitelist
+    Remove {
+        /// Name of the player
+        username: String,
+    },
+}
+
 #[derive(Clone, Debug, Parser)]
 pub enum SharedCommand {
     /// Perform operations on the admin list
@@ -71,6 +134,93 @@ pub enum Message {
     },
     /// Disconnects all connected clients
     DisconnectAllClients,
+    /// Manage the server plugins
+    Plugin {
+        #[command(subcommand)]
+        command: Plugin,
+    },
+    /// Move characters between servers
+    Character {
+        #[command(subcommand)]
+        command: Character,
+    },
+    /// Backs up the database, persisted terrain and rtsim data
+    Backup,
+    /// Lists the players currently online
+    ListPlayers,
+    /// Disconnects a player
+    Kick {
+        /// Alias of the player
+        alias: String,
+        /// Reason shown to the player
+        #[arg(default_value = "")]
+        reason: String,
+    },
+    /// Bans a player and kicks them if they are online
+    Ban {
+        /// Name of the player
+        username: String,
+        /// Reason shown to the player
+        #[arg(default_value = "")]
+        reason: String,
+        /// Duration of the ban in seconds, permanent if not set
+        #[arg(long, short)]
+        duration: Option<u64>,
+    },
+    /// Lifts the ban of a player
+    Unban {
+        /// Name of the player
+        username: String,
+    },
+    /// Perform operations on the whitelist
+    Whitelist {
+        #[command(subcommand)]
+        command: Whitelist,
+    },
+    /// Sends a message to all players
+    SendGlobalMsg { msg: String },
+    /// Sets the server description, removes it if empty
+    Motd {
+        #[arg(default_value = "")]
+        description: String,
+    },
+    /// Searches the privileged commands run on the server, most recent first
+    AuditLog {
+        /// Alias or UUID of a player who ran the commands or was targeted by
+        /// them
+        #[arg(long, short)]
+        player: Option<String>,
+        /// Keyword of the command, without the slash
+        #[arg(long, short)]
+        command: Option<String>,
+        /// Maximum number of entries
+        #[arg(long, short, default_value_t = 20)]
+        limit: u32,
+    },
+}
+
+/// Reply to a [`Message`] sent through the web API
+#[derive(Debug, Clone)]
+pub enum MessageReturn {
+    Done,
+    Failed(String),
+    Players(Vec<(String, common::uuid::Uuid)>),
+    AuditLog(Vec<AuditLogEntry>),
+    /// Only returned while handling a message, replaced by `AuditLog` or
+    /// `Failed` once the search finished
+    PendingAuditLog(AuditLogSearch),
+    /// Only returned while handling a message, replaced by `Done` or `Failed`
+    /// once the character job finished
+    PendingCharacterJob(CharacterArchiveJob),
+}
+
+impl From<Result<(), String>> for MessageReturn {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self::Done,
+            Err(e) => Self::Failed(e),
+        }
+    }
 }
 
 #[derive(Parser)]
@@ -129,6 +279,9 @@ pub struct ArgvApp {
     #[arg(default_value_t, long, short, value_parser = clap::value_parser!(SqlLogMode))]
     /// Enables SQL logging
     pub sql_log_mode: SqlLogMode,
+    #[arg(long, value_name = "FILE")]
+    /// Records the session, so it can be replayed with `veloren-server-replay`
+    pub record: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Option<ArgvCommand>,
 }
@@ -18,27 +18,44 @@ mod tui_runner;
 mod tuilog;
 mod web;
 use crate::{
-    cli::{Admin, ArgvApp, ArgvCommand, Message, SharedCommand, Shutdown},
+    cli::{
+        Admin, ArgvApp, ArgvCommand, Character, Message, MessageReturn, Plugin, SharedCommand,
+        Shutdown, Whitelist,
+    },
     shutdown_coordinator::ShutdownCoordinator,
     tui_runner::Tui,
     tuilog::TuiLog,
 };
-use common::{clock::Clock, consts::MIN_RECOMMENDED_TOKIO_THREADS};
+use common::{
+    character::CharacterId, clock::Clock, comp::ChatType, consts::MIN_RECOMMENDED_TOKIO_THREADS,
+};
 use common_base::span;
-use core::sync::atomic::{AtomicUsize, Ordering};
-use server::{persistence::DatabaseSettings, settings::Protocol, Event, Input, Server};
+use common_net::msg::ServerGeneral;
+use core::{
+    ops::ControlFlow,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use server::{
+    persistence::{audit_log::AuditLogFilter, DatabaseSettings},
+    settings::Protocol,
+    Event, Input, Server,
+};
 use std::{
     io,
     sync::{atomic::AtomicBool, mpsc, Arc},
     time::{Duration, Instant},
 };
 use tokio::sync::Notify;
-use tracing::{info, trace};
+use tracing::{error, info, trace};
 
 lazy_static::lazy_static! {
     pub static ref LOG: TuiLog<'static> = TuiLog::default();
 }
 const TPS: u64 = 30;
+/// Names that actions without an equivalent chat command are recorded under in
+/// the audit log
+const SHUTDOWN_COMMAND: &str = "shutdown";
+const BROADCAST_COMMAND: &str = "broadcast";
 
 fn main() -> io::Result<()> {
     #[cfg(feature = "tracy")]
@@ -51,6 +68,7 @@ fn main() -> io::Result<()> {
     let noninteractive = app.non_interactive;
     let no_auth = app.no_auth;
     let sql_log_mode = app.sql_log_mode;
+    let record = app.record.clone();
 
     // noninteractive implies basic
     let basic = basic || noninteractive;
@@ -199,17 +217,28 @@ fn main() -> io::Result<()> {
     )
     .expect("Failed to create server instance!");
 
+    if let Some(path) = record {
+        if let Err(e) = server.start_recording(&path) {
+            error!("Failed to start recording the session: {}", e);
+        }
+    }
+
     let registry = Arc::clone(server.metrics_registry());
     let chat = server.chat_cache().clone();
     let metrics_shutdown = Arc::new(Notify::new());
     let metrics_shutdown_clone = Arc::clone(&metrics_shutdown);
     let web_chat_secret = settings.web_chat_secret.clone();
+    let web_admin_secret = settings.web_admin_secret.clone();
+    // Requests from the web API, the server thread replies once it handled them
+    let (web_msg_s, mut web_msg_r) = tokio::sync::mpsc::channel(64);
 
     runtime.spawn(async move {
         web::run(
             registry,
             chat,
             web_chat_secret,
+            web_admin_secret,
+            web_msg_s,
             settings.web_address,
             metrics_shutdown_clone.notified(),
         )
@@ -246,6 +275,8 @@ fn main() -> io::Result<()> {
     let mut bench_exit_time = None;
 
     let mut tick_no = 0u64;
+    let mut audit_log_searches = Vec::new();
+    let mut character_jobs = Vec::new();
     loop {
         span!(guard, "work");
         if let Some(bench) = bench {
@@ -286,50 +317,77 @@ fn main() -> io::Result<()> {
 
         if let Some(tui) = tui.as_ref() {
             match tui.msg_r.try_recv() {
-                Ok(msg) => match msg {
-                    Message::Shutdown {
-                        command: Shutdown::Cancel,
-                    } => shutdown_coordinator.abort_shutdown(&mut server),
-                    Message::Shutdown {
-                        command: Shutdown::Graceful { seconds, reason },
-                    } => {
-                        shutdown_coordinator.initiate_shutdown(
-                            &mut server,
-                            Duration::from_secs(seconds),
-                            reason,
-                        );
+                Ok(msg) => match handle_message(msg, &mut server, &mut shutdown_coordinator) {
+                    ControlFlow::Break(()) => break,
+                    ControlFlow::Continue(MessageReturn::PendingAuditLog(search)) => {
+                        audit_log_searches.push((search, None));
                     },
-                    Message::Shutdown {
-                        command: Shutdown::Immediate,
-                    } => {
-                        info!("Closing the server");
-                        break;
-                    },
-                    Message::Shared(SharedCommand::Admin {
-                        command: Admin::Add { username, role },
-                    }) => {
-                        server.add_admin(&username, role);
-                    },
-                    Message::Shared(SharedCommand::Admin {
-                        command: Admin::Remove { username },
-                    }) => {
-                        server.remove_admin(&username);
-                    },
-                    Message::LoadArea { view_distance } => {
-                        #[cfg(feature = "worldgen")]
-                        server.create_centered_persister(view_distance);
-                    },
-                    Message::SqlLogMode { mode } => {
-                        server.set_sql_log_mode(mode);
-                    },
-                    Message::DisconnectAllClients => {
-                        server.disconnect_all_clients();
+                    ControlFlow::Continue(MessageReturn::PendingCharacterJob(job)) => {
+                        character_jobs.push((job, None));
                     },
+                    ControlFlow::Continue(ret) => log_message_return(ret),
                 },
                 Err(mpsc::TryRecvError::Empty) | Err(mpsc::TryRecvError::Disconnected) => {},
             }
         }
 
+        if let Ok((msg, reply)) = web_msg_r.try_recv() {
+            match handle_message(msg, &mut server, &mut shutdown_coordinator) {
+                ControlFlow::Break(()) => break,
+                ControlFlow::Continue(MessageReturn::PendingAuditLog(search)) => {
+                    audit_log_searches.push((search, Some(reply)));
+                },
+                ControlFlow::Continue(MessageReturn::PendingCharacterJob(job)) => {
+                    character_jobs.push((job, Some(reply)));
+                },
+                ControlFlow::Continue(ret) => {
+                    // The request may have timed out in the meantime
+                    let _ = reply.send(ret);
+                },
+            }
+        }
+
+        // Reply to the audit log searches which finished
+        audit_log_searches.retain_mut(|(search, reply)| {
+            let ret = match search.try_recv() {
+                Ok(Ok(entries)) => MessageReturn::AuditLog(entries),
+                Ok(Err(e)) => {
+                    MessageReturn::Failed(format!("Failed to search the audit log: {}", e))
+                },
+                Err(e) if e.is_empty() => return true,
+                Err(_) => MessageReturn::Failed("The audit log search was aborted".to_owned()),
+            };
+            match reply.take() {
+                // The request may have timed out in the meantime
+                Some(reply) => {
+                    let _ = reply.send(ret);
+                },
+                None => log_message_return(ret),
+            }
+            false
+        });
+
+        // Reply to the character jobs which finished
+        character_jobs.retain_mut(|(job, reply)| {
+            let ret = match job.try_recv() {
+                Ok(Ok(msg)) => {
+                    info!("{}", msg);
+                    MessageReturn::Done
+                },
+                Ok(Err(e)) => MessageReturn::Failed(e),
+                Err(e) if e.is_empty() => return true,
+                Err(_) => MessageReturn::Failed("The character job was aborted".to_owned()),
+            };
+            match reply.take() {
+                // The request may have timed out in the meantime
+                Some(reply) => {
+                    let _ = reply.send(ret);
+                },
+                None => log_message_return(ret),
+            }
+            false
+        });
+
         drop(guard);
         // Wait for the next tick.
         clock.tick();
@@ -340,3 +398,198 @@ fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+/// Logs the reply to a command entered in the TUI
+fn log_message_return(ret: MessageReturn) {
+    match ret {
+        MessageReturn::Done
+        | MessageReturn::PendingAuditLog(_)
+        | MessageReturn::PendingCharacterJob(_) => {},
+        MessageReturn::Failed(e) => error!("{}", e),
+        MessageReturn::Players(players) => {
+            info!("{} online players:", players.len());
+            for (alias, uuid) in players {
+                info!("[{}] {}", uuid, alias);
+            }
+        },
+        MessageReturn::AuditLog(entries) => {
+            for entry in entries.iter().rev() {
+                info!(
+                    error = ?entry.error,
+                    "{} [{}] {} /{} {}",
+                    entry.time,
+                    entry.actor_uuid,
+                    entry.actor_alias,
+                    entry.command,
+                    entry.args.join(" "),
+                );
+            }
+        },
+    }
+}
+
+/// Applies a command entered in the TUI or sent through the web API, breaks
+/// if the server should close immediately
+fn handle_message(
+    msg: Message,
+    server: &mut Server,
+    shutdown_coordinator: &mut ShutdownCoordinator,
+) -> ControlFlow<(), MessageReturn> {
+    let ret = match msg {
+        Message::Shutdown {
+            command: Shutdown::Cancel,
+        } => server
+            .audit_console_action(
+                SHUTDOWN_COMMAND,
+                None,
+                vec!["cancel".to_owned()],
+                |server| shutdown_coordinator.abort_shutdown(server),
+            )
+            .into(),
+        Message::Shutdown {
+            command: Shutdown::Graceful { seconds, reason },
+        } => {
+            let args = vec![seconds.to_string(), reason.clone()];
+            server
+                .audit_console_action(SHUTDOWN_COMMAND, None, args, |server| {
+                    shutdown_coordinator.initiate_shutdown(
+                        server,
+                        Duration::from_secs(seconds),
+                        reason,
+                    )
+                })
+                .into()
+        },
+        Message::Shutdown {
+            command: Shutdown::Immediate,
+        } => {
+            let args = vec!["now".to_owned()];
+            let _ = server.audit_console_action(SHUTDOWN_COMMAND, None, args, |_| Ok(()));
+            info!("Closing the server");
+            return ControlFlow::Break(());
+        },
+        Message::Shared(SharedCommand::Admin {
+            command: Admin::Add { username, role },
+        }) => server.add_admin(&username, role).into(),
+        Message::Shared(SharedCommand::Admin {
+            command: Admin::Remove { username },
+        }) => server.remove_admin(&username).into(),
+        Message::LoadArea { view_distance } => {
+            #[cfg(feature = "worldgen")]
+            server.create_centered_persister(view_distance);
+            MessageReturn::Done
+        },
+        Message::SqlLogMode { mode } => {
+            server.set_sql_log_mode(mode);
+            MessageReturn::Done
+        },
+        Message::DisconnectAllClients => {
+            server.disconnect_all_clients();
+            MessageReturn::Done
+        },
+        Message::Plugin { command } => handle_plugin_command(server, command).into(),
+        Message::Character { command } => handle_character_command(server, command),
+        Message::Backup => server
+            .backup()
+            .map(|name| info!("Started backup {}", name))
+            .into(),
+        Message::ListPlayers => MessageReturn::Players(server.online_players()),
+        Message::Kick { alias, reason } => server.kick_player(&alias, &reason).into(),
+        Message::Ban {
+            username,
+            reason,
+            duration,
+        } => server
+            .ban_player(&username, reason, duration.map(Duration::from_secs))
+            .into(),
+        Message::Unban { username } => server.unban_player(&username).into(),
+        Message::Whitelist {
+            command: Whitelist::Add { username },
+        } => server.add_to_whitelist(&username).into(),
+        Message::Whitelist {
+            command: Whitelist::Remove { username },
+        } => server.remove_from_whitelist(&username).into(),
+        Message::SendGlobalMsg { msg } => server
+            .audit_console_action(BROADCAST_COMMAND, None, vec![msg.clone()], |server| {
+                server.notify_players(ServerGeneral::server_msg(ChatType::Meta, msg));
+                Ok(())
+            })
+            .into(),
+        Message::Motd { description } => server.set_motd(description).into(),
+        Message::AuditLog {
+            player,
+            command,
+            limit,
+        } => {
+            let filter = AuditLogFilter {
+                player,
+                command,
+                limit: limit.min(AuditLogFilter::MAX_LIMIT),
+            };
+            MessageReturn::PendingAuditLog(server.search_audit_log(filter))
+        },
+    };
+    ControlFlow::Continue(ret)
+}
+
+fn handle_character_command(server: &mut Server, command: Character) -> MessageReturn {
+    let job = match command {
+        Character::List { username } => server.list_characters(&username),
+        Character::Export {
+            username,
+            character_id,
+        } => server.export_character(&username, CharacterId(character_id)),
+        Character::Import {
+            username,
+            file_name,
+        } => server.import_character(&username, &file_name),
+    };
+    match job {
+        Ok(job) => MessageReturn::PendingCharacterJob(job),
+        Err(e) => MessageReturn::Failed(e),
+    }
+}
+
+#[cfg(feature = "plugins")]
+fn handle_plugin_command(server: &mut Server, command: Plugin) -> Result<(), String> {
+    let cmd = common::cmd::ServerChatCommand::Plugin.keyword();
+    match command {
+        Plugin::List => {
+            let plugins = server.list_plugins();
+            info!("{} plugin(s) loaded", plugins.len());
+            for (name, modules) in plugins {
+                info!("{} ({} module(s))", name, modules);
+            }
+            Ok(())
+        },
+        Plugin::Load { file_name } => {
+            let args = vec!["load".to_owned(), file_name.clone()];
+            server.audit_console_action(cmd, Some(&file_name), args, |server| {
+                server
+                    .load_plugin(&file_name)
+                    .map(|name| info!("Loaded plugin {}", name))
+            })
+        },
+        Plugin::Unload { name } => {
+            let args = vec!["unload".to_owned(), name.clone()];
+            server.audit_console_action(cmd, Some(&name), args, |server| {
+                server
+                    .unload_plugin(&name)
+                    .map(|()| info!("Unloaded plugin {}", name))
+            })
+        },
+        Plugin::Reload { name } => {
+            let args = vec!["reload".to_owned(), name.clone()];
+            server.audit_console_action(cmd, Some(&name), args, |server| {
+                server
+                    .reload_plugin(&name)
+                    .map(|name| info!("Reloaded plugin {}", name))
+            })
+        },
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn handle_plugin_command(_server: &mut Server, _command: Plugin) -> Result<(), String> {
+    Err("Plugin commands are unsupported without plugins enabled".to_owned())
+}
@@ -0,0 +1,119 @@
+#![deny(unsafe_code)]
+#![deny(clippy::clone_on_ref_ptr)]
+
+//! Replays a session recorded with `veloren-server-cli --record` on a headless
+//! server, tick by tick. See `server::recording` for what is reproduced.
+
+use clap::Parser;
+use common::consts::MIN_RECOMMENDED_TOKIO_THREADS;
+use server::{
+    persistence::{DatabaseSettings, SqlLogMode},
+    recording::Replay,
+    EditableSettings, Server,
+};
+use std::{path::PathBuf, process::ExitCode, sync::Arc, time::Instant};
+use tracing::{error, info};
+
+#[derive(Parser)]
+#[command(
+    name = "Veloren server replay",
+    version = common::util::DISPLAY_VERSION_LONG.as_str(),
+    about = "Replays a recorded server session to reproduce crashes and desyncs.",
+    author = "The veloren devs <https://gitlab.com/veloren/veloren>",
+)]
+struct Args {
+    /// The recording to replay
+    recording: PathBuf,
+    #[arg(long, value_name = "DIR")]
+    /// Server data directory to replay in, should be a copy of the one the
+    /// session was recorded with. Defaults to the one of the server CLI.
+    data_dir: Option<PathBuf>,
+    #[arg(long, value_name = "TICK")]
+    /// Stops the replay after this tick (as numbered in the recording), to
+    /// bisect when something went wrong
+    until: Option<u64>,
+    #[arg(default_value_t, long, value_parser = clap::value_parser!(SqlLogMode))]
+    /// Enables SQL logging
+    sql_log_mode: SqlLogMode,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let _guards = common_frontend::init_stdout(None);
+
+    let data_dir = args.data_dir.unwrap_or_else(|| {
+        let mut path = common_base::userdata_dir_workspace!();
+        path.push(server::DEFAULT_DATA_DIR_NAME);
+        path
+    });
+    info!("Using data folder at {}", data_dir.display());
+
+    let runtime = Arc::new(
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .worker_threads((num_cpus::get() / 4).max(MIN_RECOMMENDED_TOKIO_THREADS))
+            .build()
+            .unwrap(),
+    );
+
+    let mut replay = match Replay::open(&args.recording, Arc::clone(&runtime)) {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("Failed to open the recording: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+    let settings = match replay.settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Failed to read the recorded settings: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let mut server = Server::new(
+        settings,
+        EditableSettings::load(&data_dir),
+        DatabaseSettings {
+            db_dir: data_dir.join("saves"),
+            sql_log_mode: args.sql_log_mode,
+        },
+        &data_dir,
+        &|_| {},
+        runtime,
+    )
+    .expect("Failed to create server instance!");
+    if let Err(e) = replay.start(&mut server) {
+        error!("Failed to start the replay: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    info!(
+        "Replaying the session recorded at {}",
+        replay.header().started
+    );
+    let start = Instant::now();
+    let mut ticks = 0u64;
+    loop {
+        let tick = match replay.tick(&mut server) {
+            Ok(Some((tick, _))) => tick,
+            Ok(None) => break,
+            Err(e) => {
+                error!(?ticks, "Replay failed: {}", e);
+                return ExitCode::FAILURE;
+            },
+        };
+        ticks += 1;
+        if args.until.map_or(false, |until| tick >= until) {
+            info!("Stopped at tick {}", tick);
+            break;
+        }
+    }
+    info!(
+        "Replayed {} ticks in {:.1}s",
+        ticks,
+        start.elapsed().as_secs_f32()
+    );
+
+    ExitCode::SUCCESS
+}
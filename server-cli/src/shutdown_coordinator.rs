@@ -52,7 +52,7 @@ impl ShutdownCoordinator {
         server: &mut Server,
         grace_period: Duration,
         message: String,
-    ) {
+    ) -> Result<(), String> {
         if self.shutdown_initiated_at.is_none() {
             self.shutdown_grace_period = grace_period;
             self.shutdown_initiated_at = Some(Instant::now());
@@ -60,19 +60,21 @@ impl ShutdownCoordinator {
 
             // Send an initial shutdown warning message to all connected clients
             self.send_shutdown_msg(server);
+            Ok(())
         } else {
-            error!("Shutdown already in progress")
+            Err("Shutdown already in progress".to_owned())
         }
     }
 
     /// Aborts an in-progress shutdown and sends a message to all connected
     /// clients.
-    pub fn abort_shutdown(&mut self, server: &mut Server) {
+    pub fn abort_shutdown(&mut self, server: &mut Server) -> Result<(), String> {
         if self.shutdown_initiated_at.is_some() {
             self.shutdown_initiated_at = None;
             ShutdownCoordinator::send_msg(server, "The shutdown has been aborted".to_owned());
+            Ok(())
         } else {
-            error!("There is no shutdown in progress");
+            Err("There is no shutdown in progress".to_owned())
         }
     }
 
@@ -120,7 +122,9 @@ impl ShutdownCoordinator {
             let grace_period =
                 Duration::from_secs(u64::from(settings.update_shutdown_grace_period_secs));
             let shutdown_message = settings.update_shutdown_message.to_owned();
-            self.initiate_shutdown(server, grace_period, shutdown_message);
+            if let Err(e) = self.initiate_shutdown(server, grace_period, shutdown_message) {
+                error!("{}", e);
+            }
 
             // Reset the SIGUSR1 signal indicator in case shutdown is aborted and we need to
             // trigger shutdown again
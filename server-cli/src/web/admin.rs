@@ -0,0 +1,260 @@
+//! Administration of the server over HTTP.
+//!
+//! Every request is turned into a [`Message`], the same way commands entered
+//! in the TUI are, and sent to the server thread which replies once it handled
+//! it during its next tick.
+
+use super::{validate_secret, SecretToken};
+use crate::cli::{Admin, Message, MessageReturn, SharedCommand, Shutdown, Whitelist};
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use common::{comp::AdminRole, uuid::Uuid};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use server::persistence::audit_log::AuditLogFilter;
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Clone)]
+struct MessageSender {
+    msg_s: mpsc::Sender<(Message, oneshot::Sender<MessageReturn>)>,
+}
+
+impl MessageSender {
+    async fn send(&self, msg: Message) -> Result<MessageReturn, StatusCode> {
+        let (reply_s, reply_r) = oneshot::channel();
+        self.msg_s
+            .send((msg, reply_s))
+            .await
+            .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+        reply_r.await.map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    /// Sends a message which doesn't return anything but success or failure
+    async fn run(&self, msg: Message) -> Result<Response, StatusCode> {
+        Ok(match self.send(msg).await? {
+            MessageReturn::Failed(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+            _ => StatusCode::NO_CONTENT.into_response(),
+        })
+    }
+}
+
+pub fn router(
+    msg_s: mpsc::Sender<(Message, oneshot::Sender<MessageReturn>)>,
+    secret_token: Option<String>,
+) -> Router {
+    let token = SecretToken { secret_token };
+    Router::new()
+        .route("/players", get(players))
+        .route("/players/:alias/kick", post(kick))
+        .route("/bans/:username", put(ban).delete(unban))
+        .route(
+            "/whitelist/:username",
+            put(add_to_whitelist).delete(remove_from_whitelist),
+        )
+        .route("/admins/:username", put(add_admin).delete(remove_admin))
+        .route("/broadcast", post(broadcast))
+        .route("/shutdown", post(shutdown).delete(cancel_shutdown))
+        .route("/motd", put(motd))
+        .route("/auditlog", get(audit_log))
+        .layer(axum::middleware::from_fn_with_state(token, validate_secret))
+        .with_state(MessageSender { msg_s })
+}
+
+#[derive(Serialize)]
+struct Player {
+    alias: String,
+    uuid: Uuid,
+}
+
+async fn players(State(sender): State<MessageSender>) -> Result<impl IntoResponse, StatusCode> {
+    match sender.send(Message::ListPlayers).await? {
+        MessageReturn::Players(players) => Ok(Json(
+            players
+                .into_iter()
+                .map(|(alias, uuid)| Player { alias, uuid })
+                .collect::<Vec<_>>(),
+        )),
+        _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[derive(Deserialize)]
+struct Reason {
+    #[serde(default)]
+    reason: String,
+}
+
+async fn kick(
+    State(sender): State<MessageSender>,
+    Path(alias): Path<String>,
+    Json(Reason { reason }): Json<Reason>,
+) -> Result<Response, StatusCode> {
+    sender.run(Message::Kick { alias, reason }).await
+}
+
+#[derive(Deserialize)]
+struct BanParams {
+    #[serde(default)]
+    reason: String,
+    /// Duration of the ban in seconds, permanent if not set
+    duration: Option<u64>,
+}
+
+async fn ban(
+    State(sender): State<MessageSender>,
+    Path(username): Path<String>,
+    Json(BanParams { reason, duration }): Json<BanParams>,
+) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Ban {
+            username,
+            reason,
+            duration,
+        })
+        .await
+}
+
+async fn unban(
+    State(sender): State<MessageSender>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    sender.run(Message::Unban { username }).await
+}
+
+async fn add_to_whitelist(
+    State(sender): State<MessageSender>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Whitelist {
+            command: Whitelist::Add { username },
+        })
+        .await
+}
+
+async fn remove_from_whitelist(
+    State(sender): State<MessageSender>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Whitelist {
+            command: Whitelist::Remove { username },
+        })
+        .await
+}
+
+#[derive(Deserialize)]
+struct RoleParams {
+    role: AdminRole,
+}
+
+async fn add_admin(
+    State(sender): State<MessageSender>,
+    Path(username): Path<String>,
+    Json(RoleParams { role }): Json<RoleParams>,
+) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Shared(SharedCommand::Admin {
+            command: Admin::Add { username, role },
+        }))
+        .await
+}
+
+async fn remove_admin(
+    State(sender): State<MessageSender>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Shared(SharedCommand::Admin {
+            command: Admin::Remove { username },
+        }))
+        .await
+}
+
+#[derive(Deserialize)]
+struct BroadcastParams {
+    msg: String,
+}
+
+async fn broadcast(
+    State(sender): State<MessageSender>,
+    Json(BroadcastParams { msg }): Json<BroadcastParams>,
+) -> Result<Response, StatusCode> {
+    sender.run(Message::SendGlobalMsg { msg }).await
+}
+
+#[derive(Deserialize)]
+struct ShutdownParams {
+    /// Number of seconds to wait before shutting down
+    seconds: u64,
+    #[serde(default = "default_shutdown_reason")]
+    reason: String,
+}
+
+fn default_shutdown_reason() -> String { "The server is shutting down".to_owned() }
+
+async fn shutdown(
+    State(sender): State<MessageSender>,
+    Json(ShutdownParams { seconds, reason }): Json<ShutdownParams>,
+) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Shutdown {
+            command: Shutdown::Graceful { seconds, reason },
+        })
+        .await
+}
+
+async fn cancel_shutdown(State(sender): State<MessageSender>) -> Result<Response, StatusCode> {
+    sender
+        .run(Message::Shutdown {
+            command: Shutdown::Cancel,
+        })
+        .await
+}
+
+#[derive(Deserialize)]
+struct MotdParams {
+    #[serde(default)]
+    description: String,
+}
+
+async fn motd(
+    State(sender): State<MessageSender>,
+    Json(MotdParams { description }): Json<MotdParams>,
+) -> Result<Response, StatusCode> {
+    sender.run(Message::Motd { description }).await
+}
+
+#[derive(Deserialize)]
+struct AuditLogParams {
+    player: Option<String>,
+    command: Option<String>,
+    #[serde(default = "default_audit_log_limit")]
+    limit: u32,
+}
+
+fn default_audit_log_limit() -> u32 { 100 }
+
+async fn audit_log(
+    State(sender): State<MessageSender>,
+    Query(AuditLogParams {
+        player,
+        command,
+        limit,
+    }): Query<AuditLogParams>,
+) -> Result<Response, StatusCode> {
+    let msg = Message::AuditLog {
+        player,
+        command,
+        limit: limit.min(AuditLogFilter::MAX_LIMIT),
+    };
+    Ok(match sender.send(msg).await? {
+        MessageReturn::AuditLog(entries) => Json(entries).into_response(),
+        MessageReturn::Failed(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    })
+}
@@ -1,3 +1,4 @@
+use super::{validate_secret, SecretToken};
 use axum::{
     extract::{ConnectInfo, Query, State},
     middleware::Next,
@@ -17,38 +18,11 @@ use std::{
 };
 use tokio::sync::Mutex;
 
-/// Keep Size small, so we dont have to Clone much for each request.
-#[derive(Clone)]
-struct ChatToken {
-    secret_token: Option<String>,
-}
-
 #[derive(Clone, Default)]
 struct IpAddresses {
     users: Arc<Mutex<HashSet<IpAddr>>>,
 }
 
-async fn validate_secret<B>(
-    State(token): State<ChatToken>,
-    req: Request<B>,
-    next: Next<B>,
-) -> Result<Response, StatusCode> {
-    // check if this endpoint is disabled
-    let secret_token = token.secret_token.ok_or(StatusCode::METHOD_NOT_ALLOWED)?;
-
-    pub const X_SECRET_TOKEN: &str = "X-Secret-Token";
-    let session_cookie = req
-        .headers()
-        .get(X_SECRET_TOKEN)
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if session_cookie.as_bytes() != secret_token.as_bytes() {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
-    Ok(next.run(req).await)
-}
-
 /// Logs each new IP address that accesses this API authenticated
 async fn log_users<B>(
     State(ip_addresses): State<IpAddresses>,
@@ -67,7 +41,7 @@ async fn log_users<B>(
 }
 
 pub fn router(cache: ChatCache, secret_token: Option<String>) -> Router {
-    let token = ChatToken { secret_token };
+    let token = SecretToken { secret_token };
     let ip_addrs = IpAddresses::default();
     Router::new()
         .route("/history", get(history))
@@ -1,16 +1,54 @@
-use axum::{extract::State, response::IntoResponse, routing::get, Router};
+use crate::cli::{Message, MessageReturn};
+use axum::{
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
 use core::{future::Future, ops::Deref};
-use hyper::{header, http, Body, StatusCode};
+use hyper::{header, http, Body, Request, StatusCode};
 use prometheus::{Registry, TextEncoder};
 use server::chat::ChatCache;
 use std::net::SocketAddr;
+use tokio::sync::{mpsc, oneshot};
 
+mod admin;
 mod chat;
 
+/// Keep Size small, so we dont have to Clone much for each request.
+#[derive(Clone)]
+struct SecretToken {
+    secret_token: Option<String>,
+}
+
+async fn validate_secret<B>(
+    State(token): State<SecretToken>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    // check if this endpoint is disabled
+    let secret_token = token.secret_token.ok_or(StatusCode::METHOD_NOT_ALLOWED)?;
+
+    pub const X_SECRET_TOKEN: &str = "X-Secret-Token";
+    let session_cookie = req
+        .headers()
+        .get(X_SECRET_TOKEN)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if session_cookie.as_bytes() != secret_token.as_bytes() {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(req).await)
+}
+
 pub async fn run<S, F, R>(
     registry: R,
     cache: ChatCache,
     chat_secret: Option<String>,
+    admin_secret: Option<String>,
+    msg_s: mpsc::Sender<(Message, oneshot::Sender<MessageReturn>)>,
     addr: S,
     shutdown: F,
 ) -> Result<(), hyper::Error>
@@ -25,6 +63,7 @@ where
 
     let app = Router::new()
         .nest("/chat/v1", chat::router(cache, chat_secret))
+        .nest("/admin/v1", admin::router(msg_s, admin_secret))
         .nest("/metrics", metrics)
         .route("/health", get(|| async {}));
 
@@ -38,7 +38,7 @@ use common::{
     vol::ReadVol,
 };
 use itertools::Itertools;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use specs::Entity as EcsEntity;
 use vek::*;
 
@@ -991,7 +991,7 @@ impl<'a> AgentData<'a> {
         #[cfg(not(feature = "be-dyn-lib"))] rng: &mut impl Rng,
     ) {
         #[cfg(feature = "be-dyn-lib")]
-        let rng = &mut thread_rng();
+        let rng = &mut rand::thread_rng();
 
         if read_data.is_riders.contains(*self.entity) {
             controller.push_event(ControlEvent::Unmount);
@@ -1935,12 +1935,18 @@ impl<'a> AgentData<'a> {
         other_scale: Option<&Scale>,
         read_data: &ReadData,
     ) -> bool {
-        self.can_sense_directly_near(other_pos)
+        self.can_sense_directly_near(other_pos, read_data)
             || self.can_see_entity(agent, controller, *other, other_pos, other_scale, read_data)
     }
 
-    pub fn can_sense_directly_near(&self, e_pos: &Pos) -> bool {
-        let chance = thread_rng().gen_bool(0.3);
+    pub fn can_sense_directly_near(&self, e_pos: &Pos, read_data: &ReadData) -> bool {
+        let chance = read_data
+            .rng_seed
+            .rng(
+                *read_data.time,
+                ("sense_near", *self.uid, e_pos.0.map(f32::to_bits)),
+            )
+            .gen_bool(0.3);
         e_pos.0.distance_squared(self.pos.0) < 5_f32.powi(2) && chance
     }
 
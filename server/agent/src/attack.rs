@@ -2813,7 +2813,7 @@ impl<'a> AgentData<'a> {
                             select_pos: None,
                         });
                         if matches!(self.char_state, CharacterState::Blink(_)) {
-                            *num_fireballs = rand::random::<u8>() % 4;
+                            *num_fireballs = rng.gen::<u8>() % 4;
                         }
                     } else if matches!(self.char_state, CharacterState::Wielding(_)) {
                         *num_fireballs -= 1;
@@ -2853,7 +2853,7 @@ impl<'a> AgentData<'a> {
                     select_pos: None,
                 });
                 if matches!(self.char_state, CharacterState::Blink(_)) {
-                    *num_fireballs = rand::random::<u8>() % 4;
+                    *num_fireballs = rng.gen::<u8>() % 4;
                 }
             } else if matches!(self.char_state, CharacterState::Wielding(_)) {
                 *num_fireballs -= 1;
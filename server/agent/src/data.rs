@@ -20,7 +20,7 @@ use common::{
     link::Is,
     mounting::{Mount, Rider, VolumeRider},
     path::TraversalConfig,
-    resources::{DeltaTime, Time, TimeOfDay},
+    resources::{DeltaTime, RngSeed, Time, TimeOfDay},
     rtsim::{Actor, RtSimEntity},
     states::utils::{ForcedMovement, StageSection},
     terrain::TerrainGrid,
@@ -326,6 +326,7 @@ pub struct ReadData<'a> {
     pub id_maps: Read<'a, IdMaps>,
     pub dt: Read<'a, DeltaTime>,
     pub time: Read<'a, Time>,
+    pub rng_seed: Read<'a, RngSeed>,
     pub cached_spatial_grid: Read<'a, common::CachedSpatialGrid>,
     pub group_manager: Read<'a, group::GroupManager>,
     pub energies: ReadStorage<'a, Energy>,
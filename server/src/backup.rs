@@ -0,0 +1,306 @@
+//! Online backups of the server's persistent state.
+//!
+//! A backup captures the database, the persisted terrain and the rtsim data at
+//! the same moment, so they stay consistent with each other when restored.
+//! Only the snapshots are taken on the main thread, which links the persisted
+//! terrain files into the backup; writing everything else to disk happens in a
+//! slow job while the server keeps running. Backups are written to a partial
+//! directory that is renamed once complete.
+
+#[cfg(feature = "persistent_world")]
+use crate::terrain_persistence::TerrainPersistence;
+use crate::{
+    persistence::{DatabaseSettings, DatabaseSnapshot},
+    rtsim::RtSim,
+    settings::BackupSettings,
+};
+use common::slowjob::SlowJobPool;
+use specs::World;
+use std::{
+    fs,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Format of the names of backup directories, used to tell them apart from
+/// other files when pruning old backups.
+const BACKUP_NAME_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// Appended to the name of a backup's directory while it's being written.
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// A resource keeping track of backups.
+pub struct Backups {
+    last_backup: Instant,
+    /// Set while a backup job is queued or running.
+    in_progress: Arc<AtomicBool>,
+}
+
+impl Default for Backups {
+    fn default() -> Self { Self::new() }
+}
+
+impl Backups {
+    pub fn new() -> Self {
+        Self {
+            last_backup: Instant::now(),
+            in_progress: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Whether a previous backup is still being written.
+    pub fn in_progress(&self) -> bool { self.in_progress.load(Ordering::Acquire) }
+
+    /// Whether a scheduled backup should be taken now.
+    pub fn is_due(&self, settings: &BackupSettings) -> bool {
+        settings.interval_minutes.map_or(false, |minutes| {
+            self.last_backup.elapsed() >= Duration::from_secs(u64::from(minutes) * 60)
+        }) && !self.in_progress()
+    }
+
+    /// Snapshots the database, persisted terrain and rtsim data and starts
+    /// writing them to a new backup directory in the background, returning the
+    /// path of that directory.
+    pub fn take(
+        &mut self,
+        ecs: &World,
+        data_dir: &Path,
+        settings: &BackupSettings,
+        database_settings: &DatabaseSettings,
+    ) -> Result<PathBuf, String> {
+        if self.in_progress() {
+            return Err("A backup is already in progress".to_owned());
+        }
+        self.last_backup = Instant::now();
+
+        let backup_dir = data_dir.join(&settings.path);
+        let name = chrono::Utc::now().format(BACKUP_NAME_FORMAT).to_string();
+        let path = backup_dir.join(&name);
+        if path.exists() {
+            return Err(format!("Backup {} already exists", path.display()));
+        }
+        // Written to a partial directory which is only renamed once complete, so
+        // that a failed backup can't be mistaken for (or pruned instead of) a
+        // complete one.
+        let partial_path = backup_dir.join(format!("{}{}", name, PARTIAL_SUFFIX));
+        fs::create_dir_all(&partial_path)
+            .map_err(|e| format!("Failed to create {}: {}", partial_path.display(), e))?;
+
+        let failed = |e: String| {
+            remove_partial(&partial_path);
+            e
+        };
+
+        let database = DatabaseSnapshot::new(database_settings)
+            .map_err(|e| failed(format!("Failed to snapshot the database: {}", e)))?;
+
+        #[cfg(feature = "persistent_world")]
+        let terrain = ecs
+            .try_fetch::<TerrainPersistence>()
+            .map(|terrain| terrain.snapshot(&partial_path.join("terrain")))
+            .transpose()
+            .map_err(|e| failed(format!("Failed to snapshot the terrain: {}", e)))?;
+
+        let rtsim_data = ecs
+            .try_fetch::<RtSim>()
+            .map(|rtsim| rtsim.state().data().clone());
+
+        // The database connection isn't `Sync`, which slow jobs require.
+        let database = Mutex::new(database);
+        let keep = settings.keep as usize;
+        let job_path = path.clone();
+        let in_progress = InProgress::start(&self.in_progress);
+        ecs.read_resource::<SlowJobPool>().spawn("BACKUP", move || {
+            // Reset once the job ends, even if it panics
+            let _in_progress = in_progress;
+            let result = (|| {
+                let database = database.into_inner().unwrap_or_else(|e| e.into_inner());
+                database
+                    .write_to(&partial_path.join("db.sqlite"))
+                    .map_err(|e| format!("Failed to write database backup: {}", e))?;
+
+                #[cfg(feature = "persistent_world")]
+                if let Some(terrain) = terrain {
+                    terrain
+                        .finish()
+                        .map_err(|e| format!("Failed to write terrain backup: {}", e))?;
+                }
+
+                if let Some(data) = rtsim_data {
+                    let rtsim_dir = partial_path.join("rtsim");
+                    fs::create_dir_all(&rtsim_dir)
+                        .and_then(|_| fs::File::create(rtsim_dir.join("data.dat")))
+                        .map_err(|e| e.to_string())
+                        .and_then(|file| {
+                            data.write_to(BufWriter::new(file))
+                                .map_err(|e| e.to_string())
+                        })
+                        .map_err(|e| format!("Failed to write rtsim backup: {}", e))?;
+                }
+
+                complete(&partial_path, &job_path, keep)
+                    .map_err(|e| format!("Failed to move the backup into place: {}", e))
+            })();
+
+            match result {
+                Ok(()) => info!(path = ?job_path, "Backup finished"),
+                Err(e) => {
+                    error!(path = ?job_path, "{}", e);
+                    remove_partial(&partial_path);
+                },
+            }
+        });
+
+        Ok(path)
+    }
+}
+
+/// Marks a backup as in progress until dropped.
+struct InProgress(Arc<AtomicBool>);
+
+impl InProgress {
+    fn start(in_progress: &Arc<AtomicBool>) -> Self {
+        in_progress.store(true, Ordering::Release);
+        Self(Arc::clone(in_progress))
+    }
+}
+
+impl Drop for InProgress {
+    fn drop(&mut self) { self.0.store(false, Ordering::Release); }
+}
+
+/// Moves a backup which was completely written to `partial_path` into place,
+/// then prunes old backups next to it.
+fn complete(partial_path: &Path, path: &Path, keep: usize) -> io::Result<()> {
+    fs::rename(partial_path, path)?;
+    if keep > 0
+        && let Some(backup_dir) = path.parent()
+    {
+        prune(backup_dir, keep);
+    }
+    Ok(())
+}
+
+fn remove_partial(path: &Path) {
+    if let Err(e) = fs::remove_dir_all(path) {
+        warn!(?e, "Failed to remove partial backup {}", path.display());
+    }
+}
+
+/// Deletes the oldest backups in `backup_dir`, so that only `keep` remain, and
+/// partial backups left behind by a crash.
+///
+/// Must only be called while no other backup is being written.
+fn prune(backup_dir: &Path, keep: usize) {
+    let names = match fs::read_dir(backup_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            warn!(?e, "Failed to list backups to prune");
+            return;
+        },
+    };
+    let is_backup =
+        |name: &str| chrono::NaiveDateTime::parse_from_str(name, BACKUP_NAME_FORMAT).is_ok();
+
+    for name in names
+        .iter()
+        .filter(|name| name.strip_suffix(PARTIAL_SUFFIX).map_or(false, is_backup))
+    {
+        remove_partial(&backup_dir.join(name));
+    }
+
+    let mut backups = names
+        .into_iter()
+        .filter(|name| is_backup(name))
+        .collect::<Vec<_>>();
+    // The names sort chronologically
+    backups.sort_unstable();
+    let to_remove = backups.len().saturating_sub(keep);
+    for name in backups.into_iter().take(to_remove) {
+        match fs::remove_dir_all(backup_dir.join(&name)) {
+            Ok(()) => info!("Removed old backup {}", name),
+            Err(e) => warn!(?e, "Failed to remove old backup {}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("veloren-backups-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn prune_removes_oldest_and_partial_backups() {
+        let dir = backup_dir();
+        for name in [
+            "20240103-000000",
+            "20240101-000000",
+            "20240104-000000",
+            "20240102-000000",
+            "20240105-000000.partial",
+            "unrelated",
+        ] {
+            fs::create_dir(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("20240101-000001"), b"not a backup").unwrap();
+
+        prune(&dir, 2);
+        let remaining = names(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(remaining, [
+            "20240101-000001",
+            "20240103-000000",
+            "20240104-000000",
+            "unrelated"
+        ]);
+    }
+
+    #[test]
+    fn completed_backups_are_rotated() {
+        let dir = backup_dir();
+        let mut completed = Vec::new();
+        for day in 1..=5 {
+            let name = format!("202401{:02}-000000", day);
+            let partial_path = dir.join(format!("{}{}", name, PARTIAL_SUFFIX));
+            fs::create_dir(&partial_path).unwrap();
+            fs::write(partial_path.join("db.sqlite"), b"").unwrap();
+            complete(&partial_path, &dir.join(&name), 3).unwrap();
+            completed.push(names(&dir));
+        }
+        let last = dir.join("20240105-000000");
+        let contents = names(&last);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(completed[0], ["20240101-000000"]);
+        assert_eq!(completed[4], [
+            "20240103-000000",
+            "20240104-000000",
+            "20240105-000000"
+        ]);
+        assert_eq!(contents, ["db.sqlite"]);
+    }
+}
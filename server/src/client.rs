@@ -1,8 +1,12 @@
-use common_net::msg::{ClientType, ServerGeneral, ServerMsg};
-use network::{Message, Participant, Stream, StreamError, StreamParams};
+use crate::recording::{ClientRecorder, ReplayInbox};
+use common_net::msg::{ClientType, ResumeToken, ServerGeneral, ServerMsg};
+use network::{ConnectAddr, Message, Participant, Stream, StreamError, StreamParams};
 use serde::{de::DeserializeOwned, Serialize};
 use specs::Component;
-use std::sync::atomic::AtomicBool;
+use std::{
+    net::IpAddr,
+    sync::atomic::{AtomicBool, AtomicU64},
+};
 
 /// Client handles ALL network related information of everything that connects
 /// to the server Client DOES NOT handle game states
@@ -15,6 +19,20 @@ pub struct Client {
     pub participant: Option<Participant>,
     pub last_ping: f64,
     pub login_msg_sent: AtomicBool,
+    /// Sequence number of the last `ControllerInputs` that were simulated,
+    /// sent back to the client so it can correct its prediction.
+    pub last_input_seq: AtomicU64,
+    /// Sequence number of the last `ControllerInputs` that were received, they
+    /// are simulated in the next tick.
+    pub received_input_seq: AtomicU64,
+    /// Sent to the client when it registers, see
+    /// [`crate::presence::ResumableLogins`].
+    pub resume_token: ResumeToken,
+    /// Set while the session is recorded.
+    pub(crate) recorder: Option<ClientRecorder>,
+    /// Set for clients of a replayed session, whose messages are received from
+    /// the recording instead of the streams.
+    pub(crate) replay_inbox: Option<ReplayInbox>,
 
     //TODO: Consider splitting each of these out into their own components so all the message
     //processing systems can run in parallel with each other (though it may turn out not to
@@ -25,6 +43,7 @@ pub struct Client {
     character_screen_stream: Stream,
     in_game_stream: Stream,
     terrain_stream: Stream,
+    physics_stream: Stream,
 
     general_stream_params: StreamParams,
     ping_stream_params: StreamParams,
@@ -32,6 +51,7 @@ pub struct Client {
     character_screen_stream_params: StreamParams,
     in_game_stream_params: StreamParams,
     terrain_stream_params: StreamParams,
+    physics_stream_params: StreamParams,
 }
 
 pub struct PreparedMsg {
@@ -54,6 +74,7 @@ impl Client {
         character_screen_stream: Stream,
         in_game_stream: Stream,
         terrain_stream: Stream,
+        physics_stream: Stream,
     ) -> Self {
         let general_stream_params = general_stream.params();
         let ping_stream_params = ping_stream.params();
@@ -61,23 +82,41 @@ impl Client {
         let character_screen_stream_params = character_screen_stream.params();
         let in_game_stream_params = in_game_stream.params();
         let terrain_stream_params = terrain_stream.params();
+        let physics_stream_params = physics_stream.params();
         Client {
             client_type,
             participant: Some(participant),
             last_ping,
             login_msg_sent: AtomicBool::new(false),
+            last_input_seq: AtomicU64::new(0),
+            received_input_seq: AtomicU64::new(0),
+            resume_token: ResumeToken(rand::random()),
+            recorder: None,
+            replay_inbox: None,
             general_stream,
             ping_stream,
             register_stream,
             character_screen_stream,
             in_game_stream,
             terrain_stream,
+            physics_stream,
             general_stream_params,
             ping_stream_params,
             register_stream_params,
             character_screen_stream_params,
             in_game_stream_params,
             terrain_stream_params,
+            physics_stream_params,
+        }
+    }
+
+    /// The IP address the client connected from, if it connected over the
+    /// network (and hasn't disconnected yet).
+    pub(crate) fn remote_ip(&self) -> Option<IpAddr> {
+        match self.participant.as_ref()?.remote_address() {
+            ConnectAddr::Tcp(addr) | ConnectAddr::Udp(addr) => Some(addr.ip()),
+            ConnectAddr::Quic(addr, ..) => Some(addr.ip()),
+            ConnectAddr::Mpsc(_) => None,
         }
     }
 
@@ -120,7 +159,7 @@ impl Client {
                     //Ingame related, terrain
                     ServerGeneral::TerrainChunkUpdate { .. }
                     | ServerGeneral::LodZoneUpdate { .. }
-                    | ServerGeneral::TerrainBlockUpdates(_) => {
+                    | ServerGeneral::TerrainChunkDiff { .. } => {
                         self.terrain_stream.lock().unwrap().send(g)
                     },
                     // Always possible
@@ -152,6 +191,7 @@ impl Client {
             3 => self.general_stream.send_raw(&msg.message),
             4 => self.ping_stream.send_raw(&msg.message),
             5 => self.terrain_stream.send_raw(&msg.message),
+            6 => self.physics_stream.send_raw(&msg.message),
             _ => unreachable!("invalid stream id"),
         }
     }
@@ -185,6 +225,9 @@ impl Client {
                     | ServerGeneral::Outcomes(_)
                     | ServerGeneral::Knockback(_)
                     | ServerGeneral::SiteEconomy(_)
+                    | ServerGeneral::QuestOffer(_)
+                    | ServerGeneral::QuestUpdate(_)
+                    | ServerGeneral::SiteFactions(_)
                     | ServerGeneral::UpdatePendingTrade(_, _, _)
                     | ServerGeneral::FinishedTrade(_)
                     | ServerGeneral::MapMarker(_)
@@ -195,9 +238,13 @@ impl Client {
                     //In-game related, terrain
                     ServerGeneral::TerrainChunkUpdate { .. }
                     | ServerGeneral::LodZoneUpdate { .. }
-                    | ServerGeneral::TerrainBlockUpdates(_) => {
+                    | ServerGeneral::TerrainChunkDiff { .. } => {
                         PreparedMsg::new(5, &g, &self.terrain_stream_params)
                     },
+                    //In-game related, physics
+                    ServerGeneral::PhysicsSync(..) => {
+                        PreparedMsg::new(6, &g, &self.physics_stream_params)
+                    },
                     // Always possible
                     ServerGeneral::PlayerListUpdate(_)
                     | ServerGeneral::ChatMsg(_)
@@ -235,20 +282,32 @@ impl Client {
         PreparedMsg::new(5, &terrain_chunk_update, params)
     }
 
-    pub(crate) fn recv<M: DeserializeOwned>(
+    pub(crate) fn recv<M: DeserializeOwned + Serialize>(
         &mut self,
         stream_id: u8,
     ) -> Result<Option<M>, StreamError> {
+        if let Some(inbox) = &mut self.replay_inbox {
+            return inbox.recv(stream_id);
+        }
         // TODO: are two systems using the same stream?? why is there contention here?
-        match stream_id {
+        let msg = match stream_id {
             0 => self.register_stream.try_recv(),
             1 => self.character_screen_stream.try_recv(),
             2 => self.in_game_stream.try_recv(),
             3 => self.general_stream.try_recv(),
             4 => self.ping_stream.try_recv(),
             5 => self.terrain_stream.try_recv(),
+            6 => self.physics_stream.try_recv(),
             _ => unreachable!("invalid stream id"),
+        };
+        if let Some(recorder) = &self.recorder {
+            // Login tokens must not end up in recordings, see
+            // `ClientRecorder::record_register`
+            if stream_id != 0 || msg.is_err() {
+                recorder.record_msg(stream_id, &msg);
+            }
         }
+        msg
     }
 }
 
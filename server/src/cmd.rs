@@ -5,8 +5,14 @@ use crate::{
     client::Client,
     location::Locations,
     login_provider::LoginProvider,
+    movement_validation::MovementReport,
+    persistence::{
+        audit_log::{AuditLog, AuditLogEntry, AuditLogFilter},
+        character_archive::CharacterArchives,
+    },
     settings::{
-        Ban, BanAction, BanInfo, EditableSetting, SettingError, WhitelistInfo, WhitelistRecord,
+        Ban, BanAction, BanInfo, BanRecord, EditableSetting, SettingError, WhitelistInfo,
+        WhitelistRecord,
     },
     sys::terrain::NpcData,
     weather::WeatherSim,
@@ -20,8 +26,9 @@ use chrono::{NaiveTime, Timelike, Utc};
 use common::{
     assets,
     calendar::Calendar,
+    character::CharacterId,
     cmd::{
-        AreaKind, KitSpec, ServerChatCommand, BUFF_PACK, BUFF_PARSER, ITEM_SPECS,
+        AreaKind, ArgumentSpec, KitSpec, ServerChatCommand, BUFF_PACK, BUFF_PARSER, ITEM_SPECS,
         KIT_MANIFEST_PATH, PRESET_MANIFEST_PATH,
     },
     comp::{
@@ -128,6 +135,10 @@ fn do_command(
         ServerChatCommand::Airship => handle_spawn_airship,
         ServerChatCommand::Alias => handle_alias,
         ServerChatCommand::Ban => handle_ban,
+        ServerChatCommand::BanInfo => handle_ban_info,
+        ServerChatCommand::BanIp => handle_ban_ip,
+        ServerChatCommand::BanNote => handle_ban_note,
+        ServerChatCommand::BanTemplate => handle_ban_template,
         ServerChatCommand::BattleMode => handle_battlemode,
         ServerChatCommand::BattleModeForce => handle_battlemode_force,
         ServerChatCommand::Body => handle_body,
@@ -136,7 +147,11 @@ fn do_command(
         ServerChatCommand::AreaAdd => handle_area_add,
         ServerChatCommand::AreaList => handle_area_list,
         ServerChatCommand::AreaRemove => handle_area_remove,
+        ServerChatCommand::AuditLog => handle_audit_log,
+        ServerChatCommand::Backup => handle_backup,
         ServerChatCommand::Campfire => handle_spawn_campfire,
+        ServerChatCommand::CharacterExport => handle_character_export,
+        ServerChatCommand::CharacterImport => handle_character_import,
         ServerChatCommand::DebugColumn => handle_debug_column,
         ServerChatCommand::DebugWays => handle_debug_ways,
         ServerChatCommand::DisconnectAllPlayers => handle_disconnect_all_players,
@@ -166,9 +181,11 @@ fn do_command(
         ServerChatCommand::MakeNpc => handle_make_npc,
         ServerChatCommand::MakeSprite => handle_make_sprite,
         ServerChatCommand::Motd => handle_motd,
+        ServerChatCommand::MovementReport => handle_movement_report,
         ServerChatCommand::Object => handle_object,
         ServerChatCommand::PermitBuild => handle_permit_build,
         ServerChatCommand::Players => handle_players,
+        ServerChatCommand::Plugin => handle_plugin,
         ServerChatCommand::Portal => handle_spawn_portal,
         ServerChatCommand::Region => handle_region,
         ServerChatCommand::ReloadChunks => handle_reload_chunks,
@@ -195,6 +212,7 @@ fn do_command(
         ServerChatCommand::RtsimPurge => handle_rtsim_purge,
         ServerChatCommand::RtsimChunk => handle_rtsim_chunk,
         ServerChatCommand::Unban => handle_unban,
+        ServerChatCommand::UnbanIp => handle_unban_ip,
         ServerChatCommand::Version => handle_version,
         ServerChatCommand::Waypoint => handle_waypoint,
         ServerChatCommand::Wiring => handle_spawn_wiring,
@@ -210,7 +228,139 @@ fn do_command(
         ServerChatCommand::RepairEquipment => handle_repair_equipment,
     };
 
-    handler(server, client, target, args, cmd)
+    let audit_entry = is_audited(cmd)
+        .then(|| audit_entry(server, client, target, &args, cmd))
+        .flatten();
+    let result = handler(server, client, target, args, cmd);
+    if let Some(mut entry) = audit_entry {
+        entry.error = result.as_ref().err().map(|err| match err {
+            Content::Plain(msg) => msg.clone(),
+            Content::Localized { key, .. } => key.clone(),
+        });
+        server.state.ecs().read_resource::<AuditLog>().record(entry);
+    }
+    result
+}
+
+/// Whether running the command is worth recording in the audit log, because
+/// it is privileged or changes the world.
+fn is_audited(cmd: &ServerChatCommand) -> bool {
+    matches!(
+        cmd,
+        ServerChatCommand::Adminify
+            | ServerChatCommand::Airship
+            | ServerChatCommand::Alias
+            | ServerChatCommand::AuditLog
+            | ServerChatCommand::Backup
+            | ServerChatCommand::Buff
+            | ServerChatCommand::Ban
+            | ServerChatCommand::BanInfo
+            | ServerChatCommand::BanIp
+            | ServerChatCommand::BanNote
+            | ServerChatCommand::BanTemplate
+            | ServerChatCommand::Body
+            | ServerChatCommand::BattleModeForce
+            | ServerChatCommand::Build
+            | ServerChatCommand::AreaAdd
+            | ServerChatCommand::AreaList
+            | ServerChatCommand::AreaRemove
+            | ServerChatCommand::Campfire
+            | ServerChatCommand::CharacterExport
+            | ServerChatCommand::CharacterImport
+            | ServerChatCommand::DebugColumn
+            | ServerChatCommand::DebugWays
+            | ServerChatCommand::DisconnectAllPlayers
+            | ServerChatCommand::DropAll
+            | ServerChatCommand::Dummy
+            | ServerChatCommand::Explosion
+            | ServerChatCommand::GiveItem
+            | ServerChatCommand::Goto
+            | ServerChatCommand::Health
+            | ServerChatCommand::Respawn
+            | ServerChatCommand::Jump
+            | ServerChatCommand::Kick
+            | ServerChatCommand::KillNpcs
+            | ServerChatCommand::Kit
+            | ServerChatCommand::Lantern
+            | ServerChatCommand::Light
+            | ServerChatCommand::MakeBlock
+            | ServerChatCommand::MakeNpc
+            | ServerChatCommand::MakeSprite
+            | ServerChatCommand::MovementReport
+            | ServerChatCommand::Object
+            | ServerChatCommand::PermitBuild
+            | ServerChatCommand::Plugin
+            | ServerChatCommand::Portal
+            | ServerChatCommand::ReloadChunks
+            | ServerChatCommand::RemoveLights
+            | ServerChatCommand::RevokeBuild
+            | ServerChatCommand::RevokeBuildAll
+            | ServerChatCommand::Safezone
+            | ServerChatCommand::ServerPhysics
+            | ServerChatCommand::SetMotd
+            | ServerChatCommand::Ship
+            | ServerChatCommand::Site
+            | ServerChatCommand::SkillPoint
+            | ServerChatCommand::SkillPreset
+            | ServerChatCommand::Spawn
+            | ServerChatCommand::Sudo
+            | ServerChatCommand::Time
+            | ServerChatCommand::TimeScale
+            | ServerChatCommand::Tp
+            | ServerChatCommand::RtsimTp
+            | ServerChatCommand::RtsimInfo
+            | ServerChatCommand::RtsimNpc
+            | ServerChatCommand::RtsimPurge
+            | ServerChatCommand::RtsimChunk
+            | ServerChatCommand::Unban
+            | ServerChatCommand::UnbanIp
+            | ServerChatCommand::Waypoint
+            | ServerChatCommand::Wiring
+            | ServerChatCommand::Whitelist
+            | ServerChatCommand::MakeVolume
+            | ServerChatCommand::CreateLocation
+            | ServerChatCommand::DeleteLocation
+            | ServerChatCommand::WeatherZone
+            | ServerChatCommand::Lightning
+            | ServerChatCommand::Scale
+            | ServerChatCommand::RepairEquipment
+    )
+}
+
+/// Describe a command for the audit log, its error is filled in once it ran
+fn audit_entry(
+    server: &Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    args: &[String],
+    cmd: &ServerChatCommand,
+) -> Option<AuditLogEntry> {
+    let players = server.state.ecs().read_storage::<comp::Player>();
+    let actor = players.get(client)?;
+    // Prefer the player named in the arguments, so that e.g. bans are attributed
+    // to the banned player rather than to the one a moderator is sudoing as
+    let target = cmd
+        .data()
+        .args
+        .iter()
+        .zip(args)
+        .find(|(spec, _)| matches!(spec, ArgumentSpec::PlayerName(_)))
+        .map(|(_, arg)| arg.clone())
+        .or_else(|| {
+            (client != target)
+                .then(|| players.get(target).map(|player| player.alias.clone()))
+                .flatten()
+        });
+
+    Some(AuditLogEntry {
+        time: Utc::now(),
+        actor_uuid: actor.uuid(),
+        actor_alias: actor.alias.clone(),
+        target,
+        command: cmd.keyword().to_owned(),
+        args: args.to_vec(),
+        error: None,
+    })
 }
 
 // Fallibly get position of entity with the given descriptor (used for error
@@ -2137,6 +2287,50 @@ fn handle_players(
     Ok(())
 }
 
+#[cfg(not(feature = "plugins"))]
+fn handle_plugin(
+    _server: &mut Server,
+    _client: EcsEntity,
+    _target: EcsEntity,
+    _args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    Err("Unsupported without plugins enabled".into())
+}
+
+#[cfg(feature = "plugins")]
+fn handle_plugin(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let msg = match parse_cmd_args!(args, String, String) {
+        (Some(subcommand), None) if subcommand == "list" => server
+            .list_plugins()
+            .into_iter()
+            .fold("Loaded plugins:".to_owned(), |s, (name, modules)| {
+                format!("{}\n{} ({} module(s))", s, name, modules)
+            }),
+        (Some(subcommand), Some(name)) => match subcommand.as_str() {
+            "load" => format!("Loaded plugin {}", server.load_plugin(&name)?),
+            "unload" => {
+                server.unload_plugin(&name)?;
+                format!("Unloaded plugin {}", name)
+            },
+            "reload" => format!("Reloaded plugin {}", server.reload_plugin(&name)?),
+            _ => return Err(Content::Plain(action.help_string())),
+        },
+        _ => return Err(Content::Plain(action.help_string())),
+    };
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+    );
+    Ok(())
+}
+
 fn handle_spawn_portal(
     server: &mut Server,
     client: EcsEntity,
@@ -2511,7 +2705,12 @@ fn handle_kit(
     }
 }
 
-fn push_kit<I>(kit: I, count: usize, server: &mut Server, target: EcsEntity) -> CmdResult<()>
+pub(crate) fn push_kit<I>(
+    kit: I,
+    count: usize,
+    server: &mut Server,
+    target: EcsEntity,
+) -> CmdResult<()>
 where
     I: Iterator<Item = (KitSpec, u32)>,
 {
@@ -3581,6 +3780,11 @@ fn handle_reload_chunks(
         .try_fetch_mut::<crate::terrain_persistence::TerrainPersistence>()
         .map(|mut terrain_persistence| terrain_persistence.unload_all());
     server.state.clear_terrain();
+    server
+        .state
+        .ecs()
+        .write_resource::<crate::terrain_versions::TerrainVersions>()
+        .clear();
 
     Ok(())
 }
@@ -3820,68 +4024,334 @@ fn handle_ban(
     if let (Some(username), overwrite, parse_duration, reason_opt) =
         parse_cmd_args!(args, String, bool, HumanDuration, String)
     {
-        let reason = reason_opt.unwrap_or_default();
-        let overwrite = overwrite.unwrap_or(false);
+        ban_player(
+            server,
+            client,
+            username,
+            overwrite.unwrap_or(false),
+            parse_duration,
+            reason_opt.unwrap_or_default(),
+            false,
+        )
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
+
+fn handle_ban_ip(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), overwrite, parse_duration, reason_opt) =
+        parse_cmd_args!(args, String, bool, HumanDuration, String)
+    {
+        ban_player(
+            server,
+            client,
+            username,
+            overwrite.unwrap_or(false),
+            parse_duration,
+            reason_opt.unwrap_or_default(),
+            true,
+        )
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
 
+fn handle_ban_template(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), Some(template_name), overwrite) =
+        parse_cmd_args!(args, String, String, bool)
+    {
+        let template = {
+            let settings = server.settings();
+            let templates = &settings.moderation.ban_templates;
+            templates.get(&template_name).cloned().ok_or_else(|| {
+                let mut names = templates.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                format!(
+                    "No ban template named {}. Available templates: {}",
+                    template_name,
+                    names.join(", ")
+                )
+            })?
+        };
+        let duration = template
+            .duration
+            .as_deref()
+            .map(HumanDuration::from_str)
+            .transpose()
+            .map_err(|err| {
+                format!(
+                    "Ban template {} has an invalid duration: {}",
+                    template_name, err
+                )
+            })?;
+        ban_player(
+            server,
+            client,
+            username,
+            overwrite.unwrap_or(false),
+            duration,
+            template.reason,
+            false,
+        )
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
+
+/// Bans `username` (and, if `ban_ip` is set, the address they are currently
+/// connected from), then kicks them if they are online.
+fn ban_player(
+    server: &mut Server,
+    client: EcsEntity,
+    username: String,
+    overwrite: bool,
+    parse_duration: Option<HumanDuration>,
+    reason: String,
+    ban_ip: bool,
+) -> CmdResult<()> {
+    let player_uuid = find_username(server, &username)?;
+
+    let client_uuid = uuid(server, client, "client")?;
+    let client_username = uuid_to_username(server, client, client_uuid)?;
+    let client_role = real_role(server, client_uuid, "client")?;
+
+    // Look up the address before doing anything else, so we don't ban the user
+    // and then fail to ban the address.
+    let ip = if ban_ip {
+        let ecs = server.state.ecs();
+        let ip = find_uuid(ecs, player_uuid)
+            .ok()
+            .and_then(|player| ecs.read_storage::<Client>().get(player)?.remote_ip())
+            .ok_or_else(|| format!("{} is not connected from a network address", username))?;
+        Some(ip)
+    } else {
+        None
+    };
+
+    let now = Utc::now();
+    let end_date = parse_duration
+        .map(|duration| chrono::Duration::from_std(duration.into()))
+        .transpose()
+        .map_err(|err| format!("Error converting to duration: {}", err))?
+        // On overflow (someone adding some ridiculous time span), just make the ban infinite.
+        .and_then(|duration| now.checked_add_signed(duration));
+
+    let ban_info = BanInfo {
+        performed_by: client_uuid,
+        performed_by_username: client_username,
+        performed_by_role: client_role.into(),
+    };
+
+    let ban = Ban {
+        reason: reason.clone(),
+        info: Some(ban_info),
+        end_date,
+    };
+
+    // Ban the account and the address in a single edit, so that if either of the
+    // two would have no effect, neither is applied.
+    let edit = match ip {
+        Some(ip) => server
+            .editable_settings_mut()
+            .banlist
+            .ban_with_ip_action(
+                server.data_dir().as_ref(),
+                now,
+                player_uuid,
+                ip,
+                username.clone(),
+                BanAction::Ban(ban),
+                overwrite,
+            )
+            .map(|result| {
+                (
+                    format!(
+                        "Added {} and their address to the banlist with reason: {}",
+                        username, reason
+                    ),
+                    result,
+                )
+            }),
+        None => server
+            .editable_settings_mut()
+            .banlist
+            .ban_action(
+                server.data_dir().as_ref(),
+                now,
+                player_uuid,
+                username.clone(),
+                BanAction::Ban(ban),
+                overwrite,
+            )
+            .map(|result| {
+                (
+                    format!("Added {} to the banlist with reason: {}", username, reason),
+                    result,
+                )
+            }),
+    };
+
+    edit_setting_feedback(server, client, edit, || {
+        if ip.is_some() {
+            format!("{} or their address is already on the banlist", username)
+        } else {
+            format!("{} is already on the banlist", username)
+        }
+    })?;
+    // If the player is online kick them (this may fail if the player is a hardcoded
+    // admin; we don't care about that case because hardcoded admins can log on even
+    // if they're on the ban list).
+    let ecs = server.state.ecs();
+    if let Ok(target_player) = find_uuid(ecs, player_uuid) {
+        let _ = kick_player(
+            server,
+            (client, client_uuid),
+            (target_player, player_uuid),
+            &reason,
+        );
+    }
+    Ok(())
+}
+
+fn handle_ban_note(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), Some(text)) = parse_cmd_args!(args, String, String) {
         let player_uuid = find_username(server, &username)?;
 
         let client_uuid = uuid(server, client, "client")?;
         let client_username = uuid_to_username(server, client, client_uuid)?;
         let client_role = real_role(server, client_uuid, "client")?;
 
-        let now = Utc::now();
-        let end_date = parse_duration
-            .map(|duration| chrono::Duration::from_std(duration.into()))
-            .transpose()
-            .map_err(|err| format!("Error converting to duration: {}", err))?
-            // On overflow (someone adding some ridiculous time span), just make the ban infinite.
-            .and_then(|duration| now.checked_add_signed(duration));
-
-        let ban_info = BanInfo {
+        let info = BanInfo {
             performed_by: client_uuid,
             performed_by_username: client_username,
             performed_by_role: client_role.into(),
         };
 
-        let ban = Ban {
-            reason: reason.clone(),
-            info: Some(ban_info),
-            end_date,
-        };
-
         let edit = server
             .editable_settings_mut()
             .banlist
-            .ban_action(
+            .add_note(
                 server.data_dir().as_ref(),
-                now,
+                Utc::now(),
                 player_uuid,
-                username.clone(),
-                BanAction::Ban(ban),
-                overwrite,
+                info,
+                text,
             )
             .map(|result| {
                 (
-                    format!("Added {} to the banlist with reason: {}", username, reason),
+                    format!("Added a note to the ban entry of {}", username),
                     result,
                 )
             });
 
         edit_setting_feedback(server, client, edit, || {
-            format!("{} is already on the banlist", username)
-        })?;
-        // If the player is online kick them (this may fail if the player is a hardcoded
-        // admin; we don't care about that case because hardcoded admins can log on even
-        // if they're on the ban list).
-        let ecs = server.state.ecs();
-        if let Ok(target_player) = find_uuid(ecs, player_uuid) {
-            let _ = kick_player(
-                server,
-                (client, client_uuid),
-                (target_player, player_uuid),
-                &reason,
+            format!("{} has no ban entry to attach a note to", username)
+        })
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
+
+fn format_ban_record(record: &BanRecord) -> String {
+    let date = record.date.format("%Y-%m-%d %H:%M UTC");
+    match &record.action {
+        BanAction::Ban(ban) => {
+            let performed_by = ban
+                .info
+                .as_ref()
+                .map_or("unknown", |info| info.performed_by_username.as_str());
+            let until = ban.end_date.map_or_else(
+                || "permanently".to_owned(),
+                |end_date| format!("until {}", end_date.format("%Y-%m-%d %H:%M UTC")),
             );
+            format!(
+                "{}: banned as {} by {} {}, reason: {}",
+                date, record.username_when_performed, performed_by, until, ban.reason
+            )
+        },
+        BanAction::Unban(info) => format!(
+            "{}: unbanned as {} by {}",
+            date, record.username_when_performed, info.performed_by_username
+        ),
+    }
+}
+
+fn handle_ban_info(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let Some(username) = parse_cmd_args!(args, String) {
+        let player_uuid = find_username(server, &username)?;
+        let now = Utc::now();
+        let editable_settings = server.editable_settings();
+        let banlist = &editable_settings.banlist;
+
+        let mut msg = format!("Ban info for {} ({}):", username, player_uuid);
+        match banlist.uuid_bans().get(&player_uuid) {
+            Some(entry) => {
+                let status = if entry.current.is_expired(now) {
+                    "not banned"
+                } else {
+                    "banned"
+                };
+                let _ = write!(msg, "\nCurrently {}", status);
+                // History is stored oldest first, with the current record last.
+                for record in entry.history.iter().chain(core::iter::once(&entry.current)) {
+                    let _ = write!(msg, "\n  {}", format_ban_record(record));
+                }
+                for note in &entry.notes {
+                    let _ = write!(
+                        msg,
+                        "\nNote ({}, {}): {}",
+                        note.date.format("%Y-%m-%d %H:%M UTC"),
+                        note.info.performed_by_username,
+                        note.text
+                    );
+                }
+            },
+            None => msg.push_str("\nNo ban history"),
+        }
+        for (ip, entry) in banlist
+            .ip_bans()
+            .iter()
+            .filter(|(_, entry)| entry.uuid_when_performed == player_uuid)
+        {
+            let status = if entry.current.is_expired(now) {
+                "not banned"
+            } else {
+                "banned"
+            };
+            let _ = write!(msg, "\nAddress {} is currently {}", ip, status);
+            for record in entry.history.iter().chain(core::iter::once(&entry.current)) {
+                let _ = write!(msg, "\n  {}", format_ban_record(record));
+            }
         }
+
+        server.notify_client(
+            client,
+            ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+        );
         Ok(())
     } else {
         Err(Content::Plain(action.help_string()))
@@ -4075,6 +4545,206 @@ fn handle_unban(
     }
 }
 
+fn handle_unban_ip(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let Some(username) = parse_cmd_args!(args, String) {
+        let player_uuid = find_username(server, &username)?;
+
+        let client_uuid = uuid(server, client, "client")?;
+        let client_username = uuid_to_username(server, client, client_uuid)?;
+        let client_role = real_role(server, client_uuid, "client")?;
+
+        let now = Utc::now();
+
+        let ban_info = BanInfo {
+            performed_by: client_uuid,
+            performed_by_username: client_username,
+            performed_by_role: client_role.into(),
+        };
+
+        // Unban the account and its addresses in a single edit.
+        let edit = server
+            .editable_settings_mut()
+            .banlist
+            .unban_with_ips_action(
+                server.data_dir().as_ref(),
+                now,
+                player_uuid,
+                username.clone(),
+                ban_info,
+            )
+            .map(|((unbanned, unbanned_ips), result)| {
+                let unbanned = unbanned
+                    .then(|| username.clone())
+                    .into_iter()
+                    .chain(unbanned_ips.iter().map(|ip| format!("the address {}", ip)))
+                    .collect::<Vec<_>>();
+                (
+                    format!("Successfully unbanned {}", unbanned.join(", ")),
+                    result,
+                )
+            });
+
+        edit_setting_feedback(server, client, edit, || {
+            format!("{} was already unbanned", username)
+        })?;
+        Ok(())
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
+
+fn handle_character_export(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), character_id) = parse_cmd_args!(args, String, i64) {
+        let job = match character_id {
+            Some(character_id) => server.export_character(&username, CharacterId(character_id))?,
+            None => server.list_characters(&username)?,
+        };
+        // The client is told how it went once the job finished
+        server
+            .state
+            .ecs()
+            .write_resource::<CharacterArchives>()
+            .push_client_job(client, job);
+        Ok(())
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
+
+fn handle_character_import(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), Some(file_name)) = parse_cmd_args!(args, String, String) {
+        let job = server.import_character(&username, &file_name)?;
+        server
+            .state
+            .ecs()
+            .write_resource::<CharacterArchives>()
+            .push_client_job(client, job);
+        Ok(())
+    } else {
+        Err(Content::Plain(action.help_string()))
+    }
+}
+
+fn handle_audit_log(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let (player, command, num) = parse_cmd_args!(args, String, String, u32);
+    let filter = AuditLogFilter {
+        player,
+        command,
+        limit: num.unwrap_or(20).min(AuditLogFilter::MAX_LIMIT),
+    };
+    // The entries are sent once the search finished, see `format_audit_log`
+    server
+        .state
+        .ecs()
+        .write_resource::<AuditLog>()
+        .search_for_client(client, filter);
+    Ok(())
+}
+
+/// Describes the entries found by searching the audit log for a client
+pub(crate) fn format_audit_log(entries: &[AuditLogEntry]) -> String {
+    entries.iter().rev().fold(
+        format!("{} audit log entries:", entries.len()),
+        |mut s, entry| {
+            let _ = write!(
+                s,
+                "\n{} [{}] /{} {}",
+                entry.time.format("%Y-%m-%d %H:%M:%S"),
+                entry.actor_alias,
+                entry.command,
+                entry.args.join(" "),
+            );
+            if let Some(error) = &entry.error {
+                let _ = write!(s, " (failed: {})", error);
+            }
+            s
+        },
+    )
+}
+
+fn handle_movement_report(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let (player, num) = parse_cmd_args!(args, String, u32);
+    let report = server.state.ecs().read_resource::<MovementReport>();
+    let violations = report
+        .search(player.as_deref())
+        .take(num.unwrap_or(20).min(100) as usize)
+        .collect::<Vec<_>>();
+
+    let msg = violations.iter().rev().fold(
+        format!("{} movement violations:", violations.len()),
+        |mut s, reported| {
+            let _ = write!(
+                s,
+                "\n{} [{}] {} at ({:.0}, {:.0}, {:.0})",
+                reported.time.format("%Y-%m-%d %H:%M:%S"),
+                reported.player_alias,
+                reported.violation,
+                reported.pos.x,
+                reported.pos.y,
+                reported.pos.z,
+            );
+            if reported.count > 1 {
+                let _ = write!(s, " ({} times)", reported.count);
+            }
+            if !reported.rubber_banded {
+                let _ = write!(s, " (not rubber-banded)");
+            }
+            s
+        },
+    );
+    drop(report);
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+    );
+    Ok(())
+}
+
+fn handle_backup(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    _args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let name = server.backup()?;
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, format!("Started backup {}", name)),
+    );
+    Ok(())
+}
+
 fn handle_server_physics(
     server: &mut Server,
     client: EcsEntity,
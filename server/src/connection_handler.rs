@@ -1,7 +1,7 @@
 use crate::{Client, ClientType, ServerInfo};
 use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use futures_util::future::FutureExt;
-use network::{Network, Participant, Promises};
+use network::{Network, Participant, ParticipantError, Promises, Stream};
 use std::time::Duration;
 use tokio::{runtime::Runtime, select, sync::oneshot};
 use tracing::{debug, error, trace, warn};
@@ -13,6 +13,17 @@ pub(crate) struct ServerInfoPacket {
 
 pub(crate) type IncomingClient = Client;
 
+/// The streams the server opens to every client.
+pub(crate) struct ClientStreams {
+    pub general: Stream,
+    pub ping: Stream,
+    pub register: Stream,
+    pub character_screen: Stream,
+    pub in_game: Stream,
+    pub terrain: Stream,
+    pub physics: Stream,
+}
+
 pub(crate) struct ConnectionHandler {
     /// We never actually use this, but if it's dropped before the network has a
     /// chance to exit, it won't block the main thread, and if it is dropped
@@ -116,24 +127,16 @@ impl ConnectionHandler {
         let (sender, receiver) = bounded(1);
         info_requester_sender.send(sender)?;
 
-        let reliable = Promises::ORDERED | Promises::CONSISTENCY;
-        let reliablec = reliable | Promises::COMPRESSED;
-
-        let general_stream = participant.open(3, reliablec, 500).await?;
-        let ping_stream = participant.open(2, reliable, 500).await?;
-        let mut register_stream = participant.open(3, reliablec, 500).await?;
-        let character_screen_stream = participant.open(3, reliablec, 500).await?;
-        let in_game_stream = participant.open(3, reliablec, 100_000).await?;
-        let terrain_stream = participant.open(4, reliable, 20_000).await?;
+        let mut streams = Self::open_streams(&participant).await?;
 
         let server_data = receiver.recv()?;
 
-        register_stream.send(server_data.info)?;
+        streams.register.send(server_data.info)?;
 
         const TIMEOUT: Duration = Duration::from_secs(5);
         let client_type = match select!(
             _ = tokio::time::sleep(TIMEOUT).fuse() => None,
-            t = register_stream.recv::<ClientType>().fuse() => Some(t),
+            t = streams.register.recv::<ClientType>().fuse() => Some(t),
         ) {
             None => {
                 debug!("Timeout for incoming client elapsed, aborting connection");
@@ -146,17 +149,41 @@ impl ConnectionHandler {
             client_type,
             participant,
             server_data.time,
-            general_stream,
-            ping_stream,
-            register_stream,
-            character_screen_stream,
-            in_game_stream,
-            terrain_stream,
+            streams.general,
+            streams.ping,
+            streams.register,
+            streams.character_screen,
+            streams.in_game,
+            streams.terrain,
+            streams.physics,
         );
 
         client_sender.send(client)?;
         Ok(())
     }
+
+    pub(crate) async fn open_streams(
+        participant: &Participant,
+    ) -> Result<ClientStreams, ParticipantError> {
+        let reliable = Promises::ORDERED | Promises::CONSISTENCY | Promises::GUARANTEED_DELIVERY;
+        let reliablec = reliable | Promises::COMPRESSED;
+        // Physics updates are superseded by the next tick, so there is no point in
+        // retransmitting or queueing old ones.
+        let latestc = Promises::ORDERED
+            | Promises::CONSISTENCY
+            | Promises::LATEST_ONLY
+            | Promises::COMPRESSED;
+
+        Ok(ClientStreams {
+            general: participant.open(3, reliablec, 500).await?,
+            ping: participant.open(2, reliable, 500).await?,
+            register: participant.open(3, reliablec, 500).await?,
+            character_screen: participant.open(3, reliablec, 500).await?,
+            in_game: participant.open(3, reliablec, 100_000).await?,
+            terrain: participant.open(4, reliable, 20_000).await?,
+            physics: participant.open(3, latestc, 100_000).await?,
+        })
+    }
 }
 
 impl Drop for ConnectionHandler {
@@ -16,7 +16,7 @@ use common::{
     event::{EventBus, NpcBuilder, UpdateCharacterMetadata},
     mounting::{Mounting, Volume, VolumeMounting, VolumePos},
     outcome::Outcome,
-    resources::{Secs, Time},
+    resources::{RngSeed, Secs, Time},
     rtsim::RtSimEntity,
     uid::{IdMaps, Uid},
     util::Dir,
@@ -44,6 +44,16 @@ pub fn handle_initialize_character(
         server
             .state
             .initialize_character_data(entity, character_id, clamped_vds);
+        #[cfg(feature = "plugins")]
+        if let Some(uid) = server.state.ecs().uid_from_entity(entity) {
+            crate::plugin::dispatch_event(
+                &server.state,
+                &plugin_api::event::CharacterSelectEvent {
+                    player: plugin_api::event::Player { id: uid },
+                    character_id,
+                },
+            );
+        }
         // Correct client if its requested VD is too high.
         if requested_view_distances.terrain != clamped_vds.terrain {
             server.notify_client(entity, ServerGeneral::SetViewDistance(clamped_vds.terrain));
@@ -93,6 +103,8 @@ pub fn handle_loaded_character_data(
         ServerGeneral::CharacterDataLoadResult(Err(err))
     } else {
         sys::subscription::initialize_region_subscription(server.state.ecs(), entity);
+        super::quest::sync_quests(server, entity);
+        super::information::sync_site_factions(server, entity);
         // We notify the client with the metadata result from the operation.
         ServerGeneral::CharacterDataLoadResult(Ok(metadata))
     };
@@ -106,6 +118,17 @@ pub fn handle_create_npc(
     mut npc: NpcBuilder,
     rider: Option<NpcBuilder>,
 ) -> EcsEntity {
+    let drop_items = {
+        let ecs = server.state.ecs();
+        // The body is part of the key so that a mount and its rider, which are
+        // spawned at the same position, don't roll the same loot.
+        let mut rng = ecs.read_resource::<RngSeed>().rng(
+            *ecs.read_resource::<Time>(),
+            ("npc_loot", pos.0.map(f32::to_bits), npc.body),
+        );
+        npc.loot.to_items_with_rng(&mut rng)
+    };
+
     let entity = server
         .state
         .create_npc(
@@ -135,7 +158,7 @@ pub fn handle_create_npc(
         entity
     };
 
-    let entity = if let Some(drop_items) = npc.loot.to_items() {
+    let entity = if let Some(drop_items) = drop_items {
         entity.with(ItemDrops(drop_items))
     } else {
         entity
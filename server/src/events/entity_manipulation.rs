@@ -28,7 +28,7 @@ use common::{
     event::{EventBus, ServerEvent},
     lottery::distribute_many,
     outcome::{HealthChangeInfo, Outcome},
-    resources::{Secs, Time},
+    resources::{RngSeed, Secs, Time},
     spiral::Spiral2d,
     states::utils::StageSection,
     terrain::{Block, BlockKind, TerrainGrid},
@@ -68,6 +68,28 @@ pub fn handle_poise(server: &Server, entity: EcsEntity, change: comp::PoiseChang
 
 pub fn handle_health_change(server: &Server, entity: EcsEntity, change: HealthChange) {
     let ecs = &server.state.ecs();
+    // Give plugins a chance to cancel or alter damage before it is applied
+    #[cfg(feature = "plugins")]
+    let change = {
+        use plugin_api::event::{DamageResult, EntityDamageEvent};
+
+        let mut change = change;
+        if change.amount < 0.0 && let Some(uid) = ecs.uid_from_entity(entity) {
+            let responses = crate::plugin::dispatch_event(&server.state, &EntityDamageEvent {
+                target: uid,
+                attacker: change.by.map(|by| by.uid()),
+                amount: -change.amount,
+            });
+            for response in responses {
+                match response {
+                    DamageResult::Cancel => return,
+                    DamageResult::SetAmount(amount) => change.amount = -amount.max(0.0),
+                    DamageResult::None => {},
+                }
+            }
+        }
+        change
+    };
     if let Some(mut health) = ecs.write_storage::<Health>().get_mut(entity) {
         // If the change amount was not zero
         let changed = health.change_by(change);
@@ -140,6 +162,14 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
         return;
     }
 
+    #[cfg(feature = "plugins")]
+    if let Some(uid) = state.ecs().uid_from_entity(entity) {
+        crate::plugin::dispatch_event(state, &plugin_api::event::EntityDeathEvent {
+            entity: uid,
+            killer: last_change.by.map(|by| by.uid()),
+        });
+    }
+
     // Remove components that should not persist across death
     state.ecs().write_storage::<comp::Melee>().remove(entity);
     state.ecs().write_storage::<comp::Beam>().remove(entity);
@@ -475,6 +505,11 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
                     }
                 }
 
+                let mut rng = state.ecs().read_resource::<RngSeed>().rng(
+                    *state.ecs().read_resource::<Time>(),
+                    ("loot", state.ecs().uid_from_entity(entity)),
+                );
+
                 let mut item_offset_spiral =
                     Spiral2d::new().map(|offset| offset.as_::<f32>() * 0.5);
 
@@ -500,7 +535,6 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
                         spawn_item(item, None)
                     }
                 } else {
-                    let mut rng = rand::thread_rng();
                     distribute_many(
                         item_receivers
                             .iter()
@@ -674,7 +708,9 @@ pub fn handle_land_on_ground(
             0.0,
             1.0,
             *time,
-            rand::random(),
+            ecs.read_resource::<RngSeed>()
+                .rng(*time, ("fall_damage", ecs.uid_from_entity(entity)))
+                .gen(),
         );
 
         server_eventbus.emit_now(ServerEvent::HealthChange { entity, change });
@@ -807,8 +843,9 @@ pub fn handle_explosion(server: &Server, pos: Vec3<f32>, explosion: Explosion, o
         }
     }
 
-    // TODO: Faster RNG?
-    let mut rng = rand::thread_rng();
+    let mut rng = ecs
+        .read_resource::<RngSeed>()
+        .rng(*time, ("explosion", pos.map(f32::to_bits)));
     // TODO: Process terrain destruction first so that entities don't get protected
     // by terrain that gets destroyed?
     'effects: for effect in explosion.effects {
@@ -50,3 +50,20 @@ pub fn handle_site_info(server: &Server, entity: EcsEntity, id: u64) {
         .get(entity)
         .map(|c| c.send(msg));
 }
+
+#[cfg(not(feature = "worldgen"))]
+pub fn sync_site_factions(_server: &Server, _entity: EcsEntity) {}
+
+/// Sends the character of `entity` which faction holds each site, once they're
+/// in game.
+#[cfg(feature = "worldgen")]
+pub fn sync_site_factions(server: &Server, entity: EcsEntity) {
+    let site_factions = server
+        .state
+        .ecs()
+        .read_resource::<crate::rtsim::RtSim>()
+        .site_factions();
+    if let Some(client) = server.state.ecs().read_storage::<Client>().get(entity) {
+        client.send_fallible(ServerGeneral::SiteFactions(site_factions));
+    }
+}
@@ -22,6 +22,7 @@ use common::{
     link::Is,
     mounting::{Mount, Mounting, Rider, VolumeMounting, VolumePos, VolumeRider},
     outcome::Outcome,
+    resources::RngSeed,
     rtsim::RtSimEntity,
     terrain::{Block, SpriteKind},
     uid::{IdMaps, Uid},
@@ -340,9 +341,12 @@ pub fn handle_mine_block(
                     }
                     use common::comp::skills::{MiningSkill, Skill, SKILL_MODIFIERS};
                     use rand::Rng;
-                    let mut rng = rand::thread_rng();
+                    let mut rng = state
+                        .ecs()
+                        .read_resource::<RngSeed>()
+                        .rng(*state.ecs().read_resource::<Time>(), ("mine", pos));
 
-                    let need_double_ore = |rng: &mut rand::rngs::ThreadRng| {
+                    let need_double_ore = |rng: &mut rand::rngs::StdRng| {
                         let chance_mod = f64::from(SKILL_MODIFIERS.mining_tree.ore_gain);
                         let skill_level = skillset
                             .skill_level(Skill::Pick(MiningSkill::OreGain))
@@ -350,7 +354,7 @@ pub fn handle_mine_block(
 
                         rng.gen_bool(chance_mod * f64::from(skill_level))
                     };
-                    let need_double_gem = |rng: &mut rand::rngs::ThreadRng| {
+                    let need_double_gem = |rng: &mut rand::rngs::StdRng| {
                         let chance_mod = f64::from(SKILL_MODIFIERS.mining_tree.gem_gain);
                         let skill_level = skillset
                             .skill_level(Skill::Pick(MiningSkill::GemGain))
@@ -396,6 +400,66 @@ pub fn handle_mine_block(
     }
 }
 
+#[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+pub fn handle_break_block(server: &mut Server, entity: EcsEntity, pos: Vec3<i32>) {
+    let Some(old_block) = server.state.terrain().get(pos).ok().copied() else {
+        return;
+    };
+
+    #[cfg(feature = "plugins")]
+    if let Some(uid) = server.state.ecs().uid_from_entity(entity) {
+        let responses =
+            crate::plugin::dispatch_event(&server.state, &plugin_api::event::BlockBreakEvent {
+                player: plugin_api::event::Player { id: uid },
+                pos,
+                block: old_block,
+            });
+        if responses.contains(&plugin_api::event::CancellableResult::Cancel) {
+            return;
+        }
+    }
+    build_block(server, pos, old_block.into_vacant());
+}
+
+#[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+pub fn handle_place_block(
+    server: &mut Server,
+    entity: EcsEntity,
+    pos: Vec3<i32>,
+    new_block: Block,
+) {
+    #[cfg(feature = "plugins")]
+    if let Some(uid) = server.state.ecs().uid_from_entity(entity) {
+        let responses =
+            crate::plugin::dispatch_event(&server.state, &plugin_api::event::BlockPlaceEvent {
+                player: plugin_api::event::Player { id: uid },
+                pos,
+                block: new_block,
+            });
+        if responses.contains(&plugin_api::event::CancellableResult::Cancel) {
+            return;
+        }
+    }
+    build_block(server, pos, new_block);
+}
+
+/// Apply a block change made in build mode, persisting it if the server keeps
+/// track of terrain modifications.
+fn build_block(server: &mut Server, pos: Vec3<i32>, new_block: Block) {
+    if server.state.can_set_block(pos) {
+        server.state.set_block(pos, new_block);
+        #[cfg(feature = "persistent_world")]
+        if let Some(terrain_persistence) = server
+            .state
+            .ecs()
+            .try_fetch_mut::<crate::TerrainPersistence>()
+            .as_mut()
+        {
+            terrain_persistence.set_block(pos, new_block);
+        }
+    }
+}
+
 pub fn handle_sound(server: &mut Server, sound: &Sound) {
     let ecs = &server.state.ecs();
     let positions = &ecs.read_storage::<Pos>();
@@ -450,9 +514,13 @@ pub fn handle_create_sprite(
             // Remove sprite after del_timeout and offset if specified
             if let Some((timeout, del_offset)) = del_timeout {
                 use rand::Rng;
-                let mut rng = rand::thread_rng();
-                let offset = rng.gen_range(0.0..del_offset);
-                let current_time: f64 = state.ecs().read_resource::<Time>().0;
+                let time = *state.ecs().read_resource::<Time>();
+                let offset = state
+                    .ecs()
+                    .read_resource::<RngSeed>()
+                    .rng(time, ("sprite_timeout", pos))
+                    .gen_range(0.0..del_offset);
+                let current_time: f64 = time.0;
                 let replace_time = current_time + (timeout + offset) as f64;
                 if old_block != new_block {
                     server
@@ -18,7 +18,7 @@ use common::{
     recipe::{
         self, default_component_recipe_book, default_recipe_book, default_repair_recipe_book,
     },
-    resources::Time,
+    resources::{RngSeed, Time},
     terrain::{Block, SpriteKind},
     trade::Trades,
     uid::Uid,
@@ -95,6 +95,35 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
         })
     };
 
+    // Plugins need read access to inventories, so they get to veto pickups before
+    // we borrow them mutably
+    #[cfg(feature = "plugins")]
+    if let comp::InventoryManip::Pickup(pickup_uid) = &manip {
+        let item = state
+            .ecs()
+            .entity_from_uid(*pickup_uid)
+            .and_then(|item_entity| {
+                state
+                    .ecs()
+                    .read_storage::<comp::Item>()
+                    .get(item_entity)
+                    .map(|item| plugin_api::InventoryItem {
+                        item_definition_id: item.item_definition_id().to_owned(),
+                        amount: item.amount(),
+                    })
+            });
+        if let Some(item) = item {
+            let responses =
+                crate::plugin::dispatch_event(state, &plugin_api::event::ItemPickupEvent {
+                    player: plugin_api::event::Player { id: uid },
+                    item,
+                });
+            if responses.contains(&plugin_api::event::CancellableResult::Cancel) {
+                return;
+            }
+        }
+    }
+
     let mut inventories = state.ecs().write_storage::<comp::Inventory>();
     let mut inventory = if let Some(inventory) = inventories.get_mut(entity) {
         inventory
@@ -271,6 +300,8 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                 .expect("We know entity exists since we got its inventory.")
                 .or_insert_with(InventoryUpdate::default);
 
+            #[cfg(feature = "worldgen")]
+            let mut collected_sprite = None;
             if let Some(block) = block {
                 if block.is_collectible() && block_change.can_set_block(sprite_pos) {
                     // If an item was required to collect the sprite, consume it now
@@ -317,6 +348,10 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
 
                     // We made sure earlier the block was not already modified this tick
                     block_change.set(sprite_pos, block.into_vacant());
+                    #[cfg(feature = "worldgen")]
+                    {
+                        collected_sprite = block.get_sprite();
+                    }
 
                     // If the block was a keyhole, remove nearby door blocks
                     // TODO: Abstract this code into a generalised way to do block updates?
@@ -377,6 +412,25 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             drop(block_change);
             drop(inventory_updates);
 
+            #[cfg(feature = "worldgen")]
+            if let Some(sprite) = collected_sprite
+                && let Some(actor) = state.entity_as_actor(entity)
+            {
+                state
+                    .ecs()
+                    .write_resource::<crate::rtsim::RtSim>()
+                    .hook_collect_sprite(
+                        &state.ecs().read_resource::<std::sync::Arc<world::World>>(),
+                        state
+                            .ecs()
+                            .read_resource::<world::IndexOwned>()
+                            .as_index_ref(),
+                        actor,
+                        sprite_pos,
+                        sprite,
+                    );
+            }
+
             for item in drop_items {
                 state.create_item_drop(
                     comp::Pos(
@@ -559,7 +613,11 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             if let Some(effects) = maybe_effect {
                 match effects {
                     item::Effects::Any(effects) => {
-                        if let Some(effect) = effects.into_iter().choose(&mut rand::thread_rng()) {
+                        let mut rng = state
+                            .ecs()
+                            .read_resource::<RngSeed>()
+                            .rng(*state.ecs().read_resource::<Time>(), ("item_effect", uid));
+                        if let Some(effect) = effects.into_iter().choose(&mut rng) {
                             state.apply_effect(entity, effect, None);
                         }
                     },
@@ -964,7 +1022,10 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
         );
     }
 
-    let mut rng = rand::thread_rng();
+    let mut rng = state
+        .ecs()
+        .read_resource::<RngSeed>()
+        .rng(*state.ecs().read_resource::<Time>(), ("throw", uid));
 
     // Throw items
     for (pos, vel, look_dir, kind) in thrown_items {
@@ -242,20 +242,23 @@ pub fn handle_invite_accept(server: &mut Server, entity: Entity) {
                             .push_back(AgentEvent::TradeAccepted(invitee_uid));
                     }
                     #[cfg(feature = "worldgen")]
-                    let pricing = agents
-                        .get(inviter)
-                        .and_then(|a| {
-                            a.behavior
-                                .trade_site()
-                                .and_then(|id| index.get_site_prices(id))
-                        })
-                        .or_else(|| {
-                            agents.get(entity).and_then(|a| {
+                    let pricing = {
+                        let rtsim = state.ecs().read_resource::<crate::rtsim::RtSim>();
+                        agents
+                            .get(inviter)
+                            .and_then(|a| {
                                 a.behavior
                                     .trade_site()
-                                    .and_then(|id| index.get_site_prices(id))
+                                    .and_then(|id| rtsim.site_prices(&index, id))
                             })
-                        });
+                            .or_else(|| {
+                                agents.get(entity).and_then(|a| {
+                                    a.behavior
+                                        .trade_site()
+                                        .and_then(|id| rtsim.site_prices(&index, id))
+                                })
+                            })
+                    };
                     #[cfg(not(feature = "worldgen"))]
                     let pricing = None;
 
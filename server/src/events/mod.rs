@@ -25,17 +25,19 @@ use entity_manipulation::{
 use group_manip::handle_group;
 use information::handle_site_info;
 use interaction::{
-    handle_create_sprite, handle_lantern, handle_mine_block, handle_mount, handle_npc_interaction,
-    handle_set_pet_stay, handle_sound, handle_unmount,
+    handle_break_block, handle_create_sprite, handle_lantern, handle_mine_block, handle_mount,
+    handle_npc_interaction, handle_place_block, handle_set_pet_stay, handle_sound, handle_unmount,
 };
 use inventory_manip::handle_inventory;
 use invite::{handle_invite, handle_invite_response};
-use player::{handle_client_disconnect, handle_exit_ingame, handle_possess};
+use player::{handle_client_disconnect, handle_exit_ingame, handle_possess, handle_resume_session};
+use quest::{handle_complete_quest, handle_offer_quest, handle_quest_response};
 use specs::{Builder, Entity as EcsEntity, WorldExt};
 use trade::handle_process_trade_action;
 
 use crate::events::player::handle_character_delete;
 pub use group_manip::update_map_markers;
+pub(crate) use player::expire_held_sessions;
 pub(crate) use trade::cancel_trades_for;
 
 mod entity_creation;
@@ -46,6 +48,7 @@ mod interaction;
 mod inventory_manip;
 mod invite;
 mod player;
+mod quest;
 mod trade;
 
 pub enum Event {
@@ -137,6 +140,17 @@ impl Server {
                 ServerEvent::ProcessTradeAction(entity, trade_id, action) => {
                     handle_process_trade_action(self, entity, trade_id, action);
                 },
+                ServerEvent::OfferQuest { entity, quest } => {
+                    handle_offer_quest(self, entity, quest)
+                },
+                ServerEvent::CompleteQuest { entity, npc, quest } => {
+                    handle_complete_quest(self, entity, npc, quest)
+                },
+                ServerEvent::QuestResponse {
+                    entity,
+                    quest,
+                    response,
+                } => handle_quest_response(self, entity, quest, response),
                 ServerEvent::Mount(mounter, mountee) => handle_mount(self, mounter, mountee),
                 ServerEvent::MountVolume(mounter, volume) => {
                     handle_mount_volume(self, mounter, volume)
@@ -226,6 +240,9 @@ impl Server {
                         true,
                     ))
                 },
+                ServerEvent::ResumeSession { entity, held } => {
+                    handle_resume_session(self, entity, held)
+                },
                 ServerEvent::Command(entity, name, args) => {
                     commands.push((entity, name, args));
                 },
@@ -255,6 +272,12 @@ impl Server {
                 ServerEvent::MineBlock { entity, pos, tool } => {
                     handle_mine_block(self, entity, pos, tool)
                 },
+                ServerEvent::BreakBlock { entity, pos } => handle_break_block(self, entity, pos),
+                ServerEvent::PlaceBlock {
+                    entity,
+                    pos,
+                    new_block,
+                } => handle_place_block(self, entity, pos, new_block),
                 ServerEvent::TeleportTo {
                     entity,
                     target,
@@ -329,6 +352,10 @@ impl Server {
         }
 
         for msg in chat_messages {
+            #[cfg(feature = "plugins")]
+            let Some(msg) = crate::plugin::filter_chat_message(&self.state, msg) else {
+                continue;
+            };
             self.state.send_chat(msg);
         }
 
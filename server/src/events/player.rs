@@ -1,7 +1,11 @@
 use super::Event;
 use crate::{
-    client::Client, metrics::PlayerMetrics, persistence::character_updater::CharacterUpdater,
-    state_ext::StateExt, BattleModeBuffer, Server,
+    client::Client,
+    metrics::PlayerMetrics,
+    persistence::character_updater::CharacterUpdater,
+    presence::{HeldSession, ResumableLogins},
+    state_ext::StateExt,
+    BattleModeBuffer, Server,
 };
 use common::{
     character::CharacterId,
@@ -11,9 +15,10 @@ use common::{
     uid::{IdMaps, Uid},
 };
 use common_base::span;
-use common_net::msg::{PlayerListUpdate, ServerGeneral};
+use common_net::msg::{DisconnectReason, PlayerListUpdate, ServerGeneral};
 use common_state::State;
 use specs::{Builder, Entity as EcsEntity, Join, WorldExt};
+use std::time::Duration;
 use tracing::{debug, error, trace, warn, Instrument};
 
 pub fn handle_character_delete(
@@ -172,6 +177,48 @@ pub fn handle_client_disconnect(
     skip_persistence: bool,
 ) -> Event {
     span!(_guard, "handle_client_disconnect");
+    // Characters of players that lost their connection stay in the world for a
+    // while, so they can pick up where they left off when reconnecting.
+    let grace_period = server.settings().reconnect_grace_period;
+    let connection_dropped = matches!(
+        reason,
+        comp::DisconnectReason::Timeout | comp::DisconnectReason::NetworkError
+    );
+    let hold = connection_dropped
+        && !skip_persistence
+        && !grace_period.is_zero()
+        && server
+            .state()
+            .ecs()
+            .read_storage::<Client>()
+            .contains(entity)
+        && server
+            .state()
+            .ecs()
+            .read_storage::<Presence>()
+            .get(entity)
+            .map_or(false, |presence| {
+                matches!(presence.kind, PresenceKind::Character(_))
+            });
+
+    // The client can log in again with the token it was issued until the grace
+    // period is over, its character (if any) is held until then.
+    if connection_dropped && !grace_period.is_zero() {
+        let ecs = server.state().ecs();
+        if let (Some(client), Some(player)) = (
+            ecs.read_storage::<Client>().get(entity),
+            ecs.read_storage::<comp::Player>().get(entity),
+        ) {
+            let until = Time(ecs.read_resource::<Time>().0 + grace_period.as_secs_f64());
+            ecs.write_resource::<ResumableLogins>().insert(
+                client.resume_token,
+                player.alias.clone(),
+                player.uuid(),
+                until,
+            );
+        }
+    }
+
     if let Some(client) = server
         .state()
         .ecs()
@@ -191,6 +238,16 @@ pub fn handle_client_disconnect(
             .inc();
 
         if let Some(participant) = client.participant.take() {
+            #[cfg(feature = "plugins")]
+            if let Some(uid) = server.state().ecs().read_storage::<Uid>().get(entity) {
+                crate::plugin::dispatch_event(
+                    server.state(),
+                    &plugin_api::event::PlayerLogoutEvent {
+                        player: plugin_api::event::Player { id: *uid },
+                    },
+                );
+            }
+
             let pid = participant.remote_pid();
             server.runtime.spawn(
                 async {
@@ -222,6 +279,11 @@ pub fn handle_client_disconnect(
 
     let state = server.state_mut();
 
+    if hold {
+        hold_session(state, entity, grace_period);
+        return Event::ClientDisconnected { entity };
+    }
+
     // Tell other clients to remove from player list
     // And send a disconnected message
     if let (Some(uid), Some(_)) = (
@@ -248,6 +310,108 @@ pub fn handle_client_disconnect(
     Event::ClientDisconnected { entity }
 }
 
+/// Keeps the character of a player whose connection dropped in the world
+/// until the grace period is over, see [`HeldSession`]. It keeps its `Player`,
+/// so logging in again finds it and resumes the session.
+fn hold_session(state: &mut State, entity: EcsEntity, grace_period: Duration) {
+    debug!(
+        ?entity,
+        ?grace_period,
+        "Holding the session of a disconnected client"
+    );
+    super::cancel_trades_for(state, entity);
+    state.delete_component::<Client>(entity);
+
+    let until = Time(state.get_time() + grace_period.as_secs_f64());
+    let ecs = state.ecs();
+    // Stop doing whatever the player was doing when the connection dropped
+    if let Some(controller) = ecs.write_storage::<comp::Controller>().get_mut(entity) {
+        *controller = Default::default();
+    }
+    if let Err(e) = ecs.write_storage().insert(entity, HeldSession { until }) {
+        error!(
+            ?e,
+            ?entity,
+            "Failed to hold the session of a disconnected client"
+        );
+    }
+}
+
+/// Moves the client that just logged in to the character it left behind,
+/// see [`hold_session`].
+pub fn handle_resume_session(server: &mut Server, entity: EcsEntity, held: EcsEntity) {
+    let state = server.state_mut();
+    let resumable = state.ecs().is_alive(held)
+        && state.ecs().read_storage::<HeldSession>().contains(held)
+        && !state.ecs().read_storage::<Client>().contains(held);
+    let held_uid = state.read_component_copied::<Uid>(held);
+    let (Some(held_uid), true) = (held_uid, resumable) else {
+        // The session expired or got resumed by another login in the meantime
+        if let Some(client) = state.ecs().read_storage::<Client>().get(entity) {
+            client.send_fallible(ServerGeneral::Disconnect(DisconnectReason::Kicked(
+                String::from("Your session could not be resumed, please log in again."),
+            )));
+        }
+        handle_client_disconnect(server, entity, comp::DisconnectReason::Kicked, true);
+        return;
+    };
+    let Some(client) = state.delete_component::<Client>(entity) else {
+        return;
+    };
+    debug!(?entity, ?held, "Resuming held session");
+
+    let ecs = state.ecs();
+    let mut update_counter = 0;
+    if let Some(force_update) = ecs.write_storage::<comp::ForceUpdate>().get_mut(held) {
+        force_update.update();
+        update_counter = force_update.counter();
+    }
+    // The initial sync only contained what is synced from any entity
+    {
+        use crate::sys::sentinel::TrackedStorages;
+        use specs::SystemData;
+        let comp_sync_package = TrackedStorages::fetch(ecs)
+            .create_sync_from_client_entity_switch(held_uid, held_uid, held);
+        if !comp_sync_package.is_empty() {
+            client.send_fallible(ServerGeneral::CompSync(comp_sync_package, update_counter));
+        }
+    }
+    if let Err(e) = ecs.write_storage().insert(held, client) {
+        error!(?e, ?held, "Failed to resume held session");
+    }
+    // Send everything around the character again
+    crate::sys::subscription::initialize_region_subscription(ecs, held);
+
+    // The entity only served for logging in
+    if let Err(e) = state.delete_entity_recorded(entity) {
+        error!(?e, ?entity, "Failed to delete login entity after resuming");
+    }
+}
+
+/// Disconnects the characters whose player did not come back in time, and
+/// forgets their logins.
+pub fn expire_held_sessions(server: &mut Server) {
+    let expired = {
+        let ecs = server.state().ecs();
+        let time = ecs.read_resource::<Time>();
+        ecs.write_resource::<ResumableLogins>().remove_expired(*time);
+        (
+            &ecs.entities(),
+            &ecs.read_storage::<HeldSession>(),
+            !&ecs.read_storage::<Client>(),
+        )
+            .join()
+            .filter(|(_, held, _)| held.until.0 <= time.0)
+            .map(|(entity, _, _)| entity)
+            .collect::<Vec<_>>()
+    };
+    for entity in expired {
+        debug!(?entity, "Held session expired");
+        // Without a `Client` this takes the usual path
+        handle_client_disconnect(server, entity, comp::DisconnectReason::Timeout, false);
+    }
+}
+
 // When a player logs out, their data is queued for persistence in the next tick
 // of the persistence batch update. The player will be
 // temporarily unable to log in during this period to avoid
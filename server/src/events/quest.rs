@@ -0,0 +1,209 @@
+use crate::Server;
+use common::rtsim::{QuestId, QuestResponse};
+use specs::Entity as EcsEntity;
+#[cfg(feature = "worldgen")]
+use {
+    crate::{client::Client, rtsim::RtSim, state_ext::StateExt},
+    common::{
+        assets::AssetExt,
+        comp::{
+            item::ItemDef, Content, Inventory, InventoryUpdate, InventoryUpdateEvent, Item,
+            Presence, UnresolvedChatMsg,
+        },
+        rtsim::{Actor, RtSimEntity},
+        uid::Uid,
+    },
+    common_net::msg::ServerGeneral,
+    rtsim::data::quest::QuestObjective,
+    specs::WorldExt,
+    std::sync::Arc,
+};
+
+#[cfg(not(feature = "worldgen"))]
+pub fn handle_offer_quest(_server: &Server, _entity: EcsEntity, _quest: QuestId) {}
+
+#[cfg(not(feature = "worldgen"))]
+pub fn handle_complete_quest(
+    _server: &Server,
+    _entity: EcsEntity,
+    _npc: EcsEntity,
+    _quest: QuestId,
+) {
+}
+
+#[cfg(not(feature = "worldgen"))]
+pub fn handle_quest_response(
+    _server: &Server,
+    _entity: EcsEntity,
+    _quest: QuestId,
+    _response: QuestResponse,
+) {
+}
+
+#[cfg(not(feature = "worldgen"))]
+pub fn sync_quests(_server: &Server, _entity: EcsEntity) {}
+
+#[cfg(feature = "worldgen")]
+fn character_of(server: &Server, entity: EcsEntity) -> Option<common::character::CharacterId> {
+    server
+        .state
+        .ecs()
+        .read_storage::<Presence>()
+        .get(entity)
+        .and_then(|presence| presence.kind.character_id())
+}
+
+#[cfg(feature = "worldgen")]
+pub fn handle_offer_quest(server: &Server, entity: EcsEntity, quest: QuestId) {
+    let Some(character) = character_of(server, entity) else {
+        return;
+    };
+    let ecs = server.state.ecs();
+    let rtsim = ecs.read_resource::<RtSim>();
+    let data = &mut *rtsim.state().data_mut();
+    // Only quests that were offered to a character may be taken on by them
+    if !data.quests.offer(quest, character) {
+        return;
+    }
+
+    if let Some(info) = data
+        .quests
+        .get(quest)
+        .map(|q| q.info(quest, data, server.index.as_index_ref()))
+        && let Some(client) = ecs.read_storage::<Client>().get(entity)
+    {
+        client.send_fallible(ServerGeneral::QuestOffer(info));
+    }
+}
+
+/// The items that a quest reward amounts to. Items that don't stack are given
+/// one at a time.
+#[cfg(feature = "worldgen")]
+fn reward_items(asset: &str, mut amount: u32) -> Vec<Item> {
+    let mut items = Vec::new();
+    while amount > 0 {
+        let mut item = match Item::new_from_asset(asset) {
+            Ok(item) => item,
+            Err(error) => {
+                tracing::warn!(?asset, ?error, "Failed to create quest reward");
+                break;
+            },
+        };
+        let stack = amount.min(item.max_amount());
+        if item.set_amount(stack).is_err() {
+            break;
+        }
+        amount -= stack;
+        items.push(item);
+    }
+    items
+}
+
+#[cfg(feature = "worldgen")]
+pub fn handle_complete_quest(server: &Server, entity: EcsEntity, npc: EcsEntity, quest: QuestId) {
+    let Some(character) = character_of(server, entity) else {
+        return;
+    };
+    let ecs = server.state.ecs();
+    let Some(RtSimEntity(npc_id)) = ecs.read_storage::<RtSimEntity>().get(npc).copied() else {
+        return;
+    };
+
+    let mut completed_for = None;
+    let response = {
+        let rtsim = ecs.read_resource::<RtSim>();
+        let data = &mut *rtsim.state().data_mut();
+        let Some(q) = data.quests.handing_in(quest, character, npc_id) else {
+            return;
+        };
+
+        let mut inventories = ecs.write_storage::<Inventory>();
+        let Some(inventory) = inventories.get_mut(entity) else {
+            return;
+        };
+        let rewards = q
+            .rewards
+            .iter()
+            .flat_map(|(item, amount)| reward_items(item, *amount))
+            .collect::<Vec<_>>();
+        if inventory.free_slots() < rewards.len() {
+            "npc-speech-quest_inventory_full"
+        } else {
+            let achieved = if let QuestObjective::Fetch { item, amount } = &q.objective {
+                Arc::<ItemDef>::load_cloned(item).map_or(false, |item_def| {
+                    inventory.remove_item_amount(&item_def, *amount)
+                })
+            } else {
+                q.can_hand_in()
+            };
+
+            if achieved {
+                let giver = q.giver;
+                data.quests.remove(quest);
+                for item in rewards {
+                    if let Err(item) = inventory.push(item) {
+                        tracing::warn!(?item, "Failed to give quest reward despite free slots");
+                    }
+                }
+                let mut inventory_update = ecs.write_storage::<InventoryUpdate>();
+                if let Some(update) = inventory_update.get_mut(entity) {
+                    update.push(InventoryUpdateEvent::Given);
+                } else {
+                    let _ = inventory_update
+                        .insert(entity, InventoryUpdate::new(InventoryUpdateEvent::Given));
+                }
+
+                completed_for = Some(giver);
+                "npc-speech-quest_complete"
+            } else {
+                "npc-speech-quest_not_done"
+            }
+        }
+    };
+
+    if let Some(giver) = completed_for {
+        ecs.write_resource::<RtSim>().hook_quest_complete(
+            &server.world,
+            server.index.as_index_ref(),
+            Actor::Character(character),
+            giver,
+        );
+    }
+
+    if let Some(uid) = ecs.read_storage::<Uid>().get(npc).copied() {
+        server
+            .state
+            .send_chat(UnresolvedChatMsg::npc(uid, Content::localized(response)));
+    }
+}
+
+#[cfg(feature = "worldgen")]
+pub fn handle_quest_response(
+    server: &Server,
+    entity: EcsEntity,
+    quest: QuestId,
+    response: QuestResponse,
+) {
+    let Some(character) = character_of(server, entity) else {
+        return;
+    };
+    let rtsim = server.state.ecs().read_resource::<RtSim>();
+    let data = &mut *rtsim.state().data_mut();
+    match response {
+        QuestResponse::Accept => {
+            data.quests.accept(quest, character, data.time_of_day);
+        },
+        QuestResponse::Decline => data.quests.decline(quest, character),
+        QuestResponse::Abandon => data.quests.abandon(quest, character),
+    }
+}
+
+/// Sends the character of `entity` the quests they took on, once they're in
+/// game.
+#[cfg(feature = "worldgen")]
+pub fn sync_quests(server: &Server, entity: EcsEntity) {
+    if let Some(character) = character_of(server, entity) {
+        let rtsim = server.state.ecs().read_resource::<RtSim>();
+        rtsim.state().data_mut().quests.changed.insert(character);
+    }
+}
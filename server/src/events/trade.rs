@@ -1,4 +1,12 @@
+#[cfg(feature = "worldgen")]
+use crate::rtsim::RtSim;
 use crate::Server;
+#[cfg(feature = "worldgen")]
+use common::{
+    comp::inventory::trade_pricing::TradePricing,
+    rtsim::{NpcId, RtSimEntity},
+    trade::{Good, SiteId},
+};
 use common::{
     comp::{
         agent::{Agent, AgentEvent},
@@ -17,6 +25,7 @@ use hashbrown::{hash_map::Entry, HashMap};
 use specs::{world::WorldExt, Entity as EcsEntity};
 use std::cmp::Ordering;
 use tracing::{error, trace};
+#[cfg(feature = "worldgen")]
 use world::IndexOwned;
 
 fn notify_agent_simple(
@@ -29,8 +38,10 @@ fn notify_agent_simple(
     }
 }
 
+#[cfg(feature = "worldgen")]
 fn notify_agent_prices(
     mut agents: specs::WriteStorage<Agent>,
+    rtsim: &RtSim,
     index: &IndexOwned,
     entity: EcsEntity,
     event: AgentEvent,
@@ -40,7 +51,7 @@ fn notify_agent_prices(
             // Prefer using this Agent's price data, but use the counterparty's price
             // data if we don't have price data
             let prices = site_id
-                .and_then(|site_id| index.get_site_prices(site_id))
+                .and_then(|site_id| rtsim.site_prices(index, site_id))
                 .unwrap_or(boxval.2);
             // Box<(tid, pend, _, inventories)>) = event {
             agent
@@ -89,7 +100,21 @@ pub(super) fn handle_process_trade_action(
             if let Entry::Occupied(entry) = trades.trades.entry(trade_id) {
                 let parties = entry.get().parties;
                 if entry.get().should_commit() {
+                    #[cfg(feature = "worldgen")]
+                    let traded_goods = traded_goods(server.state.ecs(), entry.get());
                     let result = commit_trade(server.state.ecs(), entry.get());
+                    #[cfg(feature = "worldgen")]
+                    if let (TradeResult::Completed, Some((site, npc, goods))) =
+                        (&result, traded_goods)
+                    {
+                        server.state.ecs().write_resource::<RtSim>().hook_trade(
+                            &server.world,
+                            server.index.as_index_ref(),
+                            site,
+                            npc,
+                            goods,
+                        );
+                    }
                     entry.remove();
                     for party in parties.iter() {
                         if let Some(e) = server.state.ecs().entity_from_uid(*party) {
@@ -126,7 +151,13 @@ pub(super) fn handle_process_trade_action(
                                     agents
                                         .get(e)
                                         .and_then(|a| a.behavior.trade_site())
-                                        .and_then(|id| server.index.get_site_prices(id))
+                                        .and_then(|id| {
+                                            server
+                                                .state
+                                                .ecs()
+                                                .read_resource::<RtSim>()
+                                                .site_prices(&server.index, id)
+                                        })
                                 });
                             }
                         }
@@ -145,6 +176,7 @@ pub(super) fn handle_process_trade_action(
                             #[cfg(feature = "worldgen")]
                             notify_agent_prices(
                                 server.state.ecs().write_storage::<Agent>(),
+                                &server.state.ecs().read_resource::<RtSim>(),
                                 &server.index,
                                 e,
                                 AgentEvent::UpdatePendingTrade(Box::new((
@@ -193,6 +225,46 @@ pub(crate) fn cancel_trades_for(state: &mut common_state::State, entity: EcsEnti
     }
 }
 
+/// The goods that the merchant in a trade gains, negative for the goods it
+/// gives away, along with the site the merchant trades for and the rtsim NPC
+/// of the merchant, if any.
+#[cfg(feature = "worldgen")]
+fn traded_goods(
+    ecs: &specs::World,
+    trade: &PendingTrade,
+) -> Option<(SiteId, Option<NpcId>, Vec<(Good, f32)>)> {
+    let agents = ecs.read_storage::<Agent>();
+    let inventories = ecs.read_storage::<Inventory>();
+    let rtsim_entities = ecs.read_storage::<RtSimEntity>();
+    let entities = trade.parties.map(|party| ecs.entity_from_uid(party));
+    let (merchant, site) = (0..2).find_map(|who| {
+        let site = agents.get(entities[who]?)?.behavior.trade_site()?;
+        Some((who, site))
+    })?;
+    let npc = entities[merchant]
+        .and_then(|entity| rtsim_entities.get(entity))
+        .map(|rtsim_entity| rtsim_entity.0);
+
+    let mut goods = Vec::new();
+    for who in 0..2 {
+        let inventory = inventories.get(entities[who]?)?;
+        let sign = if who == merchant { -1.0 } else { 1.0 };
+        for (slot, quantity) in trade.offers[who].iter() {
+            if let Some(materials) = inventory
+                .get(*slot)
+                .and_then(|item| TradePricing::get_materials(&item.item_definition_id()))
+            {
+                goods.extend(
+                    materials
+                        .iter()
+                        .map(|(amount, good)| (*good, sign * amount * *quantity as f32)),
+                );
+            }
+        }
+    }
+    Some((site, npc, goods))
+}
+
 /// Commit a trade that both parties have agreed to, modifying their respective
 /// inventories
 fn commit_trade(ecs: &specs::World, trade: &PendingTrade) -> TradeResult {
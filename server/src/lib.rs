@@ -7,6 +7,7 @@
 #![feature(box_patterns, let_chains, never_type, option_zip, unwrap_infallible)]
 
 pub mod automod;
+pub mod backup;
 mod character_creator;
 pub mod chat;
 pub mod chunk_generator;
@@ -22,15 +23,19 @@ pub mod location;
 pub mod lod;
 pub mod login_provider;
 pub mod metrics;
+pub mod movement_validation;
 pub mod persistence;
 mod pet;
+#[cfg(feature = "plugins")] mod plugin;
 pub mod presence;
+pub mod recording;
 pub mod rtsim;
 pub mod settings;
 pub mod state_ext;
 pub mod sys;
 #[cfg(feature = "persistent_world")]
 pub mod terrain_persistence;
+mod terrain_versions;
 #[cfg(not(feature = "worldgen"))] mod test_world;
 
 mod weather;
@@ -58,7 +63,8 @@ use crate::{
     location::Locations,
     login_provider::LoginProvider,
     persistence::PersistedComponents,
-    presence::{RegionSubscription, RepositionOnChunkLoad},
+    presence::{HeldSession, RegionSubscription, RepositionOnChunkLoad, ResumableLogins},
+    recording::RecordingError,
     state_ext::StateExt,
     sys::sentinel::DeletedEntities,
 };
@@ -75,7 +81,7 @@ use common::{
     link::Is,
     mounting::{Volume, VolumeRider},
     region::RegionMap,
-    resources::{BattleMode, GameMode, Time, TimeOfDay},
+    resources::{BattleMode, GameMode, RngSeed, Time, TimeOfDay},
     rtsim::RtSimEntity,
     shared_server_config::ServerConstants,
     slowjob::SlowJobPool,
@@ -93,6 +99,8 @@ use common_systems::add_local_systems;
 use metrics::{EcsSystemMetrics, PhysicsMetrics, TickMetrics};
 use network::{ListenAddr, Network, Pid};
 use persistence::{
+    audit_log::{AuditLog, AuditLogEntry},
+    character_archive::{CharacterArchiveJob, CharacterArchives},
     character_loader::{CharacterLoader, CharacterUpdaterMessage},
     character_updater::CharacterUpdater,
 };
@@ -123,7 +131,7 @@ use crate::settings::Protocol;
 #[cfg(feature = "plugins")]
 use {
     common::uid::IdMaps,
-    common_state::plugin::{memory_manager::EcsWorld, PluginMgr},
+    common_state::plugin::{memory_manager::EcsWorld, storage::PluginStorage, PluginMgr},
 };
 
 use crate::{chat::ChatCache, persistence::character_loader::CharacterScreenResponseKind};
@@ -217,6 +225,7 @@ pub struct Server {
     chat_cache: ChatCache,
     database_settings: Arc<RwLock<DatabaseSettings>>,
     disconnect_all_clients_requested: bool,
+    recorder: Option<recording::Recorder>,
 
     server_constants: ServerConstants,
 }
@@ -319,6 +328,7 @@ impl Server {
             .ecs_mut()
             .insert(EventBus::<chunk_serialize::ChunkSendEntry>::default());
         state.ecs_mut().insert(Locations::default());
+        state.ecs_mut().insert(ResumableLogins::default());
         state.ecs_mut().insert(LoginProvider::new(
             settings.auth_server_address.clone(),
             Arc::clone(&runtime),
@@ -328,6 +338,7 @@ impl Server {
             rayon_threads: num_cpus::get() as u32,
         });
         state.ecs_mut().insert(Tick(0));
+        state.ecs_mut().insert(RngSeed(rand::random()));
         state.ecs_mut().insert(TickStart(Instant::now()));
         state.ecs_mut().insert(job_metrics);
         state.ecs_mut().insert(network_request_metrics);
@@ -361,10 +372,15 @@ impl Server {
             pool.configure("CHUNK_GENERATOR", |n| n / 2 + n / 4);
             pool.configure("CHUNK_SERIALIZER", |n| n / 2);
             pool.configure("RTSIM_SAVE", |_| 1);
+            pool.configure("BACKUP", |_| 1);
+            pool.configure("CHARACTER_ARCHIVE", |_| 1);
         }
         state
             .ecs_mut()
             .insert(ChunkGenerator::new(chunk_gen_metrics));
+        state
+            .ecs_mut()
+            .insert(terrain_versions::TerrainVersions::default());
         {
             let (sender, receiver) =
                 crossbeam_channel::bounded::<chunk_serialize::SerializedChunk>(10_000);
@@ -375,6 +391,27 @@ impl Server {
         state.ecs_mut().insert(CharacterUpdater::new(
             Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
         )?);
+        state
+            .ecs_mut()
+            .insert(AuditLog::new(Arc::clone(&database_settings)));
+        state.ecs_mut().insert(backup::Backups::new());
+        state.ecs_mut().insert(CharacterArchives::default());
+
+        #[cfg(feature = "plugins")]
+        {
+            let plugin_storage = persistence::plugin_storage::load_plugin_storage(
+                &persistence::establish_connection(
+                    &database_settings.read().unwrap(),
+                    persistence::ConnectionMode::ReadOnly,
+                ),
+            )?;
+            state.ecs_mut().insert(plugin_storage);
+            state
+                .ecs_mut()
+                .insert(persistence::plugin_storage::PluginStorageUpdater::new(
+                    Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
+                )?);
+        }
 
         let ability_map = comp::item::tool::AbilityMap::<comp::AbilityItem>::load_expect_cloned(
             "common.abilities.ability_set_manifest",
@@ -398,6 +435,7 @@ impl Server {
 
         // Server-only components
         state.ecs_mut().register::<RegionSubscription>();
+        state.ecs_mut().register::<HeldSession>();
         state.ecs_mut().register::<Client>();
         state.ecs_mut().register::<comp::Presence>();
         state.ecs_mut().register::<wiring::WiringElement>();
@@ -407,6 +445,12 @@ impl Server {
         state.ecs_mut().register::<login_provider::PendingLogin>();
         state.ecs_mut().register::<RepositionOnChunkLoad>();
         state.ecs_mut().register::<RtSimEntity>();
+        state
+            .ecs_mut()
+            .register::<movement_validation::MovementTracker>();
+        state
+            .ecs_mut()
+            .insert(movement_validation::MovementReport::default());
 
         // Load banned words list
         let banned_words = settings.moderation.load_banned_words(data_dir);
@@ -485,6 +529,13 @@ impl Server {
         state.ecs_mut().insert(DeletedEntities::default());
 
         let network = Network::new_with_registry(Pid::new(), &runtime, &registry);
+        if let Some(link_simulation) = settings.link_simulation {
+            warn!(
+                ?link_simulation,
+                "Simulating a degraded network link, this is only meant for testing!"
+            );
+            network.set_link_simulation(Some(link_simulation));
+        }
         let (chat_cache, chat_tracker) = ChatCache::new(Duration::from_secs(60), &runtime);
         state.ecs_mut().insert(chat_tracker);
 
@@ -596,6 +647,7 @@ impl Server {
             chat_cache,
             database_settings,
             disconnect_all_clients_requested: false,
+            recorder: None,
 
             server_constants,
         };
@@ -688,6 +740,14 @@ impl Server {
         self.state.ecs().write_resource::<Tick>().0 += 1;
         self.state.ecs().write_resource::<TickStart>().0 = Instant::now();
 
+        if let Some(recorder) = &mut self.recorder {
+            let tick = self.state.ecs().read_resource::<Tick>().0;
+            if let Err(e) = recorder.tick(tick, self.state.get_time(), dt) {
+                error!("Stopped recording the session: {}", e);
+                self.recorder = None;
+            }
+        }
+
         // Update calendar events as time changes
         // TODO: If a lot of calendar events get added, this might become expensive.
         // Maybe don't do this every tick?
@@ -723,7 +783,13 @@ impl Server {
         // 1) Build up a list of events for this frame, to be passed to the frontend.
         let mut frontend_events = Vec::new();
 
-        // 2)
+        // 2) Run plugin timers, then apply the actions plugins emitted since the last
+        //    tick
+        #[cfg(feature = "plugins")]
+        {
+            self.tick_plugins();
+            self.handle_plugin_actions();
+        }
 
         let before_new_connections = Instant::now();
 
@@ -779,6 +845,7 @@ impl Server {
 
         // Handle game events
         frontend_events.append(&mut self.handle_events());
+        events::expire_held_sessions(self);
 
         let before_update_terrain_and_regions = Instant::now();
 
@@ -938,6 +1005,17 @@ impl Server {
                 CharacterUpdaterMessage::DatabaseBatchCompletion(batch_id) => {
                     character_updater.process_batch_completion(batch_id);
                 },
+                CharacterUpdaterMessage::CharacterDeleted(character_id) => {
+                    self.state
+                        .ecs()
+                        .write_resource::<PluginStorage>()
+                        .remove_character(character_id);
+                    #[cfg(feature = "worldgen")]
+                    self.state
+                        .ecs()
+                        .write_resource::<rtsim::RtSim>()
+                        .hook_character_deleted(character_id);
+                },
                 CharacterUpdaterMessage::CharacterScreenResponse(response) => {
                     match response.response_kind {
                         CharacterScreenResponseKind::CharacterList(result) => match result {
@@ -1048,6 +1126,40 @@ impl Server {
         drop(character_loader);
         drop(character_updater);
 
+        // Send the audit log entries that clients searched for
+        let audit_log_searches = self
+            .state
+            .ecs()
+            .write_resource::<AuditLog>()
+            .finished_client_searches();
+        for (client, result) in audit_log_searches {
+            let msg = match result {
+                Ok(entries) => ServerGeneral::server_msg(
+                    comp::ChatType::CommandInfo,
+                    cmd::format_audit_log(&entries),
+                ),
+                Err(e) => ServerGeneral::server_msg(
+                    comp::ChatType::CommandError,
+                    format!("Failed to search the audit log: {}", e),
+                ),
+            };
+            self.notify_client(client, msg);
+        }
+
+        // Tell clients how the character jobs they started went
+        let character_jobs = self
+            .state
+            .ecs()
+            .write_resource::<CharacterArchives>()
+            .finished_client_jobs();
+        for (client, result) in character_jobs {
+            let msg = match result {
+                Ok(msg) => ServerGeneral::server_msg(comp::ChatType::CommandInfo, msg),
+                Err(e) => ServerGeneral::server_msg(comp::ChatType::CommandError, e),
+            };
+            self.notify_client(client, msg);
+        }
+
         {
             // Check for new chunks; cancel and regenerate all chunks if the asset has been
             // reloaded. Note that all of these assignments are no-ops, so the
@@ -1146,6 +1258,18 @@ impl Server {
             .ecs()
             .try_fetch_mut::<TerrainPersistence>()
             .map(|mut t| t.maintain());
+
+        // Take scheduled backups
+        let backup_due = self
+            .state
+            .ecs()
+            .read_resource::<backup::Backups>()
+            .is_due(&self.settings().backup);
+        if backup_due {
+            if let Err(e) = self.backup() {
+                error!("Scheduled backup failed: {}", e);
+            }
+        }
     }
 
     // Run RegionMap tick to update entity region occupancy
@@ -1160,7 +1284,10 @@ impl Server {
         );
     }
 
-    fn initialize_client(&mut self, client: connection_handler::IncomingClient) -> Entity {
+    fn initialize_client(&mut self, mut client: connection_handler::IncomingClient) -> Entity {
+        if let Some(recorder) = &mut self.recorder {
+            client.recorder = Some(recorder.connect(client.client_type));
+        }
         let entity = self
             .state
             .ecs_mut()
@@ -1185,13 +1312,14 @@ impl Server {
         if let Some(disconnect_type) = disconnect_type {
             let with_persistence = disconnect_type == DisconnectType::WithPersistence;
             let clients = self.state.ecs().read_storage::<Client>();
+            let held_sessions = self.state.ecs().read_storage::<HeldSession>();
             let entities = self.state.ecs().entities();
 
             info!(
                 "Disconnecting all clients ({} persistence) as requested",
                 if with_persistence { "with" } else { "without" }
             );
-            for (_, entity) in (&clients, &entities).join() {
+            for (_, entity) in (clients.mask() | held_sessions.mask(), &entities).join() {
                 info!("Emitting client disconnect event for entity: {:?}", entity);
                 let event = if with_persistence {
                     ServerEvent::ClientDisconnect(entity, comp::DisconnectReason::Kicked)
@@ -1289,6 +1417,11 @@ impl Server {
                     uid: self.state.ecs().read_component().into(),
                     id_maps: &self.state.ecs().read_resource::<IdMaps>().into(),
                     player: self.state.ecs().read_component().into(),
+                    pos: self.state.ecs().read_component().into(),
+                    stats: self.state.ecs().read_component().into(),
+                    buffs: self.state.ecs().read_component().into(),
+                    inventory: self.state.ecs().read_component().into(),
+                    storage: &self.state.ecs().read_resource::<PluginStorage>().into(),
                 };
                 let uid = if let Some(uid) = ecs_world.uid.get(entity).copied() {
                     uid
@@ -1383,63 +1516,426 @@ impl Server {
 
     /// NOTE: Do *not* allow this to be called from any command that doesn't go
     /// through the CLI!
-    pub fn add_admin(&mut self, username: &str, role: comp::AdminRole) {
-        let mut editable_settings = self.editable_settings_mut();
-        let login_provider = self.state.ecs().fetch::<LoginProvider>();
-        let data_dir = self.data_dir();
-        if let Some(entity) = add_admin(
-            username,
-            role,
-            &login_provider,
-            &mut editable_settings,
-            &data_dir.path,
-        )
-        .and_then(|uuid| {
-            let state = &self.state;
-            (
-                &state.ecs().entities(),
-                &state.read_storage::<comp::Player>(),
+    pub fn add_admin(&mut self, username: &str, role: comp::AdminRole) -> Result<(), String> {
+        let args = vec!["add".to_owned(), username.to_owned(), format!("{:?}", role)];
+        let cmd = ServerChatCommand::Adminify.keyword();
+        self.audit_console_action(cmd, Some(username), args, |server| {
+            let uuid = add_admin(
+                username,
+                role,
+                &server.state.ecs().fetch::<LoginProvider>(),
+                &mut server.editable_settings_mut(),
+                &server.data_dir().path,
             )
-                .join()
-                .find(|(_, player)| player.uuid() == uuid)
-                .map(|(e, _)| e)
-        }) {
-            drop((data_dir, login_provider, editable_settings));
+            .ok_or_else(|| format!("Could not make {} an admin", username))?;
             // Add admin component if the player is ingame; if they are not, we can ignore
             // the write failure.
-            self.state
-                .write_component_ignore_entity_dead(entity, comp::Admin(role));
-        };
+            if let Some(entity) = server.find_player(uuid) {
+                server
+                    .state
+                    .write_component_ignore_entity_dead(entity, comp::Admin(role));
+            }
+            Ok(())
+        })
     }
 
     /// NOTE: Do *not* allow this to be called from any command that doesn't go
     /// through the CLI!
-    pub fn remove_admin(&self, username: &str) {
-        let mut editable_settings = self.editable_settings_mut();
-        let login_provider = self.state.ecs().fetch::<LoginProvider>();
-        let data_dir = self.data_dir();
-        if let Some(entity) = remove_admin(
-            username,
-            &login_provider,
-            &mut editable_settings,
-            &data_dir.path,
+    pub fn remove_admin(&mut self, username: &str) -> Result<(), String> {
+        let args = vec!["remove".to_owned(), username.to_owned()];
+        let cmd = ServerChatCommand::Adminify.keyword();
+        self.audit_console_action(cmd, Some(username), args, |server| {
+            let uuid = remove_admin(
+                username,
+                &server.state.ecs().fetch::<LoginProvider>(),
+                &mut server.editable_settings_mut(),
+                &server.data_dir().path,
+            )
+            .ok_or_else(|| format!("Could not remove {} from the admins", username))?;
+            // Remove admin component if the player is ingame
+            if let Some(entity) = server.find_player(uuid) {
+                server
+                    .state
+                    .ecs()
+                    .write_storage::<comp::Admin>()
+                    .remove(entity);
+            }
+            Ok(())
+        })
+    }
+
+    /// The entity of the player with the given UUID, if they are online
+    fn find_player(&self, uuid: common::uuid::Uuid) -> Option<EcsEntity> {
+        (
+            &self.state.ecs().entities(),
+            &self.state.read_storage::<comp::Player>(),
         )
-        .and_then(|uuid| {
-            let state = &self.state;
-            (
-                &state.ecs().entities(),
-                &state.read_storage::<comp::Player>(),
+            .join()
+            .find(|(_, player)| player.uuid() == uuid)
+            .map(|(entity, _)| entity)
+    }
+
+    /// Names and UUIDs of the players currently online
+    pub fn online_players(&self) -> Vec<(String, common::uuid::Uuid)> {
+        self.state
+            .ecs()
+            .read_storage::<comp::Player>()
+            .join()
+            .map(|player| (player.alias.clone(), player.uuid()))
+            .collect()
+    }
+
+    /// Searches the privileged commands which were run on the server in the
+    /// background
+    pub fn search_audit_log(
+        &self,
+        filter: persistence::audit_log::AuditLogFilter,
+    ) -> persistence::audit_log::AuditLogSearch {
+        self.state.ecs().read_resource::<AuditLog>().search(filter)
+    }
+
+    /// Lists the ids and aliases of the characters belonging to a player in a
+    /// slow job.
+    pub fn list_characters(&self, username: &str) -> Result<CharacterArchiveJob, String> {
+        let uuid = self.username_to_uuid(username)?;
+        let database_settings = self.database_settings.read().unwrap().clone();
+        let username = username.to_owned();
+        Ok(CharacterArchives::spawn(
+            &self.state.slow_job_pool(),
+            move || {
+                let characters = persistence::character_archive::list_characters(
+                    &database_settings,
+                    &uuid.to_string(),
+                )
+                .map_err(|e| format!("Failed to list the characters of {}: {}", username, e))?;
+                Ok(characters.iter().fold(
+                    format!("Characters of {}:", username),
+                    |s, (character_id, alias)| format!("{}\n{}: {}", s, character_id.0, alias),
+                ))
+            },
+        ))
+    }
+
+    /// Exports one of a player's characters to an archive in the
+    /// `character_archives` data directory in a slow job.
+    pub fn export_character(
+        &self,
+        username: &str,
+        character_id: CharacterId,
+    ) -> Result<CharacterArchiveJob, String> {
+        let uuid = self.username_to_uuid(username)?;
+        let database_settings = self.database_settings.read().unwrap().clone();
+        let data_dir = self.data_dir().path.clone();
+        let username = username.to_owned();
+        Ok(CharacterArchives::spawn(
+            &self.state.slow_job_pool(),
+            move || {
+                let path = persistence::character_archive::export_to_file(
+                    &database_settings,
+                    &data_dir,
+                    &uuid.to_string(),
+                    character_id,
+                )
+                .map_err(|e| {
+                    format!(
+                        "Failed to export character {} of {}: {}",
+                        character_id.0, username, e
+                    )
+                })?;
+                info!(?path, "Exported character {}", character_id.0);
+                Ok(format!(
+                    "Exported character {} of {} to {}",
+                    character_id.0,
+                    username,
+                    path.file_name()
+                        .map(|name| name.to_string_lossy())
+                        .unwrap_or_default()
+                ))
+            },
+        ))
+    }
+
+    /// Imports a character archive from the `character_archives` data
+    /// directory as a new character of the given player in a slow job.
+    pub fn import_character(
+        &self,
+        username: &str,
+        file_name: &str,
+    ) -> Result<CharacterArchiveJob, String> {
+        let uuid = self.username_to_uuid(username)?;
+        let database_settings = self.database_settings.read().unwrap().clone();
+        let data_dir = self.data_dir().path.clone();
+        let username = username.to_owned();
+        let file_name = file_name.to_owned();
+        Ok(CharacterArchives::spawn(
+            &self.state.slow_job_pool(),
+            move || {
+                let character_id = persistence::character_archive::import_from_file(
+                    &database_settings,
+                    &data_dir,
+                    &uuid.to_string(),
+                    &file_name,
+                )
+                .map_err(|e| format!("Failed to import {}: {}", file_name, e))?;
+                let msg = format!(
+                    "Imported {} as character {} of {}",
+                    file_name, character_id.0, username
+                );
+                info!("{}", msg);
+                Ok(msg)
+            },
+        ))
+    }
+
+    /// Takes a backup of the database, persisted terrain and rtsim data,
+    /// returning the name of the new backup. The backup is written in a slow
+    /// job.
+    pub fn backup(&mut self) -> Result<String, String> {
+        let ecs = self.state.ecs();
+        let path = ecs.write_resource::<backup::Backups>().take(
+            ecs,
+            &self.data_dir().path,
+            &self.settings().backup,
+            &self.database_settings.read().unwrap(),
+        )?;
+        info!(?path, "Started backup");
+        Ok(path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default())
+    }
+
+    /// Records the session to `path`, so that it can be replayed with
+    /// [`recording::Replay`]. Has to be called before the first tick.
+    pub fn start_recording(&mut self, path: &std::path::Path) -> Result<(), RecordingError> {
+        if self.state.ecs().read_resource::<Tick>().0 > 0 {
+            return Err(RecordingError::SessionStarted);
+        }
+        let recorder = recording::Recorder::create(
+            path,
+            &self.settings(),
+            self.state.ecs().read_resource::<RngSeed>().0,
+        )?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Disconnects the player with the given alias.
+    ///
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn kick_player(&mut self, alias: &str, reason: &str) -> Result<(), String> {
+        let args = vec![alias.to_owned(), reason.to_owned()];
+        let cmd = ServerChatCommand::Kick.keyword();
+        self.audit_console_action(cmd, Some(alias), args, |server| {
+            let entity = (
+                &server.state.ecs().entities(),
+                &server.state.read_storage::<comp::Player>(),
+            )
+                .join()
+                .find(|(_, player)| player.alias == alias)
+                .map(|(entity, _)| entity)
+                .ok_or_else(|| format!("Player {} is not online", alias))?;
+            server.kick_entity(entity, reason);
+            info!("Kicked {} from the server with reason: {}", alias, reason);
+            Ok(())
+        })
+    }
+
+    /// Runs an action taken from the server console or the admin API, and
+    /// records it in the audit log under the name of the equivalent command
+    pub fn audit_console_action<T>(
+        &mut self,
+        cmd: &str,
+        target: Option<&str>,
+        args: Vec<String>,
+        action: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let result = action(self);
+        self.state
+            .ecs()
+            .read_resource::<AuditLog>()
+            .record(AuditLogEntry::console(
+                cmd,
+                target,
+                args,
+                result.as_ref().err().cloned(),
+            ));
+        result
+    }
+
+    fn kick_entity(&mut self, entity: EcsEntity, reason: &str) {
+        self.notify_client(
+            entity,
+            ServerGeneral::Disconnect(DisconnectReason::Kicked(reason.to_string())),
+        );
+        self.state
+            .mut_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::ClientDisconnect(
+                entity,
+                comp::DisconnectReason::Kicked,
+            ));
+    }
+
+    /// Bans a player, permanently if no duration is given, and kicks them if
+    /// they are online.
+    ///
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn ban_player(
+        &mut self,
+        username: &str,
+        reason: String,
+        duration: Option<Duration>,
+    ) -> Result<(), String> {
+        let args = vec![
+            username.to_owned(),
+            reason.clone(),
+            duration.map_or_else(String::new, |d| d.as_secs().to_string()),
+        ];
+        let cmd = ServerChatCommand::Ban.keyword();
+        self.audit_console_action(cmd, Some(username), args, |server| {
+            let uuid = server.username_to_uuid(username)?;
+            let now = chrono::Utc::now();
+            let end_date = duration
+                .and_then(|duration| chrono::Duration::from_std(duration).ok())
+                // On overflow, just make the ban infinite.
+                .and_then(|duration| now.checked_add_signed(duration));
+            let ban = settings::Ban {
+                reason: reason.clone(),
+                info: Some(console_ban_info()),
+                end_date,
+            };
+            let edit = server
+                .editable_settings_mut()
+                .banlist
+                .ban_action(
+                    server.data_dir().as_ref(),
+                    now,
+                    uuid,
+                    username.to_owned(),
+                    settings::BanAction::Ban(ban),
+                    true,
+                )
+                .map(|result| {
+                    (
+                        format!("Added {} to the banlist with reason: {}", username, reason),
+                        result,
+                    )
+                });
+            handle_edit((), edit).ok_or_else(|| format!("Could not ban {}", username))?;
+
+            let entity = (
+                &server.state.ecs().entities(),
+                &server.state.read_storage::<comp::Player>(),
             )
                 .join()
                 .find(|(_, player)| player.uuid() == uuid)
-                .map(|(e, _)| e)
-        }) {
-            // Remove admin component if the player is ingame
-            self.state
-                .ecs()
-                .write_storage::<comp::Admin>()
-                .remove(entity);
-        };
+                .map(|(entity, _)| entity);
+            if let Some(entity) = entity {
+                server.kick_entity(entity, &reason);
+            }
+            Ok(())
+        })
+    }
+
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn unban_player(&mut self, username: &str) -> Result<(), String> {
+        let args = vec![username.to_owned()];
+        let cmd = ServerChatCommand::Unban.keyword();
+        self.audit_console_action(cmd, Some(username), args, |server| {
+            let uuid = server.username_to_uuid(username)?;
+            let edit = server
+                .editable_settings_mut()
+                .banlist
+                .ban_action(
+                    server.data_dir().as_ref(),
+                    chrono::Utc::now(),
+                    uuid,
+                    username.to_owned(),
+                    settings::BanAction::Unban(console_ban_info()),
+                    false,
+                )
+                .map(|result| (format!("{} was successfully unbanned", username), result));
+            handle_edit((), edit).ok_or_else(|| format!("{} is not banned", username))
+        })
+    }
+
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn add_to_whitelist(&mut self, username: &str) -> Result<(), String> {
+        let args = vec!["add".to_owned(), username.to_owned()];
+        let cmd = ServerChatCommand::Whitelist.keyword();
+        self.audit_console_action(cmd, Some(username), args, |server| {
+            let uuid = server.username_to_uuid(username)?;
+            let record = settings::WhitelistRecord {
+                date: chrono::Utc::now(),
+                info: None,
+            };
+            let edit = server.editable_settings_mut().whitelist.edit(
+                server.data_dir().as_ref(),
+                |whitelist| {
+                    whitelist
+                        .insert(uuid, record)
+                        .is_none()
+                        .then(|| format!("Added {} to the whitelist", username))
+                },
+            );
+            handle_edit((), edit).ok_or_else(|| format!("{} is already whitelisted", username))
+        })
+    }
+
+    /// NOTE: Do *not* allow this to be called from any command that doesn't go
+    /// through the CLI!
+    pub fn remove_from_whitelist(&mut self, username: &str) -> Result<(), String> {
+        let args = vec!["remove".to_owned(), username.to_owned()];
+        let cmd = ServerChatCommand::Whitelist.keyword();
+        self.audit_console_action(cmd, Some(username), args, |server| {
+            let uuid = server.username_to_uuid(username)?;
+            let edit = server.editable_settings_mut().whitelist.edit(
+                server.data_dir().as_ref(),
+                |whitelist| {
+                    whitelist
+                        .remove(&uuid)
+                        .map(|_| format!("Removed {} from the whitelist", username))
+                },
+            );
+            handle_edit((), edit).ok_or_else(|| format!("{} is not whitelisted", username))
+        })
+    }
+
+    /// Sets the server description shown to players, removing it if
+    /// `description` is empty
+    pub fn set_motd(&mut self, description: String) -> Result<(), String> {
+        let args = vec![description.clone()];
+        let cmd = ServerChatCommand::SetMotd.keyword();
+        self.audit_console_action(cmd, None, args, |server| {
+            let edit = server.editable_settings_mut().server_description.edit(
+                server.data_dir().as_ref(),
+                |d| {
+                    let info = format!("Server description set to {:?}", description);
+                    **d = description;
+                    Some(info)
+                },
+            );
+            handle_edit((), edit).ok_or_else(|| "Could not set the server description".to_owned())
+        })
+    }
+
+    fn username_to_uuid(&self, username: &str) -> Result<common::uuid::Uuid, String> {
+        self.state
+            .ecs()
+            .fetch::<LoginProvider>()
+            .username_to_uuid(username)
+            .map_err(|err| {
+                error!(
+                    ?err,
+                    "Could not find uuid for this name; either the user does not exist or there \
+                     was an error communicating with the auth server."
+                );
+                format!("Could not find uuid for {}", username)
+            })
     }
 
     /// Useful for testing without a client
@@ -1515,6 +2011,16 @@ impl Drop for Server {
     }
 }
 
+/// Information recorded for bans and unbans made from the CLI, which doesn't
+/// act on behalf of any player
+fn console_ban_info() -> settings::BanInfo {
+    settings::BanInfo {
+        performed_by: common::uuid::Uuid::nil(),
+        performed_by_username: "Server".to_owned(),
+        performed_by_role: comp::AdminRole::Admin.into(),
+    }
+}
+
 #[must_use]
 pub fn handle_edit<T, S: settings::EditableSetting>(
     data: T,
@@ -1,11 +1,11 @@
-use crate::settings::{AdminRecord, BanEntry, WhitelistRecord};
+use crate::settings::{AdminRecord, Banlist, WhitelistRecord};
 use authc::{AuthClient, AuthClientError, AuthToken, Uuid};
 use chrono::Utc;
 use common::comp::AdminRole;
 use common_net::msg::RegisterError;
 use hashbrown::HashMap;
 use specs::Component;
-use std::{str::FromStr, sync::Arc};
+use std::{net::IpAddr, str::FromStr, sync::Arc};
 use tokio::{runtime::Runtime, sync::oneshot};
 use tracing::{error, info};
 
@@ -34,6 +34,13 @@ impl PendingLogin {
 
         Self { pending_r }
     }
+
+    pub(crate) fn new_error(error: RegisterError) -> Self {
+        let (pending_s, pending_r) = oneshot::channel();
+        let _ = pending_s.send(Err(error));
+
+        Self { pending_r }
+    }
 }
 
 impl Component for PendingLogin {
@@ -43,6 +50,9 @@ impl Component for PendingLogin {
 pub struct LoginProvider {
     runtime: Arc<Runtime>,
     auth_server: Option<Arc<AuthClient>>,
+    /// Logins of a replayed session by the placeholder that replaced their
+    /// token, see [`crate::recording`].
+    replayed_logins: Option<HashMap<String, (String, Uuid)>>,
 }
 
 impl LoginProvider {
@@ -65,12 +75,25 @@ impl LoginProvider {
         Self {
             runtime,
             auth_server,
+            replayed_logins: None,
         }
     }
 
+    pub(crate) fn set_replayed_logins(&mut self, logins: HashMap<String, (String, Uuid)>) {
+        self.replayed_logins = Some(logins);
+    }
+
     pub fn verify(&self, username_or_token: &str) -> PendingLogin {
         let (pending_s, pending_r) = oneshot::channel();
 
+        if let Some(logins) = &self.replayed_logins {
+            // Rejected logins weren't recorded, so they are rejected again
+            let _ = pending_s.send(logins.get(username_or_token).cloned().ok_or_else(|| {
+                RegisterError::AuthError("Login failed in the recording".to_string())
+            }));
+            return PendingLogin { pending_r };
+        }
+
         match &self.auth_server {
             // Token from auth server expected
             Some(srv) => {
@@ -95,7 +118,8 @@ impl LoginProvider {
         pending: &mut PendingLogin,
         admins: &HashMap<Uuid, AdminRecord>,
         whitelist: &HashMap<Uuid, WhitelistRecord>,
-        banlist: &HashMap<Uuid, BanEntry>,
+        banlist: &Banlist,
+        remote_ip: Option<IpAddr>,
         player_count_exceeded: impl FnOnce(String, Uuid) -> (bool, R),
     ) -> Option<Result<R, RegisterError>> {
         match pending.pending_r.try_recv() {
@@ -104,10 +128,19 @@ impl LoginProvider {
                 let now = Utc::now();
                 // Hardcoded admins can always log in.
                 let admin = admins.get(&uuid);
-                if let Some(ban) = banlist
+                // Bans of the user take precedence over bans of the address they connect
+                // from, so they get shown the reason they were actually banned for.
+                let bans = banlist
+                    .uuid_bans()
                     .get(&uuid)
                     .and_then(|ban_record| ban_record.current.action.ban())
-                {
+                    .into_iter()
+                    .chain(
+                        remote_ip
+                            .and_then(|ip| banlist.ip_ban(ip))
+                            .and_then(|ban_record| ban_record.current.action.ban()),
+                    );
+                for ban in bans {
                     // Make sure the ban is active, and that we can't override it.
                     //
                     // If we are an admin and our role is at least as high as the role of the
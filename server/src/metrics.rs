@@ -28,6 +28,7 @@ pub struct PlayerMetrics {
     pub clients_connected: IntCounter,
     pub players_connected: IntCounter,
     pub clients_disconnected: IntCounterVec, // timeout, network_error, gracefully
+    pub movement_violations: IntCounterVec,  // teleport, speed, fly, noclip
 }
 
 pub struct NetworkRequestMetrics {
@@ -176,14 +177,24 @@ impl PlayerMetrics {
             &["reason"],
         )?;
 
+        let movement_violations = IntCounterVec::new(
+            Opts::new(
+                "movement_violations",
+                "shows the number of impossible movements reported by clients and their kind",
+            ),
+            &["kind"],
+        )?;
+
         registry.register(Box::new(clients_connected.clone()))?;
         registry.register(Box::new(players_connected.clone()))?;
         registry.register(Box::new(clients_disconnected.clone()))?;
+        registry.register(Box::new(movement_violations.clone()))?;
 
         Ok(Self {
             clients_connected,
             players_connected,
             clients_disconnected,
+            movement_violations,
         })
     }
 }
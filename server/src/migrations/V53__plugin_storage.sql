@@ -0,0 +1,16 @@
+-- Creates tables for the key-value data kept by server plugins
+CREATE TABLE "plugin_storage" (
+      "plugin" TEXT NOT NULL,
+      "key" TEXT NOT NULL,
+      "value" BLOB NOT NULL,
+      PRIMARY KEY("plugin", "key")
+);
+
+CREATE TABLE "plugin_character_storage" (
+      "plugin" TEXT NOT NULL,
+      "character_id" INT NOT NULL,
+      "key" TEXT NOT NULL,
+      "value" BLOB NOT NULL,
+      PRIMARY KEY("plugin", "character_id", "key"),
+      FOREIGN KEY("character_id") REFERENCES "character"("character_id")
+);
@@ -0,0 +1,13 @@
+-- Creates a table recording the privileged commands run on the server
+CREATE TABLE "audit_log" (
+      "audit_log_id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+      "time" INT NOT NULL,
+      "actor_uuid" TEXT NOT NULL,
+      "actor_alias" TEXT NOT NULL,
+      "target" TEXT,
+      "command" TEXT NOT NULL,
+      "args" TEXT NOT NULL,
+      "error" TEXT
+);
+
+CREATE INDEX "idx_audit_log_time" ON "audit_log"("time");
@@ -0,0 +1,605 @@
+//! Validation of the physics state that clients with client-authoritative
+//! physics report for their own entity, to catch teleport, fly, speed and
+//! noclip hacks.
+
+use chrono::{DateTime, Utc};
+use common::{
+    comp::{Body, CharacterState, Collider, PhysicsState, Pos, Stats, Vel},
+    consts::GRAVITY,
+    terrain::Block,
+    uuid::Uuid,
+    vol::ReadVol,
+};
+use common_systems::phys::overlaps_terrain;
+use specs::Component;
+use std::{collections::VecDeque, fmt};
+use vek::*;
+
+// Reminder: review these frequently to ensure they're reasonable
+/// Horizontal speed no movement can exceed.
+const MAX_H_VELOCITY: f32 = 75.0;
+/// Vertical speeds no movement can exceed.
+const MAX_V_VELOCITY: std::ops::Range<f32> = -100.0..80.0;
+/// How far the player is permitted to stray from the correct position (perhaps
+/// due to latency problems).
+const POSITION_THRESHOLD: f32 = 16.0;
+/// How much faster than the approximate maximum speed of their body players
+/// may move on the ground, to account for slopes, ice and similar.
+const SPEED_TOLERANCE: f32 = 2.0;
+/// How much the players may overlap solid blocks, in blocks.
+const CLIP_TOLERANCE: f32 = 0.1;
+/// How far below the player the ground may be to still count as standing on
+/// it, the server's physics state lags a tick behind the reported position.
+const GROUND_DISTANCE: f32 = 1.0;
+/// Extra height and vertical speed the player may have above the highest point
+/// a jump could reach.
+const JUMP_TOLERANCE: f32 = 2.0;
+/// Fraction of gravity the vertical speed of airborne players has to decrease
+/// by at least.
+const GRAVITY_TOLERANCE: f32 = 0.5;
+/// Lower bound for the expected vertical speed of falling players, any body
+/// falls faster than this.
+const MIN_FALL_VELOCITY: f32 = -10.0;
+
+/// A movement that isn't possible without cheating.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+    /// The position changed much more than the velocity allows.
+    Teleport { distance: f32 },
+    /// Moving faster than the body, character state and stats allow.
+    Speed { speed: f32, max: f32 },
+    /// Rising higher or staying in the air longer than a jump allows.
+    Fly { height: f32 },
+    /// Standing inside of solid terrain.
+    NoClip,
+}
+
+impl Violation {
+    /// Short name of the kind of violation, used as metrics label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Violation::Teleport { .. } => "teleport",
+            Violation::Speed { .. } => "speed",
+            Violation::Fly { .. } => "fly",
+            Violation::NoClip => "noclip",
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Teleport { distance } => write!(f, "teleported {:.1} blocks", distance),
+            Violation::Speed { speed, max } => {
+                write!(f, "moved at {:.1} blocks/s (max {:.1})", speed, max)
+            },
+            Violation::Fly { height } => write!(f, "flew {:.1} blocks too high", height),
+            Violation::NoClip => write!(f, "moved into terrain"),
+        }
+    }
+}
+
+/// Everything the server knows about a player that's needed to judge the
+/// movement it reports.
+pub struct MovementContext<'a, T> {
+    pub dt: f32,
+    pub body: Option<&'a Body>,
+    pub stats: Option<&'a Stats>,
+    pub character_state: Option<&'a CharacterState>,
+    pub physics_state: Option<&'a PhysicsState>,
+    pub collider: Option<&'a Collider>,
+    pub scale: f32,
+    pub terrain: &'a T,
+    /// Counter of the player's `ForceUpdate`, physics validated over several
+    /// ticks start over whenever the server moves the player.
+    pub force_counter: u64,
+}
+
+impl<'a, T> MovementContext<'a, T> {
+    fn move_speed_modifier(&self) -> f32 { self.stats.map_or(1.0, |s| s.move_speed_modifier) }
+
+    /// Vertical speed of a jump, see `handle_jump`.
+    fn jump_velocity(&self) -> f32 {
+        self.body
+            .and_then(|body| Some(body.jump_impulse()? / body.mass().0))
+            .map_or(0.0, |v| {
+                v * self.scale.powf(13.0).powf(0.25) * self.move_speed_modifier()
+            })
+    }
+
+    /// Whether the character state moves the player on its own (abilities,
+    /// knockback, gliding, climbing, ...), which isn't validated further.
+    fn is_free_movement(&self) -> bool {
+        !matches!(
+            self.character_state,
+            None | Some(
+                CharacterState::Idle(_)
+                    | CharacterState::Wielding(_)
+                    | CharacterState::Equipping(_)
+                    | CharacterState::GlideWield(_)
+                    | CharacterState::Sit
+                    | CharacterState::Dance
+                    | CharacterState::Talk
+            )
+        )
+    }
+}
+
+/// Checks the hard limits of any movement: speeds no player can reach and
+/// positions that don't match the velocity. Violations of these are always
+/// rejected, whatever the [`MovementValidation`] setting.
+///
+/// [`MovementValidation`]: crate::settings::MovementValidation
+pub fn exceeds_limits(
+    dt: f32,
+    (old_pos, old_vel): (Pos, Vel),
+    (new_pos, new_vel): (Pos, Vel),
+) -> Option<Violation> {
+    let is_velocity_ok = new_vel.0.xy().magnitude_squared() < MAX_H_VELOCITY.powi(2)
+        && MAX_V_VELOCITY.contains(&new_vel.0.z);
+    if !is_velocity_ok {
+        return Some(Violation::Speed {
+            speed: new_vel.0.magnitude(),
+            max: MAX_H_VELOCITY,
+        });
+    }
+
+    // The position can either be sensible with respect to either the old or the
+    // new velocity such that we don't punish for edge cases after a sudden
+    // change
+    let rpos = new_pos.0 - old_pos.0;
+    let is_position_ok = [old_vel.0, new_vel.0].into_iter().any(|ref_vel| {
+        // Determine whether the change in position is broadly consistent with both
+        // the magnitude and direction of the velocity, with appropriate thresholds.
+        LineSegment3 {
+            start: Vec3::zero(),
+            end: ref_vel * dt,
+        }
+        .projected_point(rpos)
+        // + 1.5 accounts for minor changes in position without corresponding
+        // velocity like block hopping/snapping
+        .distance_squared(rpos)
+            < (rpos.magnitude() * 0.5 + 1.5 + POSITION_THRESHOLD).powi(2)
+    });
+    (!is_position_ok).then(|| Violation::Teleport {
+        distance: rpos.magnitude(),
+    })
+}
+
+/// Tracks the movement of a player over several ticks.
+#[derive(Clone, Debug, Default)]
+pub struct MovementTracker {
+    force_counter: u64,
+    /// Highest the player could be right now, it grows with `max_vel_z` while
+    /// the player is in the air. `None` until the player was seen on the
+    /// ground.
+    max_z: Option<f32>,
+    /// Highest vertical speed the player could have right now.
+    max_vel_z: f32,
+}
+
+impl Component for MovementTracker {
+    type Storage = specs::DenseVecStorage<Self>;
+}
+
+impl MovementTracker {
+    /// Checks whether the player could have moved from `old` to `new`.
+    pub fn validate<T: ReadVol<Vox = Block>>(
+        &mut self,
+        ctx: &MovementContext<'_, T>,
+        (old_pos, old_vel): (Pos, Vel),
+        (new_pos, new_vel): (Pos, Vel),
+    ) -> Option<Violation> {
+        if ctx.force_counter != self.force_counter {
+            *self = Self {
+                force_counter: ctx.force_counter,
+                ..Self::default()
+            };
+        }
+
+        if let Some(violation) = exceeds_limits(ctx.dt, (old_pos, old_vel), (new_pos, new_vel)) {
+            return Some(violation);
+        }
+
+        if let Some(collider) = ctx.collider
+            && overlaps_terrain(ctx.terrain, collider, ctx.scale, new_pos.0, CLIP_TOLERANCE)
+        {
+            return Some(Violation::NoClip);
+        }
+
+        let ground_vel = ctx.physics_state.map_or(Vec3::zero(), |p| p.ground_vel);
+        let on_ground = ctx.physics_state.map_or(false, |p| p.on_ground.is_some());
+        let in_liquid = ctx.physics_state.map_or(false, |p| p.in_liquid().is_some());
+        let skating = ctx.physics_state.map_or(false, |p| p.skating_active);
+
+        // Players can't accelerate past the top speed of their body on the
+        // ground, but they may keep speed they got from elsewhere (e.g. when
+        // landing after gliding) until friction slows them down.
+        if let Some(body) = ctx.body
+            && on_ground
+            && !in_liquid
+            && !skating
+            && !ctx.is_free_movement()
+        {
+            let max = body.max_speed_approx() * ctx.move_speed_modifier() * SPEED_TOLERANCE
+                + POSITION_THRESHOLD / 4.0;
+            let speed = (new_vel.0 - ground_vel).xy().magnitude();
+            let old_speed = (old_vel.0 - ground_vel).xy().magnitude();
+            if speed > max && speed > old_speed + body.base_accel() * ctx.dt {
+                return Some(Violation::Speed { speed, max });
+            }
+        }
+
+        // Players that don't touch anything may only rise as high as their
+        // jump takes them, and have to fall afterwards.
+        let supported = on_ground
+            || in_liquid
+            || ctx.physics_state.map_or(false, |p| p.on_wall.is_some())
+            || ctx.is_free_movement()
+            || ctx.body.map_or(false, |b| b.fly_thrust().is_some())
+            || ctx.collider.map_or(false, |collider| {
+                overlaps_terrain(
+                    ctx.terrain,
+                    collider,
+                    ctx.scale,
+                    new_pos.0 - Vec3::unit_z() * GROUND_DISTANCE,
+                    0.0,
+                )
+            });
+        if supported {
+            // Only derived from what the server knows, the reported velocity can't be
+            // trusted. Abilities may launch the player arbitrarily fast, so only the
+            // general speed limit applies to them.
+            self.max_vel_z = if ctx.is_free_movement() {
+                MAX_V_VELOCITY.end
+            } else {
+                ctx.jump_velocity() + ground_vel.z.max(0.0) + JUMP_TOLERANCE
+            };
+            self.max_z = Some(new_pos.0.z + JUMP_TOLERANCE);
+            None
+        } else if let Some(max_z) = &mut self.max_z {
+            self.max_vel_z =
+                (self.max_vel_z - GRAVITY * GRAVITY_TOLERANCE * ctx.dt).max(MIN_FALL_VELOCITY);
+            *max_z += self.max_vel_z * ctx.dt;
+            (new_pos.0.z > *max_z).then_some(Violation::Fly {
+                height: new_pos.0.z - *max_z,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// How many violations are kept for the report.
+const MAX_REPORTED: usize = 200;
+
+pub struct ReportedViolation {
+    /// When the violation last happened.
+    pub time: DateTime<Utc>,
+    pub player_uuid: Uuid,
+    pub player_alias: String,
+    pub pos: Vec3<f32>,
+    pub violation: Violation,
+    /// How many times in a row the player committed this kind of violation.
+    pub count: u32,
+    /// Whether the movement was rejected.
+    pub rubber_banded: bool,
+}
+
+/// The most recent movement violations, for admins to review with
+/// `/movement_report`.
+#[derive(Default)]
+pub struct MovementReport {
+    violations: VecDeque<ReportedViolation>,
+}
+
+impl MovementReport {
+    pub fn record(
+        &mut self,
+        player_uuid: Uuid,
+        player_alias: &str,
+        pos: Vec3<f32>,
+        violation: Violation,
+        rubber_banded: bool,
+    ) {
+        let time = Utc::now();
+        // Cheats usually violate the same rule every tick, so repeated violations are
+        // merged to keep others from getting pushed out of the report.
+        if let Some(i) = self.violations.iter().position(|v| v.player_uuid == player_uuid)
+            && self.violations[i].violation.kind() == violation.kind()
+            && let Some(mut last) = self.violations.remove(i)
+        {
+            last.time = time;
+            last.pos = pos;
+            last.violation = violation;
+            last.count += 1;
+            last.rubber_banded |= rubber_banded;
+            self.violations.push_front(last);
+            return;
+        }
+        if self.violations.len() >= MAX_REPORTED {
+            self.violations.pop_back();
+        }
+        self.violations.push_front(ReportedViolation {
+            time,
+            player_uuid,
+            player_alias: player_alias.to_owned(),
+            pos,
+            violation,
+            count: 1,
+            rubber_banded,
+        });
+    }
+
+    /// The most recent violations first, only those of the player with the
+    /// given alias or UUID if there is one.
+    pub fn search<'a>(
+        &'a self,
+        player: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a ReportedViolation> + 'a {
+        self.violations.iter().filter(move |v| {
+            player.map_or(true, |player| {
+                v.player_alias.eq_ignore_ascii_case(player) || v.player_uuid.to_string() == player
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{comp::humanoid, terrain::BlockKind, volumes::dyna::Dyna};
+
+    const DT: f32 = 1.0 / 30.0;
+
+    fn terrain() -> Dyna<Block, ()> {
+        Dyna::from_fn(Vec3::new(32, 32, 32), (), |pos| {
+            if pos.z == 0 || (pos.x == 20 && pos.z < 4) {
+                Block::new(BlockKind::Rock, Rgb::zero())
+            } else {
+                Block::empty()
+            }
+        })
+    }
+
+    fn validate(
+        tracker: &mut MovementTracker,
+        terrain: &Dyna<Block, ()>,
+        on_ground: bool,
+        old: (Vec3<f32>, Vec3<f32>),
+        new: (Vec3<f32>, Vec3<f32>),
+    ) -> Option<Violation> {
+        let body = Body::Humanoid(humanoid::Body::random());
+        let physics_state = PhysicsState {
+            on_ground: on_ground.then(|| Block::new(BlockKind::Rock, Rgb::zero())),
+            ..PhysicsState::default()
+        };
+        let character_state = CharacterState::default();
+        let collider = body.collider();
+        let ctx = MovementContext {
+            dt: DT,
+            body: Some(&body),
+            stats: None,
+            character_state: Some(&character_state),
+            physics_state: Some(&physics_state),
+            collider: Some(&collider),
+            scale: 1.0,
+            terrain,
+            force_counter: 0,
+        };
+        tracker.validate(&ctx, (Pos(old.0), Vel(old.1)), (Pos(new.0), Vel(new.1)))
+    }
+
+    #[test]
+    fn walking_is_fine() {
+        let terrain = terrain();
+        let mut tracker = MovementTracker::default();
+        let vel = Vec3::new(9.0, 0.0, 0.0);
+        let mut pos = Vec3::new(4.0, 4.0, 1.0);
+        for _ in 0..30 {
+            let new_pos = pos + vel * DT;
+            assert_eq!(
+                validate(&mut tracker, &terrain, true, (pos, vel), (new_pos, vel)),
+                None
+            );
+            pos = new_pos;
+        }
+    }
+
+    #[test]
+    fn teleporting_is_detected() {
+        let terrain = terrain();
+        let mut tracker = MovementTracker::default();
+        let pos = Vec3::new(4.0, 4.0, 1.0);
+        let violation = validate(
+            &mut tracker,
+            &terrain,
+            true,
+            (pos, Vec3::zero()),
+            (pos + Vec3::new(0.0, 40.0, 0.0), Vec3::zero()),
+        );
+        assert!(matches!(violation, Some(Violation::Teleport { .. })));
+    }
+
+    #[test]
+    fn speeding_is_detected() {
+        let terrain = terrain();
+        let mut tracker = MovementTracker::default();
+        let pos = Vec3::new(4.0, 4.0, 1.0);
+        let vel = Vec3::new(60.0, 0.0, 0.0);
+        let violation = validate(
+            &mut tracker,
+            &terrain,
+            true,
+            (pos, Vec3::zero()),
+            (pos + vel * DT, vel),
+        );
+        assert!(matches!(violation, Some(Violation::Speed { .. })));
+    }
+
+    #[test]
+    fn walking_through_walls_is_detected() {
+        let terrain = terrain();
+        let mut tracker = MovementTracker::default();
+        let vel = Vec3::new(9.0, 0.0, 0.0);
+        let violation = validate(
+            &mut tracker,
+            &terrain,
+            true,
+            (Vec3::new(20.0, 4.0, 1.0), vel),
+            (Vec3::new(20.3, 4.0, 1.0), vel),
+        );
+        assert_eq!(violation, Some(Violation::NoClip));
+    }
+
+    #[test]
+    fn jumping_is_fine_but_hovering_is_not() {
+        let terrain = terrain();
+        let mut tracker = MovementTracker::default();
+        let mut pos = Vec3::new(4.0, 4.0, 1.0);
+        assert_eq!(
+            validate(
+                &mut tracker,
+                &terrain,
+                true,
+                (pos, Vec3::zero()),
+                (pos, Vec3::zero())
+            ),
+            None
+        );
+
+        // A regular jump
+        let mut vel = Vec3::new(0.0, 0.0, 10.0);
+        loop {
+            let new_vel = vel - Vec3::unit_z() * GRAVITY * DT;
+            let new_pos = pos + new_vel * DT;
+            if new_pos.z < 1.0 {
+                break;
+            }
+            assert_eq!(
+                validate(
+                    &mut tracker,
+                    &terrain,
+                    false,
+                    (pos, vel),
+                    (new_pos, new_vel)
+                ),
+                None
+            );
+            (pos, vel) = (new_pos, new_vel);
+        }
+
+        // Rising steadily without falling down again
+        let pos = Vec3::new(4.0, 4.0, 1.0);
+        validate(
+            &mut tracker,
+            &terrain,
+            true,
+            (pos, Vec3::zero()),
+            (pos, Vec3::zero()),
+        );
+        let vel = Vec3::new(0.0, 0.0, 2.0);
+        let violation = (1..90).find_map(|i| {
+            let old_pos = pos + vel * DT * (i - 1) as f32;
+            let new_pos = pos + vel * DT * i as f32;
+            validate(
+                &mut tracker,
+                &terrain,
+                false,
+                (old_pos, vel),
+                (new_pos, vel),
+            )
+        });
+        assert!(matches!(violation, Some(Violation::Fly { .. })));
+    }
+
+    #[test]
+    fn launching_from_the_ground_is_detected() {
+        let terrain = terrain();
+        let mut tracker = MovementTracker::default();
+        let pos = Vec3::new(4.0, 4.0, 1.0);
+        // The reported velocity doesn't raise how high the player may get
+        let vel = Vec3::new(0.0, 0.0, 60.0);
+        assert_eq!(
+            validate(
+                &mut tracker,
+                &terrain,
+                true,
+                (pos, Vec3::zero()),
+                (pos, vel)
+            ),
+            None
+        );
+        let violation = (1..30).find_map(|i| {
+            let old_pos = pos + vel * DT * (i - 1) as f32;
+            let new_pos = pos + vel * DT * i as f32;
+            validate(
+                &mut tracker,
+                &terrain,
+                false,
+                (old_pos, vel),
+                (new_pos, vel),
+            )
+        });
+        assert!(matches!(violation, Some(Violation::Fly { .. })));
+    }
+
+    #[test]
+    fn repeated_violations_are_merged() {
+        let mut report = MovementReport::default();
+        let uuid = Uuid::new_v4();
+        for _ in 0..5 {
+            report.record(uuid, "cheater", Vec3::zero(), Violation::NoClip, true);
+        }
+        report.record(
+            Uuid::new_v4(),
+            "other",
+            Vec3::zero(),
+            Violation::NoClip,
+            true,
+        );
+        assert_eq!(report.search(None).count(), 2);
+        let reported = report.search(Some("Cheater")).collect::<Vec<_>>();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].count, 5);
+    }
+
+    #[test]
+    fn merged_violations_move_to_the_front() {
+        let mut report = MovementReport::default();
+        let uuid = Uuid::new_v4();
+        report.record(uuid, "cheater", Vec3::zero(), Violation::NoClip, false);
+        report.record(
+            Uuid::new_v4(),
+            "other",
+            Vec3::zero(),
+            Violation::NoClip,
+            false,
+        );
+        report.record(uuid, "cheater", Vec3::zero(), Violation::NoClip, false);
+        let aliases = report
+            .search(None)
+            .map(|v| v.player_alias.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(aliases, ["cheater", "other"]);
+    }
+
+    #[test]
+    fn limits_are_checked_without_tracking() {
+        let pos = Pos(Vec3::new(4.0, 4.0, 1.0));
+        let still = Vel(Vec3::zero());
+        assert_eq!(exceeds_limits(DT, (pos, still), (pos, still)), None);
+        assert!(matches!(
+            exceeds_limits(
+                DT,
+                (pos, still),
+                (Pos(pos.0 + Vec3::unit_y() * 40.0), still)
+            ),
+            Some(Violation::Teleport { .. })
+        ));
+        assert!(matches!(
+            exceeds_limits(DT, (pos, still), (pos, Vel(Vec3::unit_x() * 100.0))),
+            Some(Violation::Speed { .. })
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+//! Persistence of the audit log, which records the privileged commands run on
+//! the server so that moderators can review them.
+//!
+//! Entries are written to the database and searched in a background thread,
+//! so that neither blocks the server's tick.
+
+use crate::persistence::{
+    error::PersistenceError, establish_connection, ConnectionMode, DatabaseSettings,
+    VelorenConnection,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use common::uuid::Uuid;
+use rusqlite::ToSql;
+use serde::Serialize;
+use specs::Entity as EcsEntity;
+use std::sync::{Arc, RwLock};
+use tracing::{error, trace};
+
+/// A command run by a player with an elevated role, or from the server console
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditLogEntry {
+    pub time: DateTime<Utc>,
+    pub actor_uuid: Uuid,
+    pub actor_alias: String,
+    /// The player the command was run on, if any
+    pub target: Option<String>,
+    pub command: String,
+    pub args: Vec<String>,
+    /// The error the command failed with, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+impl AuditLogEntry {
+    /// The alias that actions taken from the server console or the admin API
+    /// are recorded under, with a nil UUID
+    pub const CONSOLE_ALIAS: &'static str = "<console>";
+
+    /// An action taken from the server console or the admin API, which has no
+    /// player behind it
+    pub fn console(
+        command: &str,
+        target: Option<&str>,
+        args: Vec<String>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            time: Utc::now(),
+            actor_uuid: Uuid::nil(),
+            actor_alias: Self::CONSOLE_ALIAS.to_owned(),
+            target: target.map(str::to_owned),
+            command: command.to_owned(),
+            args,
+            error,
+        }
+    }
+}
+
+/// Restricts the entries returned by [`AuditLog::search`]. Entries are
+/// returned from the most recent to the oldest.
+#[derive(Clone, Debug, Default)]
+pub struct AuditLogFilter {
+    /// Alias or UUID of a player who ran the command or was targeted by it
+    pub player: Option<String>,
+    pub command: Option<String>,
+    pub limit: u32,
+}
+
+impl AuditLogFilter {
+    /// Most entries a search returns
+    pub const MAX_LIMIT: u32 = 100;
+}
+
+/// The result of [`AuditLog::search`], received once the search ran
+pub type AuditLogSearch = crossbeam_channel::Receiver<Result<Vec<AuditLogEntry>, PersistenceError>>;
+
+enum AuditLogRequest {
+    Record(AuditLogEntry),
+    Search(
+        AuditLogFilter,
+        crossbeam_channel::Sender<Result<Vec<AuditLogEntry>, PersistenceError>>,
+    ),
+}
+
+/// A resource for writing to and searching the audit log in a background
+/// thread.
+pub struct AuditLog {
+    request_tx: Option<crossbeam_channel::Sender<AuditLogRequest>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+    /// Searches run on behalf of clients, whose results haven't been sent to
+    /// them yet
+    client_searches: Vec<(EcsEntity, AuditLogSearch)>,
+}
+
+impl AuditLog {
+    pub fn new(settings: Arc<RwLock<DatabaseSettings>>) -> Self {
+        let (request_tx, request_rx) = crossbeam_channel::unbounded::<AuditLogRequest>();
+
+        let builder = std::thread::Builder::new().name("audit_log_updater".into());
+        let handle = builder
+            .spawn(move || {
+                // Unwrap here is safe as there is no code that can panic when the write lock is
+                // taken that could cause the RwLock to become poisoned.
+                let mut conn =
+                    establish_connection(&settings.read().unwrap(), ConnectionMode::ReadWrite);
+                while let Ok(request) = request_rx.recv() {
+                    conn.update_log_mode(&settings);
+                    match request {
+                        AuditLogRequest::Record(entry) => {
+                            if let Err(e) = insert_entry(entry, &conn) {
+                                error!(?e, "Error during audit log update");
+                            }
+                        },
+                        AuditLogRequest::Search(filter, result_tx) => {
+                            // The search may have been given up on
+                            let _ = result_tx.send(search(&filter, &conn));
+                        },
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            request_tx: Some(request_tx),
+            handle: Some(handle),
+            client_searches: Vec::new(),
+        }
+    }
+
+    fn send(&self, request: AuditLogRequest) {
+        if let Err(e) = self
+            .request_tx
+            .as_ref()
+            .expect("Channel is only taken on drop")
+            .send(request)
+        {
+            error!(?e, "Could not send audit log request");
+        }
+    }
+
+    /// Queues an entry to be written to the database
+    pub fn record(&self, entry: AuditLogEntry) { self.send(AuditLogRequest::Record(entry)); }
+
+    /// Queues a search, which includes all entries recorded before it
+    pub fn search(&self, filter: AuditLogFilter) -> AuditLogSearch {
+        let (result_tx, result_rx) = crossbeam_channel::bounded(1);
+        self.send(AuditLogRequest::Search(filter, result_tx));
+        result_rx
+    }
+
+    /// Queues a search whose result is returned by
+    /// [`AuditLog::finished_client_searches`] for the client to be notified
+    pub fn search_for_client(&mut self, client: EcsEntity, filter: AuditLogFilter) {
+        let search = self.search(filter);
+        self.client_searches.push((client, search));
+    }
+
+    /// Takes the results of the searches for clients which finished
+    pub fn finished_client_searches(
+        &mut self,
+    ) -> Vec<(EcsEntity, Result<Vec<AuditLogEntry>, PersistenceError>)> {
+        let mut finished = Vec::new();
+        self.client_searches
+            .retain(|(client, search)| match search.try_recv() {
+                Ok(result) => {
+                    finished.push((*client, result));
+                    false
+                },
+                Err(crossbeam_channel::TryRecvError::Empty) => true,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => false,
+            });
+        finished
+    }
+}
+
+fn search(
+    filter: &AuditLogFilter,
+    connection: &VelorenConnection,
+) -> Result<Vec<AuditLogEntry>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  time,
+                actor_uuid,
+                actor_alias,
+                target,
+                command,
+                args,
+                error
+        FROM    audit_log
+        WHERE   (?1 IS NULL OR actor_alias = ?1 OR actor_uuid = ?1 OR target = ?1)
+        AND     (?2 IS NULL OR command = ?2)
+        ORDER BY audit_log_id DESC
+        LIMIT   ?3",
+    )?;
+
+    let entries = stmt
+        .query_map(
+            [&filter.player as &dyn ToSql, &filter.command, &filter.limit],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get(6)?,
+                ))
+            },
+        )?
+        .map(|row| {
+            let (time, actor_uuid, actor_alias, target, command, args, error) = row?;
+            Ok(AuditLogEntry {
+                time: Utc.timestamp_opt(time, 0).single().ok_or_else(|| {
+                    PersistenceError::ConversionError(format!("Invalid time {}", time))
+                })?,
+                actor_uuid: Uuid::parse_str(&actor_uuid)
+                    .map_err(|e| PersistenceError::ConversionError(e.to_string()))?,
+                actor_alias,
+                target,
+                command,
+                args: serde_json::from_str(&args)?,
+                error,
+            })
+        })
+        .collect::<Result<_, PersistenceError>>()?;
+
+    Ok(entries)
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        drop(self.request_tx.take());
+        if let Err(e) = self.handle.take().unwrap().join() {
+            error!(?e, "Error from joining audit log update thread");
+        }
+    }
+}
+
+fn insert_entry(
+    entry: AuditLogEntry,
+    connection: &VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let args = serde_json::to_string(&entry.args)?;
+    let mut stmt = connection.prepare_cached(
+        "
+        INSERT
+        INTO    audit_log (time, actor_uuid, actor_alias, target, command, args, error)
+        VALUES  (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    stmt.execute([
+        &entry.time.timestamp() as &dyn ToSql,
+        &entry.actor_uuid.to_string(),
+        &entry.actor_alias,
+        &entry.target,
+        &entry.command,
+        &args,
+        &entry.error,
+    ])?;
+
+    trace!("Audit log entry written");
+    Ok(())
+}
@@ -19,9 +19,13 @@ use crate::{
             convert_stats_from_database, convert_waypoint_from_database_json,
             convert_waypoint_to_database_json,
         },
+        character_archive::{
+            ArchivedBody, ArchivedItem, ArchivedPet, ArchivedSkillGroup, CharacterArchive,
+        },
         character_loader::{CharacterCreationResult, CharacterDataResult, CharacterListResult},
         character_updater::PetPersistenceData,
         error::PersistenceError::DatabaseError,
+        json_models::{self, DatabaseAbilitySet},
         EditableComponents, PersistedComponents,
     },
 };
@@ -29,7 +33,8 @@ use common::{
     character::{CharacterId, CharacterItem, MAX_CHARACTERS_PER_PLAYER},
     event::UpdateCharacterMetadata,
 };
-use core::ops::Range;
+use core::{convert::TryFrom, ops::Range};
+use hashbrown::HashMap;
 use rusqlite::{types::Value, Connection, ToSql, Transaction};
 use std::{num::NonZeroU64, rc::Rc};
 use tracing::{debug, error, trace, warn};
@@ -49,6 +54,10 @@ const LOADOUT_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.lo
 const INVENTORY_PSEUDO_CONTAINER_POSITION: &str = "inventory";
 const LOADOUT_PSEUDO_CONTAINER_POSITION: &str = "loadout";
 const WORLD_PSEUDO_CONTAINER_ID: EntityId = 1;
+/// Stand-ins for the pseudo-containers of a character while converting items
+/// from a [`CharacterArchive`]; these never reach the database.
+const ARCHIVE_INVENTORY_CONTAINER_ID: EntityId = -1;
+const ARCHIVE_LOADOUT_CONTAINER_ID: EntityId = -2;
 
 #[derive(Clone, Copy)]
 struct CharacterContainers {
@@ -556,6 +565,299 @@ pub fn create_character(
     load_character_list(uuid, transaction).map(|list| (CharacterId(character_id), list))
 }
 
+/// Collects everything about a character that can be moved to another server
+/// into a [`CharacterArchive`].
+pub fn export_character(
+    player_uuid: &str,
+    char_id: CharacterId,
+    connection: &Connection,
+) -> Result<CharacterArchive, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  c.alias,
+                b.variant,
+                b.body_data
+        FROM    character c
+        JOIN    body b ON (c.character_id = b.body_id)
+        WHERE   c.character_id = ?1
+        AND     c.player_uuid = ?2",
+    )?;
+
+    let (alias, body_variant, body_data) =
+        stmt.query_row([&char_id.0 as &dyn ToSql, &player_uuid], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+    drop(stmt);
+
+    let character_containers = get_pseudo_containers(connection, char_id)?;
+    let inventory_items = load_items(connection, character_containers.inventory_container_id)?;
+    let loadout_items = load_items(connection, character_containers.loadout_container_id)?;
+
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  skill_group_kind,
+                earned_exp,
+                spent_exp,
+                skills,
+                hash_val
+        FROM    skill_group
+        WHERE   entity_id = ?1",
+    )?;
+
+    let skill_groups = stmt
+        .query_map([char_id.0], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Vec<u8>>(4)?,
+            ))
+        })?
+        .map(|row| {
+            let (kind, earned_exp, spent_exp, skills, hash_val) = row?;
+            Ok(ArchivedSkillGroup {
+                kind,
+                earned_exp,
+                spent_exp,
+                skills: serde_json::from_str(&skills)?,
+                hash_val,
+            })
+        })
+        .collect::<Result<Vec<_>, PersistenceError>>()?;
+    drop(stmt);
+
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  ability_sets
+        FROM    ability_set
+        WHERE   entity_id = ?1",
+    )?;
+
+    let ability_sets = stmt.query_row([char_id.0], |row| row.get::<_, String>(0))?;
+    drop(stmt);
+
+    #[rustfmt::skip]
+    let mut stmt = connection.prepare_cached("
+        SELECT  p.name,
+                b.variant,
+                b.body_data
+        FROM    pet p
+        JOIN    body b ON (p.pet_id = b.body_id)
+        WHERE   p.character_id = ?1",
+    )?;
+
+    let pets = stmt
+        .query_map([char_id.0], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .map(|row| {
+            let (name, variant, data) = row?;
+            Ok(ArchivedPet {
+                name,
+                body: ArchivedBody {
+                    variant,
+                    data: serde_json::from_str(&data)?,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, PersistenceError>>()?;
+    drop(stmt);
+
+    // Renumber the items, so that the archive doesn't depend on the ids used in
+    // this database.  The items are topologically sorted, so parents are always
+    // numbered before their components.
+    let mut archive_ids = HashMap::new();
+    let mut archive_items = |items: Vec<Item>, container_id: EntityId| {
+        items
+            .into_iter()
+            .map(|item| {
+                let id = archive_ids.len() as u64 + 1;
+                archive_ids.insert(item.item_id, id);
+                let parent = if item.parent_container_item_id == container_id {
+                    None
+                } else {
+                    Some(
+                        *archive_ids
+                            .get(&item.parent_container_item_id)
+                            .ok_or_else(|| {
+                                PersistenceError::ConversionError(format!(
+                                    "Couldn't find parent item {} before item {}",
+                                    item.parent_container_item_id, item.item_id
+                                ))
+                            })?,
+                    )
+                };
+                Ok(ArchivedItem {
+                    id,
+                    parent,
+                    item_definition_id: item.item_definition_id,
+                    stack_size: item.stack_size,
+                    position: item.position,
+                    properties: serde_json::from_str(&item.properties)?,
+                })
+            })
+            .collect::<Result<Vec<_>, PersistenceError>>()
+    };
+    let inventory = archive_items(inventory_items, character_containers.inventory_container_id)?;
+    let loadout = archive_items(loadout_items, character_containers.loadout_container_id)?;
+
+    Ok(CharacterArchive {
+        alias,
+        body: ArchivedBody {
+            variant: body_variant,
+            data: serde_json::from_str(&body_data)?,
+        },
+        skill_groups,
+        ability_sets: serde_json::from_str(&ability_sets)?,
+        inventory,
+        loadout,
+        pets,
+    })
+}
+
+/// Creates a new character for the player with the given uuid from a
+/// [`CharacterArchive`].
+///
+/// Everything in the archive is converted the same way as when loading a
+/// character before anything is written, so archives from servers with
+/// different assets (e.g. items which don't exist here) are rejected.
+pub fn import_character(
+    uuid: &str,
+    archive: &CharacterArchive,
+    transaction: &mut Transaction,
+) -> Result<CharacterId, PersistenceError> {
+    let database_items = |items: &[ArchivedItem], container_id: EntityId| {
+        let entity_id = |id: u64| {
+            EntityId::try_from(id)
+                .ok()
+                .filter(|id| *id > 0)
+                .ok_or_else(|| PersistenceError::ConversionError(format!("Invalid item id {}", id)))
+        };
+        items
+            .iter()
+            .map(|item| {
+                Ok(Item {
+                    item_id: entity_id(item.id)?,
+                    parent_container_item_id: item.parent.map_or(Ok(container_id), entity_id)?,
+                    item_definition_id: item.item_definition_id.clone(),
+                    stack_size: item.stack_size,
+                    position: item.position.clone(),
+                    properties: item.properties.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, PersistenceError>>()
+    };
+    let inventory_items = database_items(&archive.inventory, ARCHIVE_INVENTORY_CONTAINER_ID)?;
+    let loadout_items = database_items(&archive.loadout, ARCHIVE_LOADOUT_CONTAINER_ID)?;
+
+    // This loads every item from the local assets, so it also checks that they all
+    // exist on this server.
+    let inventory = convert_inventory_from_database_items(
+        ARCHIVE_INVENTORY_CONTAINER_ID,
+        &inventory_items,
+        ARCHIVE_LOADOUT_CONTAINER_ID,
+        &loadout_items,
+    )?;
+    // The items still carry the ids from the archive, which may already be in use
+    // in this database, so clear them to have new ones assigned when they are
+    // inserted.
+    fn clear_item_id(item: &comp::Item) {
+        item.get_item_id_for_database().store(None);
+        item.components().iter().for_each(clear_item_id);
+    }
+    inventory
+        .slots_with_id()
+        .filter_map(|(_, item)| item.as_ref())
+        .chain(
+            inventory
+                .loadout_items_with_persistence_key()
+                .filter_map(|(_, item)| item),
+        )
+        .for_each(clear_item_id);
+
+    let body = convert_body_from_database(&archive.body.variant, &archive.body.data.to_string())?;
+
+    let skill_groups = archive
+        .skill_groups
+        .iter()
+        .map(|skill_group| {
+            // Unknown skill groups would otherwise make the conversion panic.
+            json_models::try_db_string_to_skill_group(&skill_group.kind).ok_or_else(|| {
+                PersistenceError::ConversionError(format!(
+                    "Unknown skill group: {}",
+                    skill_group.kind
+                ))
+            })?;
+            Ok(SkillGroup {
+                entity_id: 0,
+                skill_group_kind: skill_group.kind.clone(),
+                earned_exp: skill_group.earned_exp,
+                spent_exp: skill_group.spent_exp,
+                skills: skill_group.skills.to_string(),
+                hash_val: skill_group.hash_val.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, PersistenceError>>()?;
+    let (skill_set, skill_set_persistence_load_error) =
+        convert_skill_set_from_database(&skill_groups);
+    if let Some(error) = skill_set_persistence_load_error {
+        warn!(
+            ?error,
+            "Skills of imported character {} could not be restored, and were refunded",
+            archive.alias
+        );
+    }
+
+    serde_json::from_value::<Vec<DatabaseAbilitySet>>(archive.ability_sets.clone())?;
+    let active_abilities = convert_active_abilities_from_database(&AbilitySets {
+        entity_id: 0,
+        ability_sets: archive.ability_sets.to_string(),
+    });
+
+    let pets = archive
+        .pets
+        .iter()
+        .map(|pet| {
+            let body = convert_body_from_database(&pet.body.variant, &pet.body.data.to_string())?;
+            Ok((
+                comp::Pet::default(),
+                body,
+                comp::Stats::new(pet.name.clone(), body),
+            ))
+        })
+        .collect::<Result<Vec<_>, PersistenceError>>()?;
+
+    let (character_id, _) = create_character(
+        uuid,
+        &archive.alias,
+        PersistedComponents {
+            body,
+            stats: convert_stats_from_database(archive.alias.clone(), body),
+            skill_set,
+            inventory,
+            // Positions are meaningless in another world.
+            waypoint: None,
+            // Pets are inserted separately below, since `create_character` ignores them.
+            pets: Vec::new(),
+            active_abilities,
+            map_marker: None,
+        },
+        transaction,
+    )?;
+    update_pets(character_id, pets, transaction)?;
+
+    Ok(character_id)
+}
+
 pub fn edit_character(
     editable_components: EditableComponents,
     transaction: &mut Transaction,
@@ -606,12 +908,13 @@ pub fn edit_character(
     char_list.map(|list| (character_id, list))
 }
 
-/// Permanently deletes a character
+/// Permanently deletes a character, returns whether it existed and belonged to
+/// the requesting player
 pub fn delete_character(
     requesting_player_uuid: &str,
     char_id: CharacterId,
     transaction: &mut Transaction,
-) -> Result<(), PersistenceError> {
+) -> Result<bool, PersistenceError> {
     debug!(?requesting_player_uuid, ?char_id, "Deleting character");
 
     let mut stmt = transaction.prepare_cached(
@@ -631,7 +934,7 @@ pub fn delete_character(
     if result != 1 {
         // The character does not exist, or does not belong to the requesting player so
         // silently drop the request.
-        return Ok(());
+        return Ok(false);
     }
 
     // Delete skill groups
@@ -664,6 +967,17 @@ pub fn delete_character(
     stmt.execute([&char_id.0])?;
     drop(stmt);
 
+    // Delete values stored by plugins
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    plugin_character_storage
+        WHERE   character_id = ?1",
+    )?;
+
+    stmt.execute([&char_id.0])?;
+    drop(stmt);
+
     // Delete character
     let mut stmt = transaction.prepare_cached(
         "
@@ -718,7 +1032,7 @@ pub fn delete_character(
         )));
     }
 
-    Ok(())
+    Ok(true)
 }
 
 /// Before creating a character, we ensure that the limit on the number of
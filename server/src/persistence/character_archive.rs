@@ -0,0 +1,239 @@
+//! Portable character archives, used to move characters between servers.
+//!
+//! An archive holds everything about a character that isn't tied to the world
+//! it was played in: its body, skill groups, ability sets, inventory and
+//! loadout items (with their properties) and pets.  Waypoints and map markers
+//! are left out, since they make no sense in another world.
+//!
+//! Archives are versioned the same way as the editable settings files: the
+//! data embedded in them uses the database's JSON formats, so if one of those
+//! changes, add a new archive version along with a migration from the
+//! previous one.
+
+use crate::persistence::{
+    character::{export_character, import_character, load_character_list},
+    error::PersistenceError,
+    establish_connection, ConnectionMode, DatabaseSettings,
+};
+use common::{character::CharacterId, slowjob::SlowJobPool};
+use serde::{Deserialize, Serialize};
+use specs::Entity as EcsEntity;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Subdirectory of the server's data directory which archives are written to
+/// and read from.
+const ARCHIVE_DIR: &str = "character_archives";
+
+/// The outcome of a job started with [`CharacterArchives::spawn`], described
+/// for whoever started it, received once the job ran
+pub type CharacterArchiveJob = crossbeam_channel::Receiver<Result<String, String>>;
+
+/// A resource for listing, exporting and importing characters in slow jobs, so
+/// that the database and file IO doesn't hold up the tick.
+#[derive(Default)]
+pub struct CharacterArchives {
+    /// Jobs run on behalf of clients, whose outcome hasn't been sent to them
+    /// yet
+    client_jobs: Vec<(EcsEntity, CharacterArchiveJob)>,
+}
+
+impl CharacterArchives {
+    /// Runs `f` in a slow job, one at a time so that imports can't race each
+    /// other
+    pub fn spawn<F>(slow_jobs: &SlowJobPool, f: F) -> CharacterArchiveJob
+    where
+        F: FnOnce() -> Result<String, String> + Send + Sync + 'static,
+    {
+        let (result_tx, result_rx) = crossbeam_channel::bounded(1);
+        slow_jobs.spawn("CHARACTER_ARCHIVE", move || {
+            // The job may have been given up on
+            let _ = result_tx.send(f());
+        });
+        result_rx
+    }
+
+    /// Keeps track of a job whose outcome is returned by
+    /// [`CharacterArchives::finished_client_jobs`] for the client to be
+    /// notified
+    pub fn push_client_job(&mut self, client: EcsEntity, job: CharacterArchiveJob) {
+        self.client_jobs.push((client, job));
+    }
+
+    /// Takes the outcomes of the jobs for clients which finished
+    pub fn finished_client_jobs(&mut self) -> Vec<(EcsEntity, Result<String, String>)> {
+        let mut finished = Vec::new();
+        self.client_jobs
+            .retain(|(client, job)| match job.try_recv() {
+                Ok(result) => {
+                    finished.push((*client, result));
+                    false
+                },
+                Err(crossbeam_channel::TryRecvError::Empty) => true,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                    finished.push((*client, Err("The job was aborted".to_owned())));
+                    false
+                },
+            });
+        finished
+    }
+}
+
+/// NOTE: Always replace this with the latest archive version, then update
+/// [`CharacterArchiveRaw`] and [`CharacterArchiveRaw::into_latest`].
+pub use self::v1::*;
+
+/// Versioned archives, one per version.
+#[derive(Deserialize, Serialize)]
+pub enum CharacterArchiveRaw {
+    V1(CharacterArchive),
+}
+
+impl From<CharacterArchive> for CharacterArchiveRaw {
+    fn from(value: CharacterArchive) -> Self {
+        // Replace variant with that of current latest version.
+        Self::V1(value)
+    }
+}
+
+impl CharacterArchiveRaw {
+    /// Migrates an archive of any version to the latest one.
+    pub fn into_latest(self) -> CharacterArchive {
+        match self {
+            // Latest version (move old versions into a migration chain, as is done for the
+            // settings files, when it is no longer latest).
+            Self::V1(value) => value,
+        }
+    }
+}
+
+mod v1 {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct CharacterArchive {
+        pub alias: String,
+        pub body: ArchivedBody,
+        pub skill_groups: Vec<ArchivedSkillGroup>,
+        pub ability_sets: serde_json::Value,
+        /// Items in the inventory, topologically sorted so that parents come
+        /// before their components.
+        pub inventory: Vec<ArchivedItem>,
+        /// Items in the loadout, sorted the same way as `inventory`.
+        pub loadout: Vec<ArchivedItem>,
+        pub pets: Vec<ArchivedPet>,
+    }
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct ArchivedBody {
+        pub variant: String,
+        pub data: serde_json::Value,
+    }
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct ArchivedSkillGroup {
+        pub kind: String,
+        pub earned_exp: i64,
+        pub spent_exp: i64,
+        pub skills: serde_json::Value,
+        pub hash_val: Vec<u8>,
+    }
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct ArchivedItem {
+        /// Identifies the item within the archive only; items get new ids when
+        /// they are imported.
+        pub id: u64,
+        /// The item this is a component of, or `None` if the item sits
+        /// directly in the inventory or loadout.
+        pub parent: Option<u64>,
+        pub item_definition_id: String,
+        pub stack_size: i32,
+        pub position: String,
+        pub properties: serde_json::Value,
+    }
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct ArchivedPet {
+        pub name: String,
+        pub body: ArchivedBody,
+    }
+}
+
+fn archive_dir(data_dir: &Path) -> PathBuf { data_dir.join(ARCHIVE_DIR) }
+
+/// Lists the ids and aliases of the characters belonging to a player.
+pub fn list_characters(
+    settings: &DatabaseSettings,
+    player_uuid: &str,
+) -> Result<Vec<(CharacterId, String)>, PersistenceError> {
+    let conn = establish_connection(settings, ConnectionMode::ReadOnly);
+    Ok(load_character_list(player_uuid, &conn)?
+        .into_iter()
+        .filter_map(|item| Some((item.character.id?, item.character.alias)))
+        .collect())
+}
+
+/// Exports a character belonging to the player with the given uuid to a new
+/// archive in the data directory, returning the path of the archive.
+///
+/// NOTE: Changes made since the character was last persisted are not included,
+/// so it's best to export characters which aren't currently being played.
+pub fn export_to_file(
+    settings: &DatabaseSettings,
+    data_dir: &Path,
+    player_uuid: &str,
+    character_id: CharacterId,
+) -> Result<PathBuf, PersistenceError> {
+    let conn = establish_connection(settings, ConnectionMode::ReadOnly);
+    let archive = export_character(player_uuid, character_id, &conn)?;
+
+    let dir = archive_dir(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| PersistenceError::OtherError(e.to_string()))?;
+    let alias = archive
+        .alias
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>();
+    let path = dir.join(format!(
+        "{}-{}-{}.json",
+        alias,
+        character_id.0,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    let file = fs::File::create(&path).map_err(|e| PersistenceError::OtherError(e.to_string()))?;
+    serde_json::to_writer_pretty(file, &CharacterArchiveRaw::from(archive))?;
+
+    Ok(path)
+}
+
+/// Imports the archive `file_name` from the data directory as a new character
+/// belonging to the player with the given uuid.
+///
+/// The archive is validated (including checking that every item exists in the
+/// local assets) before anything is written to the database.
+pub fn import_from_file(
+    settings: &DatabaseSettings,
+    data_dir: &Path,
+    player_uuid: &str,
+    file_name: &str,
+) -> Result<CharacterId, PersistenceError> {
+    if file_name.contains(['/', '\\']) || file_name.starts_with('.') {
+        return Err(PersistenceError::OtherError(format!(
+            "Invalid archive name {}, it must be a file in the {} directory",
+            file_name, ARCHIVE_DIR
+        )));
+    }
+    let file = fs::File::open(archive_dir(data_dir).join(file_name))
+        .map_err(|e| PersistenceError::OtherError(format!("{}: {}", file_name, e)))?;
+    let archive = serde_json::from_reader::<_, CharacterArchiveRaw>(file)?.into_latest();
+
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+    let mut transaction = conn.connection.transaction()?;
+    let character_id = import_character(player_uuid, &archive, &mut transaction)?;
+    transaction.commit()?;
+
+    Ok(character_id)
+}
@@ -35,6 +35,8 @@ enum CharacterLoaderRequestKind {
 pub enum CharacterUpdaterMessage {
     CharacterScreenResponse(CharacterScreenResponse),
     DatabaseBatchCompletion(u64),
+    /// A character was permanently deleted
+    CharacterDeleted(CharacterId),
 }
 
 /// An event emitted from CharacterUpdater in response to a request made from
@@ -127,14 +127,25 @@ impl CharacterUpdater {
                             }
                             conn.update_log_mode(&settings);
 
-                            if let Err(e) = execute_batch_update(updates.into_iter(), &mut conn) {
-                                error!(
-                                    ?e,
-                                    "Error during character batch update, disconnecting all \
-                                     clients to avoid loss of data integrity."
-                                );
-                                disconnect_all_clients_requested_clone
-                                    .store(true, Ordering::Relaxed);
+                            match execute_batch_update(updates.into_iter(), &mut conn) {
+                                Ok(deleted) => {
+                                    for character_id in deleted {
+                                        if let Err(e) = response_tx.send(
+                                            CharacterUpdaterMessage::CharacterDeleted(character_id),
+                                        ) {
+                                            error!(?e, "Could not send CharacterDeleted message");
+                                        }
+                                    }
+                                },
+                                Err(e) => {
+                                    error!(
+                                        ?e,
+                                        "Error during character batch update, disconnecting all \
+                                         clients to avoid loss of data integrity."
+                                    );
+                                    disconnect_all_clients_requested_clone
+                                        .store(true, Ordering::Relaxed);
+                                },
                             };
 
                             if let Err(e) = response_tx
@@ -409,13 +420,15 @@ impl CharacterUpdater {
     pub fn messages(&self) -> TryIter<CharacterUpdaterMessage> { self.response_rx.try_iter() }
 }
 
+/// Returns the characters that were deleted
 fn execute_batch_update(
     updates: impl Iterator<Item = DatabaseActionKind>,
     connection: &mut VelorenConnection,
-) -> Result<(), PersistenceError> {
+) -> Result<Vec<CharacterId>, PersistenceError> {
     let mut transaction = connection.connection.transaction()?;
     transaction.set_drop_behavior(DropBehavior::Rollback);
     trace!("Transaction started for character batch update");
+    let mut deleted = Vec::new();
     updates.into_iter().try_for_each(|event| match event {
         DatabaseActionKind::UpdateCharacter(box (
             character_id,
@@ -438,17 +451,22 @@ fn execute_batch_update(
         DatabaseActionKind::DeleteCharacter {
             requesting_player_uuid,
             character_id,
-        } => super::character::delete_character(
-            &requesting_player_uuid,
-            character_id,
-            &mut transaction,
-        ),
+        } => {
+            if super::character::delete_character(
+                &requesting_player_uuid,
+                character_id,
+                &mut transaction,
+            )? {
+                deleted.push(character_id);
+            }
+            Ok(())
+        },
     })?;
 
     transaction.commit()?;
 
     trace!("Commit for character batch update completed");
-    Ok(())
+    Ok(deleted)
 }
 
 fn execute_character_create(
@@ -96,8 +96,21 @@ pub fn skill_group_to_db_string(skill_group: comp::skillset::SkillGroupKind) ->
 }
 
 pub fn db_string_to_skill_group(skill_group_string: &str) -> comp::skillset::SkillGroupKind {
+    try_db_string_to_skill_group(skill_group_string).unwrap_or_else(|| {
+        panic!(
+            "Tried to convert an unsupported string from the database: {}",
+            skill_group_string
+        )
+    })
+}
+
+/// Like [`db_string_to_skill_group`], but for strings which don't necessarily
+/// come from this server's database.
+pub fn try_db_string_to_skill_group(
+    skill_group_string: &str,
+) -> Option<comp::skillset::SkillGroupKind> {
     use comp::{item::tool::ToolKind, skillset::SkillGroupKind::*};
-    match skill_group_string {
+    Some(match skill_group_string {
         "General" => General,
         "Weapon Sword" => Weapon(ToolKind::Sword),
         "Weapon Axe" => Weapon(ToolKind::Axe),
@@ -107,11 +120,8 @@ pub fn db_string_to_skill_group(skill_group_string: &str) -> comp::skillset::Ski
         "Weapon Sceptre" => Weapon(ToolKind::Sceptre),
         "Weapon Pick" => Weapon(ToolKind::Pick),
 
-        _ => panic!(
-            "Tried to convert an unsupported string from the database: {}",
-            skill_group_string
-        ),
-    }
+        _ => return None,
+    })
 }
 
 #[derive(Serialize, Deserialize)]
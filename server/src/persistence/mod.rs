@@ -1,21 +1,28 @@
 //! DB operations and schema migrations
 
+pub mod audit_log;
 pub(in crate::persistence) mod character;
+pub mod character_archive;
 pub mod character_loader;
 pub mod character_updater;
 mod diesel_to_rusqlite;
 pub mod error;
 mod json_models;
 mod models;
+#[cfg(feature = "plugins")]
+pub mod plugin_storage;
 
 use crate::persistence::character_updater::PetPersistenceData;
 use common::comp;
 use refinery::Report;
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{
+    backup::{Backup, StepResult},
+    Connection, OpenFlags,
+};
 use std::{
     fs,
     ops::Deref,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, RwLock},
     time::Duration,
 };
@@ -186,6 +193,43 @@ pub fn vacuum_database(settings: &DatabaseSettings) {
     info!("Database vacuumed");
 }
 
+/// A read transaction on the database, which pins its contents at the moment
+/// the snapshot was taken, so they can be copied elsewhere later (e.g. from
+/// another thread) while the server keeps writing to the database.
+pub struct DatabaseSnapshot {
+    connection: VelorenConnection,
+}
+
+impl DatabaseSnapshot {
+    pub fn new(settings: &DatabaseSettings) -> Result<Self, error::PersistenceError> {
+        let connection = establish_connection(settings, ConnectionMode::ReadOnly);
+        // In WAL mode, the first read in a transaction decides which version of the
+        // database the rest of the transaction sees.
+        connection.execute_batch("BEGIN DEFERRED")?;
+        connection.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        Ok(Self { connection })
+    }
+
+    /// Writes the snapshot to a new database file using SQLite's online
+    /// backup API.
+    pub fn write_to(self, path: &Path) -> Result<(), error::PersistenceError> {
+        let mut destination = Connection::open(path)?;
+        let backup = Backup::new(&self.connection, &mut destination)?;
+        // Copy all pages in a single step; the read transaction keeps other
+        // connections' writes from showing up in the copy.
+        loop {
+            match backup.step(-1)? {
+                StepResult::Done => break,
+                StepResult::More => {},
+                _ => std::thread::sleep(Duration::from_millis(100)),
+            }
+        }
+        Ok(())
+    }
+}
+
 // These callbacks use info logging because they are never enabled by default,
 // only when explicitly turned on via CLI arguments or interactive CLI commands.
 // Setting them to anything other than info would remove the ability to get SQL
@@ -0,0 +1,199 @@
+//! Persistence of the key-value data kept by server plugins.
+//!
+//! The whole storage is loaded into the [`PluginStorage`] resource on startup
+//! so that plugins can read it synchronously, and the changes they make are
+//! written back to the database in a background thread.
+
+use crate::persistence::{
+    error::PersistenceError, establish_connection, ConnectionMode, DatabaseSettings,
+    VelorenConnection,
+};
+use common::character::CharacterId;
+use common_state::plugin::storage::{PluginStorage, StorageKey};
+use rusqlite::{Connection, DropBehavior, ToSql};
+use std::sync::{Arc, RwLock};
+use tracing::{error, trace};
+
+/// A change to a single value of the [`PluginStorage`]
+pub enum StorageChange {
+    Set(StorageKey, Vec<u8>),
+    Delete(StorageKey),
+}
+
+/// Loads every value stored by plugins, both global and attached to
+/// characters
+pub fn load_plugin_storage(connection: &Connection) -> Result<PluginStorage, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  plugin,
+                NULL,
+                key,
+                value
+        FROM    plugin_storage
+        UNION ALL
+        SELECT  plugin,
+                character_id,
+                key,
+                value
+        FROM    plugin_character_storage",
+    )?;
+
+    let storage = stmt
+        .query_map([], |row| {
+            Ok((
+                StorageKey {
+                    plugin: row.get(0)?,
+                    character_id: row.get::<_, Option<i64>>(1)?.map(CharacterId),
+                    key: row.get(2)?,
+                },
+                row.get(3)?,
+            ))
+        })?
+        .collect::<Result<PluginStorage, _>>()?;
+
+    Ok(storage)
+}
+
+/// A unidirectional messaging resource for saving plugin storage changes in a
+/// background thread.
+pub struct PluginStorageUpdater {
+    update_tx: Option<crossbeam_channel::Sender<Vec<StorageChange>>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl PluginStorageUpdater {
+    pub fn new(settings: Arc<RwLock<DatabaseSettings>>) -> rusqlite::Result<Self> {
+        let (update_tx, update_rx) = crossbeam_channel::unbounded::<Vec<StorageChange>>();
+
+        let builder = std::thread::Builder::new().name("plugin_storage_updater".into());
+        let handle = builder
+            .spawn(move || {
+                // Unwrap here is safe as there is no code that can panic when the write lock is
+                // taken that could cause the RwLock to become poisoned.
+                let mut conn =
+                    establish_connection(&settings.read().unwrap(), ConnectionMode::ReadWrite);
+                while let Ok(changes) = update_rx.recv() {
+                    conn.update_log_mode(&settings);
+                    if let Err(e) = execute_changes(changes, &mut conn) {
+                        error!(?e, "Error during plugin storage update");
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(Self {
+            update_tx: Some(update_tx),
+            handle: Some(handle),
+        })
+    }
+
+    /// Queues a batch of changes to be written to the database in a single
+    /// transaction
+    pub fn submit(&self, changes: Vec<StorageChange>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .expect("Channel is only taken on drop")
+            .send(changes)
+        {
+            error!(?e, "Could not send plugin storage changes");
+        }
+    }
+}
+
+impl Drop for PluginStorageUpdater {
+    fn drop(&mut self) {
+        drop(self.update_tx.take());
+        if let Err(e) = self.handle.take().unwrap().join() {
+            error!(?e, "Error from joining plugin storage update thread");
+        }
+    }
+}
+
+fn execute_changes(
+    changes: Vec<StorageChange>,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    trace!("Transaction started for plugin storage update");
+
+    for change in changes {
+        match change {
+            StorageChange::Set(
+                StorageKey {
+                    plugin,
+                    character_id: None,
+                    key,
+                },
+                value,
+            ) => {
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    REPLACE
+                    INTO    plugin_storage (plugin, key, value)
+                    VALUES  (?1, ?2, ?3)",
+                )?;
+                stmt.execute([&plugin as &dyn ToSql, &key, &value])?;
+            },
+            StorageChange::Set(
+                StorageKey {
+                    plugin,
+                    character_id: Some(character_id),
+                    key,
+                },
+                value,
+            ) => {
+                // Characters can be deleted while a plugin still holds onto their id, so
+                // only attach values to characters that still exist
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    REPLACE
+                    INTO    plugin_character_storage (plugin, character_id, key, value)
+                    SELECT  ?1, character_id, ?3, ?4
+                    FROM    character
+                    WHERE   character_id = ?2",
+                )?;
+                stmt.execute([&plugin as &dyn ToSql, &character_id.0, &key, &value])?;
+            },
+            StorageChange::Delete(StorageKey {
+                plugin,
+                character_id: None,
+                key,
+            }) => {
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    DELETE
+                    FROM    plugin_storage
+                    WHERE   plugin = ?1
+                    AND     key = ?2",
+                )?;
+                stmt.execute([&plugin, &key])?;
+            },
+            StorageChange::Delete(StorageKey {
+                plugin,
+                character_id: Some(character_id),
+                key,
+            }) => {
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    DELETE
+                    FROM    plugin_character_storage
+                    WHERE   plugin = ?1
+                    AND     character_id = ?2
+                    AND     key = ?3",
+                )?;
+                stmt.execute([&plugin as &dyn ToSql, &character_id.0, &key])?;
+            },
+        }
+    }
+
+    transaction.commit()?;
+
+    trace!("Commit for plugin storage update completed");
+    Ok(())
+}
@@ -0,0 +1,381 @@
+//! Dispatch of game events to server plugins, and application of the
+//! [`Action`]s they emit.
+//!
+//! Plugins only get read access to the ECS while their event handlers run, so
+//! every mutation they request is queued by the [`PluginMgr`] and applied here
+//! once per tick, before the rest of the server events are handled.
+use crate::{
+    cmd,
+    persistence::plugin_storage::{PluginStorageUpdater, StorageChange},
+    state_ext::StateExt,
+    sys::terrain::NpcData,
+    Server,
+};
+use common::{
+    cmd::KitSpec,
+    comp::{self, Buff, BuffChange, BuffData, BuffSource, ChatType},
+    event::{EventBus, NpcBuilder, ServerEvent},
+    generation::{EntityConfig, EntityInfo},
+    resources::{GameMode, Secs, Time},
+    uid::{IdMaps, Uid},
+    util::Dir,
+};
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use common_state::{
+    plugin::{
+        memory_manager::EcsWorld,
+        storage::{PluginStorage, StorageKey},
+        PluginMgr,
+    },
+    State,
+};
+use plugin_api::{
+    event::{ChatMessageEvent, ChatMessageResult},
+    Action, Event,
+};
+use rand::thread_rng;
+use specs::{Entity as EcsEntity, Join, WorldExt};
+use tracing::{error, info, warn};
+
+/// Give `f` the [`PluginMgr`] along with the read access to the ECS that
+/// plugins need while they run.
+///
+/// This must not be called while the caller holds a write borrow on any of the
+/// components exposed through [`EcsWorld`].
+fn with_plugins<R>(state: &State, f: impl FnOnce(&mut PluginMgr, &EcsWorld) -> R) -> R {
+    let ecs = state.ecs();
+    let ecs_world = EcsWorld {
+        entities: &ecs.entities(),
+        health: ecs.read_component().into(),
+        uid: ecs.read_component().into(),
+        id_maps: &ecs.read_resource::<IdMaps>().into(),
+        player: ecs.read_component().into(),
+        pos: ecs.read_component().into(),
+        stats: ecs.read_component().into(),
+        buffs: ecs.read_component().into(),
+        inventory: ecs.read_component().into(),
+        storage: &ecs.read_resource::<PluginStorage>().into(),
+    };
+    f(&mut ecs.write_resource::<PluginMgr>(), &ecs_world)
+}
+
+/// Run the handlers every loaded plugin registered for `event` and collect
+/// their responses.
+pub(crate) fn dispatch_event<T: Event>(state: &State, event: &T) -> Vec<T::Response> {
+    with_plugins(state, |plugin_mgr, ecs_world| {
+        plugin_mgr
+            .execute_event(ecs_world, event)
+            .unwrap_or_else(|e| {
+                error!(
+                    ?e,
+                    "Failed to dispatch {} to plugins",
+                    event.get_event_name()
+                );
+                Vec::new()
+            })
+    })
+}
+
+/// Let plugins cancel or rewrite a chat message before it is broadcast.
+///
+/// Only plain-text messages sent by players are passed to plugins; returns
+/// `None` if a plugin cancelled the message.
+pub(crate) fn filter_chat_message(
+    state: &State,
+    mut msg: comp::UnresolvedChatMsg,
+) -> Option<comp::UnresolvedChatMsg> {
+    let Some(uid) = msg.uid() else {
+        return Some(msg);
+    };
+    let is_player = state.ecs().entity_from_uid(uid).map_or(false, |entity| {
+        state.ecs().read_storage::<comp::Player>().contains(entity)
+    });
+    let message = match msg.content() {
+        comp::Content::Plain(message) if is_player => message.clone(),
+        _ => return Some(msg),
+    };
+
+    let responses = dispatch_event(state, &ChatMessageEvent {
+        player: plugin_api::event::Player { id: uid },
+        message,
+    });
+    for response in responses {
+        match response {
+            ChatMessageResult::Cancel => return None,
+            ChatMessageResult::Modify(message) => msg.set_content(comp::Content::Plain(message)),
+            ChatMessageResult::None => {},
+        }
+    }
+    Some(msg)
+}
+
+impl Server {
+    /// Names of the loaded plugins, along with the number of modules they have
+    pub fn list_plugins(&self) -> Vec<(String, usize)> {
+        self.state
+            .ecs()
+            .read_resource::<PluginMgr>()
+            .plugins()
+            .map(|plugin| (plugin.name().to_owned(), plugin.module_count()))
+            .collect()
+    }
+
+    /// Load `<plugin dir>/<file_name>.plugin.tar`, returning the name of the
+    /// plugin
+    pub fn load_plugin(&mut self, file_name: &str) -> Result<String, String> {
+        // Don't allow loading archives from outside of the plugin directory
+        if file_name.contains(['/', '\\']) || file_name.starts_with('.') {
+            return Err(format!("Invalid plugin file name {}", file_name));
+        }
+        let path = PluginMgr::plugin_dir().join(format!("{}.plugin.tar", file_name));
+        with_plugins(&self.state, |plugin_mgr, ecs_world| {
+            plugin_mgr.load(path, ecs_world, GameMode::Server)
+        })
+        .map_err(|e| format!("{:?}", e))
+    }
+
+    pub fn unload_plugin(&mut self, name: &str) -> Result<(), String> {
+        self.state
+            .ecs()
+            .write_resource::<PluginMgr>()
+            .unload(name)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    /// Replace a loaded plugin with the current version of its archive,
+    /// keeping the old version if the new one fails to load
+    pub fn reload_plugin(&mut self, name: &str) -> Result<String, String> {
+        with_plugins(&self.state, |plugin_mgr, ecs_world| {
+            plugin_mgr.reload(name, ecs_world, GameMode::Server)
+        })
+        .map_err(|e| format!("{:?}", e))
+    }
+
+    /// Run the `on_tick` event and the due timers of every plugin.
+    pub(crate) fn tick_plugins(&mut self) {
+        let time = self.state.ecs().read_resource::<Time>().0;
+        with_plugins(&self.state, |plugin_mgr, ecs_world| {
+            plugin_mgr.tick(ecs_world, time)
+        });
+    }
+
+    /// Apply all the actions emitted by plugins since the last tick, in the
+    /// order in which they were emitted.
+    pub(crate) fn handle_plugin_actions(&mut self) {
+        let actions = self
+            .state
+            .ecs()
+            .write_resource::<PluginMgr>()
+            .take_actions();
+
+        let mut storage_changes = Vec::new();
+        for (plugin, action) in actions {
+            if let Err(e) = self.handle_plugin_action(&plugin, action, &mut storage_changes) {
+                warn!("Action from plugin {} failed: {}", plugin, e);
+            }
+        }
+
+        // Values are written to the database in a single transaction per tick
+        self.state
+            .ecs()
+            .read_resource::<PluginStorageUpdater>()
+            .submit(storage_changes);
+    }
+
+    fn handle_plugin_action(
+        &mut self,
+        plugin: &str,
+        action: Action,
+        storage_changes: &mut Vec<StorageChange>,
+    ) -> Result<(), String> {
+        match action {
+            Action::ServerClose => {
+                info!("Server closed by plugin");
+                std::process::exit(-1);
+            },
+            // Logged by the plugin module as soon as it's emitted, never queued
+            Action::Print(_) => {},
+            Action::PlayerSendMessage(uid, msg) => {
+                let entity = self.plugin_target(uid)?;
+                self.notify_client(
+                    entity,
+                    ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+                );
+            },
+            Action::KillEntity(uid) => {
+                let entity = self.plugin_target(uid)?;
+                self.state
+                    .ecs()
+                    .write_storage::<comp::Health>()
+                    .get_mut(entity)
+                    .ok_or_else(|| format!("Entity {} has no health", uid))?
+                    .kill();
+            },
+            Action::TeleportEntity(uid, pos) => {
+                let entity = self.plugin_target(uid)?;
+                self.state
+                    .position_mut(entity, true, |current_pos| current_pos.0 = pos)
+                    .map_err(|e| format!("{:?}", e))?;
+            },
+            Action::SetEntityHealth(uid, hp) => {
+                let entity = self.plugin_target(uid)?;
+                let time = *self.state.ecs().read_resource::<Time>();
+                let mut healths = self.state.ecs().write_storage::<comp::Health>();
+                let mut health = healths
+                    .get_mut(entity)
+                    .ok_or_else(|| format!("Entity {} has no health", uid))?;
+                let change = comp::HealthChange {
+                    amount: hp - health.current(),
+                    by: None,
+                    cause: None,
+                    precise: false,
+                    time,
+                    instance: rand::random(),
+                };
+                health.change_by(change);
+            },
+            Action::GiveItem(uid, item, amount) => {
+                let entity = self.plugin_target(uid)?;
+                cmd::push_kit(
+                    std::iter::once((KitSpec::Item(item), amount)),
+                    1,
+                    self,
+                    entity,
+                )
+                .map_err(|e| format!("{:?}", e))?;
+            },
+            Action::ApplyBuff(uid, kind, strength, duration) => {
+                let entity = self.plugin_target(uid)?;
+                let ecs = self.state.ecs();
+                let buff = Buff::new(
+                    kind,
+                    BuffData::new(strength, duration.map(Secs)),
+                    Vec::new(),
+                    BuffSource::Plugin,
+                    *ecs.read_resource::<Time>(),
+                    ecs.read_storage::<comp::Stats>().get(entity),
+                    ecs.read_storage::<comp::Health>().get(entity),
+                );
+                ecs.read_resource::<EventBus<ServerEvent>>()
+                    .emit_now(ServerEvent::Buff {
+                        entity,
+                        buff_change: BuffChange::Add(buff),
+                    });
+            },
+            Action::RemoveBuff(uid, kind) => {
+                let entity = self.plugin_target(uid)?;
+                self.state
+                    .ecs()
+                    .read_resource::<EventBus<ServerEvent>>()
+                    .emit_now(ServerEvent::Buff {
+                        entity,
+                        buff_change: BuffChange::RemoveByKind(kind),
+                    });
+            },
+            Action::SpawnNpc(entity_config, pos) => {
+                let config = EntityConfig::load(&entity_config)
+                    .map_err(|_| format!("Failed to load entity config {}", entity_config))?
+                    .read();
+                let entity_info = EntityInfo::at(pos).with_entity_config(
+                    config.clone(),
+                    Some(&entity_config),
+                    &mut thread_rng(),
+                );
+                match NpcData::from_entity_info(entity_info) {
+                    NpcData::Data {
+                        pos,
+                        stats,
+                        skill_set,
+                        health,
+                        poise,
+                        inventory,
+                        agent,
+                        body,
+                        alignment,
+                        scale,
+                        loot,
+                    } => self
+                        .state
+                        .ecs()
+                        .read_resource::<EventBus<ServerEvent>>()
+                        .emit_now(ServerEvent::CreateNpc {
+                            pos,
+                            ori: comp::Ori::from(Dir::random_2d(&mut thread_rng())),
+                            npc: NpcBuilder::new(stats, body, alignment)
+                                .with_skill_set(skill_set)
+                                .with_health(health)
+                                .with_poise(poise)
+                                .with_inventory(inventory)
+                                .with_agent(agent)
+                                .with_scale(scale)
+                                .with_loot(loot),
+                            rider: None,
+                        }),
+                    NpcData::Waypoint(_) | NpcData::Teleporter(_, _) => {
+                        return Err(format!("{} does not describe an NPC", entity_config));
+                    },
+                }
+            },
+            Action::SetStorageValue(character_id, key, value) => {
+                // Values attached to a character are only removed when it's deleted, which
+                // can't happen while it's in game
+                if let Some(character_id) = character_id {
+                    let presences = self.state.ecs().read_storage::<comp::Presence>();
+                    if !(&presences)
+                        .join()
+                        .any(|presence| presence.kind.character_id() == Some(character_id))
+                    {
+                        return Err(format!("Character {} is not in game", character_id.0));
+                    }
+                }
+                let key = StorageKey {
+                    plugin: plugin.to_owned(),
+                    character_id,
+                    key,
+                };
+                self.state
+                    .ecs()
+                    .write_resource::<PluginStorage>()
+                    .set(key.clone(), value.clone());
+                storage_changes.push(StorageChange::Set(key, value));
+            },
+            Action::StartTimer(name, delay, repeat) => {
+                let time = self.state.ecs().read_resource::<Time>().0;
+                self.state
+                    .ecs()
+                    .write_resource::<PluginMgr>()
+                    .start_timer(plugin, name, delay, repeat, time)
+                    .map_err(|e| format!("{:?}", e))?;
+            },
+            Action::CancelTimer(name) => self
+                .state
+                .ecs()
+                .write_resource::<PluginMgr>()
+                .cancel_timer(plugin, &name),
+            Action::DeleteStorageValue(character_id, key) => {
+                let key = StorageKey {
+                    plugin: plugin.to_owned(),
+                    character_id,
+                    key,
+                };
+                if self
+                    .state
+                    .ecs()
+                    .write_resource::<PluginStorage>()
+                    .remove(&key)
+                    .is_some()
+                {
+                    storage_changes.push(StorageChange::Delete(key));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn plugin_target(&self, uid: Uid) -> Result<EcsEntity, String> {
+        self.state
+            .ecs()
+            .entity_from_uid(uid)
+            .ok_or_else(|| format!("Entity {} not found", uid))
+    }
+}
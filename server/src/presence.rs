@@ -1,4 +1,6 @@
-use hashbrown::HashSet;
+use common::{resources::Time, uuid::Uuid};
+use common_net::msg::ResumeToken;
+use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage};
 use vek::*;
@@ -27,3 +29,55 @@ pub struct RepositionOnChunkLoad {
 impl Component for RepositionOnChunkLoad {
     type Storage = VecStorage<Self>;
 }
+
+/// Marks the character of a player whose connection dropped. It stays in the
+/// world until `until`, so that logging in again hands it back instead of
+/// loading it from the database, see [`ResumableLogins`].
+#[derive(Copy, Clone, Debug)]
+pub struct HeldSession {
+    pub until: Time,
+}
+
+impl Component for HeldSession {
+    type Storage = specs::DenseVecStorage<Self>;
+}
+
+/// Logins of players whose connection dropped, by the [`ResumeToken`] their
+/// client was issued when registering. The client logs in again with it
+/// instead of keeping the password around. They expire with the reconnect
+/// grace period.
+#[derive(Default)]
+pub struct ResumableLogins {
+    logins: HashMap<ResumeToken, ResumableLogin>,
+}
+
+struct ResumableLogin {
+    username: String,
+    uuid: Uuid,
+    until: Time,
+}
+
+impl ResumableLogins {
+    pub fn insert(&mut self, token: ResumeToken, username: String, uuid: Uuid, until: Time) {
+        // A player can only resume their latest session
+        self.logins.retain(|_, login| login.uuid != uuid);
+        self.logins.insert(token, ResumableLogin {
+            username,
+            uuid,
+            until,
+        });
+    }
+
+    /// Takes the username and uuid the token was issued to, each token can
+    /// only be used once.
+    pub fn take(&mut self, token: ResumeToken, now: Time) -> Option<(String, Uuid)> {
+        self.logins
+            .remove(&token)
+            .filter(|login| login.until.0 > now.0)
+            .map(|login| (login.username, login.uuid))
+    }
+
+    pub fn remove_expired(&mut self, now: Time) {
+        self.logins.retain(|_, login| login.until.0 > now.0);
+    }
+}
@@ -0,0 +1,636 @@
+//! Recording of server sessions, so that crashes and desyncs can be reproduced
+//! offline.
+//!
+//! A recording starts with a [`RecordingHeader`] holding the settings and the
+//! seeds of the session, followed by a [`Frame`] for every tick and everything
+//! that clients sent during it. Messages are captured when the server receives
+//! them from a stream, not when they arrive over the network, so a [`Replay`]
+//! can hand them to the same systems in the same tick.
+//!
+//! Replays only reproduce what the recording captures. The world, the
+//! messages of the clients, the tick lengths, the decisions of rtsim NPCs and
+//! the randomness of combat, loot and agents (which is derived from the
+//! recorded [`RngSeed`]) are reproduced. Chunks generated in slow jobs, the
+//! timing of database queries and commands entered on the server console are
+//! not, and events emitted from parallel systems are only handled in the same
+//! order on a best-effort basis. The database and the editable settings
+//! (admins, whitelist, ...) are read from the data directory the replay runs
+//! in, so it should be a copy of the one the session was recorded with, e.g. a
+//! backup taken right before it.
+
+use crate::{
+    client::Client,
+    connection_handler::{ClientStreams, ConnectionHandler},
+    login_provider::LoginProvider,
+    settings::Settings,
+    Server, Tick,
+};
+use authc::Uuid;
+use chrono::{DateTime, Utc};
+use common::resources::{RngSeed, Time};
+use common_net::msg::{ClientRegister, ClientType};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use hashbrown::HashMap;
+use network::{ConnectAddr, ListenAddr, Network, Participant, Pid, Stream, StreamError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use specs::WorldExt;
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::JoinHandle,
+    time::Duration,
+};
+use tokio::runtime::Runtime;
+use tracing::{debug, info, warn};
+
+/// Bumped whenever the format of recordings changes, recordings of other
+/// versions can't be replayed.
+pub const RECORDING_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    Settings(ron::Error),
+    /// The recording was made by an incompatible version of the server.
+    Version(u32),
+    /// Recordings have to start before the first tick, as replays start from a
+    /// freshly created server.
+    SessionStarted,
+    /// Setting up a client for the replay failed.
+    Network(String),
+    Server(crate::Error),
+    /// The thread writing the recording panicked.
+    WriterPanicked,
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> Self { Self::Io(err) }
+}
+
+impl From<bincode::Error> for RecordingError {
+    fn from(err: bincode::Error) -> Self { Self::Encoding(err) }
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "IO Error: {}", err),
+            Self::Encoding(err) => write!(f, "Encoding Error: {}", err),
+            Self::Settings(err) => write!(f, "Settings Error: {}", err),
+            Self::Version(version) => write!(
+                f,
+                "Recording has version {}, but only version {} is supported",
+                version, RECORDING_VERSION
+            ),
+            Self::SessionStarted => write!(f, "The session has already started"),
+            Self::Network(err) => write!(f, "Network Error: {}", err),
+            Self::Server(err) => write!(f, "Server Error: {}", err),
+            Self::WriterPanicked => write!(f, "The recording writer panicked"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub version: u32,
+    pub started: DateTime<Utc>,
+    /// The server [`Settings`] as RON, bincode can't deal with some of the
+    /// serde attributes they use.
+    pub settings: String,
+    pub rng_seed: u64,
+}
+
+/// Recording-local id of a client, entity ids aren't stable across replays.
+pub type ClientId = u64;
+
+#[derive(Serialize, Deserialize)]
+pub enum Frame {
+    /// Start of a tick, everything up to the next `Tick` happened during it.
+    Tick { tick: u64, time: f64, dt: Duration },
+    Connect {
+        client: ClientId,
+        client_type: ClientType,
+    },
+    /// A message that the server received, bincode encoded.
+    Msg {
+        client: ClientId,
+        stream_id: u8,
+        data: Vec<u8>,
+    },
+    /// Receiving from the stream failed, e.g. because the client disconnected.
+    Closed { client: ClientId, stream_id: u8 },
+    /// The client logged in. Login tokens aren't recorded, the `ClientRegister`
+    /// message carries a placeholder that is resolved to this login by the
+    /// replay.
+    Login {
+        client: ClientId,
+        username: String,
+        uuid: Uuid,
+    },
+}
+
+fn login_placeholder(client: ClientId) -> String { format!("replay:{}", client) }
+
+enum WriterMsg {
+    Frame(Frame),
+    /// Writes the remaining frames and stops the writer.
+    Stop,
+}
+
+/// Records the session of a [`Server`], see [`Server::start_recording`].
+///
+/// Frames are written to disk by a separate thread, so that the tick doesn't
+/// wait on IO.
+pub struct Recorder {
+    path: PathBuf,
+    msgs_s: Sender<WriterMsg>,
+    writer: Option<JoinHandle<Result<(), RecordingError>>>,
+    next_client: ClientId,
+}
+
+impl Recorder {
+    pub(crate) fn create(
+        path: &Path,
+        settings: &Settings,
+        rng_seed: u64,
+    ) -> Result<Self, RecordingError> {
+        let header = RecordingHeader {
+            version: RECORDING_VERSION,
+            started: Utc::now(),
+            settings: ron::ser::to_string(settings).map_err(RecordingError::Settings)?,
+            rng_seed,
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut writer, &header)?;
+        writer.flush()?;
+        let (msgs_s, msgs_r) = unbounded();
+        let writer = std::thread::Builder::new()
+            .name("recording_writer".into())
+            .spawn(move || Self::write_frames(writer, msgs_r))?;
+        info!(?path, "Started recording the session");
+
+        Ok(Self {
+            path: path.to_owned(),
+            msgs_s,
+            writer: Some(writer),
+            next_client: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Records a new client, the returned [`ClientRecorder`] records everything
+    /// the client sends.
+    pub(crate) fn connect(&mut self, client_type: ClientType) -> ClientRecorder {
+        let client = self.next_client;
+        self.next_client += 1;
+        let _ = self.msgs_s.send(WriterMsg::Frame(Frame::Connect {
+            client,
+            client_type,
+        }));
+        ClientRecorder {
+            client,
+            msgs_s: self.msgs_s.clone(),
+        }
+    }
+
+    /// Starts the next tick, returns the error the writer stopped with if
+    /// writing the recording failed.
+    pub(crate) fn tick(
+        &mut self,
+        tick: u64,
+        time: f64,
+        dt: Duration,
+    ) -> Result<(), RecordingError> {
+        let frame = Frame::Tick { tick, time, dt };
+        if self.msgs_s.send(WriterMsg::Frame(frame)).is_err() {
+            // The writer only stops on its own when it failed
+            return self.stop();
+        }
+        Ok(())
+    }
+
+    /// Waits for the writer to write everything recorded so far and stops it.
+    fn stop(&mut self) -> Result<(), RecordingError> {
+        let _ = self.msgs_s.send(WriterMsg::Stop);
+        match self.writer.take().map(JoinHandle::join) {
+            None | Some(Ok(Ok(()))) => Ok(()),
+            Some(Ok(Err(e))) => Err(e),
+            Some(Err(_)) => Err(RecordingError::WriterPanicked),
+        }
+    }
+
+    fn write_frames(
+        mut writer: BufWriter<File>,
+        msgs_r: Receiver<WriterMsg>,
+    ) -> Result<(), RecordingError> {
+        while let Ok(WriterMsg::Frame(frame)) = msgs_r.recv() {
+            bincode::serialize_into(&mut writer, &frame)?;
+            // Flush whenever the writer caught up, so that a crash only loses
+            // the frames that weren't sent yet
+            if msgs_r.is_empty() {
+                writer.flush()?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // Also runs when the server panics, which is when the last frames matter
+        // the most
+        if let Err(e) = self.stop() {
+            warn!(?e, "Failed to write the end of the recording");
+        }
+    }
+}
+
+/// Records what a single client sends, owned by its [`Client`].
+pub struct ClientRecorder {
+    client: ClientId,
+    msgs_s: Sender<WriterMsg>,
+}
+
+impl ClientRecorder {
+    pub(crate) fn record_msg<M: Serialize>(
+        &self,
+        stream_id: u8,
+        msg: &Result<Option<M>, StreamError>,
+    ) {
+        let frame = match msg {
+            Ok(None) => return,
+            Ok(Some(msg)) => match bincode::serialize(msg) {
+                Ok(data) => Frame::Msg {
+                    client: self.client,
+                    stream_id,
+                    data,
+                },
+                Err(e) => {
+                    warn!(?e, "Failed to record a message");
+                    return;
+                },
+            },
+            Err(_) => Frame::Closed {
+                client: self.client,
+                stream_id,
+            },
+        };
+        let _ = self.msgs_s.send(WriterMsg::Frame(frame));
+    }
+
+    /// Records a `ClientRegister` with the login or resume token replaced by
+    /// a placeholder, tokens must not end up in recordings.
+    pub(crate) fn record_register(&self) {
+        let msg = ClientRegister {
+            token_or_username: login_placeholder(self.client),
+            resume_token: None,
+        };
+        self.record_msg(0, &Ok(Some(msg)));
+    }
+
+    pub(crate) fn record_login(&self, username: &str, uuid: Uuid) {
+        let _ = self.msgs_s.send(WriterMsg::Frame(Frame::Login {
+            client: self.client,
+            username: username.to_owned(),
+            uuid,
+        }));
+    }
+}
+
+/// Messages of a replayed client, received instead of the ones on its streams.
+pub struct ReplayInbox {
+    msgs_r: Receiver<(u8, Option<Vec<u8>>)>,
+    streams: [VecDeque<Option<Vec<u8>>>; 7],
+}
+
+impl ReplayInbox {
+    pub(crate) fn recv<M: DeserializeOwned>(
+        &mut self,
+        stream_id: u8,
+    ) -> Result<Option<M>, StreamError> {
+        for (id, msg) in self.msgs_r.try_iter() {
+            self.streams[id as usize].push_back(msg);
+        }
+        let stream = &mut self.streams[stream_id as usize];
+        match stream.pop_front() {
+            None => Ok(None),
+            Some(None) => {
+                // Once closed, a stream stays closed
+                stream.push_front(None);
+                Err(StreamError::StreamClosed)
+            },
+            Some(Some(data)) => bincode::deserialize(&data)
+                .map(Some)
+                .map_err(StreamError::Deserialize),
+        }
+    }
+}
+
+/// The remote end of a replayed client, what the server sends to it is thrown
+/// away.
+struct ReplayedClient {
+    _participant: Participant,
+    streams: Vec<Stream>,
+    msgs_s: Sender<(u8, Option<Vec<u8>>)>,
+}
+
+impl ReplayedClient {
+    /// Returns whether the client is still connected.
+    fn discard_received(&mut self) -> bool {
+        let mut connected = false;
+        for stream in &mut self.streams {
+            loop {
+                match stream.try_recv::<()>() {
+                    Ok(None) => {
+                        connected = true;
+                        break;
+                    },
+                    Err(StreamError::StreamClosed) => break,
+                    _ => {},
+                }
+            }
+        }
+        connected
+    }
+}
+
+/// Replays a recorded session on a [`Server`] created with
+/// [`Replay::settings`].
+pub struct Replay {
+    header: RecordingHeader,
+    frames: VecDeque<Frame>,
+    logins: HashMap<String, (String, Uuid)>,
+    runtime: Arc<Runtime>,
+    server_network: Network,
+    client_network: Network,
+    mpsc_id: u64,
+    clients: HashMap<ClientId, ReplayedClient>,
+}
+
+impl Replay {
+    pub fn open(path: &Path, runtime: Arc<Runtime>) -> Result<Self, RecordingError> {
+        // Logins finish asynchronously, so they are recorded after the messages
+        // that start them. Collect them up front to resolve the placeholders.
+        let (header, mut reader) = Self::read_header(path)?;
+        let mut frames = VecDeque::new();
+        let mut logins = HashMap::new();
+        while let Some(frame) = Self::read_frame(&mut reader)? {
+            if let Frame::Login {
+                client,
+                username,
+                uuid,
+            } = frame
+            {
+                logins.insert(login_placeholder(client), (username, uuid));
+            } else {
+                frames.push_back(frame);
+            }
+        }
+
+        // Replayed clients are connected to the server in-process
+        let server_network = Network::new(Pid::new(), &runtime);
+        let client_network = Network::new(Pid::new(), &runtime);
+        let mpsc_id = rand::random();
+        runtime
+            .block_on(server_network.listen(ListenAddr::Mpsc(mpsc_id)))
+            .map_err(|e| RecordingError::Network(e.to_string()))?;
+
+        info!(
+            started = %header.started,
+            "Opened a recording with {} frames and {} logins",
+            frames.len(),
+            logins.len()
+        );
+        Ok(Self {
+            header,
+            frames,
+            logins,
+            runtime,
+            server_network,
+            client_network,
+            mpsc_id,
+            clients: HashMap::new(),
+        })
+    }
+
+    fn read_header(path: &Path) -> Result<(RecordingHeader, BufReader<File>), RecordingError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let header: RecordingHeader = bincode::deserialize_from(&mut reader)?;
+        if header.version != RECORDING_VERSION {
+            return Err(RecordingError::Version(header.version));
+        }
+        Ok((header, reader))
+    }
+
+    fn read_frame(reader: &mut BufReader<File>) -> Result<Option<Frame>, RecordingError> {
+        match bincode::deserialize_from(reader) {
+            Ok(frame) => Ok(Some(frame)),
+            // Recordings of crashed servers can end in the middle of a frame
+            Err(e) => match &*e {
+                bincode::ErrorKind::Io(io_err) if io_err.kind() == io::ErrorKind::UnexpectedEof => {
+                    Ok(None)
+                },
+                _ => Err(e.into()),
+            },
+        }
+    }
+
+    pub fn header(&self) -> &RecordingHeader { &self.header }
+
+    /// The settings the session was recorded with, adjusted so that the server
+    /// is neither reachable from nor talks to the outside.
+    pub fn settings(&self) -> Result<Settings, RecordingError> {
+        let mut settings: Settings =
+            ron::from_str(&self.header.settings).map_err(|e| RecordingError::Settings(e.code))?;
+        settings.gameserver_protocols.clear();
+        settings.auth_server_address = None;
+        settings.link_simulation = None;
+        Ok(settings)
+    }
+
+    /// Prepares a freshly created server for the replay.
+    pub fn start(&self, server: &mut Server) -> Result<(), RecordingError> {
+        let ecs = server.state.ecs_mut();
+        if ecs.read_resource::<Tick>().0 > 0 {
+            return Err(RecordingError::SessionStarted);
+        }
+        ecs.insert(RngSeed(self.header.rng_seed));
+        ecs.write_resource::<LoginProvider>()
+            .set_replayed_logins(self.logins.clone());
+        Ok(())
+    }
+
+    /// Runs the next recorded tick, returns its number as recorded along with
+    /// the events of the server, or `None` at the end of the recording.
+    pub fn tick(
+        &mut self,
+        server: &mut Server,
+    ) -> Result<Option<(u64, Vec<crate::Event>)>, RecordingError> {
+        let (tick, time, dt) = loop {
+            match self.frames.pop_front() {
+                None => return Ok(None),
+                Some(Frame::Tick { tick, time, dt }) => break (tick, time, dt),
+                Some(frame) => self.apply(server, frame)?,
+            }
+        };
+        // Everything up to the next tick was received during this one
+        while let Some(frame) = self.frames.pop_front() {
+            if let Frame::Tick { .. } = frame {
+                self.frames.push_front(frame);
+                break;
+            }
+            self.apply(server, frame)?;
+        }
+
+        let server_time = server.state.ecs().read_resource::<Time>().0;
+        if (server_time - time).abs() > 1.0 {
+            debug!(
+                ?tick,
+                ?server_time,
+                recorded_time = ?time,
+                "Replay is out of sync with the recording"
+            );
+        }
+        let events = server
+            .tick(crate::Input::default(), dt)
+            .map_err(RecordingError::Server)?;
+        server.cleanup();
+        self.clients.retain(|_, client| client.discard_received());
+
+        Ok(Some((tick, events)))
+    }
+
+    fn apply(&mut self, server: &mut Server, frame: Frame) -> Result<(), RecordingError> {
+        match frame {
+            Frame::Tick { .. } | Frame::Login { .. } => {},
+            Frame::Connect {
+                client,
+                client_type,
+            } => {
+                let (participant, streams, remote, remote_streams) = self
+                    .runtime
+                    .block_on(Self::connect(
+                        &self.client_network,
+                        &mut self.server_network,
+                        self.mpsc_id,
+                    ))
+                    .map_err(RecordingError::Network)?;
+                let (msgs_s, msgs_r) = unbounded();
+                let mut server_client = Client::new(
+                    client_type,
+                    participant,
+                    server.state.get_time(),
+                    streams.general,
+                    streams.ping,
+                    streams.register,
+                    streams.character_screen,
+                    streams.in_game,
+                    streams.terrain,
+                    streams.physics,
+                );
+                server_client.replay_inbox = Some(ReplayInbox {
+                    msgs_r,
+                    streams: Default::default(),
+                });
+                server.initialize_client(server_client);
+                self.clients.insert(client, ReplayedClient {
+                    _participant: remote,
+                    streams: remote_streams,
+                    msgs_s,
+                });
+            },
+            Frame::Msg {
+                client,
+                stream_id,
+                data,
+            } => {
+                if let Some(client) = self.clients.get(&client) {
+                    let _ = client.msgs_s.send((stream_id, Some(data)));
+                }
+            },
+            Frame::Closed { client, stream_id } => {
+                if let Some(client) = self.clients.get(&client) {
+                    let _ = client.msgs_s.send((stream_id, None));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    async fn connect(
+        client_network: &Network,
+        server_network: &mut Network,
+        mpsc_id: u64,
+    ) -> Result<(Participant, ClientStreams, Participant, Vec<Stream>), String> {
+        let mut remote = client_network
+            .connect(ConnectAddr::Mpsc(mpsc_id))
+            .await
+            .map_err(|e| e.to_string())?;
+        let participant = server_network
+            .connected()
+            .await
+            .map_err(|e| e.to_string())?;
+        let streams = ConnectionHandler::open_streams(&participant)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut remote_streams = Vec::with_capacity(7);
+        for _ in 0..7 {
+            remote_streams.push(remote.opened().await.map_err(|e| e.to_string())?);
+        }
+        Ok((participant, streams, remote, remote_streams))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_net::msg::PingMsg;
+
+    #[test]
+    fn recording_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("veloren-recording-{}", rand::random::<u64>()));
+        let mut recorder = Recorder::create(&path, &Settings::default(), 42).unwrap();
+        recorder.tick(1, 0.0, Duration::from_millis(33)).unwrap();
+        let client = recorder.connect(ClientType::Game);
+        client.record_register();
+        client.record_login("player", Uuid::nil());
+        client.record_msg(4, &Ok(Some(PingMsg::Ping)));
+        client.record_msg::<PingMsg>(4, &Err(StreamError::StreamClosed));
+        recorder.tick(2, 0.033, Duration::from_millis(33)).unwrap();
+        drop(recorder);
+
+        let (header, mut reader) = Replay::read_header(&path).unwrap();
+        assert_eq!(header.rng_seed, 42);
+        let frames =
+            std::iter::from_fn(|| Replay::read_frame(&mut reader).unwrap()).collect::<Vec<_>>();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(frames[0], Frame::Tick { tick: 1, .. }));
+        assert!(matches!(frames[1], Frame::Connect {
+            client: 0,
+            client_type: ClientType::Game
+        }));
+        match &frames[2] {
+            Frame::Msg {
+                stream_id: 0, data, ..
+            } => {
+                let msg: ClientRegister = bincode::deserialize(data).unwrap();
+                assert_eq!(msg.token_or_username, login_placeholder(0));
+            },
+            _ => panic!("expected the registration"),
+        }
+        assert!(matches!(frames[3], Frame::Login { client: 0, .. }));
+        assert!(matches!(frames[4], Frame::Msg { stream_id: 4, .. }));
+        assert!(matches!(frames[5], Frame::Closed { stream_id: 4, .. }));
+        assert!(matches!(frames[6], Frame::Tick { tick: 2, .. }));
+        assert_eq!(frames.len(), 7);
+    }
+}
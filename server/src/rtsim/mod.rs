@@ -4,17 +4,21 @@ pub mod tick;
 
 use atomicwrites::{AtomicFile, OverwriteBehavior};
 use common::{
+    character::CharacterId,
     grid::Grid,
     mounting::VolumePos,
-    rtsim::{Actor, ChunkResource, NpcId, RtSimEntity, WorldSettings},
+    rtsim::{Actor, ChunkResource, NpcId, RtSimEntity, SiteId, WorldSettings},
+    terrain::SpriteKind,
+    trade::{self, Good, SitePrices},
 };
 use common_ecs::{dispatch, System};
+use common_net::msg::world_msg::SiteFactionInfo;
 use common_state::BlockDiff;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use enum_map::EnumMap;
 use rtsim::{
     data::{npc::SimulationMode, Data, ReadError},
-    event::{OnDeath, OnMountVolume, OnSetup},
+    event::{OnCollectSprite, OnDeath, OnMountVolume, OnQuestComplete, OnSetup, OnTrade},
     RtState,
 };
 use specs::DispatcherBuilder;
@@ -27,7 +31,7 @@ use std::{
 };
 use tracing::{debug, error, info, trace, warn};
 use vek::*;
-use world::{IndexRef, World};
+use world::{Index, IndexRef, World};
 
 pub struct RtSim {
     file_path: PathBuf,
@@ -206,6 +210,103 @@ impl RtSim {
         );
     }
 
+    /// Lets rtsim know that someone collected a sprite, so that taking what
+    /// belongs to a site can be noticed.
+    pub fn hook_collect_sprite(
+        &mut self,
+        world: &World,
+        index: IndexRef,
+        actor: Actor,
+        wpos: Vec3<i32>,
+        sprite: SpriteKind,
+    ) {
+        self.state.emit(
+            OnCollectSprite {
+                actor,
+                wpos,
+                sprite,
+            },
+            world,
+            index,
+        );
+    }
+
+    pub fn hook_quest_complete(
+        &mut self,
+        world: &World,
+        index: IndexRef,
+        actor: Actor,
+        giver: NpcId,
+    ) {
+        self.state
+            .emit(OnQuestComplete { actor, giver }, world, index);
+    }
+
+    /// Forgets about a character that was permanently deleted.
+    pub fn hook_character_deleted(&mut self, character: CharacterId) {
+        self.state.get_data_mut().quests.remove_character(character);
+    }
+
+    /// Lets the economy of a site know that its merchant traded.
+    pub fn hook_trade(
+        &mut self,
+        world: &World,
+        index: IndexRef,
+        site: trade::SiteId,
+        npc: Option<NpcId>,
+        goods: Vec<(Good, f32)>,
+    ) {
+        if let Some(site) = self.trade_site(&index, site) {
+            self.state.emit(OnTrade { site, npc, goods }, world, index);
+        }
+    }
+
+    /// The prices that merchants of a site currently trade at, falling back to
+    /// the prices from the world simulation for sites without a live economy.
+    pub fn site_prices(&self, index: &Index, site: trade::SiteId) -> Option<SitePrices> {
+        self.trade_site(index, site)
+            .and_then(|site| {
+                self.state
+                    .data()
+                    .sites
+                    .get(site)
+                    .filter(|site| site.economy.is_active())
+                    .map(|site| site.economy.prices())
+            })
+            .or_else(|| index.get_site_prices(site))
+    }
+
+    /// Which faction holds each site, and who that faction is at war with.
+    pub fn site_factions(&self) -> Vec<SiteFactionInfo> {
+        let data = self.state.data();
+        data.sites
+            .values()
+            .filter_map(|site| {
+                let faction = data.factions.get(site.faction?)?;
+                Some(SiteFactionInfo {
+                    id: site.world_site?.id(),
+                    faction: faction.get_name(),
+                    at_war_with: faction
+                        .hostile_to
+                        .iter()
+                        .filter_map(|other| data.factions.get(*other))
+                        .map(|other| other.get_name())
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    fn trade_site(&self, index: &Index, site: trade::SiteId) -> Option<SiteId> {
+        let world_site = index.sites.recreate_id(site)?;
+        self.state
+            .data()
+            .sites
+            .world_site_map
+            .get(&world_site)
+            .copied()
+    }
+
     pub fn save(&mut self, wait_until_finished: bool) {
         debug!("Saving rtsim data...");
 
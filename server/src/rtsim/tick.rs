@@ -1,29 +1,37 @@
 #![allow(dead_code)] // TODO: Remove this when rtsim is fleshed out
 
 use super::*;
-use crate::sys::terrain::NpcData;
+use crate::{client::Client, sys::terrain::NpcData};
 use common::{
     comp::{self, Agent, Body, Presence, PresenceKind},
     event::{EventBus, NpcBuilder, ServerEvent},
     generation::{BodyBuilder, EntityConfig, EntityInfo},
-    resources::{DeltaTime, Time, TimeOfDay},
+    resources::{DeltaTime, RngSeed, Time, TimeOfDay},
     rtsim::{Actor, NpcId, RtSimEntity},
     slowjob::SlowJobPool,
     terrain::CoordinateConversions,
     trade::{Good, SiteInformation},
     util::Dir,
+    weather::{WeatherGrid, WeatherKind},
     LoadoutBuilder,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use rtsim::data::{
-    npc::{Profession, SimulationMode},
-    Npc, Sites,
+use common_net::msg::ServerGeneral;
+use rtsim::{
+    data::{
+        npc::{Profession, SimulationMode},
+        Npc, Sites,
+    },
+    event::OnStorm,
 };
 use specs::{Entities, Join, LendJoin, Read, ReadExpect, ReadStorage, WriteExpect, WriteStorage};
 use std::{sync::Arc, time::Duration};
 use tracing::error;
 use world::site::settlement::trader_loadout;
 
+/// Sites only check the weather over them every so many ticks
+const SITE_WEATHER_TICK_SKIP: u64 = 300;
+
 fn humanoid_config(profession: &Profession) -> &'static str {
     match profession {
         Profession::Farmer => "common.entity.village.farmer",
@@ -139,10 +147,26 @@ fn get_npc_entity_info(npc: &Npc, sites: &Sites, index: IndexRef) -> EntityInfo
 
     let mut rng = npc.rng(Npc::PERM_ENTITY_CONFIG);
     if let Some(profession) = npc.profession() {
-        let economy = npc.home.and_then(|home| {
-            let site = sites.get(home)?.world_site?;
-            index.sites.get(site).trade_information(site.id())
-        });
+        let economy = if let Some(cargo) = &npc.cargo {
+            // Merchants on the road sell what they are carrying
+            sites
+                .get(cargo.from)
+                .and_then(|site| site.world_site)
+                .map(|world_site| cargo.trade_information(world_site.id()))
+        } else {
+            npc.home.and_then(|home| {
+                let site = sites.get(home)?;
+                let world_site = site.world_site?;
+                if site.economy.is_active() {
+                    Some(site.economy.trade_information(world_site.id()))
+                } else {
+                    index
+                        .sites
+                        .get(world_site)
+                        .trade_information(world_site.id())
+                }
+            })
+        };
 
         let config_asset = humanoid_config(&profession);
 
@@ -221,10 +245,13 @@ impl<'a> System<'a> for Sys {
         ReadExpect<'a, Arc<world::World>>,
         ReadExpect<'a, world::IndexOwned>,
         ReadExpect<'a, SlowJobPool>,
+        ReadExpect<'a, RngSeed>,
         ReadStorage<'a, comp::Pos>,
         ReadStorage<'a, RtSimEntity>,
         WriteStorage<'a, comp::Agent>,
         ReadStorage<'a, Presence>,
+        ReadStorage<'a, Client>,
+        ReadExpect<'a, WeatherGrid>,
     );
 
     const NAME: &'static str = "rtsim::tick";
@@ -243,10 +270,13 @@ impl<'a> System<'a> for Sys {
             world,
             index,
             slow_jobs,
+            rng_seed,
             positions,
             rtsim_entities,
             mut agents,
             presences,
+            clients,
+            weather,
         ): Self::SystemData,
     ) {
         let mut emitter = server_event_bus.emitter();
@@ -274,10 +304,61 @@ impl<'a> System<'a> for Sys {
             }
         }
 
+        // Let sites know about storms raging over them
+        let storms = {
+            let data = rtsim.state.data();
+            data.sites
+                .iter()
+                .filter(|(_, site)| {
+                    (site.seed as u64 + data.tick) % SITE_WEATHER_TICK_SKIP == 0
+                        && weather.get_max_near(site.wpos.as_()).get_kind() == WeatherKind::Storm
+                })
+                .map(|(site_id, _)| site_id)
+                .collect::<Vec<_>>()
+        };
+        for site in storms {
+            rtsim
+                .state
+                .emit(OnStorm { site }, &world, index.as_index_ref());
+        }
+
         // Tick rtsim
-        rtsim
-            .state
-            .tick(&world, index.as_index_ref(), *time_of_day, *time, dt.0);
+        rtsim.state.tick(
+            &world,
+            index.as_index_ref(),
+            *time_of_day,
+            *time,
+            dt.0,
+            rng_seed.0,
+        );
+
+        // Let characters know about changes to the quests they took on
+        {
+            let data = &mut *rtsim.state.data_mut();
+            let changed = std::mem::take(&mut data.quests.changed);
+            for (presence, client) in (&presences, &clients).join() {
+                if let PresenceKind::Character(character) = presence.kind
+                    && changed.contains(&character)
+                {
+                    let quests = data
+                        .quests
+                        .taken_by(character)
+                        .map(|(id, quest)| quest.info(id, data, index.as_index_ref()))
+                        .collect();
+                    client.send_fallible(ServerGeneral::QuestUpdate(quests));
+                }
+            }
+        }
+
+        // Let characters know when sites change hands or wars break out
+        if std::mem::take(&mut rtsim.state.data_mut().factions.changed) {
+            let site_factions = rtsim.site_factions();
+            for (presence, client) in (&presences, &clients).join() {
+                if matches!(presence.kind, PresenceKind::Character(_)) {
+                    client.send_fallible(ServerGeneral::SiteFactions(site_factions.clone()));
+                }
+            }
+        }
 
         // Perform a save if required
         if rtsim
@@ -8,7 +8,8 @@ pub use editable::{EditableSetting, Error as SettingError};
 
 pub use admin::{AdminRecord, Admins};
 pub use banlist::{
-    Ban, BanAction, BanEntry, BanError, BanErrorKind, BanInfo, BanKind, BanRecord, Banlist,
+    Ban, BanAction, BanEntry, BanError, BanErrorKind, BanInfo, BanKind, BanNote, BanRecord,
+    Banlist, IpBanEntry,
 };
 pub use server_description::ServerDescription;
 pub use whitelist::{Whitelist, WhitelistInfo, WhitelistRecord};
@@ -20,6 +21,8 @@ use common::{
     rtsim::WorldSettings,
 };
 use core::time::Duration;
+use hashbrown::HashMap;
+use network::LinkSimulation;
 use portpicker::pick_unused_port;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -103,6 +106,60 @@ pub struct ModerationSettings {
     pub automod: bool,
     #[serde(default)]
     pub admins_exempt: bool,
+    /// Named presets for `/ban_template`, so moderators can apply consistent
+    /// reasons and durations.
+    #[serde(default)]
+    pub ban_templates: HashMap<String, BanTemplate>,
+    /// What to do about players with client-authoritative physics that move
+    /// in ways that aren't possible without cheating.
+    #[serde(default)]
+    pub movement_validation: MovementValidation,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementValidation {
+    /// Only reject impossible speeds and teleports, which are rejected with
+    /// any setting.
+    Off,
+    /// Log and report violations to admins, but only reject impossible speeds
+    /// and teleports.
+    #[default]
+    Flag,
+    /// Log and report violations, and put the player back to where the server
+    /// thinks they are.
+    RubberBand,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BanTemplate {
+    pub reason: String,
+    /// Length of the ban in the same format `/ban` accepts (e.g. "7d");
+    /// permanent if not set.
+    #[serde(default)]
+    pub duration: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Minutes between scheduled backups; backups are only taken on request if
+    /// not set.
+    pub interval_minutes: Option<u32>,
+    /// Number of backups to keep, older ones are deleted after each backup.
+    /// All backups are kept if 0.
+    pub keep: u32,
+    /// Directory backups are stored in, relative to the data directory.
+    pub path: PathBuf,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            interval_minutes: None,
+            keep: 10,
+            path: PathBuf::from("backups"),
+        }
+    }
 }
 
 impl ModerationSettings {
@@ -129,6 +186,8 @@ impl Default for ModerationSettings {
             banned_words_files: Vec::new(),
             automod: false,
             admins_exempt: true,
+            ban_templates: HashMap::new(),
+            movement_validation: MovementValidation::default(),
         }
     }
 }
@@ -173,6 +232,9 @@ pub struct Settings {
     pub max_view_distance: Option<u32>,
     pub max_player_group_size: u32,
     pub client_timeout: Duration,
+    /// How long the character of a player whose connection dropped stays in
+    /// the world, waiting for them to reconnect. Zero disables this.
+    pub reconnect_grace_period: Duration,
     pub max_player_for_kill_broadcast: Option<usize>,
     pub calendar_mode: CalendarMode,
 
@@ -181,10 +243,17 @@ pub struct Settings {
     #[serde(default, skip_serializing)]
     pub experimental_terrain_persistence: bool,
 
+    /// Degrades everything the server sends, to test how the game copes with
+    /// bad connections. Don't set this on a public server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_simulation: Option<LinkSimulation>,
+
     #[serde(default)]
     pub gameplay: GameplaySettings,
     #[serde(default)]
     pub moderation: ModerationSettings,
+    #[serde(default)]
+    pub backup: BackupSettings,
 
     #[serde(default)]
     pub world: WorldSettings,
@@ -212,10 +281,13 @@ impl Default for Settings {
             max_player_group_size: 6,
             calendar_mode: CalendarMode::Auto,
             client_timeout: Duration::from_secs(40),
+            reconnect_grace_period: Duration::from_secs(60),
             max_player_for_kill_broadcast: None,
             experimental_terrain_persistence: false,
+            link_simulation: None,
             gameplay: GameplaySettings::default(),
             moderation: ModerationSettings::default(),
+            backup: BackupSettings::default(),
             world: WorldSettings::default(),
         }
     }
@@ -12,20 +12,21 @@ use serde::{Deserialize, Serialize};
 /// BanlistRaw, the TryFrom<BanlistRaw> for Banlist, the previously most recent
 /// module, and add a new module for the latest version!  Please respect the
 /// migration upgrade guarantee found in the parent module with any upgrade.
-pub use self::v1::*;
+pub use self::v2::*;
 
 /// Versioned settings files, one per version (v0 is only here as an example; we
 /// do not expect to see any actual v0 settings files).
 #[derive(Deserialize, Serialize)]
 pub enum BanlistRaw {
     V0(v0::Banlist),
-    V1(Banlist),
+    V1(v1::Banlist),
+    V2(Banlist),
 }
 
 impl From<Banlist> for BanlistRaw {
     fn from(value: Banlist) -> Self {
         // Replace variant with that of current latest version.
-        Self::V1(value)
+        Self::V2(value)
     }
 }
 
@@ -37,9 +38,10 @@ impl TryFrom<BanlistRaw> for (Version, Banlist) {
         Ok(match value {
             // Old versions
             V0(value) => (Version::Old, value.try_into()?),
+            V1(value) => (Version::Old, value.try_into()?),
             // Latest version (move to old section using the pattern of other old version when it
             // is no longer latest).
-            V1(mut value) => (value.validate()?, value),
+            V2(mut value) => (value.validate()?, value),
         })
     }
 }
@@ -190,16 +192,20 @@ mod v0 {
 }
 
 mod v1 {
-    use super::{v0 as prev, BanError, BanErrorKind, BanKind, Final};
-    use crate::settings::editable::{EditableSetting, Error, Version};
+    use super::{
+        v0 as prev, v2 as next, BanError, BanErrorKind, BanKind, Final, MIGRATION_UPGRADE_GUARANTEE,
+    };
+    use crate::settings::editable::{EditableSetting, Version};
     use authc::Uuid;
     use chrono::{prelude::*, Utc};
     use common::comp::AdminRole;
-    use core::{mem, ops::Deref};
-    use hashbrown::{hash_map, HashMap};
+    use core::{
+        convert::{TryFrom, TryInto},
+        ops::Deref,
+    };
+    use hashbrown::HashMap;
     use serde::{Deserialize, Serialize};
     use tracing::warn;
-    /* use super::v2 as next; */
 
     /// Important: even if the role we are storing here appears to be identical
     /// to one used in another versioned store (like admin::Role), we *must*
@@ -475,117 +481,6 @@ mod v1 {
         fn deref(&self) -> &Self::Target { &self.0 }
     }
 
-    impl Banlist {
-        /// Attempt to perform the ban action `action` for the user with UUID
-        /// `uuid` and username `username`, starting from time `now`
-        /// (the information about the banning party will
-        /// be in the `action` record), with a settings file maintained at path
-        /// root `data_dir`.
-        ///
-        /// If trying to unban an already unbanned player, or trying to ban but
-        /// the ban status would not immediately change, the "overwrite"
-        /// boolean should also be set to true.
-        ///
-        /// We try to detect duplicates (bans that would have no effect) and
-        /// return None if such effects are encountered.  Otherwise, we
-        /// return Some(result), which works as follows.
-        ///
-        /// If the ban was invalid for any reason, then neither the in-memory
-        /// banlist nor the on-disk banlist are modified.  If the ban
-        /// entry is valid but the file encounters an error that
-        /// prevents it from being atomically written to disk, we return an
-        /// error but retain the change in memory.  Otherwise, we
-        /// complete successfully and atomically write the banlist to
-        /// disk.
-        ///
-        /// Note that the IO operation is only *guaranteed* atomic in the weak
-        /// sense that either the whole page is written or it isn't; we
-        /// cannot guarantee that the data we read in order to modify
-        /// the file was definitely up to date, so we could be missing
-        /// information if the file was manually edited or a function
-        /// edits it without going through the usual specs resources.
-        /// So, please be careful with ad hoc modifications to the file while
-        /// the server is running.
-        ///
-        /// Panics if provided a ban action with info set to None, as info: None
-        /// should only be used for legacy records.
-        ///
-        /// TODO: Consider creating a new type specifically for the entry to
-        /// avoid needing the precondition on info.
-        #[must_use]
-        pub fn ban_action(
-            &mut self,
-            data_dir: &std::path::Path,
-            now: DateTime<Utc>,
-            uuid: Uuid,
-            username_when_performed: String,
-            action: BanAction,
-            overwrite: bool,
-        ) -> Option<Result<(), Error<Final>>> {
-            assert!(
-                matches!(
-                    action,
-                    BanAction::Unban(_) | BanAction::Ban(Ban { info: Some(_), .. })
-                ),
-                "The info field is only None for legacy reasons--any new bans should have it set!",
-            );
-
-            let ban_record = BanRecord {
-                username_when_performed,
-                action,
-                date: now,
-            };
-
-            // Perform an atomic edit.
-            Some(
-                self.edit(data_dir.as_ref(), |banlist| {
-                    match banlist.0.entry(uuid) {
-                        hash_map::Entry::Vacant(v) => {
-                            // If this is an unban, it will have no effect, so return early.
-                            if matches!(ban_record.action, BanAction::Unban(_)) {
-                                return None;
-                            }
-                            // Otherwise, this will at least potentially have an effect (assuming it
-                            // succeeds).
-                            v.insert(BanEntry {
-                                current: ban_record,
-                                history: Vec::new(),
-                                // This is a hint anyway, but expired will also be set to true
-                                // before saving by the call `edit`
-                                // makes to `validate` (through `try_into`), which will set it to
-                                // true in the event that the ban
-                                // time was so short that it expired during the interval
-                                // between creating the action and saving it.
-                                //
-                                // TODO: Decide if we even care enough about this case to worry
-                                // about the gap. Probably not, even
-                                // though it does involve time!
-                                expired: false,
-                            });
-                            Some(())
-                        },
-                        hash_map::Entry::Occupied(mut o) => {
-                            let entry = o.get_mut();
-                            // If overwrite is off, check that this entry (if successful) would
-                            // actually change the ban status.
-                            if !overwrite
-                                && entry.current.is_expired(now) == ban_record.is_expired(now)
-                            {
-                                return None;
-                            }
-                            // Push the current (most recent) entry to the back of the history list.
-                            entry
-                                .history
-                                .push(mem::replace(&mut entry.current, ban_record));
-                            Some(())
-                        },
-                    }
-                })?
-                .1,
-            )
-        }
-    }
-
     impl Banlist {
         /// One-off migration from the previous version.  This must be
         /// guaranteed to produce a valid settings file as long as it is
@@ -649,6 +544,794 @@ mod v1 {
         }
     }
 
+    /// Pretty much every TryFrom implementation except that of the very last
+    /// version should look exactly like this.
+    impl TryFrom<Banlist> for Final {
+        type Error = <Final as EditableSetting>::Error;
+
+        #[allow(clippy::useless_conversion)]
+        fn try_from(mut value: Banlist) -> Result<Final, Self::Error> {
+            value.validate()?;
+            Ok(next::Banlist::migrate(value)
+                .try_into()
+                .expect(MIGRATION_UPGRADE_GUARANTEE))
+        }
+    }
+}
+
+mod v2 {
+    use super::{v1 as prev, BanError, BanErrorKind, BanKind, Final};
+    use crate::settings::editable::{EditableSetting, Error, Version};
+    use authc::Uuid;
+    use chrono::{prelude::*, Utc};
+    use common::comp::AdminRole;
+    use core::{mem, ops::Deref};
+    use hashbrown::{hash_map, HashMap};
+    use serde::{Deserialize, Serialize};
+    use std::net::IpAddr;
+    use tracing::warn;
+    /* use super::v3 as next; */
+
+    /// Important: even if the role we are storing here appears to be identical
+    /// to one used in another versioned store (like admin::Role), we *must*
+    /// have our own versioned copy!  This ensures that if there's an update
+    /// to the role somewhere else, the conversion function between them
+    /// will break, letting people make an intelligent decision.
+    ///
+    /// In particular, *never remove variants from this enum* (or any other enum
+    /// in a versioned settings file) without bumping the version and
+    /// writing a migration that understands how to properly deal with
+    /// existing instances of the old variant (you can delete From instances
+    /// for the old variants at this point).  Otherwise, we will lose
+    /// compatibility with old settings files, since we won't be able to
+    /// deserialize them!
+    #[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+    pub enum Role {
+        Moderator = 0,
+        Admin = 1,
+    }
+
+    impl From<AdminRole> for Role {
+        fn from(value: AdminRole) -> Self {
+            match value {
+                AdminRole::Moderator => Self::Moderator,
+                AdminRole::Admin => Self::Admin,
+            }
+        }
+    }
+
+    impl From<Role> for AdminRole {
+        fn from(value: Role) -> Self {
+            match value {
+                Role::Moderator => Self::Moderator,
+                Role::Admin => Self::Admin,
+            }
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    /// NOTE: May not be present if performed from the command line or from a
+    /// legacy file.
+    pub struct BanInfo {
+        pub performed_by: Uuid,
+        /// NOTE: May not be up to date, if we allow username changes.
+        pub performed_by_username: String,
+        /// NOTE: Role of the banning user at the time of the ban.
+        pub performed_by_role: Role,
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct Ban {
+        pub reason: String,
+        /// NOTE: Should only be None for migrations from legacy data.
+        pub info: Option<BanInfo>,
+        /// NOTE: Should always be higher than start_date, if both are
+        /// present!
+        pub end_date: Option<DateTime<Utc>>,
+    }
+
+    impl Ban {
+        /// Returns true if the ban is expired, false otherwise.
+        pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+            self.end_date.map_or(false, |end_date| end_date <= now)
+        }
+
+        pub fn performed_by_role(&self) -> Role {
+            self.info.as_ref().map(|info| info.performed_by_role)
+                // We know all legacy bans were performed by an admin, since we had no other roles
+                // at the time.
+                .unwrap_or(Role::Admin)
+        }
+    }
+
+    type Unban = BanInfo;
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub enum BanAction {
+        Unban(Unban),
+        Ban(Ban),
+    }
+
+    impl BanAction {
+        pub fn ban(&self) -> Option<&Ban> {
+            match self {
+                BanAction::Unban(_) => None,
+                BanAction::Ban(ban) => Some(ban),
+            }
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct BanRecord {
+        /// Username of the user upon whom the action was performed, when it was
+        /// performed.
+        pub username_when_performed: String,
+        pub action: BanAction,
+        /// NOTE: When migrating from legacy versions, this will just be the
+        /// time of the first migration (only applies to BanRecord).
+        pub date: DateTime<Utc>,
+    }
+
+    impl BanRecord {
+        /// Returns true if this record represents an expired ban, false
+        /// otherwise.
+        pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+            match &self.action {
+                BanAction::Ban(ban) => ban.is_expired(now),
+                BanAction::Unban(_) => true,
+            }
+        }
+
+        /// The history vector in a BanEntry is stored forwards (from oldest
+        /// entry to newest), so `prev_record` is the previous entry in
+        /// this vector when iterating forwards (by array index).
+        ///
+        /// Errors are:
+        ///
+        /// AlreadyUnbanned if an unban comes after anything but a ban.
+        ///
+        /// Permission(Unban) if an unban attempt is by a user with a lower role
+        /// level than the original banning party.
+        ///
+        /// PermissionDenied(Ban) if a ban length is made shorter by a user with
+        /// a role level than the original banning party.
+        ///
+        /// InvalidDateRange if the end date of the ban exceeds the start date.
+        fn validate(&self, prev_record: Option<&BanRecord>) -> Result<(), BanErrorKind> {
+            // Check to make sure the actions temporally line up--if they don't, we will
+            // prevent warn an administrator (since this may indicate a system
+            // clock issue and could require manual editing to resolve).
+            // However, we will not actually invalidate the ban list for this, in case
+            // this would otherwise prevent people from adding a new ban.
+            //
+            // We also deliberately leave the bad order intact, in case this reflects
+            // history more accurately than the system clock does.
+            if let Some(prev_record) = prev_record {
+                if prev_record.date > self.date {
+                    warn!(
+                        "Ban list history is inconsistent, or a just-added ban was behind a \
+                         historical entry in the ban
+                          record; please investigate the contents of the file (might indicate a \
+                         system clock change?)."
+                    );
+                }
+            }
+            let ban = match (&self.action, prev_record.map(|record| &record.action)) {
+                // A ban is always valid if it follows an unban.
+                (BanAction::Ban(ban), None) | (BanAction::Ban(ban), Some(BanAction::Unban(_))) => {
+                    ban
+                },
+                // A ban record following a ban is valid if either the role of the person doing the
+                // banning is at least the privilege level of the person who did the ban, or the
+                // ban's new end time is at least the previous end time.
+                (BanAction::Ban(new_ban), Some(BanAction::Ban(old_ban))) => {
+                    match (new_ban.end_date, old_ban.end_date) {
+                        // New role ≥ old role
+                        _ if new_ban.performed_by_role() >= old_ban.performed_by_role() => new_ban,
+                        // Permanent ban retracted to temp ban.
+                        (Some(_), None) => {
+                            return Err(BanErrorKind::PermissionDenied(BanKind::Ban));
+                        },
+                        // Temp ban retracted to shorter temp ban.
+                        (Some(new_date), Some(old_date)) if new_date < old_date => {
+                            return Err(BanErrorKind::PermissionDenied(BanKind::Ban));
+                        },
+                        // Anything else (extension to permanent ban, or temp ban extension to
+                        // longer temp ban).
+                        _ => new_ban,
+                    }
+                },
+                // An unban record is invalid if it does not follow a ban.
+                (BanAction::Unban(_), None) | (BanAction::Unban(_), Some(BanAction::Unban(_))) => {
+                    return Err(BanErrorKind::AlreadyUnbanned);
+                },
+                // An unban record following a ban is valid if the role of the person doing the
+                // unbanning is at least the privilege level of the person who did the ban.
+                (BanAction::Unban(unban), Some(BanAction::Ban(ban))) => {
+                    return if unban.performed_by_role >= ban.performed_by_role() {
+                        Ok(())
+                    } else {
+                        Err(BanErrorKind::PermissionDenied(BanKind::Unban))
+                    };
+                },
+            };
+
+            // End date of a ban must be at least as big as the start date.
+            if let Some(end_date) = ban.end_date {
+                if self.date > end_date {
+                    return Err(BanErrorKind::InvalidDateRange {
+                        start_date: self.date,
+                        end_date,
+                    });
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Validates a ban history (stored from oldest to newest) followed by the
+    /// current record, then updates the expiration hint if it's inconsistent
+    /// with reality.
+    ///
+    /// If we were invalid, returns an error.  Otherwise, returns Ok(v), where
+    /// v is Old if the hint bit was modified, Latest otherwise.
+    fn validate_records(
+        history: &[BanRecord],
+        current: &BanRecord,
+        expired: &mut bool,
+        now: DateTime<Utc>,
+        uuid: Uuid,
+    ) -> Result<Version, <Final as EditableSetting>::Error> {
+        let make_error = |current_entry: &BanRecord| {
+            let username = current_entry.username_when_performed.clone();
+            move |kind| BanError {
+                kind,
+                uuid,
+                username,
+            }
+        };
+        // First, go forwards through history (also forwards in terms of the iterator
+        // direction), validating each entry in turn.
+        let mut prev_entry = None;
+        for current_entry in history {
+            current_entry
+                .validate(prev_entry)
+                .map_err(make_error(current_entry))?;
+            prev_entry = Some(current_entry);
+        }
+
+        // History has now been validated, so validate the current entry.
+        current.validate(prev_entry).map_err(make_error(current))?;
+
+        // Make sure the expired hint is correct, and if not indicate that we should
+        // resave the file.
+        let is_expired = current.is_expired(now);
+        if *expired != is_expired {
+            *expired = is_expired;
+            Ok(Version::Old)
+        } else {
+            Ok(Version::Latest)
+        }
+    }
+
+    /// Pushes `ban_record` as the new current record, moving the old one into
+    /// the history.
+    ///
+    /// Returns None (without modifying anything) if overwrite is off and the
+    /// record would not change the ban status.
+    fn push_record(
+        current: &mut BanRecord,
+        history: &mut Vec<BanRecord>,
+        ban_record: BanRecord,
+        now: DateTime<Utc>,
+        overwrite: bool,
+    ) -> Option<()> {
+        // If overwrite is off, check that this entry (if successful) would
+        // actually change the ban status.
+        if !overwrite && current.is_expired(now) == ban_record.is_expired(now) {
+            return None;
+        }
+        // Push the current (most recent) entry to the back of the history list.
+        history.push(mem::replace(current, ban_record));
+        Some(())
+    }
+
+    /// Normalizes an IP address so that the same host always maps to the same
+    /// banlist key (IPv4 clients connecting over a dual-stack socket show up
+    /// as IPv4-mapped IPv6 addresses).
+    pub fn normalize_ip(ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        }
+    }
+
+    /// A free-form note attached to a ban entry by a moderator, e.g. to record
+    /// the outcome of an appeal.
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct BanNote {
+        pub date: DateTime<Utc>,
+        pub info: BanInfo,
+        pub text: String,
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct BanEntry {
+        /// The latest ban record for this user.
+        pub current: BanRecord,
+        /// Historical ban records for this user, stored in order from oldest to
+        /// newest.
+        pub history: Vec<BanRecord>,
+        /// A *hint* about whether the system thinks this entry is expired,
+        /// mostly to make it easier for someone manually going through
+        /// a file to see whether an entry is currently in effect or
+        /// not.  This is based off the contents of `current`.
+        pub expired: bool,
+        /// Moderator notes attached to this entry, stored in order from oldest
+        /// to newest.  Notes never affect the ban status.
+        pub notes: Vec<BanNote>,
+    }
+
+    impl Deref for BanEntry {
+        type Target = BanRecord;
+
+        fn deref(&self) -> &Self::Target { &self.current }
+    }
+
+    impl BanEntry {
+        /// Both validates, and updates the hint bit if it's inconsistent with
+        /// reality.
+        ///
+        /// If we were invalid, returns an error.  Otherwise, returns Ok(v),
+        /// where v is Latest if the hint bit was modified, Old
+        /// otherwise.
+        fn validate(
+            &mut self,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+        ) -> Result<Version, <Final as EditableSetting>::Error> {
+            validate_records(&self.history, &self.current, &mut self.expired, now, uuid)
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct IpBanEntry {
+        /// The latest ban record for this address.
+        pub current: BanRecord,
+        /// Historical ban records for this address, stored in order from oldest
+        /// to newest.
+        pub history: Vec<BanRecord>,
+        /// A *hint* about whether the system thinks this entry is expired (see
+        /// [BanEntry::expired]).
+        pub expired: bool,
+        /// Uuid of the user who was connected from this address when the ban
+        /// was performed; used to find the address again when unbanning.
+        pub uuid_when_performed: Uuid,
+    }
+
+    impl Deref for IpBanEntry {
+        type Target = BanRecord;
+
+        fn deref(&self) -> &Self::Target { &self.current }
+    }
+
+    impl IpBanEntry {
+        /// See [BanEntry::validate].
+        fn validate(
+            &mut self,
+            now: DateTime<Utc>,
+        ) -> Result<Version, <Final as EditableSetting>::Error> {
+            validate_records(
+                &self.history,
+                &self.current,
+                &mut self.expired,
+                now,
+                self.uuid_when_performed,
+            )
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize, Default)]
+    pub struct Banlist {
+        pub(super) uuid_bans: HashMap<Uuid, BanEntry>,
+        pub(super) ip_bans: HashMap<IpAddr, IpBanEntry>,
+    }
+
+    impl Banlist {
+        pub fn uuid_bans(&self) -> &HashMap<Uuid, BanEntry> { &self.uuid_bans }
+
+        pub fn ip_bans(&self) -> &HashMap<IpAddr, IpBanEntry> { &self.ip_bans }
+
+        /// Looks up the ban entry for an address, normalizing it first.
+        pub fn ip_ban(&self, ip: IpAddr) -> Option<&IpBanEntry> {
+            self.ip_bans.get(&normalize_ip(ip))
+        }
+
+        /// Attempt to perform the ban action `action` for the user with UUID
+        /// `uuid` and username `username`, starting from time `now`
+        /// (the information about the banning party will
+        /// be in the `action` record), with a settings file maintained at path
+        /// root `data_dir`.
+        ///
+        /// If trying to unban an already unbanned player, or trying to ban but
+        /// the ban status would not immediately change, the "overwrite"
+        /// boolean should also be set to true.
+        ///
+        /// We try to detect duplicates (bans that would have no effect) and
+        /// return None if such effects are encountered.  Otherwise, we
+        /// return Some(result), which works as follows.
+        ///
+        /// If the ban was invalid for any reason, then neither the in-memory
+        /// banlist nor the on-disk banlist are modified.  If the ban
+        /// entry is valid but the file encounters an error that
+        /// prevents it from being atomically written to disk, we return an
+        /// error but retain the change in memory.  Otherwise, we
+        /// complete successfully and atomically write the banlist to
+        /// disk.
+        ///
+        /// Note that the IO operation is only *guaranteed* atomic in the weak
+        /// sense that either the whole page is written or it isn't; we
+        /// cannot guarantee that the data we read in order to modify
+        /// the file was definitely up to date, so we could be missing
+        /// information if the file was manually edited or a function
+        /// edits it without going through the usual specs resources.
+        /// So, please be careful with ad hoc modifications to the file while
+        /// the server is running.
+        ///
+        /// Panics if provided a ban action with info set to None, as info: None
+        /// should only be used for legacy records.
+        ///
+        /// TODO: Consider creating a new type specifically for the entry to
+        /// avoid needing the precondition on info.
+        #[must_use]
+        pub fn ban_action(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            username_when_performed: String,
+            action: BanAction,
+            overwrite: bool,
+        ) -> Option<Result<(), Error<Final>>> {
+            let ban_record = Self::new_record(now, username_when_performed, action);
+
+            // Perform an atomic edit.
+            Some(
+                self.edit(data_dir.as_ref(), |banlist| {
+                    banlist.apply_uuid_record(now, uuid, ban_record, overwrite)
+                })?
+                .1,
+            )
+        }
+
+        /// Performs the ban action `action` for the user with UUID `uuid` and
+        /// for the address `ip` they are connected from, as a single atomic
+        /// edit.
+        ///
+        /// If either of the two actions would have no effect (see
+        /// [Banlist::ban_action]), neither is applied and None is returned.
+        /// Otherwise, the result has the same meaning as for
+        /// [Banlist::ban_action].
+        ///
+        /// Panics under the same conditions as [Banlist::ban_action].
+        #[must_use]
+        pub fn ban_with_ip_action(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            ip: IpAddr,
+            username_when_performed: String,
+            action: BanAction,
+            overwrite: bool,
+        ) -> Option<Result<(), Error<Final>>> {
+            let ban_record = Self::new_record(now, username_when_performed, action);
+            let ip = normalize_ip(ip);
+
+            // Perform an atomic edit; since `edit` works on a copy, returning None
+            // from the second action also discards the first.
+            Some(
+                self.edit(data_dir.as_ref(), |banlist| {
+                    banlist.apply_ip_record(now, ip, uuid, ban_record.clone(), overwrite)?;
+                    banlist.apply_uuid_record(now, uuid, ban_record, overwrite)
+                })?
+                .1,
+            )
+        }
+
+        /// Unbans the user with UUID `uuid`, along with every address that is
+        /// still banned because of them, as a single atomic edit.
+        ///
+        /// Returns None if none of the unbans would have an effect. Otherwise,
+        /// returns whether the user was unbanned and which addresses were,
+        /// along with a result that has the same meaning as for
+        /// [Banlist::ban_action].
+        #[must_use]
+        pub fn unban_with_ips_action(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            username_when_performed: String,
+            info: BanInfo,
+        ) -> Option<((bool, Vec<IpAddr>), Result<(), Error<Final>>)> {
+            let ban_record = Self::new_record(now, username_when_performed, BanAction::Unban(info));
+
+            // Perform an atomic edit.
+            self.edit(data_dir.as_ref(), |banlist| {
+                let banned_ips = banlist
+                    .ip_bans
+                    .iter()
+                    .filter(|(_, entry)| {
+                        entry.uuid_when_performed == uuid && !entry.current.is_expired(now)
+                    })
+                    .map(|(ip, _)| *ip)
+                    .collect::<Vec<_>>();
+                let unbanned_ips = banned_ips
+                    .into_iter()
+                    .filter(|ip| {
+                        banlist
+                            .apply_ip_record(now, *ip, uuid, ban_record.clone(), false)
+                            .is_some()
+                    })
+                    .collect::<Vec<_>>();
+                let unbanned = banlist
+                    .apply_uuid_record(now, uuid, ban_record, false)
+                    .is_some();
+                (unbanned || !unbanned_ips.is_empty()).then_some((unbanned, unbanned_ips))
+            })
+        }
+
+        /// Like [Banlist::ban_action], but applies to the address `ip` rather
+        /// than to a user.  `uuid` and `username_when_performed` identify the
+        /// user who was connected from this address at the time.
+        ///
+        /// Panics under the same conditions as [Banlist::ban_action].
+        #[must_use]
+        pub fn ip_ban_action(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            ip: IpAddr,
+            uuid: Uuid,
+            username_when_performed: String,
+            action: BanAction,
+            overwrite: bool,
+        ) -> Option<Result<(), Error<Final>>> {
+            let ban_record = Self::new_record(now, username_when_performed, action);
+            let ip = normalize_ip(ip);
+
+            // Perform an atomic edit.
+            Some(
+                self.edit(data_dir.as_ref(), |banlist| {
+                    banlist.apply_ip_record(now, ip, uuid, ban_record, overwrite)
+                })?
+                .1,
+            )
+        }
+
+        /// Attaches a moderator note to the ban entry of the user with UUID
+        /// `uuid`.
+        ///
+        /// Returns None if the user has no ban entry (notes are only kept
+        /// alongside a ban history).  Otherwise, the result has the same
+        /// meaning as for [Banlist::ban_action].
+        #[must_use]
+        pub fn add_note(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            info: BanInfo,
+            text: String,
+        ) -> Option<Result<(), Error<Final>>> {
+            Some(
+                self.edit(data_dir.as_ref(), |banlist| {
+                    banlist.uuid_bans.get_mut(&uuid).map(|entry| {
+                        entry.notes.push(BanNote {
+                            date: now,
+                            info,
+                            text,
+                        })
+                    })
+                })?
+                .1,
+            )
+        }
+
+        /// Applies `ban_record` to the ban entry of the user with UUID `uuid`,
+        /// returning None if it would have no effect.
+        fn apply_uuid_record(
+            &mut self,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            ban_record: BanRecord,
+            overwrite: bool,
+        ) -> Option<()> {
+            match self.uuid_bans.entry(uuid) {
+                hash_map::Entry::Vacant(v) => {
+                    // If this is an unban, it will have no effect, so return early.
+                    if matches!(ban_record.action, BanAction::Unban(_)) {
+                        return None;
+                    }
+                    // Otherwise, this will at least potentially have an effect (assuming it
+                    // succeeds).  The expired hint will be corrected by the call `edit`
+                    // makes to `validate`, should the ban already have expired.
+                    v.insert(BanEntry {
+                        current: ban_record,
+                        history: Vec::new(),
+                        expired: false,
+                        notes: Vec::new(),
+                    });
+                    Some(())
+                },
+                hash_map::Entry::Occupied(mut o) => {
+                    let entry = o.get_mut();
+                    push_record(
+                        &mut entry.current,
+                        &mut entry.history,
+                        ban_record,
+                        now,
+                        overwrite,
+                    )
+                },
+            }
+        }
+
+        /// Applies `ban_record` to the ban entry of the (already normalized)
+        /// address `ip`, returning None if it would have no effect.
+        fn apply_ip_record(
+            &mut self,
+            now: DateTime<Utc>,
+            ip: IpAddr,
+            uuid: Uuid,
+            ban_record: BanRecord,
+            overwrite: bool,
+        ) -> Option<()> {
+            match self.ip_bans.entry(ip) {
+                hash_map::Entry::Vacant(v) => {
+                    if matches!(ban_record.action, BanAction::Unban(_)) {
+                        return None;
+                    }
+                    v.insert(IpBanEntry {
+                        current: ban_record,
+                        history: Vec::new(),
+                        expired: false,
+                        uuid_when_performed: uuid,
+                    });
+                    Some(())
+                },
+                hash_map::Entry::Occupied(mut o) => {
+                    let entry = o.get_mut();
+                    entry.uuid_when_performed = uuid;
+                    push_record(
+                        &mut entry.current,
+                        &mut entry.history,
+                        ban_record,
+                        now,
+                        overwrite,
+                    )
+                },
+            }
+        }
+
+        fn new_record(
+            now: DateTime<Utc>,
+            username_when_performed: String,
+            action: BanAction,
+        ) -> BanRecord {
+            assert!(
+                matches!(
+                    action,
+                    BanAction::Unban(_) | BanAction::Ban(Ban { info: Some(_), .. })
+                ),
+                "The info field is only None for legacy reasons--any new bans should have it set!",
+            );
+
+            BanRecord {
+                username_when_performed,
+                action,
+                date: now,
+            }
+        }
+    }
+
+    impl Banlist {
+        /// One-off migration from the previous version.  This must be
+        /// guaranteed to produce a valid settings file as long as it is
+        /// called with a valid settings file from the previous version.
+        pub(super) fn migrate(prev: prev::Banlist) -> Self {
+            fn migrate_info(info: prev::BanInfo) -> BanInfo {
+                BanInfo {
+                    performed_by: info.performed_by,
+                    performed_by_username: info.performed_by_username,
+                    performed_by_role: match info.performed_by_role {
+                        prev::Role::Moderator => Role::Moderator,
+                        prev::Role::Admin => Role::Admin,
+                    },
+                }
+            }
+
+            fn migrate_record(record: prev::BanRecord) -> BanRecord {
+                BanRecord {
+                    username_when_performed: record.username_when_performed,
+                    action: match record.action {
+                        prev::BanAction::Unban(info) => BanAction::Unban(migrate_info(info)),
+                        prev::BanAction::Ban(prev::Ban {
+                            reason,
+                            info,
+                            end_date,
+                        }) => BanAction::Ban(Ban {
+                            reason,
+                            info: info.map(migrate_info),
+                            end_date,
+                        }),
+                    },
+                    date: record.date,
+                }
+            }
+
+            Banlist {
+                uuid_bans: prev
+                    .0
+                    .into_iter()
+                    .map(
+                        |(
+                            uuid,
+                            prev::BanEntry {
+                                current,
+                                history,
+                                expired,
+                            },
+                        )| {
+                            (uuid, BanEntry {
+                                current: migrate_record(current),
+                                history: history.into_iter().map(migrate_record).collect(),
+                                expired,
+                                // There were no notes before this version.
+                                notes: Vec::new(),
+                            })
+                        },
+                    )
+                    .collect(),
+                // IP bans did not exist before this version.
+                ip_bans: HashMap::new(),
+            }
+        }
+
+        /// Perform any needed validation on this banlist that can't be done
+        /// using parsing.
+        ///
+        /// The returned version being "Old" indicates the loaded setting has
+        /// been modified during validation (this is why validate takes
+        /// `&mut self`).
+        pub(super) fn validate(&mut self) -> Result<Version, <Final as EditableSetting>::Error> {
+            let mut version = Version::Latest;
+            let now = Utc::now();
+            for (&uuid, value) in self.uuid_bans.iter_mut() {
+                if matches!(value.validate(now, uuid)?, Version::Old) {
+                    // Update detected.
+                    version = Version::Old;
+                }
+            }
+            // Addresses edited in by hand may not be normalized; fix them up so lookups
+            // by connecting address find them.
+            if self.ip_bans.keys().any(|&ip| normalize_ip(ip) != ip) {
+                self.ip_bans = mem::take(&mut self.ip_bans)
+                    .into_iter()
+                    .map(|(ip, entry)| (normalize_ip(ip), entry))
+                    .collect();
+                version = Version::Old;
+            }
+            for value in self.ip_bans.values_mut() {
+                if matches!(value.validate(now)?, Version::Old) {
+                    version = Version::Old;
+                }
+            }
+            Ok(version)
+        }
+    }
+
     // NOTE: Whenever there is a version upgrade, copy this note as well as the
     // commented-out code below to the next version, then uncomment the code
     // for this version.
@@ -3,6 +3,7 @@ use crate::{
     chat::ChatExporter,
     client::Client,
     events::{self, update_map_markers},
+    movement_validation::MovementTracker,
     persistence::PersistedComponents,
     pet::restore_pet,
     presence::RepositionOnChunkLoad,
@@ -28,7 +29,7 @@ use common::{
     effect::Effect,
     link::{Is, Link, LinkHandle},
     mounting::{Mounting, Rider, VolumeMounting, VolumeRider},
-    resources::{Secs, Time, TimeOfDay},
+    resources::{RngSeed, Secs, Time, TimeOfDay},
     rtsim::{Actor, RtSimEntity},
     slowjob::SlowJobPool,
     uid::{IdMaps, Uid},
@@ -393,13 +394,17 @@ impl StateExt for State {
             }),
             _ => None,
         };
+        let ori = item_drop.orientation(&mut self.ecs().read_resource::<RngSeed>().rng(
+            *self.ecs().read_resource::<Time>(),
+            ("item_drop", pos.0.map(f32::to_bits)),
+        ));
         Some(
             self.ecs_mut()
                 .create_entity_synced()
                 .with(item)
                 .with(pos)
                 .with(vel)
-                .with(item_drop.orientation(&mut thread_rng()))
+                .with(ori)
                 .with(item_drop.mass())
                 .with(item_drop.density())
                 .with(body.collider())
@@ -644,6 +649,7 @@ impl StateExt for State {
 
             // Make sure physics components are updated
             self.write_component_ignore_entity_dead(entity, comp::ForceUpdate::forced());
+            self.write_component_ignore_entity_dead(entity, MovementTracker::default());
 
             self.write_component_ignore_entity_dead(
                 entity,
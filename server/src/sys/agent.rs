@@ -17,7 +17,6 @@ use common::{
 };
 use common_base::prof_span;
 use common_ecs::{Job, Origin, ParMode, Phase, System};
-use rand::thread_rng;
 use rayon::iter::ParallelIterator;
 use specs::{LendJoin, ParJoin, Read, WriteStorage};
 
@@ -98,7 +97,7 @@ impl<'a> System<'a> for Sys {
                     (_, is_rider, is_volume_rider),
                 )| {
                     let mut event_emitter = event_bus.emitter();
-                    let mut rng = thread_rng();
+                    let mut rng = read_data.rng_seed.rng(*read_data.time, (Self::NAME, *uid));
 
                     // The entity that is moving, if riding it's the mount, otherwise it's itself
                     let moving_entity = is_rider
@@ -12,7 +12,7 @@ use common::{
     path::TraversalConfig,
     rtsim::{NpcAction, RtSimEntity},
 };
-use rand::{prelude::ThreadRng, thread_rng, Rng};
+use rand::{rngs::StdRng, Rng};
 use specs::Entity as EcsEntity;
 use vek::{Vec2, Vec3};
 
@@ -41,7 +41,7 @@ pub struct BehaviorData<'a, 'b, 'c> {
     pub read_data: &'a ReadData<'a>,
     pub event_emitter: &'a mut Emitter<'c, ServerEvent>,
     pub controller: &'a mut Controller,
-    pub rng: &'b mut ThreadRng,
+    pub rng: &'b mut StdRng,
 }
 
 /// Behavior function
@@ -534,6 +534,23 @@ fn handle_rtsim_actions(bdata: &mut BehaviorData) -> bool {
                     bdata.agent.awareness.set_maximally_aware();
                 }
             },
+            NpcAction::OfferQuest(target, quest) => {
+                if let Some(target) = bdata.read_data.lookup_actor(target) {
+                    bdata.event_emitter.emit(ServerEvent::OfferQuest {
+                        entity: target,
+                        quest,
+                    });
+                }
+            },
+            NpcAction::CompleteQuest(target, quest) => {
+                if let Some(target) = bdata.read_data.lookup_actor(target) {
+                    bdata.event_emitter.emit(ServerEvent::CompleteQuest {
+                        entity: target,
+                        npc: *bdata.agent_data.entity,
+                        quest,
+                    });
+                }
+            },
         }
         true
     } else {
@@ -676,7 +693,7 @@ fn update_target_awareness(bdata: &mut BehaviorData) -> bool {
     if let (Some(target), Some(tgt_pos)) = (target, tgt_pos) {
         if agent_data.can_see_entity(agent, controller, target, tgt_pos, tgt_scale, read_data) {
             agent.awareness.change_by(1.75 * read_data.dt.0);
-        } else if agent_data.can_sense_directly_near(tgt_pos) {
+        } else if agent_data.can_sense_directly_near(tgt_pos, read_data) {
             agent.awareness.change_by(0.25);
         } else {
             agent
@@ -781,7 +798,7 @@ fn do_combat(bdata: &mut BehaviorData) -> bool {
                     agent.behavior_state.timers
                         [ActionStateBehaviorTreeTimers::TimerBehaviorTree as usize] = 0.01;
                     agent.flee_from_pos = {
-                        let random = || thread_rng().gen_range(-1.0..1.0);
+                        let mut random = || rng.gen_range(-1.0..1.0);
                         Some(Pos(
                             agent_data.pos.0 + Vec3::new(random(), random(), random())
                         ))
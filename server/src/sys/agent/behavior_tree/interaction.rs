@@ -13,7 +13,7 @@ use common::{
     rtsim::{Actor, NpcInput, PersonalityTrait},
     trade::{TradeAction, TradePhase, TradeResult},
 };
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use crate::sys::agent::util::get_entity_by_id;
 
@@ -78,7 +78,7 @@ pub fn handle_inbox_talk(bdata: &mut BehaviorData) -> bool {
         read_data,
         event_emitter,
         controller,
-        ..
+        rng,
     } = bdata;
 
     if !matches!(agent.inbox.front(), Some(AgentEvent::Talk(_, _))) {
@@ -175,12 +175,8 @@ pub fn handle_inbox_talk(bdata: &mut BehaviorData) -> bool {
                                 // TODO: Localise
                                 agent_data.chat_npc(Content::Plain(msg), event_emitter);
                             } else {
-                                let mut rng = thread_rng();
                                 agent_data.chat_npc(
-                                    agent
-                                        .rtsim_controller
-                                        .personality
-                                        .get_generic_comment(&mut rng),
+                                    agent.rtsim_controller.personality.get_generic_comment(rng),
                                     event_emitter,
                                 );
                             }
@@ -2,6 +2,7 @@ use crate::{
     chunk_serialize::{ChunkSendEntry, SerializedChunk},
     client::Client,
     metrics::NetworkRequestMetrics,
+    terrain_versions::TerrainVersions,
     Tick,
 };
 use common::{comp::Presence, event::EventBus, slowjob::SlowJobPool, terrain::TerrainGrid};
@@ -26,6 +27,7 @@ impl<'a> System<'a> for Sys {
         ReadExpect<'a, NetworkRequestMetrics>,
         ReadExpect<'a, SlowJobPool>,
         ReadExpect<'a, TerrainGrid>,
+        ReadExpect<'a, TerrainVersions>,
         ReadExpect<'a, crossbeam_channel::Sender<SerializedChunk>>,
     );
 
@@ -43,6 +45,7 @@ impl<'a> System<'a> for Sys {
             network_metrics,
             slow_jobs,
             terrain,
+            terrain_versions,
             chunk_sender,
         ): Self::SystemData,
     ) {
@@ -101,14 +104,16 @@ impl<'a> System<'a> for Sys {
             .chunks_distinct_serialisation_requests
             .inc_by(distinct_requests);
 
-        // Trigger serialization in a SlowJob
+        // Trigger serialization in a SlowJob, the version is taken along with the
+        // chunk so that both match
         const CHUNK_SIZE: usize = 10; // trigger one job per 10 chunks to reduce SlowJob overhead. as we use a channel, there is no disadvantage to this
         let mut chunks_iter = chunks
             .into_iter()
             .filter_map(|(chunk_key, meta)| {
+                let version = terrain_versions.version(chunk_key).unwrap_or_default();
                 terrain
                     .get_key_arc_real(chunk_key)
-                    .map(|chunk| (Arc::clone(chunk), chunk_key, meta))
+                    .map(|chunk| (Arc::clone(chunk), chunk_key, version, meta))
             })
             .peekable();
 
@@ -116,10 +121,11 @@ impl<'a> System<'a> for Sys {
             let chunks: Vec<_> = chunks_iter.by_ref().take(CHUNK_SIZE).collect();
             let chunk_sender = chunk_sender.clone();
             slow_jobs.spawn("CHUNK_SERIALIZER", move || {
-                for (chunk, chunk_key, mut meta) in chunks {
+                for (chunk, chunk_key, version, mut meta) in chunks {
                     let msg = Client::prepare_chunk_update_msg(
                         ServerGeneral::TerrainChunkUpdate {
                             key: chunk_key,
+                            version,
                             chunk: Ok(SerializedTerrainChunk::via_heuristic(
                                 &chunk,
                                 meta.lossy_compression,
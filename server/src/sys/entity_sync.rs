@@ -14,9 +14,14 @@ use common::{
     vol::RectVolSize,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::{msg::ServerGeneral, sync::CompSyncPackage};
+use common_net::{
+    msg::{EcsCompPacket, PredictionAck, ServerGeneral},
+    sync::CompSyncPackage,
+};
+use hashbrown::HashMap;
 use itertools::Either;
 use specs::{Entities, Join, LendJoin, Read, ReadExpect, ReadStorage, Write, WriteStorage};
+use std::sync::atomic::Ordering;
 use vek::*;
 
 /// This system will send physics updates to the client
@@ -124,193 +129,215 @@ impl<'a> System<'a> for Sys {
         use rayon::iter::{IntoParallelIterator, ParallelIterator};
         job.cpu_stats.measure(common_ecs::ParMode::Rayon);
         common_base::prof_span!(guard, "regions");
-        regions_and_deleted_entities.into_par_iter().for_each_init(
-            || {
-                common_base::prof_span!(guard, "entity sync rayon job");
-                guard
-            },
-            |_guard, (key, region, deleted_entities_in_region)| {
-                // Assemble subscriber list for this region by iterating through clients and
-                // checking if they are subscribed to this region
-                let mut subscribers = (
-                    &clients,
-                    &entities,
-                    presences.maybe(),
-                    &subscriptions,
-                    &positions,
-                )
-                    .join()
-                    .filter_map(|(client, entity, presence, subscription, pos)| {
-                        if presence.is_some() && subscription.regions.contains(&key) {
-                            Some((client, &subscription.regions, entity, *pos))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>();
-
-                for event in region.events() {
-                    match event {
-                        RegionEvent::Entered(id, maybe_key) => {
-                            // Don't process newly created entities here (redundant network
-                            // messages)
-                            if trackers.uid.inserted().contains(*id) {
-                                continue;
+        let region_physics = regions_and_deleted_entities
+            .into_par_iter()
+            .map_init(
+                || {
+                    common_base::prof_span!(guard, "entity sync rayon job");
+                    guard
+                },
+                |_guard, (key, region, deleted_entities_in_region)| {
+                    // Assemble subscriber list for this region by iterating through clients and
+                    // checking if they are subscribed to this region
+                    let mut subscribers = (
+                        &clients,
+                        &entities,
+                        presences.maybe(),
+                        &subscriptions,
+                        &positions,
+                    )
+                        .join()
+                        .filter_map(|(client, entity, presence, subscription, pos)| {
+                            if presence.is_some() && subscription.regions.contains(&key) {
+                                Some((client, &subscription.regions, entity, *pos))
+                            } else {
+                                None
                             }
-                            let entity = entities.entity(*id);
-                            if let Some(pkg) = positions
-                                .get(entity)
-                                .map(|pos| (pos, velocities.get(entity), orientations.get(entity)))
-                                .and_then(|(pos, vel, ori)| {
-                                    tracked_storages.create_entity_package(
-                                        entity,
-                                        Some(*pos),
-                                        vel.copied(),
-                                        ori.copied(),
-                                    )
-                                })
-                            {
-                                let create_msg = ServerGeneral::CreateEntity(pkg);
-                                for (client, regions, client_entity, _) in &mut subscribers {
-                                    if maybe_key
+                        })
+                        .collect::<Vec<_>>();
+
+                    for event in region.events() {
+                        match event {
+                            RegionEvent::Entered(id, maybe_key) => {
+                                // Don't process newly created entities here (redundant network
+                                // messages)
+                                if trackers.uid.inserted().contains(*id) {
+                                    continue;
+                                }
+                                let entity = entities.entity(*id);
+                                if let Some(pkg) = positions
+                                    .get(entity)
+                                    .map(|pos| {
+                                        (pos, velocities.get(entity), orientations.get(entity))
+                                    })
+                                    .and_then(|(pos, vel, ori)| {
+                                        tracked_storages.create_entity_package(
+                                            entity,
+                                            Some(*pos),
+                                            vel.copied(),
+                                            ori.copied(),
+                                        )
+                                    })
+                                {
+                                    let create_msg = ServerGeneral::CreateEntity(pkg);
+                                    for (client, regions, client_entity, _) in &mut subscribers {
+                                        if maybe_key
                                     .as_ref()
                                     .map(|key| !regions.contains(key))
                                     .unwrap_or(true)
                                     // Client doesn't need to know about itself
                                     && *client_entity != entity
-                                    {
-                                        client.send_fallible(create_msg.clone());
+                                        {
+                                            client.send_fallible(create_msg.clone());
+                                        }
                                     }
                                 }
-                            }
-                        },
-                        RegionEvent::Left(id, maybe_key) => {
-                            // Lookup UID for entity
-                            if let Some(&uid) = uids.get(entities.entity(*id)) {
-                                for (client, regions, _, _) in &mut subscribers {
-                                    if maybe_key
-                                        .as_ref()
-                                        .map(|key| !regions.contains(key))
-                                        .unwrap_or(true)
-                                    {
-                                        // TODO: I suspect it would be more efficient (in terms of
-                                        // bandwidth) to batch messages like this (same in
-                                        // subscription.rs).
-                                        client.send_fallible(ServerGeneral::DeleteEntity(uid));
+                            },
+                            RegionEvent::Left(id, maybe_key) => {
+                                // Lookup UID for entity
+                                if let Some(&uid) = uids.get(entities.entity(*id)) {
+                                    for (client, regions, _, _) in &mut subscribers {
+                                        if maybe_key
+                                            .as_ref()
+                                            .map(|key| !regions.contains(key))
+                                            .unwrap_or(true)
+                                        {
+                                            // TODO: I suspect it would be more efficient (in terms
+                                            // of bandwidth) to batch messages like this (same in
+                                            // subscription.rs).
+                                            client.send_fallible(ServerGeneral::DeleteEntity(uid));
+                                        }
                                     }
                                 }
-                            }
-                        },
+                            },
+                        }
                     }
-                }
 
-                // Sync tracked components
-                // Get deleted entities in this region from DeletedEntities
-                let (entity_sync_package, comp_sync_package) = trackers.create_sync_packages(
-                    &tracked_storages,
-                    region.entities(),
-                    deleted_entities_in_region,
-                );
-                // We lazily initialize the the synchronization messages in case there are no
-                // clients.
-                let mut entity_comp_sync = Either::Left((entity_sync_package, comp_sync_package));
-                for (client, _, client_entity, _) in &mut subscribers {
-                    let msg = entity_comp_sync.right_or_else(
-                        |(entity_sync_package, comp_sync_package)| {
-                            (
-                                client.prepare(ServerGeneral::EntitySync(entity_sync_package)),
-                                client.prepare(ServerGeneral::CompSync(
-                                    comp_sync_package,
-                                    force_updates.get(*client_entity).map_or(0, |f| f.counter()),
-                                )),
-                            )
-                        },
+                    // Sync tracked components
+                    // Get deleted entities in this region from DeletedEntities
+                    let (entity_sync_package, comp_sync_package) = trackers.create_sync_packages(
+                        &tracked_storages,
+                        region.entities(),
+                        deleted_entities_in_region,
                     );
-                    // We don't care much about stream errors here since they could just represent
-                    // network disconnection, which is handled elsewhere.
-                    let _ = client.send_prepared(&msg.0);
-                    let _ = client.send_prepared(&msg.1);
-                    entity_comp_sync = Either::Right(msg);
-                }
-
-                for (client, _, client_entity, client_pos) in &mut subscribers {
-                    let mut comp_sync_package = CompSyncPackage::new();
+                    // We lazily initialize the the synchronization messages in case there are no
+                    // clients.
+                    let mut entity_comp_sync =
+                        Either::Left((entity_sync_package, comp_sync_package));
+                    for (client, _, client_entity, _) in &mut subscribers {
+                        let msg = entity_comp_sync.right_or_else(
+                            |(entity_sync_package, comp_sync_package)| {
+                                (
+                                    client.prepare(ServerGeneral::EntitySync(entity_sync_package)),
+                                    client.prepare(ServerGeneral::CompSync(
+                                        comp_sync_package,
+                                        force_updates
+                                            .get(*client_entity)
+                                            .map_or(0, |f| f.counter()),
+                                    )),
+                                )
+                            },
+                        );
+                        // We don't care much about stream errors here since they could just
+                        // represent network disconnection, which is handled elsewhere.
+                        let _ = client.send_prepared(&msg.0);
+                        let _ = client.send_prepared(&msg.1);
+                        entity_comp_sync = Either::Right(msg);
+                    }
 
-                    for (_, entity, &uid, (&pos, last_pos), vel, ori, collider) in (
-                        region.entities(),
-                        &entities,
-                        uids,
-                        (&positions, last_pos.mask().maybe()),
-                        (&velocities, last_vel.mask().maybe()).maybe(),
-                        (&orientations, last_vel.mask().maybe()).maybe(),
-                        colliders.maybe(),
-                    )
-                        .join()
-                    {
-                        // Decide how regularly to send physics updates.
-                        let send_now = if client_entity == &entity {
-                            should_sync_client_physics(
-                                entity,
-                                &player_physics_settings,
-                                &players,
-                                &force_updates,
-                                is_rider,
+                    subscribers
+                        .iter()
+                        .map(|(client, _, client_entity, client_pos)| {
+                            let mut comp_sync_package = CompSyncPackage::new();
+                            let mut forced_package = CompSyncPackage::new();
+
+                            for (_, entity, &uid, (&pos, last_pos), vel, ori, collider) in (
+                                region.entities(),
+                                &entities,
+                                uids,
+                                (&positions, last_pos.mask().maybe()),
+                                (&velocities, last_vel.mask().maybe()).maybe(),
+                                (&orientations, last_vel.mask().maybe()).maybe(),
+                                colliders.maybe(),
                             )
-                        } else if matches!(collider, Some(Collider::Voxel { .. })) {
-                            // Things with a voxel collider (airships, etc.) need to have very
-                            // stable physics so we always send updated
-                            // for these where we can.
-                            true
-                        } else {
-                            // Throttle update rates for all other entities based on distance to
-                            // client
-                            let distance_sq = client_pos.0.distance_squared(pos.0);
-                            let id_staggered_tick = tick + entity.id() as u64;
-
-                            // More entities farther away so checks start there
-                            if distance_sq > 500.0f32.powi(2) {
-                                id_staggered_tick % 32 == 0
-                            } else if distance_sq > 300.0f32.powi(2) {
-                                id_staggered_tick % 16 == 0
-                            } else if distance_sq > 200.0f32.powi(2) {
-                                id_staggered_tick % 8 == 0
-                            } else if distance_sq > 120.0f32.powi(2) {
-                                id_staggered_tick % 6 == 0
-                            } else if distance_sq > 64.0f32.powi(2) {
-                                id_staggered_tick % 3 == 0
-                            } else if distance_sq > 24.0f32.powi(2) {
-                                id_staggered_tick % 2 == 0
-                            } else {
-                                true
+                                .join()
+                            {
+                                // Decide how regularly to send physics updates.
+                                let send_now = if client_entity == &entity {
+                                    should_sync_client_physics(entity, &force_updates, is_rider)
+                                } else if matches!(collider, Some(Collider::Voxel { .. })) {
+                                    // Things with a voxel collider (airships, etc.) need to have
+                                    // very stable physics so we always send updated for these
+                                    // where we can.
+                                    true
+                                } else {
+                                    // Throttle update rates for all other entities based on
+                                    // distance to client
+                                    let distance_sq = client_pos.0.distance_squared(pos.0);
+                                    let id_staggered_tick = tick + entity.id() as u64;
+
+                                    // More entities farther away so checks start there
+                                    if distance_sq > 500.0f32.powi(2) {
+                                        id_staggered_tick % 32 == 0
+                                    } else if distance_sq > 300.0f32.powi(2) {
+                                        id_staggered_tick % 16 == 0
+                                    } else if distance_sq > 200.0f32.powi(2) {
+                                        id_staggered_tick % 8 == 0
+                                    } else if distance_sq > 120.0f32.powi(2) {
+                                        id_staggered_tick % 6 == 0
+                                    } else if distance_sq > 64.0f32.powi(2) {
+                                        id_staggered_tick % 3 == 0
+                                    } else if distance_sq > 24.0f32.powi(2) {
+                                        id_staggered_tick % 2 == 0
+                                    } else {
+                                        true
+                                    }
+                                };
+
+                                // Forced updates of the client's own entity (e.g. teleports) must
+                                // not get lost, so they go through the reliable `CompSync`
+                                // message.
+                                let package = if client_entity == &entity
+                                    && is_forced(entity, &force_updates)
+                                {
+                                    &mut forced_package
+                                } else {
+                                    &mut comp_sync_package
+                                };
+                                add_physics_components(
+                                    send_now, package, uid, pos, last_pos, ori, vel,
+                                );
                             }
-                        };
-
-                        add_physics_components(
-                            send_now,
-                            &mut comp_sync_package,
-                            uid,
-                            pos,
-                            last_pos,
-                            ori,
-                            vel,
-                        );
-                    }
 
-                    // TODO: force update counter only needs to be sent once per frame (and only if
-                    // it changed, although it might not be worth having a separate message for
-                    // optionally sending it since individual messages may have a bandwidth
-                    // overhead), however, here we send it potentially 2 times per subscribed
-                    // region by including it in the `CompSync` message.
-                    client.send_fallible(ServerGeneral::CompSync(
-                        comp_sync_package,
-                        force_updates.get(*client_entity).map_or(0, |f| f.counter()),
-                    ));
-                }
-            },
-        );
+                            if !forced_package.is_empty() {
+                                client.send_fallible(ServerGeneral::CompSync(
+                                    forced_package,
+                                    force_updates.get(*client_entity).map_or(0, |f| f.counter()),
+                                ));
+                            }
+
+                            (*client_entity, comp_sync_package)
+                        })
+                        .collect::<Vec<_>>()
+                },
+            )
+            .collect::<Vec<_>>();
         drop(guard);
         job.cpu_stats.measure(common_ecs::ParMode::Single);
 
+        // Physics updates are sent over a stream that only keeps the newest message,
+        // so every client has to get all of its physics updates for this tick in a
+        // single package.
+        let mut physics_packages: HashMap<specs::Entity, CompSyncPackage<EcsCompPacket>> =
+            HashMap::new();
+        for (client_entity, package) in region_physics.into_iter().flatten() {
+            physics_packages
+                .entry(client_entity)
+                .or_insert_with(CompSyncPackage::new)
+                .comp_updates
+                .extend(package.comp_updates);
+        }
+
         // Sync components that are only synced for the client's own entity.
         for (entity, client, &uid, (maybe_pos, last_pos), vel, ori) in (
             &entities,
@@ -333,9 +360,38 @@ impl<'a> System<'a> for Sys {
                 include_all_comps,
             );
 
+            let mut prediction_ack = None;
             if include_all_comps && let Some(&pos) = maybe_pos {
-                let send_now = should_sync_client_physics(entity, &player_physics_settings, &players, &force_updates, is_rider);
-                add_physics_components(send_now, &mut comp_sync_package, uid, pos, last_pos, ori, vel);
+                let send_now = should_sync_client_physics(entity, &force_updates, is_rider);
+                let package = if is_forced(entity, &force_updates) {
+                    &mut comp_sync_package
+                } else {
+                    physics_packages.entry(entity).or_insert_with(CompSyncPackage::new)
+                };
+                add_physics_components(send_now, package, uid, pos, last_pos, ori, vel);
+
+                if let Some((&vel, _)) = vel
+                    && client_predicts_physics(
+                        entity,
+                        &player_physics_settings,
+                        &players,
+                        &force_updates,
+                        is_rider,
+                    )
+                {
+                    prediction_ack = Some(PredictionAck {
+                        input_seq: client.last_input_seq.load(Ordering::Relaxed),
+                        pos,
+                        vel,
+                    });
+                }
+            }
+
+            let physics_package = physics_packages
+                .remove(&entity)
+                .unwrap_or_else(CompSyncPackage::new);
+            if !physics_package.is_empty() || prediction_ack.is_some() {
+                client.send_fallible(ServerGeneral::PhysicsSync(physics_package, prediction_ack));
             }
 
             if !comp_sync_package.is_empty() {
@@ -449,6 +505,20 @@ impl<'a> System<'a> for Sys {
 /// Determines whether a client should receive an update about its own physics
 /// components.
 fn should_sync_client_physics(
+    entity: specs::Entity,
+    force_updates: &WriteStorage<'_, ForceUpdate>,
+    is_rider: &ReadStorage<'_, Is<Rider>>,
+) -> bool {
+    // Don't send client physics updates about itself unless force update is
+    // set or the client is riding something. Clients subject to
+    // server-authoritative physics get corrected via `PredictionAck` instead.
+    is_forced(entity, force_updates) || is_rider.contains(entity)
+}
+
+/// Determines whether a client is subject to server-authoritative physics and
+/// predicts the movement of its own entity, so it needs to be told about the
+/// outcome of its inputs.
+fn client_predicts_physics(
     entity: specs::Entity,
     player_physics_settings: &PlayerPhysicsSettings,
     players: &ReadStorage<'_, Player>,
@@ -459,12 +529,14 @@ fn should_sync_client_physics(
         .get(entity)
         .and_then(|p| player_physics_settings.settings.get(&p.uuid()).copied())
         .unwrap_or_default();
-    // Don't send client physics updates about itself unless force update is
-    // set or the client is subject to
-    // server-authoritative physics
+    player_physics_setting.server_authoritative()
+        && !should_sync_client_physics(entity, force_updates, is_rider)
+}
+
+/// Whether the physics components of `entity` were forcefully updated on the
+/// server this tick.
+fn is_forced(entity: specs::Entity, force_updates: &WriteStorage<'_, ForceUpdate>) -> bool {
     force_updates.get(entity).map_or(false, |f| f.is_forced())
-        || player_physics_setting.server_authoritative()
-        || is_rider.contains(entity)
 }
 
 /// Adds physics components if `send_now` is true or `Option<Last<T>>` is
@@ -474,7 +546,7 @@ fn should_sync_client_physics(
 /// modification.
 fn add_physics_components(
     send_now: bool,
-    comp_sync_package: &mut CompSyncPackage<common_net::msg::EcsCompPacket>,
+    comp_sync_package: &mut CompSyncPackage<EcsCompPacket>,
     uid: Uid,
     pos: Pos,
     last_pos: Option<u32>,
@@ -8,11 +8,12 @@ use crate::{
     character_creator,
     client::Client,
     persistence::{character_loader::CharacterLoader, character_updater::CharacterUpdater},
+    presence::HeldSession,
     EditableSettings,
 };
 use common::{
-    comp::{Admin, AdminRole, ChatType, Player, Presence, Waypoint},
-    event::{EventBus, ServerEvent},
+    comp::{Admin, AdminRole, ChatType, Player, Presence, PresenceKind, Waypoint},
+    event::{EventBus, ServerEvent, UpdateCharacterMetadata},
     resources::Time,
     terrain::TerrainChunkSize,
     uid::Uid,
@@ -35,6 +36,7 @@ impl Sys {
         players: &ReadStorage<'_, Player>,
         admins: &ReadStorage<'_, Admin>,
         presences: &ReadStorage<'_, Presence>,
+        held_sessions: &mut WriteStorage<'_, HeldSession>,
         editable_settings: &ReadExpect<'_, EditableSettings>,
         censor: &ReadExpect<'_, Arc<censor::Censor>>,
         automod: &AutoMod,
@@ -91,7 +93,27 @@ impl Sys {
                     // removal without this being mixed up (and e.g. telling other clients to
                     // delete the re-joined version) or requiring more complex handling to avoid
                     // this.
-                    if presences.contains(entity) {
+                    if held_sessions.contains(entity) {
+                        // The client reconnected, the character is still in the world
+                        if presences.get(entity).map(|presence| presence.kind)
+                            == Some(PresenceKind::Character(character_id))
+                        {
+                            debug!("resuming held character");
+                            held_sessions.remove(entity);
+                            client.send(ServerGeneral::CharacterDataLoadResult(Ok(
+                                UpdateCharacterMetadata::default(),
+                            )))?;
+                        } else {
+                            debug!("player picked another character than the held one");
+                            held_sessions.remove(entity);
+                            server_emitter.emit(ServerEvent::ExitIngame { entity });
+                            client.send(ServerGeneral::CharacterDataLoadResult(Err(
+                                "You have recently logged out, please wait a few seconds and try \
+                                 again"
+                                    .to_string(),
+                            )))?;
+                        }
+                    } else if presences.contains(entity) {
                         debug!("player already ingame, aborting");
                     } else if character_updater.has_pending_database_action(character_id)
                     {
@@ -255,6 +277,7 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Player>,
         ReadStorage<'a, Admin>,
         ReadStorage<'a, Presence>,
+        WriteStorage<'a, HeldSession>,
         ReadExpect<'a, EditableSettings>,
         ReadExpect<'a, Arc<censor::Censor>>,
         ReadExpect<'a, AutoMod>,
@@ -279,6 +302,7 @@ impl<'a> System<'a> for Sys {
             players,
             admins,
             presences,
+            mut held_sessions,
             editable_settings,
             censor,
             automod,
@@ -301,6 +325,7 @@ impl<'a> System<'a> for Sys {
                     &players,
                     &admins,
                     &presences,
+                    &mut held_sessions,
                     &editable_settings,
                     &censor,
                     &automod,
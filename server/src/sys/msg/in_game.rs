@@ -1,10 +1,15 @@
-#[cfg(feature = "persistent_world")]
-use crate::TerrainPersistence;
-use crate::{client::Client, Settings};
+use crate::{
+    client::Client,
+    metrics::PlayerMetrics,
+    movement_validation::{self, MovementContext, MovementReport, MovementTracker},
+    settings::MovementValidation,
+    Settings,
+};
 use common::{
     comp::{
-        Admin, AdminRole, CanBuild, ControlEvent, Controller, ForceUpdate, Health, Ori, Player,
-        Pos, Presence, PresenceKind, SkillSet, Vel,
+        Admin, AdminRole, Body, CanBuild, CharacterState, Collider, ControlEvent, Controller,
+        ForceUpdate, Health, Ori, PhysicsState, Player, Pos, Presence, PresenceKind, Scale,
+        SkillSet, Stats, Vel,
     },
     event::{EventBus, ServerEvent},
     link::Is,
@@ -12,34 +17,41 @@ use common::{
     resources::{DeltaTime, PlayerPhysicsSetting, PlayerPhysicsSettings},
     slowjob::SlowJobPool,
     terrain::TerrainGrid,
-    vol::ReadVol,
 };
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{ClientGeneral, ServerGeneral};
-use common_state::{AreasContainer, BlockChange, BuildArea};
+use common_state::{AreasContainer, BuildArea};
 use core::mem;
 use rayon::prelude::*;
-use specs::{Entities, Join, LendJoin, Read, ReadExpect, ReadStorage, Write, WriteStorage};
-use std::{borrow::Cow, time::Instant};
+use specs::{
+    shred::ResourceId, Entities, Join, LendJoin, Read, ReadExpect, ReadStorage, SystemData, World,
+    Write, WriteStorage,
+};
+use std::{borrow::Cow, sync::atomic::Ordering, time::Instant};
 use tracing::{debug, trace, warn};
 use vek::*;
 
-#[cfg(feature = "persistent_world")]
-pub type TerrainPersistenceData<'a> = Option<Write<'a, TerrainPersistence>>;
-#[cfg(not(feature = "persistent_world"))]
-pub type TerrainPersistenceData<'a> = core::marker::PhantomData<&'a mut ()>;
-
-// NOTE: These writes are considered "rare", meaning (currently) that they are
-// admin-gated features that players shouldn't normally access, and which we're
-// not that concerned about the performance of when two players try to use them
-// at once.
-//
-// In such cases, we're okay putting them behind a mutex and penalizing the
-// system if they're actually used concurrently by lots of users.  Please do not
-// put less rare writes here, unless you want to serialize the system!
-struct RareWrites<'a, 'b> {
-    block_changes: &'b mut BlockChange,
-    _terrain_persistence: &'b mut TerrainPersistenceData<'a>,
+/// Whether `entity` is in build mode and allowed to build at `pos`.
+///
+/// The block change itself is applied when the resulting server event is
+/// handled, so that plugins get a chance to veto it.
+fn can_build_at(
+    entity: specs::Entity,
+    pos: Vec3<i32>,
+    can_build: &ReadStorage<'_, CanBuild>,
+    build_areas: &AreasContainer<BuildArea>,
+) -> bool {
+    can_build.get(entity).map_or(false, |comp_can_build| {
+        comp_can_build.enabled
+            && comp_can_build.build_areas.iter().any(|area| {
+                build_areas
+                    .areas()
+                    .get(*area)
+                    // TODO: Make this an exclusive check on the upper bound of the AABB
+                    // Vek defaults to inclusive which is not optimal
+                    .map_or(false, |aabb| aabb.contains_point(pos))
+            })
+    })
 }
 
 impl Sys {
@@ -49,14 +61,12 @@ impl Sys {
         entity: specs::Entity,
         client: &Client,
         maybe_presence: &mut Option<&mut Presence>,
-        terrain: &ReadExpect<'_, TerrainGrid>,
         can_build: &ReadStorage<'_, CanBuild>,
         is_rider: &ReadStorage<'_, Is<Rider>>,
         is_volume_rider: &ReadStorage<'_, Is<VolumeRider>>,
         force_update: Option<&&mut ForceUpdate>,
         skill_set: &mut Option<Cow<'_, SkillSet>>,
         healths: &ReadStorage<'_, Health>,
-        rare_writes: &parking_lot::Mutex<RareWrites<'_, '_>>,
         position: Option<&mut Pos>,
         controller: Option<&mut Controller>,
         settings: &Read<'_, Settings>,
@@ -93,10 +103,11 @@ impl Sys {
                     client.send(ServerGeneral::SetViewDistance(clamped_vds.terrain))?;
                 }
             },
-            ClientGeneral::ControllerInputs(inputs) => {
+            ClientGeneral::ControllerInputs(inputs, input_seq) => {
                 if presence.kind.controlling_char() {
                     if let Some(controller) = controller {
                         controller.inputs.update_with_new(*inputs);
+                        client.received_input_seq.store(input_seq, Ordering::Relaxed);
                     }
                 }
             },
@@ -132,58 +143,17 @@ impl Sys {
                 }
             },
             ClientGeneral::BreakBlock(pos) => {
-                if let Some(comp_can_build) = can_build.get(entity) {
-                    if comp_can_build.enabled {
-                        for area in comp_can_build.build_areas.iter() {
-                            if let Some(old_block) = build_areas
-                                .areas()
-                                .get(*area)
-                                // TODO: Make this an exclusive check on the upper bound of the AABB
-                                // Vek defaults to inclusive which is not optimal
-                                .filter(|aabb| aabb.contains_point(pos))
-                                .and_then(|_| terrain.get(pos).ok())
-                            {
-                                let new_block = old_block.into_vacant();
-                                // Take the rare writes lock as briefly as possible.
-                                let mut guard = rare_writes.lock();
-                                let _was_set = guard.block_changes.try_set(pos, new_block).is_some();
-                                #[cfg(feature = "persistent_world")]
-                                if _was_set {
-                                    if let Some(terrain_persistence) = guard._terrain_persistence.as_mut()
-                                    {
-                                        terrain_persistence.set_block(pos, new_block);
-                                    }
-                                }
-                            }
-                        }
-                    }
+                if can_build_at(entity, pos, can_build, build_areas) {
+                    server_emitter.emit(ServerEvent::BreakBlock { entity, pos });
                 }
             },
             ClientGeneral::PlaceBlock(pos, new_block) => {
-                if let Some(comp_can_build) = can_build.get(entity) {
-                    if comp_can_build.enabled {
-                        for area in comp_can_build.build_areas.iter() {
-                            if build_areas
-                                .areas()
-                                .get(*area)
-                                // TODO: Make this an exclusive check on the upper bound of the AABB
-                                // Vek defaults to inclusive which is not optimal
-                                .filter(|aabb| aabb.contains_point(pos))
-                                .is_some()
-                            {
-                                // Take the rare writes lock as briefly as possible.
-                                let mut guard = rare_writes.lock();
-                                let _was_set = guard.block_changes.try_set(pos, new_block).is_some();
-                                #[cfg(feature = "persistent_world")]
-                                if _was_set {
-                                    if let Some(terrain_persistence) = guard._terrain_persistence.as_mut()
-                                    {
-                                        terrain_persistence.set_block(pos, new_block);
-                                    }
-                                }
-                            }
-                        }
-                    }
+                if can_build_at(entity, pos, can_build, build_areas) {
+                    server_emitter.emit(ServerEvent::PlaceBlock {
+                        entity,
+                        pos,
+                        new_block,
+                    });
                 }
             },
             ClientGeneral::UnlockSkill(skill) => {
@@ -210,6 +180,13 @@ impl Sys {
             ClientGeneral::UpdateMapMarker(update) => {
                 server_emitter.emit(ServerEvent::UpdateMapMarker { entity, update });
             },
+            ClientGeneral::QuestResponse(quest, response) => {
+                server_emitter.emit(ServerEvent::QuestResponse {
+                    entity,
+                    quest,
+                    response,
+                });
+            },
             ClientGeneral::SpectatePosition(pos) => {
                 if let Some(admin) = maybe_admin && admin.0 >= AdminRole::Moderator && presence.kind == PresenceKind::Spectator {
                     if let Some(position) = position {
@@ -239,6 +216,21 @@ impl Sys {
     }
 }
 
+/// What's needed to validate the movement reported by clients.
+#[derive(SystemData)]
+pub struct MovementData<'a> {
+    terrain: ReadExpect<'a, TerrainGrid>,
+    bodies: ReadStorage<'a, Body>,
+    stats: ReadStorage<'a, Stats>,
+    character_states: ReadStorage<'a, CharacterState>,
+    physics_states: ReadStorage<'a, PhysicsState>,
+    colliders: ReadStorage<'a, Collider>,
+    scales: ReadStorage<'a, Scale>,
+    trackers: WriteStorage<'a, MovementTracker>,
+    movement_report: Write<'a, MovementReport>,
+    player_metrics: ReadExpect<'a, PlayerMetrics>,
+}
+
 /// This system will handle new messages from clients
 #[derive(Default)]
 pub struct Sys;
@@ -247,7 +239,6 @@ impl<'a> System<'a> for Sys {
     type SystemData = (
         Entities<'a>,
         Read<'a, EventBus<ServerEvent>>,
-        ReadExpect<'a, TerrainGrid>,
         ReadExpect<'a, SlowJobPool>,
         ReadStorage<'a, CanBuild>,
         WriteStorage<'a, ForceUpdate>,
@@ -255,7 +246,6 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Is<VolumeRider>>,
         WriteStorage<'a, SkillSet>,
         ReadStorage<'a, Health>,
-        Write<'a, BlockChange>,
         WriteStorage<'a, Pos>,
         WriteStorage<'a, Vel>,
         WriteStorage<'a, Ori>,
@@ -266,9 +256,9 @@ impl<'a> System<'a> for Sys {
         Read<'a, Settings>,
         Read<'a, AreasContainer<BuildArea>>,
         Write<'a, PlayerPhysicsSettings>,
-        TerrainPersistenceData<'a>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, Admin>,
+        MovementData<'a>,
     );
 
     const NAME: &'static str = "msg::in_game";
@@ -280,7 +270,6 @@ impl<'a> System<'a> for Sys {
         (
             entities,
             server_event_bus,
-            terrain,
             slow_jobs,
             can_build,
             mut force_updates,
@@ -288,7 +277,6 @@ impl<'a> System<'a> for Sys {
             is_volume_rider,
             mut skill_sets,
             healths,
-            mut block_changes,
             mut positions,
             mut velocities,
             mut orientations,
@@ -299,20 +287,25 @@ impl<'a> System<'a> for Sys {
             settings,
             build_areas,
             mut player_physics_settings_,
-            mut terrain_persistence,
             players,
             admins,
+            movement_data,
         ): Self::SystemData,
     ) {
+        let MovementData {
+            terrain,
+            bodies,
+            stats,
+            character_states,
+            physics_states,
+            colliders,
+            scales,
+            mut trackers,
+            mut movement_report,
+            player_metrics,
+        } = movement_data;
         let time_for_vd_changes = Instant::now();
 
-        // NOTE: stdlib mutex is more than good enough on Linux and (probably) Windows,
-        // but not Mac.
-        let rare_writes = parking_lot::Mutex::new(RareWrites {
-            block_changes: &mut block_changes,
-            _terrain_persistence: &mut terrain_persistence,
-        });
-
         let player_physics_settings = &*player_physics_settings_;
         let mut deferred_updates = (
             &entities,
@@ -326,6 +319,7 @@ impl<'a> System<'a> for Sys {
             (&mut orientations).maybe(),
             (&mut controllers).maybe(),
             (&mut force_updates).maybe(),
+            (&mut trackers).maybe(),
         )
             .join()
             // NOTE: Required because Specs has very poor work splitting for sparse joins.
@@ -344,6 +338,7 @@ impl<'a> System<'a> for Sys {
                     ref mut ori,
                     ref mut controller,
                     ref mut force_update,
+                    ref mut tracker,
                 )| {
                     let old_player_physics_setting = maybe_player.map(|p| {
                         player_physics_settings
@@ -358,20 +353,23 @@ impl<'a> System<'a> for Sys {
                     let mut clearable_maybe_presence = maybe_presence.as_deref_mut();
                     let mut skill_set = skill_set.map(Cow::Borrowed);
                     let mut player_physics = None;
+                    let mut movement_violation = None;
+                    // This system runs after the physics of this tick, which therefore simulated
+                    // all inputs received in previous ticks.
+                    let simulated_input_seq = client.received_input_seq.load(Ordering::Relaxed);
+                    client.last_input_seq.store(simulated_input_seq, Ordering::Relaxed);
                     let _ = super::try_recv_all(client, 2, |client, msg| {
                         Self::handle_client_in_game_msg(
                             server_emitter,
                             entity,
                             client,
                             &mut clearable_maybe_presence,
-                            &terrain,
                             &can_build,
                             &is_rider,
                             &is_volume_rider,
                             force_update.as_ref(),
                             &mut skill_set,
                             &healths,
-                            &rare_writes,
                             pos.as_deref_mut(),
                             controller.as_deref_mut(),
                             &settings,
@@ -389,64 +387,47 @@ impl<'a> System<'a> for Sys {
                         && let Some(old_vel) = vel.as_deref_mut()
                         && let Some(old_ori) = ori.as_deref_mut()
                     {
-                        enum Rejection {
-                            TooFar { old: Vec3<f32>, new: Vec3<f32> },
-                            TooFast { vel: Vec3<f32> },
-                        }
-
-                        let rejection = if maybe_admin.is_some() {
+                        let validation = settings.moderation.movement_validation;
+                        // Impossible speeds and teleports are always rejected, the setting only
+                        // decides about the more thorough checks
+                        let limit_violation = if maybe_admin.is_some() {
                             None
                         } else {
-                            // Reminder: review these frequently to ensure they're reasonable
-                            const MAX_H_VELOCITY: f32 = 75.0;
-                            const MAX_V_VELOCITY: std::ops::Range<f32> = -100.0..80.0;
-
-                            'rejection: {
-                                let is_velocity_ok = new_vel.0.xy().magnitude_squared() < MAX_H_VELOCITY.powi(2)
-                                    && MAX_V_VELOCITY.contains(&new_vel.0.z);
-
-                                if !is_velocity_ok {
-                                    break 'rejection Some(Rejection::TooFast { vel: new_vel.0 });
-                                }
-
-                                // How far the player is permitted to stray from the correct position (perhaps due to
-                                // latency problems).
-                                const POSITION_THRESHOLD: f32 = 16.0;
-
-                                // The position can either be sensible with respect to either the old or the new
-                                // velocity such that we don't punish for edge cases after a sudden change
-                                let is_position_ok = [old_vel.0, new_vel.0]
-                                    .into_iter()
-                                    .any(|ref_vel| {
-                                        let rpos = new_pos.0 - old_pos.0;
-                                        // Determine whether the change in position is broadly consistent with both
-                                        // the magnitude and direction of the velocity, with appropriate thresholds.
-                                        LineSegment3 {
-                                            start: Vec3::zero(),
-                                            end: ref_vel * dt.0,
-                                        }
-                                            .projected_point(rpos)
-                                            // + 1.5 accounts for minor changes in position without corresponding
-                                            // velocity like block hopping/snapping
-                                            .distance_squared(rpos) < (rpos.magnitude() * 0.5 + 1.5 + POSITION_THRESHOLD).powi(2)
-                                    });
-
-                                if !is_position_ok {
-                                    break 'rejection Some(Rejection::TooFar { old: old_pos.0, new: new_pos.0 });
-                                }
-
-                                None
-                            }
+                            movement_validation::exceeds_limits(dt.0, (*old_pos, *old_vel), (new_pos, new_vel))
+                        };
+                        let violation = if limit_violation.is_some() {
+                            limit_violation
+                        } else if maybe_admin.is_some() || validation == MovementValidation::Off {
+                            None
+                        } else {
+                            let ctx = MovementContext {
+                                dt: dt.0,
+                                body: bodies.get(entity),
+                                stats: stats.get(entity),
+                                character_state: character_states.get(entity),
+                                physics_state: physics_states.get(entity),
+                                collider: colliders.get(entity),
+                                scale: scales.get(entity).map_or(1.0, |s| s.0),
+                                terrain: &*terrain,
+                                force_counter: force_update.as_ref().map_or(0, |fu| fu.counter()),
+                            };
+                            let mut fallback_tracker = MovementTracker::default();
+                            tracker
+                                .as_deref_mut()
+                                .unwrap_or(&mut fallback_tracker)
+                                .validate(&ctx, (*old_pos, *old_vel), (new_pos, new_vel))
                         };
+                        let rubber_band = limit_violation.is_some()
+                            || (violation.is_some() && validation == MovementValidation::RubberBand);
 
-                        if let Some(rejection) = rejection {
-                            // TODO: Log when false positives aren't generated often
+                        if let Some(violation) = violation {
                             let alias = maybe_player.map(|p| &p.alias);
-                            match rejection {
-                                Rejection::TooFar { old, new } => warn!("Rejected physics for player {alias:?} (new position {new:?} is too far from old position {old:?})"),
-                                Rejection::TooFast { vel } => warn!("Rejected physics for player {alias:?} (new velocity {vel:?} is too fast)"),
-                            }
+                            warn!("Movement violation by player {alias:?}: {violation} (from {:?} to {:?})", old_pos.0, new_pos.0);
+                            movement_violation = maybe_player
+                                .map(|p| (p.uuid(), p.alias.clone(), new_pos.0, violation, rubber_band));
+                        }
 
+                        if rubber_band {
                             /*
                             // Perhaps this is overzealous?
                             if let Some(mut setting) = new_player_physics_setting.as_mut() {
@@ -484,12 +465,12 @@ impl<'a> System<'a> for Sys {
                     let physics_update = maybe_player.map(|p| p.uuid())
                         .zip(new_player_physics_setting
                              .filter(|_| old_player_physics_setting != new_player_physics_setting));
-                    (skill_set_update, physics_update)
+                    (skill_set_update, physics_update, movement_violation)
                 },
             )
             // NOTE: Would be nice to combine this with the map_init somehow, but I'm not sure if
             // that's possible.
-            .filter(|(x, y)| x.is_some() || y.is_some())
+            .filter(|(x, y, z)| x.is_some() || y.is_some() || z.is_some())
             // NOTE: I feel like we shouldn't actually need to allocate here, but hopefully this
             // doesn't turn out to be important as there shouldn't be that many connected clients.
             // The reason we can't just use unzip is that the two sides might be different lengths.
@@ -502,9 +483,8 @@ impl<'a> System<'a> for Sys {
         // per uuid, so the physics update is sound and doesn't depend on evaluation
         // order, even though we're not updating directly by entity or uid (note that
         // for a given entity, we process messages serially).
-        deferred_updates
-            .iter_mut()
-            .for_each(|(skill_set_update, physics_update)| {
+        deferred_updates.iter_mut().for_each(
+            |(skill_set_update, physics_update, movement_violation)| {
                 if let Some((entity, new_skill_set)) = skill_set_update {
                     // We know this exists, because we already iterated over it with the skillset
                     // lock taken, so we can ignore the error.
@@ -523,7 +503,15 @@ impl<'a> System<'a> for Sys {
                         .settings
                         .insert(uuid, player_physics_setting);
                 }
-            });
+                if let Some((uuid, alias, pos, violation, rubber_banded)) = movement_violation {
+                    player_metrics
+                        .movement_violations
+                        .with_label_values(&[violation.kind()])
+                        .inc();
+                    movement_report.record(*uuid, alias, *pos, *violation, *rubber_banded);
+                }
+            },
+        );
         // Finally, drop the deferred updates in another thread.
         slow_jobs.spawn("CHUNK_DROP", move || {
             drop(deferred_updates);
@@ -10,7 +10,8 @@ use crate::{
     sys::{loot, pets},
 };
 use common_ecs::{dispatch, System};
-use serde::de::DeserializeOwned;
+use common_systems::phys;
+use serde::{de::DeserializeOwned, Serialize};
 use specs::DispatcherBuilder;
 
 pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
@@ -18,7 +19,8 @@ pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
     // disconnect then.
     dispatch::<character_screen::Sys>(dispatch_builder, &[]);
     dispatch::<general::Sys>(dispatch_builder, &[]);
-    dispatch::<in_game::Sys>(dispatch_builder, &[]);
+    // After physics, so the acknowledged inputs are the ones it has simulated
+    dispatch::<in_game::Sys>(dispatch_builder, &[&phys::Sys::sys_name()]);
     dispatch::<ping::Sys>(dispatch_builder, &[&general::Sys::sys_name()]);
     dispatch::<register::Sys>(dispatch_builder, &[]);
     dispatch::<terrain::Sys>(dispatch_builder, &[]);
@@ -34,7 +36,7 @@ pub(crate) fn try_recv_all<M, F>(
     mut f: F,
 ) -> Result<u64, crate::error::Error>
 where
-    M: DeserializeOwned,
+    M: DeserializeOwned + Serialize,
     F: FnMut(&Client, M) -> Result<(), crate::error::Error>,
 {
     let mut cnt = 0u64;
@@ -2,6 +2,7 @@ use crate::{
     client::Client,
     login_provider::{LoginProvider, PendingLogin},
     metrics::PlayerMetrics,
+    presence::{HeldSession, ResumableLogins},
     sys::sentinel::TrackedStorages,
     EditableSettings, Settings,
 };
@@ -9,7 +10,7 @@ use common::{
     comp::{self, Admin, Player, Stats},
     event::{EventBus, ServerEvent},
     recipe::{default_component_recipe_book, default_recipe_book, default_repair_recipe_book},
-    resources::TimeOfDay,
+    resources::{Time, TimeOfDay},
     shared_server_config::ServerConstants,
     uid::{IdMaps, Uid},
 };
@@ -19,13 +20,13 @@ use common_net::msg::{
     CharacterInfo, ClientRegister, DisconnectReason, PlayerInfo, PlayerListUpdate, RegisterError,
     ServerGeneral, ServerInit, WorldMapMsg,
 };
-use hashbrown::{hash_map, HashMap};
+use hashbrown::{hash_map, HashMap, HashSet};
 use itertools::Either;
 use plugin_api::Health;
 use rayon::prelude::*;
 use specs::{
-    shred::ResourceId, Entities, Join, LendJoin, ParJoin, Read, ReadExpect, ReadStorage,
-    SystemData, World, WriteStorage,
+    shred::ResourceId, Entities, Entity, Join, LendJoin, ParJoin, Read, ReadExpect, ReadStorage,
+    SystemData, World, Write, WriteStorage,
 };
 use tracing::{debug, info, trace, warn};
 
@@ -42,11 +43,13 @@ pub struct ReadData<'a> {
     entities: Entities<'a>,
     stats: ReadStorage<'a, Stats>,
     uids: ReadStorage<'a, Uid>,
+    held_sessions: ReadStorage<'a, HeldSession>,
     server_event_bus: Read<'a, EventBus<ServerEvent>>,
     login_provider: ReadExpect<'a, LoginProvider>,
     player_metrics: ReadExpect<'a, PlayerMetrics>,
     settings: ReadExpect<'a, Settings>,
     editable_settings: ReadExpect<'a, EditableSettings>,
+    time: Read<'a, Time>,
     time_of_day: Read<'a, TimeOfDay>,
     material_stats: ReadExpect<'a, comp::item::MaterialStatManifest>,
     ability_map: ReadExpect<'a, comp::item::tool::AbilityMap>,
@@ -67,6 +70,7 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Client>,
         WriteStorage<'a, Player>,
         WriteStorage<'a, PendingLogin>,
+        Write<'a, ResumableLogins>,
     );
 
     const NAME: &'static str = "msg::register";
@@ -75,7 +79,14 @@ impl<'a> System<'a> for Sys {
 
     fn run(
         _job: &mut Job<Self>,
-        (event_bus, read_data, mut clients, mut players, mut pending_logins): Self::SystemData,
+        (
+            event_bus,
+            read_data,
+            mut clients,
+            mut players,
+            mut pending_logins,
+            mut resumable_logins,
+        ): Self::SystemData,
     ) {
         // Player list to send new players, and lookup from UUID to entity (so we don't
         // have to do a linear scan over all entities on each login to see if
@@ -128,15 +139,46 @@ impl<'a> System<'a> for Sys {
         ));
 
         // defer auth lockup
+        let mut resumes = Vec::new();
         for (entity, client) in (&read_data.entities, &mut clients).join() {
-            let _ = super::try_recv_all(client, 0, |_, msg: ClientRegister| {
+            let _ = super::try_recv_all(client, 0, |client, msg: ClientRegister| {
                 trace!(?msg.token_or_username, "defer auth lockup");
+                if let Some(recorder) = &client.recorder {
+                    recorder.record_register();
+                }
+                if let Some(token) = msg.resume_token {
+                    resumes.push((entity, token));
+                    return Ok(());
+                }
                 let pending = read_data.login_provider.verify(&msg.token_or_username);
                 let _ = pending_logins.insert(entity, pending);
                 Ok(())
             });
         }
 
+        // Clients whose connection dropped log in again with the token they were
+        // issued. If we didn't notice that the connection dropped yet, the old
+        // client still has it, and gets disconnected like a dropped one below.
+        let mut dropped_clients = HashSet::new();
+        for (entity, token) in resumes {
+            let login = resumable_logins.take(token, *read_data.time).or_else(|| {
+                (&read_data.entities, &clients, &players)
+                    .join()
+                    .find(|(_, client, _)| client.resume_token == token)
+                    .map(|(old_entity, _, player)| {
+                        dropped_clients.insert(old_entity);
+                        (player.alias.clone(), player.uuid())
+                    })
+            });
+            let pending = match login {
+                Some((username, uuid)) => PendingLogin::new_success(username, uuid),
+                None => PendingLogin::new_error(RegisterError::AuthError(
+                    "The session can't be resumed anymore".to_string(),
+                )),
+            };
+            let _ = pending_logins.insert(entity, pending);
+        }
+
         let old_player_count = player_list.len();
 
         // NOTE: this is just default value.
@@ -144,6 +186,41 @@ impl<'a> System<'a> for Sys {
         // It will be overwritten in ServerExt::update_character_data.
         let battle_mode = read_data.settings.gameplay.battle_mode.default_mode();
 
+        // Tell the client its request was successful, and send it all the tracked
+        // components currently attached to its entity as well as synced resources
+        // (currently only `TimeOfDay`) and the player list.
+        let send_initial_sync =
+            |client: &Client, entity: Entity, uid: Uid| -> Result<(), crate::error::Error> {
+                client.send(Ok(client.resume_token))?;
+
+                debug!("Starting initial sync with client.");
+                client.send(ServerInit::GameSync {
+                    // Send client their entity
+                    entity_package: read_data
+                        .trackers
+                        .create_entity_package_with_uid(entity, uid, None, None, None),
+                    time_of_day: *read_data.time_of_day,
+                    max_group_size: read_data.settings.max_player_group_size,
+                    client_timeout: read_data.settings.client_timeout,
+                    world_map: (*read_data.map).clone(),
+                    recipe_book: default_recipe_book().cloned(),
+                    component_recipe_book: default_component_recipe_book().cloned(),
+                    repair_recipe_book: default_repair_recipe_book().cloned(),
+                    material_stats: (*read_data.material_stats).clone(),
+                    ability_map: (*read_data.ability_map).clone(),
+                    server_constants: ServerConstants {
+                        day_cycle_coefficient: read_data.settings.day_cycle_coefficient(),
+                    },
+                })?;
+                debug!("Done initial sync with client.");
+
+                // Send initial player list
+                client.send(ServerGeneral::PlayerListUpdate(PlayerListUpdate::Init(
+                    player_list.clone(),
+                )))?;
+                Ok(())
+            };
+
         (
             &read_data.entities,
             &read_data.uids,
@@ -160,6 +237,9 @@ impl<'a> System<'a> for Sys {
                     prof_span!("msg::register login");
                     if let Err(e) = || -> Result<(), crate::error::Error> {
                         let extra_checks = |username: String, uuid: authc::Uuid| {
+                            if let Some(recorder) = &client.recorder {
+                                recorder.record_login(&username, uuid);
+                            }
                             // We construct a few things outside the lock to reduce contention.
                             let pending_login =
                                 PendingLogin::new_success(username.clone(), uuid);
@@ -218,6 +298,7 @@ impl<'a> System<'a> for Sys {
                             &read_data.editable_settings.admins,
                             &read_data.editable_settings.whitelist,
                             &read_data.editable_settings.banlist,
+                            client.remote_ip(),
                             extra_checks,
                         ) {
                             None => return Ok(()),
@@ -280,6 +361,20 @@ impl<'a> System<'a> for Sys {
                                             "You have logged in from another location.",
                                         )),
                                     ));
+                                } else if read_data.held_sessions.contains(old_entity)
+                                    && let Some(held_uid) = read_data.uids.get(old_entity)
+                                {
+                                    drop(new_players_guard);
+                                    // The player reconnected while their character was held
+                                    // in the world, hand it back instead of creating a new
+                                    // player.
+                                    debug!(?old_entity, "Resuming held session");
+                                    send_initial_sync(client, old_entity, *held_uid)?;
+                                    server_emitter.emit(ServerEvent::ResumeSession {
+                                        entity,
+                                        held: old_entity,
+                                    });
+                                    return Ok(());
                                 } else {
                                     drop(new_players_guard);
                                     // A player without a client is strange, so we don't really want
@@ -291,10 +386,15 @@ impl<'a> System<'a> for Sys {
                                         old_entity
                                     );
                                 }
-                                // Remove old client
+                                // Remove old client, keeping its character if its connection
+                                // dropped.
+                                let reason = if dropped_clients.contains(&old_entity) {
+                                    common::comp::DisconnectReason::Timeout
+                                } else {
+                                    common::comp::DisconnectReason::NewerLogin
+                                };
                                 server_emitter.emit(ServerEvent::ClientDisconnect(
-                                    old_entity,
-                                    common::comp::DisconnectReason::NewerLogin,
+                                    old_entity, reason,
                                 ));
                                 return Ok(());
                             },
@@ -317,38 +417,7 @@ impl<'a> System<'a> for Sys {
                         drop(new_players_guard);
                         read_data.player_metrics.players_connected.inc();
 
-                        // Tell the client its request was successful.
-                        client.send(Ok(()))?;
-
-                        // Send client all the tracked components currently attached to its entity
-                        // as well as synced resources (currently only `TimeOfDay`)
-                        debug!("Starting initial sync with client.");
-                        client.send(ServerInit::GameSync {
-                            // Send client their entity
-                            entity_package: read_data
-                                .trackers
-                                .create_entity_package_with_uid(entity, *uid, None, None, None),
-                            time_of_day: *read_data.time_of_day,
-                            max_group_size: read_data.settings.max_player_group_size,
-                            client_timeout: read_data.settings.client_timeout,
-                            world_map: (*read_data.map).clone(),
-                            recipe_book: default_recipe_book().cloned(),
-                            component_recipe_book: default_component_recipe_book().cloned(),
-                            repair_recipe_book: default_repair_recipe_book().cloned(),
-                            material_stats: (*read_data.material_stats).clone(),
-                            ability_map: (*read_data.ability_map).clone(),
-                            server_constants: ServerConstants {
-                                day_cycle_coefficient: read_data.settings.day_cycle_coefficient()
-                            },
-                        })?;
-                        debug!("Done initial sync with client.");
-
-                        // Send initial player list
-                        client.send(ServerGeneral::PlayerListUpdate(PlayerListUpdate::Init(
-                            player_list.clone(),
-                        )))?;
-
-                        Ok(())
+                        send_initial_sync(client, entity, *uid)
                     }() {
                         trace!(?e, "failed to process register");
                     }
@@ -1,5 +1,9 @@
 use crate::{
-    chunk_serialize::ChunkSendEntry, client::Client, lod::Lod, metrics::NetworkRequestMetrics,
+    chunk_serialize::ChunkSendEntry,
+    client::Client,
+    lod::Lod,
+    metrics::NetworkRequestMetrics,
+    terrain_versions::{TerrainVersions, MAX_DIFF_BLOCKS},
     ChunkRequest,
 };
 use common::{
@@ -10,7 +14,7 @@ use common::{
     vol::RectVolSize,
 };
 use common_ecs::{Job, Origin, ParMode, Phase, System};
-use common_net::msg::{ClientGeneral, ServerGeneral};
+use common_net::msg::{ClientGeneral, CompressedData, ServerGeneral};
 use rayon::prelude::*;
 use specs::{Entities, Join, LendJoin, Read, ReadExpect, ReadStorage, Write, WriteStorage};
 use tracing::{debug, trace};
@@ -24,6 +28,7 @@ impl<'a> System<'a> for Sys {
         Read<'a, EventBus<ServerEvent>>,
         ReadExpect<'a, EventBus<ChunkSendEntry>>,
         ReadExpect<'a, TerrainGrid>,
+        ReadExpect<'a, TerrainVersions>,
         ReadExpect<'a, Lod>,
         ReadExpect<'a, NetworkRequestMetrics>,
         Write<'a, Vec<ChunkRequest>>,
@@ -43,6 +48,7 @@ impl<'a> System<'a> for Sys {
             server_event_bus,
             chunk_send_bus,
             terrain,
+            terrain_versions,
             lod,
             network_metrics,
             mut chunk_requests,
@@ -73,7 +79,7 @@ impl<'a> System<'a> for Sys {
                             },
                         };
                         match msg {
-                            ClientGeneral::TerrainChunkRequest { key } => {
+                            ClientGeneral::TerrainChunkRequest { key, version } => {
                                 let in_vd = if let Some(pos) = positions.get(entity) {
                                     pos.0.xy().map(|e| e as f64).distance_squared(
                                         key.map(|e| e as f64 + 0.5)
@@ -88,10 +94,33 @@ impl<'a> System<'a> for Sys {
                                 if in_vd {
                                     if terrain.get_key_arc(key).is_some() {
                                         network_metrics.chunks_served_from_memory.inc();
-                                        chunk_send_emitter.emit(ChunkSendEntry {
-                                            chunk_key: key,
-                                            entity,
-                                        });
+                                        // If the client has an older version of the chunk, the
+                                        // blocks that changed since might be enough
+                                        let diff = version
+                                            .zip(terrain_versions.version(key))
+                                            .and_then(|(base, version)| {
+                                                Some((
+                                                    base,
+                                                    version,
+                                                    terrain_versions.diff(key, base)?,
+                                                ))
+                                            })
+                                            .filter(|(_, _, blocks)| {
+                                                blocks.len() <= MAX_DIFF_BLOCKS
+                                            });
+                                        if let Some((base, version, blocks)) = diff {
+                                            client.send(ServerGeneral::TerrainChunkDiff {
+                                                key,
+                                                base,
+                                                version,
+                                                blocks: CompressedData::compress(&blocks, 1),
+                                            })?;
+                                        } else {
+                                            chunk_send_emitter.emit(ChunkSendEntry {
+                                                chunk_key: key,
+                                                entity,
+                                            });
+                                        }
                                     } else {
                                         network_metrics.chunks_generation_triggered.inc();
                                         chunk_requests.push(ChunkRequest { entity, key });
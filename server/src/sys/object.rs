@@ -4,7 +4,7 @@ use common::{
     effect::Effect,
     event::{EventBus, ServerEvent},
     outcome::Outcome,
-    resources::{DeltaTime, Time},
+    resources::{DeltaTime, RngSeed, Time},
     CachedSpatialGrid, Damage, DamageKind, DamageSource, Explosion, RadiusEffect,
 };
 use common_ecs::{Job, Origin, Phase, System};
@@ -19,6 +19,7 @@ impl<'a> System<'a> for Sys {
         Entities<'a>,
         Read<'a, DeltaTime>,
         Read<'a, Time>,
+        Read<'a, RngSeed>,
         Read<'a, EventBus<ServerEvent>>,
         Read<'a, EventBus<Outcome>>,
         Read<'a, CachedSpatialGrid>,
@@ -40,6 +41,7 @@ impl<'a> System<'a> for Sys {
             entities,
             _dt,
             time,
+            rng_seed,
             server_bus,
             outcome_bus,
             spatial_grid,
@@ -99,7 +101,7 @@ impl<'a> System<'a> for Sys {
                             use rand::Rng;
                             use std::{f32::consts::PI, time::Duration};
                             use vek::Vec3;
-                            let mut rng = rand::thread_rng();
+                            let mut rng = rng_seed.rng(*time, (Self::NAME, entity));
                             // Note that if the expected fireworks per firework is > 1, this will
                             // eventually cause enough server lag that more players can't log in.
                             let thresholds: &[(f32, usize)] = &[(0.25, 2), (0.7, 1)];
@@ -7,7 +7,8 @@ use world::{IndexOwned, World};
 
 use crate::{
     chunk_generator::ChunkGenerator, chunk_serialize::ChunkSendEntry, client::Client,
-    presence::RepositionOnChunkLoad, rtsim, settings::Settings, ChunkRequest, Tick,
+    presence::RepositionOnChunkLoad, rtsim, settings::Settings, terrain_versions::TerrainVersions,
+    ChunkRequest, Tick,
 };
 use common::{
     calendar::Calendar,
@@ -74,6 +75,7 @@ impl<'a> System<'a> for Sys {
         WriteExpect<'a, ChunkGenerator>,
         WriteExpect<'a, TerrainGrid>,
         Write<'a, TerrainChanges>,
+        WriteExpect<'a, TerrainVersions>,
         Write<'a, Vec<ChunkRequest>>,
         RtSimData<'a>,
         TerrainPersistenceData<'a>,
@@ -106,6 +108,7 @@ impl<'a> System<'a> for Sys {
             mut chunk_generator,
             mut terrain,
             mut terrain_changes,
+            mut terrain_versions,
             mut chunk_requests,
             mut rtsim,
             mut _terrain_persistence,
@@ -150,6 +153,8 @@ impl<'a> System<'a> for Sys {
                     if let Some(client) = clients.get(entity) {
                         client.send_fallible(ServerGeneral::TerrainChunkUpdate {
                             key,
+                            // Versions start at 1, so there is nothing to diff against
+                            version: 0,
                             chunk: Err(()),
                         });
                     }
@@ -174,6 +179,7 @@ impl<'a> System<'a> for Sys {
 
             // TODO: code duplication for chunk insertion between here and state.rs
             // Insert the chunk into terrain changes
+            terrain_versions.reset(key);
             if terrain.insert(key, chunk).is_some() {
                 terrain_changes.modified_chunks.insert(key);
             } else {
@@ -391,6 +397,7 @@ impl<'a> System<'a> for Sys {
                 // TODO: code duplication for chunk insertion between here and state.rs
                 terrain.remove(key).map(|chunk| {
                     terrain_changes.removed_chunks.insert(key);
+                    terrain_versions.remove(key);
                     chunk
                 })
             })
@@ -1,18 +1,27 @@
-use crate::{chunk_serialize::ChunkSendEntry, client::Client, Settings};
+use crate::{
+    chunk_serialize::ChunkSendEntry,
+    client::Client,
+    terrain_versions::{TerrainVersions, MAX_DIFF_BLOCKS},
+    Settings,
+};
 use common::{
     comp::{Pos, Presence},
     event::EventBus,
+    terrain::TerrainGrid,
 };
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{CompressedData, ServerGeneral};
 use common_state::TerrainChanges;
+use hashbrown::HashMap;
 use rayon::prelude::*;
-use specs::{Entities, Join, Read, ReadExpect, ReadStorage};
+use specs::{Entities, Entity, Read, ReadExpect, ReadStorage, WriteExpect};
 use std::sync::Arc;
+use vek::*;
 use world::World;
 
 /// This systems sends new chunks to clients as well as changes to existing
-/// chunks
+/// chunks, which are versioned so that clients can catch up on changes they
+/// missed
 #[derive(Default)]
 pub struct Sys;
 impl<'a> System<'a> for Sys {
@@ -21,6 +30,7 @@ impl<'a> System<'a> for Sys {
         ReadExpect<'a, Arc<World>>,
         Read<'a, Settings>,
         Read<'a, TerrainChanges>,
+        WriteExpect<'a, TerrainVersions>,
         ReadExpect<'a, EventBus<ChunkSendEntry>>,
         ReadStorage<'a, Pos>,
         ReadStorage<'a, Presence>,
@@ -38,6 +48,7 @@ impl<'a> System<'a> for Sys {
             world,
             server_settings,
             terrain_changes,
+            mut terrain_versions,
             chunk_send_bus,
             positions,
             presences,
@@ -60,57 +71,96 @@ impl<'a> System<'a> for Sys {
         terrain_changes.modified_chunks.par_iter().for_each_init(
             || chunk_send_bus.emitter(),
             |chunk_send_emitter, &chunk_key| {
-                // We only have to check players inside the maximum view distance of the server
-                // of our own position.
-                //
-                // We start by partitioning by X, finding only entities in chunks within the X
-                // range of us.  These are guaranteed in bounds due to restrictions on max view
-                // distance (namely: the square of any chunk coordinate plus the max view
-                // distance along both axes must fit in an i32).
-                let min_chunk_x = chunk_key.x - real_max_view_distance;
-                let max_chunk_x = chunk_key.x + real_max_view_distance;
-                let start = presences_position_entities
-                    .partition_point(|((pos, _), _)| i32::from(pos.x) < min_chunk_x);
-                // NOTE: We *could* just scan forward until we hit the end, but this way we save
-                // a comparison in the inner loop, since also needs to check the
-                // list length.  We could also save some time by starting from
-                // start rather than end, but the hope is that this way the
-                // compiler (and machine) can reorder things so both ends are
-                // fetched in parallel; since the vast majority of the time both fetched
-                // elements should already be in cache, this should not use any
-                // extra memory bandwidth.
-                //
-                // TODO: Benchmark and figure out whether this is better in practice than just
-                // scanning forward.
-                let end = presences_position_entities
-                    .partition_point(|((pos, _), _)| i32::from(pos.x) < max_chunk_x);
-                let interior = &presences_position_entities[start..end];
-                interior
-                    .iter()
-                    .filter(|((player_chunk_pos, player_vd_sqr), _)| {
-                        super::terrain::chunk_in_vd(*player_chunk_pos, *player_vd_sqr, chunk_key)
-                    })
-                    .for_each(|(_, entity)| {
-                        chunk_send_emitter.emit(ChunkSendEntry {
-                            entity: *entity,
-                            chunk_key,
-                        });
-                    });
+                players_in_vd(
+                    &presences_position_entities,
+                    real_max_view_distance,
+                    chunk_key,
+                )
+                .for_each(|entity| {
+                    chunk_send_emitter.emit(ChunkSendEntry { entity, chunk_key });
+                });
             },
         );
 
-        // TODO: Don't send all changed blocks to all clients
-        // Sync changed blocks
-        if !terrain_changes.modified_blocks.is_empty() {
-            let mut lazy_msg = None;
-            for (_, client) in (&presences, &clients).join() {
-                if lazy_msg.is_none() {
-                    lazy_msg = Some(client.prepare(ServerGeneral::TerrainBlockUpdates(
-                        CompressedData::compress(&terrain_changes.modified_blocks, 1),
-                    )));
+        // Sync changed blocks, as a diff to the previous version of their chunk for
+        // the players that can see it
+        let mut chunk_blocks = HashMap::<_, HashMap<_, _>>::new();
+        for (&wpos, &block) in terrain_changes.modified_blocks.iter() {
+            chunk_blocks
+                .entry(TerrainGrid::chunk_key(wpos))
+                .or_default()
+                .insert(wpos, block);
+        }
+        let chunk_send_emitter = chunk_send_bus.emitter();
+        for (chunk_key, blocks) in chunk_blocks {
+            let (base, version) = terrain_versions.record(
+                chunk_key,
+                blocks.iter().map(|(wpos, block)| (*wpos, *block)),
+            );
+            let recipients = players_in_vd(
+                &presences_position_entities,
+                real_max_view_distance,
+                chunk_key,
+            );
+            if blocks.len() > MAX_DIFF_BLOCKS {
+                recipients.for_each(|entity| {
+                    chunk_send_emitter.emit(ChunkSendEntry { entity, chunk_key });
+                });
+            } else {
+                let mut lazy_msg = None;
+                for client in recipients.filter_map(|entity| clients.get(entity)) {
+                    if lazy_msg.is_none() {
+                        lazy_msg = Some(client.prepare(ServerGeneral::TerrainChunkDiff {
+                            key: chunk_key,
+                            base,
+                            version,
+                            blocks: CompressedData::compress(&blocks, 1),
+                        }));
+                    }
+                    lazy_msg.as_ref().map(|msg| client.send_prepared(msg));
                 }
-                lazy_msg.as_ref().map(|msg| client.send_prepared(msg));
             }
         }
     }
 }
+
+/// The players that have the chunk within their view distance. Clients keep
+/// chunks loaded a bit further, and request the changes they missed once those
+/// come back into view.
+fn players_in_vd(
+    presences_position_entities: &[((Vec2<i16>, i32), Entity)],
+    real_max_view_distance: i32,
+    chunk_key: Vec2<i32>,
+) -> impl Iterator<Item = Entity> + '_ {
+    // We only have to check players inside the maximum view distance of the server
+    // of our own position.
+    //
+    // We start by partitioning by X, finding only entities in chunks within the X
+    // range of us.  These are guaranteed in bounds due to restrictions on max view
+    // distance (namely: the square of any chunk coordinate plus the max view
+    // distance along both axes must fit in an i32).
+    let min_chunk_x = chunk_key.x - real_max_view_distance;
+    let max_chunk_x = chunk_key.x + real_max_view_distance;
+    let start =
+        presences_position_entities.partition_point(|((pos, _), _)| i32::from(pos.x) < min_chunk_x);
+    // NOTE: We *could* just scan forward until we hit the end, but this way we save
+    // a comparison in the inner loop, since also needs to check the
+    // list length.  We could also save some time by starting from
+    // start rather than end, but the hope is that this way the
+    // compiler (and machine) can reorder things so both ends are
+    // fetched in parallel; since the vast majority of the time both fetched
+    // elements should already be in cache, this should not use any
+    // extra memory bandwidth.
+    //
+    // TODO: Benchmark and figure out whether this is better in practice than just
+    // scanning forward.
+    let end =
+        presences_position_entities.partition_point(|((pos, _), _)| i32::from(pos.x) < max_chunk_x);
+    let interior = &presences_position_entities[start..end];
+    interior
+        .iter()
+        .filter(move |((player_chunk_pos, player_vd_sqr), _)| {
+            super::terrain::chunk_in_vd(*player_chunk_pos, *player_vd_sqr, chunk_key)
+        })
+        .map(|(_, entity)| *entity)
+}
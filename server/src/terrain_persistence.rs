@@ -10,7 +10,7 @@ use std::{
     any::{type_name, Any},
     fs::File,
     io::{self, Read as _, Write as _},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use tracing::{debug, error, info, warn};
 use vek::*;
@@ -94,9 +94,11 @@ impl TerrainPersistence {
         // reliable strategy should be implemented here.
     }
 
+    fn file_name_for(key: Vec2<i32>) -> String { format!("chunk_{}_{}.dat", key.x, key.y) }
+
     fn path_for(&self, key: Vec2<i32>) -> PathBuf {
         let mut path = self.path.clone();
-        path.push(format!("chunk_{}_{}.dat", key.x, key.y));
+        path.push(Self::file_name_for(key));
         path
     }
 
@@ -189,6 +191,41 @@ impl TerrainPersistence {
         }
     }
 
+    /// Captures the current state of all persisted terrain into `dir`, so
+    /// that it can be completed elsewhere later (e.g. from another thread)
+    /// with [`TerrainSnapshot::finish`].
+    ///
+    /// Chunk files are hard linked into `dir` right away, copied where that
+    /// isn't possible. Since chunk files are always replaced atomically rather
+    /// than written in place, the links keep pointing at the data they were
+    /// made from. Changes to loaded chunks which haven't been written back yet
+    /// are captured in memory.
+    pub fn snapshot(&self, dir: &Path) -> io::Result<TerrainSnapshot> {
+        std::fs::create_dir_all(dir)?;
+
+        for entry in std::fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            if !entry.file_type()?.is_file() || !file_name.to_string_lossy().starts_with("chunk_") {
+                continue;
+            }
+            let target = dir.join(&file_name);
+            if std::fs::hard_link(entry.path(), &target).is_err() {
+                std::fs::copy(entry.path(), &target)?;
+            }
+        }
+
+        Ok(TerrainSnapshot {
+            dir: dir.to_owned(),
+            modified: self
+                .chunks
+                .iter()
+                .filter(|(_, c)| c.modified)
+                .map(|(key, c)| (*key, c.chunk.clone()))
+                .collect(),
+        })
+    }
+
     pub fn unload_all(&mut self) {
         for key in self.chunks.keys().copied().collect::<Vec<_>>() {
             self.unload_chunk(key);
@@ -218,6 +255,30 @@ impl Drop for TerrainPersistence {
     fn drop(&mut self) { self.unload_all(); }
 }
 
+/// The state of all persisted terrain at the moment
+/// [`TerrainPersistence::snapshot`] was called.
+pub struct TerrainSnapshot {
+    dir: PathBuf,
+    modified: Vec<(Vec2<i32>, Chunk)>,
+}
+
+impl TerrainSnapshot {
+    /// Writes the captured changes to loaded chunks into the snapshot's
+    /// directory, completing it.
+    pub fn finish(self) -> io::Result<()> {
+        for (key, chunk) in self.modified {
+            let bytes = bincode::serialize::<version::Current>(&chunk.prepare_raw())
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            let path = self.dir.join(TerrainPersistence::file_name_for(key));
+            // Don't write through a link to the original file.
+            let _ = std::fs::remove_file(&path);
+            File::create(path)?.write_all(&bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Chunk {
     blocks: HashMap<Vec3<i32>, Block>,
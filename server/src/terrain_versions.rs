@@ -0,0 +1,156 @@
+use common::terrain::Block;
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use vek::*;
+
+/// How many block changes are remembered per chunk. Clients that are further
+/// behind than this get the whole chunk again.
+const MAX_LOGGED_CHANGES: usize = 1024;
+/// Diffs with more blocks than this are not worth it, the whole chunk is sent
+/// instead.
+pub const MAX_DIFF_BLOCKS: usize = 256;
+
+struct ChunkVersion {
+    version: u64,
+    /// The oldest version that diffs can still be made against.
+    oldest: u64,
+    log: VecDeque<(u64, Vec3<i32>, Block)>,
+}
+
+/// Tracks a version for every loaded chunk along with the blocks that changed
+/// recently, so that clients that have an older version of a chunk can be
+/// sent just the blocks that changed instead of the whole chunk.
+///
+/// Versions are taken from a single counter shared by all chunks, so a chunk
+/// that gets unloaded and loaded again never reuses a version a client might
+/// still have.
+#[derive(Default)]
+pub struct TerrainVersions {
+    next: u64,
+    chunks: HashMap<Vec2<i32>, ChunkVersion>,
+}
+
+impl TerrainVersions {
+    fn next_version(&mut self) -> u64 {
+        self.next += 1;
+        self.next
+    }
+
+    /// The current version of the chunk, if it is loaded.
+    pub fn version(&self, key: Vec2<i32>) -> Option<u64> {
+        self.chunks.get(&key).map(|chunk| chunk.version)
+    }
+
+    /// Gives the chunk a new version that no diff can be made against, to be
+    /// called whenever the whole chunk is (re)placed.
+    pub fn reset(&mut self, key: Vec2<i32>) {
+        let version = self.next_version();
+        self.chunks.insert(key, ChunkVersion {
+            version,
+            oldest: version,
+            log: VecDeque::new(),
+        });
+    }
+
+    pub fn remove(&mut self, key: Vec2<i32>) { self.chunks.remove(&key); }
+
+    pub fn clear(&mut self) { self.chunks.clear(); }
+
+    /// Records changed blocks of a chunk as a new version, returns the
+    /// previous and the new version of the chunk.
+    pub fn record(
+        &mut self,
+        key: Vec2<i32>,
+        blocks: impl IntoIterator<Item = (Vec3<i32>, Block)>,
+    ) -> (u64, u64) {
+        if !self.chunks.contains_key(&key) {
+            self.reset(key);
+        }
+        let version = self.next_version();
+        let chunk = self
+            .chunks
+            .get_mut(&key)
+            .expect("Chunk version was inserted above");
+        let base = chunk.version;
+        chunk.version = version;
+        chunk.log.extend(
+            blocks
+                .into_iter()
+                .map(|(wpos, block)| (version, wpos, block)),
+        );
+        while chunk.log.len() > MAX_LOGGED_CHANGES {
+            if let Some((forgotten, _, _)) = chunk.log.pop_front() {
+                chunk.oldest = forgotten;
+            }
+        }
+        (base, version)
+    }
+
+    /// The blocks that changed in the chunk since version `base`, or `None` if
+    /// they aren't known (anymore).
+    pub fn diff(&self, key: Vec2<i32>, base: u64) -> Option<HashMap<Vec3<i32>, Block>> {
+        let chunk = self.chunks.get(&key)?;
+        if base < chunk.oldest || base > chunk.version {
+            return None;
+        }
+        Some(
+            chunk
+                .log
+                .iter()
+                .filter(|(version, _, _)| *version > base)
+                .map(|(_, wpos, block)| (*wpos, *block))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::terrain::BlockKind;
+
+    fn block(kind: BlockKind) -> Block { Block::new(kind, Rgb::zero()) }
+
+    #[test]
+    fn diff_contains_latest_changes_since_base() {
+        let key = Vec2::zero();
+        let mut versions = TerrainVersions::default();
+        versions.reset(key);
+        let start = versions.version(key).unwrap();
+
+        let (base, first) = versions.record(key, [(Vec3::zero(), block(BlockKind::Rock))]);
+        assert_eq!(base, start);
+        let (base, second) = versions.record(key, [
+            (Vec3::zero(), block(BlockKind::Air)),
+            (Vec3::unit_z(), block(BlockKind::Rock)),
+        ]);
+        assert_eq!(base, first);
+
+        let diff = versions.diff(key, start).unwrap();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[&Vec3::zero()], block(BlockKind::Air));
+        assert_eq!(versions.diff(key, first).unwrap().len(), 2);
+        assert!(versions.diff(key, second).unwrap().is_empty());
+        assert!(versions.diff(key, second + 1).is_none());
+    }
+
+    #[test]
+    fn no_diff_across_resets_or_forgotten_changes() {
+        let key = Vec2::zero();
+        let mut versions = TerrainVersions::default();
+        versions.reset(key);
+        let start = versions.version(key).unwrap();
+        versions.reset(key);
+        assert!(versions.diff(key, start).is_none());
+
+        let start = versions.version(key).unwrap();
+        for z in 0..=MAX_LOGGED_CHANGES as i32 {
+            versions.record(key, [(Vec3::new(0, 0, z), block(BlockKind::Rock))]);
+        }
+        assert!(versions.diff(key, start).is_none());
+        assert_eq!(
+            versions.diff(key, start + 1).map(|diff| diff.len()),
+            Some(MAX_LOGGED_CHANGES)
+        );
+    }
+}
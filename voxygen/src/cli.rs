@@ -8,6 +8,7 @@
 //! Airshipper should only use arguments listed above! Since we will not try to
 //! be careful about their stability otherwise.
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 pub struct Args {
@@ -16,4 +17,11 @@ pub struct Args {
     /// This allows passing in server selection performed in airshipper.
     #[clap(short, long)]
     pub server: Option<String>,
+
+    /// Plays back a demo file instead of showing the main menu.
+    ///
+    /// Demos are recorded into the `demos` folder of the config directory
+    /// while the "Record demos" networking setting is enabled.
+    #[clap(long, value_name = "FILE")]
+    pub demo: Option<PathBuf>,
 }
@@ -11,6 +11,7 @@ use strum::IntoEnumIterator;
 // Please keep this sorted alphabetically, same as with server commands :-)
 #[derive(Clone, Copy, strum::EnumIter)]
 pub enum ClientChatCommand {
+    Demo,
     ExperimentalShader,
     Help,
     Mute,
@@ -23,6 +24,22 @@ impl ClientChatCommand {
         use Requirement::*;
         let cmd = ChatCommandData::new;
         match self {
+            ClientChatCommand::Demo => cmd(
+                vec![
+                    Enum(
+                        "action",
+                        ["pause", "resume", "seek", "speed"]
+                            .iter()
+                            .map(|s| s.to_string())
+                            .collect(),
+                        Optional,
+                    ),
+                    Float("value", 0.0, Optional),
+                ],
+                "Controls the playback of a demo. Seeks to a time in seconds or sets the playback \
+                 speed. Shows the position of the playback without arguments.",
+                None,
+            ),
             ClientChatCommand::ExperimentalShader => cmd(
                 vec![Enum(
                     "Shader",
@@ -54,6 +71,7 @@ impl ClientChatCommand {
 
     pub fn keyword(&self) -> &'static str {
         match self {
+            ClientChatCommand::Demo => "demo",
             ClientChatCommand::ExperimentalShader => "experimental_shader",
             ClientChatCommand::Help => "help",
             ClientChatCommand::Mute => "mute",
@@ -202,6 +220,8 @@ fn run_client_command(
     args: Vec<String>,
 ) -> Result<String, String> {
     let command = match command {
+        // Needs to change the client
+        ClientChatCommand::Demo => return handle_demo(client, args),
         ClientChatCommand::ExperimentalShader => handle_experimental_shader,
         ClientChatCommand::Help => handle_help,
         ClientChatCommand::Mute => handle_mute,
@@ -211,6 +231,32 @@ fn run_client_command(
     command(client, global_state, args)
 }
 
+fn handle_demo(client: &mut Client, args: Vec<String>) -> Result<String, String> {
+    let playback = client
+        .demo_playback_mut()
+        .ok_or_else(|| "No demo is being played back.".to_string())?;
+    match parse_cmd_args!(args, String, f64) {
+        (None, _) => {},
+        (Some(action), value) => match (action.as_str(), value) {
+            ("pause", _) => playback.set_paused(true),
+            ("resume", _) => playback.set_paused(false),
+            ("seek", Some(time)) => playback.seek(time),
+            ("speed", Some(speed)) => playback.set_speed(speed),
+            ("seek" | "speed", None) => {
+                return Err(format!("You must specify a value to {}.", action));
+            },
+            _ => return Err(format!("Unknown demo action {}.", action)),
+        },
+    }
+    Ok(format!(
+        "{:.0}s of {:.0}s at {}x speed{}",
+        playback.time(),
+        playback.duration(),
+        playback.speed(),
+        if playback.is_paused() { ", paused" } else { "" }
+    ))
+}
+
 fn handle_help(
     client: &Client,
     _global_state: &mut GlobalState,
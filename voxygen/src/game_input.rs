@@ -99,6 +99,8 @@ pub enum GameInput {
     Social,
     #[strum(serialize = "gameinput-crafting")]
     Crafting,
+    #[strum(serialize = "gameinput-quests")]
+    Quests,
     #[strum(serialize = "gameinput-spellbook")]
     Spellbook,
     #[strum(serialize = "gameinput-settings")]
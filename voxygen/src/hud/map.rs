@@ -203,6 +203,24 @@ fn get_site_economy(site_rich: &SiteInfoRich) -> String {
     }
 }
 
+fn get_site_faction(site_rich: &SiteInfoRich, i18n: &Localization) -> String {
+    if let Some(faction) = &site_rich.faction {
+        let mut result = String::from("\n\n");
+        result += &i18n.get_msg_ctx("hud-map-site_faction", &i18n::fluent_args! {
+            "faction" => faction.faction.as_str()
+        });
+        if !faction.at_war_with.is_empty() {
+            result += "\n";
+            result += &i18n.get_msg_ctx("hud-map-site_at_war", &i18n::fluent_args! {
+                "factions" => faction.at_war_with.join(", ")
+            });
+        }
+        result
+    } else {
+        String::new()
+    }
+}
+
 impl<'a> Widget for Map<'a> {
     type Event = Vec<Event>;
     type State = State;
@@ -955,7 +973,9 @@ impl<'a> Widget for Map<'a> {
                 SiteKind::Adlet => (Some(1), i18n.get_msg("hud-map-adlet")),
                 SiteKind::DwarvenMine => (Some(5), i18n.get_msg("hud-map-df_mine")),
             };
-            let desc = desc.into_owned() + &get_site_economy(site_rich);
+            let desc = desc.into_owned()
+                + &get_site_faction(site_rich, i18n)
+                + &get_site_economy(site_rich);
             let site_btn = Button::image(match &site.kind {
                 SiteKind::Town => self.imgs.mmap_site_town,
                 SiteKind::ChapelSite => self.imgs.mmap_site_sea_chapel,
@@ -110,6 +110,7 @@ use common::{
     mounting::{Mount, Rider, VolumePos},
     outcome::Outcome,
     resources::{Secs, Time},
+    rtsim::{QuestId, QuestResponse},
     slowjob::SlowJobPool,
     terrain::{SpriteKind, TerrainChunk, UnlockKind},
     trade::{ReducedInventory, TradeAction},
@@ -740,6 +741,7 @@ pub enum Event {
     SelectExpBar(Option<SkillGroupKind>),
 
     RequestSiteInfo(SiteId),
+    RespondToQuest(QuestId, QuestResponse),
     ChangeAbility(usize, AuxiliaryAbility),
 
     SettingsChange(SettingsChange),
@@ -1045,6 +1047,8 @@ impl Show {
 
     fn toggle_crafting(&mut self) { self.crafting(!self.crafting) }
 
+    fn toggle_quest(&mut self) { self.quest(!self.quest) }
+
     fn toggle_spell(&mut self) { self.diary(!self.diary) }
 
     fn toggle_ui(&mut self) { self.ui = !self.ui; }
@@ -3575,6 +3579,9 @@ impl Hud {
                 )
                 .set(self.ids.quest_window, ui_widgets)
                 {
+                    Some(quest::Event::Respond(quest, response)) => {
+                        events.push(Event::RespondToQuest(quest, response));
+                    },
                     Some(quest::Event::Close) => {
                         self.show.quest(false);
                         if !self.show.bag {
@@ -4466,6 +4473,8 @@ impl Hud {
 
     pub fn new_notification(&mut self, msg: Notification) { self.new_notifications.push_back(msg); }
 
+    pub fn show_quests(&mut self) { self.show.quest(true); }
+
     pub fn set_scaling_mode(&mut self, scale_mode: ScaleMode) {
         self.ui.set_scaling_mode(scale_mode);
     }
@@ -4694,6 +4703,10 @@ impl Hud {
                         self.show.toggle_crafting();
                         true
                     },
+                    GameInput::Quests if state => {
+                        self.show.toggle_quest();
+                        true
+                    },
                     GameInput::Spellbook if state => {
                         self.show.toggle_spell();
                         true
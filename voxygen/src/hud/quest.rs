@@ -1,14 +1,22 @@
 use client::Client;
-use common::comp::{inventory::item::item_key::ItemKey, Stats};
+use common::{
+    assets::AssetExt,
+    comp::{
+        inventory::item::{item_key::ItemKey, ItemDef},
+        Stats,
+    },
+    rtsim::{QuestId, QuestInfo, QuestResponse},
+};
 use conrod_core::{
     color,
-    widget::{self, Button, Image, Rectangle, Scrollbar, Text},
-    widget_ids, Color, Colorable, Labelable, Positionable, Sizeable, Widget, WidgetCommon,
+    widget::{self, Button, Image, Rectangle, Scrollbar, State as ConrodState, Text},
+    widget_ids, Color, Colorable, Labelable, Positionable, Sizeable, UiCell, Widget, WidgetCommon,
 };
 use i18n::Localization;
 
 use crate::ui::{fonts::Fonts, TooltipManager};
 use inline_tweak::*;
+use std::sync::Arc;
 
 use super::{
     img_ids::{Imgs, ImgsRot},
@@ -34,6 +42,8 @@ widget_ids! {
         intro_txt,
         desc_txt_0,
         quest_objectives[],
+        abandon_btns[],
+        no_quests_txt,
         quest_reward_txt,
         objective_text,
         quest_rewards_frames[],
@@ -47,7 +57,7 @@ widget_ids! {
 #[derive(WidgetCommon)]
 pub struct Quest<'a> {
     _show: &'a Show,
-    _client: &'a Client,
+    client: &'a Client,
     imgs: &'a Imgs,
     fonts: &'a Fonts,
     localized_strings: &'a Localization,
@@ -64,7 +74,7 @@ pub struct Quest<'a> {
 impl<'a> Quest<'a> {
     pub fn new(
         _show: &'a Show,
-        _client: &'a Client,
+        client: &'a Client,
         imgs: &'a Imgs,
         fonts: &'a Fonts,
         localized_strings: &'a Localization,
@@ -76,7 +86,7 @@ impl<'a> Quest<'a> {
     ) -> Self {
         Self {
             _show,
-            _client,
+            client,
             imgs,
             _rot_imgs,
             fonts,
@@ -91,6 +101,7 @@ impl<'a> Quest<'a> {
 }
 
 pub enum Event {
+    Respond(QuestId, QuestResponse),
     Close,
 }
 
@@ -162,10 +173,35 @@ impl<'a> Widget for Quest<'a> {
             .color(Color::Rgba(0.79, 1.09, 1.09, 0.0))
             .set(state.ids.scrollbar, ui);
 
-        // Quest Text
+        match self.client.quest_offer() {
+            Some(offer) => {
+                if let Some(response) = self.offer(offer, state, ui) {
+                    event = Some(Event::Respond(offer.id, response));
+                }
+            },
+            None => {
+                if let Some(quest) = self.taken_quests(state, ui) {
+                    event = Some(Event::Respond(quest, QuestResponse::Abandon));
+                }
+            },
+        }
 
-        // Introduction
+        event
+    }
+}
+
+impl<'a> Quest<'a> {
+    /// Shows the quest an NPC offered, returning how the player responded to
+    /// it.
+    fn offer(
+        &self,
+        offer: &QuestInfo,
+        state: &mut ConrodState<'_, State>,
+        ui: &mut UiCell<'_>,
+    ) -> Option<QuestResponse> {
+        let mut response = None;
 
+        // Introduction
         Text::new(
             &self
                 .localized_strings
@@ -179,34 +215,24 @@ impl<'a> Widget for Quest<'a> {
         .color(TEXT_COLOR)
         .set(state.ids.intro_txt, ui);
 
-        enum QuestType {
-            FetchQuest,
-            // KillQuest,
-        }
-
-        // Define type of quest to change introduction text
-
-        let quest_type = QuestType::FetchQuest;
-
-        let q_desc0 = match quest_type {
-            QuestType::FetchQuest => "hud-quest-desc-fetch",
-            // QuestType::KillQuest => "hud-quest-desc-kill",
-        };
-
-        Text::new(&self.localized_strings.get_msg(q_desc0))
-            .top_left_with_margins_on(state.ids.intro_txt, tweak!(40.0), tweak!(0.0))
-            .font_id(self.fonts.cyri.conrod_id)
-            .font_size(self.fonts.cyri.scale(tweak!(20)))
-            .color(TEXT_COLOR)
-            .set(state.ids.desc_txt_0, ui);
-
-        // Objective(s)
-        let objective_amount = 20.0;
-        let objective_name = "Flower";
-        let objective_txt = format!("{}x {}", objective_amount, objective_name);
+        Text::new(
+            &self
+                .localized_strings
+                .get_msg_ctx("hud-quest-desc", &i18n::fluent_args! {
+                    "giver" => offer.giver.clone(),
+                }),
+        )
+        .top_left_with_margins_on(state.ids.intro_txt, tweak!(40.0), tweak!(0.0))
+        .w(260.0)
+        .font_id(self.fonts.cyri.conrod_id)
+        .font_size(self.fonts.cyri.scale(tweak!(20)))
+        .color(TEXT_COLOR)
+        .set(state.ids.desc_txt_0, ui);
 
-        Text::new(&objective_txt)
+        // Objective
+        Text::new(&self.localized_strings.get_content(&offer.objective))
             .down_from(state.ids.desc_txt_0, tweak!(10.0))
+            .w(260.0)
             .font_id(self.fonts.cyri.conrod_id)
             .font_size(self.fonts.cyri.scale(tweak!(20)))
             .color(TEXT_VELORITE)
@@ -219,44 +245,31 @@ impl<'a> Widget for Quest<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.quest_reward_txt, ui);
 
-        // insert reward item data here
-        // [amount, item_desc]
-
-        //("common.items.weapons.sword.caladbolg");
-        let rewards = [
-            (1, "common.items.weapons.dagger.starter_dagger", "Dagger"),
-            (4, "common.items.crafting_ing.seashells", "Seashell"),
-            (
-                8,
-                "common.items.crafting_ing.animal_misc.raptor_feather",
-                "Raptor Feather",
-            ),
-        ];
-        let rewards_amount = rewards.len();
+        let rewards_amount = offer.rewards.len();
 
         if state.ids.quest_rewards_frames.len() < rewards_amount {
             state.update(|s| {
                 s.ids
                     .quest_rewards_frames
-                    .resize(rewards.len(), &mut ui.widget_id_generator())
+                    .resize(rewards_amount, &mut ui.widget_id_generator())
             })
         };
         if state.ids.quest_rewards_icons.len() < rewards_amount {
             state.update(|s| {
                 s.ids
                     .quest_rewards_icons
-                    .resize(rewards.len(), &mut ui.widget_id_generator())
+                    .resize(rewards_amount, &mut ui.widget_id_generator())
             })
         };
         if state.ids.quest_rewards_txts.len() < rewards_amount {
             state.update(|s| {
                 s.ids
                     .quest_rewards_txts
-                    .resize(rewards.len(), &mut ui.widget_id_generator())
+                    .resize(rewards_amount, &mut ui.widget_id_generator())
             })
         };
 
-        for (i, item) in rewards.iter().enumerate() {
+        for (i, (item, amount)) in offer.rewards.iter().enumerate() {
             // Slot BG
             let mut frame_img = Image::new(self.imgs.skillbar_slot)
                 .w_h(40.0, 40.0)
@@ -270,10 +283,13 @@ impl<'a> Widget for Quest<'a> {
             frame_img.set(state.ids.quest_rewards_frames[i], ui);
 
             // Item amount and text
-            let item_txt = if item.0 == 1 {
-                item.2.to_string()
+            let name = Arc::<ItemDef>::load_cloned(item)
+                .map(|item_def| item_def.name.clone())
+                .unwrap_or_else(|_| item.clone());
+            let item_txt = if *amount == 1 {
+                name
             } else {
-                format!("{}x {}", item.0, item.2)
+                format!("{}x {}", amount, name)
             };
             //INPUT QUALITY HERE TO CHANGE COLOR
             let item_quality = TEXT_VELORITE;
@@ -287,7 +303,7 @@ impl<'a> Widget for Quest<'a> {
             Image::new(animate_by_pulse(
                 &self
                     .item_imgs
-                    .img_ids_or_not_found_img(ItemKey::Simple(item.1.to_string())),
+                    .img_ids_or_not_found_img(ItemKey::Simple(item.clone())),
                 self.pulse,
             ))
             .w_h(38.0, 38.0)
@@ -312,7 +328,7 @@ impl<'a> Widget for Quest<'a> {
             .set(state.ids.accept_btn, ui)
             .was_clicked()
         {
-            event = Some(Event::Close);
+            response = Some(QuestResponse::Accept);
         };
 
         if Button::image(self.imgs.button)
@@ -329,9 +345,97 @@ impl<'a> Widget for Quest<'a> {
             .set(state.ids.decline_btn, ui)
             .was_clicked()
         {
-            event = Some(Event::Close);
+            response = Some(QuestResponse::Decline);
         };
 
-        event
+        response
+    }
+
+    /// Lists the quests the character took on, returning the one the player
+    /// chose to abandon.
+    fn taken_quests(
+        &self,
+        state: &mut ConrodState<'_, State>,
+        ui: &mut UiCell<'_>,
+    ) -> Option<QuestId> {
+        let quests = self.client.quests();
+        if quests.is_empty() {
+            Text::new(&self.localized_strings.get_msg("hud-quest-none"))
+                .top_left_with_margins_on(state.ids.content_align, tweak!(0.0), tweak!(2.0))
+                .w(260.0)
+                .font_id(self.fonts.cyri.conrod_id)
+                .font_size(self.fonts.cyri.scale(tweak!(18)))
+                .color(TEXT_COLOR)
+                .set(state.ids.no_quests_txt, ui);
+            return None;
+        }
+
+        if state.ids.quest_objectives.len() < quests.len() {
+            state.update(|s| {
+                s.ids
+                    .quest_objectives
+                    .resize(quests.len(), &mut ui.widget_id_generator())
+            })
+        };
+        if state.ids.abandon_btns.len() < quests.len() {
+            state.update(|s| {
+                s.ids
+                    .abandon_btns
+                    .resize(quests.len(), &mut ui.widget_id_generator())
+            })
+        };
+
+        let mut abandoned = None;
+        for (i, quest) in quests.iter().enumerate() {
+            let objective = self.localized_strings.get_content(&quest.objective);
+            let (txt, color) = if quest.done {
+                (
+                    self.localized_strings
+                        .get_msg_ctx("hud-quest-done", &i18n::fluent_args! {
+                            "objective" => objective,
+                            "giver" => quest.giver.clone(),
+                        })
+                        .into_owned(),
+                    HP_COLOR,
+                )
+            } else {
+                (objective, TEXT_VELORITE)
+            };
+            let mut objective_txt = Text::new(&txt)
+                .w(260.0)
+                .font_id(self.fonts.cyri.conrod_id)
+                .font_size(self.fonts.cyri.scale(tweak!(18)))
+                .color(color);
+            if i == 0 {
+                objective_txt = objective_txt.top_left_with_margins_on(
+                    state.ids.content_align,
+                    tweak!(0.0),
+                    tweak!(2.0),
+                );
+            } else {
+                objective_txt =
+                    objective_txt.down_from(state.ids.abandon_btns[i - 1], tweak!(15.0));
+            }
+            objective_txt.set(state.ids.quest_objectives[i], ui);
+
+            if Button::image(self.imgs.button)
+                .down_from(state.ids.quest_objectives[i], tweak!(5.0))
+                .w_h(tweak!(100.0), tweak!(30.0))
+                .hover_image(self.imgs.button_hover)
+                .press_image(self.imgs.button_press)
+                .label(&self.localized_strings.get_msg("hud-quest-abandon"))
+                .label_y(conrod_core::position::Relative::Scalar(2.0))
+                .label_color(TEXT_COLOR)
+                .label_font_size(self.fonts.cyri.scale(16))
+                .label_font_id(self.fonts.cyri.conrod_id)
+                .image_color(TEXT_DULL_RED_COLOR)
+                .set(state.ids.abandon_btns[i], ui)
+                .was_clicked()
+            {
+                abandoned = Some(quest.id);
+            }
+        }
+
+        abandoned
     }
 }
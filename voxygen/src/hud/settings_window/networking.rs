@@ -25,6 +25,10 @@ widget_ids! {
         player_physics_behavior_list,
         lossy_terrain_compression_button,
         lossy_terrain_compression_label,
+        record_demos_button,
+        record_demos_label,
+        auto_reconnect_button,
+        auto_reconnect_label,
         third_party_integrations_title,
         enable_discord_integration_text,
         enable_discord_integration_button
@@ -256,6 +260,56 @@ impl<'a> Widget for Networking<'a> {
             ));
         }
 
+        // Record demos
+        Text::new(&self.localized_strings.get_msg("hud-settings-record_demos"))
+            .font_size(self.fonts.cyri.scale(14))
+            .font_id(self.fonts.cyri.conrod_id)
+            .down_from(state.ids.lossy_terrain_compression_label, 16.0)
+            .color(TEXT_COLOR)
+            .set(state.ids.record_demos_label, ui);
+
+        let record_demos = ToggleButton::new(
+            self.global_state.settings.networking.record_demos,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.record_demos_label, 10.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.record_demos_button, ui);
+
+        if self.global_state.settings.networking.record_demos != record_demos {
+            events.push(ToggleRecordDemos(record_demos));
+        }
+
+        // Reconnect automatically
+        Text::new(
+            &self
+                .localized_strings
+                .get_msg("hud-settings-auto_reconnect"),
+        )
+        .font_size(self.fonts.cyri.scale(14))
+        .font_id(self.fonts.cyri.conrod_id)
+        .down_from(state.ids.record_demos_label, 16.0)
+        .color(TEXT_COLOR)
+        .set(state.ids.auto_reconnect_label, ui);
+
+        let auto_reconnect = ToggleButton::new(
+            self.global_state.settings.networking.auto_reconnect,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.auto_reconnect_label, 10.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.auto_reconnect_button, ui);
+
+        if self.global_state.settings.networking.auto_reconnect != auto_reconnect {
+            events.push(ToggleAutoReconnect(auto_reconnect));
+        }
+
         #[cfg(feature = "discord")]
         {
             // Third party integrations
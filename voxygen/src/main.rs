@@ -233,5 +233,5 @@ fn main() {
         discord,
     };
 
-    run::run(global_state, event_loop, args.server);
+    run::run(global_state, event_loop, args.server, args.demo);
 }
@@ -39,6 +39,22 @@ impl CharSelectionState {
         }
     }
 
+    fn spectate(&self, global_state: &mut GlobalState) -> PlayStateResult {
+        {
+            let mut c = self.client.borrow_mut();
+            let graphics = &global_state.settings.graphics;
+            c.request_spectate(common::ViewDistances {
+                terrain: graphics.terrain_view_distance,
+                entity: graphics.entity_view_distance,
+            });
+        }
+        PlayStateResult::Switch(Box::new(SessionState::new(
+            global_state,
+            UpdateCharacterMetadata::default(),
+            Rc::clone(&self.client),
+        )))
+    }
+
     fn get_humanoid_body_inventory<'a>(
         char_selection_ui: &'a CharSelectionUi,
         client: &'a Client,
@@ -86,6 +102,11 @@ impl PlayState for CharSelectionState {
             client.registered()
         };
         if client_registered {
+            // Demos are watched as a spectator, there are no characters to select
+            if self.client.borrow().demo_playback().is_some() {
+                return self.spectate(global_state);
+            }
+
             // Handle window events
             for event in events {
                 if self.char_selection_ui.handle_event(event.clone()) {
@@ -143,21 +164,7 @@ impl PlayState for CharSelectionState {
                             entity: graphics.entity_view_distance,
                         });
                     },
-                    ui::Event::Spectate => {
-                        {
-                            let mut c = self.client.borrow_mut();
-                            let graphics = &global_state.settings.graphics;
-                            c.request_spectate(common::ViewDistances {
-                                terrain: graphics.terrain_view_distance,
-                                entity: graphics.entity_view_distance,
-                            });
-                        }
-                        return PlayStateResult::Switch(Box::new(SessionState::new(
-                            global_state,
-                            UpdateCharacterMetadata::default(),
-                            Rc::clone(&self.client),
-                        )));
-                    },
+                    ui::Event::Spectate => return self.spectate(global_state),
                     ui::Event::ClearCharacterListError => {
                         self.char_selection_ui.error = None;
                     },
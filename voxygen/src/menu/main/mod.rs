@@ -23,7 +23,11 @@ use i18n::LocalizationHandle;
 use scene::Scene;
 #[cfg(feature = "singleplayer")]
 use server::ServerInitStage;
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 use tokio::runtime;
 use tracing::error;
 use ui::{Event as MainMenuEvent, MainMenuUi};
@@ -64,11 +68,25 @@ pub struct MainMenuState {
 }
 
 impl MainMenuState {
-    /// Create a new `MainMenuState`.
-    pub fn new(global_state: &mut GlobalState, server: Option<String>) -> Self {
+    /// Create a new `MainMenuState`, which immediately starts playing back
+    /// `demo` if given.
+    pub fn new(
+        global_state: &mut GlobalState,
+        server: Option<String>,
+        demo: Option<PathBuf>,
+    ) -> Self {
+        let init = match demo {
+            Some(path) => InitState::Client(ClientInit::new(
+                ConnectionArgs::Demo(path),
+                "demo".to_owned(),
+                String::new(),
+                Arc::clone(&global_state.tokio_runtime),
+            )),
+            None => InitState::None,
+        };
         Self {
             main_menu_ui: MainMenuUi::new(global_state, server),
-            init: InitState::None,
+            init,
             scene: Scene::new(global_state.window.renderer_mut()),
         }
     }
@@ -122,7 +140,11 @@ impl PlayState for MainMenuState {
                             &mut global_state.info_message,
                             "singleplayer".to_owned(),
                             "".to_owned(),
-                            ConnectionArgs::Mpsc(14004),
+                            with_demo_recording(
+                                ConnectionArgs::Mpsc(14004),
+                                &global_state.settings,
+                                &global_state.config_dir,
+                            ),
                             &mut self.init,
                             &global_state.tokio_runtime,
                             &global_state.i18n,
@@ -215,6 +237,7 @@ impl PlayState for MainMenuState {
             Some(InitMsg::Done(Ok(mut client))) => {
                 // Register voxygen components / resources
                 crate::ecs::init(client.state_mut().ecs_mut());
+                client.set_auto_reconnect(global_state.settings.networking.auto_reconnect);
                 self.init = InitState::Pipeline(Box::new(client));
             },
             Some(InitMsg::Done(Err(e))) => {
@@ -339,6 +362,11 @@ impl PlayState for MainMenuState {
                             prefer_ipv6: false,
                         }
                     };
+                    let connection_args = with_demo_recording(
+                        connection_args,
+                        &global_state.settings,
+                        &global_state.config_dir,
+                    );
                     attempt_login(
                         &mut global_state.info_message,
                         username,
@@ -539,6 +567,9 @@ fn get_client_msg_error(
             Error::Other(e) => {
                 format!("{}: {}", localization.get_msg("common-error"), e)
             },
+            Error::Demo(e) => {
+                format!("{}: {}", localization.get_msg("common-error"), e)
+            },
             Error::AuthClientError(e) => match e {
                 // TODO: remove parentheses
                 client::AuthClientError::RequestError(e) => format!(
@@ -569,6 +600,26 @@ fn get_client_msg_error(
     }
 }
 
+/// Records the connection into a new demo file if enabled in the settings.
+fn with_demo_recording(
+    connection_args: ConnectionArgs,
+    settings: &Settings,
+    config_dir: &Path,
+) -> ConnectionArgs {
+    if settings.networking.record_demos {
+        let path = config_dir.join("demos").join(format!(
+            "demo_{}.demo",
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0)
+        ));
+        ConnectionArgs::Recorded(Box::new(connection_args), path)
+    } else {
+        connection_args
+    }
+}
+
 fn attempt_login(
     info_message: &mut Option<String>,
     username: String,
@@ -433,6 +433,20 @@ impl SessionState {
                 client::Event::SpectatePosition(pos) => {
                     self.scene.camera_mut().force_focus_pos(pos);
                 },
+                client::Event::QuestOffer => self.hud.show_quests(),
+                client::Event::Reconnecting { attempt } => {
+                    self.hud.new_message(ChatType::CommandError.into_msg(
+                        Content::localized_with_args("hud-chat-reconnecting", [(
+                            "attempt",
+                            u64::from(attempt),
+                        )]),
+                    ));
+                },
+                client::Event::Reconnected => {
+                    self.hud.new_message(
+                        ChatType::CommandInfo.into_msg(Content::localized("hud-chat-reconnected")),
+                    );
+                },
             }
         }
 
@@ -1814,6 +1828,9 @@ impl PlayState for SessionState {
                     HudEvent::RequestSiteInfo(id) => {
                         self.client.borrow_mut().request_site_economy(id);
                     },
+                    HudEvent::RespondToQuest(quest, response) => {
+                        self.client.borrow_mut().respond_to_quest(quest, response);
+                    },
 
                     HudEvent::CraftRecipe {
                         recipe_name: recipe,
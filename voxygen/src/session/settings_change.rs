@@ -169,6 +169,8 @@ pub enum Networking {
         server_authoritative: bool,
     },
     ToggleLossyTerrainCompression(bool),
+    ToggleRecordDemos(bool),
+    ToggleAutoReconnect(bool),
 
     #[cfg(feature = "discord")]
     ToggleDiscordIntegration(bool),
@@ -733,6 +735,17 @@ impl SettingsChange {
                         .borrow_mut()
                         .request_lossy_terrain_compression(lossy_terrain_compression);
                 },
+                Networking::ToggleRecordDemos(record_demos) => {
+                    // Takes effect on the next connection
+                    settings.networking.record_demos = record_demos;
+                },
+                Networking::ToggleAutoReconnect(auto_reconnect) => {
+                    settings.networking.auto_reconnect = auto_reconnect;
+                    session_state
+                        .client
+                        .borrow_mut()
+                        .set_auto_reconnect(auto_reconnect);
+                },
                 #[cfg(feature = "discord")]
                 Networking::ToggleDiscordIntegration(enabled) => {
                     use crate::discord::Discord;
@@ -154,6 +154,7 @@ impl ControlSettings {
             GameInput::Trade => Some(KeyMouse::Key(VirtualKeyCode::T)),
             GameInput::Social => Some(KeyMouse::Key(VirtualKeyCode::O)),
             GameInput::Crafting => Some(KeyMouse::Key(VirtualKeyCode::C)),
+            GameInput::Quests => Some(KeyMouse::Key(VirtualKeyCode::U)),
             GameInput::Spellbook => Some(KeyMouse::Key(VirtualKeyCode::P)),
             GameInput::Settings => Some(KeyMouse::Key(VirtualKeyCode::F10)),
             GameInput::Help => Some(KeyMouse::Key(VirtualKeyCode::F1)),
@@ -12,6 +12,12 @@ pub struct NetworkingSettings {
     pub use_quic: bool,
     pub player_physics_behavior: bool,
     pub lossy_terrain_compression: bool,
+    /// Record everything servers send into demo files that can be watched
+    /// later with `--demo`.
+    pub record_demos: bool,
+    /// Reconnect to the server when the connection drops, instead of
+    /// returning to the main menu.
+    pub auto_reconnect: bool,
     pub enable_discord_integration: bool,
 }
 
@@ -28,6 +34,8 @@ impl Default for NetworkingSettings {
             use_quic: false,
             player_physics_behavior: false,
             lossy_terrain_compression: false,
+            record_demos: false,
+            auto_reconnect: true,
             enable_discord_integration: true,
         }
     }
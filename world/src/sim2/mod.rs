@@ -1,6 +1,8 @@
 use crate::{sim::WorldSim, site::economy::simulate_economy, Index};
 use common_base::prof_span;
 
+/// Simulates the history of the world during worldgen. While the game is
+/// running, rtsim carries the economy of sites on from where this left off.
 pub fn simulate(index: &mut Index, _world: &mut WorldSim) {
     prof_span!("sim2::simulate");
     simulate_economy(index);